@@ -0,0 +1,66 @@
+//! Count the heap allocations performed by lightyear in the send/receive hot paths.
+//!
+//! Run with `cargo run --release --features alloc_audit --bin alloc_audit`.
+//! To get the before/after numbers of a change, run the binary on both revisions and compare the outputs.
+use bevy::log::error;
+use lightyear::prelude::{client, server, ClientId};
+use lightyear::utils::alloc_audit::{self, CountingAllocator, HotPath};
+use lightyear_benches::local_stepper::{LocalBevyStepper, Step};
+use lightyear_benches::protocol::*;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const NUM_MESSAGES: &[usize] = &[0, 10, 100, 1000];
+const NUM_FRAMES: usize = 100;
+
+fn main() {
+    println!(
+        "{:>12} | {:>16} | {:>16} | {:>16} | {:>16}",
+        "messages", "send allocs", "send bytes", "receive allocs", "receive bytes"
+    );
+    for n in NUM_MESSAGES {
+        let mut stepper = LocalBevyStepper::default();
+        let client_id = ClientId::Netcode(0);
+        // warm up the buffers
+        for _ in 0..10 {
+            send_messages(&mut stepper, client_id, *n);
+            stepper.frame_step();
+        }
+
+        alloc_audit::reset();
+        for _ in 0..NUM_FRAMES {
+            send_messages(&mut stepper, client_id, *n);
+            stepper.frame_step();
+            // drain the events so that they are not accumulated across frames
+            stepper
+                .client_resource_mut::<bevy::prelude::Events<client::MessageEvent<Message2>>>(
+                    client_id,
+                )
+                .clear();
+        }
+        let send = alloc_audit::stats(HotPath::Send);
+        let receive = alloc_audit::stats(HotPath::Receive);
+        // report the numbers per frame
+        println!(
+            "{:>12} | {:>16} | {:>16} | {:>16} | {:>16}",
+            n,
+            send.allocations / NUM_FRAMES,
+            send.bytes / NUM_FRAMES,
+            receive.allocations / NUM_FRAMES,
+            receive.bytes / NUM_FRAMES,
+        );
+    }
+}
+
+fn send_messages(stepper: &mut LocalBevyStepper, client_id: ClientId, n: usize) {
+    let mut manager = stepper
+        .server_app
+        .world_mut()
+        .resource_mut::<server::ConnectionManager>();
+    for _ in 0..n {
+        let _ = manager
+            .send_message::<Channel1, _>(client_id, &Message2(1))
+            .inspect_err(|e| error!("error: {e:?}"));
+    }
+}
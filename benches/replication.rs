@@ -36,6 +36,7 @@ criterion_group!(
     receive_float_insert,
     receive_float_update,
     send_float_insert_n_clients,
+    send_float_update_static_entities,
 );
 criterion_main!(replication_benches);
 
@@ -318,3 +319,64 @@ fn send_float_insert_n_clients(criterion: &mut Criterion) {
     }
     group.finish();
 }
+
+const NUM_STATIC_ENTITIES: &[usize] = &[100_000];
+const NUM_DYNAMIC_ENTITIES: usize = 100;
+
+/// Replicating the updates of a few moving entities, while most of the replicated entities are static.
+/// The archetypes that did not change since the last send should be skipped entirely.
+fn send_float_update_static_entities(criterion: &mut Criterion) {
+    let mut group =
+        criterion.benchmark_group("replication/send_float_update_static_entities/1_client");
+    group.warm_up_time(std::time::Duration::from_millis(500));
+    group.measurement_time(std::time::Duration::from_millis(4000));
+    for n in NUM_STATIC_ENTITIES.iter() {
+        group.bench_with_input(
+            criterion::BenchmarkId::new("num_static_entities", n),
+            n,
+            |bencher, n| {
+                let mut stepper = LocalBevyStepper::default();
+                // the static entities
+                stepper.server_app.world_mut().spawn_batch(vec![
+                    (
+                        Component1(0.0),
+                        Replicate::default()
+                    );
+                    *n
+                ]);
+                // the moving entities, in a different archetype
+                stepper.server_app.world_mut().spawn_batch(vec![
+                    (
+                        Component2(0.0),
+                        Replicate::default()
+                    );
+                    NUM_DYNAMIC_ENTITIES
+                ]);
+                stepper.update();
+                bencher.iter_custom(|iter| {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iter {
+                        // update the moving entities
+                        let world = stepper.server_app.world_mut();
+                        let mut query = world.query::<&mut Component2>();
+                        for mut component in query.iter_mut(world) {
+                            component.0 += 1.0;
+                        }
+
+                        // advance time by one frame
+                        stepper.advance_time(stepper.frame_duration);
+
+                        let instant = Instant::now();
+                        // buffer and send replication messages
+                        stepper.server_update();
+                        elapsed += instant.elapsed();
+
+                        stepper.client_update();
+                    }
+                    elapsed
+                });
+            },
+        );
+    }
+    group.finish();
+}
@@ -0,0 +1,276 @@
+//! Soak-test lightyear with a server and many headless clients driven by a scenario file.
+//!
+//! Run with `cargo run --release --features stress --bin lightyear-stress -- [scenario.json]`.
+//! If no scenario file is provided, the default scenario is used.
+//!
+//! Example scenario:
+//! ```json
+//! {
+//!     "num_clients": 8,
+//!     "duration_secs": 30.0,
+//!     "spawn_rate": 50.0,
+//!     "entity_lifetime_secs": 5.0,
+//!     "movement": "Circle",
+//!     "message_size": 256,
+//!     "messages_per_frame": 4,
+//!     "send_inputs": true
+//! }
+//! ```
+//!
+//! At the end of the run, the binary reports the throughput, latency and cost of the updates, so that
+//! the numbers can be compared between releases.
+use std::collections::VecDeque;
+
+use bevy::log::error;
+use bevy::prelude::{Entity, Events};
+use bevy::utils::{Duration, Instant};
+use rand::Rng;
+use serde::Deserialize;
+
+use lightyear::prelude::client::{ClientConnection, InputManager, NetClient};
+use lightyear::prelude::server::Replicate;
+use lightyear::prelude::{client, server, ClientId, NetworkTarget, TickManager};
+use lightyear_benches::local_stepper::{LocalBevyStepper, Step};
+use lightyear_benches::protocol::*;
+
+/// How the replicated entities move
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+enum Movement {
+    /// The entities never move
+    Static,
+    /// The entities move at a constant speed
+    #[default]
+    Linear,
+    /// The entities oscillate around their spawn position
+    Circle,
+    /// The entities move randomly
+    Random,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+struct Scenario {
+    /// Number of headless clients connected to the server
+    num_clients: usize,
+    /// Duration of the simulation (in simulated time)
+    duration_secs: f32,
+    /// Number of entities spawned on the server per second
+    spawn_rate: f32,
+    /// Entities are despawned after this duration
+    entity_lifetime_secs: f32,
+    /// Movement pattern of the replicated entities
+    movement: Movement,
+    /// Size (in bytes) of the messages sent from the server to the clients
+    message_size: usize,
+    /// Number of messages sent from the server to every client each frame
+    messages_per_frame: usize,
+    /// If true, every client sends an input each frame
+    send_inputs: bool,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            num_clients: 4,
+            duration_secs: 10.0,
+            spawn_rate: 20.0,
+            entity_lifetime_secs: 5.0,
+            movement: Movement::default(),
+            message_size: 64,
+            messages_per_frame: 1,
+            send_inputs: true,
+        }
+    }
+}
+
+/// Statistics accumulated during the run
+#[derive(Default)]
+struct Report {
+    frames: usize,
+    entities_spawned: usize,
+    entities_despawned: usize,
+    messages_sent: usize,
+    messages_received: usize,
+    server_update_time: Duration,
+    max_server_update_time: Duration,
+    client_update_time: Duration,
+    rtt_sum: Duration,
+    rtt_samples: usize,
+}
+
+fn main() {
+    let scenario = match std::env::args().nth(1) {
+        Some(path) => {
+            let file = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("could not read the scenario file {path}: {e}"));
+            serde_json::from_str::<Scenario>(&file)
+                .unwrap_or_else(|e| panic!("invalid scenario file {path}: {e}"))
+        }
+        None => Scenario::default(),
+    };
+    println!("Running scenario: {scenario:?}");
+
+    let mut stepper = LocalBevyStepper::default_n_clients(scenario.num_clients);
+    let client_ids: Vec<ClientId> = stepper.client_apps.keys().copied().collect();
+    let mut rng = rand::thread_rng();
+    let mut report = Report::default();
+    // entities alive on the server, along with their spawn time
+    let mut alive: VecDeque<(Entity, f32)> = VecDeque::new();
+    let mut spawn_accumulator = 0.0;
+    let message = Message1("a".repeat(scenario.message_size));
+
+    let frame_secs = stepper.frame_duration.as_secs_f32();
+    let num_frames = (scenario.duration_secs / frame_secs) as usize;
+    let start = Instant::now();
+    for frame in 0..num_frames {
+        let elapsed = frame as f32 * frame_secs;
+
+        // entity churn
+        while alive
+            .front()
+            .is_some_and(|(_, spawn_time)| elapsed - spawn_time >= scenario.entity_lifetime_secs)
+        {
+            let (entity, _) = alive.pop_front().unwrap();
+            stepper.server_app.world_mut().despawn(entity);
+            report.entities_despawned += 1;
+        }
+        spawn_accumulator += scenario.spawn_rate * frame_secs;
+        while spawn_accumulator >= 1.0 {
+            spawn_accumulator -= 1.0;
+            let entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Component1(0.0), Replicate::default()))
+                .id();
+            alive.push_back((entity, elapsed));
+            report.entities_spawned += 1;
+        }
+
+        // movement
+        for (entity, spawn_time) in alive.iter() {
+            let age = elapsed - spawn_time;
+            let mut entity_mut = stepper.server_app.world_mut().entity_mut(*entity);
+            let mut component = entity_mut.get_mut::<Component1>().unwrap();
+            match scenario.movement {
+                Movement::Static => {}
+                Movement::Linear => component.0 = age,
+                Movement::Circle => component.0 = age.sin(),
+                Movement::Random => component.0 += rng.gen_range(-1.0..1.0),
+            }
+        }
+
+        // messages
+        if scenario.messages_per_frame > 0 {
+            let mut manager = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<server::ConnectionManager>();
+            for _ in 0..scenario.messages_per_frame {
+                let _ = manager
+                    .send_message_to_target::<Channel1, _>(&mut message.clone(), NetworkTarget::All)
+                    .inspect_err(|e| error!("error: {e:?}"));
+                report.messages_sent += client_ids.len();
+            }
+        }
+
+        // inputs
+        if scenario.send_inputs {
+            for client_app in stepper.client_apps.values_mut() {
+                let tick = client_app.world().resource::<TickManager>().tick();
+                client_app
+                    .world_mut()
+                    .resource_mut::<InputManager<MyInput>>()
+                    .add_input(MyInput(rng.gen()), tick);
+            }
+        }
+
+        // step the apps, measuring the time spent in the updates
+        stepper.advance_time(stepper.frame_duration);
+        let server_start = Instant::now();
+        stepper.server_update();
+        let server_time = server_start.elapsed();
+        report.server_update_time += server_time;
+        report.max_server_update_time = report.max_server_update_time.max(server_time);
+        let client_start = Instant::now();
+        stepper.client_update();
+        report.client_update_time += client_start.elapsed();
+        report.frames += 1;
+
+        for client_id in client_ids.iter() {
+            let mut events =
+                stepper.client_resource_mut::<Events<client::MessageEvent<Message1>>>(*client_id);
+            report.messages_received += events.drain().count();
+            let rtt = stepper
+                .client_resource::<client::ConnectionManager>(*client_id)
+                .ping_manager
+                .rtt();
+            report.rtt_sum += rtt;
+            report.rtt_samples += 1;
+        }
+    }
+    let wall_time = start.elapsed();
+
+    // io statistics of all the clients
+    let (bytes_sent, bytes_received) = client_ids.iter().fold((0, 0), |(sent, received), id| {
+        match stepper.client_resource::<ClientConnection>(*id).io() {
+            Some(io) => (
+                sent + io.stats().bytes_sent,
+                received + io.stats().bytes_received,
+            ),
+            None => (sent, received),
+        }
+    });
+    print_report(&scenario, &report, wall_time, bytes_sent, bytes_received);
+}
+
+fn print_report(
+    scenario: &Scenario,
+    report: &Report,
+    wall_time: Duration,
+    bytes_sent: usize,
+    bytes_received: usize,
+) {
+    let simulated_secs = scenario.duration_secs.max(f32::EPSILON);
+    let frames = report.frames.max(1) as u32;
+    println!("---------------- lightyear stress report ----------------");
+    println!("clients:                 {}", scenario.num_clients);
+    println!("frames:                  {}", report.frames);
+    println!(
+        "wall time:               {:.2}s ({:.2}x real-time)",
+        wall_time.as_secs_f32(),
+        simulated_secs / wall_time.as_secs_f32().max(f32::EPSILON)
+    );
+    println!(
+        "entities:                {} spawned, {} despawned",
+        report.entities_spawned, report.entities_despawned
+    );
+    println!(
+        "messages:                {} sent, {} received ({:.1}%)",
+        report.messages_sent,
+        report.messages_received,
+        100.0 * report.messages_received as f32 / report.messages_sent.max(1) as f32
+    );
+    println!(
+        "throughput (clients):    up {:.1} KB/s, down {:.1} KB/s",
+        bytes_sent as f32 / 1000.0 / simulated_secs,
+        bytes_received as f32 / 1000.0 / simulated_secs
+    );
+    println!(
+        "average rtt:             {:?}",
+        report.rtt_sum / report.rtt_samples.max(1) as u32
+    );
+    println!(
+        "server update time:      avg {:?}, max {:?}",
+        report.server_update_time / frames,
+        report.max_server_update_time
+    );
+    println!(
+        "client update time:      avg {:?} (all clients)",
+        report.client_update_time / frames
+    );
+    println!(
+        "cpu usage:               {:.1}% of the frame budget",
+        100.0 * (report.server_update_time + report.client_update_time).as_secs_f32()
+            / simulated_secs
+    );
+}
@@ -119,6 +119,8 @@ impl Apps {
             Cli::HostServer { client_id } => {
                 let client_net_config = client::NetConfig::Local {
                     id: client_id.unwrap_or(settings.client.client_id),
+                    // simulate the network conditions for the host client as well
+                    conditioner: settings.client.conditioner.as_ref().map(|c| c.build()),
                 };
                 let (app, client_config, server_config) =
                     combined_app(settings, vec![], client_net_config);
@@ -336,6 +338,24 @@ impl Apps {
     }
 }
 
+/// Keyboard controls for the [`client::TimeTravelPlugin`]:
+/// - F9 to pause/resume the predicted world
+/// - Left/Right arrows to step backwards/forwards through the prediction history
+fn time_travel_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut events: EventWriter<client::TimeTravel>,
+) {
+    if keys.just_pressed(KeyCode::F9) {
+        events.send(client::TimeTravel::Toggle);
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        events.send(client::TimeTravel::Step(-1));
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        events.send(client::TimeTravel::Step(1));
+    }
+}
+
 /// Build the client app with the `ClientPlugins` added.
 /// Takes in a `net_config` parameter so that we configure the network transport.
 fn client_app(settings: Settings, net_config: client::NetConfig) -> (App, ClientConfig) {
@@ -357,6 +377,9 @@ fn client_app(settings: Settings, net_config: client::NetConfig) -> (App, Client
     );
     if settings.client.inspector {
         app.add_plugins(WorldInspectorPlugin::new());
+        // inspect the predicted world at previous ticks
+        app.add_plugins(client::TimeTravelPlugin);
+        app.add_systems(Update, time_travel_controls);
     }
     let client_config = ClientConfig {
         shared: shared_config(Mode::Separate),
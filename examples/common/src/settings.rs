@@ -214,6 +214,7 @@ pub(crate) fn get_server_net_configs(settings: &Settings) -> Vec<server::NetConf
                 &settings.shared,
                 server::ServerTransport::WebSocketServer {
                     server_addr: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), *local_port),
+                    certificate: None,
                 },
             ),
             ServerTransports::Steam {
@@ -305,7 +306,10 @@ pub fn get_client_net_config(settings: &Settings, client_id: u64) -> client::Net
             server_addr,
             settings.client.conditioner.as_ref(),
             &settings.shared,
-            client::ClientTransport::WebSocketClient { server_addr },
+            client::ClientTransport::WebSocketClient {
+                server_addr,
+                tls_domain: None,
+            },
         ),
         #[cfg(not(target_family = "wasm"))]
         ClientTransports::Steam { app_id } => client::NetConfig::Steam {
@@ -458,7 +458,10 @@ mod lobby {
                 if host == connection.id() {
                     info!("We are the host of the game!");
                     // set the client connection to be local
-                    config.net = NetConfig::Local { id: host.to_bits() };
+                    config.net = NetConfig::Local {
+                        id: host.to_bits(),
+                        conditioner: None,
+                    };
                     // start the server
                     commands.start_server();
                 } else {
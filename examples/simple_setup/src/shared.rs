@@ -19,6 +19,7 @@ pub fn shared_config() -> SharedConfig {
             tick_duration: Duration::from_secs_f64(1.0 / FIXED_TIMESTEP_HZ),
         },
         mode: Mode::Separate,
+        ..default()
     }
 }
 
@@ -83,15 +83,21 @@ impl ChannelContainer {
         match settings.mode {
             ChannelMode::UnorderedUnreliableWithAcks => {
                 receiver = UnorderedUnreliableReceiver::new().into();
-                sender = UnorderedUnreliableWithAcksSender::new(settings.send_frequency).into();
+                sender = UnorderedUnreliableWithAcksSender::new(
+                    settings.send_frequency,
+                    settings.max_age,
+                )
+                .into();
             }
             ChannelMode::UnorderedUnreliable => {
                 receiver = UnorderedUnreliableReceiver::new().into();
-                sender = UnorderedUnreliableSender::new(settings.send_frequency).into();
+                sender = UnorderedUnreliableSender::new(settings.send_frequency, settings.max_age)
+                    .into();
             }
             ChannelMode::SequencedUnreliable => {
                 receiver = SequencedUnreliableReceiver::new().into();
-                sender = SequencedUnreliableSender::new(settings.send_frequency).into();
+                sender = SequencedUnreliableSender::new(settings.send_frequency, settings.max_age)
+                    .into();
             }
             ChannelMode::UnorderedReliable(reliable_settings) => {
                 receiver = UnorderedReliableReceiver::new().into();
@@ -122,9 +128,23 @@ pub struct ChannelSettings {
     pub mode: ChannelMode,
     /// How often should we try to send messages on this channel.
     /// Set to `Duration::default()` to send messages every frame if possible.
+    ///
+    /// Each channel has its own send timer: messages are buffered in the channel until the timer fires.
+    /// The messages of all the channels that are ready in the same frame are aggregated into the same packets.
+    /// The interval can be changed at runtime with `ConnectionManager::set_channel_send_interval`.
     pub send_frequency: Duration,
     /// Sets the priority of the channel. The final priority of a message will be `MessagePriority * ChannelPriority`
+    ///
+    /// When the bandwidth cap is reached, the messages with the highest priority are sent first. The messages of
+    /// unordered unreliable channels that could not be sent are deferred to the next frames (up to
+    /// `PacketConfig::max_deferred_bytes`); the other unreliable messages are dropped.
     pub priority: f32,
+    /// Maximum time that a message can stay buffered in the channel before being sent.
+    /// Messages that are older than this are dropped instead of being sent late, which is useful
+    /// for time-sensitive data (voice frames, position updates, etc.)
+    ///
+    /// Can only be set on unreliable channels. `None` means that messages never expire.
+    pub max_age: Option<Duration>,
 }
 
 impl Default for ChannelSettings {
@@ -133,6 +153,7 @@ impl Default for ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             send_frequency: Duration::default(),
             priority: 1.0,
+            max_age: None,
         }
     }
 }
@@ -191,12 +212,28 @@ pub enum ChannelDirection {
     Bidirectional,
 }
 
+/// Retransmission strategy of a reliable channel
 #[derive(Clone, Debug, PartialEq)]
 pub struct ReliableSettings {
-    /// Duration to wait before resending a packet if it has not been acked
+    /// Factor applied to the RTT to compute the duration to wait before resending a message that has not been acked
     pub rtt_resend_factor: f32,
-    /// Minimum duration to wait before resending a packet if it has not been acked
+    /// Minimum duration to wait before resending a message that has not been acked
     pub rtt_resend_min_delay: Duration,
+    /// Maximum duration to wait before resending a message that has not been acked.
+    ///
+    /// If None, the resend delay is not bounded.
+    pub rtt_resend_max_delay: Option<Duration>,
+    /// Resend a message immediately (without waiting for the resend delay) if this many messages
+    /// that were sent after it have been acked while it is still unacked.
+    ///
+    /// If None, messages are only resent after the resend delay.
+    pub fast_retransmit_threshold: Option<u8>,
+    /// Maximum number of bytes that can be sent without having been acked.
+    /// New messages are not sent until enough in-flight messages get acked; the new messages with
+    /// the highest priority are sent first.
+    ///
+    /// If None, the number of in-flight bytes is not limited.
+    pub max_in_flight_bytes: Option<usize>,
 }
 
 impl Default for ReliableSettings {
@@ -204,6 +241,9 @@ impl Default for ReliableSettings {
         Self {
             rtt_resend_factor: 1.5,
             rtt_resend_min_delay: Duration::default(),
+            rtt_resend_max_delay: None,
+            fast_retransmit_threshold: None,
+            max_in_flight_bytes: None,
         }
     }
 }
@@ -211,7 +251,11 @@ impl Default for ReliableSettings {
 impl ReliableSettings {
     pub(crate) fn resend_delay(&self, rtt: Duration) -> Duration {
         let delay = rtt.mul_f32(self.rtt_resend_factor);
-        std::cmp::max(delay, self.rtt_resend_min_delay)
+        let delay = std::cmp::max(delay, self.rtt_resend_min_delay);
+        match self.rtt_resend_max_delay {
+            Some(max_delay) => std::cmp::min(delay, max_delay),
+            None => delay,
+        }
     }
 }
 
@@ -244,3 +288,89 @@ pub struct InputChannel;
 /// Channel to send messages related to Authority transfers
 /// This is an Ordered Reliable channel
 pub struct AuthorityChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send the despawns of entire replication groups
+/// This is an Ordered Reliable channel
+pub struct DespawnGroupsChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send the interest subscription requests and responses
+/// This is an Ordered Reliable channel
+pub struct InterestChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used by the client to report its synchronization state (e.g. interpolation delay) to the server
+/// This is a Sequenced Unreliable channel
+pub struct SyncChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to send the bevy [`Events`](bevy::prelude::Event) that are replicated over the network
+/// This is an Ordered Reliable channel
+pub struct EventChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used by the server to send the reason of a disconnection to the client before disconnecting it
+/// This is an Ordered Reliable channel
+pub struct DisconnectChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used by the client to send the [`ProtocolHash`](crate::protocol::version::ProtocolHash) of its protocol to the server
+/// This is an Ordered Reliable channel
+pub struct ProtocolCheckChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used by the server to issue a session token to the client, and by the client to send it back
+/// when it resumes its session (see [`ServerConfig::session_grace_period`](crate::server::config::ServerConfig::session_grace_period))
+/// This is an Ordered Reliable channel
+pub struct SessionChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to send the console commands of authorized clients to the server, and their output back
+/// This is an Ordered Reliable channel
+pub struct AdminChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to replicate the seed of the [`NetworkedRng`](crate::shared::rng::NetworkedRng)
+/// This is an Ordered Reliable channel
+pub struct RngChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to send the [`ProximityMetadata`](crate::shared::proximity::ProximityMetadata) to the clients
+/// This is a Sequenced Unreliable channel
+pub struct ProximityChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used by the server to send the snapshot of the replicated world to a newly connected client
+/// (see [`ReplicationConfig::join_snapshot`](crate::prelude::ReplicationConfig::join_snapshot))
+/// This is an Ordered Reliable channel
+pub struct JoinSnapshotChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to send the [`WorldSeed`](crate::shared::world_seed::WorldSeed) to the clients, and their
+/// [`WorldReady`](crate::shared::world_seed::WorldReady) notification back
+/// This is an Ordered Reliable channel
+pub struct WorldSeedChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to replicate resources whose updates must be applied before the entity updates that follow them.
+///
+/// The server holds the replication of the entities to a client until that client has received all the
+/// messages sent on this channel (see [`ReplicateResourceExt`](crate::prelude::ReplicateResourceExt)).
+/// This is an Ordered Reliable channel
+pub struct OrderedResourceChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used to send many small cosmetic events (animations, sounds, etc.) that can be lost or arrive late
+/// (see [`PresentationEventsPlugin`](crate::shared::presentation::PresentationEventsPlugin)).
+///
+/// This is an Unordered Unreliable channel with a low priority; the messages that could not be sent within
+/// 200ms are dropped.
+pub struct PresentationChannel;
+
+#[derive(ChannelInternal)]
+/// Channel used by the server to send the checksums of the replication groups
+/// (see [`DesyncDetectionPlugin`](crate::shared::replication::integrity::DesyncDetectionPlugin)).
+///
+/// This is an Unordered Unreliable channel with a low priority; a lost checksum only skips one check.
+pub struct IntegrityChannel;
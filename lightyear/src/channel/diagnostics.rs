@@ -0,0 +1,66 @@
+//! Compute Diagnostics about the channels (messages dropped because they were stale, etc.)
+
+use bevy::app::{App, Plugin};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::Resource;
+
+use crate::packet::message_manager::MessageManager;
+use crate::protocol::channel::{ChannelKind, ChannelRegistry};
+
+/// Plugin that registers a diagnostic for every channel that has a
+/// [`max_age`](crate::prelude::ChannelSettings::max_age), tracking the number of messages that were dropped
+/// because they stayed buffered for too long.
+///
+/// The diagnostics are registered in [`Plugin::finish`] so that all the channels of the protocol are known.
+pub struct ChannelDiagnosticsPlugin {
+    pub history_len: usize,
+}
+
+impl Default for ChannelDiagnosticsPlugin {
+    fn default() -> Self {
+        Self { history_len: 60 }
+    }
+}
+
+/// The diagnostic path of each channel that can drop stale messages
+#[derive(Resource, Default)]
+pub(crate) struct ChannelDiagnosticPaths(Vec<(ChannelKind, DiagnosticPath)>);
+
+impl ChannelDiagnosticsPlugin {
+    /// Path of the diagnostic that counts the number of stale messages dropped on the channel `name`
+    pub fn stale_messages_dropped(name: &str) -> DiagnosticPath {
+        DiagnosticPath::new(format!("channel.{name}.stale_messages_dropped"))
+    }
+
+    pub(crate) fn add_measurements(
+        message_manager: &MessageManager,
+        paths: &ChannelDiagnosticPaths,
+        mut diagnostics: Diagnostics,
+    ) {
+        for (kind, path) in paths.0.iter() {
+            if let Ok(dropped) = message_manager.stale_messages_dropped(*kind) {
+                diagnostics.add_measurement(path, || dropped as f64);
+            }
+        }
+    }
+}
+
+impl Plugin for ChannelDiagnosticsPlugin {
+    fn build(&self, _: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        let registry = app.world().resource::<ChannelRegistry>();
+        let paths: Vec<_> = registry
+            .channels_with_max_age()
+            .map(|(kind, name)| (*kind, Self::stale_messages_dropped(name)))
+            .collect();
+        for (_, path) in paths.iter() {
+            app.register_diagnostic(
+                Diagnostic::new(path.clone())
+                    .with_suffix("")
+                    .with_max_history_length(self.history_len),
+            );
+        }
+        app.insert_resource(ChannelDiagnosticPaths(paths));
+    }
+}
@@ -1,6 +1,7 @@
 /*! Channels are used to add reliability/ordering on top of the transport layer
 */
 pub mod builder;
+pub mod diagnostics;
 pub(crate) mod receivers;
 pub(crate) mod senders;
 
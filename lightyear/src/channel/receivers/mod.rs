@@ -1,9 +1,12 @@
 //! This module contains the various types of receivers available to receive messages over a channel
+use std::collections::VecDeque;
+
 use bytes::Bytes;
 use enum_dispatch::enum_dispatch;
 
 use crate::packet::message::ReceiveMessage;
 use crate::prelude::Tick;
+use crate::shared::memory::EvictionPolicy;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
 use error::Result;
@@ -38,6 +41,40 @@ pub trait ChannelReceive {
 
     /// Reads a message from the internal buffer to get its content
     fn read_message(&mut self) -> Option<(Tick, Bytes)>;
+
+    /// Number of bytes of the messages that are buffered in the channel
+    fn buffered_bytes(&self) -> usize;
+
+    /// Drop buffered messages, according to the `policy`, until at most `max_bytes` are buffered.
+    ///
+    /// Returns the number of messages that were dropped. Reliable channels never drop messages.
+    fn evict(&mut self, _max_bytes: usize, _policy: EvictionPolicy) -> usize {
+        0
+    }
+}
+
+/// Drop received messages from the buffer until at most `max_bytes` are buffered.
+///
+/// Returns the number of messages that were dropped.
+pub(crate) fn evict_received(
+    messages: &mut VecDeque<(Tick, Bytes)>,
+    max_bytes: usize,
+    policy: EvictionPolicy,
+) -> usize {
+    let mut bytes: usize = messages.iter().map(|(_, data)| data.len()).sum();
+    let mut evicted = 0;
+    while bytes > max_bytes {
+        let message = match policy {
+            EvictionPolicy::DropOldest => messages.pop_front(),
+            EvictionPolicy::DropNewest => messages.pop_back(),
+        };
+        let Some((_, data)) = message else {
+            break;
+        };
+        bytes -= data.len();
+        evicted += 1;
+    }
+    evicted
 }
 
 /// This enum contains the various types of receivers available
@@ -83,6 +83,13 @@ impl ChannelReceive for OrderedReliableReceiver {
         self.pending_recv_message_id += 1;
         Some(message)
     }
+
+    fn buffered_bytes(&self) -> usize {
+        self.recv_message_buffer
+            .values()
+            .map(|(_, data)| data.len())
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -82,6 +82,13 @@ impl ChannelReceive for SequencedReliableReceiver {
             }
         }
     }
+
+    fn buffered_bytes(&self) -> usize {
+        self.recv_message_buffer
+            .values()
+            .map(|(_, data)| data.len())
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -5,9 +5,10 @@ use bytes::Bytes;
 use super::error::{ChannelReceiveError, Result};
 
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
-use crate::channel::receivers::ChannelReceive;
+use crate::channel::receivers::{evict_received, ChannelReceive};
 use crate::packet::message::{MessageData, MessageId, ReceiveMessage};
 use crate::prelude::Tick;
+use crate::shared::memory::EvictionPolicy;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
 
@@ -82,6 +83,17 @@ impl ChannelReceive for SequencedUnreliableReceiver {
         self.recv_message_buffer.pop_front()
         // TODO: naia does a more optimized version by return a Vec<Message> instead of Option<Message>
     }
+
+    fn buffered_bytes(&self) -> usize {
+        self.recv_message_buffer
+            .iter()
+            .map(|(_, data)| data.len())
+            .sum()
+    }
+
+    fn evict(&mut self, max_bytes: usize, policy: EvictionPolicy) -> usize {
+        evict_received(&mut self.recv_message_buffer, max_bytes, policy)
+    }
 }
 
 #[cfg(test)]
@@ -102,6 +102,13 @@ impl ChannelReceive for UnorderedReliableReceiver {
         // receive oldest message in the buffer
         Some(data)
     }
+
+    fn buffered_bytes(&self) -> usize {
+        self.recv_message_buffer
+            .values()
+            .map(|(_, data)| data.len())
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -3,9 +3,10 @@ use std::collections::VecDeque;
 
 use crate::channel::receivers::error::ChannelReceiveError;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
-use crate::channel::receivers::ChannelReceive;
+use crate::channel::receivers::{evict_received, ChannelReceive};
 use crate::packet::message::{MessageData, ReceiveMessage};
 use crate::prelude::Tick;
+use crate::shared::memory::EvictionPolicy;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
 
@@ -57,6 +58,17 @@ impl ChannelReceive for UnorderedUnreliableReceiver {
     fn read_message(&mut self) -> Option<(Tick, Bytes)> {
         self.recv_message_buffer.pop_front()
     }
+
+    fn buffered_bytes(&self) -> usize {
+        self.recv_message_buffer
+            .iter()
+            .map(|(_, data)| data.len())
+            .sum()
+    }
+
+    fn evict(&mut self, max_bytes: usize, policy: EvictionPolicy) -> usize {
+        evict_received(&mut self.recv_message_buffer, max_bytes, policy)
+    }
 }
 
 #[cfg(test)]
@@ -14,20 +14,20 @@ pub(crate) struct FragmentSender {
 impl FragmentSender {
     pub fn new() -> Self {
         Self {
-            // TODO: make this overridable?
             fragment_size: FRAGMENT_SIZE,
         }
     }
+
     pub fn build_fragments(
         &self,
         fragment_message_id: MessageId,
         tick: Option<Tick>,
         fragment_bytes: Bytes,
     ) -> Result<Vec<FragmentData>, SerializationError> {
-        if fragment_bytes.len() <= FRAGMENT_SIZE {
+        if fragment_bytes.len() <= self.fragment_size {
             unreachable!(
                 "Message size must be at least {} to need to be fragmented",
-                FRAGMENT_SIZE
+                self.fragment_size
             );
         }
         let chunks = fragment_bytes.chunks(self.fragment_size);
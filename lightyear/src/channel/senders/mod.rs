@@ -1,14 +1,17 @@
 use std::collections::VecDeque;
 
+use bevy::time::{Timer, TimerMode};
+use bevy::utils::Duration;
 use bytes::Bytes;
 use crossbeam_channel::Receiver;
 use enum_dispatch::enum_dispatch;
 
 use crate::packet::message::{MessageAck, MessageId, SendMessage};
 use crate::serialize::SerializationError;
+use crate::shared::memory::EvictionPolicy;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
-use crate::shared::time_manager::TimeManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
 
 pub(crate) mod fragment_ack_receiver;
 pub(crate) mod fragment_sender;
@@ -32,6 +35,17 @@ pub trait ChannelSend {
         tick_manager: &TickManager,
     );
 
+    /// Change the interval at which the channel flushes its buffered messages.
+    ///
+    /// A zero interval means that messages are sent every time the connection sends packets.
+    fn set_send_interval(&mut self, send_interval: Duration);
+
+    /// Change the maximum number of bytes of a message before it gets split into fragments
+    fn set_fragment_size(&mut self, fragment_size: usize);
+
+    /// The maximum number of bytes of a message before it gets split into fragments
+    fn fragment_size(&self) -> usize;
+
     /// Queues a message to be transmitted.
     /// The priority of the message needs to be specified
     ///
@@ -58,6 +72,93 @@ pub trait ChannelSend {
 
     /// Send nacks to the subscribers of nacks
     fn send_nacks(&mut self, nack: MessageId);
+
+    /// Number of messages that were dropped instead of being sent because they stayed buffered
+    /// for longer than the channel's `max_age`
+    fn stale_messages_dropped(&self) -> usize {
+        0
+    }
+
+    /// Number of bytes of the messages that are buffered in the channel
+    fn buffered_bytes(&self) -> usize;
+
+    /// Drop buffered messages, according to the `policy`, until at most `max_bytes` are buffered.
+    ///
+    /// Returns the number of messages that were dropped. Reliable channels never drop messages.
+    fn evict(&mut self, _max_bytes: usize, _policy: EvictionPolicy) -> usize {
+        0
+    }
+}
+
+/// Timer that determines when a channel is ready to send its buffered messages.
+/// `None` if the channel sends messages as soon as possible.
+pub(crate) fn send_timer(send_interval: Duration) -> Option<Timer> {
+    if send_interval == Duration::default() {
+        None
+    } else {
+        Some(Timer::new(send_interval, TimerMode::Repeating))
+    }
+}
+
+/// Remove the messages that have been buffered for longer than `max_age`.
+///
+/// Returns the number of messages that were dropped.
+pub(crate) fn drop_stale_messages(
+    messages: &mut VecDeque<SendMessage>,
+    current_time: WrappedTime,
+    max_age: Duration,
+) -> usize {
+    let max_age = chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::max_value());
+    let len = messages.len();
+    messages.retain(|message| current_time - message.buffered_at <= max_age);
+    len - messages.len()
+}
+
+/// Number of bytes of the messages in the queue
+pub(crate) fn queued_bytes(messages: &VecDeque<SendMessage>) -> usize {
+    messages.iter().map(|message| message.data.len()).sum()
+}
+
+/// Drop messages from the queues until at most `max_bytes` are buffered.
+///
+/// The single messages are dropped first; a fragmented message is dropped with all its fragments.
+/// Returns the number of messages that were dropped.
+pub(crate) fn evict_messages(
+    single_messages: &mut VecDeque<SendMessage>,
+    fragmented_messages: &mut VecDeque<SendMessage>,
+    max_bytes: usize,
+    policy: EvictionPolicy,
+) -> usize {
+    let mut bytes = queued_bytes(single_messages) + queued_bytes(fragmented_messages);
+    let mut evicted = 0;
+    while bytes > max_bytes {
+        let message = match policy {
+            EvictionPolicy::DropOldest => single_messages.pop_front(),
+            EvictionPolicy::DropNewest => single_messages.pop_back(),
+        };
+        if let Some(message) = message {
+            bytes -= message.data.len();
+            evicted += 1;
+            continue;
+        }
+        let fragment = match policy {
+            EvictionPolicy::DropOldest => fragmented_messages.front(),
+            EvictionPolicy::DropNewest => fragmented_messages.back(),
+        };
+        let Some(message_id) = fragment.and_then(|fragment| fragment.data.message_id()) else {
+            break;
+        };
+        fragmented_messages.retain(|fragment| {
+            if fragment.data.message_id() == Some(message_id) {
+                bytes -= fragment.data.len();
+                false
+            } else {
+                true
+            }
+        });
+        evicted += 1;
+    }
+    evicted
 }
 
 /// Enum dispatch lets us derive ChannelSend on each enum variant
@@ -1,4 +1,4 @@
-use bevy::prelude::{Timer, TimerMode};
+use bevy::prelude::Timer;
 use std::collections::VecDeque;
 use std::collections::{BTreeMap, HashSet};
 
@@ -9,7 +9,7 @@ use tracing::trace;
 
 use crate::channel::builder::ReliableSettings;
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{send_timer, ChannelSend};
 use crate::packet::message::{FragmentData, MessageAck, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
 use crate::shared::ping::manager::PingManager;
@@ -40,6 +40,52 @@ pub struct UnackedMessageWithPriority {
     pub unacked_message: UnackedMessage,
     pub base_priority: f32,
     pub accumulated_priority: f32,
+    /// Number of messages sent after this one that were acked since this message was last sent.
+    /// Used for fast retransmission.
+    pub later_acks: u8,
+}
+
+impl UnackedMessage {
+    /// Number of bytes of this message that were sent but not acked yet
+    fn in_flight_bytes(&self) -> usize {
+        match self {
+            UnackedMessage::Single { bytes, last_sent } => {
+                if last_sent.is_some() {
+                    bytes.len()
+                } else {
+                    0
+                }
+            }
+            UnackedMessage::Fragmented(fragment_acks) => fragment_acks
+                .iter()
+                .filter(|f| !f.acked && f.last_sent.is_some())
+                .map(|f| f.data.bytes.len())
+                .sum(),
+        }
+    }
+
+    /// Number of bytes of this message that were not acked yet
+    fn unacked_bytes(&self) -> usize {
+        match self {
+            UnackedMessage::Single { bytes, .. } => bytes.len(),
+            UnackedMessage::Fragmented(fragment_acks) => fragment_acks
+                .iter()
+                .filter(|f| !f.acked)
+                .map(|f| f.data.bytes.len())
+                .sum(),
+        }
+    }
+
+    /// Returns the number of bytes of the message if it has never been sent
+    fn new_message_bytes(&self) -> Option<usize> {
+        match self {
+            UnackedMessage::Single { bytes, last_sent } => last_sent.is_none().then(|| bytes.len()),
+            UnackedMessage::Fragmented(fragment_acks) => fragment_acks
+                .iter()
+                .all(|f| f.last_sent.is_none())
+                .then(|| fragment_acks.iter().map(|f| f.data.bytes.len()).sum()),
+        }
+    }
 }
 
 /// A sender that makes sure to resend messages until it receives an ack
@@ -80,11 +126,7 @@ pub struct ReliableSender {
 
 impl ReliableSender {
     pub fn new(reliable_settings: ReliableSettings, send_frequency: Duration) -> Self {
-        let timer = if send_frequency == Duration::default() {
-            None
-        } else {
-            Some(Timer::new(send_frequency, TimerMode::Repeating))
-        };
+        let timer = send_timer(send_frequency);
         Self {
             reliable_settings,
             unacked_messages: Default::default(),
@@ -101,6 +143,18 @@ impl ReliableSender {
             priority_multiplier: 1.0,
         }
     }
+
+    /// The message `acked_id` was acked: all the older messages that are still unacked are
+    /// probably lost, so we count it towards their fast retransmission
+    fn record_later_ack(&mut self, acked_id: MessageId) {
+        if self.reliable_settings.fast_retransmit_threshold.is_none() {
+            return;
+        }
+        self.unacked_messages
+            .range_mut(..acked_id)
+            .filter(|(_, m)| m.unacked_message.in_flight_bytes() > 0)
+            .for_each(|(_, m)| m.later_acks = m.later_acks.saturating_add(1));
+    }
 }
 
 impl ChannelSend for ReliableSender {
@@ -119,6 +173,18 @@ impl ChannelSend for ReliableSender {
         }
     }
 
+    fn set_send_interval(&mut self, send_interval: Duration) {
+        self.timer = send_timer(send_interval);
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn fragment_size(&self) -> usize {
+        self.fragment_sender.fragment_size
+    }
+
     /// Add a new message to the buffer of messages to be sent.
     /// This is a client-facing function, to be called when you want to send a message
     fn buffer_send(
@@ -153,6 +219,7 @@ impl ChannelSend for ReliableSender {
             // store with 0.0 accumulated priority because priority gets accumulated when we collect the messages
             // for sending (even the first time the message is sent)
             accumulated_priority: 0.0,
+            later_acks: 0,
         };
         self.unacked_messages
             .insert(message_id, unacked_message_with_priority);
@@ -175,15 +242,65 @@ impl ChannelSend for ReliableSender {
         let resend_delay =
             chrono::Duration::from_std(self.reliable_settings.resend_delay(self.current_rtt))
                 .unwrap();
-        let should_send = |last_sent: &Option<WrappedTime>| -> bool {
+        let fast_retransmit_threshold = self.reliable_settings.fast_retransmit_threshold;
+        let should_send = |last_sent: &Option<WrappedTime>, later_acks: u8| -> bool {
             match last_sent {
                 // send if the message has never been sent
                 None => true,
                 // or if we sent it a while back but didn't get an ack
-                Some(last_sent) => self.current_time - *last_sent > resend_delay,
+                Some(last_sent) => {
+                    self.current_time - *last_sent > resend_delay
+                        // or if enough messages sent after it have been acked
+                        || fast_retransmit_threshold.is_some_and(|n| later_acks >= n)
+                }
             }
         };
 
+        // messages that are resent are already in-flight, but new messages can only be sent
+        // if the number of in-flight bytes stays below the limit
+        let new_messages_allowed =
+            self.reliable_settings
+                .max_in_flight_bytes
+                .map(|max_in_flight_bytes| {
+                    let mut in_flight_bytes: usize = self
+                        .unacked_messages
+                        .values()
+                        .map(|m| m.unacked_message.in_flight_bytes())
+                        .sum();
+                    // new messages with a higher priority get to use the in-flight budget first;
+                    // the sort is stable so messages with the same priority stay in order
+                    let mut new_messages = self
+                        .unacked_messages
+                        .iter()
+                        .filter_map(|(message_id, m)| {
+                            m.unacked_message
+                                .new_message_bytes()
+                                .map(|num_bytes| (*message_id, m.base_priority, num_bytes))
+                        })
+                        .collect::<Vec<_>>();
+                    new_messages.sort_by(|a, b| b.1.total_cmp(&a.1));
+                    // once a new message is blocked, we don't send any message that comes after it
+                    new_messages
+                        .into_iter()
+                        .take_while(|(_, _, num_bytes)| {
+                            // always allow one message to be in-flight, even if it is bigger than the limit
+                            if in_flight_bytes > 0
+                                && in_flight_bytes + num_bytes > max_in_flight_bytes
+                            {
+                                return false;
+                            }
+                            in_flight_bytes += num_bytes;
+                            true
+                        })
+                        .map(|(message_id, _, _)| message_id)
+                        .collect::<HashSet<_>>()
+                });
+        let can_send_new = |message_id: &MessageId| -> bool {
+            new_messages_allowed
+                .as_ref()
+                .map_or(true, |allowed| allowed.contains(message_id))
+        };
+
         // Iterate through all unacked messages, oldest message ids first
         for (message_id, unacked_message_with_priority) in self.unacked_messages.iter_mut() {
             // accumulate the priority for all messages (including the ones that were just added, since we set the accumulated priority to 0.0)
@@ -202,7 +319,9 @@ impl ChannelSend for ReliableSender {
                     bytes,
                     ref mut last_sent,
                 } => {
-                    if should_send(last_sent) {
+                    if should_send(last_sent, unacked_message_with_priority.later_acks)
+                        && (last_sent.is_some() || can_send_new(message_id))
+                    {
                         trace!("Should send message {:?}", message_id);
                         let message_info = MessageAck {
                             message_id: *message_id,
@@ -213,17 +332,25 @@ impl ChannelSend for ReliableSender {
                             self.single_messages_to_send.push_back(SendMessage {
                                 data: message.into(),
                                 priority: unacked_message_with_priority.accumulated_priority,
+                                buffered_at: self.current_time,
                             });
                             self.message_ids_to_send.insert(message_info);
                             *last_sent = Some(self.current_time);
+                            unacked_message_with_priority.later_acks = 0;
                         }
                     }
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
+                    let later_acks = unacked_message_with_priority.later_acks;
+                    // a fragmented message counts as new if none of its fragments have been sent
+                    let is_new = fragment_acks.iter().all(|f| f.last_sent.is_none());
+                    if is_new && !can_send_new(message_id) {
+                        continue;
+                    }
                     // only send the fragments that haven't been acked and should be resent
                     fragment_acks
                         .iter_mut()
-                        .filter(|f| !f.acked && should_send(&f.last_sent))
+                        .filter(|f| !f.acked && should_send(&f.last_sent, later_acks))
                         .for_each(|f| {
                             let message_info = MessageAck {
                                 message_id: *message_id,
@@ -234,9 +361,11 @@ impl ChannelSend for ReliableSender {
                                 self.fragmented_messages_to_send.push_back(SendMessage {
                                     data: message.into(),
                                     priority: unacked_message_with_priority.accumulated_priority,
+                                    buffered_at: self.current_time,
                                 });
                                 self.message_ids_to_send.insert(message_info);
                                 f.last_sent = Some(self.current_time);
+                                unacked_message_with_priority.later_acks = 0;
                             }
                         })
                 }
@@ -291,6 +420,7 @@ impl ChannelSend for ReliableSender {
                         sender.send(message_ack.message_id).unwrap();
                     }
                     self.unacked_messages.remove(&message_ack.message_id);
+                    self.record_later_ack(message_ack.message_id);
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     let Some(fragment_id) = message_ack.fragment_id else {
@@ -305,6 +435,7 @@ impl ChannelSend for ReliableSender {
                             for sender in &self.ack_senders {
                                 sender.send(message_ack.message_id).unwrap();
                             }
+                            self.record_later_ack(message_ack.message_id);
                         }
                     }
                 }
@@ -333,6 +464,13 @@ impl ChannelSend for ReliableSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn buffered_bytes(&self) -> usize {
+        self.unacked_messages
+            .values()
+            .map(|message| message.unacked_message.unacked_bytes())
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -351,6 +489,7 @@ mod tests {
             ReliableSettings {
                 rtt_resend_factor: 1.5,
                 rtt_resend_min_delay: Duration::from_millis(100),
+                ..Default::default()
             },
             Duration::default(),
         );
@@ -380,7 +519,8 @@ mod tests {
             &SendMessage {
                 data: SingleData::new(Some(MessageId(0)), message1.clone()).into(),
                 // priority is accumulated every time the message is not sent
-                priority: 3.0
+                priority: 3.0,
+                buffered_at: sender.current_time,
             }
         );
 
@@ -396,4 +536,110 @@ mod tests {
         let (single, _) = sender.send_packet();
         assert_eq!(single.len(), 0);
     }
+
+    #[test]
+    fn test_reliable_sender_fast_retransmit() {
+        let mut sender = ReliableSender::new(
+            ReliableSettings {
+                rtt_resend_min_delay: Duration::from_millis(1000),
+                fast_retransmit_threshold: Some(2),
+                ..Default::default()
+            },
+            Duration::default(),
+        );
+        sender.current_time = WrappedTime::new(0);
+        for _ in 0..3 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 3);
+
+        // the message 0 is not resent after one later ack
+        sender.receive_ack(&MessageAck {
+            message_id: MessageId(1),
+            fragment_id: None,
+        });
+        sender.current_time += Duration::from_millis(10);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 0);
+
+        // it is resent immediately after two later acks
+        sender.receive_ack(&MessageAck {
+            message_id: MessageId(2),
+            fragment_id: None,
+        });
+        sender.current_time += Duration::from_millis(10);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(
+            single.front().unwrap().data.message_id(),
+            Some(MessageId(0))
+        );
+
+        // the counter is reset after the resend
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 0);
+    }
+
+    #[test]
+    fn test_reliable_sender_max_in_flight_bytes() {
+        let mut sender = ReliableSender::new(
+            ReliableSettings {
+                rtt_resend_min_delay: Duration::from_millis(1000),
+                rtt_resend_max_delay: Some(Duration::from_millis(1000)),
+                max_in_flight_bytes: Some(10),
+                ..Default::default()
+            },
+            Duration::default(),
+        );
+        sender.current_rtt = Duration::from_secs(10);
+        sender.current_time = WrappedTime::new(0);
+        for _ in 0..3 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        // only 2 messages fit in the in-flight limit
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 0);
+
+        // the third message is sent once a message is acked
+        sender.receive_ack(&MessageAck {
+            message_id: MessageId(0),
+            fragment_id: None,
+        });
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+
+        // the resend delay is bounded by the max delay, even with a high rtt
+        sender.current_time += Duration::from_millis(1100);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+    }
+
+    #[test]
+    fn test_reliable_sender_max_in_flight_bytes_priority() {
+        let mut sender = ReliableSender::new(
+            ReliableSettings {
+                rtt_resend_min_delay: Duration::from_millis(1000),
+                max_in_flight_bytes: Some(10),
+                ..Default::default()
+            },
+            Duration::default(),
+        );
+        sender.current_rtt = Duration::from_secs(10);
+        sender.current_time = WrappedTime::new(0);
+        for _ in 0..3 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        sender.buffer_send(Bytes::from("hi"), 10.0).unwrap();
+
+        // the urgent message jumps ahead of the other messages in the in-flight limit
+        let (single, _) = sender.send_packet();
+        let message_ids = single
+            .iter()
+            .map(|m| m.data.message_id().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(message_ids, vec![MessageId(0), MessageId(3)]);
+    }
 }
@@ -1,4 +1,4 @@
-use bevy::time::{Timer, TimerMode};
+use bevy::time::Timer;
 use bevy::utils::Duration;
 use std::collections::VecDeque;
 
@@ -6,12 +6,15 @@ use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{
+    drop_stale_messages, evict_messages, queued_bytes, send_timer, ChannelSend,
+};
 use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
+use crate::shared::memory::EvictionPolicy;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
-use crate::shared::time_manager::TimeManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
 
 /// A sender that simply sends the messages without checking if they were received
 /// Same as UnorderedUnreliableSender, but includes ordering information (MessageId)
@@ -30,15 +33,16 @@ pub struct SequencedUnreliableSender {
     nack_senders: Vec<Sender<MessageId>>,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
+    current_time: WrappedTime,
+    /// Messages that stay buffered for longer than this are dropped instead of being sent
+    max_age: Option<Duration>,
+    /// Number of messages that were dropped because they stayed buffered for longer than `max_age`
+    stale_messages_dropped: usize,
 }
 
 impl SequencedUnreliableSender {
-    pub(crate) fn new(send_frequency: Duration) -> Self {
-        let timer = if send_frequency == Duration::default() {
-            None
-        } else {
-            Some(Timer::new(send_frequency, TimerMode::Repeating))
-        };
+    pub(crate) fn new(send_frequency: Duration, max_age: Option<Duration>) -> Self {
+        let timer = send_timer(send_frequency);
         Self {
             single_messages_to_send: VecDeque::new(),
             fragmented_messages_to_send: VecDeque::new(),
@@ -46,17 +50,33 @@ impl SequencedUnreliableSender {
             fragment_sender: FragmentSender::new(),
             nack_senders: vec![],
             timer,
+            current_time: WrappedTime::default(),
+            max_age,
+            stale_messages_dropped: 0,
         }
     }
 }
 
 impl ChannelSend for SequencedUnreliableSender {
     fn update(&mut self, time_manager: &TimeManager, _: &PingManager, _: &TickManager) {
+        self.current_time = time_manager.current_time();
         if let Some(timer) = &mut self.timer {
             timer.tick(time_manager.delta());
         }
     }
 
+    fn set_send_interval(&mut self, send_interval: Duration) {
+        self.timer = send_timer(send_interval);
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn fragment_size(&self) -> usize {
+        self.fragment_sender.fragment_size
+    }
+
     /// Add a new message to the buffer of messages to be sent.
     /// This is a client-facing function, to be called when you want to send a message
     fn buffer_send(
@@ -73,6 +93,7 @@ impl ChannelSend for SequencedUnreliableSender {
                 self.fragmented_messages_to_send.push_back(SendMessage {
                     data: MessageData::Fragment(fragment),
                     priority,
+                    buffered_at: self.current_time,
                 });
             }
         } else {
@@ -80,6 +101,7 @@ impl ChannelSend for SequencedUnreliableSender {
             self.single_messages_to_send.push_back(SendMessage {
                 data: MessageData::Single(single_data),
                 priority,
+                buffered_at: self.current_time,
             });
         }
         self.next_send_message_id += 1;
@@ -92,6 +114,17 @@ impl ChannelSend for SequencedUnreliableSender {
         if self.timer.as_ref().is_some_and(|t| !t.finished()) {
             return (VecDeque::new(), VecDeque::new());
         }
+        if let Some(max_age) = self.max_age {
+            self.stale_messages_dropped += drop_stale_messages(
+                &mut self.single_messages_to_send,
+                self.current_time,
+                max_age,
+            ) + drop_stale_messages(
+                &mut self.fragmented_messages_to_send,
+                self.current_time,
+                max_age,
+            );
+        }
         (
             std::mem::take(&mut self.single_messages_to_send),
             std::mem::take(&mut self.fragmented_messages_to_send),
@@ -122,6 +155,24 @@ impl ChannelSend for SequencedUnreliableSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn stale_messages_dropped(&self) -> usize {
+        self.stale_messages_dropped
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        queued_bytes(&self.single_messages_to_send)
+            + queued_bytes(&self.fragmented_messages_to_send)
+    }
+
+    fn evict(&mut self, max_bytes: usize, policy: EvictionPolicy) -> usize {
+        evict_messages(
+            &mut self.single_messages_to_send,
+            &mut self.fragmented_messages_to_send,
+            max_bytes,
+            policy,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -130,7 +181,7 @@ mod tests {
     use crate::prelude::{PingConfig, TickConfig};
     #[test]
     fn test_sequenced_unreliable_sender_internals() {
-        let mut sender = SequencedUnreliableSender::new(Duration::from_secs(1));
+        let mut sender = SequencedUnreliableSender::new(Duration::from_secs(1), None);
         assert!(sender.timer.as_ref().is_some_and(|t| !t.finished()));
 
         sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
@@ -152,4 +203,34 @@ mod tests {
         let (single, _) = sender.send_packet();
         assert_eq!(single.len(), 1);
     }
+
+    #[test]
+    fn test_sequenced_unreliable_sender_drops_stale_messages() {
+        let mut sender = SequencedUnreliableSender::new(
+            Duration::from_secs(1),
+            Some(Duration::from_millis(500)),
+        );
+        let mut time_manager = TimeManager::default();
+        let ping_manager = PingManager::new(PingConfig::default());
+        let tick_manager = TickManager::from_config(TickConfig::new(Duration::from_secs(1)));
+
+        sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+
+        // the message stayed buffered for longer than the max age when the timer fires
+        time_manager.update(Duration::from_secs(1));
+        sender.update(&time_manager, &ping_manager, &tick_manager);
+        let (single, _) = sender.send_packet();
+        assert!(single.is_empty());
+        assert_eq!(sender.stale_messages_dropped(), 1);
+
+        // a message that is buffered right before the timer fires is sent
+        time_manager.update(Duration::from_millis(800));
+        sender.update(&time_manager, &ping_manager, &tick_manager);
+        sender.buffer_send(Bytes::from("world"), 1.0).unwrap();
+        time_manager.update(Duration::from_millis(200));
+        sender.update(&time_manager, &ping_manager, &tick_manager);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(sender.stale_messages_dropped(), 1);
+    }
 }
@@ -1,5 +1,4 @@
 use bevy::prelude::Timer;
-use bevy::time::TimerMode;
 use bevy::utils::Duration;
 use std::collections::VecDeque;
 
@@ -7,12 +6,15 @@ use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{
+    drop_stale_messages, evict_messages, queued_bytes, send_timer, ChannelSend,
+};
 use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
+use crate::shared::memory::EvictionPolicy;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
-use crate::shared::time_manager::TimeManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
 
 /// A sender that simply sends the messages without checking if they were received
 /// Does not include any ordering information
@@ -31,15 +33,16 @@ pub struct UnorderedUnreliableSender {
     nack_senders: Vec<Sender<MessageId>>,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
+    current_time: WrappedTime,
+    /// Messages that stay buffered for longer than this are dropped instead of being sent
+    max_age: Option<Duration>,
+    /// Number of messages that were dropped because they stayed buffered for longer than `max_age`
+    stale_messages_dropped: usize,
 }
 
 impl UnorderedUnreliableSender {
-    pub(crate) fn new(send_frequency: Duration) -> Self {
-        let timer = if send_frequency == Duration::default() {
-            None
-        } else {
-            Some(Timer::new(send_frequency, TimerMode::Repeating))
-        };
+    pub(crate) fn new(send_frequency: Duration, max_age: Option<Duration>) -> Self {
+        let timer = send_timer(send_frequency);
         Self {
             single_messages_to_send: VecDeque::new(),
             fragmented_messages_to_send: VecDeque::new(),
@@ -47,17 +50,33 @@ impl UnorderedUnreliableSender {
             fragment_sender: FragmentSender::new(),
             nack_senders: vec![],
             timer,
+            current_time: WrappedTime::default(),
+            max_age,
+            stale_messages_dropped: 0,
         }
     }
 }
 
 impl ChannelSend for UnorderedUnreliableSender {
     fn update(&mut self, time_manager: &TimeManager, _: &PingManager, _: &TickManager) {
+        self.current_time = time_manager.current_time();
         if let Some(timer) = &mut self.timer {
             timer.tick(time_manager.delta());
         }
     }
 
+    fn set_send_interval(&mut self, send_interval: Duration) {
+        self.timer = send_timer(send_interval);
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn fragment_size(&self) -> usize {
+        self.fragment_sender.fragment_size
+    }
+
     /// Add a new message to the buffer of messages to be sent.
     /// This is a client-facing function, to be called when you want to send a message
     fn buffer_send(
@@ -74,6 +93,7 @@ impl ChannelSend for UnorderedUnreliableSender {
                 self.fragmented_messages_to_send.push_back(SendMessage {
                     data: MessageData::Fragment(fragment),
                     priority,
+                    buffered_at: self.current_time,
                 });
             }
             self.next_send_fragmented_message_id += 1;
@@ -83,6 +103,7 @@ impl ChannelSend for UnorderedUnreliableSender {
             self.single_messages_to_send.push_back(SendMessage {
                 data: MessageData::Single(single_data),
                 priority,
+                buffered_at: self.current_time,
             });
             Ok(None)
         }
@@ -93,6 +114,17 @@ impl ChannelSend for UnorderedUnreliableSender {
         if self.timer.as_ref().is_some_and(|t| !t.finished()) {
             return (VecDeque::new(), VecDeque::new());
         }
+        if let Some(max_age) = self.max_age {
+            self.stale_messages_dropped += drop_stale_messages(
+                &mut self.single_messages_to_send,
+                self.current_time,
+                max_age,
+            ) + drop_stale_messages(
+                &mut self.fragmented_messages_to_send,
+                self.current_time,
+                max_age,
+            );
+        }
         (
             std::mem::take(&mut self.single_messages_to_send),
             std::mem::take(&mut self.fragmented_messages_to_send),
@@ -123,12 +155,53 @@ impl ChannelSend for UnorderedUnreliableSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn stale_messages_dropped(&self) -> usize {
+        self.stale_messages_dropped
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        queued_bytes(&self.single_messages_to_send)
+            + queued_bytes(&self.fragmented_messages_to_send)
+    }
+
+    fn evict(&mut self, max_bytes: usize, policy: EvictionPolicy) -> usize {
+        evict_messages(
+            &mut self.single_messages_to_send,
+            &mut self.fragmented_messages_to_send,
+            max_bytes,
+            policy,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     // #[test]
     // fn test_unordered_unreliable_sender_internals() {
     //     todo!()
     // }
+
+    #[test]
+    fn test_evict_buffered_messages() {
+        let mut sender = UnorderedUnreliableSender::new(Duration::default(), None);
+        sender.buffer_send(Bytes::from("a"), 1.0).unwrap();
+        sender.buffer_send(Bytes::from("bb"), 1.0).unwrap();
+        sender.buffer_send(Bytes::from("ccc"), 1.0).unwrap();
+        // each message also contains its length and the absence of message id
+        assert_eq!(sender.buffered_bytes(), 12);
+
+        // drop the oldest message
+        assert_eq!(sender.evict(10, EvictionPolicy::DropOldest), 1);
+        assert_eq!(sender.buffered_bytes(), 9);
+
+        // drop the most recent message
+        assert_eq!(sender.evict(4, EvictionPolicy::DropNewest), 1);
+        assert_eq!(
+            sender.single_messages_to_send.front().unwrap().data.bytes(),
+            Bytes::from("bb")
+        );
+    }
 }
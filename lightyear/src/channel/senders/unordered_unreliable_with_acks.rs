@@ -1,4 +1,4 @@
-use bevy::prelude::{Timer, TimerMode};
+use bevy::prelude::Timer;
 use bevy::utils::Duration;
 use std::collections::VecDeque;
 
@@ -7,7 +7,7 @@ use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_ack_receiver::FragmentAckReceiver;
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{drop_stale_messages, queued_bytes, send_timer, ChannelSend};
 use crate::packet::message::{MessageAck, MessageData, MessageId, SendMessage, SingleData};
 use crate::serialize::SerializationError;
 use crate::shared::ping::manager::PingManager;
@@ -41,15 +41,15 @@ pub struct UnorderedUnreliableWithAcksSender {
     current_time: WrappedTime,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
+    /// Messages that stay buffered for longer than this are dropped instead of being sent
+    max_age: Option<Duration>,
+    /// Number of messages that were dropped because they stayed buffered for longer than `max_age`
+    stale_messages_dropped: usize,
 }
 
 impl UnorderedUnreliableWithAcksSender {
-    pub(crate) fn new(send_frequency: Duration) -> Self {
-        let timer = if send_frequency == Duration::default() {
-            None
-        } else {
-            Some(Timer::new(send_frequency, TimerMode::Repeating))
-        };
+    pub(crate) fn new(send_frequency: Duration, max_age: Option<Duration>) -> Self {
+        let timer = send_timer(send_frequency);
         Self {
             single_messages_to_send: VecDeque::new(),
             fragmented_messages_to_send: VecDeque::new(),
@@ -60,6 +60,8 @@ impl UnorderedUnreliableWithAcksSender {
             fragment_ack_receiver: FragmentAckReceiver::new(),
             current_time: WrappedTime::default(),
             timer,
+            max_age,
+            stale_messages_dropped: 0,
         }
     }
 }
@@ -74,6 +76,18 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
         }
     }
 
+    fn set_send_interval(&mut self, send_interval: Duration) {
+        self.timer = send_timer(send_interval);
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
+
+    fn fragment_size(&self) -> usize {
+        self.fragment_sender.fragment_size
+    }
+
     /// Add a new message to the buffer of messages to be sent.
     /// This is a client-facing function, to be called when you want to send a message
     fn buffer_send(
@@ -92,6 +106,7 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
                 self.fragmented_messages_to_send.push_back(SendMessage {
                     data: MessageData::Fragment(fragment),
                     priority,
+                    buffered_at: self.current_time,
                 });
             }
         } else {
@@ -99,6 +114,7 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
             self.single_messages_to_send.push_back(SendMessage {
                 data: MessageData::Single(single_data),
                 priority,
+                buffered_at: self.current_time,
             });
         }
         self.next_send_message_id += 1;
@@ -110,6 +126,17 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
         if self.timer.as_ref().is_some_and(|t| !t.finished()) {
             return (VecDeque::new(), VecDeque::new());
         }
+        if let Some(max_age) = self.max_age {
+            self.stale_messages_dropped += drop_stale_messages(
+                &mut self.single_messages_to_send,
+                self.current_time,
+                max_age,
+            ) + drop_stale_messages(
+                &mut self.fragmented_messages_to_send,
+                self.current_time,
+                max_age,
+            );
+        }
         (
             std::mem::take(&mut self.single_messages_to_send),
             std::mem::take(&mut self.fragmented_messages_to_send),
@@ -163,6 +190,17 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
             sender.send(nack).unwrap();
         }
     }
+
+    fn stale_messages_dropped(&self) -> usize {
+        self.stale_messages_dropped
+    }
+
+    /// The buffered messages are never evicted: this channel is used to send the entity updates, and the replication
+    /// sender considers the changes as sent as soon as they are buffered (unless it gets notified of a nack).
+    fn buffered_bytes(&self) -> usize {
+        queued_bytes(&self.single_messages_to_send)
+            + queued_bytes(&self.fragmented_messages_to_send)
+    }
 }
 
 #[cfg(test)]
@@ -173,7 +211,7 @@ mod tests {
 
     #[test]
     fn test_receive_ack() {
-        let mut sender = UnorderedUnreliableWithAcksSender::new(Duration::default());
+        let mut sender = UnorderedUnreliableWithAcksSender::new(Duration::default(), None);
 
         // create subscriber
         let receiver = sender.subscribe_acks();
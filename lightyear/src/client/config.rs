@@ -1,14 +1,18 @@
 //! Defines client-specific configuration options
 use bevy::prelude::Resource;
 use bevy::reflect::Reflect;
+use bevy::utils::Duration;
 use governor::Quota;
 use nonzero_ext::nonzero;
 
+use crate::client::connection_quality::ConnectionQualityConfig;
 use crate::client::input::native::InputConfig;
 use crate::client::interpolation::plugin::InterpolationConfig;
+use crate::client::io::config::ClientTransport;
 use crate::client::prediction::plugin::PredictionConfig;
 use crate::client::sync::SyncConfig;
 use crate::connection::client::NetConfig;
+use crate::connection::netcode::MAX_PACKET_SIZE;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
 use crate::shared::replication::plugin::ReplicationConfig;
@@ -60,6 +64,18 @@ pub struct PacketConfig {
     pub send_bandwidth_cap: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub bandwidth_cap_enabled: bool,
+    /// Maximum number of bytes in a packet. Messages that don't fit in a single packet are fragmented.
+    ///
+    /// The default is [`MAX_PACKET_SIZE`], which is safe for UDP. Lower it if the transport has a smaller
+    /// practical limit (for example WebTransport datagrams, which must also fit the QUIC overhead).
+    /// The value is clamped between 256 and [`MAX_PACKET_SIZE`].
+    pub max_packet_size: usize,
+    /// Maximum number of bytes of messages that are kept to be sent later when the bandwidth cap is reached.
+    ///
+    /// Only the messages of the unordered unreliable channels (without a `max_age`) are deferred: when the
+    /// bandwidth is limited, the messages of the low-priority channels (chat, telemetry, etc.) are sent a bit
+    /// later instead of being lost, while the inputs and the replication messages are sent first.
+    pub max_deferred_bytes: usize,
 }
 
 impl Default for PacketConfig {
@@ -69,6 +85,8 @@ impl Default for PacketConfig {
             // 56 KB/s bandwidth cap
             send_bandwidth_cap: Quota::per_second(nonzero!(56000u32)),
             bandwidth_cap_enabled: false,
+            max_packet_size: MAX_PACKET_SIZE,
+            max_deferred_bytes: 16 * 1024,
         }
     }
 }
@@ -89,6 +107,12 @@ impl PacketConfig {
         self.bandwidth_cap_enabled = true;
         self
     }
+
+    /// Set the maximum number of bytes in a packet sent on the connection
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
 }
 
 /// The configuration object that lets you create a `ClientPlugin` with the desired settings.
@@ -124,4 +148,24 @@ pub struct ClientConfig {
     pub replication: ReplicationConfig,
     pub prediction: PredictionConfig,
     pub interpolation: InterpolationConfig,
+    pub connection_quality: ConnectionQualityConfig,
+    /// How long the client keeps its replicated entities and its connection state after losing its
+    /// connection, so that it can resume its session if it reconnects (with the same client id) before then.
+    ///
+    /// This should not be longer than the server's
+    /// [`ServerConfig::session_grace_period`](crate::server::config::ServerConfig::session_grace_period),
+    /// otherwise the client would try to resume a session that the server has already dropped.
+    ///
+    /// The default is zero: the replicated entities are despawned as soon as the client disconnects.
+    pub session_resumption: Duration,
+    /// Transports to try, in order, if the client fails to connect with the transport of [`net`](Self::net).
+    ///
+    /// The same authentication and netcode config are used, only the transport of the io is replaced.
+    /// For example, a wasm client can fall back to WebSocket if WebTransport is not available in the browser,
+    /// and a native client can use [`ClientTransport::TcpClient`] as the last resort on networks that block UDP.
+    /// The list is only used with [`NetConfig::Netcode`].
+    ///
+    /// Every new connection attempt starts again with the transport of [`net`](Self::net).
+    #[reflect(ignore)]
+    pub transport_fallbacks: Vec<ClientTransport>,
 }
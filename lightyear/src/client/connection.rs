@@ -1,45 +1,63 @@
 //! Specify how a Client sends/receives messages with a Server
+use std::io::Write;
+use std::sync::Arc;
+
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::MapEntities;
-use bevy::prelude::{Resource, World};
-use bevy::utils::{Duration, HashMap};
+use bevy::prelude::{Entity, Resource, World};
+use bevy::utils::{Duration, HashMap, HashSet};
 use bytes::Bytes;
 use tracing::{debug, trace, trace_span};
 
 use crate::channel::builder::{
-    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel,
+    AdminChannel, DisconnectChannel, EntityUpdatesChannel, InterestChannel, JoinSnapshotChannel,
+    PingChannel, PongChannel, ProtocolCheckChannel, SessionChannel, SyncChannel,
 };
 
 use crate::channel::receivers::ChannelReceive;
 use crate::channel::senders::ChannelSend;
 use crate::client::config::ClientConfig;
 use crate::client::error::ClientError;
+use crate::client::replay::{ReplayMessageKind, ReplayRecorder};
 use crate::client::sync::SyncConfig;
+use crate::connection::client::KickReason;
+use crate::connection::local::client::LocalLinkConditioner;
 use crate::connection::netcode::MAX_PACKET_SIZE;
-use crate::packet::message_manager::MessageManager;
+use crate::packet::message_manager::{
+    is_valid_priority, FragmentationStats, MessageManager, DEFAULT_MESSAGE_PRIORITY,
+};
 use crate::packet::packet_builder::{Payload, RecvPayload};
 use crate::packet::priority_manager::PriorityConfig;
 use crate::prelude::client::PredictionConfig;
 use crate::prelude::{Channel, ChannelKind, ClientId, Message, ReplicationConfig};
 use crate::protocol::channel::ChannelRegistry;
 use crate::protocol::component::ComponentRegistry;
-use crate::protocol::message::{MessageRegistry, MessageType};
+use crate::protocol::extension::{ProtocolExtensionHashes, ProtocolExtensionId};
+use crate::protocol::message::{MessageError, MessageKind, MessageRegistry, MessageType};
 use crate::protocol::registry::NetId;
+use crate::protocol::version::ProtocolHash;
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
+use crate::server::connection::SessionToken;
 use crate::server::error::ServerError;
+use crate::shared::config::Mode;
+use crate::shared::console::ConsoleCommandRequest;
 use crate::shared::events::connection::ConnectionEvents;
-use crate::shared::message::MessageSend;
+use crate::shared::interest::InterestRequest;
+use crate::shared::message::{MessageSend, ScheduledMessages};
 use crate::shared::ping::manager::{PingConfig, PingManager};
 use crate::shared::ping::message::{Ping, Pong};
 use crate::shared::replication::delta::DeltaManager;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
+use crate::shared::replication::snapshot::JoinSnapshot;
+use crate::shared::replication::strategy::ReplicationSendStrategy;
 use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage, ReplicationSend};
 use crate::shared::replication::{ReplicationPeer, ReplicationReceive};
 use crate::shared::sets::ClientMarker;
+use crate::shared::sync::InterpolationDelayMessage;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
@@ -81,13 +99,27 @@ pub struct ConnectionManager {
     pub(crate) received_leafwing_input_messages: HashMap<NetId, Vec<Bytes>>,
     /// Used to transfer raw bytes to a system that can convert the bytes to the actual type
     pub(crate) received_messages: HashMap<NetId, Vec<Bytes>>,
+    /// Messages sent by the server with a target tick, that are only emitted once the client reaches that tick
+    pub(crate) scheduled_messages: ScheduledMessages,
+    /// Raw bytes of the disconnection reason sent by the server, waiting to be deserialized
+    pub(crate) received_kick_reason: Option<(NetId, Bytes)>,
+    /// Disconnection reason sent by the server, emitted in the [`DisconnectEvent`](crate::client::events::DisconnectEvent)
+    pub(crate) kick_reason: Option<KickReason>,
+    /// Real time at which the connection was lost, if the session can be resumed
+    pub(crate) suspended_at: Option<Duration>,
+    /// Token issued by the server, that we need to send back to resume our session
+    pub(crate) session_token: Option<SessionToken>,
+    /// Protocol extensions that the server accepted
+    pub(crate) protocol_extensions: HashSet<ProtocolExtensionId>,
+    /// Number of consecutive frames during which the transport failed to send some packets
+    pub(crate) send_error_frames: u32,
     pub(crate) writer: Writer,
 
     /// Internal buffer of the messages that we want to send.
     /// We use this so that:
     /// - in host server mode, we deserialize the bytes and push them to the server's Message Events queue directly
     /// - in non-host server mode, we buffer the bytes to the message manager as usual
-    pub(crate) messages_to_send: Vec<(Bytes, ChannelKind)>,
+    pub(crate) messages_to_send: Vec<(Bytes, ChannelKind, f32)>,
 }
 
 // NOTE: useful when we sometimes need to create a temporary fake ConnectionManager
@@ -118,6 +150,13 @@ impl Default for ConnectionManager {
             #[cfg(feature = "leafwing")]
             received_leafwing_input_messages: HashMap::default(),
             received_messages: HashMap::default(),
+            scheduled_messages: ScheduledMessages::default(),
+            received_kick_reason: None,
+            kick_reason: None,
+            suspended_at: None,
+            session_token: None,
+            send_error_frames: 0,
+            protocol_extensions: HashSet::default(),
             writer: Writer::with_capacity(0),
             messages_to_send: Vec::default(),
         }
@@ -139,6 +178,26 @@ impl ConnectionManager {
             client_config.packet.nack_rtt_multiple,
             client_config.packet.into(),
         );
+        message_manager.set_max_packet_size(client_config.packet.max_packet_size);
+        // let the server check that we are using the same protocol, and tell it which extensions
+        // and which versions of the messages and components we have
+        let extensions = ProtocolExtensionHashes::new(message_registry, component_registry);
+        let mut protocol_extensions = HashSet::default();
+        if client_config.shared.mode != Mode::HostServer {
+            let mut writer = Writer::with_capacity(8);
+            let hash = ProtocolHash::new(channel_registry, message_registry, component_registry);
+            if hash.to_bytes(&mut writer).is_ok()
+                && extensions.to_bytes(&mut writer).is_ok()
+                && message_registry.versions().to_bytes(&mut writer).is_ok()
+                && component_registry.versions().to_bytes(&mut writer).is_ok()
+            {
+                let _ = message_manager
+                    .buffer_send(writer.split(), ChannelKind::of::<ProtocolCheckChannel>());
+            }
+        } else {
+            // in host-server mode, the client shares the protocol of the server
+            protocol_extensions.extend(extensions.ids());
+        }
         // get notified when a replication-update message gets acked/nacked
         let entity_updates_sender = &mut message_manager
             .channels
@@ -157,7 +216,9 @@ impl ConnectionManager {
             client_config.replication,
             bandwidth_cap_enabled,
         );
-        let replication_receiver = ReplicationReceiver::new();
+        let mut replication_receiver = ReplicationReceiver::new();
+        // used to emit the RemoteEntityMapped/RemoteEntityUnmapped events
+        replication_receiver.remote_entity_map.record_changes = true;
         Self {
             component_registry: component_registry.clone(),
             message_registry: message_registry.clone(),
@@ -171,6 +232,13 @@ impl ConnectionManager {
             #[cfg(feature = "leafwing")]
             received_leafwing_input_messages: HashMap::default(),
             received_messages: HashMap::default(),
+            scheduled_messages: ScheduledMessages::default(),
+            received_kick_reason: None,
+            kick_reason: None,
+            suspended_at: None,
+            session_token: None,
+            send_error_frames: 0,
+            protocol_extensions,
             writer: Writer::with_capacity(MAX_PACKET_SIZE),
             messages_to_send: Vec::default(),
         }
@@ -182,6 +250,31 @@ impl ConnectionManager {
         self.sync_manager.is_synced()
     }
 
+    /// Return the latest estimate of the round-trip time to the server
+    pub fn rtt(&self) -> Duration {
+        self.ping_manager.rtt()
+    }
+
+    /// Return the latest estimate of the jitter of the round-trip time to the server
+    pub fn jitter(&self) -> Duration {
+        self.ping_manager.jitter()
+    }
+
+    /// Estimate of the fraction of packets sent to the server that were lost (between 0.0 and 1.0)
+    pub fn packet_loss(&self) -> f32 {
+        self.message_manager.packet_loss()
+    }
+
+    /// Estimate of the fraction of packets sent by the server that were lost (between 0.0 and 1.0)
+    pub fn incoming_packet_loss(&self) -> f32 {
+        self.message_manager.incoming_packet_loss()
+    }
+
+    /// Statistics about the messages sent to the server that had to be fragmented
+    pub fn fragmentation_stats(&self) -> FragmentationStats {
+        self.message_manager.fragmentation_stats()
+    }
+
     /// Returns true if we received a new server packet on this frame
     pub(crate) fn received_new_server_tick(&self) -> bool {
         self.sync_manager.duration_since_latest_received_server_tick == Duration::default()
@@ -234,6 +327,43 @@ impl ConnectionManager {
         message.map_entities(mapper);
     }
 
+    /// Map an entity from the server's world to an existing local entity.
+    ///
+    /// When the server replicates `remote_entity`, the replicated components will be inserted on
+    /// `local_entity` instead of spawning a new entity.
+    pub fn map_remote_entity(&mut self, remote_entity: Entity, local_entity: Entity) {
+        self.replication_receiver
+            .map_remote_entity(remote_entity, local_entity);
+    }
+
+    /// Replace the [`ReplicationSendStrategy`] used to send the replication messages to the server
+    pub fn set_replication_strategy(&mut self, strategy: impl ReplicationSendStrategy) {
+        self.replication_sender.strategy = Arc::new(strategy);
+    }
+
+    /// Returns true if the server accepted the protocol extension, so that its types can be exchanged
+    pub fn has_protocol_extension(&self, extension: ProtocolExtensionId) -> bool {
+        self.protocol_extensions.contains(&extension)
+    }
+
+    /// Returns true if the type with this network id can be sent to the server,
+    /// i.e. it is part of the base protocol or of an extension that the server accepted
+    pub(crate) fn is_net_id_enabled(&self, net_id: NetId) -> bool {
+        ProtocolExtensionId::of(net_id)
+            .map_or(true, |extension| self.has_protocol_extension(extension))
+    }
+
+    /// Returns an error if the message with this network id belongs to an extension that the server
+    /// has not accepted
+    fn check_net_id_enabled(&self, net_id: NetId) -> Result<(), ClientError> {
+        match ProtocolExtensionId::of(net_id) {
+            Some(extension) if !self.is_net_id_enabled(net_id) => {
+                Err(ClientError::ProtocolExtensionDisabled(extension))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Send a [`Message`] to the server using a specific [`Channel`]
     pub fn send_message<C: Channel, M: Message>(
         &mut self,
@@ -250,7 +380,131 @@ impl ConnectionManager {
         message: &mut M,
         target: NetworkTarget,
     ) -> Result<(), ClientError> {
-        self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target)
+        self.send_message_to_target_with_priority::<C, M>(message, target, DEFAULT_MESSAGE_PRIORITY)
+    }
+
+    /// Send a [`Message`] to the server using a specific [`Channel`], with a given priority.
+    ///
+    /// Messages with a higher priority are sent before the other messages buffered on the same channel
+    /// (the default priority is 1.0). The priority must be finite and non-negative.
+    /// Note that the priority does not change the order in which messages are received on
+    /// ordered channels, and is ignored on sequenced channels.
+    pub fn send_message_with_priority<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        priority: f32,
+    ) -> Result<(), ClientError> {
+        self.send_message_to_target_with_priority::<C, M>(message, NetworkTarget::None, priority)
+    }
+
+    /// Send a [`Message`] to the server using a specific [`Channel`], with a given priority.
+    ///
+    /// See [`send_message_with_priority`](Self::send_message_with_priority)
+    pub fn send_message_to_target_with_priority<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        target: NetworkTarget,
+        priority: f32,
+    ) -> Result<(), ClientError> {
+        if !is_valid_priority(priority) {
+            return Err(ClientError::InvalidPriority(priority));
+        }
+        self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target, priority)
+    }
+
+    /// Request a subscription to an interest set defined by the server.
+    ///
+    /// The server will answer with an [`InterestResponse`](crate::shared::interest::InterestResponse) message.
+    /// See the [`interest`](crate::shared::interest) module for more details.
+    pub fn subscribe(&mut self, name: impl Into<String>) -> Result<(), ClientError> {
+        self.send_message::<InterestChannel, _>(&mut InterestRequest {
+            name: name.into(),
+            subscribe: true,
+        })
+    }
+
+    /// Request to be unsubscribed from an interest set defined by the server
+    pub fn unsubscribe(&mut self, name: impl Into<String>) -> Result<(), ClientError> {
+        self.send_message::<InterestChannel, _>(&mut InterestRequest {
+            name: name.into(),
+            subscribe: false,
+        })
+    }
+
+    /// Run a console command on the server, e.g. `kick 42 "spamming the chat"`.
+    ///
+    /// The client must have been authorized by the server. The output of the command is sent back in a
+    /// [`ConsoleCommandResponse`](crate::shared::console::ConsoleCommandResponse) message.
+    /// See the [`console`](crate::server::console) module for more details.
+    pub fn send_console_command(&mut self, line: impl Into<String>) -> Result<(), ClientError> {
+        self.send_message::<AdminChannel, _>(&mut ConsoleCommandRequest { line: line.into() })
+    }
+
+    /// Change the interval at which the messages buffered on the [`Channel`] are sent to the server.
+    ///
+    /// This overrides the [`send_frequency`](crate::prelude::ChannelSettings::send_frequency) of the channel
+    /// for this connection; a zero interval sends the messages as soon as possible.
+    /// If the interval is shorter than the replication send interval, the replication messages are also
+    /// buffered at that interval so that the channel is not limited by the replication send interval.
+    pub fn set_channel_send_interval<C: Channel>(
+        &mut self,
+        send_interval: Duration,
+    ) -> Result<(), ClientError> {
+        self.message_manager
+            .set_channel_send_interval(ChannelKind::of::<C>(), send_interval)?;
+        Ok(())
+    }
+
+    /// Number of messages sent on the [`Channel`] that were dropped because they stayed buffered
+    /// for longer than the channel's [`max_age`](crate::prelude::ChannelSettings::max_age)
+    pub fn stale_messages_dropped<C: Channel>(&self) -> Result<usize, ClientError> {
+        Ok(self
+            .message_manager
+            .stale_messages_dropped(ChannelKind::of::<C>())?)
+    }
+
+    /// Serialize a [`Message`] so that it can be sent later with [`send_raw`](Self::send_raw).
+    ///
+    /// The entities in the message are mapped at the time of serialization.
+    pub fn serialize_message<M: Message>(&mut self, message: &M) -> Result<Bytes, ClientError> {
+        self.message_registry.serialize(
+            message,
+            &mut self.writer,
+            Some(&mut self.replication_receiver.remote_entity_map.local_to_remote),
+        )?;
+        Ok(self.writer.split())
+    }
+
+    /// Send an already serialized message to the server using a specific [`Channel`]
+    ///
+    /// The bytes must contain a message serialized with [`serialize_message`](Self::serialize_message)
+    /// (or by any peer using the same protocol): the receiver uses the message kind at the start of the bytes
+    /// to deserialize the message.
+    pub fn send_raw<C: Channel>(&mut self, message_bytes: Bytes) -> Result<(), ClientError> {
+        self.send_raw_to_target::<C>(message_bytes, NetworkTarget::None)
+    }
+
+    /// Send an already serialized message to the server using a specific [`Channel`]
+    ///
+    /// The message will be sent to the server and re-broadcasted to all clients that match the [`NetworkTarget`]
+    pub fn send_raw_to_target<C: Channel>(
+        &mut self,
+        message_bytes: Bytes,
+        target: NetworkTarget,
+    ) -> Result<(), ClientError> {
+        let net_id = self.message_registry.check_raw(&message_bytes)?;
+        self.check_net_id_enabled(net_id)?;
+        target.to_bytes(&mut self.writer)?;
+        self.writer
+            .write_all(&message_bytes)
+            .map_err(SerializationError::from)?;
+        let message_bytes = self.writer.split();
+        self.messages_to_send.push((
+            message_bytes,
+            ChannelKind::of::<C>(),
+            DEFAULT_MESSAGE_PRIORITY,
+        ));
+        Ok(())
     }
 
     /// Serialize a message and buffer it internally so that it can be sent later
@@ -259,7 +513,15 @@ impl ConnectionManager {
         message: &M,
         channel_kind: ChannelKind,
         target: NetworkTarget,
+        priority: f32,
     ) -> Result<(), ClientError> {
+        if let Some(net_id) = self
+            .message_registry
+            .kind_map
+            .net_id(&MessageKind::of::<M>())
+        {
+            self.check_net_id_enabled(*net_id)?;
+        }
         // write the target first
         // NOTE: this is ok to do because most of the time (without rebroadcast, this just adds 1 byte)
         target.to_bytes(&mut self.writer)?;
@@ -272,7 +534,8 @@ impl ConnectionManager {
         let message_bytes = self.writer.split();
 
         // TODO: emit logs/metrics about the message being buffered?
-        self.messages_to_send.push((message_bytes, channel_kind));
+        self.messages_to_send
+            .push((message_bytes, channel_kind, priority));
         Ok(())
     }
 
@@ -291,16 +554,10 @@ impl ConnectionManager {
         //     return Ok(());
         // }
 
-        self.replication_sender.accumulate_priority(time_manager);
-        self.replication_sender.send_actions_messages(
-            tick,
-            bevy_tick,
-            &mut self.writer,
-            &mut self.message_manager,
-        )?;
-        self.replication_sender.send_updates_messages(
+        self.replication_sender.buffer_messages(
             tick,
             bevy_tick,
+            time_manager,
             &mut self.writer,
             &mut self.message_manager,
         )?;
@@ -310,14 +567,25 @@ impl ConnectionManager {
     /// Send packets that are ready to be sent.
     /// In host-server mode:
     /// - go through messages_to_send and make the server's ConnectionManager receive them
+    /// - if there is a link conditioner, the messages are only received once they are ready
     pub(crate) fn send_packets_host_server(
         &mut self,
         local_client_id: ClientId,
         server_manager: &mut crate::server::connection::ConnectionManager,
+        conditioner: Option<&mut LocalLinkConditioner>,
+        channel_registry: &ChannelRegistry,
     ) -> Result<(), ServerError> {
-        // go through messages_to_send, deserialize them and make the server receive them
-        self.messages_to_send
+        let messages = self
+            .messages_to_send
             .drain(..)
+            .map(|(message_bytes, channel_kind, _)| (message_bytes, channel_kind));
+        let messages = match conditioner {
+            Some(conditioner) => conditioner.condition_to_server(messages, channel_registry),
+            None => messages.collect(),
+        };
+        // deserialize the messages and make the server receive them
+        messages
+            .into_iter()
             .try_for_each(|(message_bytes, channel_kind)| {
                 server_manager
                     .connection_mut(local_client_id)?
@@ -347,6 +615,12 @@ impl ConnectionManager {
         // (and not have the delay between when we prepare the ping and when we send the packet)
         if let Some(ping) = self.ping_manager.maybe_prepare_ping(time_manager) {
             self.send_ping(ping)?;
+            // at the same frequency, let the server know which ticks we are interpolating
+            if self.sync_manager.is_synced() {
+                self.send_message::<SyncChannel, _>(&mut InterpolationDelayMessage {
+                    delay: self.sync_manager.interpolation_delay(),
+                })?;
+            }
         }
 
         // prepare the pong messages with the correct send time
@@ -363,13 +637,16 @@ impl ConnectionManager {
             })?;
 
         // buffer the messages into the message manager
-        self.messages_to_send
-            .drain(..)
-            .try_for_each(|(message_bytes, channel_kind)| {
-                self.message_manager
-                    .buffer_send(message_bytes, channel_kind)?;
+        self.messages_to_send.drain(..).try_for_each(
+            |(message_bytes, channel_kind, priority)| {
+                self.message_manager.buffer_send_with_priority(
+                    message_bytes,
+                    channel_kind,
+                    priority,
+                )?;
                 Ok::<(), ClientError>(())
-            })?;
+            },
+        )?;
 
         // get the payloads from the message manager
         let payloads = self.message_manager.send_packets(tick_manager.tick());
@@ -386,6 +663,7 @@ impl ConnectionManager {
         //  in the `ConnectionManager`
         time_manager: &TimeManager,
         tick_manager: &TickManager,
+        mut recorder: Option<&mut ReplayRecorder>,
     ) -> Result<(), ClientError> {
         let _span = trace_span!("receive").entered();
         self.message_manager
@@ -428,18 +706,80 @@ impl ConnectionManager {
                             time = ?pong.pong_sent_time,
                             "Updated server pong generation"
                         )
-                    } else if *channel_kind == ChannelKind::of::<EntityActionsChannel>() {
+                    } else if self
+                        .message_manager
+                        .channel_registry
+                        .is_replication_actions_channel(channel_kind)
+                    {
+                        let sequenced = self
+                            .message_manager
+                            .channel_registry
+                            .is_sequenced(channel_kind);
+                        if let Some(recorder) = recorder.as_mut() {
+                            let kind = if sequenced {
+                                ReplayMessageKind::SequencedActions
+                            } else {
+                                ReplayMessageKind::Actions
+                            };
+                            recorder.record(tick, kind, reader.peek_remaining());
+                        }
                         let actions = EntityActionsMessage::from_bytes(&mut reader)?;
-                        self.replication_receiver.recv_actions(actions, tick);
+                        if sequenced {
+                            self.replication_receiver
+                                .recv_sequenced_actions(actions, tick);
+                        } else {
+                            self.replication_receiver.recv_actions(actions, tick);
+                        }
+                    } else if *channel_kind == ChannelKind::of::<JoinSnapshotChannel>() {
+                        // all the actions messages of the snapshot are received at once, so that they
+                        // are applied to the World in the same frame
+                        let snapshot = JoinSnapshot::from_compressed_bytes(reader)?;
+                        debug!(
+                            num_groups = snapshot.messages.len(),
+                            "Received join snapshot"
+                        );
+                        for message in snapshot.messages {
+                            if let Some(recorder) = recorder.as_mut() {
+                                recorder.record(tick, ReplayMessageKind::Actions, message.clone());
+                            }
+                            let actions =
+                                EntityActionsMessage::from_bytes(&mut Reader::from(message))?;
+                            self.replication_receiver.recv_actions(actions, tick);
+                        }
                     } else if *channel_kind == ChannelKind::of::<EntityUpdatesChannel>() {
+                        if let Some(recorder) = recorder.as_mut() {
+                            recorder.record(
+                                tick,
+                                ReplayMessageKind::Updates,
+                                reader.peek_remaining(),
+                            );
+                        }
                         let updates = EntityUpdatesMessage::from_bytes(&mut reader)?;
                         self.replication_receiver.recv_updates(updates, tick);
+                    } else if *channel_kind == ChannelKind::of::<DisconnectChannel>() {
+                        // the server is about to disconnect us; keep the reason until it can be deserialized
+                        let net_id = NetId::from_bytes(&mut reader)?;
+                        self.received_kick_reason = Some((net_id, reader.consume()));
+                    } else if *channel_kind == ChannelKind::of::<SessionChannel>() {
+                        // the server issued a new token to resume our session
+                        self.session_token = Some(SessionToken::from_bytes(&mut reader)?);
+                    } else if *channel_kind == ChannelKind::of::<ProtocolCheckChannel>() {
+                        // the server replies with the protocol extensions that it accepted
+                        let accepted = ProtocolExtensionHashes::from_bytes(&mut reader)?;
+                        debug!(?accepted, "Server accepted protocol extensions");
+                        self.protocol_extensions = accepted.ids().collect();
                     } else {
                         // TODO: this code is copy-pasted from self.receive_message because of borrow checker limitations
                         // identify the type of message
                         let net_id = NetId::from_bytes(&mut reader)?;
                         let single_data = reader.consume();
-                        match self.message_registry.message_type(net_id) {
+                        let Some(message_type) = self
+                            .message_registry
+                            .message_type(net_id, &mut self.events)?
+                        else {
+                            continue;
+                        };
+                        match message_type {
                             #[cfg(feature = "leafwing")]
                             MessageType::LeafwingInput => {
                                 self.received_leafwing_input_messages
@@ -456,10 +796,13 @@ impl ConnectionManager {
                                     .or_default()
                                     .push(single_data);
                             }
+                            MessageType::TickTargeted => {
+                                self.scheduled_messages.receive(single_data)?;
+                            }
                         }
                     }
                 }
-                Ok::<(), SerializationError>(())
+                Ok::<(), MessageError>(())
             })?;
 
         if self.sync_manager.is_synced() {
@@ -476,11 +819,17 @@ impl ConnectionManager {
     }
 
     /// Receive a message from the server
-    pub(crate) fn receive_message(&mut self, mut reader: Reader) -> Result<(), SerializationError> {
+    pub(crate) fn receive_message(&mut self, mut reader: Reader) -> Result<(), MessageError> {
         // identify the type of message
         let net_id = NetId::from_bytes(&mut reader)?;
         let single_data = reader.consume();
-        match self.message_registry.message_type(net_id) {
+        let Some(message_type) = self
+            .message_registry
+            .message_type(net_id, &mut self.events)?
+        else {
+            return Ok(());
+        };
+        match message_type {
             #[cfg(feature = "leafwing")]
             MessageType::LeafwingInput => {
                 self.received_leafwing_input_messages
@@ -497,6 +846,9 @@ impl ConnectionManager {
                     .or_default()
                     .push(single_data);
             }
+            MessageType::TickTargeted => {
+                self.scheduled_messages.receive(single_data)?;
+            }
         }
         Ok(())
     }
@@ -551,7 +903,7 @@ impl MessageSend for ConnectionManager {
         channel_kind: ChannelKind,
         target: NetworkTarget,
     ) -> Result<(), ClientError> {
-        self.erased_send_message_to_target(message, channel_kind, target)
+        self.erased_send_message_to_target(message, channel_kind, target, DEFAULT_MESSAGE_PRIORITY)
     }
 }
 
@@ -582,6 +934,10 @@ impl ReplicationSend for ConnectionManager {
         vec![]
     }
 
+    fn fastest_channel_send_interval(&self) -> Option<Duration> {
+        self.message_manager.fastest_send_interval_override()
+    }
+
     fn cleanup(&mut self, tick: Tick) {
         debug!("Running replication clean");
         self.replication_sender.cleanup(tick);
@@ -591,8 +947,13 @@ impl ReplicationSend for ConnectionManager {
 
 #[cfg(test)]
 mod tests {
-    use crate::prelude::{client, server, ClientConnectionManager, RemoteEntityMap};
-    use crate::tests::protocol::EntityMessage;
+    use bevy::prelude::Events;
+
+    use crate::prelude::{
+        client, server, ClientConnectionManager, ClientMessageEvent, NetworkTarget,
+        RemoteEntityMap, ServerMessageEvent,
+    };
+    use crate::tests::protocol::{Channel1, EntityMessage, StringMessage};
     use crate::tests::stepper::BevyStepper;
 
     /// Check that we can map entities from the local world to the remote world
@@ -644,4 +1005,143 @@ mod tests {
         assert!(RemoteEntityMap::is_mapped(message.0));
         assert_eq!(RemoteEntityMap::mark_unmapped(message.0), server_entity);
     }
+
+    /// Check that we can send already serialized messages
+    #[test]
+    fn test_send_raw() {
+        let mut stepper = BevyStepper::default();
+
+        // client to server
+        let mut manager = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConnectionManager>();
+        let bytes = manager
+            .serialize_message(&StringMessage("a".to_string()))
+            .unwrap();
+        manager.send_raw::<Channel1>(bytes).unwrap();
+        // the bytes must contain a registered message
+        assert!(manager
+            .send_raw::<Channel1>(bytes::Bytes::from_static(&[255, 255]))
+            .is_err());
+        stepper.frame_step();
+        stepper.frame_step();
+        let messages: Vec<_> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<ServerMessageEvent<StringMessage>>>()
+            .drain()
+            .map(|event| event.message.0)
+            .collect();
+        assert_eq!(messages, vec!["a".to_string()]);
+
+        // server to client: the same bytes can be sent multiple times
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>();
+        let bytes = manager
+            .serialize_message(&StringMessage("b".to_string()))
+            .unwrap();
+        manager
+            .broadcast_raw::<Channel1>(bytes.clone(), NetworkTarget::All)
+            .unwrap();
+        manager
+            .broadcast_raw::<Channel1>(bytes, NetworkTarget::All)
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+        let messages: Vec<_> = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Events<ClientMessageEvent<StringMessage>>>()
+            .drain()
+            .map(|event| event.message.0)
+            .collect();
+        assert_eq!(messages, vec!["b".to_string(), "b".to_string()]);
+    }
+
+    /// Check that a raw message of a protocol extension is refused until the server accepts the extension
+    #[test]
+    fn test_send_raw_extension_disabled() {
+        use crate::client::error::ClientError;
+        use crate::protocol::extension::ProtocolExtensionId;
+        use crate::protocol::message::MessageType;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+        struct ExtensionMessage(u8);
+
+        let mut stepper = BevyStepper::default();
+        let mut manager = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConnectionManager>();
+        let extension = ProtocolExtensionId(1);
+        manager.message_registry.kind_map.current_extension = Some(extension);
+        manager
+            .message_registry
+            .add_message::<ExtensionMessage>(MessageType::Normal);
+        manager.message_registry.kind_map.current_extension = None;
+
+        let bytes = manager.serialize_message(&ExtensionMessage(1)).unwrap();
+        assert!(matches!(
+            manager.send_raw::<Channel1>(bytes.clone()),
+            Err(ClientError::ProtocolExtensionDisabled(id)) if id == extension
+        ));
+
+        // the message can be sent once the server accepted the extension
+        manager.protocol_extensions.insert(extension);
+        assert!(manager.send_raw::<Channel1>(bytes).is_ok());
+    }
+
+    /// Check that messages cannot be sent with a priority that cannot be sorted
+    #[test]
+    fn test_send_message_invalid_priority() {
+        use crate::client::error::ClientError;
+        use crate::server::error::ServerError;
+
+        let mut stepper = BevyStepper::default();
+        let mut manager = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConnectionManager>();
+        for priority in [f32::NAN, f32::INFINITY, -1.0] {
+            assert!(matches!(
+                manager.send_message_with_priority::<Channel1, _>(
+                    &mut StringMessage("a".to_string()),
+                    priority
+                ),
+                Err(ClientError::InvalidPriority(_))
+            ));
+        }
+        manager
+            .send_message_with_priority::<Channel1, _>(&mut StringMessage("a".to_string()), 0.0)
+            .unwrap();
+
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>();
+        assert!(matches!(
+            manager.send_message_to_target_with_priority::<Channel1, _>(
+                &mut StringMessage("b".to_string()),
+                NetworkTarget::All,
+                f32::NAN
+            ),
+            Err(ServerError::InvalidPriority(_))
+        ));
+
+        // the valid message is still sent
+        stepper.frame_step();
+        stepper.frame_step();
+        let messages: Vec<_> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<ServerMessageEvent<StringMessage>>>()
+            .drain()
+            .map(|event| event.message.0)
+            .collect();
+        assert_eq!(messages, vec!["a".to_string()]);
+    }
 }
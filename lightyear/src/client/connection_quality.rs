@@ -0,0 +1,301 @@
+//! Standardized connection quality levels, computed from the network statistics of the client.
+//!
+//! Games usually display a "wifi bars" indicator to tell the player how good their connection is.
+//! The [`ConnectionQualityPlugin`] classifies the connection into one of the [`ConnectionQuality`] levels
+//! using the RTT, the jitter, the packet loss, the stability of the tick sync with the server and
+//! whether the transport is able to send packets.
+//!
+//! The current level is available as a [`ConnectionQuality`] resource, and a [`ConnectionQualityChanged`]
+//! event is emitted whenever the level changes.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::client::*;
+//!
+//! fn update_indicator(mut events: EventReader<ConnectionQualityChanged>) {
+//!     for event in events.read() {
+//!         info!("connection quality: {} bars", event.current.bars());
+//!     }
+//! }
+//! ```
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use tracing::debug;
+
+use crate::client::config::ClientConfig;
+use crate::client::connection::ConnectionManager;
+use crate::client::run_conditions::is_connected;
+use crate::prelude::is_host_server;
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+use crate::shared::tick_manager::TickEvent;
+
+/// Quality level of the connection to the server
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+#[reflect(Resource)]
+pub enum ConnectionQuality {
+    /// The connection is good
+    #[default]
+    Good,
+    /// The connection is usable but the player might notice some lag or corrections
+    Degraded,
+    /// The connection is bad, or the client is not synced with the server
+    Bad,
+}
+
+impl ConnectionQuality {
+    /// Number of bars to display in a "wifi bars" indicator (from 1 to 3)
+    pub fn bars(&self) -> u8 {
+        match self {
+            ConnectionQuality::Good => 3,
+            ConnectionQuality::Degraded => 2,
+            ConnectionQuality::Bad => 1,
+        }
+    }
+}
+
+/// Event emitted when the [`ConnectionQuality`] changes
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionQualityChanged {
+    pub previous: ConnectionQuality,
+    pub current: ConnectionQuality,
+}
+
+/// Thresholds above which the connection is downgraded to a given [`ConnectionQuality`]
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct QualityThresholds {
+    /// Round-trip time
+    pub rtt: Duration,
+    /// Jitter of the round-trip time
+    pub jitter: Duration,
+    /// Fraction of the packets sent to the server that were lost (between 0.0 and 1.0)
+    pub packet_loss: f32,
+}
+
+impl QualityThresholds {
+    /// Returns true if any of the statistics is above the thresholds
+    fn exceeded_by(&self, rtt: Duration, jitter: Duration, packet_loss: f32) -> bool {
+        rtt > self.rtt || jitter > self.jitter || packet_loss > self.packet_loss
+    }
+}
+
+/// Configuration of the heuristics used to compute the [`ConnectionQuality`]
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct ConnectionQualityConfig {
+    /// The connection is at least [`ConnectionQuality::Degraded`] if any of these thresholds is exceeded
+    pub degraded: QualityThresholds,
+    /// The connection is [`ConnectionQuality::Bad`] if any of these thresholds is exceeded
+    pub bad: QualityThresholds,
+    /// The connection is at least [`ConnectionQuality::Degraded`] for this duration after the client
+    /// had to resync its tick with the server
+    pub resync_penalty: Duration,
+    /// The quality gets worse immediately, but it only gets better if the connection stayed better
+    /// for this duration. This avoids flickering between two levels.
+    pub recovery_duration: Duration,
+    /// The connection is at least [`ConnectionQuality::Degraded`] if the transport failed to send packets
+    /// during this many consecutive frames (for example because the socket buffer is full)
+    pub send_error_frames: u32,
+}
+
+impl Default for ConnectionQualityConfig {
+    fn default() -> Self {
+        Self {
+            degraded: QualityThresholds {
+                rtt: Duration::from_millis(150),
+                jitter: Duration::from_millis(30),
+                packet_loss: 0.05,
+            },
+            bad: QualityThresholds {
+                rtt: Duration::from_millis(300),
+                jitter: Duration::from_millis(80),
+                packet_loss: 0.15,
+            },
+            resync_penalty: Duration::from_secs(2),
+            recovery_duration: Duration::from_secs(1),
+            send_error_frames: 10,
+        }
+    }
+}
+
+impl ConnectionQualityConfig {
+    /// Compute the [`ConnectionQuality`] from the network statistics
+    pub fn classify(&self, rtt: Duration, jitter: Duration, packet_loss: f32) -> ConnectionQuality {
+        if self.bad.exceeded_by(rtt, jitter, packet_loss) {
+            ConnectionQuality::Bad
+        } else if self.degraded.exceeded_by(rtt, jitter, packet_loss) {
+            ConnectionQuality::Degraded
+        } else {
+            ConnectionQuality::Good
+        }
+    }
+}
+
+/// Computes the [`ConnectionQuality`] of the client
+///
+/// The heuristics can be configured via [`ConnectionQualityConfig`] in the [`ClientConfig`].
+#[derive(Default)]
+pub struct ConnectionQualityPlugin;
+
+/// Internal bookkeeping used to compute the [`ConnectionQuality`]
+#[derive(Resource, Debug, Default)]
+struct QualityTracker {
+    /// True once we have observed that the client is synced. Used to ignore the initial tick snap.
+    synced: bool,
+    /// Real time at which the last resync happened
+    last_resync: Option<Duration>,
+    /// Real time since which the connection has been better than the current quality
+    improving_since: Option<Duration>,
+}
+
+impl Plugin for ConnectionQualityPlugin {
+    fn build(&self, app: &mut App) {
+        // REFLECTION
+        app.register_type::<ConnectionQuality>();
+        // RESOURCES
+        app.init_resource::<ConnectionQuality>();
+        app.init_resource::<QualityTracker>();
+        // EVENTS
+        app.add_event::<ConnectionQualityChanged>();
+        // OBSERVERS
+        app.observe(record_resync);
+        // SYSTEMS
+        app.add_systems(
+            PreUpdate,
+            update_connection_quality
+                .after(InternalMainSet::<ClientMarker>::Receive)
+                .run_if(is_connected.and_then(not(is_host_server))),
+        );
+    }
+}
+
+/// Record the time of the tick snaps that happen after the initial sync
+fn record_resync(
+    _trigger: Trigger<TickEvent>,
+    time: Res<Time<Real>>,
+    mut tracker: ResMut<QualityTracker>,
+) {
+    if tracker.synced {
+        tracker.last_resync = Some(time.elapsed());
+    }
+}
+
+fn update_connection_quality(
+    config: Res<ClientConfig>,
+    connection: Res<ConnectionManager>,
+    time: Res<Time<Real>>,
+    mut tracker: ResMut<QualityTracker>,
+    mut quality: ResMut<ConnectionQuality>,
+    mut events: EventWriter<ConnectionQualityChanged>,
+) {
+    let config = &config.connection_quality;
+    let now = time.elapsed();
+    let synced = connection.is_synced();
+    tracker.synced = synced;
+    let target = if !synced {
+        ConnectionQuality::Bad
+    } else {
+        let mut target = config.classify(
+            connection.ping_manager.rtt(),
+            connection.ping_manager.jitter(),
+            connection.packet_loss(),
+        );
+        if tracker
+            .last_resync
+            .is_some_and(|resync| now.saturating_sub(resync) < config.resync_penalty)
+        {
+            target = target.max(ConnectionQuality::Degraded);
+        }
+        if connection.send_error_frames >= config.send_error_frames {
+            target = target.max(ConnectionQuality::Degraded);
+        }
+        target
+    };
+
+    let current = *quality;
+    let new = if target >= current {
+        tracker.improving_since = None;
+        target
+    } else {
+        let since = *tracker.improving_since.get_or_insert(now);
+        if now.saturating_sub(since) >= config.recovery_duration {
+            tracker.improving_since = None;
+            target
+        } else {
+            current
+        }
+    };
+    if new != current {
+        debug!(previous = ?current, current = ?new, "Connection quality changed");
+        *quality = new;
+        events.send(ConnectionQualityChanged {
+            previous: current,
+            current: new,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_classify() {
+        let config = ConnectionQualityConfig::default();
+        assert_eq!(
+            config.classify(Duration::from_millis(50), Duration::from_millis(5), 0.0),
+            ConnectionQuality::Good
+        );
+        assert_eq!(
+            config.classify(Duration::from_millis(200), Duration::from_millis(5), 0.0),
+            ConnectionQuality::Degraded
+        );
+        assert_eq!(
+            config.classify(Duration::from_millis(50), Duration::from_millis(5), 0.1),
+            ConnectionQuality::Degraded
+        );
+        assert_eq!(
+            config.classify(Duration::from_millis(50), Duration::from_millis(100), 0.0),
+            ConnectionQuality::Bad
+        );
+    }
+
+    #[test]
+    fn test_resync_degrades_quality() {
+        let mut stepper = BevyStepper::default();
+        // the quality is Bad until the client is synced; recover immediately
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .connection_quality
+            .recovery_duration = Duration::ZERO;
+        stepper.frame_step();
+        assert_eq!(
+            *stepper.client_app.world().resource::<ConnectionQuality>(),
+            ConnectionQuality::Good
+        );
+
+        // a tick snap after the initial sync means that the sync is unstable
+        let tick = stepper.client_tick();
+        stepper.client_app.world_mut().trigger(TickEvent::TickSnap {
+            old_tick: tick,
+            new_tick: tick + 10,
+        });
+        stepper.frame_step();
+        assert_eq!(
+            *stepper.client_app.world().resource::<ConnectionQuality>(),
+            ConnectionQuality::Degraded
+        );
+        let events = stepper
+            .client_app
+            .world()
+            .resource::<Events<ConnectionQualityChanged>>();
+        assert_eq!(
+            events.iter_current_update_events().next(),
+            Some(&ConnectionQualityChanged {
+                previous: ConnectionQuality::Good,
+                current: ConnectionQuality::Degraded,
+            })
+        );
+    }
+}
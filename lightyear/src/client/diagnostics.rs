@@ -1,8 +1,9 @@
+use crate::channel::diagnostics::{ChannelDiagnosticPaths, ChannelDiagnosticsPlugin};
 use crate::client::connection::ConnectionManager;
 use crate::client::prediction::diagnostics::PredictionDiagnosticsPlugin;
 use bevy::app::{App, Plugin, PostUpdate};
 use bevy::diagnostic::Diagnostics;
-use bevy::prelude::{not, Condition, IntoSystemConfigs, Real, Res, ResMut, Time};
+use bevy::prelude::{not, resource_exists, Condition, IntoSystemConfigs, Real, Res, ResMut, Time};
 use bevy::time::common_conditions::on_timer;
 use bevy::utils::Duration;
 
@@ -39,6 +40,14 @@ fn ping_diagnostics_system(connection: Res<ConnectionManager>, diagnostics: Diag
     PingDiagnosticsPlugin::add_measurements(&connection.ping_manager, diagnostics);
 }
 
+fn channel_diagnostics_system(
+    connection: Res<ConnectionManager>,
+    paths: Res<ChannelDiagnosticPaths>,
+    diagnostics: Diagnostics,
+) {
+    ChannelDiagnosticsPlugin::add_measurements(&connection.message_manager, &paths, diagnostics);
+}
+
 impl Plugin for ClientDiagnosticsPlugin {
     fn build(&self, app: &mut App) {
         {
@@ -53,6 +62,15 @@ impl Plugin for ClientDiagnosticsPlugin {
             );
         }
         app.add_plugins(PredictionDiagnosticsPlugin::default());
+        app.add_plugins(ChannelDiagnosticsPlugin::default());
+        app.add_systems(
+            PostUpdate,
+            channel_diagnostics_system.run_if(
+                on_timer(self.flush_interval)
+                    .and_then(not(is_host_server.or_else(is_disconnected)))
+                    .and_then(resource_exists::<ChannelDiagnosticPaths>),
+            ),
+        );
 
         {
             app.add_plugins(IoDiagnosticsPlugin);
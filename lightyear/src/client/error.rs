@@ -16,4 +16,8 @@ pub enum ClientError {
     MessageProtocolError(#[from] crate::protocol::message::MessageError),
     #[error(transparent)]
     ComponentProtocolError(#[from] crate::protocol::component::ComponentError),
+    #[error("the protocol extension {0:?} was not accepted by the server")]
+    ProtocolExtensionDisabled(crate::protocol::extension::ProtocolExtensionId),
+    #[error("the message priority {0} must be a finite, non-negative number")]
+    InvalidPriority(f32),
 }
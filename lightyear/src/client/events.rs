@@ -33,6 +33,7 @@ impl Plugin for ClientEventsPlugin {
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<NetworkErrorEvent>()
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default());
     }
@@ -71,6 +72,14 @@ pub struct DisconnectEvent {
     pub reason: Option<DisconnectReason>,
 }
 
+/// Bevy [`Event`] emitted on the client when an error happens while receiving or sending packets
+/// (for example a malformed packet, or a transient io error)
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct NetworkErrorEvent {
+    /// Description of the error
+    pub error: String,
+}
+
 /// Bevy [`Event`] emitted on the client to indicate the user input for the tick
 pub type InputEvent<I> = crate::shared::events::components::InputEvent<I, ()>;
 /// Bevy [`Event`] emitted on the client when a EntitySpawn replication message is received
@@ -85,3 +94,5 @@ pub type ComponentInsertEvent<C> = crate::shared::events::components::ComponentI
 pub type ComponentRemoveEvent<C> = crate::shared::events::components::ComponentRemoveEvent<C, ()>;
 /// Bevy [`Event`] emitted on the client when a (non-replication) message is received
 pub type MessageEvent<M> = crate::shared::events::components::MessageEvent<M, ()>;
+/// Bevy [`Event`] emitted on the client when a message or component of an unknown type is skipped
+pub type UnknownTypeEvent = crate::shared::events::components::UnknownTypeEvent<()>;
@@ -40,10 +40,37 @@
 //!   If we have 2 frames with no FixedUpdate in between (because the framerate is high compared to the tickrate), then on the second frame
 //!   the button won't be `JustPressed` anymore (it will simply be `Pressed`) so your system might not react correctly to it.
 //!
+//! ### Local players
+//!
+//! Multiple players can share the same client connection (for example in a split-screen game).
+//! Each local player controls its own entity, with its own [`InputMap`], [`ActionState`] and [`InputBuffer`]:
+//! the inputs of each entity are sent to the server separately, which applies them to the corresponding entity.
+//!
+//! The server spawns one entity per local player, controlled by the client, with a [`LocalPlayerId`]:
+//! ```rust,ignore
+//! commands.spawn((
+//!     Replicate {
+//!         controlled_by: ControlledBy { target: NetworkTarget::Single(client_id), ..default() },
+//!         ..default()
+//!     },
+//!     LocalPlayerId(1),
+//! ));
+//! ```
+//! and the client registers the [`InputMap`] of each local player. It is inserted automatically on the
+//! entity of that player (the predicted entity if the entity is predicted):
+//! ```rust,ignore
+//! app.add_plugins(
+//!     LeafwingInputPlugin::<PlayerActions>::default()
+//!         .with_local_player(LocalPlayerId(0), InputMap::new([(PlayerActions::Up, KeyCode::KeyW)]))
+//!         .with_local_player(LocalPlayerId(1), InputMap::new([(PlayerActions::Up, KeyCode::ArrowUp)])),
+//! );
+//! ```
+//!
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use leafwing_input_manager::prelude::*;
 use tracing::{error, trace};
 
@@ -51,6 +78,7 @@ use crate::channel::builder::InputChannel;
 use crate::client::components::Confirmed;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
+use crate::client::interpolation::Interpolated;
 use crate::client::prediction::plugin::{is_in_rollback, PredictionSet};
 use crate::client::prediction::resource::PredictionManager;
 use crate::client::prediction::rollback::Rollback;
@@ -66,7 +94,7 @@ use crate::prelude::{
 };
 use crate::protocol::message::MessageKind;
 use crate::serialize::reader::Reader;
-use crate::shared::replication::components::PrePredicted;
+use crate::shared::replication::components::{Controlled, LocalPlayerId, PrePredicted};
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 use crate::shared::tick_manager::TickEvent;
 
@@ -118,18 +146,34 @@ impl<A> Default for LeafwingInputConfig<A> {
     }
 }
 
+/// The [`InputMap`] of each local player, that is inserted on the entities controlled by that player
+#[derive(Debug, Resource)]
+struct LocalPlayerInputMaps<A: LeafwingUserAction>(HashMap<LocalPlayerId, InputMap<A>>);
+
 /// Adds a plugin to handle inputs using the LeafwingInputManager
-pub struct LeafwingInputPlugin<A> {
+pub struct LeafwingInputPlugin<A: LeafwingUserAction> {
     config: LeafwingInputConfig<A>,
+    local_players: HashMap<LocalPlayerId, InputMap<A>>,
 }
 
-impl<A> LeafwingInputPlugin<A> {
+impl<A: LeafwingUserAction> LeafwingInputPlugin<A> {
     pub fn new(config: LeafwingInputConfig<A>) -> Self {
-        Self { config }
+        Self {
+            config,
+            local_players: HashMap::default(),
+        }
+    }
+
+    /// Register the [`InputMap`] of a local player.
+    ///
+    /// It will be inserted on the entities controlled by this client that have the same [`LocalPlayerId`].
+    pub fn with_local_player(mut self, id: LocalPlayerId, input_map: InputMap<A>) -> Self {
+        self.local_players.insert(id, input_map);
+        self
     }
 }
 
-impl<A> Default for LeafwingInputPlugin<A> {
+impl<A: LeafwingUserAction> Default for LeafwingInputPlugin<A> {
     fn default() -> Self {
         Self::new(LeafwingInputConfig::default())
     }
@@ -170,6 +214,7 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A>
 
         app.init_resource::<InputBuffer<A>>();
         app.init_resource::<MessageBuffer<A>>();
+        app.insert_resource(LocalPlayerInputMaps(self.local_players.clone()));
 
         // SETS
         app.configure_sets(
@@ -215,6 +260,10 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A>
                 add_action_state_buffer::<A>
                     .in_set(InputSystemSet::AddBuffers)
                     .after(PredictionSet::SpawnPrediction),
+                // the LocalPlayerId and Controlled components are copied to the predicted entity in SpawnHistory
+                add_local_player_input_map::<A>
+                    .after(PredictionSet::SpawnHistory)
+                    .run_if(should_run.clone()),
             ),
         );
 
@@ -349,6 +398,33 @@ fn add_action_state_buffer<A: LeafwingUserAction>(
     }
 }
 
+/// Insert the [`InputMap`] of the local player on the entities that it controls.
+///
+/// If the entity is predicted, the input map is only added to the predicted entity.
+fn add_local_player_input_map<A: LeafwingUserAction>(
+    mut commands: Commands,
+    input_maps: Res<LocalPlayerInputMaps<A>>,
+    query: Query<
+        (Entity, &LocalPlayerId, Option<&Confirmed>),
+        (
+            With<Controlled>,
+            Without<InputMap<A>>,
+            Without<Interpolated>,
+            Or<(Added<LocalPlayerId>, Added<Controlled>)>,
+        ),
+    >,
+) {
+    for (entity, local_player, confirmed) in query.iter() {
+        if confirmed.is_some_and(|confirmed| confirmed.predicted.is_some()) {
+            continue;
+        }
+        if let Some(input_map) = input_maps.0.get(local_player) {
+            trace!(?entity, ?local_player, "adding input map of local player");
+            commands.entity(entity).insert(input_map.clone());
+        }
+    }
+}
+
 /// At the start of the frame, restore the ActionState to the latest-action state in buffer
 /// (e.g. the delayed action state) because all inputs (i.e. diffs) are applied to the delayed action-state.
 fn get_delayed_action_state<A: LeafwingUserAction>(
@@ -805,8 +881,8 @@ mod tests {
     use std::time::Duration;
 
     use crate::prelude::client::PredictionConfig;
-    use crate::prelude::server::Replicate;
-    use crate::prelude::{client, SharedConfig, TickConfig};
+    use crate::prelude::server::{ControlledBy, Replicate};
+    use crate::prelude::{client, NetworkTarget, SharedConfig, TickConfig};
     use crate::tests::protocol::*;
     use crate::tests::stepper::BevyStepper;
 
@@ -881,6 +957,60 @@ mod tests {
         (server_entity, client_entity)
     }
 
+    /// Check that each local player gets its own InputMap and InputBuffer
+    #[test]
+    fn test_local_players() {
+        let mut stepper = BevyStepper::default();
+        let input_map = |key| InputMap::<LeafwingInput1>::new([(LeafwingInput1::Jump, key)]);
+        stepper
+            .client_app
+            .world_mut()
+            .insert_resource(LocalPlayerInputMaps(HashMap::from_iter([
+                (LocalPlayerId(0), input_map(KeyCode::KeyA)),
+                (LocalPlayerId(1), input_map(KeyCode::KeyB)),
+            ])));
+        let replicate = || Replicate {
+            controlled_by: ControlledBy {
+                target: NetworkTarget::All,
+                ..default()
+            },
+            ..default()
+        };
+        let server_entity_0 = stepper
+            .server_app
+            .world_mut()
+            .spawn((replicate(), LocalPlayerId(0)))
+            .id();
+        let server_entity_1 = stepper
+            .server_app
+            .world_mut()
+            .spawn((replicate(), LocalPlayerId(1)))
+            .id();
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+
+        for (server_entity, key) in [
+            (server_entity_0, KeyCode::KeyA),
+            (server_entity_1, KeyCode::KeyB),
+        ] {
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .unwrap();
+            let client_entity = stepper.client_app.world().entity(client_entity);
+            assert_eq!(
+                client_entity.get::<InputMap<LeafwingInput1>>(),
+                Some(&input_map(key))
+            );
+            assert!(client_entity.get::<InputBuffer<LeafwingInput1>>().is_some());
+        }
+    }
+
     /// Check that ActionStates are stored correctly in the InputBuffer
     #[test]
     fn test_buffer_inputs_no_delay() {
@@ -71,11 +71,33 @@ pub struct InputConfig {
     /// For instance, a value of 3 means that each input packet will contain the inputs for all the ticks
     ///  for the 3 last packets.
     pub packet_redundancy: u16,
+    /// Number of ticks of inputs that are included in each input message.
+    ///
+    /// If set, this overrides the redundancy computed from `packet_redundancy`. It will still be at least
+    /// the number of ticks between two input messages, so that no input is skipped.
+    pub redundancy_ticks: Option<u16>,
+    /// Maximum number of ticks of inputs that can be included in a single input message.
+    /// Use this to bound the bandwidth used by inputs.
+    pub max_packet_inputs: u16,
     /// How often do we send input messages to the server?
     /// Duration::default() means that we will send input messages every frame.
     pub send_interval: Duration,
 }
 
+impl InputConfig {
+    /// Number of ticks of inputs to include in an input message, given the number of ticks
+    /// between two consecutive input messages
+    pub(crate) fn message_len(&self, ticks_per_message: u16) -> u16 {
+        self.redundancy_ticks
+            .map_or(
+                self.packet_redundancy.saturating_mul(ticks_per_message),
+                |redundancy_ticks| redundancy_ticks.max(ticks_per_message),
+            )
+            .min(self.max_packet_inputs)
+            .max(1)
+    }
+}
+
 /// Resource that handles buffering and sending inputs to the server
 ///
 /// Note: it is advised to enable the feature `leafwing` and  switch to the `LeafwingInputPlugin`,
@@ -110,6 +132,8 @@ impl Default for InputConfig {
     fn default() -> Self {
         InputConfig {
             packet_redundancy: 10,
+            redundancy_ticks: None,
+            max_packet_inputs: u16::MAX,
             send_interval: Duration::default(),
         }
     }
@@ -294,9 +318,7 @@ fn prepare_input_message<A: UserAction>(
         ((input_send_interval.as_nanos() / config.shared.tick.tick_duration.as_nanos()) + 1)
             .try_into()
             .unwrap();
-    let redundancy = config.input.packet_redundancy;
-    // let redundancy = 3;
-    let message_len = redundancy * num_tick;
+    let message_len = config.input.message_len(num_tick);
     // TODO: we can either:
     //  - buffer an input message at every tick, and not require that much redundancy
     //  - buffer an input every frame; and require some redundancy (number of tick per frame)
@@ -347,7 +369,7 @@ fn send_input_directly_to_client_events<A: UserAction>(
 
 #[cfg(test)]
 mod tests {
-    use crate::client::input::native::InputSystemSet;
+    use crate::client::input::native::{InputConfig, InputSystemSet};
     use crate::prelude::client::InputManager;
     use crate::prelude::{server, TickManager};
     use crate::tests::host_server_stepper::HostServerStepper;
@@ -390,4 +412,25 @@ mod tests {
         stepper.frame_step();
         assert!(stepper.server_app.world().resource::<Counter>().0 > 0);
     }
+
+    #[test]
+    fn test_input_message_len() {
+        let config = InputConfig::default();
+        assert_eq!(config.message_len(1), 10);
+        assert_eq!(config.message_len(3), 30);
+
+        let config = InputConfig {
+            redundancy_ticks: Some(5),
+            ..default()
+        };
+        assert_eq!(config.message_len(1), 5);
+        // we need to include at least all the ticks since the last input message
+        assert_eq!(config.message_len(8), 8);
+
+        let config = InputConfig {
+            max_packet_inputs: 4,
+            ..default()
+        };
+        assert_eq!(config.message_len(1), 4);
+    }
 }
@@ -1,9 +1,12 @@
-use bevy::prelude::{Commands, DespawnRecursiveExt, OnRemove, Query, ResMut, Trigger};
+use bevy::prelude::{Commands, DespawnRecursiveExt, OnAdd, OnRemove, Query, ResMut, Trigger};
+use tracing::debug;
 
 use crate::client::components::{Confirmed, SyncComponent};
 use crate::client::interpolation::interpolate::InterpolateStatus;
 use crate::client::interpolation::interpolation_history::ConfirmedHistory;
 use crate::client::interpolation::resource::InterpolationManager;
+use crate::prelude::ShouldBePredicted;
+use crate::shared::replication::components::ShouldBeInterpolated;
 
 /// Remove the component from interpolated entities when it gets removed from confirmed
 pub(crate) fn removed_components<C: SyncComponent>(
@@ -40,3 +43,35 @@ pub(crate) fn despawn_interpolated(
         }
     }
 }
+
+/// Despawn the interpolated entity when the server switches the confirmed entity to prediction
+/// (for example when the client gained the ownership of the entity)
+pub(crate) fn despawn_interpolated_on_prediction(
+    trigger: Trigger<OnAdd, ShouldBePredicted>,
+    mut manager: ResMut<InterpolationManager>,
+    mut query: Query<&mut Confirmed>,
+    mut commands: Commands,
+) {
+    let confirmed_entity = trigger.entity();
+    let Ok(mut confirmed) = query.get_mut(confirmed_entity) else {
+        return;
+    };
+    if let Some(interpolated) = confirmed.interpolated.take() {
+        debug!(
+            ?confirmed_entity,
+            ?interpolated,
+            "Despawn interpolated entity: the confirmed entity is now predicted"
+        );
+        manager
+            .interpolated_entity_map
+            .get_mut()
+            .confirmed_to_interpolated
+            .remove(&confirmed_entity);
+        if let Some(entity_mut) = commands.get_entity(interpolated) {
+            entity_mut.despawn_recursive();
+        }
+        commands
+            .entity(confirmed_entity)
+            .remove::<ShouldBeInterpolated>();
+    }
+}
@@ -0,0 +1,181 @@
+//! Health of the interpolation buffer.
+//!
+//! Interpolated entities are rendered [`InterpolationDelay`](crate::prelude::client::InterpolationDelay) behind the latest server update, so that there is
+//! always a server snapshot to interpolate towards. The [`InterpolationBufferStats`] resource tracks how many
+//! ticks of server updates are buffered ahead of the interpolation timeline:
+//! - an *underrun* happens when the interpolation timeline reaches the latest received server tick. The
+//!   interpolated entities stop moving (or get extrapolated) until a new update arrives: the delay is too small
+//!   for the network conditions.
+//! - an *overrun* happens when the buffer becomes much longer than the interpolation delay (for example after
+//!   a burst of packets), and the interpolation timeline has to catch up.
+//!
+//! Frequent underruns or overruns usually show up as rubber-banding. The delay can be changed at runtime by
+//! modifying the [`ClientConfig`] resource; the interpolation timeline then moves progressively to the new delay:
+//!
+//! ```rust,ignore
+//! fn increase_delay(mut config: ResMut<ClientConfig>, stats: Res<InterpolationBufferStats>) {
+//!     if stats.underruns > 10 {
+//!         config.interpolation.delay.min_delay += Duration::from_millis(20);
+//!     }
+//! }
+//! ```
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::{resource_changed, IntoSystemConfigs, Res, ResMut, Resource};
+use bevy::utils::Duration;
+
+use crate::client::config::ClientConfig;
+use crate::client::connection::ConnectionManager;
+use crate::prelude::TickManager;
+
+/// Number of ticks of server updates that are buffered ahead of the interpolation timeline
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct InterpolationBufferStats {
+    /// Number of ticks between the interpolation tick and the latest received server tick
+    pub buffer_ticks: i16,
+    /// Current delay between the estimated server time and the interpolation time
+    pub current_delay: Duration,
+    /// Delay computed from the [`InterpolationDelay`](crate::prelude::client::InterpolationDelay) that the interpolation timeline converges to
+    pub target_delay: Duration,
+    /// Number of times the interpolation timeline ran out of server updates
+    pub underruns: u32,
+    /// Number of times the buffer became much longer than the target delay
+    pub overruns: u32,
+    underrun: bool,
+    overrun: bool,
+}
+
+impl InterpolationBufferStats {
+    /// Update the stats with the latest state of the buffer
+    fn update(
+        &mut self,
+        buffer_ticks: i16,
+        current_delay: Duration,
+        target_delay: Duration,
+        max_margin: Duration,
+    ) {
+        self.buffer_ticks = buffer_ticks;
+        self.current_delay = current_delay;
+        self.target_delay = target_delay;
+        // only count the transitions, not every frame spent in the underrun/overrun state
+        let underrun = buffer_ticks <= 0;
+        if underrun && !self.underrun {
+            self.underruns += 1;
+        }
+        self.underrun = underrun;
+        let overrun = current_delay > target_delay + max_margin;
+        if overrun && !self.overrun {
+            self.overruns += 1;
+        }
+        self.overrun = overrun;
+    }
+}
+
+pub(crate) fn update_interpolation_buffer_stats(
+    config: Res<ClientConfig>,
+    connection: Res<ConnectionManager>,
+    tick_manager: Res<TickManager>,
+    mut stats: ResMut<InterpolationBufferStats>,
+) {
+    let sync_manager = &connection.sync_manager;
+    let Some(latest_server_tick) = sync_manager.latest_received_server_tick else {
+        return;
+    };
+    let tick_duration = tick_manager.config.tick_duration;
+    stats.update(
+        latest_server_tick - sync_manager.interpolation_tick(&tick_manager),
+        sync_manager.interpolation_delay(),
+        config
+            .interpolation
+            .delay
+            .to_duration(config.shared.server_replication_send_interval),
+        tick_duration.mul_f32(config.sync.max_error_margin),
+    );
+}
+
+/// Plugin that registers the [`InterpolationBufferStats`] as [`Diagnostics`]
+pub struct InterpolationDiagnosticsPlugin {
+    pub history_len: usize,
+}
+
+impl Default for InterpolationDiagnosticsPlugin {
+    fn default() -> Self {
+        Self { history_len: 60 }
+    }
+}
+
+impl InterpolationDiagnosticsPlugin {
+    /// Number of ticks buffered ahead of the interpolation timeline
+    pub const BUFFER_TICKS: DiagnosticPath =
+        DiagnosticPath::const_new("interpolation.buffer_ticks");
+
+    /// Current interpolation delay
+    pub const DELAY: DiagnosticPath = DiagnosticPath::const_new("interpolation.delay.ms");
+
+    /// Number of buffer underruns
+    pub const UNDERRUNS: DiagnosticPath = DiagnosticPath::const_new("interpolation.underruns");
+
+    /// Number of buffer overruns
+    pub const OVERRUNS: DiagnosticPath = DiagnosticPath::const_new("interpolation.overruns");
+
+    fn add_measurements(stats: Res<InterpolationBufferStats>, mut diagnostics: Diagnostics) {
+        diagnostics.add_measurement(&Self::BUFFER_TICKS, || stats.buffer_ticks as f64);
+        diagnostics.add_measurement(&Self::DELAY, || stats.current_delay.as_secs_f64() * 1000.0);
+        diagnostics.add_measurement(&Self::UNDERRUNS, || stats.underruns as f64);
+        diagnostics.add_measurement(&Self::OVERRUNS, || stats.overruns as f64);
+    }
+}
+
+impl Plugin for InterpolationDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(
+            Diagnostic::new(Self::BUFFER_TICKS)
+                .with_suffix("ticks")
+                .with_max_history_length(self.history_len),
+        );
+        app.register_diagnostic(
+            Diagnostic::new(Self::DELAY)
+                .with_suffix("ms")
+                .with_max_history_length(self.history_len),
+        );
+        app.register_diagnostic(
+            Diagnostic::new(Self::UNDERRUNS)
+                .with_suffix("")
+                .with_max_history_length(self.history_len),
+        );
+        app.register_diagnostic(
+            Diagnostic::new(Self::OVERRUNS)
+                .with_suffix("")
+                .with_max_history_length(self.history_len),
+        );
+        app.add_systems(
+            PostUpdate,
+            Self::add_measurements.run_if(resource_changed::<InterpolationBufferStats>),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolation_buffer_stats() {
+        let mut stats = InterpolationBufferStats::default();
+        let target = Duration::from_millis(100);
+        let margin = Duration::from_millis(50);
+        stats.update(5, target, target, margin);
+        assert_eq!((stats.underruns, stats.overruns), (0, 0));
+        // consecutive frames in the underrun state only count once
+        stats.update(0, target, target, margin);
+        stats.update(-1, target, target, margin);
+        assert_eq!(stats.underruns, 1);
+        stats.update(3, target, target, margin);
+        stats.update(0, target, target, margin);
+        assert_eq!(stats.underruns, 2);
+
+        stats.update(20, Duration::from_millis(200), target, margin);
+        assert_eq!(stats.overruns, 1);
+        assert_eq!(stats.buffer_ticks, 20);
+    }
+}
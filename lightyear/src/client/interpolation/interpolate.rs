@@ -1,10 +1,11 @@
-use bevy::prelude::{Commands, Component, Entity, Query, Res, Without};
+use bevy::prelude::{Commands, Component, Entity, Query, Res, Time, Without};
 use tracing::{debug, trace};
 
 use crate::client::components::SyncComponent;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::interpolation_history::ConfirmedHistory;
+use crate::client::interpolation::Interpolated;
 use crate::prelude::{ComponentRegistry, TickManager};
 use crate::shared::tick_manager::Tick;
 
@@ -31,6 +32,9 @@ pub struct InterpolateStatus<C: Component> {
     pub current_tick: Tick,
     /// for more accurate interpolation, this is the fraction between [current_tick, current_tick + 1[
     pub current_overstep: f32,
+    /// value of the component when we stopped extrapolating, along with the blend progress in [0, 1[.
+    /// The displayed value is blended from this value to the interpolated value
+    pub blend: Option<(C, f32)>,
 }
 
 impl<C: Component> InterpolateStatus<C> {
@@ -51,6 +55,7 @@ impl<C: Component> InterpolateStatus<C> {
 /// At the end of each frame, interpolate the components between the last 2 confirmed server states
 /// Invariant: start_tick <= current_interpolate_tick + overstep < end_tick
 pub(crate) fn update_interpolate_status<C: SyncComponent>(
+    component_registry: Res<ComponentRegistry>,
     config: Res<ClientConfig>,
     connection: Res<ConnectionManager>,
     tick_manager: Res<TickManager>,
@@ -68,6 +73,21 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
         * config.shared.server_replication_send_interval.as_secs_f32()
         / config.shared.tick.tick_duration.as_secs_f32()) as i16
         + 1;
+    // keep the start tick around for as long as we are extrapolating from it
+    let extrapolation = component_registry.has_extrapolation::<C>();
+    let keep_start_delta_tick = if extrapolation {
+        let max_extrapolation_tick = (config
+            .interpolation
+            .extrapolation
+            .max_duration
+            .as_secs_f32()
+            / config.shared.tick.tick_duration.as_secs_f32())
+            as i16
+            + 1;
+        send_interval_delta_tick.max(max_extrapolation_tick + 1)
+    } else {
+        send_interval_delta_tick
+    };
 
     let current_interpolate_tick = connection
         .sync_manager
@@ -75,27 +95,32 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
     let current_interpolate_overstep = connection
         .sync_manager
         .interpolation_overstep(tick_manager.as_ref());
-    for (entity, component, mut status, mut history) in query.iter_mut() {
+    for (entity, mut component, mut status, mut history) in query.iter_mut() {
+        // we were extrapolating past the start tick because we had no end tick
+        let previous_start_tick = status.start.as_ref().map(|(tick, _)| *tick);
+        let was_extrapolating = extrapolation
+            && status.end.is_none()
+            && previous_start_tick.is_some_and(|tick| tick < status.current_tick);
         let mut start = status.start.take();
         let mut end = status.end.take();
 
         // if the interpolation tick is beyond the previous end tick,
         // we need to replace start with end, and clear end
-        if let Some((end_tick, ref end_value)) = end {
-            if end_tick <= current_interpolate_tick {
-                trace!(
-                    ?entity,
-                    ?end_tick,
-                    ?current_interpolate_tick,
-                    "interpolation is beyond previous end tick"
-                );
-                start.clone_from(&end);
-                // TODO: this clone should be avoidable
-                if let Some(mut component) = component {
-                    *component = end_value.clone();
-                }
-                end = None;
+        if end
+            .as_ref()
+            .is_some_and(|(end_tick, _)| *end_tick <= current_interpolate_tick)
+        {
+            trace!(
+                ?entity,
+                end_tick = ?end.as_ref().map(|(tick, _)| tick),
+                ?current_interpolate_tick,
+                "interpolation is beyond previous end tick"
+            );
+            // TODO: this clone should be avoidable
+            if let (Some(component), Some((_, end_value))) = (component.as_mut(), end.as_ref()) {
+                **component = end_value.clone();
             }
+            start = end.take();
         }
 
         // TODO: do we need to call this if status.end is set? probably not because the updates are sequenced?
@@ -172,13 +197,27 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
         if end.is_none() {
             let temp_start = std::mem::take(&mut start);
             if let Some((start_tick, _)) = temp_start {
-                if current_interpolate_tick - start_tick < send_interval_delta_tick {
+                if current_interpolate_tick - start_tick < keep_start_delta_tick {
                     start = temp_start;
                 }
                 // else (if it's been too long), reset the server tick to None
             }
         }
 
+        // we received a new server update while extrapolating: blend the extrapolated value
+        // back into the interpolated value to avoid a visible jump
+        let start_tick = start.as_ref().map(|(tick, _)| *tick);
+        if was_extrapolating
+            && start_tick.is_some()
+            && (end.is_some() || start_tick != previous_start_tick)
+            && !config.interpolation.extrapolation.blend_duration.is_zero()
+        {
+            if let Some(component) = component.as_ref() {
+                trace!(?entity, "blending from the extrapolated value");
+                status.blend = Some(((**component).clone(), 0.0));
+            }
+        }
+
         trace!(
             ?entity,
             component = ?kind,
@@ -253,24 +292,95 @@ pub(crate) fn insert_interpolated_component<C: SyncComponent>(
 /// Update the component value on the Interpolate entity
 pub(crate) fn interpolate<C: Component + Clone>(
     component_registry: Res<ComponentRegistry>,
-    mut query: Query<(&mut C, &InterpolateStatus<C>)>,
+    config: Res<ClientConfig>,
+    time: Res<Time>,
+    mut query: Query<(&mut C, &mut InterpolateStatus<C>)>,
 ) {
-    for (mut component, status) in query.iter_mut() {
+    let blend_delta = blend_delta(&config, &time);
+    for (mut component, mut status) in query.iter_mut() {
         debug!("checking if we do interpolation");
         // NOTE: it is possible that we reach start_tick when end_tick is not set
-        if let Some((start_tick, start_value)) = &status.start {
-            if let Some((end_tick, end_value)) = &status.end {
-                debug!(?start_tick, interpolate_tick=?status.current_tick, ?end_tick, "doing interpolation!");
-                assert!(status.current_tick < *end_tick);
-                if start_tick != end_tick {
-                    let t = status.interpolation_fraction().unwrap();
-                    let value = component_registry.interpolate(start_value, end_value, t);
-                    *component = value;
-                } else {
-                    *component = start_value.clone();
-                }
-            }
+        // (the component is then either frozen or extrapolated by `extrapolate`)
+        let (Some((start_tick, start_value)), Some((end_tick, end_value))) =
+            (&status.start, &status.end)
+        else {
+            continue;
+        };
+        debug!(?start_tick, interpolate_tick=?status.current_tick, ?end_tick, "doing interpolation!");
+        assert!(status.current_tick < *end_tick);
+        let value = if start_tick != end_tick {
+            let t = status.interpolation_fraction().unwrap();
+            component_registry.interpolate(start_value, end_value, t)
+        } else {
+            start_value.clone()
+        };
+        *component = blend(&component_registry, &mut status, value, blend_delta);
+    }
+}
+
+/// Dead-reckon the component on the Interpolated entity from the velocity `V` of the confirmed entity,
+/// when we don't have a server update to interpolate towards
+pub(crate) fn extrapolate<C: Component + Clone, V: Component>(
+    component_registry: Res<ComponentRegistry>,
+    config: Res<ClientConfig>,
+    time: Res<Time>,
+    confirmed: Query<&V>,
+    mut query: Query<(&Interpolated, &mut C, &mut InterpolateStatus<C>)>,
+) {
+    // maximum number of ticks that we can extrapolate past the last server update
+    let max_ticks = config
+        .interpolation
+        .extrapolation
+        .max_duration
+        .as_secs_f32()
+        / config.shared.tick.tick_duration.as_secs_f32();
+    let blend_delta = blend_delta(&config, &time);
+    for (interpolated, mut component, mut status) in query.iter_mut() {
+        let (Some((start_tick, start_value)), None) = (&status.start, &status.end) else {
+            continue;
+        };
+        if status.current_tick < *start_tick {
+            continue;
         }
+        let Ok(velocity) = confirmed.get(interpolated.confirmed_entity) else {
+            continue;
+        };
+        let elapsed_ticks =
+            ((status.current_tick - *start_tick) as f32 + status.current_overstep).min(max_ticks);
+        let elapsed = elapsed_ticks * config.shared.tick.tick_duration.as_secs_f32();
+        debug!(?start_tick, interpolate_tick=?status.current_tick, ?elapsed, "doing extrapolation!");
+        let value = component_registry.extrapolate(start_value, velocity, elapsed);
+        *component = blend(&component_registry, &mut status, value, blend_delta);
+    }
+}
+
+/// Progress of the blend from the extrapolated value during this frame
+fn blend_delta(config: &ClientConfig, time: &Time) -> f32 {
+    time.delta_seconds()
+        / config
+            .interpolation
+            .extrapolation
+            .blend_duration
+            .as_secs_f32()
+}
+
+/// Smoothly blend from the previously extrapolated value to `value`
+fn blend<C: Component + Clone>(
+    component_registry: &ComponentRegistry,
+    status: &mut InterpolateStatus<C>,
+    value: C,
+    blend_delta: f32,
+) -> C {
+    let Some((blend_from, progress)) = status.blend.take() else {
+        return value;
+    };
+    let progress = progress + blend_delta;
+    if progress < 1.0 {
+        let blended = component_registry.interpolate(&blend_from, &value, progress);
+        status.blend = Some((blend_from, progress));
+        blended
+    } else {
+        value
     }
 }
 
@@ -527,3 +637,88 @@ pub(crate) fn interpolate<C: Component + Clone>(
 //         Ok(())
 //     }
 // }
+
+#[cfg(test)]
+mod extrapolation_tests {
+    use super::*;
+    use crate::prelude::AppComponentExt;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+
+    fn status(
+        start: (u16, f32),
+        end: Option<(u16, f32)>,
+        current_tick: u16,
+    ) -> InterpolateStatus<ComponentSyncModeFull> {
+        InterpolateStatus {
+            start: Some((Tick(start.0), ComponentSyncModeFull(start.1))),
+            end: end.map(|(tick, value)| (Tick(tick), ComponentSyncModeFull(value))),
+            current_tick: Tick(current_tick),
+            current_overstep: 0.0,
+            blend: None,
+        }
+    }
+
+    fn value(stepper: &BevyStepper, entity: Entity) -> f32 {
+        stepper
+            .client_app
+            .world()
+            .get::<ComponentSyncModeFull>(entity)
+            .unwrap()
+            .0
+    }
+
+    /// Check that components are extrapolated from the replicated velocity past the last server update
+    /// when extrapolation is enabled
+    #[test]
+    fn test_extrapolation() {
+        let mut stepper = BevyStepper::default();
+        // use ComponentSyncModeFull2 as the velocity of ComponentSyncModeFull
+        stepper
+            .client_app
+            .add_extrapolation::<ComponentSyncModeFull, ComponentSyncModeFull2>(
+                |position, velocity, elapsed| {
+                    ComponentSyncModeFull(position.0 + velocity.0 * elapsed)
+                },
+            );
+
+        // the confirmed velocity is 100.0 per second: extrapolate 5 ticks (50ms) past the last update
+        let confirmed_entity = stepper
+            .client_app
+            .world_mut()
+            .spawn(ComponentSyncModeFull2(100.0))
+            .id();
+        let entity = stepper
+            .client_app
+            .world_mut()
+            .spawn((
+                Interpolated { confirmed_entity },
+                ComponentSyncModeFull(10.0),
+                status((10, 10.0), None, 15),
+            ))
+            .id();
+        stepper.frame_step();
+        assert!((value(&stepper, entity) - 15.0).abs() < 1e-3);
+
+        // the extrapolation is capped at max_duration (200ms = 20 ticks)
+        *stepper
+            .client_app
+            .world_mut()
+            .get_mut::<InterpolateStatus<ComponentSyncModeFull>>(entity)
+            .unwrap() = status((10, 10.0), None, 100);
+        stepper.frame_step();
+        assert!((value(&stepper, entity) - 30.0).abs() < 1e-3);
+
+        // a new update arrived: the extrapolated value is blended towards the interpolated value
+        let mut new_status = status((10, 10.0), Some((20, 20.0)), 15);
+        new_status.blend = Some((ComponentSyncModeFull(30.0), 0.0));
+        *stepper
+            .client_app
+            .world_mut()
+            .get_mut::<InterpolateStatus<ComponentSyncModeFull>>(entity)
+            .unwrap() = new_status;
+        stepper.frame_step();
+        let value = value(&stepper, entity);
+        assert!(value > 15.0 && value < 30.0);
+    }
+}
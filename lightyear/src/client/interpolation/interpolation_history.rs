@@ -1,17 +1,21 @@
 use std::ops::Deref;
 
 use bevy::prelude::{
-    Commands, Component, DetectChanges, Entity, Has, Query, Ref, Res, With, Without,
+    Commands, Component, DetectChanges, DetectChangesMut, Entity, Has, Query, Ref, Res, ResMut,
+    With, Without,
 };
 use tracing::{debug, trace};
 
 use crate::client::components::Confirmed;
 use crate::client::components::{ComponentSyncMode, SyncComponent};
+use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::interpolate::InterpolateStatus;
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::Interpolated;
 use crate::prelude::{ComponentRegistry, HasAuthority, TickManager};
+use crate::protocol::component::ComponentKind;
+use crate::shared::memory::{evict_ready_buffer, MemoryStats, MemorySubsystem, MemoryTally};
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
 
@@ -83,7 +87,10 @@ pub(crate) fn add_component_history<C: SyncComponent>(
     tick_manager: Res<TickManager>,
     mut commands: Commands,
     connection: Res<ConnectionManager>,
-    interpolated_entities: Query<Entity, (Without<ConfirmedHistory<C>>, With<Interpolated>)>,
+    mut interpolated_entities: Query<
+        (Entity, Ref<Interpolated>, Option<&mut ConfirmedHistory<C>>),
+        Without<Confirmed>,
+    >,
     confirmed_entities: Query<(&Confirmed, Ref<C>)>,
 ) {
     let current_tick = connection
@@ -94,8 +101,22 @@ pub(crate) fn add_component_history<C: SyncComponent>(
         .interpolation_overstep(tick_manager.as_ref());
     for (confirmed_entity, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed_entity.interpolated {
-            if let Ok(interpolated_entity) = interpolated_entities.get(p) {
-                if confirmed_component.is_added() {
+            if let Ok((interpolated_entity, interpolated, existing_history)) =
+                interpolated_entities.get_mut(p)
+            {
+                // the interpolated entity can also be spawned for an existing confirmed entity (for example
+                // when the client lost the ownership of the entity)
+                if confirmed_component.is_added() || interpolated.is_added() {
+                    // the interpolated entity was handed over from a replaced entity (see `ReplacesEntity`):
+                    // keep interpolating from the existing history
+                    if let Some(mut history) = existing_history {
+                        let mut new_component = confirmed_component.deref().clone();
+                        let _ =
+                            manager.map_entities(&mut new_component, component_registry.as_ref());
+                        trace!(?interpolated_entity, tick=?confirmed_entity.tick, "handover: add confirmed value to existing interpolation history");
+                        history.buffer.push(confirmed_entity.tick, new_component);
+                        continue;
+                    }
                     // safety: we know the entity exists
                     let mut interpolated_entity_mut =
                         commands.get_entity(interpolated_entity).unwrap();
@@ -118,6 +139,7 @@ pub(crate) fn add_component_history<C: SyncComponent>(
                                     end: None,
                                     current_tick,
                                     current_overstep,
+                                    blend: None,
                                 },
                             ));
                         }
@@ -133,6 +155,28 @@ pub(crate) fn add_component_history<C: SyncComponent>(
     }
 }
 
+/// Evict the buffered server updates of the confirmed histories that are over the memory limit
+pub(crate) fn bound_confirmed_history<C: SyncComponent>(
+    config: Res<ClientConfig>,
+    mut stats: ResMut<MemoryStats>,
+    mut query: Query<&mut ConfirmedHistory<C>>,
+) {
+    let limit = &config.shared.memory.interpolation_buffers;
+    let mut tally = MemoryTally::default();
+    for mut history in query.iter_mut() {
+        evict_ready_buffer(
+            &mut history.bypass_change_detection().buffer,
+            limit,
+            &mut tally,
+        );
+    }
+    stats.record(
+        MemorySubsystem::InterpolationBuffers,
+        Some(ComponentKind::of::<C>()),
+        tally,
+    );
+}
+
 /// When we receive a server update for an interpolated component, we need to store it in the confirmed history,
 pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
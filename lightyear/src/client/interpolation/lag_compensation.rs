@@ -0,0 +1,148 @@
+//! Client-side helpers for lag compensation.
+//!
+//! Interpolated entities are displayed at the interpolation tick, which lags behind the server.
+//! When the client shoots at an interpolated entity, the server needs to rewind the target to the state
+//! that the client was seeing (see [`interpolated_tick`](crate::server::connection::ConnectionManager::interpolated_tick)).
+//!
+//! The [`LagCompensation`] system param lets the client compute the same result locally, for example to display
+//! hit markers before the server confirms the hit. The client can also send the returned tick to the
+//! server along with its action, so that the server rewinds to exactly the same tick.
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Component, Entity, Query, Res, With};
+use bevy::utils::Duration;
+
+use crate::client::connection::ConnectionManager;
+use crate::client::interpolation::Interpolated;
+use crate::prelude::{Tick, TickManager};
+
+/// A target hit by a lag-compensated ray cast
+#[derive(Debug, Clone, PartialEq)]
+pub struct LagCompensatedHit<C> {
+    /// Server tick of the state of the target that was hit. This is the tick that the server should
+    /// rewind to.
+    pub tick: Tick,
+    /// Interpolated entity that was hit
+    pub entity: Entity,
+    /// Distance along the ray at which the target was hit
+    pub distance: f32,
+    /// State of the target when it was hit
+    pub target: C,
+}
+
+/// [`SystemParam`] to query the interpolated entities at the state that the server's lag compensation will use
+#[derive(SystemParam)]
+pub struct LagCompensation<'w, 's, C: Component> {
+    connection: Res<'w, ConnectionManager>,
+    tick_manager: Res<'w, TickManager>,
+    targets: Query<'w, 's, (Entity, &'static C), With<Interpolated>>,
+}
+
+impl<'w, 's, C: Component> LagCompensation<'w, 's, C> {
+    /// Server tick of the state of the interpolated entities that are currently displayed
+    ///
+    /// This uses the same formula as the server (see [`TickManager::interpolation_tick`]), applied to the
+    /// client's estimate of the current server tick and to the [`delay`](Self::delay) reported to the server.
+    pub fn tick(&self) -> Tick {
+        let server_tick = self
+            .connection
+            .sync_manager
+            .server_time_estimate()
+            .to_tick(self.tick_manager.config.tick_duration);
+        self.tick_manager
+            .interpolation_tick(server_tick, self.delay())
+    }
+
+    /// Fraction of a tick elapsed between [`tick`](Self::tick) and the next tick
+    pub fn overstep(&self) -> f32 {
+        self.tick_manager.interpolation_overstep(self.delay())
+    }
+
+    /// Delay between the server's current time and the time of the interpolated entities.
+    ///
+    /// This is the delay that is reported to the server.
+    pub fn delay(&self) -> Duration {
+        self.connection.sync_manager.interpolation_delay()
+    }
+
+    /// Iterate through the interpolated targets, in the state that the server's lag compensation will use
+    pub fn targets(&self) -> impl Iterator<Item = (Entity, &C)> {
+        self.targets.iter()
+    }
+
+    /// Cast a ray against the interpolated targets and return the closest hit.
+    ///
+    /// `intersect` returns the distance along the ray at which the ray intersects a target with the given
+    /// state, or `None` if there is no intersection. This lets you use any collision library.
+    pub fn cast_ray<R>(
+        &self,
+        ray: &R,
+        mut intersect: impl FnMut(&R, &C) -> Option<f32>,
+    ) -> Option<LagCompensatedHit<C>>
+    where
+        C: Clone,
+    {
+        let (entity, target, distance) = self
+            .targets
+            .iter()
+            .filter_map(|(entity, target)| {
+                intersect(ray, target).map(|distance| (entity, target, distance))
+            })
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))?;
+        Some(LagCompensatedHit {
+            tick: self.tick(),
+            entity,
+            distance,
+            target: target.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_cast_ray() {
+        let mut stepper = BevyStepper::default();
+        let confirmed_entity = stepper.client_app.world_mut().spawn_empty().id();
+        let near = stepper
+            .client_app
+            .world_mut()
+            .spawn((
+                Interpolated { confirmed_entity },
+                ComponentSyncModeFull(1.0),
+            ))
+            .id();
+        stepper.client_app.world_mut().spawn((
+            Interpolated { confirmed_entity },
+            ComponentSyncModeFull(3.0),
+        ));
+        // not interpolated: ignored
+        stepper
+            .client_app
+            .world_mut()
+            .spawn(ComponentSyncModeFull(0.5));
+
+        let (hit, tick) = stepper.client_app.world_mut().run_system_once(
+            |lag_compensation: LagCompensation<ComponentSyncModeFull>| {
+                // the ray starts at 0.0 and hits every target
+                let hit =
+                    lag_compensation.cast_ray(&0.0, |origin: &f32, target| Some(target.0 - origin));
+                (hit, lag_compensation.tick())
+            },
+        );
+        assert_eq!(
+            hit,
+            Some(LagCompensatedHit {
+                tick,
+                entity: near,
+                distance: 1.0,
+                target: ComponentSyncModeFull(1.0),
+            })
+        );
+    }
+}
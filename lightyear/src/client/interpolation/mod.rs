@@ -5,14 +5,18 @@ use bevy::prelude::{Component, Entity, Reflect};
 
 pub use interpolate::InterpolateStatus;
 pub use interpolation_history::ConfirmedHistory;
-pub use plugin::{add_interpolation_systems, add_prepare_interpolation_systems};
+pub use plugin::{
+    add_extrapolation_systems, add_interpolation_systems, add_prepare_interpolation_systems,
+};
 pub use visual_interpolation::{VisualInterpolateStatus, VisualInterpolationPlugin};
 
 use crate::client::components::LerpFn;
 
 mod despawn;
+pub mod diagnostics;
 pub mod interpolate;
 pub mod interpolation_history;
+pub mod lag_compensation;
 pub mod plugin;
 mod resource;
 mod spawn;
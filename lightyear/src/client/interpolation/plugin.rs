@@ -2,21 +2,32 @@ use bevy::prelude::*;
 use bevy::utils::Duration;
 
 use crate::client::components::{ComponentSyncMode, SyncComponent};
-use crate::client::interpolation::despawn::{despawn_interpolated, removed_components};
+use crate::client::interpolation::despawn::{
+    despawn_interpolated, despawn_interpolated_on_prediction, removed_components,
+};
+use crate::client::interpolation::diagnostics::{
+    update_interpolation_buffer_stats, InterpolationBufferStats,
+};
 use crate::client::interpolation::interpolate::{
-    insert_interpolated_component, interpolate, update_interpolate_status,
+    extrapolate, insert_interpolated_component, interpolate, update_interpolate_status,
 };
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::spawn::spawn_interpolated_entity;
 use crate::client::interpolation::Interpolated;
 use crate::client::run_conditions::is_synced;
+use crate::client::sync::SyncSet;
 use crate::prelude::is_host_server;
 
 use super::interpolation_history::{
     add_component_history, apply_confirmed_update_mode_full, apply_confirmed_update_mode_simple,
+    bound_confirmed_history,
 };
 
 // TODO: maybe this is not an enum and user can specify multiple values, and we use the max delay between all of them?
+/// How far behind the latest server update the interpolated entities are rendered.
+///
+/// It can be modified at runtime through the [`ClientConfig`](crate::prelude::client::ClientConfig) resource;
+/// the interpolation timeline then converges progressively to the new delay.
 #[derive(Clone, Copy, Reflect)]
 pub struct InterpolationDelay {
     /// The minimum delay that we will apply for interpolation
@@ -58,10 +69,48 @@ impl InterpolationDelay {
     }
 }
 
+/// Config to specify how components that have extrapolation enabled are extrapolated
+/// when the interpolation runs out of server updates.
+///
+/// Extrapolation is enabled per component with
+/// [`ComponentRegistration::add_extrapolation`](crate::protocol::component::ComponentRegistration::add_extrapolation).
+#[derive(Clone, Copy, Reflect)]
+pub struct ExtrapolationConfig {
+    /// Maximum duration for which we keep extrapolating past the last server update.
+    /// After that, the component stays at its last extrapolated value until a new update arrives.
+    pub max_duration: Duration,
+    /// Duration over which the extrapolated value is blended back into the interpolated value
+    /// when a new server update arrives
+    pub blend_duration: Duration,
+}
+
+impl Default for ExtrapolationConfig {
+    fn default() -> Self {
+        Self {
+            max_duration: Duration::from_millis(200),
+            blend_duration: Duration::from_millis(100),
+        }
+    }
+}
+
+impl ExtrapolationConfig {
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+
+    pub fn with_blend_duration(mut self, blend_duration: Duration) -> Self {
+        self.blend_duration = blend_duration;
+        self
+    }
+}
+
 /// Config to specify how the snapshot interpolation should behave
 #[derive(Clone, Copy, Reflect)]
 pub struct InterpolationConfig {
     pub delay: InterpolationDelay,
+    /// How to extrapolate the components that have extrapolation enabled
+    pub extrapolation: ExtrapolationConfig,
     // How long are we keeping the history of the confirmed entities so we can interpolate between them?
     // pub(crate) interpolation_buffer_size: Duration,
 }
@@ -71,6 +120,7 @@ impl Default for InterpolationConfig {
     fn default() -> Self {
         Self {
             delay: InterpolationDelay::default(),
+            extrapolation: ExtrapolationConfig::default(),
             // interpolation_buffer_size: Duration::from_millis(100),
         }
     }
@@ -81,6 +131,11 @@ impl InterpolationConfig {
         self.delay = delay;
         self
     }
+
+    pub fn with_extrapolation(mut self, extrapolation: ExtrapolationConfig) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
 }
 
 #[derive(Default)]
@@ -138,6 +193,7 @@ pub fn add_prepare_interpolation_systems<C: SyncComponent>(
                 Update,
                 (
                     apply_confirmed_update_mode_full::<C>,
+                    bound_confirmed_history::<C>,
                     update_interpolate_status::<C>.run_if(is_synced),
                     // TODO: that means we could insert the component twice, here and then in interpolate...
                     //  need to optimize this
@@ -167,6 +223,17 @@ pub fn add_interpolation_systems<C: SyncComponent>(app: &mut App) {
     );
 }
 
+/// Add the system that extrapolates `C` from the velocity `V` of the confirmed entity when the
+/// interpolation runs out of server updates
+pub fn add_extrapolation_systems<C: SyncComponent, V: Component>(app: &mut App) {
+    app.add_systems(
+        Update,
+        extrapolate::<C, V>
+            .after(interpolate::<C>)
+            .in_set(InterpolationSet::Interpolate),
+    );
+}
+
 impl Plugin for InterpolationPlugin {
     fn build(&self, app: &mut App) {
         let should_run_interpolation = not(is_host_server).and_then(is_synced);
@@ -174,10 +241,12 @@ impl Plugin for InterpolationPlugin {
         // REFLECT
         app.register_type::<InterpolationConfig>()
             .register_type::<InterpolationDelay>()
+            .register_type::<ExtrapolationConfig>()
             .register_type::<Interpolated>();
 
         // RESOURCES
         app.init_resource::<InterpolationManager>();
+        app.init_resource::<InterpolationBufferStats>();
         // SETS
         app.configure_sets(
             Update,
@@ -199,6 +268,13 @@ impl Plugin for InterpolationPlugin {
             Update,
             spawn_interpolated_entity.in_set(InterpolationSet::SpawnInterpolation),
         );
+        app.add_systems(
+            PostUpdate,
+            update_interpolation_buffer_stats
+                .after(SyncSet)
+                .run_if(not(is_host_server).and_then(is_synced)),
+        );
         app.observe(despawn_interpolated);
+        app.observe(despawn_interpolated_on_prediction);
     }
 }
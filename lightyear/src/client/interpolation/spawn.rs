@@ -1,4 +1,4 @@
-use bevy::prelude::{Added, Commands, Entity, Query, Res, ResMut};
+use bevy::prelude::{Added, Commands, Entity, Query, Res, ResMut, World};
 use tracing::trace;
 
 use crate::client::components::Confirmed;
@@ -6,18 +6,52 @@ use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::Interpolated;
-use crate::shared::replication::components::ShouldBeInterpolated;
+use crate::shared::replication::components::{ReplacesEntity, ShouldBeInterpolated};
 
 /// Spawn an interpolated entity for each confirmed entity that has the `ShouldBeInterpolated` component added
+///
+/// If the confirmed entity replaces another entity (see [`ReplacesEntity`]), the interpolated entity of the replaced
+/// entity is handed over to the new confirmed entity instead of spawning a new one.
 pub(crate) fn spawn_interpolated_entity(
     config: Res<ClientConfig>,
     connection: Res<ConnectionManager>,
     mut manager: ResMut<InterpolationManager>,
     mut commands: Commands,
-    mut confirmed_entities: Query<(Entity, Option<&mut Confirmed>), Added<ShouldBeInterpolated>>,
+    mut confirmed_entities: Query<
+        (Entity, Option<&mut Confirmed>, Option<&ReplacesEntity>),
+        Added<ShouldBeInterpolated>,
+    >,
 ) {
-    for (confirmed_entity, confirmed) in confirmed_entities.iter_mut() {
-        let interpolated = commands.spawn(Interpolated { confirmed_entity }).id();
+    for (confirmed_entity, confirmed, replaces) in confirmed_entities.iter_mut() {
+        let handed_over = replaces.and_then(|replaces| {
+            manager
+                .interpolated_entity_map
+                .get_mut()
+                .confirmed_to_interpolated
+                .remove(&replaces.0)
+                .map(|interpolated| (replaces.0, interpolated))
+        });
+        let interpolated = if let Some((replaced_entity, interpolated)) = handed_over {
+            trace!(
+                ?replaced_entity,
+                ?interpolated,
+                "Hand over interpolated entity to confirmed: {:?}",
+                confirmed_entity
+            );
+            commands
+                .entity(interpolated)
+                .insert(Interpolated { confirmed_entity });
+            // detach the interpolated entity from the replaced entity, so that despawning the replaced
+            // entity doesn't despawn the interpolated entity or remove its components
+            commands.add(move |world: &mut World| {
+                if let Some(mut confirmed) = world.get_mut::<Confirmed>(replaced_entity) {
+                    confirmed.interpolated = None;
+                }
+            });
+            interpolated
+        } else {
+            commands.spawn(Interpolated { confirmed_entity }).id()
+        };
 
         // update the entity mapping
         manager
@@ -55,3 +89,98 @@ pub(crate) fn spawn_interpolated_entity(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::default;
+
+    use super::*;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::NetworkTarget;
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    fn interpolated_entity(stepper: &BevyStepper, server_entity: Entity) -> Option<Entity> {
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        stepper
+            .client_app
+            .world()
+            .get::<Confirmed>(client_entity)
+            .expect("Confirmed component missing")
+            .interpolated
+    }
+
+    #[test]
+    fn test_replaces_entity_handover() {
+        let mut stepper = BevyStepper::default();
+        let replicate = Replicate {
+            sync: SyncTarget {
+                interpolation: NetworkTarget::All,
+                ..default()
+            },
+            ..default()
+        };
+        let old_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((replicate.clone(), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let interpolated = interpolated_entity(&stepper, old_entity).unwrap();
+
+        // spawn the replacement entity
+        let new_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                replicate,
+                ComponentSyncModeFull(2.0),
+                ReplacesEntity(old_entity),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the interpolated entity was handed over to the new confirmed entity
+        let new_client_entity = stepper
+            .client_app
+            .world()
+            .resource::<ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(new_entity)
+            .unwrap();
+        assert_eq!(
+            interpolated_entity(&stepper, new_entity),
+            Some(interpolated)
+        );
+        assert_eq!(interpolated_entity(&stepper, old_entity), None);
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<Interpolated>(interpolated)
+                .unwrap()
+                .confirmed_entity,
+            new_client_entity
+        );
+
+        // despawning the replaced entity keeps the interpolated entity alive
+        stepper.server_app.world_mut().despawn(old_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get_entity(interpolated)
+            .is_some());
+    }
+}
@@ -13,6 +13,8 @@ use crate::transport::middleware::compression::zstd::decompression::ZstdDecompre
 use crate::transport::middleware::conditioner::LinkConditioner;
 use crate::transport::middleware::PacketReceiverWrapper;
 #[cfg(not(target_family = "wasm"))]
+use crate::transport::tcp::TcpClientSocketBuilder;
+#[cfg(not(target_family = "wasm"))]
 use crate::transport::udp::UdpSocketBuilder;
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::WebSocketClientSocketBuilder;
@@ -41,7 +43,18 @@ pub enum ClientTransport {
     },
     /// Use [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket) as a transport
     #[cfg(feature = "websocket")]
-    WebSocketClient { server_addr: SocketAddr },
+    WebSocketClient {
+        server_addr: SocketAddr,
+        /// If provided, the connection uses TLS (`wss://`) and the server certificate is validated
+        /// against this domain name. This is required to connect from a page served over https.
+        tls_domain: Option<String>,
+    },
+    /// Use a TCP stream, with each packet prefixed by its length.
+    ///
+    /// This should only be used as the last fallback (see [`ClientConfig::transport_fallbacks`](crate::client::config::ClientConfig::transport_fallbacks))
+    /// for networks that block UDP: the latency is worse because of head-of-line blocking.
+    #[cfg(not(target_family = "wasm"))]
+    TcpClient { server_addr: SocketAddr },
     /// Use a crossbeam_channel as a transport. This is useful for testing.
     /// This is mostly for clients.
     LocalChannel {
@@ -78,10 +91,16 @@ impl ClientTransport {
                 certificate_digest,
             }),
             #[cfg(feature = "websocket")]
-            ClientTransport::WebSocketClient { server_addr } => {
-                ClientTransportBuilderEnum::WebSocketClient(WebSocketClientSocketBuilder {
-                    server_addr,
-                })
+            ClientTransport::WebSocketClient {
+                server_addr,
+                tls_domain,
+            } => ClientTransportBuilderEnum::WebSocketClient(WebSocketClientSocketBuilder {
+                server_addr,
+                tls_domain,
+            }),
+            #[cfg(not(target_family = "wasm"))]
+            ClientTransport::TcpClient { server_addr } => {
+                ClientTransportBuilderEnum::TcpClient(TcpClientSocketBuilder { server_addr })
             }
             ClientTransport::LocalChannel { recv, send } => {
                 ClientTransportBuilderEnum::LocalChannel(LocalChannelBuilder { recv, send })
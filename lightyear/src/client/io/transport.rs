@@ -4,6 +4,8 @@ use crate::transport::error::Error as TransportError;
 use crate::transport::io::IoState;
 use crate::transport::local::{LocalChannel, LocalChannelBuilder};
 #[cfg(not(target_family = "wasm"))]
+use crate::transport::tcp::{TcpClientSocket, TcpClientSocketBuilder};
+#[cfg(not(target_family = "wasm"))]
 use crate::transport::udp::{UdpSocket, UdpSocketBuilder};
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::{WebSocketClientSocket, WebSocketClientSocketBuilder};
@@ -41,6 +43,8 @@ pub(crate) enum ClientTransportBuilderEnum {
     WebTransportClient(WebTransportClientSocketBuilder),
     #[cfg(feature = "websocket")]
     WebSocketClient(WebSocketClientSocketBuilder),
+    #[cfg(not(target_family = "wasm"))]
+    TcpClient(TcpClientSocketBuilder),
     LocalChannel(LocalChannelBuilder),
     Dummy(DummyIo),
 }
@@ -54,6 +58,8 @@ pub(crate) enum ClientTransportEnum {
     WebTransportClient(WebTransportClientSocket),
     #[cfg(feature = "websocket")]
     WebSocketClient(WebSocketClientSocket),
+    #[cfg(not(target_family = "wasm"))]
+    TcpClient(TcpClientSocket),
     LocalChannel(LocalChannel),
     Dummy(DummyIo),
 }
@@ -7,7 +7,8 @@ use tracing::error;
 
 use crate::client::connection::ConnectionManager;
 use crate::client::events::MessageEvent;
-use crate::prelude::{client::is_connected, Message};
+use crate::connection::client::KickReason;
+use crate::prelude::{client::is_connected, Message, TickManager};
 use crate::protocol::message::{MessageKind, MessageRegistry};
 use crate::serialize::reader::Reader;
 use crate::serialize::{SerializationError, ToBytes};
@@ -48,6 +49,7 @@ impl ToBytes for ClientMessage {
 /// Read the message received from the server and emit the MessageEvent event
 fn read_message<M: Message>(
     message_registry: Res<MessageRegistry>,
+    tick_manager: Res<TickManager>,
     mut connection: ResMut<ConnectionManager>,
     mut event: EventWriter<MessageEvent<M>>,
 ) {
@@ -59,21 +61,48 @@ fn read_message<M: Message>(
         );
         return;
     };
-    if let Some(message_list) = connection.received_messages.remove(&net) {
-        for message in message_list {
-            let mut reader = Reader::from(message);
-            // we have to re-decode the net id
-            let Ok(message) = message_registry.deserialize::<M>(
-                &mut reader,
-                &mut connection
-                    .replication_receiver
-                    .remote_entity_map
-                    .remote_to_local,
-            ) else {
-                error!("Could not deserialize message");
-                continue;
-            };
-            event.send(MessageEvent::new(message, ()));
+    let mut message_list = connection
+        .received_messages
+        .remove(&net)
+        .unwrap_or_default();
+    // messages that were sent for a specific tick are only emitted once we reach that tick
+    message_list.extend(
+        connection
+            .scheduled_messages
+            .drain(net, tick_manager.tick()),
+    );
+    for message in message_list {
+        let mut reader = Reader::from(message);
+        // we have to re-decode the net id
+        let Ok(message) = message_registry.deserialize::<M>(
+            &mut reader,
+            &mut connection
+                .replication_receiver
+                .remote_entity_map
+                .remote_to_local,
+        ) else {
+            error!("Could not deserialize message");
+            continue;
+        };
+        event.send(MessageEvent::new(message, ()));
+    }
+    // the server sent this message as the reason for disconnecting us
+    if connection
+        .received_kick_reason
+        .as_ref()
+        .is_some_and(|(net_id, _)| *net_id == net)
+    {
+        let (_, message) = connection.received_kick_reason.take().unwrap();
+        let mut reader = Reader::from(message);
+        match message_registry.deserialize::<M>(
+            &mut reader,
+            &mut connection
+                .replication_receiver
+                .remote_entity_map
+                .remote_to_local,
+        ) {
+            Ok(reason) => connection.kick_reason = Some(KickReason(Box::new(reason))),
+            Err(e) => error!("Could not deserialize the disconnection reason: {:?}", e),
         }
     }
 }
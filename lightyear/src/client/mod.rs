@@ -7,6 +7,8 @@ pub mod config;
 
 pub mod connection;
 
+pub mod connection_quality;
+
 pub mod events;
 
 pub mod input;
@@ -17,14 +19,22 @@ pub mod plugin;
 
 pub mod prediction;
 
+pub mod remote_entity;
+
+pub mod replay;
+
 pub mod sync;
 
+pub mod timelines;
+
 pub mod diagnostics;
 mod easings;
 
 pub(crate) mod io;
 pub(crate) mod message;
 pub mod networking;
+#[cfg(feature = "overlay")]
+pub mod overlay;
 pub mod replication;
 
 pub mod error;
@@ -5,26 +5,33 @@ use async_channel::TryRecvError;
 use bevy::ecs::system::{RunSystemOnce, SystemChangeTick};
 use bevy::prelude::ResMut;
 use bevy::prelude::*;
+use bevy::utils::Duration;
 use tracing::{error, trace};
 
+use crate::channel::builder::SessionChannel;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
-use crate::client::events::{ConnectEvent, DisconnectEvent};
+use crate::client::events::{ConnectEvent, DisconnectEvent, NetworkErrorEvent};
 use crate::client::interpolation::Interpolated;
 use crate::client::io::ClientIoEvent;
 use crate::client::networking::utils::AppStateExt;
 use crate::client::prediction::Predicted;
+use crate::client::replay::ReplayRecorder;
 use crate::client::replication::send::ReplicateToServer;
 use crate::client::run_conditions::is_disconnected;
 use crate::client::sync::SyncSet;
 use crate::connection::client::{ClientConnection, ConnectionState, DisconnectReason, NetClient};
 use crate::connection::server::IoConfig;
 use crate::prelude::{
-    is_host_server, ChannelRegistry, MainSet, MessageRegistry, TickManager, TimeManager,
+    is_host_server, ChannelKind, ChannelRegistry, MainSet, MessageRegistry, TickManager,
+    TimeManager,
 };
 use crate::protocol::component::ComponentRegistry;
+use crate::serialize::writer::Writer;
+use crate::serialize::ToBytes;
 use crate::server::clients::ControlledEntities;
 use crate::shared::config::Mode;
+use crate::shared::memory::enforce_client_memory_limits;
 use crate::shared::replication::components::Replicated;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 use crate::transport::io::IoState;
@@ -42,6 +49,7 @@ impl Plugin for ClientNetworkingPlugin {
             .init_state_without_entering(NetworkingState::Disconnected)
             // RESOURCE
             .init_resource::<HostServerMetadata>()
+            .init_resource::<TransportFallback>()
             // SYSTEM SETS
             .configure_sets(
                 PreUpdate,
@@ -92,6 +100,9 @@ impl Plugin for ClientNetworkingPlugin {
                         .in_set(InternalMainSet::<ClientMarker>::Send),
                     // TODO: update virtual time with Time<Real> so we have more accurate time at Send time.
                     sync_update.in_set(SyncSet),
+                    enforce_client_memory_limits
+                        .after(InternalMainSet::<ClientMarker>::Send)
+                        .run_if(not(is_host_server.or_else(is_disconnected))),
                 ),
             );
 
@@ -140,8 +151,11 @@ pub(crate) fn receive_packets(
     component_registry: Res<ComponentRegistry>,
     message_registry: Res<MessageRegistry>,
     system_change_tick: SystemChangeTick,
+    mut network_errors: EventWriter<NetworkErrorEvent>,
 ) {
     trace!("Receive server packets");
+    #[cfg(feature = "alloc_audit")]
+    let _audit = crate::utils::alloc_audit::enter(crate::utils::alloc_audit::HotPath::Receive);
     let delta = virtual_time.delta();
     // UPDATE: update client state, send keep-alives, receive packets from io, update connection sync state
     time_manager.update(delta);
@@ -150,6 +164,9 @@ pub(crate) fn receive_packets(
     if !matches!(netclient.state(), ConnectionState::Disconnected { .. }) {
         let _ = netclient.try_update(delta.as_secs_f64()).map_err(|e| {
             error!("Error updating netcode: {}", e);
+            network_errors.send(NetworkErrorEvent {
+                error: e.to_string(),
+            });
         });
     }
 
@@ -176,15 +193,23 @@ pub(crate) fn receive_packets(
     }
 
     // RECV PACKETS: buffer packets into message managers
+    // (a malformed packet is dropped instead of crashing the app)
     while let Some(packet) = netclient.recv() {
-        connection
+        let _ = connection
             .recv_packet(packet, tick_manager.as_ref(), component_registry.as_ref())
-            .unwrap();
+            .inspect_err(|e| {
+                error!("Error receiving packet: {}", e);
+                network_errors.send(NetworkErrorEvent {
+                    error: e.to_string(),
+                });
+            });
     }
 }
 
 /// Read from internal buffers and apply the changes to the world
 pub(crate) fn receive(world: &mut World) {
+    #[cfg(feature = "alloc_audit")]
+    let _audit = crate::utils::alloc_audit::enter(crate::utils::alloc_audit::HotPath::Receive);
     let unsafe_world = world.as_unsafe_world_cell();
 
     // TODO: an alternative would be to use `Commands + EntityMut` which both don't conflict with resources
@@ -194,14 +219,19 @@ pub(crate) fn receive(world: &mut World) {
         unsafe { unsafe_world.get_resource_mut::<ConnectionManager>() }.unwrap();
     let time_manager = unsafe { unsafe_world.get_resource::<TimeManager>() }.unwrap();
     let tick_manager = unsafe { unsafe_world.get_resource::<TickManager>() }.unwrap();
+    let recorder = unsafe { unsafe_world.get_resource_mut::<ReplayRecorder>() };
     // RECEIVE: read messages and parse them into events
-    let _ = connection_manager
-        .receive(
-            unsafe { unsafe_world.world_mut() },
-            time_manager,
-            tick_manager,
-        )
-        .inspect_err(|e| error!("Error receiving packets: {}", e));
+    if let Err(e) = connection_manager.receive(
+        unsafe { unsafe_world.world_mut() },
+        time_manager,
+        tick_manager,
+        recorder.map(|recorder| recorder.into_inner()),
+    ) {
+        error!("Error receiving packets: {}", e);
+        world.send_event(NetworkErrorEvent {
+            error: e.to_string(),
+        });
+    }
 }
 
 pub(crate) fn send(
@@ -210,16 +240,48 @@ pub(crate) fn send(
     tick_manager: Res<TickManager>,
     time_manager: Res<TimeManager>,
     mut connection: ResMut<ConnectionManager>,
+    mut network_errors: EventWriter<NetworkErrorEvent>,
 ) {
     trace!("Send packets to server");
+    #[cfg(feature = "alloc_audit")]
+    let _audit = crate::utils::alloc_audit::enter(crate::utils::alloc_audit::HotPath::Send);
     // SEND_PACKETS: send buffered packets to io
-    let packet_bytes = connection
-        .send_packets(time_manager.as_ref(), tick_manager.as_ref())
-        .unwrap();
+    let packet_bytes = match connection.send_packets(time_manager.as_ref(), tick_manager.as_ref()) {
+        Ok(packet_bytes) => packet_bytes,
+        Err(e) => {
+            error!("Error building packets: {}", e);
+            network_errors.send(NetworkErrorEvent {
+                error: e.to_string(),
+            });
+            return;
+        }
+    };
+    let mut send_error = None;
+    // packets containing reliable messages that could not be sent during the previous frame are retried once
+    for packet_byte in connection.message_manager.take_retry_payloads() {
+        if let Err(e) = netcode.send(packet_byte.as_slice()) {
+            send_error.get_or_insert(e);
+        }
+        connection.message_manager.recycle_payload(packet_byte);
+    }
     for packet_byte in packet_bytes {
-        let _ = netcode.send(packet_byte.as_slice()).map_err(|e| {
-            error!("Error sending packet: {}", e);
+        match netcode.send(packet_byte.as_slice()) {
+            Ok(()) => connection.message_manager.recycle_payload(packet_byte),
+            // the socket buffer might be full: keep the packet to retry it next frame instead of dropping it
+            Err(e) => {
+                send_error.get_or_insert(e);
+                connection.message_manager.retry_payload(packet_byte);
+            }
+        }
+    }
+    if let Some(e) = send_error {
+        connection.send_error_frames += 1;
+        error!("Error sending packet: {}", e);
+        network_errors.send(NetworkErrorEvent {
+            error: e.to_string(),
         });
+    } else {
+        connection.send_error_frames = 0;
     }
 
     // no need to clear the connection, because we already std::mem::take it
@@ -229,12 +291,19 @@ pub(crate) fn send(
 /// Send messages in host-server mode
 /// We cannot use the normal `send` function because there is no IO available
 pub(crate) fn send_host_server(
-    netcode: Res<ClientConnection>,
+    mut netcode: ResMut<ClientConnection>,
     mut client_manager: ResMut<ConnectionManager>,
     mut server_manager: ResMut<crate::server::connection::ConnectionManager>,
+    channel_registry: Res<ChannelRegistry>,
 ) {
+    let id = netcode.id();
     let _ = client_manager
-        .send_packets_host_server(netcode.id(), server_manager.as_mut())
+        .send_packets_host_server(
+            id,
+            server_manager.as_mut(),
+            netcode.local_conditioner_mut(),
+            channel_registry.as_ref(),
+        )
         .inspect_err(|e| {
             error!(
                 "Error sending messages from local client to server in host-server mode: {}",
@@ -348,14 +417,26 @@ struct HostServerMetadata {
     client_entity: Option<Entity>,
 }
 
+/// Keeps track of which transport of [`ClientConfig::transport_fallbacks`] is used
+/// for the current connection attempt
+#[derive(Resource, Default, Debug)]
+pub(crate) struct TransportFallback {
+    /// 0 is the transport of [`ClientConfig::net`], `i` is `transport_fallbacks[i - 1]`
+    index: usize,
+    /// True if the current connection attempt succeeded
+    connected: bool,
+}
+
 /// System that runs when we enter the Connected state
 /// Updates the ConnectEvent events
 fn on_connect(
     mut connect_event_writer: EventWriter<ConnectEvent>,
     mut commands: Commands,
     netcode: Res<ClientConnection>,
+    mut fallback: ResMut<TransportFallback>,
     mut query: Query<&mut ReplicateToServer>,
 ) {
+    fallback.connected = true;
     // Set all the ReplicateToServer ticks to changed, so that we replicate existing entities to the server
     for mut replicate in query.iter_mut() {
         // TODO: ideally set is_added instead of simply changed
@@ -399,15 +480,46 @@ fn on_disconnect(
     mut disconnect_event_writer: EventWriter<DisconnectEvent>,
     mut netclient: ResMut<ClientConnection>,
     mut commands: Commands,
+    config: Res<ClientConfig>,
+    real_time: Res<Time<Real>>,
+    mut fallback: ResMut<TransportFallback>,
+    mut next_state: ResMut<NextState<NetworkingState>>,
     received_entities: Query<Entity, Or<(With<Replicated>, With<Predicted>, With<Interpolated>)>>,
 ) {
     info!("Running OnDisconnect schedule");
-    // despawn any entities that were spawned from replication
-    received_entities.iter().for_each(|e| {
-        if let Some(commands) = commands.get_entity(e) {
-            commands.despawn_recursive();
-        }
-    });
+    // the connection attempt failed (it was not cancelled by the user): retry with the next transport
+    if !fallback.connected
+        && netclient.disconnect_reason.is_some()
+        && connection_manager.kick_reason.is_none()
+        && fallback.index < config.transport_fallbacks.len()
+    {
+        warn!(
+            reason = ?netclient.disconnect_reason,
+            "Could not connect to the server, retrying with fallback transport {}",
+            fallback.index
+        );
+        fallback.index += 1;
+        // close the io tasks of the failed connection
+        let _ = netclient.disconnect();
+        netclient.disconnect_reason = None;
+        next_state.set(NetworkingState::Connecting);
+        return;
+    }
+    *fallback = TransportFallback::default();
+    if config.session_resumption > Duration::ZERO
+        && connection_manager.kick_reason.is_none()
+        && connection_manager.session_token.is_some()
+    {
+        // keep the replicated entities and the connection state in case we can resume the session
+        connection_manager.suspended_at = Some(real_time.elapsed());
+    } else {
+        // despawn any entities that were spawned from replication
+        received_entities.iter().for_each(|e| {
+            if let Some(commands) = commands.get_entity(e) {
+                commands.despawn_recursive();
+            }
+        });
+    }
 
     // set synced to false
     connection_manager.sync_manager.synced = false;
@@ -417,7 +529,12 @@ fn on_disconnect(
 
     // no need to update the io state, because we will recreate a new `ClientConnection`
     // for the next connection attempt
-    let reason = std::mem::take(&mut netclient.disconnect_reason);
+    // if the server provided a reason when disconnecting us, use it
+    let reason = connection_manager
+        .kick_reason
+        .take()
+        .map(DisconnectReason::Kicked)
+        .or_else(|| std::mem::take(&mut netclient.disconnect_reason));
     disconnect_event_writer.send(DisconnectEvent { reason });
     // commands.trigger(DisconnectEvent { reason });
     // TODO: remove ClientConnection and ConnectionManager resources?
@@ -452,19 +569,72 @@ fn rebuild_client_connection(world: &mut World) {
     //     );
     // }
 
-    // insert a new connection manager (to reset sync, priority, message numbers, etc.)
-    let connection_manager = ConnectionManager::new(
-        world.resource::<ComponentRegistry>(),
-        world.resource::<MessageRegistry>(),
-        world.resource::<ChannelRegistry>(),
-        &client_config,
-    );
-    world.insert_resource(connection_manager);
+    let mut resume = false;
+    if let Some(suspended_at) = world
+        .get_resource::<ConnectionManager>()
+        .and_then(|manager| manager.suspended_at)
+    {
+        let elapsed = world
+            .resource::<Time<Real>>()
+            .elapsed()
+            .saturating_sub(suspended_at);
+        if elapsed < client_config.session_resumption {
+            // keep the previous connection manager so that the server can resume the session
+            // (the entity mappings, message numbers and replication state must not change)
+            info!(?elapsed, "Resuming the previous session");
+            let mut manager = world.resource_mut::<ConnectionManager>();
+            manager.suspended_at = None;
+            // the token can only be used once: if the server rejects it, the next connection starts a new session
+            if let Some(token) = manager.session_token.take() {
+                let mut writer = Writer::with_capacity(token.len());
+                resume = token.to_bytes(&mut writer).is_ok()
+                    && manager
+                        .message_manager
+                        .buffer_send(writer.split(), ChannelKind::of::<SessionChannel>())
+                        .is_ok();
+            }
+        }
+        if !resume {
+            // the session cannot be resumed: despawn the entities that we kept
+            let mut query = world.query_filtered::<Entity, Or<(
+                With<Replicated>,
+                With<Predicted>,
+                With<Interpolated>,
+            )>>();
+            let received_entities = query.iter(world).collect::<Vec<_>>();
+            for entity in received_entities {
+                if let Some(entity_mut) = world.get_entity_mut(entity) {
+                    entity_mut.despawn_recursive();
+                }
+            }
+        }
+    }
+
+    if !resume {
+        // insert a new connection manager (to reset sync, priority, message numbers, etc.)
+        let connection_manager = ConnectionManager::new(
+            world.resource::<ComponentRegistry>(),
+            world.resource::<MessageRegistry>(),
+            world.resource::<ChannelRegistry>(),
+            &client_config,
+        );
+        world.insert_resource(connection_manager);
+    }
 
     // drop the previous client connection to make sure we release any resources before creating the new one
     world.remove_resource::<ClientConnection>();
     // insert the new client connection
-    let client_connection = client_config.net.build_client();
+    let mut net_config = client_config.net;
+    let fallback = world
+        .get_resource::<TransportFallback>()
+        .map_or(0, |fallback| fallback.index);
+    if let Some(transport) = fallback
+        .checked_sub(1)
+        .and_then(|i| client_config.transport_fallbacks.get(i))
+    {
+        net_config = net_config.with_transport(transport.clone());
+    }
+    let client_connection = net_config.build_client();
     world.insert_resource(client_connection);
 }
 
@@ -0,0 +1,226 @@
+//! Lightweight text overlay that displays the network statistics of the client.
+//!
+//! The overlay shows the ping, the packet loss, the difference between the client tick and the latest
+//! tick received from the server, and the bandwidth used. It only uses `bevy_ui` text, so it does not
+//! require any additional UI library.
+//!
+//! The overlay can be shown or hidden at runtime via the [`NetworkOverlay`] resource.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::client::*;
+//!
+//! app.add_plugins(NetworkOverlayPlugin::default());
+//!
+//! fn toggle_overlay(keys: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<NetworkOverlay>) {
+//!     if keys.just_pressed(KeyCode::F3) {
+//!         overlay.enabled = !overlay.enabled;
+//!     }
+//! }
+//! ```
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_real_timer;
+use bevy::utils::{Duration, Instant};
+
+use crate::client::connection::ConnectionManager;
+use crate::connection::client::{ClientConnection, ConnectionState, NetClient};
+use crate::shared::tick_manager::TickManager;
+
+/// Plugin that displays a text overlay with the network statistics of the client
+#[derive(Clone, Debug)]
+pub struct NetworkOverlayPlugin {
+    /// How often the statistics are refreshed
+    pub update_interval: Duration,
+    /// Font size of the overlay text
+    pub font_size: f32,
+    /// If false, the overlay is hidden until [`NetworkOverlay::enabled`] is set
+    pub enabled: bool,
+}
+
+impl Default for NetworkOverlayPlugin {
+    fn default() -> Self {
+        Self {
+            update_interval: Duration::from_millis(250),
+            font_size: 14.0,
+            enabled: true,
+        }
+    }
+}
+
+/// Toggle and latest content of the network overlay
+#[derive(Resource, Debug, Clone, Default)]
+pub struct NetworkOverlay {
+    /// Whether the overlay is visible
+    pub enabled: bool,
+    /// Text currently displayed by the overlay
+    text: String,
+    /// Time and io totals (bytes sent, bytes received) at the previous update, used to compute the bandwidth
+    last_update: Option<(Instant, usize, usize)>,
+}
+
+impl NetworkOverlay {
+    /// Text currently displayed by the overlay
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Marker component for the overlay text entity
+#[derive(Component, Debug)]
+pub struct NetworkOverlayText;
+
+impl Plugin for NetworkOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NetworkOverlay {
+            enabled: self.enabled,
+            ..default()
+        });
+        spawn_overlay(app.world_mut(), self.font_size);
+        app.add_systems(
+            PostUpdate,
+            (
+                update_overlay_stats.run_if(on_real_timer(self.update_interval)),
+                update_overlay_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn spawn_overlay(world: &mut World, font_size: f32) {
+    world.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        NetworkOverlayText,
+        Name::new("NetworkOverlay"),
+    ));
+}
+
+/// Compute the text of the overlay from the latest network statistics
+fn update_overlay_stats(
+    mut overlay: ResMut<NetworkOverlay>,
+    netclient: Option<Res<ClientConnection>>,
+    connection: Option<Res<ConnectionManager>>,
+    tick_manager: Res<TickManager>,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    let (Some(netclient), Some(connection)) = (netclient, connection) else {
+        return;
+    };
+    if !matches!(netclient.state(), ConnectionState::Connected) {
+        overlay.text = "disconnected".to_string();
+        overlay.last_update = None;
+        return;
+    }
+    let now = Instant::now();
+    let rtt = connection.ping_manager.rtt();
+    let jitter = connection.ping_manager.jitter();
+    let mut text = format!(
+        "ping: {} ms (jitter: {} ms)\npacket loss: {:.1}% (incoming: {:.1}%)\ntick delta: {}",
+        rtt.as_millis(),
+        jitter.as_millis(),
+        connection.packet_loss() * 100.0,
+        connection.incoming_packet_loss() * 100.0,
+        tick_manager.tick() - connection.latest_received_server_tick(),
+    );
+    // there is no io in HostServer mode
+    if let Some(io) = netclient.io() {
+        let stats = io.stats();
+        let (send_kbps, recv_kbps) = match overlay.last_update {
+            Some((last_time, last_sent, last_received)) if now > last_time => {
+                let elapsed = (now - last_time).as_secs_f64();
+                (
+                    stats.bytes_sent.saturating_sub(last_sent) as f64 / 1000.0 / elapsed,
+                    stats.bytes_received.saturating_sub(last_received) as f64 / 1000.0 / elapsed,
+                )
+            }
+            _ => (0.0, 0.0),
+        };
+        text.push_str(&format!(
+            "\nbandwidth: up {:.1} KB/s, down {:.1} KB/s",
+            send_kbps, recv_kbps
+        ));
+        overlay.last_update = Some((now, stats.bytes_sent, stats.bytes_received));
+    }
+    overlay.text = text;
+}
+
+/// Display the latest text of the overlay, and show/hide it depending on [`NetworkOverlay::enabled`]
+fn update_overlay_text(
+    overlay: Res<NetworkOverlay>,
+    mut query: Query<(&mut Text, &mut Visibility), With<NetworkOverlayText>>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+    for (mut text, mut visibility) in query.iter_mut() {
+        *visibility = if overlay.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if let Some(section) = text.sections.first_mut() {
+            if section.value != overlay.text {
+                section.value.clone_from(&overlay.text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_network_overlay() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.add_plugins(NetworkOverlayPlugin {
+            update_interval: Duration::ZERO,
+            ..default()
+        });
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let text = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<&Text, With<NetworkOverlayText>>()
+            .single(stepper.client_app.world())
+            .sections[0]
+            .value
+            .clone();
+        assert!(text.contains("ping:"));
+        assert!(text.contains("bandwidth:"));
+
+        // hide the overlay
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<NetworkOverlay>()
+            .enabled = false;
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world_mut()
+                .query_filtered::<&Visibility, With<NetworkOverlayText>>()
+                .single(stepper.client_app.world()),
+            &Visibility::Hidden
+        );
+    }
+}
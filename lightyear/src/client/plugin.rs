@@ -11,6 +11,7 @@
 use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
+use crate::client::connection_quality::ConnectionQualityPlugin;
 use crate::client::diagnostics::ClientDiagnosticsPlugin;
 use crate::client::events::ClientEventsPlugin;
 use crate::client::interpolation::plugin::InterpolationPlugin;
@@ -30,6 +31,8 @@ use super::config::ClientConfig;
 /// - [`ClientEventsPlugin`]: Adds the client network event
 /// - [`ClientNetworkingPlugin`]: Handles the network state (connecting/disconnecting the client, sending/receiving packets)
 /// - [`ClientDiagnosticsPlugin`]: Computes diagnostics about the client connection. Can be disabled if you don't need it.
+/// - [`ConnectionQualityPlugin`]: Computes a standardized [`ConnectionQuality`](crate::client::connection_quality::ConnectionQuality)
+///   level from the connection statistics. Can be disabled if you don't need it.
 /// - [`ClientReplicationReceivePlugin`]: Handles the replication of entities and resources from server to client. This can be
 ///   disabled if you don't need server to client replication.
 /// - [`ClientReplicationSendPlugin`]: Handles the replication of entities and resources from client to server. This can be
@@ -59,6 +62,7 @@ impl PluginGroup for ClientPlugins {
             .add(ClientEventsPlugin)
             .add(ClientNetworkingPlugin)
             .add(ClientDiagnosticsPlugin::default())
+            .add(ConnectionQualityPlugin)
             .add(ClientReplicationReceivePlugin { tick_interval })
             .add(ClientReplicationSendPlugin { tick_interval })
             .add(PredictionPlugin)
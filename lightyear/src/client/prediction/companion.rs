@@ -0,0 +1,188 @@
+//! Client-local components that are added alongside replicated components.
+//!
+//! It is common to react to the insertion of a replicated component by adding client-only components
+//! (meshes, sprites, audio emitters, etc.). However a replicated component can be inserted multiple times
+//! on the same entity: for example a rollback can remove and re-insert a component on the [`Predicted`] entity.
+//! Any work done in an `OnAdd` hook or an `Added<C>` system would then be duplicated.
+//!
+//! Instead you can declare the client-local components as companions of the replicated component with
+//! [`add_companion`](AppCompanionExt::add_companion). The companion bundle is inserted exactly once on every
+//! entity that matches the filter, either when the replicated component is first inserted or when the entity
+//! becomes [`Predicted`], [`Confirmed`] or [`Interpolated`] (whichever comes last).
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::client::*;
+//!
+//! fn setup(app: &mut App) {
+//!     // only render the predicted entity
+//!     app.add_companion::<PlayerPosition, With<Predicted>, _>(|position| SpriteBundle {
+//!         transform: Transform::from_xyz(position.x, position.y, 0.0),
+//!         ..default()
+//!     });
+//! }
+//! ```
+use std::marker::PhantomData;
+
+use bevy::ecs::query::QueryFilter;
+use bevy::prelude::*;
+
+use crate::client::components::Confirmed;
+use crate::client::interpolation::Interpolated;
+use crate::client::prediction::Predicted;
+
+/// Marker component indicating that the companion bundle `B` has already been inserted on the entity
+#[derive(Component, Debug)]
+pub struct CompanionAdded<B: Bundle>(PhantomData<B>);
+
+impl<B: Bundle> Default for CompanionAdded<B> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub trait AppCompanionExt {
+    /// Insert the client-local bundle `B` (computed from the replicated component `C`) exactly once
+    /// on every entity that has the component `C` and matches the filter `F`.
+    ///
+    /// The bundle is not inserted again if `C` is removed and re-inserted (for example during a rollback),
+    /// or if the companion components are removed by the user.
+    fn add_companion<C: Component, F: QueryFilter + 'static, B: Bundle>(
+        &mut self,
+        companion: fn(&C) -> B,
+    ) -> &mut Self;
+}
+
+impl AppCompanionExt for App {
+    fn add_companion<C: Component, F: QueryFilter + 'static, B: Bundle>(
+        &mut self,
+        companion: fn(&C) -> B,
+    ) -> &mut Self {
+        // the component can be inserted before or after the entity gets its prediction/interpolation marker
+        self.observe(
+            move |trigger: Trigger<OnAdd, C>, query: Query<&C, F>, commands: Commands| {
+                insert_companion(trigger.entity(), companion, query, commands);
+            },
+        );
+        self.observe(
+            move |trigger: Trigger<OnAdd, Predicted>, query: Query<&C, F>, commands: Commands| {
+                insert_companion(trigger.entity(), companion, query, commands);
+            },
+        );
+        self.observe(
+            move |trigger: Trigger<OnAdd, Confirmed>, query: Query<&C, F>, commands: Commands| {
+                insert_companion(trigger.entity(), companion, query, commands);
+            },
+        );
+        self.observe(
+            move |trigger: Trigger<OnAdd, Interpolated>,
+                  query: Query<&C, F>,
+                  commands: Commands| {
+                insert_companion(trigger.entity(), companion, query, commands);
+            },
+        );
+        self
+    }
+}
+
+fn insert_companion<C: Component, F: QueryFilter, B: Bundle>(
+    entity: Entity,
+    companion: fn(&C) -> B,
+    query: Query<&C, F>,
+    mut commands: Commands,
+) {
+    let Ok(component) = query.get(entity) else {
+        return;
+    };
+    let bundle = companion(component);
+    // check for the marker when the command is applied, since multiple observers
+    // could be triggered for the same entity before the commands are flushed
+    commands
+        .entity(entity)
+        .add(move |mut entity: EntityWorldMut| {
+            if entity.contains::<CompanionAdded<B>>() {
+                return;
+            }
+            entity.insert((bundle, CompanionAdded::<B>::default()));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::NetworkTarget;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Companion(f32);
+
+    #[derive(Resource, Default)]
+    struct CompanionCount(usize);
+
+    #[test]
+    fn test_companion_added_once() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<CompanionCount>();
+        stepper
+            .client_app
+            .add_companion::<ComponentSyncModeFull, With<Predicted>, _>(|c| Companion(c.0));
+        stepper.client_app.observe(
+            |_: Trigger<OnAdd, Companion>, mut count: ResMut<CompanionCount>| {
+                count.0 += 1;
+            },
+        );
+
+        stepper.server_app.world_mut().spawn((
+            ComponentSyncModeFull(1.0),
+            Replicate {
+                sync: SyncTarget {
+                    prediction: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let predicted = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<Entity, With<Predicted>>()
+            .single(stepper.client_app.world());
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<Companion>(predicted)
+                .unwrap(),
+            &Companion(1.0)
+        );
+        // the companion is only added on the predicted entity
+        assert_eq!(stepper.client_app.world().resource::<CompanionCount>().0, 1);
+
+        // removing and re-inserting the component (as in a rollback) does not add the companion again
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(predicted)
+            .remove::<ComponentSyncModeFull>();
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(predicted)
+            .insert(ComponentSyncModeFull(2.0));
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<Companion>(predicted)
+                .unwrap(),
+            &Companion(1.0)
+        );
+        assert_eq!(stepper.client_app.world().resource::<CompanionCount>().0, 1);
+    }
+}
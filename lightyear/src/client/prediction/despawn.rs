@@ -1,7 +1,7 @@
 use bevy::ecs::system::EntityCommands;
 use bevy::ecs::world::Command;
 use bevy::prelude::{
-    Commands, Component, DespawnRecursiveExt, Entity, OnRemove, Query, Reflect, Res, ResMut,
+    Commands, Component, DespawnRecursiveExt, Entity, OnAdd, OnRemove, Query, Reflect, Res, ResMut,
     Trigger, With, Without, World,
 };
 use tracing::{debug, error, trace};
@@ -11,6 +11,7 @@ use crate::client::config::ClientConfig;
 use crate::client::prediction::resource::PredictionManager;
 use crate::client::prediction::Predicted;
 use crate::prelude::{ComponentRegistry, Mode, ShouldBePredicted, TickManager};
+use crate::shared::replication::components::ShouldBeInterpolated;
 use crate::shared::tick_manager::Tick;
 
 // - TODO: despawning another client entity as a consequence from prediction, but we want to roll that back:
@@ -103,6 +104,38 @@ pub(crate) fn despawn_confirmed(
     }
 }
 
+/// Despawn the predicted entity when the server switches the confirmed entity to interpolation
+/// (for example when the client lost the ownership of the entity)
+pub(crate) fn despawn_predicted_on_interpolation(
+    trigger: Trigger<OnAdd, ShouldBeInterpolated>,
+    mut manager: ResMut<PredictionManager>,
+    mut query: Query<&mut Confirmed>,
+    mut commands: Commands,
+) {
+    let confirmed_entity = trigger.entity();
+    let Ok(mut confirmed) = query.get_mut(confirmed_entity) else {
+        return;
+    };
+    if let Some(predicted) = confirmed.predicted.take() {
+        debug!(
+            ?confirmed_entity,
+            ?predicted,
+            "Despawn predicted entity: the confirmed entity is now interpolated"
+        );
+        manager
+            .predicted_entity_map
+            .get_mut()
+            .confirmed_to_predicted
+            .remove(&confirmed_entity);
+        if let Some(entity_mut) = commands.get_entity(predicted) {
+            entity_mut.despawn_recursive();
+        }
+        commands
+            .entity(confirmed_entity)
+            .remove::<ShouldBePredicted>();
+    }
+}
+
 #[derive(Component)]
 pub(crate) struct RemovedCache<C: Component>(pub Option<C>);
 
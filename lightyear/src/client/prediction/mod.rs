@@ -2,16 +2,19 @@
 use bevy::prelude::{Component, Entity, Reflect};
 use std::fmt::Debug;
 
+pub mod companion;
 pub mod correction;
 pub mod despawn;
 pub mod diagnostics;
 pub mod plugin;
 pub mod pre_prediction;
 pub mod predicted_history;
+pub(crate) mod predicted_resource;
 pub mod prespawn;
 pub(crate) mod resource;
 pub mod rollback;
 pub mod spawn;
+pub mod time_travel;
 
 /// Marks an entity that is being predicted by the client
 #[derive(Component, Debug, Reflect)]
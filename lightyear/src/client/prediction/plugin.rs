@@ -1,6 +1,7 @@
 use bevy::prelude::{
-    not, App, Component, Condition, FixedPostUpdate, IntoSystemConfigs, IntoSystemSetConfigs,
-    Plugin, PostUpdate, PreUpdate, Res, SystemSet,
+    not, resource_exists, resource_removed, App, Component, Condition, First, FixedPostUpdate,
+    IntoSystemConfigs, IntoSystemSetConfigs, Plugin, PostUpdate, PreUpdate, Res, Resource,
+    SystemSet,
 };
 use bevy::reflect::Reflect;
 use bevy::transform::TransformSystem;
@@ -11,24 +12,33 @@ use crate::client::prediction::correction::{
     get_visually_corrected_state, restore_corrected_state,
 };
 use crate::client::prediction::despawn::{
-    despawn_confirmed, remove_component_for_despawn_predicted, remove_despawn_marker,
-    restore_components_if_despawn_rolled_back, PredictionDespawnMarker,
+    despawn_confirmed, despawn_predicted_on_interpolation, remove_component_for_despawn_predicted,
+    remove_despawn_marker, restore_components_if_despawn_rolled_back, PredictionDespawnMarker,
 };
 use crate::client::prediction::predicted_history::{
     add_non_networked_component_history, add_prespawned_component_history,
     apply_component_removal_confirmed, apply_component_removal_predicted,
     update_prediction_history,
 };
+use crate::client::prediction::predicted_resource::{
+    check_resource_rollback, prepare_resource_rollback, receive_confirmed_resource,
+    update_resource_history, update_resource_history_removal, PredictedResource,
+};
 use crate::client::prediction::prespawn::{
     PreSpawnedPlayerObjectPlugin, PreSpawnedPlayerObjectSet,
 };
 use crate::client::prediction::resource::PredictionManager;
+use crate::client::prediction::time_travel::{apply_time_travel, TimeTravelSet, TimeTravelState};
 use crate::client::prediction::Predicted;
+use crate::packet::message::Message;
 use crate::prelude::{client::is_synced, is_host_server, PreSpawnedPlayerObject};
-use crate::shared::sets::{ClientMarker, InternalMainSet};
+use crate::shared::sets::{ClientMarker, InternalMainSet, InternalReplicationSet};
 
 use super::pre_prediction::PrePredictionPlugin;
-use super::predicted_history::{add_component_history, apply_confirmed_update};
+use super::predicted_history::{
+    add_component_history, apply_confirmed_update, bound_prediction_history,
+    PredictedComponentAdded,
+};
 use super::rollback::{
     check_rollback, increment_rollback_tick, prepare_rollback, prepare_rollback_non_networked,
     prepare_rollback_prespawn, run_rollback, Rollback, RollbackState,
@@ -75,6 +85,25 @@ pub struct PredictionConfig {
     /// (i.e. if the client is 10 ticks head and correction_ticks is 1.0, then the correction will be done over 10 ticks)
     // Number of ticks it will take to visually update the Predicted state to the new Corrected state
     pub correction_ticks_factor: f32,
+    /// How the prediction history is initialized for the predicted entities that are not controlled by this
+    /// client (for example the other players' spaceships when every client predicts every spaceship)
+    pub remote_history_init: PredictionHistoryInit,
+}
+
+/// How the prediction history of a newly spawned Predicted entity is initialized
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PredictionHistoryInit {
+    /// The history starts with the first confirmed state, at the confirmed tick.
+    ///
+    /// The predicted entity starts at the (past) confirmed state and only catches up with the predicted
+    /// timeline through the simulation, but spawning it never triggers a rollback.
+    #[default]
+    FromConfirmed,
+    /// The entity is extrapolated from the first confirmed state to the current predicted tick, by running a
+    /// rollback when it is spawned.
+    ///
+    /// The predicted entity is immediately in the predicted timeline, at the cost of one rollback per spawn.
+    Extrapolated,
 }
 
 impl Default for PredictionConfig {
@@ -93,6 +122,7 @@ impl Default for PredictionConfig {
             maximum_input_delay_before_prediction: 0,
             maximum_predicted_ticks: 100,
             correction_ticks_factor: 1.0,
+            remote_history_init: PredictionHistoryInit::FromConfirmed,
         }
     }
 }
@@ -115,6 +145,12 @@ impl PredictionConfig {
         self
     }
 
+    /// Set how the prediction history is initialized for the predicted entities that are not controlled by this client
+    pub fn with_remote_history_init(mut self, history_init: PredictionHistoryInit) -> Self {
+        self.remote_history_init = history_init;
+        self
+    }
+
     /// Compute the amount of input delay that should be applied, considering the current RTT
     pub fn input_delay_ticks(&self, rtt: Duration, tick_interval: Duration) -> u16 {
         let rtt_ticks = rtt.as_nanos() as f32 / tick_interval.as_nanos() as f32;
@@ -197,9 +233,40 @@ pub fn add_non_networked_rollback_systems<C: Component + PartialEq + Clone>(app:
         FixedPostUpdate,
         update_prediction_history::<C>.in_set(PredictionSet::UpdateHistory),
     );
+    app.add_systems(
+        First,
+        apply_time_travel::<C>
+            .run_if(resource_exists::<TimeTravelState>)
+            .in_set(TimeTravelSet::Apply),
+    );
+}
+
+/// Enable prediction and rollbacks for a resource that is replicated from the server
+pub fn add_resource_prediction_systems<R: Resource + Message + PartialEq + Clone>(app: &mut App) {
+    app.init_resource::<PredictedResource<R>>();
+    app.add_systems(
+        PreUpdate,
+        (
+            // intercept the server updates before they are written to the resource
+            receive_confirmed_resource::<R>
+                .in_set(PredictionSet::SpawnHistory)
+                .before(InternalReplicationSet::<ClientMarker>::ReceiveResourceUpdates),
+            check_resource_rollback::<R>.in_set(PredictionSet::CheckRollback),
+            prepare_resource_rollback::<R>.in_set(PredictionSet::PrepareRollback),
+        ),
+    );
+    app.add_systems(
+        FixedPostUpdate,
+        (
+            update_resource_history::<R>,
+            update_resource_history_removal::<R>.run_if(resource_removed::<R>()),
+        )
+            .in_set(PredictionSet::UpdateHistory),
+    );
 }
 
 pub fn add_prediction_systems<C: SyncComponent>(app: &mut App, prediction_mode: ComponentSyncMode) {
+    app.add_event::<PredictedComponentAdded<C>>();
     app.add_systems(
         PreUpdate,
         (
@@ -222,6 +289,8 @@ pub fn add_prediction_systems<C: SyncComponent>(app: &mut App, prediction_mode:
                     // for SyncMode::Full, we need to check if we need to rollback.
                     // TODO: for mode=simple/once, we still need to re-add the component if the entity ends up not being despawned!
                     check_rollback::<C>.in_set(PredictionSet::CheckRollback),
+                    // components that are excluded from prediction are copied from the confirmed entity
+                    apply_confirmed_update::<C>.in_set(PredictionSet::CheckRollback),
                     (prepare_rollback::<C>, prepare_rollback_prespawn::<C>)
                         .in_set(PredictionSet::PrepareRollback),
                 ),
@@ -236,7 +305,16 @@ pub fn add_prediction_systems<C: SyncComponent>(app: &mut App, prediction_mode:
             );
             app.add_systems(
                 PostUpdate,
-                get_visually_corrected_state::<C>.in_set(PredictionSet::VisualCorrection),
+                (
+                    get_visually_corrected_state::<C>.in_set(PredictionSet::VisualCorrection),
+                    bound_prediction_history::<C>.in_set(PredictionSet::All),
+                ),
+            );
+            app.add_systems(
+                First,
+                apply_time_travel::<C>
+                    .run_if(resource_exists::<TimeTravelState>)
+                    .in_set(TimeTravelSet::Apply),
             );
         }
         ComponentSyncMode::Simple => {
@@ -284,7 +362,8 @@ impl Plugin for PredictionPlugin {
             .register_type::<Rollback>()
             .register_type::<RollbackState>()
             .register_type::<PredictionDespawnMarker>()
-            .register_type::<PredictionConfig>();
+            .register_type::<PredictionConfig>()
+            .register_type::<PredictionHistoryInit>();
 
         // RESOURCES
         app.init_resource::<PredictionManager>();
@@ -329,6 +408,7 @@ impl Plugin for PredictionPlugin {
             ),
         );
         app.observe(despawn_confirmed);
+        app.observe(despawn_predicted_on_interpolation);
 
         // FixedUpdate systems
         // 1. Update client tick (don't run in rollback)
@@ -387,6 +467,7 @@ mod tests {
             maximum_input_delay_before_prediction: 3,
             maximum_predicted_ticks: 7,
             correction_ticks_factor: 0.0,
+            remote_history_init: PredictionHistoryInit::FromConfirmed,
         };
         // 1. Test the minimum input delay
         assert_eq!(
@@ -1,18 +1,26 @@
 //! Managed the history buffer, which is a buffer of the past predicted component states,
 //! so that whenever we receive an update from the server we can compare the predicted entity's history with the server update.
+use std::marker::PhantomData;
 use std::ops::Deref;
 
 use bevy::prelude::{
-    Added, Commands, Component, DetectChanges, Entity, OnRemove, Or, Query, Ref, Res, Trigger,
-    With, Without,
+    Added, Commands, Component, DetectChanges, DetectChangesMut, Entity, Event, EventWriter, Has,
+    OnRemove, Or, Query, Ref, Res, ResMut, Trigger, With, Without,
 };
 use tracing::{debug, trace};
 
 use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent};
+use crate::client::config::ClientConfig;
+use crate::client::prediction::plugin::PredictionHistoryInit;
 use crate::client::prediction::resource::PredictionManager;
 use crate::client::prediction::rollback::Rollback;
 use crate::client::prediction::Predicted;
-use crate::prelude::{ComponentRegistry, PreSpawnedPlayerObject, ShouldBePredicted, TickManager};
+use crate::prelude::{
+    ComponentRegistry, PreSpawnedPlayerObject, PredictionExclusions, ShouldBePredicted, TickManager,
+};
+use crate::protocol::component::ComponentKind;
+use crate::shared::memory::{evict_ready_buffer, MemoryStats, MemorySubsystem, MemoryTally};
+use crate::shared::replication::components::Controlled;
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
 
@@ -114,81 +122,168 @@ pub(crate) fn add_non_networked_component_history<C: Component + PartialEq + Clo
     }
 }
 
+/// Event emitted when a component that was inserted on the [`Confirmed`] entity gets added to its [`Predicted`] entity
+///
+/// This is also emitted when the Predicted entity is spawned, for each component that the Confirmed entity
+/// already had, and when a component that was removed is inserted again on the Confirmed entity.
+#[derive(Event, Debug)]
+pub struct PredictedComponentAdded<C> {
+    pub confirmed: Entity,
+    pub predicted: Entity,
+    /// Confirmed tick at which the component was inserted
+    pub tick: Tick,
+    marker: PhantomData<C>,
+}
+
+impl<C> PredictedComponentAdded<C> {
+    pub(crate) fn new(confirmed: Entity, predicted: Entity, tick: Tick) -> Self {
+        Self {
+            confirmed,
+            predicted,
+            tick,
+            marker: PhantomData,
+        }
+    }
+}
+
 /// Add component history for entities that are predicted
 /// There is extra complexity because the component could get added on the Confirmed entity (received from the server), or added to the Predited entity directly
+///
+/// When a component is inserted on the Confirmed entity, it is added to the Predicted entity (with entities mapped):
+/// - full: the [`PredictionHistory`] is initialized with the confirmed value at the confirmed tick, so that the
+///   next rollback check compares against it. If the Predicted entity already has a history (for example because the
+///   component was removed and is now inserted again), the value is added to the existing history.
+/// - simple: the component is copied, there is no history
+/// - once: the component is copied, unless the Predicted entity already has it (prespawned entities)
+///
+/// Components that are listed in the [`PredictionExclusions`] of the Confirmed entity are handled like `simple`
+/// components, even in `full` mode.
+///
+/// For Predicted entities that are not [`Controlled`] by the client, the history is initialized according to the
+/// [`PredictionHistoryInit`] of the [`PredictionConfig`](super::plugin::PredictionConfig).
+///
+/// A [`PredictedComponentAdded`] event is emitted when the component is added to the Predicted entity.
 #[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn add_component_history<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
     manager: Res<PredictionManager>,
+    config: Res<ClientConfig>,
+    rollback: Res<Rollback>,
     mut commands: Commands,
     tick_manager: Res<TickManager>,
-    predicted_entities: Query<
-        (Entity, Option<Ref<C>>),
+    mut events: EventWriter<PredictedComponentAdded<C>>,
+    mut predicted_entities: Query<
+        // for all types of predicted entities, we want to add the component history to enable them to be rolled-back
         (
-            Without<PredictionHistory<C>>,
-            // for all types of predicted entities, we want to add the component history to enable them to be rolled-back
-            With<Predicted>,
+            Entity,
+            Ref<Predicted>,
+            Option<Ref<C>>,
+            Option<&mut PredictionHistory<C>>,
         ),
     >,
-    confirmed_entities: Query<(Entity, &Confirmed, Option<Ref<C>>)>,
+    confirmed_entities: Query<(
+        Entity,
+        &Confirmed,
+        Option<Ref<C>>,
+        Option<&PredictionExclusions>,
+        Has<Controlled>,
+    )>,
 ) {
     let kind = std::any::type_name::<C>();
     let tick = tick_manager.tick();
-    for (confirmed_entity, confirmed, confirmed_component) in confirmed_entities.iter() {
-        if let Some(p) = confirmed.predicted {
-            if let Ok((predicted_entity, predicted_component)) = predicted_entities.get(p) {
-                // if component got added on predicted side, add history
-                add_history::<C>(
-                    component_registry.as_ref(),
-                    tick,
-                    predicted_entity,
-                    &predicted_component,
-                    &mut commands,
-                );
+    for (confirmed_entity, confirmed, confirmed_component, exclusions, controlled) in
+        confirmed_entities.iter()
+    {
+        let Some(p) = confirmed.predicted else {
+            continue;
+        };
+        let Ok((predicted_entity, predicted, predicted_component, mut history)) =
+            predicted_entities.get_mut(p)
+        else {
+            continue;
+        };
+        let excluded = exclusions.is_some_and(|e| e.is_excluded::<C>(component_registry.as_ref()));
+        if excluded {
+            // the component is not predicted anymore
+            if history.is_some() {
+                commands
+                    .entity(predicted_entity)
+                    .remove::<PredictionHistory<C>>();
+            }
+        } else if history.is_none() {
+            // if component got added on predicted side, add history
+            add_history::<C>(
+                component_registry.as_ref(),
+                tick,
+                predicted_entity,
+                &predicted_component,
+                &mut commands,
+            );
+        }
 
-                // if component got added on confirmed side
-                // - full: sync component and add history
-                // - simple/once: sync component
-                if let Some(confirmed_component) = confirmed_component {
-                    if confirmed_component.is_added() {
-                        trace!(?kind, "Component added on confirmed side");
-                        // safety: we know the entity exists
-                        let mut predicted_entity_mut =
-                            commands.get_entity(predicted_entity).unwrap();
-                        // map any entities from confirmed to predicted
-                        let mut new_component = confirmed_component.deref().clone();
-                        let _ =
-                            manager.map_entities(&mut new_component, component_registry.as_ref());
-                        match component_registry.prediction_mode::<C>() {
-                            ComponentSyncMode::Full => {
-                                // insert history, it will be quickly filled by a rollback (since it starts empty before the current client tick)
-                                // or will it? because the component just got spawned anyway..
-                                // TODO: then there's no need to add the component here, since it's going to get added during rollback anyway?
-                                let mut history = PredictionHistory::<C>::default();
-                                history.add_update(tick, confirmed_component.deref().clone());
-                                predicted_entity_mut.insert((new_component, history));
-                            }
-                            ComponentSyncMode::Simple => {
-                                debug!(
-                                    ?kind,
-                                    "Component simple synced between confirmed and predicted"
-                                );
-                                // we only sync the components once, but we don't do rollback so no need for a component history
-                                predicted_entity_mut.insert(new_component);
-                            }
-                            ComponentSyncMode::Once => {
-                                // if this was a prespawned entity, don't override SyncMode::Once components!
-                                if predicted_component.is_none() {
-                                    // we only sync the components once, but we don't do rollback so no need for a component history
-                                    predicted_entity_mut.insert(new_component);
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
+        // if component got added on confirmed side
+        // - full: sync component and add history
+        // - simple/once: sync component
+        let Some(confirmed_component) = confirmed_component else {
+            continue;
+        };
+        // the predicted entity can also be spawned for an existing confirmed entity (for example
+        // when the client gained the ownership of the entity)
+        if !confirmed_component.is_added() && !predicted.is_added() {
+            continue;
+        }
+        trace!(?kind, "Component added on confirmed side");
+        // safety: we know the entity exists
+        let mut predicted_entity_mut = commands.get_entity(predicted_entity).unwrap();
+        // map any entities from confirmed to predicted
+        let mut new_component = confirmed_component.deref().clone();
+        let _ = manager.map_entities(&mut new_component, component_registry.as_ref());
+        match component_registry.prediction_mode::<C>() {
+            ComponentSyncMode::Full if !excluded => {
+                // the confirmed value is the correct value at the confirmed tick
+                if let Some(history) = history.as_mut() {
+                    history.add_update(confirmed.tick, new_component.clone());
+                    predicted_entity_mut.insert(new_component);
+                } else {
+                    let mut history = PredictionHistory::<C>::default();
+                    history.add_update(confirmed.tick, new_component.clone());
+                    predicted_entity_mut.insert((new_component, history));
+                }
+                // extrapolate the newly spawned entity to the current tick by rolling back from the confirmed tick
+                if predicted.is_added()
+                    && !controlled
+                    && config.prediction.remote_history_init == PredictionHistoryInit::Extrapolated
+                    && tick > confirmed.tick
+                    && !rollback.is_rollback()
+                {
+                    debug!(?predicted_entity, confirmed_tick = ?confirmed.tick, "Extrapolating the new predicted entity");
+                    rollback.set_rollback_tick(confirmed.tick + 1);
+                }
+            }
+            ComponentSyncMode::Simple | ComponentSyncMode::Full => {
+                debug!(
+                    ?kind,
+                    "Component simple synced between confirmed and predicted"
+                );
+                // we only sync the components once, but we don't do rollback so no need for a component history
+                predicted_entity_mut.insert(new_component);
+            }
+            ComponentSyncMode::Once => {
+                // if this was a prespawned entity, don't override SyncMode::Once components!
+                if predicted_component.is_some() {
+                    continue;
                 }
+                // we only sync the components once, but we don't do rollback so no need for a component history
+                predicted_entity_mut.insert(new_component);
             }
+            _ => continue,
         }
+        events.send(PredictedComponentAdded::new(
+            confirmed_entity,
+            predicted_entity,
+            confirmed.tick,
+        ));
     }
 }
 
@@ -264,6 +359,28 @@ pub(crate) fn update_prediction_history<T: Component + PartialEq + Clone>(
     }
 }
 
+/// Evict the entries of the prediction histories that are over the memory limit
+pub(crate) fn bound_prediction_history<C: SyncComponent>(
+    config: Res<ClientConfig>,
+    mut stats: ResMut<MemoryStats>,
+    mut query: Query<&mut PredictionHistory<C>>,
+) {
+    let limit = &config.shared.memory.prediction_history;
+    let mut tally = MemoryTally::default();
+    for mut history in query.iter_mut() {
+        evict_ready_buffer(
+            &mut history.bypass_change_detection().buffer,
+            limit,
+            &mut tally,
+        );
+    }
+    stats.record(
+        MemorySubsystem::PredictionHistory,
+        Some(ComponentKind::of::<C>()),
+        tally,
+    );
+}
+
 /// If a component is removed on the Predicted entity, and the ComponentSyncMode == FULL
 /// Add the removal to the history (for potential rollbacks)
 pub(crate) fn apply_component_removal_predicted<C: Component + PartialEq + Clone>(
@@ -300,7 +417,9 @@ pub(crate) fn apply_component_removal_confirmed<C: SyncComponent>(
     }
 }
 
-/// If ComponentSyncMode == Simple, when we receive a server update we want to apply it to the predicted entity
+/// If ComponentSyncMode == Simple, when we receive a server update we want to apply it to the predicted entity.
+///
+/// This is also the case for ComponentSyncMode == Full components that are excluded from prediction.
 #[allow(clippy::type_complexity)]
 pub(crate) fn apply_confirmed_update<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
@@ -313,16 +432,18 @@ pub(crate) fn apply_confirmed_update<C: SyncComponent>(
             With<Predicted>,
         ),
     >,
-    confirmed_entities: Query<(&Confirmed, Ref<C>)>,
+    confirmed_entities: Query<(&Confirmed, Ref<C>, Option<&PredictionExclusions>)>,
 ) {
-    for (confirmed_entity, confirmed_component) in confirmed_entities.iter() {
+    let is_simple = component_registry.prediction_mode::<C>() == ComponentSyncMode::Simple;
+    for (confirmed_entity, confirmed_component, exclusions) in confirmed_entities.iter() {
+        if !is_simple
+            && !exclusions.is_some_and(|e| e.is_excluded::<C>(component_registry.as_ref()))
+        {
+            continue;
+        }
         if let Some(p) = confirmed_entity.predicted {
             if confirmed_component.is_changed() && !confirmed_component.is_added() {
                 if let Ok(mut predicted_component) = predicted_entities.get_mut(p) {
-                    assert_eq!(
-                        component_registry.prediction_mode::<C>(),
-                        ComponentSyncMode::Simple
-                    );
                     // map any entities from confirmed to predicted
                     let mut component = confirmed_component.deref().clone();
                     let _ = manager.map_entities(&mut component, component_registry.as_ref());
@@ -340,6 +461,7 @@ mod tests {
     use crate::tests::protocol::*;
     use crate::tests::stepper::BevyStepper;
     use crate::utils::ready_buffer::ItemWithReadyKey;
+    use bevy::ecs::event::Events;
     use bevy::ecs::system::RunSystemOnce;
 
     /// Test adding and removing updates to the component history
@@ -536,6 +658,172 @@ mod tests {
         );
     }
 
+    /// A component that is inserted again on the Confirmed entity after the Predicted entity already has a
+    /// history for it should be predicted again, and emit a [`PredictedComponentAdded`] event
+    #[test]
+    fn test_component_added_to_confirmed_after_removal() {
+        let mut stepper = BevyStepper::default();
+        let confirmed = stepper
+            .client_app
+            .world_mut()
+            .spawn(Confirmed::default())
+            .id();
+        let predicted = stepper
+            .client_app
+            .world_mut()
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<Confirmed>(confirmed)
+            .unwrap()
+            .predicted = Some(predicted);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .insert(ComponentSyncModeFull(1.0));
+        stepper.frame_step();
+        let events = stepper
+            .client_app
+            .world()
+            .resource::<Events<PredictedComponentAdded<ComponentSyncModeFull>>>();
+        assert_eq!(events.len(), 1);
+
+        // the component gets removed, the removal is recorded in the history
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .remove::<ComponentSyncModeFull>();
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(predicted)
+            .remove::<ComponentSyncModeFull>();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<PredictionHistory<ComponentSyncModeFull>>(predicted)
+            .is_some());
+
+        // the component is inserted again on the confirmed entity at a later tick
+        let tick = Tick(10);
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<Confirmed>(confirmed)
+            .unwrap()
+            .tick = tick;
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .insert(ComponentSyncModeFull(2.0));
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(predicted),
+            Some(&ComponentSyncModeFull(2.0))
+        );
+        // the history is initialized with the confirmed value at the confirmed tick
+        assert_eq!(
+            stepper
+                .client_app
+                .world_mut()
+                .get_mut::<PredictionHistory<ComponentSyncModeFull>>(predicted)
+                .unwrap()
+                .pop_until_tick(tick),
+            Some(ComponentState::Updated(ComponentSyncModeFull(2.0)))
+        );
+        let mut events = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Events<PredictedComponentAdded<ComponentSyncModeFull>>>();
+        let event = events.drain().last().unwrap();
+        assert_eq!(event.confirmed, confirmed);
+        assert_eq!(event.predicted, predicted);
+        assert_eq!(event.tick, tick);
+    }
+
+    /// Components that are excluded from prediction are copied from the confirmed entity, without history
+    #[test]
+    fn test_prediction_exclusions() {
+        let mut stepper = BevyStepper::default();
+
+        let exclusions = PredictionExclusions::default().exclude::<ComponentSyncModeFull>(
+            stepper.client_app.world().resource::<ComponentRegistry>(),
+        );
+        let confirmed = stepper
+            .client_app
+            .world_mut()
+            .spawn((Confirmed::default(), exclusions))
+            .id();
+        let predicted = stepper
+            .client_app
+            .world_mut()
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+
+        // the component is synced, but no history is added
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .insert(ComponentSyncModeFull(1.0));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(predicted),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<PredictionHistory<ComponentSyncModeFull>>(predicted)
+            .is_none());
+
+        // confirmed updates are applied directly to the predicted entity
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .get_mut::<ComponentSyncModeFull>()
+            .unwrap()
+            .0 = 2.0;
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(predicted),
+            Some(&ComponentSyncModeFull(2.0))
+        );
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<PredictionHistory<ComponentSyncModeFull>>(predicted)
+            .is_none());
+    }
+
     /// Test that the history gets updated correctly
     /// 1. Updating the predicted component for ComponentSyncMode::Full
     /// 2. Updating the confirmed component for ComponentSyncMode::Simple
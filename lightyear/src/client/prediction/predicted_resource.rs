@@ -0,0 +1,329 @@
+//! Client-side prediction of [`Resource`]s.
+//!
+//! The resource is replicated from the server like any other replicated resource, but the server sends its
+//! updates as [`PredictedResourceUpdate`] messages that contain the server tick of the value. Instead of
+//! overwriting the client's resource with the server value, we store the server value as the confirmed state.
+//! We keep a history of the predicted values of the resource, and trigger a rollback if the confirmed state
+//! does not match the predicted history.
+use bevy::prelude::{Commands, DetectChanges, Events, Res, ResMut, Resource};
+use tracing::{debug, error, info, trace_span};
+
+use crate::client::events::MessageEvent;
+use crate::client::prediction::predicted_history::{ComponentState, PredictionHistory};
+use crate::client::prediction::rollback::Rollback;
+use crate::packet::message::Message;
+use crate::prelude::{Tick, TickManager};
+use crate::shared::replication::resources::PredictedResourceUpdate;
+
+/// Tracks the predicted history and the latest confirmed (server) state of a predicted resource `R`
+#[derive(Resource, Debug)]
+pub(crate) struct PredictedResource<R> {
+    pub(crate) history: PredictionHistory<R>,
+    /// Latest server state of the resource, along with the server tick of that state
+    pub(crate) confirmed: Option<(Tick, ComponentState<R>)>,
+    /// True if we received a new confirmed state that hasn't been checked for rollback yet
+    pub(crate) new_confirmed: bool,
+}
+
+impl<R: PartialEq> Default for PredictedResource<R> {
+    fn default() -> Self {
+        Self {
+            history: PredictionHistory::default(),
+            confirmed: None,
+            new_confirmed: false,
+        }
+    }
+}
+
+/// Store the server updates of the resource as the confirmed state, instead of directly
+/// overwriting the predicted resource.
+///
+/// Each update contains the server tick at which the value was computed; updates that are older
+/// than the current confirmed state (for example because of an unordered channel) are ignored.
+pub(crate) fn receive_confirmed_resource<R: Resource + Message + PartialEq + Clone>(
+    mut predicted_resource: ResMut<PredictedResource<R>>,
+    mut update_events: ResMut<Events<MessageEvent<PredictedResourceUpdate<R>>>>,
+) {
+    // only the most recent update matters
+    let Some(update) = update_events
+        .drain()
+        .map(|event| event.message)
+        .reduce(|latest, update| {
+            if update.tick >= latest.tick {
+                update
+            } else {
+                latest
+            }
+        })
+    else {
+        return;
+    };
+    if predicted_resource
+        .confirmed
+        .as_ref()
+        .is_some_and(|(tick, _)| *tick > update.tick)
+    {
+        return;
+    }
+    let state = match update.value {
+        Some(value) => ComponentState::Updated(value),
+        None => ComponentState::Removed,
+    };
+    predicted_resource.confirmed = Some((update.tick, state));
+    predicted_resource.new_confirmed = true;
+}
+
+/// Check if we need to do a rollback because the confirmed state of the resource doesn't match
+/// the predicted history
+pub(crate) fn check_resource_rollback<R: Resource + PartialEq + Clone>(
+    tick_manager: Res<TickManager>,
+    mut predicted_resource: ResMut<PredictedResource<R>>,
+    rollback: Res<Rollback>,
+) {
+    let kind = std::any::type_name::<R>();
+    let _span = trace_span!("client resource rollback check", ?kind);
+    let predicted_resource = predicted_resource.as_mut();
+    if !std::mem::take(&mut predicted_resource.new_confirmed) {
+        return;
+    }
+    let Some((tick, confirmed)) = &predicted_resource.confirmed else {
+        return;
+    };
+    let current_tick = tick_manager.tick();
+    if *tick > current_tick {
+        debug!(
+            ?kind,
+            "Confirmed resource is at a tick in the future: {:?} compared to client timeline. Current tick: {:?}",
+            tick,
+            current_tick
+        );
+        return;
+    }
+    // we already know that we should do a rollback (because of another resource/component)
+    if rollback.is_rollback() {
+        return;
+    }
+    let history_value = predicted_resource.history.pop_until_tick(*tick);
+    let should_rollback = match history_value {
+        None => *confirmed != ComponentState::Removed,
+        Some(history_value) => history_value != *confirmed,
+    };
+    if should_rollback {
+        info!(
+            ?kind,
+            "Rollback check: mismatch for resource between predicted and confirmed on tick {:?}. Current tick: {:?}",
+            tick, current_tick
+        );
+        rollback.set_rollback_tick(*tick + 1);
+    }
+}
+
+/// If we are in rollback, restore the resource to its state at the rollback tick:
+/// - the confirmed state, if it was received for the rollback tick
+/// - otherwise the predicted value from the history
+pub(crate) fn prepare_resource_rollback<R: Resource + PartialEq + Clone>(
+    mut commands: Commands,
+    mut predicted_resource: ResMut<PredictedResource<R>>,
+    resource: Option<ResMut<R>>,
+    rollback: Res<Rollback>,
+) {
+    let kind = std::any::type_name::<R>();
+    let _span = trace_span!("client prepare rollback for resource", ?kind);
+    let Some(rollback_tick_plus_one) = rollback.get_rollback_tick() else {
+        error!("prepare_resource_rollback should only be called when we are in rollback");
+        return;
+    };
+    // careful, the rollback tick is already incremented by 1 in the check_rollback stage...
+    let rollback_tick = rollback_tick_plus_one - 1;
+
+    let predicted_resource = predicted_resource.as_mut();
+    let state = match &predicted_resource.confirmed {
+        Some((tick, state)) if *tick == rollback_tick => Some(state.clone()),
+        _ => predicted_resource.history.pop_until_tick(rollback_tick),
+    };
+    // clear the history so we can write a new one
+    predicted_resource.history.clear();
+    match state {
+        None | Some(ComponentState::Removed) => {
+            predicted_resource.history.add_remove(rollback_tick);
+            if resource.is_some() {
+                debug!(
+                    ?kind,
+                    "Resource didn't exist at time of rollback, removing it"
+                );
+                commands.remove_resource::<R>();
+            }
+        }
+        Some(ComponentState::Updated(r)) => {
+            predicted_resource
+                .history
+                .add_update(rollback_tick, r.clone());
+            if let Some(mut resource) = resource {
+                *resource = r;
+            } else {
+                debug!(?kind, "Resource existed at time of rollback, inserting it");
+                commands.insert_resource(r);
+            }
+        }
+    }
+}
+
+/// Add the predicted value of the resource to the history if it changed
+pub(crate) fn update_resource_history<R: Resource + PartialEq + Clone>(
+    resource: Option<Res<R>>,
+    mut predicted_resource: ResMut<PredictedResource<R>>,
+    tick_manager: Res<TickManager>,
+    rollback: Res<Rollback>,
+) {
+    // tick for which we will record the history (either the current client tick or the current rollback tick)
+    let tick = tick_manager.tick_or_rollback_tick(rollback.as_ref());
+    if let Some(resource) = resource {
+        if resource.is_changed() {
+            predicted_resource
+                .history
+                .add_update(tick, resource.as_ref().clone());
+        }
+    }
+}
+
+/// Add the removal of the resource to the history
+pub(crate) fn update_resource_history_removal<R: Resource + PartialEq + Clone>(
+    mut predicted_resource: ResMut<PredictedResource<R>>,
+    tick_manager: Res<TickManager>,
+    rollback: Res<Rollback>,
+) {
+    let tick = tick_manager.tick_or_rollback_tick(rollback.as_ref());
+    predicted_resource.history.add_remove(tick);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::prediction::diagnostics::PredictionMetrics;
+    use crate::shared::replication::network_target::NetworkTarget;
+    use crate::shared::replication::resources::ReplicateResourceExt;
+    use crate::tests::protocol::{Channel1, ResourcePredicted};
+    use crate::tests::stepper::BevyStepper;
+
+    /// Simulate that we received a server update for the resource at the given tick
+    fn received_confirmed_resource(stepper: &mut BevyStepper, tick: Tick, value: f32) {
+        let mut predicted_resource = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<PredictedResource<ResourcePredicted>>();
+        predicted_resource.confirmed =
+            Some((tick, ComponentState::Updated(ResourcePredicted(value))));
+        predicted_resource.new_confirmed = true;
+    }
+
+    fn rollbacks(stepper: &BevyStepper) -> u32 {
+        stepper
+            .client_app
+            .world()
+            .resource::<PredictionMetrics>()
+            .rollbacks
+    }
+
+    #[test]
+    fn test_resource_rollback() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .world_mut()
+            .insert_resource(ResourcePredicted(1.0));
+        stepper.frame_step();
+
+        // 1. the confirmed state matches the predicted history: no rollback
+        let tick = stepper.client_tick();
+        received_confirmed_resource(&mut stepper, tick, 1.0);
+        stepper.frame_step();
+        assert_eq!(rollbacks(&stepper), 0);
+        assert_eq!(
+            stepper.client_app.world().resource::<ResourcePredicted>(),
+            &ResourcePredicted(1.0)
+        );
+
+        // 2. the confirmed state doesn't match the predicted history: rollback and restore
+        // the confirmed state
+        let tick = stepper.client_tick();
+        received_confirmed_resource(&mut stepper, tick, 2.0);
+        stepper.frame_step();
+        assert_eq!(rollbacks(&stepper), 1);
+        assert_eq!(
+            stepper.client_app.world().resource::<ResourcePredicted>(),
+            &ResourcePredicted(2.0)
+        );
+    }
+
+    fn confirmed(stepper: &BevyStepper) -> Option<(Tick, ComponentState<ResourcePredicted>)> {
+        stepper
+            .client_app
+            .world()
+            .resource::<PredictedResource<ResourcePredicted>>()
+            .confirmed
+            .clone()
+    }
+
+    /// The server sends the updates of a predicted resource with the server tick of the value
+    #[test]
+    fn test_confirmed_resource_uses_server_tick() {
+        let mut stepper = BevyStepper::default();
+        let replicate_system =
+            stepper
+                .server_app
+                .world_mut()
+                .register_system(|mut commands: Commands| {
+                    commands.replicate_resource::<ResourcePredicted, Channel1>(NetworkTarget::All);
+                });
+        let _ = stepper.server_app.world_mut().run_system(replicate_system);
+        stepper.frame_step();
+
+        // insert the resource on the server
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(ResourcePredicted(1.0));
+        stepper.frame_step();
+        let server_tick = stepper.server_tick();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            confirmed(&stepper),
+            Some((server_tick, ComponentState::Updated(ResourcePredicted(1.0))))
+        );
+        // the resource didn't exist in the predicted history, so it got inserted via rollback
+        assert_eq!(
+            stepper.client_app.world().resource::<ResourcePredicted>(),
+            &ResourcePredicted(1.0)
+        );
+
+        // update the resource on the server
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ResourcePredicted>()
+            .0 = 2.0;
+        stepper.frame_step();
+        let server_tick = stepper.server_tick();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            confirmed(&stepper),
+            Some((server_tick, ComponentState::Updated(ResourcePredicted(2.0))))
+        );
+
+        // remove the resource on the server
+        stepper
+            .server_app
+            .world_mut()
+            .remove_resource::<ResourcePredicted>();
+        stepper.frame_step();
+        let server_tick = stepper.server_tick();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            confirmed(&stepper),
+            Some((server_tick, ComponentState::Removed))
+        );
+    }
+}
@@ -748,6 +748,89 @@ mod unit_tests {
             .resource::<Rollback>()
             .is_rollback());
     }
+
+    /// Check that a custom rollback predicate registered for the component is used
+    /// to compare the confirmed value with the predicted history
+    #[test]
+    fn test_check_rollback_custom_predicate() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ComponentRegistry>()
+            .set_should_rollback::<ComponentSyncModeFull>(|this, that| {
+                (this.0 - that.0).abs() >= 0.01
+            });
+
+        // add predicted/confirmed entities
+        let confirmed = stepper
+            .client_app
+            .world_mut()
+            .spawn(Confirmed::default())
+            .id();
+        let predicted = stepper
+            .client_app
+            .world_mut()
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .insert(ComponentSyncModeFull(1.0));
+        stepper.frame_step();
+
+        // 1. the difference is below the threshold: no rollback
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .get_mut::<ComponentSyncModeFull>()
+            .unwrap()
+            .0 = 1.005;
+        received_confirmed_update(&mut stepper, confirmed, tick);
+        stepper
+            .client_app
+            .world_mut()
+            .run_system_once(check_rollback::<ComponentSyncModeFull>);
+        assert!(!stepper
+            .client_app
+            .world()
+            .resource::<Rollback>()
+            .is_rollback());
+
+        // 2. the difference is above the threshold: rollback
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .get_mut::<ComponentSyncModeFull>()
+            .unwrap()
+            .0 = 1.5;
+        received_confirmed_update(&mut stepper, confirmed, tick);
+        stepper
+            .client_app
+            .world_mut()
+            .run_system_once(check_rollback::<ComponentSyncModeFull>);
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<Rollback>()
+                .get_rollback_tick(),
+            Some(tick + 1)
+        );
+    }
 }
 
 /// More general integration tests for rollback
@@ -0,0 +1,366 @@
+//! Dev-mode tool to inspect the predicted world at previous ticks.
+//!
+//! The [`PredictionHistory`] of each predicted component keeps the states of the component
+//! since the latest confirmed server update. The [`TimeTravelPlugin`] uses these histories to
+//! step the predicted entities backwards and forwards within that window, which is useful to
+//! inspect mispredictions frame-by-frame (for example with `bevy-inspector-egui`).
+//!
+//! While time-travelling:
+//! - the virtual time is paused, so the `FixedUpdate` schedule does not run
+//! - the client does not receive or send any packets, and the prediction systems are paused.
+//!   Note that the connection could time out if the client stays paused for too long.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::client::*;
+//!
+//! fn debug_controls(keys: Res<ButtonInput<KeyCode>>, mut events: EventWriter<TimeTravel>) {
+//!     if keys.just_pressed(KeyCode::F9) {
+//!         events.send(TimeTravel::Toggle);
+//!     }
+//!     if keys.just_pressed(KeyCode::ArrowLeft) {
+//!         events.send(TimeTravel::Step(-1));
+//!     }
+//!     if keys.just_pressed(KeyCode::ArrowRight) {
+//!         events.send(TimeTravel::Step(1));
+//!     }
+//! }
+//! ```
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+use tracing::{debug, info};
+
+use crate::client::components::Confirmed;
+use crate::client::prediction::plugin::PredictionSet;
+use crate::client::prediction::predicted_history::{ComponentState, PredictionHistory};
+use crate::client::prediction::Predicted;
+use crate::prelude::{Tick, TickManager};
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+
+/// Plugin that lets you step the predicted entities backwards and forwards in time,
+/// using the stored prediction history.
+///
+/// Control it by sending [`TimeTravel`] events, and read the current state from the [`TimeTravelState`] resource.
+#[derive(Default)]
+pub struct TimeTravelPlugin;
+
+/// Events used to control the [`TimeTravelPlugin`]
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum TimeTravel {
+    /// Pause the predicted world (and networking) at the current tick
+    Pause,
+    /// Move the predicted world by the given number of ticks (negative to go backwards).
+    /// Pauses the world if it wasn't already paused.
+    ///
+    /// It is not possible to step past the tick at which the world was paused.
+    Step(i16),
+    /// Restore the predicted world to the tick at which it was paused, and resume the simulation
+    Resume,
+    /// Pause if the world is running, resume if it is paused
+    Toggle,
+}
+
+/// Current state of the time-travel debugger
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct TimeTravelState {
+    /// Tick at which the world was paused
+    live_tick: Option<Tick>,
+    /// Tick that is currently displayed
+    tick: Option<Tick>,
+    /// True if we are restoring the live state before resuming the simulation
+    resuming: bool,
+}
+
+impl TimeTravelState {
+    /// Returns true if the predicted world is currently paused
+    pub fn is_active(&self) -> bool {
+        self.live_tick.is_some()
+    }
+
+    /// The tick at which the world was paused
+    pub fn live_tick(&self) -> Option<Tick> {
+        self.live_tick
+    }
+
+    /// The tick whose state is currently displayed
+    pub fn tick(&self) -> Option<Tick> {
+        self.tick
+    }
+}
+
+/// Returns true if the time-travel debugger is active
+pub fn is_time_travelling(state: Option<Res<TimeTravelState>>) -> bool {
+    state.is_some_and(|state| state.is_active())
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum TimeTravelSet {
+    /// Handle the [`TimeTravel`] events
+    HandleEvents,
+    /// Write the historical state of the predicted components
+    Apply,
+    /// Resume the simulation if requested
+    Resume,
+}
+
+impl Plugin for TimeTravelPlugin {
+    fn build(&self, app: &mut App) {
+        // REFLECTION
+        app.register_type::<TimeTravelState>();
+        // RESOURCES
+        app.init_resource::<TimeTravelState>();
+        // EVENTS
+        app.add_event::<TimeTravel>();
+        // SETS
+        // we run before the virtual time gets updated, so that the `FixedUpdate` schedule
+        // does not run in the frame where we pause
+        app.configure_sets(
+            First,
+            (
+                TimeTravelSet::HandleEvents,
+                TimeTravelSet::Apply,
+                TimeTravelSet::Resume,
+            )
+                .chain()
+                .before(TimeSystem),
+        );
+        // pause networking and prediction while time-travelling
+        app.configure_sets(
+            PreUpdate,
+            (
+                InternalMainSet::<ClientMarker>::Receive,
+                InternalMainSet::<ClientMarker>::EmitEvents,
+                PredictionSet::All,
+            )
+                .run_if(not(is_time_travelling)),
+        );
+        app.configure_sets(
+            PostUpdate,
+            (InternalMainSet::<ClientMarker>::Send, PredictionSet::All)
+                .run_if(not(is_time_travelling)),
+        );
+        // SYSTEMS
+        app.add_systems(
+            First,
+            (
+                handle_time_travel_events.in_set(TimeTravelSet::HandleEvents),
+                resume.in_set(TimeTravelSet::Resume),
+            ),
+        );
+    }
+}
+
+fn handle_time_travel_events(
+    tick_manager: Res<TickManager>,
+    mut time: ResMut<Time<Virtual>>,
+    mut state: ResMut<TimeTravelState>,
+    mut events: EventReader<TimeTravel>,
+) {
+    for event in events.read() {
+        let event = match event {
+            TimeTravel::Toggle if state.is_active() => TimeTravel::Resume,
+            TimeTravel::Toggle => TimeTravel::Pause,
+            event => *event,
+        };
+        match event {
+            TimeTravel::Pause | TimeTravel::Step(_) if !state.is_active() => {
+                let tick = tick_manager.tick();
+                info!(?tick, "Time-travel: pausing the predicted world");
+                time.pause();
+                state.live_tick = Some(tick);
+                state.tick = Some(tick);
+                state.resuming = false;
+                if let TimeTravel::Step(delta) = event {
+                    step(&mut state, delta);
+                }
+            }
+            TimeTravel::Step(delta) => step(&mut state, delta),
+            TimeTravel::Resume if state.is_active() => {
+                // restore the live state before resuming
+                state.tick = state.live_tick;
+                state.resuming = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn step(state: &mut TimeTravelState, delta: i16) {
+    let (Some(live_tick), Some(tick)) = (state.live_tick, state.tick) else {
+        return;
+    };
+    let mut new_tick = tick + delta;
+    // we cannot go to the future
+    if new_tick > live_tick {
+        new_tick = live_tick;
+    }
+    debug!(?new_tick, ?live_tick, "Time-travel: step");
+    state.tick = Some(new_tick);
+}
+
+fn resume(mut time: ResMut<Time<Virtual>>, mut state: ResMut<TimeTravelState>) {
+    if state.resuming {
+        info!(tick = ?state.live_tick, "Time-travel: resuming the predicted world");
+        time.unpause();
+        *state = TimeTravelState::default();
+    }
+}
+
+/// Write the state of the component `C` at the displayed tick on all predicted entities.
+///
+/// If the history doesn't go as far back as the displayed tick, the oldest state in the history is used.
+pub(crate) fn apply_time_travel<C: Component + PartialEq + Clone>(
+    mut commands: Commands,
+    state: Res<TimeTravelState>,
+    mut query: Query<
+        (Entity, Option<&mut C>, &PredictionHistory<C>),
+        (With<Predicted>, Without<Confirmed>),
+    >,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let Some(tick) = state.tick else {
+        return;
+    };
+    for (entity, component, history) in query.iter_mut() {
+        let Some(historical_state) = history
+            .buffer
+            .heap
+            .iter()
+            .filter(|item| item.key <= tick)
+            .max_by_key(|item| item.key)
+            .or_else(|| history.buffer.heap.iter().min_by_key(|item| item.key))
+            .map(|item| &item.item)
+        else {
+            continue;
+        };
+        match (historical_state, component) {
+            (ComponentState::Updated(value), Some(mut component)) => {
+                *component = value.clone();
+            }
+            (ComponentState::Updated(value), None) => {
+                commands.entity(entity).insert(value.clone());
+            }
+            (ComponentState::Removed, Some(_)) => {
+                commands.entity(entity).remove::<C>();
+            }
+            (ComponentState::Removed, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::NetworkTarget;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_time_travel() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.add_plugins(TimeTravelPlugin);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                ComponentSyncModeFull(0.0),
+                Replicate {
+                    sync: SyncTarget {
+                        prediction: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let predicted = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<Entity, With<Predicted>>()
+            .single(stepper.client_app.world());
+
+        // update the predicted component at every tick
+        stepper.client_app.add_systems(
+            FixedUpdate,
+            |mut query: Query<&mut ComponentSyncModeFull, With<Predicted>>| {
+                for mut component in query.iter_mut() {
+                    component.0 += 1.0;
+                }
+            },
+        );
+        stepper.frame_step();
+        stepper.frame_step();
+        let live_value = stepper
+            .client_app
+            .world()
+            .get::<ComponentSyncModeFull>(predicted)
+            .unwrap()
+            .0;
+
+        // step backwards
+        stepper
+            .client_app
+            .world_mut()
+            .send_event(TimeTravel::Step(-1));
+        stepper.frame_step();
+        let state = stepper.client_app.world().resource::<TimeTravelState>();
+        assert!(state.is_active());
+        assert_eq!(state.tick(), state.live_tick().map(|t| t - 1));
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(predicted)
+                .unwrap()
+                .0,
+            live_value - 1.0
+        );
+
+        // stepping past the live tick is not possible
+        stepper
+            .client_app
+            .world_mut()
+            .send_event(TimeTravel::Step(5));
+        stepper.frame_step();
+        let state = stepper.client_app.world().resource::<TimeTravelState>();
+        assert_eq!(state.tick(), state.live_tick());
+
+        // resume restores the live state
+        stepper
+            .client_app
+            .world_mut()
+            .send_event(TimeTravel::Step(-1));
+        stepper.frame_step();
+        stepper
+            .client_app
+            .world_mut()
+            .send_event(TimeTravel::Resume);
+        stepper.frame_step();
+        assert!(!stepper
+            .client_app
+            .world()
+            .resource::<TimeTravelState>()
+            .is_active());
+        assert!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(predicted)
+                .unwrap()
+                .0
+                >= live_value
+        );
+        assert!(!stepper
+            .client_app
+            .world()
+            .resource::<Time<Virtual>>()
+            .is_paused());
+    }
+}
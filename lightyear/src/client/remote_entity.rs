@@ -0,0 +1,187 @@
+//! Stable references to server entities on the client.
+//!
+//! Entities that are sent in messages are usually mapped to the client's local entities via [`MapEntities`](bevy::ecs::entity::MapEntities).
+//! However the mapping only works if the entity has already been replicated to the client when the message is received.
+//!
+//! Instead, you can send the server entity wrapped in a [`RemoteEntity`], which is never mapped, and resolve it to a local
+//! entity whenever you need it with the [`RemoteEntityMapService`].
+//! The [`RemoteEntityMapped`] and [`RemoteEntityUnmapped`] events are emitted when a [`RemoteEntity`] starts or stops
+//! resolving to a local entity, so that UI and gameplay systems can update their references.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::client::*;
+//!
+//! #[derive(Resource)]
+//! struct Target(RemoteEntity);
+//!
+//! fn highlight_target(target: Res<Target>, service: RemoteEntityMapService, mut commands: Commands) {
+//!     if let Some(entity) = service.resolve(target.0) {
+//!         commands.entity(entity).insert(Highlighted);
+//!     }
+//! }
+//! ```
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::client::config::ClientConfig;
+use crate::client::connection::ConnectionManager;
+use crate::prelude::Mode;
+use crate::shared::replication::entity_map::EntityMapChange;
+
+/// An entity of the server's world.
+///
+/// Unlike a regular [`Entity`], it is not mapped to the client's world when it is received in a message;
+/// use the [`RemoteEntityMapService`] to get the corresponding local entity.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Reflect,
+)]
+pub struct RemoteEntity(pub Entity);
+
+impl From<Entity> for RemoteEntity {
+    fn from(entity: Entity) -> Self {
+        Self(entity)
+    }
+}
+
+/// Event emitted when a [`RemoteEntity`] gets replicated to the client and starts resolving to a local entity
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteEntityMapped {
+    pub remote: RemoteEntity,
+    pub local: Entity,
+}
+
+/// Event emitted when a [`RemoteEntity`] stops resolving to a local entity (for example because
+/// the entity was despawned on the server or is not relevant to the client anymore)
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteEntityUnmapped {
+    pub remote: RemoteEntity,
+    pub local: Entity,
+}
+
+/// [`SystemParam`] to convert between [`RemoteEntity`] and local entities
+#[derive(SystemParam)]
+pub struct RemoteEntityMapService<'w> {
+    config: Res<'w, ClientConfig>,
+    connection: Res<'w, ConnectionManager>,
+}
+
+impl RemoteEntityMapService<'_> {
+    /// Get the local entity corresponding to the server entity, if it has been replicated to the client
+    pub fn resolve(&self, remote: RemoteEntity) -> Option<Entity> {
+        // in host-server mode, the client and the server share the same world
+        if self.config.shared.mode == Mode::HostServer {
+            return Some(remote.0);
+        }
+        self.connection
+            .replication_receiver
+            .remote_entity_map
+            .get_local(remote.0)
+    }
+
+    /// Get the server entity corresponding to a local entity that was replicated from the server
+    pub fn to_remote(&self, local: Entity) -> Option<RemoteEntity> {
+        if self.config.shared.mode == Mode::HostServer {
+            return Some(RemoteEntity(local));
+        }
+        self.connection
+            .replication_receiver
+            .remote_entity_map
+            .get_remote(local)
+            .map(RemoteEntity)
+    }
+}
+
+/// Emit the [`RemoteEntityMapped`] and [`RemoteEntityUnmapped`] events from the changes to the entity map
+pub(crate) fn emit_remote_entity_events(
+    mut connection: ResMut<ConnectionManager>,
+    mut mapped: EventWriter<RemoteEntityMapped>,
+    mut unmapped: EventWriter<RemoteEntityUnmapped>,
+) {
+    if connection
+        .replication_receiver
+        .remote_entity_map
+        .changes
+        .is_empty()
+    {
+        return;
+    }
+    for change in connection
+        .replication_receiver
+        .remote_entity_map
+        .changes
+        .drain(..)
+    {
+        match change {
+            EntityMapChange::Inserted { remote, local } => {
+                mapped.send(RemoteEntityMapped {
+                    remote: RemoteEntity(remote),
+                    local,
+                });
+            }
+            EntityMapChange::Removed { remote, local } => {
+                unmapped.send(RemoteEntityUnmapped {
+                    remote: RemoteEntity(remote),
+                    local,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+
+    fn resolve(stepper: &mut BevyStepper, remote: RemoteEntity) -> Option<Entity> {
+        stepper
+            .client_app
+            .world_mut()
+            .run_system_once_with(remote, |In(remote), service: RemoteEntityMapService| {
+                service.resolve(remote)
+            })
+    }
+
+    #[test]
+    fn test_resolve_remote_entity() {
+        let mut stepper = BevyStepper::default();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(1.0), Replicate::default()))
+            .id();
+        let remote = RemoteEntity(server_entity);
+        assert_eq!(resolve(&mut stepper, remote), None);
+
+        stepper.frame_step();
+        stepper.frame_step();
+        let local = resolve(&mut stepper, remote).expect("entity was not replicated");
+        let events = stepper
+            .client_app
+            .world()
+            .resource::<Events<RemoteEntityMapped>>();
+        assert_eq!(
+            events.get_reader().read(events).collect::<Vec<_>>(),
+            vec![&RemoteEntityMapped { remote, local }]
+        );
+
+        stepper.server_app.world_mut().despawn(server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(resolve(&mut stepper, remote), None);
+        let events = stepper
+            .client_app
+            .world()
+            .resource::<Events<RemoteEntityUnmapped>>();
+        assert_eq!(
+            events.get_reader().read(events).collect::<Vec<_>>(),
+            vec![&RemoteEntityUnmapped { remote, local }]
+        );
+    }
+}
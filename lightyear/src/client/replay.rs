@@ -0,0 +1,360 @@
+//! Record the replication messages received from the server, and play them back later.
+//!
+//! In [`ReplayMode::Record`], every replication message (entity actions and updates) received by the client
+//! is stored with the server tick at which it was sent. The recording can be saved to a file with
+//! [`Replay::save`].
+//!
+//! In [`ReplayMode::Playback`], the client does not need to be connected: the recorded messages are fed to the
+//! replication receiver at the rate at which they were recorded, and applied to the World through the normal
+//! replication receive path. This can be used to watch a match again, or for spectators.
+//! Only the replicated (`Confirmed`) entities are recreated; there is no prediction or interpolation during
+//! playback, and the replication events are not emitted.
+//!
+//! A replay can also be played in the server World, to re-broadcast it to connected clients
+//! (see [`ServerReplay`](crate::server::replay::ServerReplay)).
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::client::*;
+//!
+//! // record
+//! app.add_plugins(ReplayPlugin { mode: ReplayMode::Record });
+//! // ... after the match
+//! app.world().resource::<ReplayRecorder>().replay().save("match.replay")?;
+//!
+//! // playback
+//! let replay = Replay::load("match.replay")?;
+//! app.add_plugins(ReplayPlugin { mode: ReplayMode::Playback(replay) });
+//! ```
+use std::path::Path;
+
+use bevy::prelude::*;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use tracing::error;
+
+use crate::client::connection::ConnectionManager;
+use crate::client::run_conditions::is_disconnected;
+use crate::prelude::{ComponentRegistry, Tick, TickManager};
+use crate::serialize::reader::Reader;
+use crate::serialize::{SerializationError, ToBytes};
+use crate::shared::events::connection::ConnectionEvents;
+use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage};
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+
+/// Type of a recorded replication message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReplayMessageKind {
+    /// Entity actions received on a reliable channel
+    Actions = 0,
+    /// Entity actions received on a sequenced channel
+    SequencedActions = 1,
+    /// Entity updates
+    Updates = 2,
+}
+
+/// A replication message received from the server, along with the server tick at which it was sent
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ReplayFrame {
+    pub(crate) tick: Tick,
+    pub(crate) kind: ReplayMessageKind,
+    pub(crate) data: Bytes,
+}
+
+impl ToBytes for ReplayFrame {
+    fn len(&self) -> usize {
+        self.tick.len() + 1 + ToBytes::len(&self.data)
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        self.tick.to_bytes(buffer)?;
+        buffer.write_u8(self.kind as u8)?;
+        self.data.to_bytes(buffer)?;
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        let tick = Tick::from_bytes(buffer)?;
+        let kind = match buffer.read_u8()? {
+            0 => ReplayMessageKind::Actions,
+            1 => ReplayMessageKind::SequencedActions,
+            2 => ReplayMessageKind::Updates,
+            _ => return Err(SerializationError::InvalidValue),
+        };
+        let data = Bytes::from_bytes(buffer)?;
+        Ok(Self { tick, kind, data })
+    }
+}
+
+/// The replication messages received during a session, in the order in which they were received
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    pub(crate) frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    /// Number of recorded replication messages
+    pub fn num_messages(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Write the replay to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SerializationError> {
+        let mut buffer = Vec::with_capacity(ToBytes::len(self));
+        self.to_bytes(&mut buffer)?;
+        std::fs::write(path, buffer)?;
+        Ok(())
+    }
+
+    /// Read a replay from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SerializationError> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&mut Reader::from(bytes))
+    }
+}
+
+impl ToBytes for Replay {
+    fn len(&self) -> usize {
+        ToBytes::len(&self.frames)
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        self.frames.to_bytes(buffer)
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            frames: Vec::from_bytes(buffer)?,
+        })
+    }
+}
+
+/// Resource that records the replication messages received by the client
+#[derive(Resource, Debug, Default)]
+pub struct ReplayRecorder {
+    replay: Replay,
+}
+
+impl ReplayRecorder {
+    /// The messages recorded so far
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Take the messages recorded so far, and start a new recording
+    pub fn take(&mut self) -> Replay {
+        std::mem::take(&mut self.replay)
+    }
+
+    pub(crate) fn record(&mut self, tick: Tick, kind: ReplayMessageKind, data: Bytes) {
+        self.replay.frames.push(ReplayFrame { tick, kind, data });
+    }
+}
+
+/// Resource that plays back a [`Replay`]
+#[derive(Resource, Debug)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// Index of the next frame to play
+    cursor: usize,
+    /// Server tick that is currently being played
+    playback_tick: Option<Tick>,
+    /// Local tick during the previous playback update
+    last_local_tick: Option<Tick>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            cursor: 0,
+            playback_tick: None,
+            last_local_tick: None,
+        }
+    }
+
+    /// Returns true if all the messages of the replay have been played
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.frames.len()
+    }
+
+    /// The server tick that is currently being played
+    pub fn playback_tick(&self) -> Option<Tick> {
+        self.playback_tick
+    }
+}
+
+/// Whether the [`ReplayPlugin`] records the replication messages or plays them back
+#[derive(Debug, Clone)]
+pub enum ReplayMode {
+    /// Record the replication messages received from the server in the [`ReplayRecorder`] resource
+    Record,
+    /// Play back a [`Replay`] without being connected to a server
+    Playback(Replay),
+}
+
+/// Plugin to record or play back the replication messages received from the server
+#[derive(Debug, Clone)]
+pub struct ReplayPlugin {
+    pub mode: ReplayMode,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match &self.mode {
+            ReplayMode::Record => {
+                app.init_resource::<ReplayRecorder>();
+            }
+            ReplayMode::Playback(replay) => {
+                app.insert_resource(ReplayPlayer::new(replay.clone()));
+                app.add_systems(
+                    PreUpdate,
+                    play_replay
+                        .after(InternalMainSet::<ClientMarker>::EmitEvents)
+                        .run_if(is_disconnected),
+                );
+            }
+        }
+    }
+}
+
+/// Feed the recorded messages whose tick has been reached to the replication receiver, and apply them to the World
+fn play_replay(world: &mut World) {
+    let local_tick = world.resource::<TickManager>().tick();
+    world.resource_scope(|world, mut player: Mut<ReplayPlayer>| {
+        let Some(first_tick) = player.replay.frames.first().map(|frame| frame.tick) else {
+            return;
+        };
+        // advance the playback by the number of ticks that elapsed locally
+        let elapsed = player
+            .last_local_tick
+            .map_or(0, |last_local_tick| local_tick - last_local_tick);
+        player.last_local_tick = Some(local_tick);
+        let playback_tick = player
+            .playback_tick
+            .map_or(first_tick, |playback_tick| playback_tick + elapsed);
+        player.playback_tick = Some(playback_tick);
+
+        world.resource_scope(|world, mut connection: Mut<ConnectionManager>| {
+            let connection = connection.as_mut();
+            while let Some(frame) = player.replay.frames.get(player.cursor) {
+                if frame.tick > playback_tick {
+                    break;
+                }
+                let mut reader = Reader::from(frame.data.clone());
+                let result = match frame.kind {
+                    ReplayMessageKind::Actions => EntityActionsMessage::from_bytes(&mut reader)
+                        .map(|actions| {
+                            connection
+                                .replication_receiver
+                                .recv_actions(actions, frame.tick)
+                        }),
+                    ReplayMessageKind::SequencedActions => {
+                        EntityActionsMessage::from_bytes(&mut reader).map(|actions| {
+                            connection
+                                .replication_receiver
+                                .recv_sequenced_actions(actions, frame.tick)
+                        })
+                    }
+                    ReplayMessageKind::Updates => EntityUpdatesMessage::from_bytes(&mut reader)
+                        .map(|updates| {
+                            connection
+                                .replication_receiver
+                                .recv_updates(updates, frame.tick)
+                        }),
+                };
+                if let Err(e) = result {
+                    error!(tick = ?frame.tick, "Could not read replay message: {:?}", e);
+                }
+                player.cursor += 1;
+            }
+            world.resource_scope(|world, registry: Mut<ComponentRegistry>| {
+                // the replication events are not emitted during playback
+                let mut events = ConnectionEvents::default();
+                connection.replication_receiver.apply_world(
+                    world,
+                    None,
+                    &registry,
+                    playback_tick,
+                    &mut events,
+                );
+            });
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client::ClientConfig;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{Replicated, SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_record_and_playback() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<ReplayRecorder>();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(server_entity)
+            .unwrap()
+            .0 = 2.0;
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let replay = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ReplayRecorder>()
+            .take();
+        assert!(replay.num_messages() >= 2);
+        let mut bytes = Vec::new();
+        replay.to_bytes(&mut bytes).unwrap();
+        let replay = Replay::from_bytes(&mut Reader::from(bytes)).unwrap();
+
+        // play the replay in a client that is not connected
+        let mut playback = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(stepper.tick_duration),
+                ..Default::default()
+            },
+            ClientConfig::default(),
+            stepper.frame_duration,
+        );
+        playback.client_app.add_plugins(ReplayPlugin {
+            mode: ReplayMode::Playback(replay),
+        });
+        playback.build();
+        for _ in 0..10 {
+            playback.frame_step();
+        }
+        assert!(playback
+            .client_app
+            .world()
+            .resource::<ReplayPlayer>()
+            .is_finished());
+        let mut query = playback
+            .client_app
+            .world_mut()
+            .query_filtered::<&ComponentSyncModeFull, With<Replicated>>();
+        assert_eq!(
+            query.single(playback.client_app.world()),
+            &ComponentSyncModeFull(2.0)
+        );
+    }
+}
@@ -9,12 +9,17 @@ use crate::shared::sets::{ClientMarker, InternalReplicationSet};
 
 pub(crate) mod receive {
     use super::*;
+    use crate::client::remote_entity::{
+        emit_remote_entity_events, RemoteEntityMapped, RemoteEntityUnmapped,
+    };
     use crate::prelude::client::MessageEvent;
     use crate::prelude::{
         client::{is_connected, is_synced},
         is_host_server,
     };
     use crate::shared::replication::authority::{AuthorityChange, HasAuthority};
+    use crate::shared::replication::tombstone::{emit_tombstone_events, ComponentTombstoneEvent};
+    use crate::shared::replication::DespawnGroupsMessage;
     use crate::shared::sets::InternalMainSet;
 
     #[derive(Default)]
@@ -43,13 +48,31 @@ pub(crate) mod receive {
                 ),
             );
 
+            // EVENTS
+            app.add_event::<RemoteEntityMapped>();
+            app.add_event::<RemoteEntityUnmapped>();
+
             app.add_systems(
                 PreUpdate,
-                handle_authority_change.after(InternalMainSet::<ClientMarker>::EmitEvents),
+                (
+                    (handle_authority_change, handle_despawn_groups),
+                    emit_remote_entity_events,
+                )
+                    .chain()
+                    .after(InternalMainSet::<ClientMarker>::EmitEvents),
             );
         }
     }
 
+    /// Register the [`ComponentTombstoneEvent<C>`] that is emitted when a tombstone for `C` is received
+    pub(crate) fn register_tombstone_events<C: Component>(app: &mut App) {
+        app.add_event::<ComponentTombstoneEvent<C>>();
+        app.add_systems(
+            PreUpdate,
+            emit_tombstone_events::<C>.after(InternalMainSet::<ClientMarker>::EmitEvents),
+        );
+    }
+
     /// Apply authority changes requested by the server
     // TODO: use observer to handle these?
     fn handle_authority_change(
@@ -67,6 +90,31 @@ pub(crate) mod receive {
             }
         }
     }
+
+    /// Despawn all the entities of the replication groups that were despawned at once by the server
+    fn handle_despawn_groups(world: &mut World) {
+        world.resource_scope(
+            |world, mut messages: Mut<Events<MessageEvent<DespawnGroupsMessage>>>| {
+                if messages.is_empty() {
+                    return;
+                }
+                world.resource_scope(|world, mut manager: Mut<ConnectionManager>| {
+                    let manager = manager.as_mut();
+                    for message in messages.drain() {
+                        for (group_id, next_message_id) in message.message.groups {
+                            manager.replication_receiver.despawn_group(
+                                group_id,
+                                next_message_id,
+                                message.message.tick,
+                                world,
+                                &mut manager.events,
+                            );
+                        }
+                    }
+                });
+            },
+        );
+    }
 }
 
 pub(crate) mod send {
@@ -77,11 +125,13 @@ pub(crate) mod send {
 
     use crate::prelude::client::{ClientConfig, NetClient};
 
+    use crate::channel::builder::EntityActionsChannel;
     use crate::prelude::{
         client::{is_connected, is_synced},
         is_host_server, ComponentRegistry, DisabledComponent, ReplicateHierarchy, Replicated,
         ReplicationGroup, TargetEntity, Tick, TickManager, TimeManager,
     };
+    use crate::protocol::channel::ChannelKind;
     use crate::protocol::component::ComponentKind;
 
     use crate::shared::replication::components::{Replicating, ReplicationGroupId};
@@ -285,6 +335,10 @@ pub(crate) mod send {
                     g.group_id(Some(entity.id()))
                 });
                 let priority = group.map_or(1.0, |g| g.priority());
+                let actions_channel = group.map_or(
+                    ChannelKind::of::<EntityActionsChannel>(),
+                    ReplicationGroup::actions_channel,
+                );
                 let target_entity = entity_ref.get::<TargetEntity>();
                 // SAFETY: we know that the entity has the ReplicationTarget component
                 // because the archetype is in replicated_archetypes
@@ -317,6 +371,7 @@ pub(crate) mod send {
                         entity.id(),
                         group_id,
                         priority,
+                        actions_channel,
                         target_entity,
                         &mut sender,
                     );
@@ -376,6 +431,7 @@ pub(crate) mod send {
         entity: Entity,
         group_id: ReplicationGroupId,
         priority: f32,
+        actions_channel: ChannelKind,
         target_entity: Option<&TargetEntity>,
         sender: &mut ConnectionManager,
     ) {
@@ -389,10 +445,13 @@ pub(crate) mod send {
                 .replication_sender
                 .prepare_entity_spawn(entity, group_id);
         }
-        // also set the priority for the group when we spawn it
+        // also set the priority and the actions channel for the group when we spawn it
         sender
             .replication_sender
             .update_base_priority(group_id, priority);
+        sender
+            .replication_sender
+            .update_actions_channel(group_id, actions_channel);
     }
 
     /// Send entity despawn if:
@@ -444,6 +503,14 @@ pub(crate) mod send {
         system_ticks: &SystemChangeTick,
         sender: &mut ConnectionManager,
     ) -> Result<(), ReplicationError> {
+        // do not send the components of protocol extensions that the server did not accept
+        if component_registry
+            .kind_map
+            .net_id(&component_kind)
+            .is_some_and(|net_id| !sender.is_net_id_enabled(*net_id))
+        {
+            return Ok(());
+        }
         let (mut insert, mut update) = (false, false);
 
         // send a component_insert for components that were newly added
@@ -514,12 +581,7 @@ pub(crate) mod send {
                     .prepare_component_insert(entity, group_id, raw_data);
             } else {
                 trace!(?entity, "send update");
-                let send_tick = sender
-                    .replication_sender
-                    .group_channels
-                    .entry(group_id)
-                    .or_default()
-                    .send_tick;
+                let send_tick = sender.replication_sender.gather_changes_since(group_id);
 
                 // send the update for all changes newer than the last send bevy tick for the group
                 if send_tick.map_or(true, |c| {
@@ -564,9 +626,15 @@ pub(crate) mod send {
                             ),
                         )?;
                         let raw_data = writer.split();
-                        sender
-                            .replication_sender
-                            .prepare_component_update(entity, group_id, raw_data);
+                        if component_registry.has_reliable_updates(component_kind) {
+                            sender
+                                .replication_sender
+                                .prepare_reliable_component_update(entity, group_id, raw_data);
+                        } else {
+                            sender
+                                .replication_sender
+                                .prepare_component_update(entity, group_id, raw_data);
+                        }
                     }
                 }
             }
@@ -599,6 +667,9 @@ pub(crate) mod send {
             let group_id = group.group_id(Some(entity));
             trace!(?entity, kind = ?std::any::type_name::<C>(), "Sending RemoveComponent");
             let kind = registry.net_id::<C>();
+            if !sender.is_net_id_enabled(kind) {
+                return;
+            }
             sender
                 .replication_sender
                 .prepare_component_remove(entity, group_id, kind);
@@ -45,6 +45,23 @@ pub struct SyncConfig {
     // TODO: instead of constant speedup_factor, the speedup should be linear w.r.t the offset
     /// By how much should we speed up the simulation to make ticks stay in sync with server?
     pub speedup_factor: f32,
+    /// Minimum relative speed of the simulation when it is slowed down to stay in sync with the server
+    pub min_relative_speed: f32,
+    /// Maximum relative speed of the simulation when it is sped up to stay in sync with the server
+    ///
+    /// Large speed changes scale the delta time of the simulation, which can destabilize physics.
+    pub max_relative_speed: f32,
+    /// If set, the relative speed of the simulation changes progressively, by at most this amount per second
+    pub max_relative_speed_change: Option<f32>,
+    /// If set, when the prediction time is off by more than this number of ticks (but less than
+    /// `max_error_margin`), a tick is skipped or played twice instead of changing the speed of the simulation
+    pub tick_skip_margin: Option<f32>,
+    /// If set, the interpolation timeline catches up by playing faster (at most at this speed ratio)
+    /// when it is too far behind its objective, for example right after the initial replication,
+    /// instead of snapping to the objective.
+    ///
+    /// The interpolation timeline still snaps if it is too far ahead.
+    pub interpolation_catch_up_speed: Option<f32>,
 
     // Integration
     pub server_time_estimate_smoothing: f32,
@@ -59,6 +76,11 @@ impl Default for SyncConfig {
             error_margin: 0.5,
             max_error_margin: 5.0,
             speedup_factor: 1.05,
+            min_relative_speed: 0.8,
+            max_relative_speed: 1.25,
+            max_relative_speed_change: None,
+            tick_skip_margin: None,
+            interpolation_catch_up_speed: None,
             // server_time_estimate_smoothing: 0.0,
             server_time_estimate_smoothing: 0.2,
         }
@@ -70,6 +92,27 @@ impl SyncConfig {
         self.speedup_factor = speedup_factor;
         self
     }
+
+    pub fn relative_speed_range(mut self, min_speed: f32, max_speed: f32) -> Self {
+        self.min_relative_speed = min_speed;
+        self.max_relative_speed = max_speed;
+        self
+    }
+
+    pub fn max_relative_speed_change(mut self, max_change_per_second: f32) -> Self {
+        self.max_relative_speed_change = Some(max_change_per_second);
+        self
+    }
+
+    pub fn tick_skip_margin(mut self, margin_ticks: f32) -> Self {
+        self.tick_skip_margin = Some(margin_ticks);
+        self
+    }
+
+    pub fn interpolation_catch_up_speed(mut self, max_speed: f32) -> Self {
+        self.interpolation_catch_up_speed = Some(max_speed);
+        self
+    }
 }
 
 #[derive(Default)]
@@ -98,6 +141,12 @@ pub struct SyncManager {
     server_time_estimate: WrappedTime,
     pub(crate) interpolation_time: WrappedTime,
     interpolation_speed_ratio: f32,
+    /// True if the interpolation timeline is catching up with its objective
+    interpolation_catching_up: bool,
+    /// Interpolation delay that the interpolation timeline is converging to
+    interpolation_delay_objective: Option<Duration>,
+    /// True if the interpolation delay was changed and the timeline is moving progressively to the new delay
+    interpolation_reconverging: bool,
 
     // ticks
     // TODO: see if this is correct; should we instead attach the tick on every update message?
@@ -124,6 +173,9 @@ impl SyncManager {
             server_time_estimate: WrappedTime::default(),
             interpolation_time: WrappedTime::default(),
             interpolation_speed_ratio: 1.0,
+            interpolation_catching_up: false,
+            interpolation_delay_objective: None,
+            interpolation_reconverging: false,
             // server tick
             latest_received_server_tick: None,
             duration_since_latest_received_server_tick: Duration::default(),
@@ -346,6 +398,13 @@ impl SyncManager {
         self.server_time_estimate() - objective_delta
     }
 
+    /// Current delay between the estimated server time and the interpolation time
+    pub(crate) fn interpolation_delay(&self) -> Duration {
+        (self.server_time_estimate() - self.interpolation_time)
+            .to_std()
+            .unwrap_or_default()
+    }
+
     pub(crate) fn interpolation_tick(&self, tick_manager: &TickManager) -> Tick {
         self.interpolation_time
             .to_tick(tick_manager.config.tick_duration)
@@ -371,6 +430,20 @@ impl SyncManager {
         let objective_time =
             self.interpolation_objective(interpolation_delay, server_update_rate, tick_manager);
         let delta = objective_time - self.interpolation_time;
+        // the interpolation delay can be changed at runtime; instead of snapping to the new objective
+        // we move the timeline progressively to avoid visual jumps
+        let objective_delay = interpolation_delay.to_duration(server_update_rate);
+        if self
+            .interpolation_delay_objective
+            .is_some_and(|delay| delay != objective_delay)
+        {
+            debug!(
+                ?objective_delay,
+                "Interpolation delay changed, re-converging"
+            );
+            self.interpolation_reconverging = true;
+        }
+        self.interpolation_delay_objective = Some(objective_delay);
         trace!(
             ?objective_time,
             interpolation_time = ?self.interpolation_time,
@@ -384,6 +457,36 @@ impl SyncManager {
                 .mul_f32(self.config.max_error_margin),
         )
         .unwrap();
+        // TODO: make this configurable
+        let error_margin = chrono::Duration::milliseconds(10);
+        if let Some(max_speed) = self.config.interpolation_catch_up_speed {
+            if delta > max_error_margin_time
+                || (self.interpolation_catching_up && delta > error_margin)
+            {
+                // interpolation time is far behind: play the buffer faster until we reach the objective,
+                // without overshooting it during the next tick
+                let no_overshoot_speed = 1.0
+                    + delta.to_std().unwrap_or_default().as_secs_f32()
+                        / tick_manager.config.tick_duration.as_secs_f32();
+                self.interpolation_speed_ratio = max_speed.min(no_overshoot_speed).max(1.0);
+                if !self.interpolation_catching_up {
+                    debug!(?delta, "Interpolation time is too far behind, catching up");
+                }
+                self.interpolation_catching_up = true;
+                return;
+            }
+            self.interpolation_catching_up = false;
+        }
+        if self.interpolation_reconverging {
+            if delta > error_margin {
+                self.interpolation_speed_ratio = self.config.speedup_factor;
+                return;
+            } else if delta < -error_margin {
+                self.interpolation_speed_ratio = 1.0 / self.config.speedup_factor;
+                return;
+            }
+            self.interpolation_reconverging = false;
+        }
         if delta > max_error_margin_time || delta < -max_error_margin_time {
             debug!(
                 ?objective_time,
@@ -394,8 +497,6 @@ impl SyncManager {
             return;
         }
 
-        // TODO: make this configurable
-        let error_margin = chrono::Duration::milliseconds(10);
         if delta > error_margin {
             // interpolation time is too far behind, speed-up!
             self.interpolation_speed_ratio = 1.0 * self.config.speedup_factor;
@@ -466,7 +567,37 @@ impl SyncManager {
             return self.finalize(time_manager, tick_manager, ping_manager);
         }
 
-        time_manager.sync_relative_speed = if error > error_margin_time {
+        // skip or replay a tick instead of changing the speed of the simulation too much
+        if let Some(tick_skip_margin) = self.config.tick_skip_margin {
+            let tick_skip_margin_time = chrono::Duration::from_std(
+                tick_manager.config.tick_duration.mul_f32(tick_skip_margin),
+            )
+            .unwrap();
+            let new_tick = if error > tick_skip_margin_time {
+                Some(tick_manager.tick() - 1)
+            } else if error < -tick_skip_margin_time {
+                Some(tick_manager.tick() + 1)
+            } else {
+                None
+            };
+            if let Some(new_tick) = new_tick {
+                debug!(
+                    ?rtt,
+                    client_tick = ?tick_manager.tick(),
+                    ?new_tick,
+                    error_ms = ?error.num_milliseconds(),
+                    "Error above the tick skip margin, skipping/replaying a tick",
+                );
+                time_manager.sync_relative_speed = self.relative_speed(
+                    time_manager.sync_relative_speed,
+                    1.0,
+                    time_manager.delta(),
+                );
+                return Some(tick_manager.set_tick_to(new_tick));
+            }
+        }
+
+        let target_speed = if error > error_margin_time {
             debug!(
                 ?rtt,
                 ?jitter,
@@ -499,9 +630,29 @@ impl SyncManager {
             trace!("good speed");
             1.0
         };
+        time_manager.sync_relative_speed = self.relative_speed(
+            time_manager.sync_relative_speed,
+            target_speed,
+            time_manager.delta(),
+        );
         None
     }
 
+    /// Move the relative speed of the simulation towards the `target_speed`, within the limits of the [`SyncConfig`]
+    fn relative_speed(&self, current_speed: f32, target_speed: f32, delta: Duration) -> f32 {
+        let target_speed = target_speed.clamp(
+            self.config.min_relative_speed,
+            self.config.max_relative_speed,
+        );
+        match self.config.max_relative_speed_change {
+            Some(max_change) => {
+                let max_step = max_change * delta.as_secs_f32();
+                current_speed + (target_speed - current_speed).clamp(-max_step, max_step)
+            }
+            None => target_speed,
+        }
+    }
+
     // Update internal time using offset so that times are synced.
     // This happens when a necessary # of handshake pongs have been recorded
     // Compute the final RTT/offset and set the client tick accordingly
@@ -565,6 +716,7 @@ mod tests {
     use crate::prelude::server::Replicate;
     use crate::prelude::*;
     use crate::server::events::InputEvent;
+    use crate::shared::ping::manager::{FinalStats, PingConfig};
     use crate::tests::protocol::*;
     use crate::tests::stepper::BevyStepper;
 
@@ -587,6 +739,190 @@ mod tests {
         }
     }
 
+    /// Check that the interpolation timeline catches up progressively instead of snapping
+    /// when it is far behind its objective
+    #[test]
+    fn test_interpolation_catch_up() {
+        let tick_duration = Duration::from_millis(10);
+        let tick_manager = TickManager::from_config(TickConfig::new(tick_duration));
+        let interpolation_delay = InterpolationDelay::default();
+        let mut sync_manager = SyncManager::new(
+            SyncConfig::default().interpolation_catch_up_speed(2.0),
+            PredictionConfig::default(),
+        );
+        sync_manager.synced = true;
+        sync_manager.server_time_estimate = WrappedTime::from_duration(Duration::from_secs(10));
+        let objective = sync_manager.interpolation_objective(
+            &interpolation_delay,
+            tick_duration,
+            &tick_manager,
+        );
+        // the interpolation timeline starts 1 second behind its objective
+        sync_manager.interpolation_time = objective - chrono::Duration::seconds(1);
+
+        sync_manager.update_interpolation_time(&interpolation_delay, tick_duration, &tick_manager);
+        // no snapping, the interpolation timeline is played faster instead
+        assert_eq!(
+            sync_manager.interpolation_time,
+            objective - chrono::Duration::seconds(1)
+        );
+        assert_eq!(sync_manager.interpolation_speed_ratio, 2.0);
+        assert!(sync_manager.interpolation_catching_up);
+
+        // advance time until the timeline has caught up
+        for _ in 0..200 {
+            sync_manager.server_time_estimate += tick_duration;
+            sync_manager.interpolation_time +=
+                tick_duration.mul_f32(sync_manager.interpolation_speed_ratio);
+            sync_manager.update_interpolation_time(
+                &interpolation_delay,
+                tick_duration,
+                &tick_manager,
+            );
+        }
+        assert!(!sync_manager.interpolation_catching_up);
+        let objective = sync_manager.interpolation_objective(
+            &interpolation_delay,
+            tick_duration,
+            &tick_manager,
+        );
+        assert!(
+            (objective - sync_manager.interpolation_time)
+                .num_milliseconds()
+                .abs()
+                <= 10
+        );
+    }
+
+    /// Check that changing the interpolation delay at runtime moves the interpolation timeline progressively
+    #[test]
+    fn test_interpolation_delay_change() {
+        let tick_duration = Duration::from_millis(10);
+        let tick_manager = TickManager::from_config(TickConfig::new(tick_duration));
+        let mut sync_manager = SyncManager::new(SyncConfig::default(), PredictionConfig::default());
+        sync_manager.synced = true;
+        sync_manager.server_time_estimate = WrappedTime::from_duration(Duration::from_secs(10));
+        let interpolation_delay = InterpolationDelay::default();
+        sync_manager.interpolation_time = sync_manager.interpolation_objective(
+            &interpolation_delay,
+            tick_duration,
+            &tick_manager,
+        );
+        sync_manager.update_interpolation_time(&interpolation_delay, tick_duration, &tick_manager);
+        assert_eq!(sync_manager.interpolation_speed_ratio, 1.0);
+
+        // the new delay is much bigger than the max error margin, but we don't snap
+        let interpolation_delay = interpolation_delay.with_min_delay(Duration::from_millis(200));
+        let previous_time = sync_manager.interpolation_time;
+        sync_manager.update_interpolation_time(&interpolation_delay, tick_duration, &tick_manager);
+        assert_eq!(sync_manager.interpolation_time, previous_time);
+        assert_eq!(sync_manager.interpolation_speed_ratio, 1.0 / 1.05);
+        assert!(sync_manager.interpolation_reconverging);
+
+        for _ in 0..1000 {
+            sync_manager.server_time_estimate += tick_duration;
+            sync_manager.interpolation_time +=
+                tick_duration.mul_f32(sync_manager.interpolation_speed_ratio);
+            sync_manager.update_interpolation_time(
+                &interpolation_delay,
+                tick_duration,
+                &tick_manager,
+            );
+        }
+        assert!(!sync_manager.interpolation_reconverging);
+        assert!(
+            (sync_manager.interpolation_delay().as_millis() as i64 - 200).abs() <= 10,
+            "{:?}",
+            sync_manager.interpolation_delay()
+        );
+    }
+
+    /// Return a SyncManager that is synced with a server at tick 1000, with the given RTT
+    fn synced_manager(
+        config: SyncConfig,
+        rtt: Duration,
+    ) -> (SyncManager, TimeManager, TickManager, PingManager) {
+        let tick_duration = Duration::from_millis(10);
+        let mut tick_manager = TickManager::from_config(TickConfig::new(tick_duration));
+        let mut time_manager = TimeManager::default();
+        time_manager.update(Duration::from_millis(16));
+        let mut ping_manager = PingManager::new(PingConfig::default());
+        ping_manager.final_stats = FinalStats {
+            rtt,
+            jitter: Duration::default(),
+        };
+        let mut sync_manager = SyncManager::new(config, PredictionConfig::default());
+        sync_manager.synced = true;
+        sync_manager.latest_received_server_tick = Some(Tick(1000));
+        sync_manager.finalize(&mut time_manager, &mut tick_manager, &ping_manager);
+        (sync_manager, time_manager, tick_manager, ping_manager)
+    }
+
+    /// Check that a sudden RTT change doesn't make the relative speed of the simulation jump
+    #[test]
+    fn test_relative_speed_clamp_on_rtt_change() {
+        let config = SyncConfig::default()
+            .speedup_factor(2.0)
+            .relative_speed_range(0.9, 1.1)
+            .max_relative_speed_change(0.5);
+        let (mut sync_manager, mut time_manager, mut tick_manager, mut ping_manager) =
+            synced_manager(config, Duration::from_millis(100));
+        assert!(sync_manager
+            .update_prediction_time(&mut time_manager, &mut tick_manager, &ping_manager)
+            .is_none());
+        assert_eq!(time_manager.sync_relative_speed, 1.0);
+
+        // the RTT increases by 3 ticks: the client is too far behind the server and speeds up progressively
+        ping_manager.final_stats.rtt = Duration::from_millis(130);
+        assert!(sync_manager
+            .update_prediction_time(&mut time_manager, &mut tick_manager, &ping_manager)
+            .is_none());
+        assert!((time_manager.sync_relative_speed - 1.008).abs() < 1e-4);
+        for _ in 0..100 {
+            sync_manager.update_prediction_time(
+                &mut time_manager,
+                &mut tick_manager,
+                &ping_manager,
+            );
+        }
+        // the speedup factor is clamped
+        assert_eq!(time_manager.sync_relative_speed, 1.1);
+    }
+
+    /// Check that a tick is skipped instead of speeding up the simulation when the error is above the tick skip margin
+    #[test]
+    fn test_tick_skip_on_rtt_change() {
+        let config = SyncConfig::default().tick_skip_margin(2.0);
+        let (mut sync_manager, mut time_manager, mut tick_manager, mut ping_manager) =
+            synced_manager(config, Duration::from_millis(100));
+        let tick = tick_manager.tick();
+
+        // the RTT increases by 3 ticks: the client skips a tick
+        ping_manager.final_stats.rtt = Duration::from_millis(130);
+        let event = sync_manager.update_prediction_time(
+            &mut time_manager,
+            &mut tick_manager,
+            &ping_manager,
+        );
+        assert!(matches!(
+            event,
+            Some(TickEvent::TickSnap { old_tick, new_tick }) if old_tick == tick && new_tick == tick + 1
+        ));
+        assert_eq!(time_manager.sync_relative_speed, 1.0);
+
+        // the RTT decreases by 3 ticks: the client plays a tick again
+        ping_manager.final_stats.rtt = Duration::from_millis(70);
+        let event = sync_manager.update_prediction_time(
+            &mut time_manager,
+            &mut tick_manager,
+            &ping_manager,
+        );
+        assert!(matches!(
+            event,
+            Some(TickEvent::TickSnap { old_tick, new_tick }) if old_tick == tick + 1 && new_tick == tick
+        ));
+    }
+
     /// Check that after a big tick discrepancy between server/client, the client tick gets updated
     /// to match the server tick
     #[test]
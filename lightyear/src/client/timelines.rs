@@ -0,0 +1,178 @@
+//! Query the different timelines of a replicated component in one call.
+//!
+//! On the client, a replicated entity can exist in up to three versions:
+//! - the [`Confirmed`] entity, which holds the latest state received from the server
+//! - the [`Predicted`] entity, which is simulated ahead of the server
+//! - the [`Interpolated`] entity, which is displayed behind the server
+//!
+//! The [`Timelines`] system param follows the links between these entities so that you can get
+//! the value of a component on each timeline starting from any of them.
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Component, Entity, Query};
+
+use crate::client::components::Confirmed;
+use crate::client::interpolation::Interpolated;
+use crate::client::prediction::Predicted;
+
+/// The value of a component on each timeline of a replicated entity
+#[derive(Debug, PartialEq)]
+pub struct TimelineValues<'a, C> {
+    /// Latest value received from the server
+    pub confirmed: Option<&'a C>,
+    /// Value on the predicted entity
+    pub predicted: Option<&'a C>,
+    /// Value on the interpolated entity
+    pub interpolated: Option<&'a C>,
+}
+
+/// The entities that hold each timeline of a replicated entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelineEntities {
+    pub confirmed: Option<Entity>,
+    pub predicted: Option<Entity>,
+    pub interpolated: Option<Entity>,
+}
+
+/// [`SystemParam`] to get the confirmed, predicted and interpolated values of a component for an entity,
+/// without manually walking the links between the [`Confirmed`], [`Predicted`] and [`Interpolated`] entities
+#[derive(SystemParam)]
+pub struct Timelines<'w, 's, C: Component> {
+    query: Query<
+        'w,
+        's,
+        (
+            Option<&'static Confirmed>,
+            Option<&'static Predicted>,
+            Option<&'static Interpolated>,
+            Option<&'static C>,
+        ),
+    >,
+}
+
+impl<'w, 's, C: Component> Timelines<'w, 's, C> {
+    /// Find the entities of all the timelines of `entity`, which can be either the confirmed, the predicted
+    /// or the interpolated entity.
+    ///
+    /// Returns `None` if the entity does not exist or is not part of any timeline.
+    pub fn entities(&self, entity: Entity) -> Option<TimelineEntities> {
+        let (confirmed, predicted, interpolated, _) = self.query.get(entity).ok()?;
+        let confirmed_entity = if confirmed.is_some() {
+            Some(entity)
+        } else if let Some(predicted) = predicted {
+            predicted.confirmed_entity
+        } else if let Some(interpolated) = interpolated {
+            Some(interpolated.confirmed_entity)
+        } else {
+            return None;
+        };
+        let links = confirmed_entity
+            .and_then(|e| self.query.get(e).ok())
+            .and_then(|(confirmed, ..)| confirmed);
+        Some(TimelineEntities {
+            confirmed: confirmed_entity,
+            // a pre-predicted entity might not have a confirmed entity yet
+            predicted: links
+                .and_then(|c| c.predicted)
+                .or(predicted.is_some().then_some(entity)),
+            interpolated: links
+                .and_then(|c| c.interpolated)
+                .or(interpolated.is_some().then_some(entity)),
+        })
+    }
+
+    /// Get the value of the component on each timeline of `entity`, which can be either the confirmed,
+    /// the predicted or the interpolated entity.
+    ///
+    /// Returns `None` if the entity does not exist or is not part of any timeline.
+    pub fn get(&self, entity: Entity) -> Option<TimelineValues<'_, C>> {
+        let entities = self.entities(entity)?;
+        let value = |entity: Option<Entity>| {
+            entity
+                .and_then(|e| self.query.get(e).ok())
+                .and_then(|(.., value)| value)
+        };
+        Some(TimelineValues {
+            confirmed: value(entities.confirmed),
+            predicted: value(entities.predicted),
+            interpolated: value(entities.interpolated),
+        })
+    }
+
+    /// Value of the component on the confirmed entity
+    pub fn confirmed(&self, entity: Entity) -> Option<&C> {
+        self.get(entity)?.confirmed
+    }
+
+    /// Value of the component on the predicted entity
+    pub fn predicted(&self, entity: Entity) -> Option<&C> {
+        self.get(entity)?.predicted
+    }
+
+    /// Value of the component on the interpolated entity
+    pub fn interpolated(&self, entity: Entity) -> Option<&C> {
+        self.get(entity)?.interpolated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::prelude::Tick;
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_timelines() {
+        let mut stepper = BevyStepper::default();
+        let world = stepper.client_app.world_mut();
+        let confirmed = world.spawn(ComponentSyncModeFull(1.0)).id();
+        let predicted = world
+            .spawn((
+                Predicted {
+                    confirmed_entity: Some(confirmed),
+                },
+                ComponentSyncModeFull(2.0),
+            ))
+            .id();
+        // the interpolated entity does not have the component yet
+        let interpolated = world
+            .spawn(Interpolated {
+                confirmed_entity: confirmed,
+            })
+            .id();
+        world.entity_mut(confirmed).insert(Confirmed {
+            predicted: Some(predicted),
+            interpolated: Some(interpolated),
+            tick: Tick(0),
+        });
+        let other = world.spawn(ComponentSyncModeFull(3.0)).id();
+
+        stepper.client_app.world_mut().run_system_once(
+            move |timelines: Timelines<ComponentSyncModeFull>| {
+                // the timelines can be reached from any of the entities
+                for entity in [confirmed, predicted, interpolated] {
+                    assert_eq!(
+                        timelines.get(entity),
+                        Some(TimelineValues {
+                            confirmed: Some(&ComponentSyncModeFull(1.0)),
+                            predicted: Some(&ComponentSyncModeFull(2.0)),
+                            interpolated: None,
+                        })
+                    );
+                }
+                assert_eq!(
+                    timelines.entities(interpolated),
+                    Some(TimelineEntities {
+                        confirmed: Some(confirmed),
+                        predicted: Some(predicted),
+                        interpolated: Some(interpolated),
+                    })
+                );
+                // entities that are not replicated do not have timelines
+                assert_eq!(timelines.get(other), None);
+            },
+        );
+    }
+}
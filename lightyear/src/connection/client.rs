@@ -1,3 +1,4 @@
+use std::any::Any;
 use std::net::SocketAddr;
 use std::str::FromStr;
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
@@ -18,9 +19,8 @@ use crate::connection::steam::{client::SteamConfig, steamworks_client::Steamwork
 use crate::packet::packet_builder::RecvPayload;
 
 use crate::prelude::client::ClientTransport;
-#[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::prelude::LinkConditionerConfig;
-use crate::prelude::{generate_key, Key};
+use crate::prelude::{generate_key, Key, Message};
 use crate::transport::config::SharedIoConfig;
 
 #[derive(Debug)]
@@ -89,12 +89,30 @@ pub struct ClientConnection {
 /// Enumerates the possible reasons for a client to disconnect from the server
 #[derive(Debug)]
 pub enum DisconnectReason {
+    /// The server disconnected the client with [`ConnectionManager::disconnect`](crate::server::connection::ConnectionManager::disconnect)
+    Kicked(KickReason),
     Transport(crate::transport::error::Error),
     Netcode(super::netcode::ClientState),
     #[cfg(all(feature = "steam", not(target_family = "wasm")))]
     Steam(steamworks::networking_types::NetConnectionEnd),
 }
 
+/// Message provided by the server when it disconnected the client
+pub struct KickReason(pub(crate) Box<dyn Any + Send + Sync>);
+
+impl KickReason {
+    /// Returns the reason message if it is of type `M`
+    pub fn get<M: Message>(&self) -> Option<&M> {
+        self.0.downcast_ref::<M>()
+    }
+}
+
+impl std::fmt::Debug for KickReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KickReason").finish_non_exhaustive()
+    }
+}
+
 pub type IoConfig = SharedIoConfig<ClientTransport>;
 
 #[allow(clippy::large_enum_variant)]
@@ -119,6 +137,9 @@ pub enum NetConfig {
     },
     Local {
         id: u64,
+        /// Simulate network conditions for the messages exchanged between the local client
+        /// and the server, even though they run in the same process
+        conditioner: Option<LinkConditionerConfig>,
     },
 }
 
@@ -133,6 +154,16 @@ impl Default for NetConfig {
 }
 
 impl NetConfig {
+    /// Replace the transport of the io, keeping the same authentication and netcode config.
+    ///
+    /// This has no effect for connections that don't use a [`ClientTransport`] (Steam, Local).
+    pub fn with_transport(mut self, transport: ClientTransport) -> Self {
+        if let NetConfig::Netcode { io, .. } = &mut self {
+            io.transport = transport;
+        }
+        self
+    }
+
     pub fn build_client(self) -> ClientConnection {
         match self {
             NetConfig::Netcode {
@@ -175,8 +206,8 @@ impl NetConfig {
                     disconnect_reason: None,
                 }
             }
-            NetConfig::Local { id } => {
-                let client = super::local::client::Client::new(id);
+            NetConfig::Local { id, conditioner } => {
+                let client = super::local::client::Client::new(id, conditioner);
                 ClientConnection {
                     client: NetClientDispatch::Local(client),
                     disconnect_reason: None,
@@ -186,6 +217,18 @@ impl NetConfig {
     }
 }
 
+impl ClientConnection {
+    /// Returns the link conditioner of the local client in HostServer mode, if there is one
+    pub(crate) fn local_conditioner_mut(
+        &mut self,
+    ) -> Option<&mut super::local::client::LocalLinkConditioner> {
+        match &mut self.client {
+            NetClientDispatch::Local(client) => client.conditioner.as_mut(),
+            _ => None,
+        }
+    }
+}
+
 impl NetClient for ClientConnection {
     fn connect(&mut self) -> Result<(), ConnectionError> {
         self.client.connect()
@@ -1,25 +1,85 @@
 use crate::client::io::Io;
 use crate::connection::client::{ConnectionError, ConnectionState, NetClient};
 use crate::packet::packet_builder::RecvPayload;
-use crate::prelude::ClientId;
+use crate::prelude::{ChannelKind, ChannelRegistry, ClientId, LinkConditionerConfig};
+use crate::transport::middleware::conditioner::LinkConditioner;
 use crate::transport::LOCAL_SOCKET;
+use bytes::Bytes;
 use std::net::SocketAddr;
 
 #[derive(Default)]
 pub struct Client {
     id: u64,
     is_connected: bool,
+    pub(crate) conditioner: Option<LocalLinkConditioner>,
 }
 
 impl Client {
-    pub fn new(id: u64) -> Self {
+    pub fn new(id: u64, conditioner: Option<LinkConditionerConfig>) -> Self {
         Self {
             id,
             is_connected: false,
+            conditioner: conditioner.map(LocalLinkConditioner::new),
         }
     }
 }
 
+/// Simulates network conditions between the local client and the server in HostServer mode.
+///
+/// There are no packets in HostServer mode, so the latency and jitter are applied to each message.
+/// Messages sent on reliable channels are never dropped and are not reordered.
+pub(crate) struct LocalLinkConditioner {
+    to_server: LinkConditioner<(Bytes, ChannelKind)>,
+    to_client: LinkConditioner<(Bytes, ChannelKind)>,
+}
+
+impl LocalLinkConditioner {
+    fn new(config: LinkConditionerConfig) -> Self {
+        Self {
+            to_server: LinkConditioner::new(config.clone()),
+            to_client: LinkConditioner::new(config),
+        }
+    }
+
+    /// Delay the messages sent by the local client, and return the messages that should be received
+    /// by the server
+    pub(crate) fn condition_to_server(
+        &mut self,
+        messages: impl Iterator<Item = (Bytes, ChannelKind)>,
+        channel_registry: &ChannelRegistry,
+    ) -> Vec<(Bytes, ChannelKind)> {
+        Self::condition(&mut self.to_server, messages, channel_registry)
+    }
+
+    /// Delay the messages sent by the server, and return the messages that should be received
+    /// by the local client
+    pub(crate) fn condition_to_client(
+        &mut self,
+        messages: impl Iterator<Item = (Bytes, ChannelKind)>,
+        channel_registry: &ChannelRegistry,
+    ) -> Vec<(Bytes, ChannelKind)> {
+        Self::condition(&mut self.to_client, messages, channel_registry)
+    }
+
+    fn condition(
+        conditioner: &mut LinkConditioner<(Bytes, ChannelKind)>,
+        messages: impl Iterator<Item = (Bytes, ChannelKind)>,
+        channel_registry: &ChannelRegistry,
+    ) -> Vec<(Bytes, ChannelKind)> {
+        for (message, channel_kind) in messages {
+            let reliable = channel_registry
+                .get_builder_from_kind(&channel_kind)
+                .map_or(true, |builder| builder.settings.mode.is_reliable());
+            if reliable {
+                conditioner.condition_packet_ordered((message, channel_kind));
+            } else {
+                conditioner.condition_packet((message, channel_kind));
+            }
+        }
+        std::iter::from_fn(|| conditioner.pop_packet()).collect()
+    }
+}
+
 impl NetClient for Client {
     fn connect(&mut self) -> Result<(), ConnectionError> {
         self.is_connected = true;
@@ -67,3 +127,65 @@ impl NetClient for Client {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::prelude::Events;
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::connection::client::{ClientConnection, NetClientDispatch};
+    use crate::prelude::{client, server};
+    use crate::tests::host_server_stepper::{HostServerStepper, LOCAL_CLIENT_ID};
+    use crate::tests::protocol::*;
+
+    #[test]
+    fn test_local_client_link_conditioner() {
+        let mut stepper = HostServerStepper::default();
+        let latency = Duration::from_millis(100);
+        match &mut stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ClientConnection>()
+            .client
+        {
+            NetClientDispatch::Local(client) => {
+                client.conditioner = Some(LocalLinkConditioner::new(LinkConditionerConfig::new(
+                    latency,
+                    Duration::default(),
+                    0.0,
+                )));
+            }
+            _ => panic!("the host-server client should be local"),
+        }
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .send_message::<Channel1, _>(
+                ClientId::Local(LOCAL_CLIENT_ID),
+                &mut StringMessage("a".to_string()),
+            )
+            .unwrap();
+        let mut reader = ManualEventReader::<client::MessageEvent<StringMessage>>::default();
+        let mut received_after = None;
+        let mut elapsed = Duration::default();
+        while elapsed < latency * 2 {
+            stepper.frame_step();
+            elapsed += stepper.frame_duration;
+            let events = stepper
+                .server_app
+                .world()
+                .resource::<Events<client::MessageEvent<StringMessage>>>();
+            if reader.read(events).next().is_some() {
+                received_after = Some(elapsed);
+                break;
+            }
+        }
+        // the message is only received by the local client once the latency has elapsed
+        let received_after = received_after.expect("the message should have been received");
+        assert!(received_after >= latency);
+    }
+}
@@ -6,7 +6,7 @@ pub mod netcode;
 pub mod server;
 
 pub mod id;
-mod local;
+pub(crate) mod local;
 #[cfg_attr(docsrs, doc(cfg(all(feature = "steam", not(target_family = "wasm")))))]
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 pub mod steam;
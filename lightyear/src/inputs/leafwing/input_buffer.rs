@@ -255,6 +255,44 @@ impl<T: LeafwingUserAction> InputBuffer<T> {
         }
     }
 
+    /// Same as [`update_from_message`](Self::update_from_message), but only the [`ActionState`]s for the ticks
+    /// that are after the end of the buffer are written, after being passed to `validate`.
+    ///
+    /// The inputs of a tick are sent in several messages for redundancy, so this makes sure that each
+    /// [`ActionState`] is only validated once. `validate` can modify the [`ActionState`], or return `false`
+    /// to reject it, in which case the [`ActionState`] of the previous tick is kept.
+    pub(crate) fn update_from_message_with_validation(
+        &mut self,
+        end_tick: Tick,
+        start_value: &ActionState<T>,
+        diffs: &[Vec<ActionDiff<T>>],
+        mut validate: impl FnMut(Tick, &mut ActionState<T>) -> bool,
+    ) {
+        let start_tick = end_tick - diffs.len() as u16;
+        let buffer_end_tick = self.end_tick();
+
+        let mut value = start_value.clone();
+        for delta in 0..=diffs.len() {
+            let tick = start_tick + Tick(delta as u16);
+            if delta > 0 {
+                // see `update_from_message`: the values are ticked manually so that JustPressed becomes Pressed
+                value.tick(Instant::now(), Instant::now());
+                for diff in &diffs[delta - 1] {
+                    diff.apply(&mut value);
+                }
+            }
+            if buffer_end_tick.is_some_and(|buffer_end_tick| tick <= buffer_end_tick) {
+                continue;
+            }
+            let mut validated = value.clone();
+            if validate(tick, &mut validated) {
+                self.set(tick, &validated);
+            } else if let Some(previous) = self.get(tick - 1).cloned() {
+                self.set(tick, &previous);
+            }
+        }
+    }
+
     /// Get the last tick in the buffer
     pub fn end_tick(&self) -> Option<Tick> {
         self.start_tick
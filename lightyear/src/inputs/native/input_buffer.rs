@@ -155,6 +155,41 @@ impl<T: UserAction> InputBuffer<T> {
         }
     }
 
+    /// Same as [`update_from_message`](Self::update_from_message), but only the inputs for the ticks that
+    /// are after the end of the buffer are written, after being passed to `validate`.
+    ///
+    /// The inputs of a tick are sent in several messages for redundancy, so this makes sure that each input
+    /// is only validated once. `validate` can modify the input, or return `false` to reject it, in which case
+    /// the tick is considered to have no input.
+    pub(crate) fn update_from_message_with_validation(
+        &mut self,
+        message: InputMessage<T>,
+        mut validate: impl FnMut(Tick, &mut T) -> bool,
+    ) {
+        let message_start_tick = Tick(message.end_tick.0) - message.inputs.len() as u16 + 1;
+        let end_tick = self
+            .start_tick
+            .map(|start_tick| start_tick + (self.buffer.len() as i16 - 1));
+        let mut prev_value = None;
+
+        for (delta, input) in message.inputs.into_iter().enumerate() {
+            let tick = message_start_tick + Tick(delta as u16);
+            match input {
+                InputData::Absent => prev_value = None,
+                InputData::SameAsPrecedent => {}
+                InputData::Input(input) => prev_value = Some(input),
+            }
+            if end_tick.is_some_and(|end_tick| tick <= end_tick) {
+                continue;
+            }
+            let mut value = prev_value.clone();
+            if value.as_mut().is_some_and(|input| !validate(tick, input)) {
+                value = None;
+            }
+            self.set(tick, value);
+        }
+    }
+
     // Convert the last N ticks up to end_tick included into a compressed message that we can send to the server
     // Return None if the last N inputs are all Absent
     pub(crate) fn create_message(&self, end_tick: Tick, num_ticks: u16) -> InputMessage<T> {
@@ -264,4 +299,41 @@ mod tests {
         assert_eq!(input_buffer.get(Tick(14)), Some(&0));
         assert_eq!(input_buffer.get(Tick(13)), None);
     }
+
+    #[test]
+    fn test_update_from_message_with_validation() {
+        let mut input_buffer = InputBuffer::default();
+        let mut validated = vec![];
+        // reject the input 1
+        let mut validate = |tick: Tick, input: &mut i16| {
+            validated.push(tick);
+            *input != 1
+        };
+
+        let message = InputMessage {
+            end_tick: Tick(12),
+            inputs: vec![
+                InputData::Input(0),
+                InputData::Input(1),
+                InputData::SameAsPrecedent,
+            ],
+        };
+        input_buffer.update_from_message_with_validation(message, &mut validate);
+        // the inputs for ticks 11 and 12 are sent again: they are not validated twice
+        let message = InputMessage {
+            end_tick: Tick(13),
+            inputs: vec![
+                InputData::Input(1),
+                InputData::SameAsPrecedent,
+                InputData::Input(2),
+            ],
+        };
+        input_buffer.update_from_message_with_validation(message, &mut validate);
+
+        assert_eq!(validated, vec![Tick(10), Tick(11), Tick(12), Tick(13)]);
+        assert_eq!(input_buffer.get(Tick(10)), Some(&0));
+        assert_eq!(input_buffer.get(Tick(11)), None);
+        assert_eq!(input_buffer.get(Tick(12)), None);
+        assert_eq!(input_buffer.get(Tick(13)), Some(&2));
+    }
 }
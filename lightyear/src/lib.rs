@@ -115,6 +115,8 @@ The [`Replicate`](prelude::server::Replicate) bundle contains many components to
 The marker component [`Replicating`] indicates that the entity is getting replicated to a remote peer.
 You can remove the [`Replicating`] component to pause the replication. This will not despawn the entity on the remote world; it will simply
 stop sending replication updates.
+On the server, you can also add the [`ReplicationPaused`] component to only stop sending the component updates of the entity;
+all the components are sent again when the replication resumes.
 
 In contrast, the [`ReplicationTarget`] component is used to indicate which clients you want to replicate this entity to.
 If you update the target to exclude a given client, the entity will get despawned on that client.
@@ -161,6 +163,7 @@ fn component_inserted(query: Query<Entity, (With<Replicated>, Added<MyComponent>
 [`Replicated`]: prelude::Replicated
 [`ReplicationTarget`]: prelude::ReplicationTarget
 [`Replicating`]: prelude::Replicating
+[`ReplicationPaused`]: prelude::ReplicationPaused
 [`SharedConfig`]: prelude::SharedConfig
  */
 #![allow(clippy::missing_transmute_annotations)]
@@ -179,14 +182,19 @@ pub(crate) mod _internal {
     pub use paste::paste;
 }
 
+#[doc(hidden)]
+pub mod _reexport {
+    pub use serde;
+}
+
 /// Prelude containing commonly used types
 pub mod prelude {
-    pub use lightyear_macros::Channel;
+    pub use lightyear_macros::{Channel, DeltaReplicate};
     pub use serde::{Deserialize, Serialize};
 
     pub use crate::channel::builder::{
         Channel, ChannelBuilder, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings,
-        InputChannel, ReliableSettings,
+        InputChannel, OrderedResourceChannel, PresentationChannel, ReliableSettings,
     };
     pub use crate::client::prediction::prespawn::PreSpawnedPlayerObject;
     pub use crate::connection::id::ClientId;
@@ -196,35 +204,84 @@ pub mod prelude {
     pub use crate::inputs::native::UserAction;
     pub use crate::packet::error::PacketError;
     pub use crate::packet::message::Message;
-    pub use crate::protocol::channel::{AppChannelExt, ChannelKind, ChannelRegistry};
-    pub use crate::protocol::component::{AppComponentExt, ComponentRegistry, Linear};
+    pub use crate::packet::message_manager::FragmentationStats;
+    pub use crate::protocol::channel::{
+        AppChannelExt, ChannelKind, ChannelRegistry, DynamicChannelExt,
+    };
+    pub use crate::protocol::component::{
+        AppComponentExt, ComponentRegistry, ComponentTuple, ComponentsRegistration, Linear,
+        SyncComponentTuple,
+    };
+    pub use crate::protocol::extension::{AppProtocolExtensionExt, ProtocolExtensionId};
     pub use crate::protocol::message::{AppMessageExt, MessageRegistry};
+    pub use crate::protocol::quantize::{QuantizationConfig, Quantize};
     pub use crate::protocol::serialize::AppSerializeExt;
+    pub use crate::protocol::version::ProtocolHash;
     pub use crate::shared::config::{Mode, SharedConfig};
+    pub use crate::shared::console::{ConsoleCommandRequest, ConsoleCommandResponse};
+    pub use crate::shared::diagnostics::NetworkDiagnosticsPlugin;
+    pub use crate::shared::events::components::UnknownTypeKind;
+    pub use crate::shared::events::handlers::AppMessageHandlerExt;
     #[cfg(feature = "leafwing")]
     pub use crate::shared::input::leafwing::LeafwingInputPlugin;
     pub use crate::shared::input::native::InputPlugin;
+    pub use crate::shared::interest::{InterestRequest, InterestResponse};
+    pub use crate::shared::memory::{
+        EvictionPolicy, MemoryConfig, MemoryDiagnosticsPlugin, MemoryLimit, MemoryStats,
+        MemorySubsystem,
+    };
     pub use crate::shared::ping::manager::PingConfig;
     pub use crate::shared::plugin::{NetworkIdentity, SharedPlugin};
+    pub use crate::shared::presentation::{
+        PresentationBatch, PresentationEvent, PresentationEvents, PresentationEventsPlugin,
+    };
+    pub use crate::shared::proximity::{
+        NearbyPlayer, ProximityMetadata, ProximityPlayer, ProximityPlugin,
+    };
     pub use crate::shared::replication::authority::HasAuthority;
     pub use crate::shared::replication::components::{
-        DeltaCompression, DisabledComponent, NetworkRelevanceMode, OverrideTargetComponent,
-        PrePredicted, ReplicateHierarchy, ReplicateOnceComponent, Replicated, Replicating,
-        ReplicationGroup, ReplicationTarget, ShouldBePredicted, TargetEntity,
+        AdditionalReplicationGroups, DeltaCompression, DisabledComponent, LocalPlayerId,
+        NetworkRelevanceMode, OverrideTargetComponent, PrePredicted, PredictionExclusions,
+        ReplacesEntity, ReplicateHierarchy, ReplicateOnceComponent, Replicated, Replicating,
+        ReplicationGroup, ReplicationGroupId, ReplicationPaused, ReplicationTarget,
+        ShouldBePredicted, TargetEntity,
+    };
+    pub use crate::shared::replication::conversion::{
+        AppReplicationConversionExt, ReplicationConversion,
+    };
+    pub use crate::shared::replication::debug::{
+        NetworkEntityId, NetworkEntityIndex, RemoteEntity, ReplicationDebugPlugin,
     };
     pub use crate::shared::replication::entity_map::RemoteEntityMap;
+    pub use crate::shared::replication::events::{
+        EventRegistration, PendingEvent, ReplicatedEventBuffer,
+    };
     pub use crate::shared::replication::hierarchy::ParentSync;
+    pub use crate::shared::replication::integrity::{
+        AppDesyncCheckExt, DesyncDetectedEvent, DesyncDetectionPlugin, GroupChecksum,
+    };
     pub use crate::shared::replication::network_target::NetworkTarget;
     pub use crate::shared::replication::plugin::ReplicationConfig;
     pub use crate::shared::replication::plugin::SendUpdatesMode;
     pub use crate::shared::replication::resources::{
-        ReplicateResourceExt, ReplicateResourceMetadata, StopReplicateResourceExt,
+        PerClientResource, ReplicateResourceExt, ReplicateResourceMetadata,
+        StopReplicateResourceExt,
     };
+    pub use crate::shared::replication::strategy::{
+        DefaultReplicationStrategy, ReplicationSendContext, ReplicationSendStrategy,
+    };
+    pub use crate::shared::replication::tombstone::{
+        ComponentTombstoneEvent, ComponentTombstones, TombstoneHistory,
+    };
+    pub use crate::shared::rng::{NetworkedRng, NetworkedRngPlugin, NetworkedRngSeed};
     pub use crate::shared::run_conditions::*;
     pub use crate::shared::sets::{FixedUpdateSet, MainSet};
     pub use crate::shared::tick_manager::TickManager;
     pub use crate::shared::tick_manager::{Tick, TickConfig};
     pub use crate::shared::time_manager::TimeManager;
+    pub use crate::shared::world_seed::{
+        is_world_ready, WorldGeneration, WorldReady, WorldReadyEvent, WorldSeed, WorldSeedPlugin,
+    };
     pub use crate::transport::middleware::compression::CompressionConfig;
     pub use crate::transport::middleware::conditioner::LinkConditionerConfig;
 
@@ -259,17 +316,28 @@ pub mod prelude {
         };
         pub use crate::client::config::{ClientConfig, NetcodeConfig, PacketConfig};
         pub use crate::client::connection::ConnectionManager;
+        pub use crate::client::connection_quality::{
+            ConnectionQuality, ConnectionQualityChanged, ConnectionQualityConfig,
+            ConnectionQualityPlugin, QualityThresholds,
+        };
         pub use crate::client::error::ClientError;
         pub use crate::client::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
             DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            NetworkErrorEvent, UnknownTypeEvent,
         };
         #[cfg(feature = "leafwing")]
         pub use crate::client::input::leafwing::LeafwingInputConfig;
         pub use crate::client::input::native::{InputConfig, InputManager};
+        pub use crate::client::interpolation::diagnostics::{
+            InterpolationBufferStats, InterpolationDiagnosticsPlugin,
+        };
         pub use crate::client::interpolation::interpolation_history::ConfirmedHistory;
+        pub use crate::client::interpolation::lag_compensation::{
+            LagCompensatedHit, LagCompensation,
+        };
         pub use crate::client::interpolation::plugin::{
-            InterpolationConfig, InterpolationDelay, InterpolationSet,
+            ExtrapolationConfig, InterpolationConfig, InterpolationDelay, InterpolationSet,
         };
         pub use crate::client::interpolation::{
             InterpolateStatus, Interpolated, VisualInterpolateStatus, VisualInterpolationPlugin,
@@ -277,24 +345,45 @@ pub mod prelude {
         pub use crate::client::io::config::ClientTransport;
         pub use crate::client::io::Io;
         pub use crate::client::networking::{ClientCommands, NetworkingState};
+        #[cfg(feature = "overlay")]
+        pub use crate::client::overlay::{
+            NetworkOverlay, NetworkOverlayPlugin, NetworkOverlayText,
+        };
         pub use crate::client::plugin::ClientPlugins;
+        pub use crate::client::prediction::companion::{AppCompanionExt, CompanionAdded};
         pub use crate::client::prediction::correction::Correction;
         pub use crate::client::prediction::despawn::PredictionDespawnCommandsExt;
         pub use crate::client::prediction::plugin::is_in_rollback;
-        pub use crate::client::prediction::plugin::{PredictionConfig, PredictionSet};
+        pub use crate::client::prediction::plugin::{
+            PredictionConfig, PredictionHistoryInit, PredictionSet,
+        };
+        pub use crate::client::prediction::predicted_history::PredictedComponentAdded;
         pub use crate::client::prediction::rollback::{Rollback, RollbackState};
+        pub use crate::client::prediction::time_travel::{
+            is_time_travelling, TimeTravel, TimeTravelPlugin, TimeTravelState,
+        };
         pub use crate::client::prediction::Predicted;
+        pub use crate::client::remote_entity::{
+            RemoteEntity, RemoteEntityMapService, RemoteEntityMapped, RemoteEntityUnmapped,
+        };
+        pub use crate::client::replay::{
+            Replay, ReplayMode, ReplayPlayer, ReplayPlugin, ReplayRecorder,
+        };
         pub use crate::client::replication::commands::DespawnReplicationCommandExt;
         pub use crate::client::replication::send::Replicate;
         pub use crate::client::run_conditions::{is_connected, is_disconnected, is_synced};
         pub use crate::client::sync::SyncConfig;
+        pub use crate::client::timelines::{TimelineEntities, TimelineValues, Timelines};
         pub use crate::connection::client::{
             Authentication, ClientConnection, IoConfig, NetClient, NetConfig,
         };
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::client::{SocketConfig, SteamConfig};
+        pub use crate::protocol::component::ShouldRollbackFn;
     }
     pub mod server {
+        #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
+        pub use crate::transport::websocket::server::WebSocketIdentity;
         #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
         pub use wtransport::tls::Identity;
 
@@ -303,27 +392,54 @@ pub mod prelude {
         };
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::server::{SocketConfig, SteamConfig};
+        pub use crate::server::budget::{
+            BudgetExceeded, BudgetOverflowPolicy, ReplicationBudget, ReplicationBudgetExceededEvent,
+        };
         pub use crate::server::clients::ControlledEntities;
         pub use crate::server::config::{NetcodeConfig, PacketConfig, ServerConfig};
         pub use crate::server::connection::ConnectionManager;
+        pub use crate::server::console::{
+            AppConsoleCommandExt, ConsoleCommandInput, ConsoleCommandResult, ConsoleCommands,
+            ConsolePlugin, ConsoleSource,
+        };
         pub use crate::server::error::ServerError;
         pub use crate::server::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
             DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            NetworkErrorEvent, ProtocolMismatchEvent, UnknownTypeEvent,
+        };
+        pub use crate::server::input::native::{
+            InputValidator, InvalidInputEvent, MissingInputPolicy,
         };
         pub use crate::server::io::config::ServerTransport;
         pub use crate::server::io::Io;
+        pub use crate::server::load_shedding::{
+            LoadShedding, LoadSheddingEvent, LoadSheddingPlugin,
+        };
+        pub use crate::server::metadata::ConnectionMetadata;
         pub use crate::server::networking::{NetworkingState, ServerCommands};
         pub use crate::server::plugin::ServerPlugins;
+        #[cfg(feature = "gizmos")]
+        pub use crate::server::relevance::gizmos::{RelevanceGizmos, RelevanceGizmosPlugin};
         pub use crate::server::relevance::immediate::RelevanceManager;
+        pub use crate::server::relevance::interest::{
+            AppInterestSetExt, InterestSetRequest, InterestSets,
+        };
         pub use crate::server::relevance::room::{RoomId, RoomManager};
+        pub use crate::server::replay::{ServerReplay, ServerReplayPlugin};
         pub use crate::server::replication::commands::AuthorityCommandExt;
-        pub use crate::server::replication::commands::DespawnReplicationCommandExt;
+        pub use crate::server::replication::commands::{
+            DespawnReplicationCommandExt, DespawnReplicationGroupsCommandExt,
+        };
         pub use crate::server::replication::{
             send::{ControlledBy, Lifetime, Replicate, ServerFilter, SyncTarget},
             ReplicationSet, ServerReplicationSet,
         };
         pub use crate::server::run_conditions::{is_started, is_stopped};
+        pub use crate::server::scene::NetworkScene;
+        pub use crate::server::send_budget::{ClientSendStats, SendBudget};
+        #[cfg(all(feature = "status_endpoint", not(target_family = "wasm")))]
+        pub use crate::server::status::{ServerStatus, StatusEndpoint, StatusEndpointPlugin};
         pub use crate::shared::replication::authority::AuthorityPeer;
     }
 
@@ -348,6 +464,9 @@ pub mod server;
 
 pub mod shared;
 
+/// Helpers to write deterministic integration tests with a server and multiple clients
+pub mod testing;
+
 #[cfg(test)]
 pub(crate) mod tests;
 
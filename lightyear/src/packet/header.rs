@@ -32,6 +32,15 @@ pub(crate) struct PacketHeader {
     pub(crate) tick: Tick,
 }
 
+impl PacketHeader {
+    /// Read the [`PacketId`] of a serialized packet without deserializing the whole header
+    pub(crate) fn packet_id_of(payload: &[u8]) -> Option<PacketId> {
+        payload
+            .get(1..3)
+            .map(|bytes| PacketId(u16::from_be_bytes([bytes[0], bytes[1]])))
+    }
+}
+
 impl ToBytes for PacketHeader {
     fn len(&self) -> usize {
         11
@@ -141,6 +150,16 @@ impl PacketHeaderManager {
         }
     }
 
+    /// Estimate of the fraction of sent packets that were lost
+    pub(crate) fn packet_loss(&self) -> f32 {
+        self.stats_manager.packet_loss()
+    }
+
+    /// Estimate of the fraction of the packets sent by the remote that we did not receive
+    pub(crate) fn incoming_packet_loss(&self) -> f32 {
+        self.stats_manager.incoming_packet_loss()
+    }
+
     /// Internal bookkeeping.
     /// Returns a list of packets that are considered NACKed (i.e. acknowledged as losts)
     pub(crate) fn update(
@@ -195,7 +214,12 @@ impl PacketHeaderManager {
     /// Returns the list of packets that have been newly acked by the remote
     pub(crate) fn process_recv_packet_header(&mut self, header: &PacketHeader) -> Vec<PacketId> {
         // update the receive buffer
-        self.stats_manager.received_packet();
+        // the remote sent all the packets between the most recent packet we received and this one
+        let expected = self
+            .recv_buffer
+            .last_recv_packet_id
+            .map_or(1, |last| (header.packet_id - last).max(0) as u32);
+        self.stats_manager.received_packet(expected);
         self.recv_buffer.recv_packet(header.packet_id);
 
         let mut newly_acked_packets = Vec::new();
@@ -416,6 +440,7 @@ mod tests {
         let mut writer = Vec::new();
         header.to_bytes(&mut writer)?;
         assert_eq!(writer.len(), header.len());
+        assert_eq!(PacketHeader::packet_id_of(&writer), Some(PacketId(27)));
 
         let mut reader = writer.into();
         let read_header = PacketHeader::from_bytes(&mut reader)?;
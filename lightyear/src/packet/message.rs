@@ -9,6 +9,7 @@ use crate::serialize::reader::Reader;
 use crate::serialize::varint::varint_len;
 use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::tick_manager::Tick;
+use crate::shared::time_manager::WrappedTime;
 use crate::utils::wrapping_id::wrapping_id;
 
 // Internal id that we assign to each message sent over the network
@@ -49,6 +50,8 @@ pub(crate) struct MessageAck {
 pub struct SendMessage {
     pub(crate) data: MessageData,
     pub(crate) priority: f32,
+    /// Time at which the message was buffered in the channel
+    pub(crate) buffered_at: WrappedTime,
 }
 
 #[derive(Debug, PartialEq)]
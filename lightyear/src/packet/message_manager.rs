@@ -1,5 +1,6 @@
 use std::collections::{HashMap, VecDeque};
 
+use bevy::utils::Duration;
 use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 use tracing::trace;
@@ -16,15 +17,18 @@ use crate::packet::header::PacketHeader;
 use crate::packet::message::{
     FragmentData, MessageAck, MessageId, ReceiveMessage, SendMessage, SingleData,
 };
-use crate::packet::packet::PacketId;
+use crate::packet::packet::{fragment_size, PacketId, MIN_PACKET_SIZE};
 use crate::packet::packet_builder::{PacketBuilder, Payload, RecvPayload};
 use crate::packet::packet_type::PacketType;
 use crate::packet::priority_manager::{PriorityConfig, PriorityManager};
-use crate::protocol::channel::{ChannelId, ChannelKind, ChannelRegistry};
+use crate::protocol::channel::{
+    is_dynamic_channel_net_id, ChannelId, ChannelKind, ChannelRegistry,
+};
 use crate::protocol::registry::NetId;
 use crate::serialize::reader::Reader;
 use crate::serialize::varint::VarIntReadExt;
 use crate::serialize::ToBytes;
+use crate::shared::memory::{EvictionPolicy, MemoryConfig, MemoryTally};
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
@@ -37,6 +41,33 @@ use crate::utils::captures::Captures;
 
 pub const DEFAULT_MESSAGE_PRIORITY: f32 = 1.0;
 
+/// Returns true if the priority can be used to send a message: it must be finite and non-negative
+pub(crate) fn is_valid_priority(priority: f32) -> bool {
+    priority.is_finite() && priority >= 0.0
+}
+
+/// Statistics about the messages that had to be split into multiple fragments because
+/// they didn't fit in a single packet
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FragmentationStats {
+    /// Number of messages buffered for sending
+    pub messages: u64,
+    /// Number of messages that were too big to fit in a single packet
+    pub fragmented_messages: u64,
+    /// Total number of fragments that the fragmented messages were split into
+    pub fragments: u64,
+}
+
+impl FragmentationStats {
+    /// Fraction of the messages that had to be fragmented
+    pub fn fragmented_ratio(&self) -> f32 {
+        if self.messages == 0 {
+            return 0.0;
+        }
+        self.fragmented_messages as f32 / self.messages as f32
+    }
+}
+
 /// Wrapper to: send/receive messages via channels to a remote address
 /// By splitting the data into packets and sending them through a given transport
 #[derive(Debug)]
@@ -51,6 +82,15 @@ pub struct MessageManager {
     /// reliable senders can stop trying to send a message that has already been received
     packet_to_message_ack_map: HashMap<PacketId, Vec<(ChannelKind, MessageAck)>>,
     nack_senders: Vec<Sender<MessageId>>,
+    fragmentation_stats: FragmentationStats,
+    /// Packets containing reliable messages that the transport failed to send,
+    /// to be retried during the next frame
+    retry_payloads: Vec<Payload>,
+    /// Send intervals that were set at runtime with [`set_channel_send_interval`](Self::set_channel_send_interval)
+    send_interval_overrides: HashMap<ChannelKind, Duration>,
+    /// Channels that were paused with [`pause_channel`](Self::pause_channel), along with the maximum number
+    /// of bytes that they can keep buffered
+    paused_channels: HashMap<ChannelKind, usize>,
 }
 
 impl MessageManager {
@@ -66,9 +106,91 @@ impl MessageManager {
             channel_registry: channel_registry.clone(),
             packet_to_message_ack_map: HashMap::new(),
             nack_senders: vec![],
+            fragmentation_stats: FragmentationStats::default(),
+            retry_payloads: vec![],
+            send_interval_overrides: HashMap::new(),
+            paused_channels: HashMap::new(),
+        }
+    }
+
+    /// Set the maximum number of bytes in a packet sent on this connection.
+    ///
+    /// Messages that are bigger than the packet (minus the packet header) will be fragmented.
+    /// The value is clamped between 256 and [`MAX_PACKET_SIZE`](crate::connection::netcode::MAX_PACKET_SIZE).
+    pub fn set_max_packet_size(&mut self, max_packet_size: usize) {
+        let max_packet_size =
+            max_packet_size.clamp(MIN_PACKET_SIZE, crate::connection::netcode::MAX_PACKET_SIZE);
+        self.packet_manager.set_max_packet_size(max_packet_size);
+        for channel in self.channels.values_mut() {
+            channel
+                .sender
+                .set_fragment_size(fragment_size(max_packet_size));
         }
     }
 
+    /// Add a channel that was registered after the connection was created
+    pub(crate) fn add_channel(&mut self, channel_kind: ChannelKind, registry: &ChannelRegistry) {
+        let Some(builder) = registry.get_builder_from_kind(&channel_kind) else {
+            return;
+        };
+        let mut channel = builder.build();
+        channel
+            .sender
+            .set_fragment_size(fragment_size(self.packet_manager.max_packet_size()));
+        self.channels.insert(channel_kind, channel);
+        self.channel_registry = registry.clone();
+    }
+
+    /// Statistics about the messages that were fragmented on this connection
+    pub fn fragmentation_stats(&self) -> FragmentationStats {
+        self.fragmentation_stats
+    }
+
+    /// Estimate of the fraction of sent packets that were lost
+    pub(crate) fn packet_loss(&self) -> f32 {
+        self.packet_manager.header_manager.packet_loss()
+    }
+
+    /// Estimate of the fraction of packets sent by the remote peer that were lost, derived
+    /// from the gaps in the packet ids that we received
+    pub(crate) fn incoming_packet_loss(&self) -> f32 {
+        self.packet_manager.header_manager.incoming_packet_loss()
+    }
+
+    /// Return the payload of a packet that was sent, so that its allocation can be reused for the next packets
+    pub(crate) fn recycle_payload(&mut self, payload: Payload) {
+        self.packet_manager.recycle_buffer(payload);
+    }
+
+    /// Keep a packet that the transport failed to send (for example because the socket buffer is full),
+    /// so that it is retried during the next frame if it contains reliable messages.
+    ///
+    /// Other packets are dropped: their messages would be stale by the time they are retried.
+    pub(crate) fn retry_payload(&mut self, payload: Payload) {
+        let reliable = PacketHeader::packet_id_of(&payload)
+            .and_then(|packet_id| self.packet_to_message_ack_map.get(&packet_id))
+            .is_some_and(|acks| {
+                acks.iter().any(|(channel_kind, _)| {
+                    self.channels
+                        .get(channel_kind)
+                        .is_some_and(|channel| channel.setting.mode.is_reliable())
+                })
+            });
+        if reliable {
+            self.retry_payloads.push(payload);
+        } else {
+            self.recycle_payload(payload);
+        }
+    }
+
+    /// Take the packets that the transport failed to send during the previous frame.
+    ///
+    /// They are only retried once: if they fail again, they should be recycled, and the reliable
+    /// channels will resend their messages once they are considered lost.
+    pub(crate) fn take_retry_payloads(&mut self) -> Vec<Payload> {
+        std::mem::take(&mut self.retry_payloads)
+    }
+
     pub(crate) fn get_replication_update_send_receiver(&mut self) -> Receiver<MessageId> {
         self.priority_manager
             .subscribe_replication_update_sent_messages()
@@ -146,7 +268,17 @@ impl MessageManager {
             .channels
             .get_mut(&channel_kind)
             .ok_or(PacketError::ChannelNotFound)?;
-        Ok(channel.sender.buffer_send(message, priority)?)
+        let fragment_size = channel.sender.fragment_size();
+        self.fragmentation_stats.messages += 1;
+        if message.len() > fragment_size {
+            self.fragmentation_stats.fragmented_messages += 1;
+            self.fragmentation_stats.fragments += message.len().div_ceil(fragment_size) as u64;
+        }
+        let message_id = channel.sender.buffer_send(message, priority)?;
+        if let Some(max_bytes) = self.paused_channels.get(&channel_kind) {
+            channel.sender.evict(*max_bytes, EvictionPolicy::DropOldest);
+        }
+        Ok(message_id)
     }
 
     /// Prepare buckets from the internal send buffers, and return the bytes to send
@@ -161,6 +293,9 @@ impl MessageManager {
         let mut data_to_send: Vec<(NetId, (VecDeque<SendMessage>, VecDeque<SendMessage>))> = vec![];
         let mut has_data_to_send = false;
         for (channel_kind, channel) in self.channels.iter_mut() {
+            if self.paused_channels.contains_key(channel_kind) {
+                continue;
+            }
             let channel_id = self
                 .channel_registry
                 .get_net_from_kind(channel_kind)
@@ -174,7 +309,7 @@ impl MessageManager {
             }
         }
         // return early if there are no messages to send
-        if !has_data_to_send {
+        if !has_data_to_send && !self.priority_manager.has_deferred_messages() {
             return Ok(vec![]);
         }
 
@@ -326,6 +461,9 @@ impl MessageManager {
             // read the fragment data
             let channel_id = ChannelId::from_bytes(&mut cursor)?;
             let fragment_data = FragmentData::from_bytes(&mut cursor)?;
+            if self.is_unknown_dynamic_channel(channel_id) {
+                return Ok(tick);
+            }
             self.get_channel_mut(channel_id)?
                 .receiver
                 .buffer_recv(ReceiveMessage {
@@ -337,8 +475,13 @@ impl MessageManager {
         while cursor.has_remaining() {
             let channel_id = ChannelId::from_bytes(&mut cursor)?;
             let num_messages = cursor.read_varint()?;
+            // the remote registered a channel after startup that we don't have: skip its messages
+            let skip = self.is_unknown_dynamic_channel(channel_id);
             for i in 0..num_messages {
                 let single_data = SingleData::from_bytes(&mut cursor)?;
+                if skip {
+                    continue;
+                }
                 self.get_channel_mut(channel_id)?
                     .receiver
                     .buffer_recv(ReceiveMessage {
@@ -401,6 +544,22 @@ impl MessageManager {
         map
     }
 
+    /// Returns true if the channel id belongs to a dynamic channel that was not registered locally
+    fn is_unknown_dynamic_channel(&self, channel_id: ChannelId) -> bool {
+        let unknown = is_dynamic_channel_net_id(channel_id)
+            && self
+                .channel_registry
+                .get_kind_from_net_id(channel_id)
+                .is_none();
+        if unknown {
+            trace!(
+                ?channel_id,
+                "Discarding messages from unknown dynamic channel"
+            );
+        }
+        unknown
+    }
+
     pub fn get_channel_mut(
         &mut self,
         channel_id: ChannelId,
@@ -414,6 +573,100 @@ impl MessageManager {
             .ok_or(PacketError::ChannelNotFound)
     }
 
+    /// Change the interval at which a channel flushes its buffered messages.
+    ///
+    /// Each channel buffers its messages until its own timer fires; all the channels that are ready to send
+    /// in the same frame are aggregated into the same packets.
+    pub fn set_channel_send_interval(
+        &mut self,
+        channel_kind: ChannelKind,
+        send_interval: Duration,
+    ) -> Result<(), PacketError> {
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .ok_or(PacketError::ChannelNotFound)?;
+        channel.sender.set_send_interval(send_interval);
+        // going back to the interval of the channel settings removes the override
+        if send_interval == channel.setting.send_frequency {
+            self.send_interval_overrides.remove(&channel_kind);
+        } else {
+            self.send_interval_overrides
+                .insert(channel_kind, send_interval);
+        }
+        Ok(())
+    }
+
+    /// Stop sending the messages of a channel until [`resume_channel`](Self::resume_channel) is called.
+    ///
+    /// The messages buffered while the channel is paused are kept, but the oldest ones are dropped
+    /// so that at most `max_buffered_bytes` stay buffered (reliable channels never drop messages).
+    /// The send interval of the channel is not modified.
+    pub fn pause_channel(
+        &mut self,
+        channel_kind: ChannelKind,
+        max_buffered_bytes: usize,
+    ) -> Result<(), PacketError> {
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .ok_or(PacketError::ChannelNotFound)?;
+        channel
+            .sender
+            .evict(max_buffered_bytes, EvictionPolicy::DropOldest);
+        self.paused_channels
+            .insert(channel_kind, max_buffered_bytes);
+        Ok(())
+    }
+
+    /// Resume sending the messages of a channel that was paused with [`pause_channel`](Self::pause_channel)
+    pub fn resume_channel(&mut self, channel_kind: ChannelKind) {
+        self.paused_channels.remove(&channel_kind);
+    }
+
+    /// Returns true if the channel was paused with [`pause_channel`](Self::pause_channel)
+    pub fn is_channel_paused(&self, channel_kind: ChannelKind) -> bool {
+        self.paused_channels.contains_key(&channel_kind)
+    }
+
+    /// Shortest send interval among the channels whose interval was changed at runtime
+    pub(crate) fn fastest_send_interval_override(&self) -> Option<Duration> {
+        self.send_interval_overrides.values().min().copied()
+    }
+
+    /// Number of messages of a channel that were dropped because they stayed buffered for longer
+    /// than the channel's `max_age`
+    pub fn stale_messages_dropped(&self, channel_kind: ChannelKind) -> Result<usize, PacketError> {
+        Ok(self
+            .channels
+            .get(&channel_kind)
+            .ok_or(PacketError::ChannelNotFound)?
+            .sender
+            .stale_messages_dropped())
+    }
+
+    /// Enforce the [`MemoryConfig`] limits on the send and receive queues of every channel,
+    /// and add the bytes used and the number of messages evicted to the tallies
+    pub(crate) fn enforce_memory_limits(
+        &mut self,
+        config: &MemoryConfig,
+        send: &mut MemoryTally,
+        receive: &mut MemoryTally,
+    ) {
+        for channel in self.channels.values_mut() {
+            if let Some(max_bytes) = config.send_queues.max_bytes {
+                send.evictions += channel.sender.evict(max_bytes, config.send_queues.policy);
+            }
+            send.bytes += channel.sender.buffered_bytes();
+            if let Some(max_bytes) = config.receive_queues.max_bytes {
+                receive.evictions += channel
+                    .receiver
+                    .evict(max_bytes, config.receive_queues.policy);
+            }
+            receive.bytes += channel.receiver.buffered_bytes();
+        }
+    }
+
     /// Get the ChannelSendStats of a given channel
     #[cfg(feature = "trace")]
     pub fn channel_send_stats<C: crate::prelude::Channel>(&self) -> Option<&ChannelSendStats> {
@@ -435,6 +688,7 @@ mod tests {
     use crate::packet::packet::FRAGMENT_SIZE;
     use crate::packet::priority_manager::PriorityConfig;
     use crate::prelude::*;
+    use governor::Quota;
 
     use crate::tests::protocol::*;
 
@@ -635,6 +889,172 @@ mod tests {
         Ok(())
     }
 
+    /// Check that a smaller maximum packet size produces smaller fragments, and that they
+    /// get counted in the fragmentation stats
+    #[test]
+    fn test_max_packet_size() -> Result<(), PacketError> {
+        let (mut client_message_manager, mut server_message_manager) = setup();
+        client_message_manager.set_max_packet_size(500);
+
+        let message = Bytes::from(vec![1u8; 1000]);
+        let channel_kind_1 = ChannelKind::of::<Channel1>();
+        client_message_manager.buffer_send(message.clone(), channel_kind_1)?;
+        client_message_manager.buffer_send(vec![1].into(), channel_kind_1)?;
+        let payloads = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(payloads.len(), 3);
+        assert!(payloads.iter().all(|payload| payload.len() <= 500));
+        assert_eq!(
+            client_message_manager.fragmentation_stats(),
+            FragmentationStats {
+                messages: 2,
+                fragmented_messages: 1,
+                fragments: 3,
+            }
+        );
+
+        // the server uses the default packet size but can still reconstruct the message
+        for payload in payloads {
+            server_message_manager.recv_packet(payload.into())?;
+        }
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert!(data
+            .get(&channel_kind_1)
+            .unwrap()
+            .contains(&(Tick(0), message)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_send_interval() -> Result<(), PacketError> {
+        let (mut client_message_manager, mut server_message_manager) = setup();
+        client_message_manager
+            .set_channel_send_interval(Channel1::kind(), Duration::from_millis(100))?;
+        let mut time_manager = TimeManager::default();
+        let ping_manager = PingManager::new(PingConfig::default());
+        let tick_manager = TickManager::from_config(TickConfig::new(Duration::from_millis(50)));
+
+        let send_and_receive = |client: &mut MessageManager,
+                                server: &mut MessageManager|
+         -> Result<Vec<ChannelKind>, PacketError> {
+            for payload in client.send_packets(Tick(0))? {
+                server.recv_packet(payload.into())?;
+            }
+            Ok(server.read_messages().map(|(kind, _)| kind).collect())
+        };
+
+        // only the channel without a send interval sends its message right away
+        client_message_manager.buffer_send(vec![0].into(), Channel1::kind())?;
+        client_message_manager.buffer_send(vec![1].into(), Channel2::kind())?;
+        assert_eq!(
+            send_and_receive(&mut client_message_manager, &mut server_message_manager)?,
+            vec![Channel2::kind()]
+        );
+
+        // the message of Channel1 is flushed when its timer fires
+        time_manager.update(Duration::from_millis(100));
+        client_message_manager.update(&time_manager, &ping_manager, &tick_manager);
+        assert_eq!(
+            send_and_receive(&mut client_message_manager, &mut server_message_manager)?,
+            vec![Channel1::kind()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pause_channel() -> Result<(), PacketError> {
+        let (mut client_message_manager, mut server_message_manager) = setup();
+        let send_and_receive = |client: &mut MessageManager,
+                                server: &mut MessageManager|
+         -> Result<Vec<(ChannelKind, Bytes)>, PacketError> {
+            for payload in client.send_packets(Tick(0))? {
+                server.recv_packet(payload.into())?;
+            }
+            Ok(server
+                .read_messages()
+                .map(|(kind, (_, bytes))| (kind, bytes))
+                .collect())
+        };
+
+        // a paused channel keeps at most 2 bytes of messages buffered
+        client_message_manager.pause_channel(Channel1::kind(), 2)?;
+        assert!(client_message_manager.is_channel_paused(Channel1::kind()));
+        client_message_manager.buffer_send(vec![0].into(), Channel1::kind())?;
+        client_message_manager.buffer_send(vec![1].into(), Channel1::kind())?;
+        client_message_manager.buffer_send(vec![2].into(), Channel1::kind())?;
+        client_message_manager.buffer_send(vec![3].into(), Channel2::kind())?;
+        assert_eq!(
+            send_and_receive(&mut client_message_manager, &mut server_message_manager)?,
+            vec![(Channel2::kind(), vec![3].into())]
+        );
+
+        // the most recent messages are sent once the channel is resumed
+        client_message_manager.resume_channel(Channel1::kind());
+        assert_eq!(
+            send_and_receive(&mut client_message_manager, &mut server_message_manager)?,
+            vec![
+                (Channel1::kind(), vec![1].into()),
+                (Channel1::kind(), vec![2].into())
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_low_priority_messages_are_deferred() -> Result<(), PacketError> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            priority: 0.1,
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        let priority_config = PriorityConfig {
+            // 1KB/s, with bursts of 100 bytes
+            bandwidth_quota: Quota::per_second(nonzero_ext::nonzero!(1000u32))
+                .allow_burst(nonzero_ext::nonzero!(100u32)),
+            enabled: true,
+            ..default()
+        };
+        let mut client_message_manager =
+            MessageManager::new(&channel_registry, 1.5, priority_config.clone());
+        let mut server_message_manager =
+            MessageManager::new(&channel_registry, 1.5, priority_config);
+
+        let send_and_receive = |client: &mut MessageManager,
+                                server: &mut MessageManager|
+         -> Result<Vec<ChannelKind>, PacketError> {
+            for payload in client.send_packets(Tick(0))? {
+                server.recv_packet(payload.into())?;
+            }
+            Ok(server.read_messages().map(|(kind, _)| kind).collect())
+        };
+
+        // only one of the messages fits in the bandwidth quota: the message of the low priority channel is deferred
+        client_message_manager.buffer_send(vec![0; 60].into(), Channel1::kind())?;
+        client_message_manager.buffer_send(vec![1; 60].into(), Channel2::kind())?;
+        assert_eq!(
+            send_and_receive(&mut client_message_manager, &mut server_message_manager)?,
+            vec![Channel2::kind()]
+        );
+        assert!(client_message_manager
+            .priority_manager
+            .has_deferred_messages());
+
+        // the deferred message is sent once the bandwidth is available again
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            send_and_receive(&mut client_message_manager, &mut server_message_manager)?,
+            vec![Channel1::kind()]
+        );
+        assert!(!client_message_manager
+            .priority_manager
+            .has_deferred_messages());
+        Ok(())
+    }
+
     #[test]
     fn test_notify_ack() -> Result<(), PacketError> {
         let (mut client_message_manager, mut server_message_manager) = setup();
@@ -24,13 +24,25 @@ wrapping_id!(PacketId);
 /// Number of bytes to write the header
 const HEADER_BYTES: usize = 11;
 
-/// The maximum number of bytes for a message before it is fragmented
-/// MAX_PACKET_SIZE - HEADER_BYTES - 1 (channel_net_id) - 6 (message_id/fragment_id/num_fragments) - 2 (num bytes in fragment)
+/// Number of bytes written in a fragment packet in addition to the fragment bytes:
+/// 1 (channel_net_id) + 6 (message_id/fragment_id/num_fragments) + 2 (num bytes in fragment)
 #[cfg(feature = "big_messages")]
-pub(crate) const FRAGMENT_SIZE: usize = MAX_PACKET_SIZE - HEADER_BYTES - 9;
+const FRAGMENT_OVERHEAD_BYTES: usize = 9;
 
 #[cfg(not(feature = "big_messages"))]
-pub(crate) const FRAGMENT_SIZE: usize = MAX_PACKET_SIZE - HEADER_BYTES - 7;
+const FRAGMENT_OVERHEAD_BYTES: usize = 7;
+
+/// The smallest maximum packet size that can be configured
+pub(crate) const MIN_PACKET_SIZE: usize = 256;
+
+/// The maximum number of bytes for a message before it is fragmented, when using the default
+/// maximum packet size
+pub(crate) const FRAGMENT_SIZE: usize = fragment_size(MAX_PACKET_SIZE);
+
+/// The maximum number of bytes for a message before it is fragmented, for a given maximum packet size
+pub(crate) const fn fragment_size(max_packet_size: usize) -> usize {
+    max_packet_size - HEADER_BYTES - FRAGMENT_OVERHEAD_BYTES
+}
 
 /// Data structure that will help us write the packet
 #[derive(Debug)]
@@ -41,12 +53,14 @@ pub(crate) struct Packet {
     pub(crate) packet_id: PacketId,
     // How many bytes we know we are going to have to write in the packet, but haven't written yet
     pub(crate) prewritten_size: usize,
+    /// Maximum number of bytes that can be written in the packet
+    pub(crate) max_size: usize,
 }
 
 impl Packet {
     /// Check that we can still fit some data in the buffer
     pub(crate) fn can_fit(&self, size: usize) -> bool {
-        self.payload.len() + size + self.prewritten_size <= MAX_PACKET_SIZE
+        self.payload.len() + size + self.prewritten_size <= self.max_size
     }
 
     /// Check if we can write a channel_id + the number of messages in the packet.
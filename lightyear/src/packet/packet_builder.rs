@@ -1,6 +1,5 @@
 //! Module to take a buffer of messages to send and build packets
 use crate::connection::netcode::MAX_PACKET_SIZE;
-use byteorder::WriteBytesExt;
 use bytes::Bytes;
 use std::collections::VecDeque;
 #[cfg(feature = "trace")]
@@ -8,13 +7,14 @@ use tracing::{instrument, Level};
 
 use crate::packet::header::PacketHeaderManager;
 use crate::packet::message::{FragmentData, MessageAck, SingleData};
-use crate::packet::packet::{Packet, FRAGMENT_SIZE};
+use crate::packet::packet::{fragment_size, Packet};
 use crate::packet::packet_type::PacketType;
 use crate::prelude::Tick;
 use crate::protocol::channel::ChannelId;
 use crate::protocol::registry::NetId;
-use crate::serialize::varint::varint_len;
+use crate::serialize::varint::{varint_len, VarIntWriteExt};
 use crate::serialize::{SerializationError, ToBytes};
+use crate::utils::pool::Pool;
 
 pub type Payload = Vec<u8>;
 
@@ -25,12 +25,19 @@ pub type Payload = Vec<u8>;
 /// store subslices in receiver channels without allocating.
 pub type RecvPayload = Bytes;
 
+/// Maximum number of packet buffers kept in the pool for reuse
+const PACKET_BUFFER_POOL_SIZE: usize = 32;
+
 /// `PacketBuilder` handles the process of creating a packet (writing the header and packing the
 /// messages into packets)
 #[derive(Debug)]
 pub(crate) struct PacketBuilder {
     pub(crate) header_manager: PacketHeaderManager,
     current_packet: Option<Packet>,
+    /// Buffers of packets that have been sent, that can be reused to build new packets
+    buffer_pool: Pool<Payload>,
+    /// Maximum number of bytes in a packet
+    max_packet_size: usize,
     // Pre-allocated buffer to encode/decode without allocation.
     // TODO: should this be associated with Packet?
     // cursor: Vec<u8>,
@@ -45,6 +52,8 @@ impl PacketBuilder {
         Self {
             header_manager: PacketHeaderManager::new(nack_rtt_multiple),
             current_packet: None,
+            buffer_pool: Pool::from_vec(Vec::with_capacity(PACKET_BUFFER_POOL_SIZE)),
+            max_packet_size: MAX_PACKET_SIZE,
             // cursor: Vec::with_capacity(PACKET_BUFFER_CAPACITY),
             // acks: Vec::new(),
 
@@ -55,9 +64,32 @@ impl PacketBuilder {
         }
     }
 
-    // TODO: get the vec from a pool of preallocated buffers
+    /// Set the maximum number of bytes in a packet
+    pub(crate) fn set_max_packet_size(&mut self, max_packet_size: usize) {
+        self.max_packet_size = max_packet_size;
+    }
+
+    pub(crate) fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+
+    /// Get an empty buffer from the pool, or allocate a new one if the pool is empty
     fn get_new_buffer(&self) -> Payload {
-        Vec::with_capacity(MAX_PACKET_SIZE)
+        match self.buffer_pool.try_pull() {
+            Some(buffer) => {
+                let (_, mut buffer) = buffer.detach();
+                buffer.clear();
+                buffer
+            }
+            None => Vec::with_capacity(self.max_packet_size),
+        }
+    }
+
+    /// Return the buffer of a packet that has been sent to the pool, so that it can be reused
+    pub(crate) fn recycle_buffer(&mut self, buffer: Payload) {
+        if self.buffer_pool.len() < PACKET_BUFFER_POOL_SIZE {
+            self.buffer_pool.attach(buffer);
+        }
     }
 
     /// Start building new packet, we start with an empty packet
@@ -80,6 +112,7 @@ impl PacketBuilder {
             message_acks: vec![],
             packet_id: header.packet_id,
             prewritten_size: 0,
+            max_size: self.max_packet_size,
         });
         Ok(())
     }
@@ -112,6 +145,7 @@ impl PacketBuilder {
             )],
             packet_id: header.packet_id,
             prewritten_size: 0,
+            max_size: self.max_packet_size,
         });
         Ok(())
 
@@ -138,10 +172,8 @@ impl PacketBuilder {
     }
 
     pub fn finish_packet(&mut self) -> Packet {
-        let mut packet = self.current_packet.take().unwrap();
-        packet.payload.shrink_to_fit();
-        // TODO: should we use bytes so this clone is cheap?
-        packet
+        // NOTE: we don't shrink the payload, so that the allocation can be reused via the buffer pool
+        self.current_packet.take().unwrap()
     }
 
     /// Pack messages into packets
@@ -172,7 +204,7 @@ impl PacketBuilder {
         // try to fill the packet with fragment messages first
         for (channel_id, mut fragment_messages) in fragment_data.into_iter() {
             while let Some(fragment_data) = fragment_messages.pop_front() {
-                debug_assert!(fragment_data.bytes.len() <= FRAGMENT_SIZE);
+                debug_assert!(fragment_data.bytes.len() <= fragment_size(self.max_packet_size));
                 self.build_new_fragment_packet(channel_id, &fragment_data, current_tick)?;
                 if !fragment_data.is_last_fragment() {
                     // big fragment, write packet immediately
@@ -204,8 +236,11 @@ impl PacketBuilder {
                                 break;
                             }
 
-                            if packet.can_fit(single_messages[num_messages].len()) {
-                                packet.prewritten_size += single_messages[num_messages].len();
+                            if Self::try_reserve_message(
+                                &mut packet,
+                                &single_messages[num_messages],
+                                num_messages,
+                            ) {
                                 num_messages += 1;
                             } else {
                                 // can't add any more messages (since we sorted messages from smallest to largest)
@@ -265,8 +300,11 @@ impl PacketBuilder {
                     break;
                 }
 
-                if packet.can_fit(single_messages[num_messages].len()) {
-                    packet.prewritten_size += single_messages[num_messages].len();
+                if Self::try_reserve_message(
+                    &mut packet,
+                    &single_messages[num_messages],
+                    num_messages,
+                ) {
                     num_messages += 1;
                 } else {
                     // can't add any more messages (since we sorted messages from smallest to largest)
@@ -291,6 +329,21 @@ impl PacketBuilder {
         Ok(packets)
     }
 
+    /// Reserve space in the packet for one more message of the current channel, if it fits.
+    ///
+    /// The number of messages of the channel is written as a varint, so it takes an extra byte
+    /// once the channel has more than 63 messages in the packet. This lets us coalesce many
+    /// small messages (for example the replication messages of many small groups) in the same packet.
+    fn try_reserve_message(packet: &mut Packet, message: &SingleData, num_messages: usize) -> bool {
+        let size =
+            message.len() + varint_len(num_messages as u64 + 1) - varint_len(num_messages as u64);
+        if !packet.can_fit(size) {
+            return false;
+        }
+        packet.prewritten_size += size;
+        true
+    }
+
     /// Helper function to fill the current packet with single data message from the current channel
     fn write_single_messages(
         packet: &mut Packet,
@@ -300,12 +353,12 @@ impl PacketBuilder {
     ) -> Result<(), SerializationError> {
         packet.prewritten_size = packet
             .prewritten_size
-            .checked_sub(varint_len(channel_id as u64) + 1)
+            .checked_sub(varint_len(channel_id as u64) + varint_len(*num_messages as u64))
             .ok_or(SerializationError::SubstractionOverflow)?;
         if *num_messages > 0 {
             channel_id.to_bytes(&mut packet.payload)?;
             // write the number of messages for the current channel
-            packet.payload.write_u8(*num_messages as u8).unwrap();
+            packet.payload.write_varint(*num_messages as u64)?;
             // write the messages
             for _ in 0..*num_messages {
                 // TODO: deal with error
@@ -426,6 +479,7 @@ mod tests {
 
     use crate::channel::senders::fragment_sender::FragmentSender;
     use crate::packet::message::MessageId;
+    use crate::packet::packet::FRAGMENT_SIZE;
     use crate::prelude::*;
 
     use super::*;
@@ -568,6 +622,29 @@ mod tests {
         Ok(())
     }
 
+    /// Many tiny messages on the same channel (for example the updates of many small replication groups)
+    /// are coalesced in the same packet, even if there are more than 63 of them
+    #[test]
+    fn test_pack_many_tiny_messages_same_channel() -> Result<(), PacketError> {
+        let channel_registry = get_channel_registry();
+        let mut manager = PacketBuilder::new(1.5);
+        let channel_kind1 = ChannelKind::of::<Channel1>();
+        let channel_id1 = channel_registry.get_net_from_kind(&channel_kind1).unwrap();
+
+        let tiny_bytes = Bytes::from(vec![7u8; 3]);
+        let tiny_message = SingleData::new(None, tiny_bytes.clone());
+
+        let single_data = vec![(
+            *channel_id1,
+            VecDeque::from(vec![tiny_message.clone(); 200]),
+        )];
+        let mut packets = manager.build_packets(Tick(0), single_data, vec![])?;
+        assert_eq!(packets.len(), 1);
+        let contents = packets.pop().unwrap().parse_packet_payload()?;
+        assert_eq!(contents.get(channel_id1).unwrap(), &vec![tiny_bytes; 200]);
+        Ok(())
+    }
+
     /// A bunch of small messages that fit in multiple packets
     #[test]
     fn test_pack_single_data_multiple_packets() -> Result<(), PacketError> {
@@ -14,7 +14,8 @@ use crate::prelude::{ChannelRegistry, Tick};
 use crate::protocol::channel::ChannelId;
 use crate::protocol::registry::NetId;
 
-const BYPASS_QUOTA_PRIORITY: f32 = 100000.0;
+/// Messages with a priority above this value are sent even if the bandwidth quota is exceeded
+pub(crate) const BYPASS_QUOTA_PRIORITY: f32 = 100000.0;
 
 #[derive(Debug)]
 pub struct BufferedMessage {
@@ -29,6 +30,11 @@ pub struct PriorityConfig {
     pub bandwidth_quota: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub enabled: bool,
+    /// Maximum number of bytes of messages that are kept to be sent later when the bandwidth quota is reached.
+    ///
+    /// See [`ChannelRegistry::is_deferrable`] for the messages that can be deferred. When the buffer is full,
+    /// the messages with the lowest priority are dropped.
+    pub max_deferred_bytes: usize,
 }
 
 // this is mostly for testing
@@ -38,6 +44,7 @@ impl Default for PriorityConfig {
             // 56 KB/s bandwidth cap
             bandwidth_quota: Quota::per_second(nonzero!(56000u32)),
             enabled: false,
+            max_deferred_bytes: 16 * 1024,
         }
     }
 }
@@ -47,6 +54,7 @@ impl From<crate::client::config::PacketConfig> for PriorityConfig {
         Self {
             bandwidth_quota: value.send_bandwidth_cap,
             enabled: value.bandwidth_cap_enabled,
+            max_deferred_bytes: value.max_deferred_bytes,
         }
     }
 }
@@ -56,6 +64,7 @@ impl From<crate::server::config::PacketConfig> for PriorityConfig {
         Self {
             bandwidth_quota: value.per_client_send_bandwidth_cap,
             enabled: value.bandwidth_cap_enabled,
+            max_deferred_bytes: value.max_deferred_bytes,
         }
     }
 }
@@ -68,8 +77,9 @@ pub(crate) struct PriorityManager {
     // // Internal buffer of data that we want to send
     // // Reuse allocation across frames
     // data_to_send: BTreeMap<ChannelId, (VecDeque<SendMessage>, VecDeque<SendMessage>)>,
-    // Messages that could not be sent because of the bandwidth quota
-    // buffered_data: Vec<BufferedMessage>,
+    /// Messages that could not be sent because of the bandwidth quota, and that will be sent
+    /// during the next frames (sorted from highest to lowest priority)
+    deferred: Vec<BufferedMessage>,
     /// List of senders to notify when a replication update message is actually sent (included in packet)
     replication_update_senders: Vec<Sender<MessageId>>,
 }
@@ -80,11 +90,16 @@ impl PriorityManager {
             config: config.clone(),
             limiter: DefaultDirectRateLimiter::direct(config.bandwidth_quota),
             // data_to_send: BTreeMap::new(),
-            // buffered_data: Vec::new(),
+            deferred: Vec::new(),
             replication_update_senders: Vec::new(),
         }
     }
 
+    /// Returns true if some messages were deferred because of the bandwidth quota
+    pub(crate) fn has_deferred_messages(&self) -> bool {
+        !self.deferred.is_empty()
+    }
+
     /// Create a channel to notify when a replication update message is actually sent (included in packet)
     /// (as opposed to dropped because of the bandwidth quota)
     pub(crate) fn subscribe_replication_update_sent_messages(&mut self) -> Receiver<MessageId> {
@@ -97,6 +112,11 @@ impl PriorityManager {
     /// Filter the messages by priority and bandwidth quota
     /// Returns the list of messages that we can send, along with the amount of bytes we used
     /// in the rate limiter.
+    ///
+    /// Messages with the same priority are sent in the order in which they were buffered.
+    ///
+    /// Messages of the deferrable channels that don't fit in the bandwidth quota are kept and sent during the
+    /// next frames, before the new messages that have the same priority. The other messages are dropped.
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     pub(crate) fn priority_filter(
         &mut self,
@@ -142,10 +162,12 @@ impl PriorityManager {
             return (single_data, fragment_data, 0);
         }
 
-        // compute the priority of each new message
-        let mut all_messages = data
+        // compute the priority of each new message.
+        // The deferred messages come first, so that they are sent before the new messages with the same priority
+        let deferred = std::mem::take(&mut self.deferred);
+        let mut all_messages = deferred
             .into_iter()
-            .flat_map(|(net_id, (single, fragment))| {
+            .chain(data.into_iter().flat_map(|(net_id, (single, fragment))| {
                 let channel_priority = channel_registry
                     .get_builder_from_net_id(net_id)
                     .unwrap()
@@ -168,11 +190,12 @@ impl PriorityManager {
                             data: fragment.data,
                         }
                     }))
-            })
+            }))
             .collect::<Vec<_>>();
 
-        // sort from highest priority to lower
-        all_messages.sort_by(|a, b| a.priority.partial_cmp(&b.priority).unwrap());
+        // sort from highest priority to lower.
+        // The sort is stable: messages with the same priority are sent in the order in which they were buffered
+        all_messages.sort_by(|a, b| b.priority.total_cmp(&a.priority));
         debug!(
             "all messages to send, sorted by priority: {:?}",
             all_messages
@@ -182,13 +205,16 @@ impl PriorityManager {
         let mut single_data: HashMap<ChannelId, VecDeque<SingleData>> = HashMap::new();
         let mut fragment_data: HashMap<ChannelId, VecDeque<FragmentData>> = HashMap::new();
         let mut bytes_used = 0;
-        while let Some(buffered_message) = all_messages.pop() {
+        let mut all_messages = all_messages.into_iter().peekable();
+        while let Some(buffered_message) = all_messages.peek() {
             // we don't use the exact size of the message, but the size of the bytes
             // we will adjust for this later
             let message_bytes = buffered_message.data.len() as u32;
             let nonzero_message_bytes = NonZeroU32::try_from(message_bytes).unwrap();
             let Ok(result) = self.limiter.check_n(nonzero_message_bytes) else {
                 error!("the bandwidth does not have enough capacity for a message of this size!");
+                // the message can never be sent, don't defer it
+                all_messages.next();
                 break;
             };
 
@@ -199,6 +225,7 @@ impl PriorityManager {
                     break;
                 };
             }
+            let buffered_message = all_messages.next().unwrap();
             trace!(channel=?buffered_message.channel_net_id, "Sending message with priority {:?}", buffered_message.priority);
 
             // keep track of the bytes we added to the rate limiter
@@ -236,6 +263,21 @@ impl PriorityManager {
             }
         }
 
+        // the messages of the deferrable channels that don't make the cut are sent during the next frames,
+        // the messages with the lowest priority are dropped if there are too many of them
+        let mut deferred_bytes = 0;
+        let mut num_messages_discarded = 0;
+        for buffered_message in all_messages {
+            if channel_registry.is_deferrable(buffered_message.channel_net_id)
+                && deferred_bytes + buffered_message.data.len() <= self.config.max_deferred_bytes
+            {
+                deferred_bytes += buffered_message.data.len();
+                self.deferred.push(buffered_message);
+            } else {
+                num_messages_discarded += 1;
+            }
+        }
+
         // all the other messages that don't make the cut, we just drop
         // - unreliable messages: they are unreliable so it's ok
         // - reliable messages: they will be retried later, maybe with higher priority?
@@ -248,7 +290,8 @@ impl PriorityManager {
         debug!(
             bytes_sent = ?bytes_used,
             ?num_messages_sent,
-            num_messages_discarded = ?all_messages.len(),
+            num_messages_deferred = ?self.deferred.len(),
+            ?num_messages_discarded,
             "priority filter done.");
 
         (
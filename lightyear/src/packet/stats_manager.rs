@@ -14,6 +14,8 @@ pub(crate) mod packet {
         num_sent_packets_acked: u32,
         num_sent_packets_lost: u32,
         num_received_packets: u32,
+        /// Number of packets that the remote sent to us, inferred from the gaps between the ids of the received packets
+        num_expected_packets: u32,
     }
 
     impl AddAssign for PacketStats {
@@ -22,6 +24,7 @@ pub(crate) mod packet {
             self.num_sent_packets_acked += other.num_sent_packets_acked;
             self.num_sent_packets_lost += other.num_sent_packets_lost;
             self.num_received_packets += other.num_received_packets;
+            self.num_expected_packets += other.num_expected_packets;
         }
     }
 
@@ -31,12 +34,14 @@ pub(crate) mod packet {
             self.num_sent_packets_acked -= other.num_sent_packets_acked;
             self.num_sent_packets_lost -= other.num_sent_packets_lost;
             self.num_received_packets -= other.num_received_packets;
+            self.num_expected_packets -= other.num_expected_packets;
         }
     }
 
     #[derive(Default, Debug)]
     struct FinalStats {
         packet_loss: f32,
+        incoming_packet_loss: f32,
     }
 
     #[derive(Debug)]
@@ -90,7 +95,27 @@ pub(crate) mod packet {
             trace!("packet loss: {}", self.final_stats.packet_loss);
         }
 
+        /// Fraction of the sent packets that were lost over the stats buffer duration
+        pub(crate) fn packet_loss(&self) -> f32 {
+            self.final_stats.packet_loss
+        }
+
+        /// Fraction of the packets sent by the remote that we did not receive over the stats buffer duration
+        pub(crate) fn incoming_packet_loss(&self) -> f32 {
+            self.final_stats.incoming_packet_loss
+        }
+
         fn compute_stats(&mut self) {
+            if self.rolling_stats.num_expected_packets > 0 {
+                // packets that arrive out of order are counted as received but not as expected
+                self.final_stats.incoming_packet_loss = (1.0
+                    - self.rolling_stats.num_received_packets as f32
+                        / self.rolling_stats.num_expected_packets as f32)
+                    .max(0.0);
+                #[cfg(feature = "metrics")]
+                metrics::gauge!("incoming_packet_loss")
+                    .set(self.final_stats.incoming_packet_loss as f64);
+            }
             if self.rolling_stats.num_sent_packets > 0 {
                 self.final_stats.packet_loss = self.rolling_stats.num_sent_packets_lost as f32
                     / self.rolling_stats.num_sent_packets as f32;
@@ -124,12 +149,16 @@ pub(crate) mod packet {
             self.current_stats.num_sent_packets_acked += 1;
         }
 
-        /// Notify that we received a packet
-        pub(crate) fn received_packet(&mut self) {
+        /// Notify that we received a packet.
+        ///
+        /// `expected` is the number of packets that the remote sent since the most recent packet we received
+        /// (1 if there is no gap, 0 if the packet arrived out of order)
+        pub(crate) fn received_packet(&mut self, expected: u32) {
             #[cfg(feature = "metrics")]
             metrics::counter!("received_packet").increment(1);
 
             self.current_stats.num_received_packets += 1;
+            self.current_stats.num_expected_packets += expected;
         }
     }
 
@@ -170,6 +199,7 @@ pub(crate) mod packet {
                     num_sent_packets_acked: 0,
                     num_sent_packets_lost: 1,
                     num_received_packets: 0,
+                    num_expected_packets: 0,
                 }
             );
             packet_stats_manager.update(&time_manager);
@@ -191,10 +221,31 @@ pub(crate) mod packet {
                     num_sent_packets_acked: 0,
                     num_sent_packets_lost: 1,
                     num_received_packets: 0,
+                    num_expected_packets: 0,
                 }
             );
             packet_stats_manager.compute_stats();
             assert_eq!(packet_stats_manager.final_stats.packet_loss, 1.0 / 2.0);
         }
+
+        #[test]
+        fn test_incoming_packet_loss() {
+            let mut time_manager = TimeManager::default();
+            let mut packet_stats_manager = PacketStatsManager::new(Duration::from_secs(2));
+            time_manager.update(Duration::from_secs(3));
+
+            // received packets 0, 1, 3: packet 2 is missing
+            packet_stats_manager.received_packet(1);
+            packet_stats_manager.received_packet(1);
+            packet_stats_manager.received_packet(2);
+            packet_stats_manager.update(&time_manager);
+            assert_eq!(packet_stats_manager.incoming_packet_loss(), 1.0 / 4.0);
+
+            // packet 2 arrives late
+            packet_stats_manager.received_packet(0);
+            time_manager.update(Duration::from_millis(100));
+            packet_stats_manager.update(&time_manager);
+            assert_eq!(packet_stats_manager.incoming_packet_loss(), 0.0);
+        }
     }
 }
@@ -1,11 +1,19 @@
 use bevy::app::App;
+use bevy::ecs::world::Command;
+use bevy::prelude::{Commands, World};
 use bevy::prelude::{Resource, TypePath};
 use bevy::utils::Duration;
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use tracing::info;
 
 use crate::channel::builder::{
-    AuthorityChannel, Channel, ChannelBuilder, ChannelSettings, PongChannel,
+    AdminChannel, AuthorityChannel, Channel, ChannelBuilder, ChannelSettings, DespawnGroupsChannel,
+    DisconnectChannel, EventChannel, IntegrityChannel, InterestChannel, JoinSnapshotChannel,
+    OrderedResourceChannel, PongChannel, PresentationChannel, ProtocolCheckChannel,
+    ProximityChannel, RngChannel, SessionChannel, SyncChannel, WorldSeedChannel,
 };
 use crate::channel::builder::{
     ChannelContainer, EntityActionsChannel, EntityUpdatesChannel, InputChannel, PingChannel,
@@ -20,6 +28,26 @@ pub struct ChannelKind(TypeId);
 
 pub type ChannelId = NetId;
 
+/// First network id of the range used by the channels that are registered after startup
+pub(crate) const DYNAMIC_CHANNEL_NET_ID_START: ChannelId = 1 << 13;
+/// Number of network ids reserved for the channels that are registered after startup
+pub(crate) const DYNAMIC_CHANNEL_NET_ID_RANGE: ChannelId = 1 << 13;
+
+/// Network id of a channel registered after startup, derived from the name of the channel
+pub(crate) fn dynamic_channel_net_id(name: &str) -> ChannelId {
+    // the SeaHasher is deterministic across processes, which is not the case of the default hasher
+    let mut hasher = seahash::SeaHasher::new();
+    name.hash(&mut hasher);
+    DYNAMIC_CHANNEL_NET_ID_START
+        + (hasher.finish() % DYNAMIC_CHANNEL_NET_ID_RANGE as u64) as ChannelId
+}
+
+/// Returns true if the network id belongs to the range of the channels registered after startup
+pub(crate) fn is_dynamic_channel_net_id(net_id: ChannelId) -> bool {
+    (DYNAMIC_CHANNEL_NET_ID_START..DYNAMIC_CHANNEL_NET_ID_START + DYNAMIC_CHANNEL_NET_ID_RANGE)
+        .contains(&net_id)
+}
+
 impl ChannelKind {
     pub fn of<C: Channel>() -> Self {
         Self(TypeId::of::<C>())
@@ -57,13 +85,35 @@ impl From<TypeId> for ChannelKind {
 /// # }
 /// ```
 ///
+/// ### Adding channels after startup
 ///
+/// Channels added with [`add_channel`](ChannelRegistry::add_channel) get their network id in the order in which
+/// they are registered, so all of them must be registered before the client and the server connect.
+///
+/// Channels that are added later (for example by a mod that is loaded at runtime) can be registered with
+/// [`DynamicChannelExt::add_dynamic_channel`]. Their network id is derived from a hash of the channel's name,
+/// so the client and the server assign the same id without having to register the channels in the same order,
+/// and the [`ProtocolHash`](crate::protocol::version::ProtocolHash) is not modified.
+/// The channel is added to the existing connections right away.
+///
+/// A peer that did not register a dynamic channel discards the messages that it receives on it.
+///
+/// ```rust,ignore
+/// fn load_mod(mut commands: Commands) {
+///     commands.add_dynamic_channel::<ModChannel>(ChannelSettings {
+///         mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+///         ..default()
+///     });
+/// }
+/// ```
 #[derive(Resource, Default, Clone, Debug, PartialEq, TypePath)]
 pub struct ChannelRegistry {
     // we only store the ChannelBuilder because we might want to create multiple instances of the same channel
     pub(in crate::protocol) builder_map: HashMap<ChannelKind, ChannelBuilder>,
     pub(in crate::protocol) kind_map: TypeMapper<ChannelKind>,
     pub(in crate::protocol) name_map: HashMap<ChannelKind, String>,
+    /// Channels that can be used to send entity actions, in addition to the [`EntityActionsChannel`]
+    replication_actions_channels: HashSet<ChannelKind>,
     built: bool,
 }
 
@@ -73,6 +123,7 @@ impl ChannelRegistry {
             builder_map: HashMap::new(),
             kind_map: TypeMapper::new(),
             name_map: HashMap::new(),
+            replication_actions_channels: HashSet::new(),
             built: false,
         };
         registry.add_channel::<EntityUpdatesChannel>(ChannelSettings {
@@ -83,6 +134,7 @@ impl ChannelRegistry {
             // directly on the replication_sender
             send_frequency: Duration::default(),
             priority: 1.0,
+            max_age: None,
         });
         registry.add_channel::<EntityActionsChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
@@ -93,30 +145,132 @@ impl ChannelRegistry {
             send_frequency: Duration::default(),
             // we want to send the entity actions as soon as possible
             priority: 10.0,
+            max_age: None,
         });
         registry.add_channel::<PingChannel>(ChannelSettings {
             mode: ChannelMode::SequencedUnreliable,
             send_frequency: Duration::default(),
             // we always want to include the ping in the packet
             priority: f32::INFINITY,
+            max_age: None,
         });
         registry.add_channel::<PongChannel>(ChannelSettings {
             mode: ChannelMode::SequencedUnreliable,
             send_frequency: Duration::default(),
             // we always want to include the pong in the packet
             priority: f32::INFINITY,
+            max_age: None,
         });
         registry.add_channel::<InputChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             send_frequency: input_send_interval,
             // we always want to include the inputs in the packet
             priority: f32::INFINITY,
+            max_age: None,
         });
         registry.add_channel::<AuthorityChannel>(ChannelSettings {
             mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
             send_frequency: Duration::default(),
             // we want to send the authority transfers as soon as possible
             priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<DespawnGroupsChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            // same priority as the entity actions
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<InterestChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 1.0,
+            max_age: None,
+        });
+        registry.add_channel::<SyncChannel>(ChannelSettings {
+            mode: ChannelMode::SequencedUnreliable,
+            send_frequency: Duration::default(),
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<EventChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 1.0,
+            max_age: None,
+        });
+        registry.add_channel::<DisconnectChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            // the client is about to be disconnected, there is no point in sending anything else first
+            priority: f32::INFINITY,
+            max_age: None,
+        });
+        registry.add_channel::<ProtocolCheckChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<SessionChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<AdminChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 1.0,
+            max_age: None,
+        });
+        registry.add_channel::<RngChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 1.0,
+            max_age: None,
+        });
+        registry.add_channel::<ProximityChannel>(ChannelSettings {
+            mode: ChannelMode::SequencedUnreliable,
+            send_frequency: Duration::default(),
+            priority: 1.0,
+            max_age: None,
+        });
+        registry.add_channel::<JoinSnapshotChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            // the client cannot see the world until it receives the snapshot
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<WorldSeedChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            // the replication to the client is held until the world is generated
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<OrderedResourceChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            // the replication of the entities is held until these messages are received
+            priority: 10.0,
+            max_age: None,
+        });
+        registry.add_channel::<PresentationChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            send_frequency: Duration::default(),
+            // cosmetic events are sent after the rest of the data if the bandwidth is limited
+            priority: 0.5,
+            // a cosmetic event that arrives late is worse than no event
+            max_age: Some(Duration::from_millis(200)),
+        });
+        registry.add_channel::<IntegrityChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            send_frequency: Duration::default(),
+            priority: 0.5,
+            max_age: None,
         });
         registry
     }
@@ -125,7 +279,44 @@ impl ChannelRegistry {
     pub(crate) fn is_replication_channel(&self, net_id: NetId) -> bool {
         self.kind_map.kind(net_id).map_or(false, |kind| {
             *kind == ChannelKind::of::<EntityUpdatesChannel>()
-                || *kind == ChannelKind::of::<EntityActionsChannel>()
+                || self.is_replication_actions_channel(kind)
+        })
+    }
+
+    /// Returns true if the channel is used to send entity actions
+    pub(crate) fn is_replication_actions_channel(&self, kind: &ChannelKind) -> bool {
+        *kind == ChannelKind::of::<EntityActionsChannel>()
+            || self.replication_actions_channels.contains(kind)
+    }
+
+    /// Returns true if the channel only delivers the most recent messages
+    pub(crate) fn is_sequenced(&self, kind: &ChannelKind) -> bool {
+        self.builder_map.get(kind).is_some_and(|builder| {
+            matches!(
+                builder.settings.mode,
+                ChannelMode::SequencedReliable(_) | ChannelMode::SequencedUnreliable
+            )
+        })
+    }
+
+    /// Returns true if the messages of the channel can be kept to be sent later when the bandwidth quota is reached,
+    /// instead of being dropped.
+    ///
+    /// This is the case for the user channels that are unordered and unreliable:
+    /// - reliable channels already resend the messages that were not sent
+    /// - sequenced channels only care about the most recent message
+    /// - channels with a `max_age` prefer dropping the messages that are late
+    /// - the replication channels keep track of the updates that could not be sent
+    pub(crate) fn is_deferrable(&self, net_id: NetId) -> bool {
+        if self.is_replication_channel(net_id) {
+            return false;
+        }
+        self.get_builder_from_net_id(net_id).is_some_and(|builder| {
+            builder.settings.max_age.is_none()
+                && matches!(
+                    builder.settings.mode,
+                    ChannelMode::UnorderedUnreliable | ChannelMode::UnorderedUnreliableWithAcks
+                )
         })
     }
 
@@ -151,12 +342,78 @@ impl ChannelRegistry {
 
     /// Register a new type
     pub fn add_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        assert!(
+            settings.max_age.is_none() || !settings.mode.is_reliable(),
+            "The channel {} cannot have a max_age because it is reliable",
+            C::name()
+        );
+        assert!(
+            self.kind_map.next_net_id < DYNAMIC_CHANNEL_NET_ID_START,
+            "Too many channels registered in the protocol"
+        );
         let kind = self.kind_map.add::<C>();
         self.builder_map.insert(kind, C::get_builder(settings));
         let name = C::name();
         self.name_map.insert(kind, name.to_string());
     }
 
+    /// Register a new channel whose network id is derived from its name.
+    ///
+    /// This can be done after the client and the server are connected; see
+    /// [adding channels after startup](ChannelRegistry#adding-channels-after-startup).
+    ///
+    /// Panics if the hash of the name collides with another dynamic channel; in that case the channel
+    /// must be renamed.
+    pub fn add_dynamic_channel<C: Channel>(&mut self, settings: ChannelSettings) -> ChannelKind {
+        assert!(
+            settings.max_age.is_none() || !settings.mode.is_reliable(),
+            "The channel {} cannot have a max_age because it is reliable",
+            C::name()
+        );
+        let name = C::name();
+        let kind = self
+            .kind_map
+            .add_with_net_id::<C>(dynamic_channel_net_id(name));
+        self.builder_map.insert(kind, C::get_builder(settings));
+        self.name_map.insert(kind, name.to_string());
+        kind
+    }
+
+    /// Register a new channel that can be used to send the entity actions of a
+    /// [`ReplicationGroup`](crate::prelude::ReplicationGroup).
+    ///
+    /// The channel must be reliable. If it is sequenced, older actions messages are dropped on the receiver,
+    /// see [`ReplicationGroup::set_actions_channel`](crate::prelude::ReplicationGroup::set_actions_channel).
+    pub fn add_replication_actions_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        assert!(
+            settings.mode.is_reliable(),
+            "The channel {} used to send entity actions must be reliable",
+            C::name()
+        );
+        self.add_channel::<C>(settings);
+        self.replication_actions_channels
+            .insert(ChannelKind::of::<C>());
+    }
+
+    /// Iterate through the channels that have a `max_age`, along with their name
+    pub(crate) fn channels_with_max_age(&self) -> impl Iterator<Item = (&ChannelKind, &str)> {
+        self.builder_map
+            .iter()
+            .filter(|(_, builder)| builder.settings.max_age.is_some())
+            .filter_map(|(kind, _)| Some((kind, self.name_map.get(kind)?.as_str())))
+    }
+
+    /// Iterate through the channels whose priority is lower than `priority`, along with their settings
+    pub(crate) fn channels_below_priority(
+        &self,
+        priority: f32,
+    ) -> impl Iterator<Item = (&ChannelKind, &ChannelSettings)> {
+        self.builder_map
+            .iter()
+            .filter(move |(_, builder)| builder.settings.priority < priority)
+            .map(|(kind, builder)| (kind, &builder.settings))
+    }
+
     /// get the registered object for a given type
     pub fn get_builder_from_kind(&self, channel_kind: &ChannelKind) -> Option<&ChannelBuilder> {
         self.builder_map.get(channel_kind)
@@ -188,6 +445,11 @@ impl ChannelRegistry {
 /// Add a message to the list of messages that can be sent
 pub trait AppChannelExt {
     fn add_channel<C: Channel>(&mut self, settings: ChannelSettings);
+
+    /// Add a channel that can be used to send the entity actions of a [`ReplicationGroup`](crate::prelude::ReplicationGroup).
+    ///
+    /// See [`ReplicationGroup::set_actions_channel`](crate::prelude::ReplicationGroup::set_actions_channel)
+    fn add_replication_actions_channel<C: Channel>(&mut self, settings: ChannelSettings);
 }
 
 impl AppChannelExt for App {
@@ -195,6 +457,62 @@ impl AppChannelExt for App {
         let mut registry = self.world_mut().resource_mut::<ChannelRegistry>();
         registry.add_channel::<C>(settings);
     }
+
+    fn add_replication_actions_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        let mut registry = self.world_mut().resource_mut::<ChannelRegistry>();
+        registry.add_replication_actions_channel::<C>(settings);
+    }
+}
+
+/// Register channels after the app has started
+pub trait DynamicChannelExt {
+    /// Add a channel whose network id is derived from its name, and add it to the existing connections.
+    ///
+    /// See [adding channels after startup](ChannelRegistry#adding-channels-after-startup).
+    fn add_dynamic_channel<C: Channel>(&mut self, settings: ChannelSettings);
+}
+
+impl DynamicChannelExt for World {
+    fn add_dynamic_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        let mut registry = self.resource_mut::<ChannelRegistry>();
+        let kind = registry.add_dynamic_channel::<C>(settings);
+        let registry = registry.clone();
+        info!(
+            channel = C::name(),
+            net_id = ?registry.get_net_from_kind(&kind),
+            "Registered dynamic channel"
+        );
+        if let Some(mut manager) =
+            self.get_resource_mut::<crate::server::connection::ConnectionManager>()
+        {
+            manager.add_dynamic_channel(kind, &registry);
+        }
+        if let Some(mut manager) =
+            self.get_resource_mut::<crate::client::connection::ConnectionManager>()
+        {
+            manager.message_manager.add_channel(kind, &registry);
+        }
+    }
+}
+
+impl DynamicChannelExt for Commands<'_, '_> {
+    fn add_dynamic_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        self.add(AddDynamicChannel::<C> {
+            settings,
+            marker: PhantomData,
+        });
+    }
+}
+
+struct AddDynamicChannel<C> {
+    settings: ChannelSettings,
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<C: Channel> Command for AddDynamicChannel<C> {
+    fn apply(self, world: &mut World) {
+        world.add_dynamic_channel::<C>(self.settings);
+    }
 }
 
 #[cfg(test)]
@@ -227,4 +545,74 @@ mod tests {
             ChannelMode::UnorderedUnreliable
         );
     }
+
+    #[derive(ChannelInternal, TypePath)]
+    pub struct ModChannel;
+
+    #[test]
+    fn test_dynamic_channel_net_id() {
+        let settings = ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        };
+        // the id does not depend on the channels that were registered before
+        let mut registry = ChannelRegistry::default();
+        registry.add_dynamic_channel::<ModChannel>(settings.clone());
+        let mut other = ChannelRegistry::default();
+        other.add_channel::<MyChannel>(settings.clone());
+        other.add_dynamic_channel::<ModChannel>(settings);
+        let net_id = *registry
+            .get_net_from_kind(&ChannelKind::of::<ModChannel>())
+            .unwrap();
+        assert_eq!(
+            other.get_net_from_kind(&ChannelKind::of::<ModChannel>()),
+            Some(&net_id)
+        );
+        assert!(is_dynamic_channel_net_id(net_id));
+    }
+
+    /// A channel registered after the client and the server are connected can be used right away
+    #[test]
+    fn test_add_dynamic_channel_after_connection() {
+        use crate::prelude::{server, NetworkTarget};
+        use crate::tests::protocol::StringMessage;
+        use crate::tests::stepper::BevyStepper;
+        use bevy::ecs::event::{Events, ManualEventReader};
+
+        let mut stepper = BevyStepper::default();
+        let settings = ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        };
+        stepper
+            .server_app
+            .world_mut()
+            .add_dynamic_channel::<ModChannel>(settings.clone());
+        stepper
+            .client_app
+            .world_mut()
+            .add_dynamic_channel::<ModChannel>(settings);
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .send_message_to_target::<ModChannel, StringMessage>(
+                &mut StringMessage("a".to_string()),
+                NetworkTarget::All,
+            )
+            .unwrap();
+        let mut reader =
+            ManualEventReader::<crate::client::events::MessageEvent<StringMessage>>::default();
+        let mut received = 0;
+        for _ in 0..5 {
+            stepper.frame_step();
+            let events = stepper
+                .client_app
+                .world()
+                .resource::<Events<crate::client::events::MessageEvent<StringMessage>>>();
+            received += reader.read(events).count();
+        }
+        assert_eq!(received, 1);
+    }
 }
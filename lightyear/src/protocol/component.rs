@@ -1,13 +1,14 @@
 use bevy::ecs::component::ComponentId;
 use bevy::ecs::entity::MapEntities;
-use std::any::TypeId;
+use std::any::{Any, TypeId};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::{Add, Mul};
+use std::sync::Arc;
 
 use bevy::prelude::{App, Component, EntityWorldMut, Mut, Resource, TypePath, World};
 use bevy::ptr::Ptr;
-use bevy::utils::HashMap;
+use bevy::utils::{Duration, HashMap, HashSet};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -15,7 +16,9 @@ use tracing::{debug, error, trace};
 
 use crate::client::components::ComponentSyncMode;
 use crate::client::config::ClientConfig;
-use crate::client::interpolation::{add_interpolation_systems, add_prepare_interpolation_systems};
+use crate::client::interpolation::{
+    add_extrapolation_systems, add_interpolation_systems, add_prepare_interpolation_systems,
+};
 use crate::client::prediction::plugin::{
     add_non_networked_rollback_systems, add_prediction_systems,
 };
@@ -23,10 +26,13 @@ use crate::prelude::client::SyncComponent;
 use crate::prelude::server::ServerConfig;
 use crate::prelude::{ChannelDirection, Message, Tick};
 use crate::protocol::delta::ErasedDeltaFns;
+use crate::protocol::quantize::{QuantizationConfig, Quantize};
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
 use crate::protocol::serialize::{ErasedSerializeFns, SerializeFns};
+use crate::protocol::version::TypeVersion;
 use crate::serialize::reader::Reader;
 use crate::serialize::SerializationError;
+use crate::shared::events::components::UnknownTypeKind;
 use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::replication::delta::{DeltaMessage, Diffable};
 use crate::shared::replication::entity_map::{EntityMap, ReceiveEntityMap};
@@ -143,7 +149,19 @@ pub struct ComponentRegistry {
     prediction_map: HashMap<ComponentKind, PredictionMetadata>,
     serialize_fns_map: HashMap<ComponentKind, ErasedSerializeFns>,
     delta_fns_map: HashMap<ComponentKind, ErasedDeltaFns>,
+    /// Retention duration of the removal tombstones, for components that have tombstones enabled
+    tombstone_map: HashMap<ComponentNetId, Duration>,
+    /// Interval at which the components are sent again even if they did not change
+    refresh_map: HashMap<ComponentKind, Duration>,
+    /// Minimum interval between two updates of the components
+    replication_interval_map: HashMap<ComponentKind, Duration>,
+    /// Components whose updates are sent through the reliable entity actions channel
+    reliable_updates: HashSet<ComponentKind>,
+    /// Previous versions of the components (see [`version`](crate::protocol::version))
+    versions: HashMap<ComponentKind, version::ComponentVersions>,
     pub(crate) kind_map: TypeMapper<ComponentKind>,
+    /// If true, components with an unknown [`ComponentNetId`] are skipped instead of returning an error
+    pub(crate) skip_unknown_types: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -182,11 +200,24 @@ impl PredictionMetadata {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct InterpolationMetadata {
     pub interpolation_mode: ComponentSyncMode,
     pub interpolation: Option<unsafe fn()>,
     pub custom_interpolation: bool,
+    /// [`ExtrapolateFn`] used to keep moving the component (using its replicated velocity)
+    /// when the interpolation runs out of server updates
+    pub extrapolation: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl PartialEq for InterpolationMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        // the extrapolation functions cannot be compared, we only check that both have one
+        self.interpolation_mode == other.interpolation_mode
+            && self.interpolation == other.interpolation
+            && self.custom_interpolation == other.custom_interpolation
+            && self.extrapolation.is_some() == other.extrapolation.is_some()
+    }
 }
 
 type RawRemoveFn = fn(&ComponentRegistry, &mut EntityWorldMut);
@@ -204,9 +235,12 @@ type RawWriteFn = fn(
 /// t goes from 0.0 (`start`) to 1.0 (`other`)
 pub type LerpFn<C> = fn(start: &C, other: &C, t: f32) -> C;
 
+/// Function that extrapolates the value of a component from its `velocity`, `elapsed` seconds after `start`
+pub type ExtrapolateFn<C, V> = fn(start: &C, velocity: &V, elapsed: f32) -> C;
+
 /// Function that returns true if a rollback is needed, by comparing the server's value with the client's predicted value.
 /// Defaults to PartialEq::ne
-type ShouldRollbackFn<C> = fn(this: &C, that: &C) -> bool;
+pub type ShouldRollbackFn<C> = fn(this: &C, that: &C) -> bool;
 
 pub trait Linear {
     fn lerp(start: &Self, other: &Self, t: f32) -> Self;
@@ -267,6 +301,14 @@ impl ComponentRegistry {
                 panic!("The Component {name:?} was registered for interpolation with ComponentSyncMode::FULL but no interpolation function was provided!");
             }
         }
+        for component_kind in self.versions.keys() {
+            if self.delta_fns_map.contains_key(component_kind) {
+                panic!(
+                    "The Component {:?} cannot have previous versions because it uses delta compression",
+                    self.name(*component_kind)
+                );
+            }
+        }
     }
 
     pub(crate) fn register_component<C: Message + Serialize + DeserializeOwned>(&mut self) {
@@ -313,6 +355,20 @@ mod serialize {
             erased_fns.add_map_entities::<C>();
         }
 
+        pub(crate) fn add_quantization<C: Quantize + 'static>(
+            &mut self,
+            config: QuantizationConfig,
+        ) {
+            let kind = ComponentKind::of::<C>();
+            let erased_fns = self.serialize_fns_map.get_mut(&kind).unwrap_or_else(|| {
+                panic!(
+                    "Component {} is not part of the protocol",
+                    std::any::type_name::<C>()
+                )
+            });
+            erased_fns.add_quantization::<C>(config);
+        }
+
         /// Returns true if we have a registered `map_entities` function for this component type
         pub(crate) fn is_map_entities<C: 'static>(&self) -> bool {
             let kind = ComponentKind::of::<C>();
@@ -424,9 +480,10 @@ mod prediction {
     impl ComponentRegistry {
         pub(crate) fn set_prediction_mode<C: SyncComponent>(&mut self, mode: ComponentSyncMode) {
             let kind = ComponentKind::of::<C>();
-            let default_equality_fn = <C as PartialEq>::eq;
+            // the metadata might already exist if the correction or rollback functions were registered first
             self.prediction_map
                 .entry(kind)
+                .and_modify(|metadata| metadata.prediction_mode = mode)
                 .or_insert_with(|| PredictionMetadata::default_from::<C>(mode));
         }
 
@@ -514,6 +571,7 @@ mod interpolation {
                     interpolation_mode: mode,
                     interpolation: None,
                     custom_interpolation: false,
+                    extrapolation: None,
                 })
                 .interpolation_mode = mode;
         }
@@ -530,6 +588,7 @@ mod interpolation {
                     interpolation_mode: ComponentSyncMode::Full,
                     interpolation: None,
                     custom_interpolation: false,
+                    extrapolation: None,
                 })
                 .interpolation = Some(unsafe {
                 std::mem::transmute::<for<'a, 'b> fn(&'a C, &'b C, f32) -> C, unsafe fn()>(
@@ -537,6 +596,57 @@ mod interpolation {
                 )
             });
         }
+        pub(crate) fn is_interpolated<C: Component>(&self) -> bool {
+            self.interpolation_map
+                .contains_key(&ComponentKind::of::<C>())
+        }
+
+        /// Set the function used to extrapolate `C` from the velocity `V`.
+        ///
+        /// The component must have been registered for interpolation.
+        pub(crate) fn set_extrapolation<C: Component, V: Component>(
+            &mut self,
+            extrapolation_fn: ExtrapolateFn<C, V>,
+        ) {
+            let kind = ComponentKind::of::<C>();
+            let Some(metadata) = self.interpolation_map.get_mut(&kind) else {
+                panic!(
+                    "The Component {:?} must be registered for interpolation before adding extrapolation",
+                    std::any::type_name::<C>()
+                );
+            };
+            metadata.extrapolation = Some(Arc::new(extrapolation_fn));
+        }
+
+        pub(crate) fn has_extrapolation<C: Component>(&self) -> bool {
+            let kind = ComponentKind::of::<C>();
+            self.interpolation_map
+                .get(&kind)
+                .is_some_and(|metadata| metadata.extrapolation.is_some())
+        }
+
+        /// Extrapolate the value of the component from its velocity.
+        ///
+        /// `V` must be the velocity type that was registered with [`set_extrapolation`](Self::set_extrapolation).
+        pub(crate) fn extrapolate<C: Component, V: Component>(
+            &self,
+            start: &C,
+            velocity: &V,
+            elapsed: f32,
+        ) -> C {
+            let kind = ComponentKind::of::<C>();
+            let interpolation_metadata = self
+                .interpolation_map
+                .get(&kind)
+                .expect("the component is not part of the protocol");
+            let extrapolation_fn = interpolation_metadata
+                .extrapolation
+                .as_ref()
+                .and_then(|extrapolation| extrapolation.downcast_ref::<ExtrapolateFn<C, V>>())
+                .expect("the component was not registered for extrapolation with this velocity");
+            extrapolation_fn(start, velocity, elapsed)
+        }
+
         pub(crate) fn interpolation_mode<C: Component>(&self) -> ComponentSyncMode {
             let kind = ComponentKind::of::<C>();
             self.interpolation_map
@@ -596,10 +706,14 @@ mod replication {
             events: &mut ConnectionEvents,
         ) -> Result<(), ComponentError> {
             let net_id = ComponentNetId::from_bytes(reader).map_err(SerializationError::from)?;
-            let kind = self
-                .kind_map
-                .kind(net_id)
-                .ok_or(ComponentError::NotRegistered)?;
+            let Some(kind) = self.kind_map.kind(net_id) else {
+                // each component is serialized separately, so we can just skip the rest of the bytes
+                if self.skip_unknown_types {
+                    events.push_unknown_type(UnknownTypeKind::Component, net_id);
+                    return Ok(());
+                }
+                return Err(ComponentError::NotRegistered);
+            };
             let replication_metadata = self
                 .replication_map
                 .get(kind)
@@ -645,16 +759,25 @@ mod replication {
             &self,
             net_id: ComponentNetId,
             entity_world_mut: &mut EntityWorldMut,
-        ) {
-            let kind = self.kind_map.kind(net_id).expect("unknown component kind");
+            events: &mut ConnectionEvents,
+        ) -> Result<(), ComponentError> {
+            let Some(kind) = self.kind_map.kind(net_id) else {
+                if self.skip_unknown_types {
+                    events.push_unknown_type(UnknownTypeKind::Component, net_id);
+                    return Ok(());
+                }
+                return Err(ComponentError::NotRegistered);
+            };
             let replication_metadata = self
                 .replication_map
                 .get(kind)
-                .expect("the component is not part of the protocol");
+                .ok_or(ComponentError::MissingReplicationFns)?;
             let f = replication_metadata
                 .remove
-                .expect("the component does not have a remove function");
+                .ok_or(ComponentError::MissingReplicationFns)?;
+            events.push_remove_component(entity_world_mut.id(), net_id, Tick(0));
             f(self, entity_world_mut);
+            Ok(())
         }
 
         pub(crate) fn remove<C: Component>(&self, entity_world_mut: &mut EntityWorldMut) {
@@ -857,6 +980,274 @@ mod delta {
     }
 }
 
+mod version {
+    use super::*;
+    use crate::protocol::version::{ProtocolVersions, TypeVersion, VersionChain};
+    use crate::serialize::writer::Writer;
+    use crate::serialize::ToBytes;
+    use crate::shared::replication::entity_map::SendEntityMap;
+    use bevy::ptr::PtrMut;
+    use bytes::Bytes;
+
+    /// Previous versions of a component, along with the functions to serialize the component in a previous
+    /// version and to migrate a serialized previous version to the current version
+    #[derive(Debug, Clone)]
+    pub(crate) struct ComponentVersions {
+        chain: VersionChain,
+        serialize: SerializeVersionFn,
+        upgrade: UpgradeFn,
+    }
+
+    impl PartialEq for ComponentVersions {
+        fn eq(&self, other: &Self) -> bool {
+            // the functions are determined by the component type
+            self.chain == other.chain
+        }
+    }
+
+    type SerializeVersionFn = unsafe fn(
+        registry: &ComponentRegistry,
+        chain: &VersionChain,
+        component: Ptr,
+        writer: &mut Writer,
+        entity_map: Option<&mut SendEntityMap>,
+        version: TypeVersion,
+    ) -> Result<(), ComponentError>;
+
+    type UpgradeFn = fn(
+        registry: &ComponentRegistry,
+        chain: &VersionChain,
+        reader: &mut Reader,
+        version: TypeVersion,
+        writer: &mut Writer,
+    ) -> Result<(), ComponentError>;
+
+    /// SAFETY: the Ptr must point to a component of type C
+    unsafe fn serialize_version<C: 'static>(
+        registry: &ComponentRegistry,
+        chain: &VersionChain,
+        component: Ptr,
+        writer: &mut Writer,
+        entity_map: Option<&mut SendEntityMap>,
+        version: TypeVersion,
+    ) -> Result<(), ComponentError> {
+        let component = component.deref::<C>();
+        let erased_fns = registry
+            .serialize_fns_map
+            .get(&ComponentKind::of::<C>())
+            .ok_or(ComponentError::MissingSerializationFns)?;
+        // map the entities of the current version before migrating it
+        let mapped = match (erased_fns.send_map_entities, entity_map) {
+            (Some(map_entities), Some(entity_map)) => {
+                // SAFETY: the ErasedSerializeFns was created for the type C
+                let clone: fn(&C) -> C = std::mem::transmute(erased_fns.erased_clone.unwrap());
+                let mut mapped = clone(component);
+                map_entities(PtrMut::from(&mut mapped), entity_map);
+                Some(mapped)
+            }
+            _ => None,
+        };
+        chain.serialize(mapped.as_ref().unwrap_or(component), version, writer)?;
+        Ok(())
+    }
+
+    fn upgrade<C: 'static>(
+        registry: &ComponentRegistry,
+        chain: &VersionChain,
+        reader: &mut Reader,
+        version: TypeVersion,
+        writer: &mut Writer,
+    ) -> Result<(), ComponentError> {
+        let mut component = chain.deserialize::<C>(reader, version)?;
+        // the entities are mapped when the component is written to the World, not here
+        registry.serialize::<C>(&mut component, writer, Some(&mut SendEntityMap::default()))
+    }
+
+    impl ComponentRegistry {
+        pub(crate) fn add_previous_version<C: 'static, Old, Next: 'static>(
+            &mut self,
+            version: TypeVersion,
+            upgrade_fn: fn(Old) -> Next,
+            downgrade_fn: fn(&Next) -> Old,
+        ) where
+            Old: Message + Serialize + DeserializeOwned,
+        {
+            let kind = ComponentKind::of::<C>();
+            assert!(
+                self.serialize_fns_map.contains_key(&kind),
+                "the component is not part of the protocol"
+            );
+            self.versions
+                .entry(kind)
+                .or_insert_with(|| ComponentVersions {
+                    chain: VersionChain::default(),
+                    serialize: serialize_version::<C>,
+                    upgrade: upgrade::<C>,
+                })
+                .chain
+                .push::<C, Old, Next>(version, upgrade_fn, downgrade_fn);
+        }
+
+        /// Current version of the component with the given [`ComponentNetId`]
+        pub(crate) fn current_version(&self, net_id: ComponentNetId) -> TypeVersion {
+            self.kind_map
+                .kind(net_id)
+                .and_then(|kind| self.versions.get(kind))
+                .map_or(0, |versions| versions.chain.current())
+        }
+
+        /// Returns true if the component with the given [`ComponentNetId`] can be sent and received in the given version
+        pub(crate) fn supports_version(
+            &self,
+            net_id: ComponentNetId,
+            version: TypeVersion,
+        ) -> bool {
+            self.kind_map
+                .kind(net_id)
+                .and_then(|kind| self.versions.get(kind))
+                .map_or(version == 0, |versions| versions.chain.supports(version))
+        }
+
+        /// Current version of all the components that have previous versions
+        pub(crate) fn versions(&self) -> ProtocolVersions {
+            let mut versions: Vec<_> = self
+                .versions
+                .iter()
+                .filter_map(|(kind, versions)| {
+                    self.kind_map
+                        .net_id(kind)
+                        .map(|net_id| (*net_id, versions.chain.current()))
+                })
+                .collect();
+            versions.sort();
+            ProtocolVersions(versions)
+        }
+
+        /// Serialize the component in a previous version, if `version` is not the current version of the component
+        ///
+        /// SAFETY: the Ptr must correspond to the correct ComponentKind
+        pub(crate) fn erased_serialize_version(
+            &self,
+            component: Ptr,
+            writer: &mut Writer,
+            kind: ComponentKind,
+            entity_map: Option<&mut SendEntityMap>,
+            version: TypeVersion,
+        ) -> Result<(), ComponentError> {
+            let Some(versions) = self
+                .versions
+                .get(&kind)
+                .filter(|versions| versions.chain.current() != version)
+            else {
+                return self.erased_serialize(component, writer, kind, entity_map);
+            };
+            let net_id = self.kind_map.net_id(&kind).unwrap();
+            net_id.to_bytes(writer)?;
+            // SAFETY: the functions were created for the type of the component
+            unsafe {
+                (versions.serialize)(
+                    self,
+                    &versions.chain,
+                    component,
+                    writer,
+                    entity_map,
+                    version,
+                )
+            }
+        }
+
+        /// Migrate the serialized components that are in a previous version to their current version.
+        ///
+        /// `previous_versions` contains the versions of the components that are not in their current version.
+        pub(crate) fn upgrade_components(
+            &self,
+            components: &mut [Bytes],
+            previous_versions: &HashMap<ComponentNetId, TypeVersion>,
+            writer: &mut Writer,
+        ) -> Result<(), ComponentError> {
+            for bytes in components.iter_mut() {
+                let mut reader = Reader::from(bytes.clone());
+                let net_id = ComponentNetId::from_bytes(&mut reader)?;
+                let Some(version) = previous_versions.get(&net_id) else {
+                    continue;
+                };
+                let versions = self
+                    .kind_map
+                    .kind(net_id)
+                    .and_then(|kind| self.versions.get(kind))
+                    .ok_or(SerializationError::UnsupportedVersion(*version))?;
+                (versions.upgrade)(self, &versions.chain, &mut reader, *version, writer)?;
+                *bytes = writer.split();
+            }
+            Ok(())
+        }
+    }
+}
+
+mod tombstone {
+    use super::*;
+
+    impl ComponentRegistry {
+        /// Record a tombstone whenever the component is removed from a replicated entity.
+        /// The tombstone is kept for `retention`.
+        pub(crate) fn set_tombstone_retention<C: Component>(&mut self, retention: Duration) {
+            let net_id = self.net_id::<C>();
+            self.tombstone_map.insert(net_id, retention);
+        }
+
+        /// Returns the retention duration of the tombstones for the component,
+        /// or `None` if tombstones are not enabled for this component
+        pub(crate) fn tombstone_retention(&self, net_id: ComponentNetId) -> Option<Duration> {
+            self.tombstone_map.get(&net_id).copied()
+        }
+    }
+}
+
+mod refresh {
+    use super::*;
+
+    impl ComponentRegistry {
+        /// Send the component again every `interval`, even if it did not change
+        pub(crate) fn set_refresh_interval<C: Component>(&mut self, interval: Duration) {
+            self.refresh_map.insert(ComponentKind::of::<C>(), interval);
+        }
+
+        /// Returns the interval at which the component is sent again even if it did not change,
+        /// or `None` if the component is only sent when it changes
+        pub(crate) fn refresh_interval(&self, kind: ComponentKind) -> Option<Duration> {
+            self.refresh_map.get(&kind).copied()
+        }
+    }
+}
+
+mod throttle {
+    use super::*;
+
+    impl ComponentRegistry {
+        /// Send the updates of the component at most once every `interval`
+        pub(crate) fn set_replication_interval<C: Component>(&mut self, interval: Duration) {
+            self.replication_interval_map
+                .insert(ComponentKind::of::<C>(), interval);
+        }
+
+        /// Returns the minimum interval between two updates of the component,
+        /// or `None` if the updates are sent as soon as the component changes
+        pub(crate) fn replication_interval(&self, kind: ComponentKind) -> Option<Duration> {
+            self.replication_interval_map.get(&kind).copied()
+        }
+
+        /// Send the updates of the component through the reliable entity actions channel
+        pub(crate) fn set_reliable_updates<C: Component>(&mut self) {
+            self.reliable_updates.insert(ComponentKind::of::<C>());
+        }
+
+        /// Returns true if the updates of the component are sent reliably
+        pub(crate) fn has_reliable_updates(&self, kind: ComponentKind) -> bool {
+            self.reliable_updates.contains(&kind)
+        }
+    }
+}
+
 fn register_component_send<C: Component>(app: &mut App, direction: ChannelDirection) {
     let is_client = app.world().get_resource::<ClientConfig>().is_some();
     let is_server = app.world().get_resource::<ServerConfig>().is_some();
@@ -910,6 +1301,18 @@ pub trait AppComponentExt {
         serialize_fns: SerializeFns<C>,
     ) -> ComponentRegistration<'_, C>;
 
+    /// Registers every component of the tuple `T` in the Registry, with the same direction.
+    ///
+    /// This is useful to register all the instantiations of a generic component at once:
+    /// ```rust,ignore
+    /// app.register_components::<(Stat<Health>, Stat<Mana>, Stat<Speed>)>(ChannelDirection::ServerToClient)
+    ///     .add_prediction(ComponentSyncMode::Simple);
+    /// ```
+    fn register_components<T: ComponentTuple>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> ComponentsRegistration<'_, T>;
+
     /// Enable rollbacks for a component even if the component is not networked
     fn add_rollback<C: Component + PartialEq + Clone>(&mut self);
 
@@ -943,10 +1346,38 @@ pub trait AppComponentExt {
     /// Add a `Interpolation` behaviour to this component.
     fn add_interpolation_fn<C: SyncComponent>(&mut self, interpolation_fn: LerpFn<C>);
 
+    /// Keep extrapolating this component from its replicated velocity `V` when the interpolation runs out of server updates.
+    ///
+    /// The component is registered for interpolation with [`ComponentSyncMode::Full`] if it wasn't already.
+    fn add_extrapolation<C: SyncComponent, V: Component>(
+        &mut self,
+        extrapolation_fn: ExtrapolateFn<C, V>,
+    );
+
     /// Enable delta compression when serializing this component
     fn add_delta_compression<C: Component + PartialEq + Diffable>(&mut self)
     where
         C::Delta: Serialize + DeserializeOwned;
+
+    /// Keep a [`ComponentTombstones`](crate::prelude::ComponentTombstones) record of when this component was removed from a replicated entity.
+    /// The tombstone is replicated, and is kept for the `retention` duration.
+    ///
+    /// On the client, a [`ComponentTombstoneEvent<C>`](crate::prelude::ComponentTombstoneEvent) is emitted when
+    /// the tombstone is received, and the removal tick can be queried with [`TombstoneHistory`](crate::prelude::TombstoneHistory).
+    fn add_tombstones<C: Component>(&mut self, retention: Duration);
+
+    /// The server sends the current value of the component every `interval`, even if it did not change.
+    fn add_refresh_interval<C: Component>(&mut self, interval: Duration);
+
+    /// The server sends the updates of the component at most once every `interval`.
+    fn add_replication_interval<C: Component>(&mut self, interval: Duration);
+
+    /// The updates of the component are sent through the reliable entity actions channel
+    /// instead of the unreliable updates channel.
+    fn add_reliable_updates<C: Component>(&mut self);
+
+    /// Quantize the floats of the component to fixed-point integers when it is serialized.
+    fn add_quantization<C: Component + Quantize>(&mut self, config: QuantizationConfig);
 }
 
 pub struct ComponentRegistration<'a, C> {
@@ -954,6 +1385,76 @@ pub struct ComponentRegistration<'a, C> {
     _phantom: std::marker::PhantomData<C>,
 }
 
+/// A tuple of components that can be registered together with [`AppComponentExt::register_components`]
+///
+/// It is implemented for tuples of up to 15 components.
+pub trait ComponentTuple: 'static {
+    /// Register each component of the tuple
+    fn register(app: &mut App, direction: ChannelDirection);
+}
+
+/// A tuple of components for which prediction or interpolation can be enabled
+pub trait SyncComponentTuple: ComponentTuple {
+    /// Enable prediction for each component of the tuple
+    fn add_prediction(app: &mut App, prediction_mode: ComponentSyncMode);
+
+    /// Enable interpolation for each component of the tuple
+    fn add_interpolation(app: &mut App, interpolation_mode: ComponentSyncMode);
+}
+
+macro_rules! impl_component_tuple {
+    ($($name: ident),*) => {
+        impl<$($name: Component + Message + Serialize + DeserializeOwned + PartialEq),*> ComponentTuple for ($($name,)*) {
+            fn register(app: &mut App, direction: ChannelDirection) {
+                $(app.register_component::<$name>(direction);)*
+            }
+        }
+
+        impl<$($name: SyncComponent + Serialize + DeserializeOwned),*> SyncComponentTuple for ($($name,)*) {
+            fn add_prediction(app: &mut App, prediction_mode: ComponentSyncMode) {
+                $(app.add_prediction::<$name>(prediction_mode);)*
+            }
+
+            fn add_interpolation(app: &mut App, interpolation_mode: ComponentSyncMode) {
+                $(app.add_interpolation::<$name>(interpolation_mode);)*
+            }
+        }
+    };
+}
+
+bevy::utils::all_tuples!(impl_component_tuple, 1, 15, C);
+
+/// Returned by [`AppComponentExt::register_components`] to apply the same settings to every
+/// component of the tuple `T`
+pub struct ComponentsRegistration<'a, T> {
+    app: &'a mut App,
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> ComponentsRegistration<'_, T> {
+    /// Enable prediction systems for every component of the tuple.
+    /// You can specify the prediction [`ComponentSyncMode`]
+    pub fn add_prediction(self, prediction_mode: ComponentSyncMode) -> Self
+    where
+        T: SyncComponentTuple,
+    {
+        T::add_prediction(self.app, prediction_mode);
+        self
+    }
+
+    /// Enable interpolation systems for every component of the tuple.
+    /// You can specify the interpolation [`ComponentSyncMode`]
+    ///
+    /// With [`ComponentSyncMode::Full`], an interpolation function still has to be added for each component.
+    pub fn add_interpolation(self, interpolation_mode: ComponentSyncMode) -> Self
+    where
+        T: SyncComponentTuple,
+    {
+        T::add_interpolation(self.app, interpolation_mode);
+        self
+    }
+}
+
 impl<C> ComponentRegistration<'_, C> {
     /// Specify that the component contains entities which should be mapped from the remote world to the local world
     /// upon deserialization
@@ -966,6 +1467,30 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Register a previous version of the component, so that the server can replicate it to and from clients that
+    /// still use that version (see [`version`](crate::protocol::version)).
+    ///
+    /// `upgrade` migrates a value of this version to the next version `Next`, and `downgrade` does the opposite.
+    /// The previous versions must be added from the most recent (where `Next` is `C`) to the oldest,
+    /// and the current version of `C` is the version after the most recent previous version.
+    ///
+    /// A component with previous versions cannot use delta compression.
+    pub fn add_previous_version<Old, Next>(
+        self,
+        version: TypeVersion,
+        upgrade: fn(Old) -> Next,
+        downgrade: fn(&Next) -> Old,
+    ) -> Self
+    where
+        C: 'static,
+        Old: Message + Serialize + DeserializeOwned,
+        Next: 'static,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry.add_previous_version::<C, Old, Next>(version, upgrade, downgrade);
+        self
+    }
+
     /// Enable prediction systems for this component.
     /// You can specify the prediction [`ComponentSyncMode`]
     pub fn add_prediction(self, prediction_mode: ComponentSyncMode) -> Self
@@ -998,6 +1523,13 @@ impl<C> ComponentRegistration<'_, C> {
     ///
     /// (By default we use the PartialEq::ne function, but you can use this to override the
     ///  equality check. For example, you might want to add a threshold for floating point numbers)
+    ///
+    /// ```rust,ignore
+    /// app.register_component::<Position>(ChannelDirection::ServerToClient)
+    ///     .add_prediction(ComponentSyncMode::Full)
+    ///     // positions within 1cm of each other are considered equal
+    ///     .add_should_rollback(|this, that| this.0.distance(that.0) >= 0.01);
+    /// ```
     pub fn add_should_rollback(self, should_rollback: ShouldRollbackFn<C>) -> Self
     where
         C: SyncComponent,
@@ -1044,6 +1576,35 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Keep extrapolating this component when the interpolation runs out of server updates,
+    /// instead of freezing at the last server update.
+    ///
+    /// The component is dead-reckoned from the velocity `V` replicated on the confirmed entity, by calling
+    /// `extrapolation_fn` with the number of seconds elapsed since the last server update (capped at
+    /// [`ExtrapolationConfig::max_duration`](crate::client::interpolation::plugin::ExtrapolationConfig::max_duration)).
+    /// When a new server update arrives, the extrapolated value is blended back into the interpolated value
+    /// with the interpolation function.
+    ///
+    /// The component is registered for interpolation with [`ComponentSyncMode::Full`] if it wasn't already,
+    /// so an interpolation function must still be provided.
+    ///
+    /// ```rust,ignore
+    /// app.register_component::<Velocity>(ChannelDirection::ServerToClient);
+    /// app.register_component::<Position>(ChannelDirection::ServerToClient)
+    ///     .add_interpolation(ComponentSyncMode::Full)
+    ///     .add_linear_interpolation_fn()
+    ///     .add_extrapolation::<Velocity>(|position, velocity, elapsed| {
+    ///         Position(position.0 + velocity.0 * elapsed)
+    ///     });
+    /// ```
+    pub fn add_extrapolation<V: Component>(self, extrapolation_fn: ExtrapolateFn<C, V>) -> Self
+    where
+        C: SyncComponent,
+    {
+        self.app.add_extrapolation::<C, V>(extrapolation_fn);
+        self
+    }
+
     /// Enable delta compression when serializing this component
     pub fn add_delta_compression(self) -> Self
     where
@@ -1053,6 +1614,72 @@ impl<C> ComponentRegistration<'_, C> {
         self.app.add_delta_compression::<C>();
         self
     }
+
+    /// Keep a [`ComponentTombstones`](crate::prelude::ComponentTombstones) record of when this component was removed from a replicated entity.
+    /// The tombstone is replicated, and is kept for the `retention` duration.
+    ///
+    /// On the client, a [`ComponentTombstoneEvent<C>`](crate::prelude::ComponentTombstoneEvent) is emitted when
+    /// the tombstone is received, and the removal tick can be queried with [`TombstoneHistory`](crate::prelude::TombstoneHistory).
+    pub fn add_tombstones(self, retention: Duration) -> Self
+    where
+        C: Component,
+    {
+        self.app.add_tombstones::<C>(retention);
+        self
+    }
+
+    /// The server sends the current value of the component every `interval`, even if it did not change.
+    ///
+    /// This is useful for components with [`ComponentSyncMode::Simple`]: the updates are only sent when the
+    /// component changes, so a lost update could otherwise leave the client with a stale value until the next change.
+    /// The refresh bounds how long the client can diverge from the server.
+    pub fn add_refresh_interval(self, interval: Duration) -> Self
+    where
+        C: Component,
+    {
+        self.app.add_refresh_interval::<C>(interval);
+        self
+    }
+
+    /// The server sends the updates of the component at most once every `interval`, instead of
+    /// sending every change immediately.
+    ///
+    /// This is useful for components that change often but don't need to be precise on the client
+    /// (e.g. `Health` every 100ms while `Position` is sent every tick).
+    /// Changes that happen before the interval has elapsed are not lost: the most recent value is sent
+    /// once the interval has elapsed. Inserts and removals are never throttled.
+    pub fn with_replication_interval(self, interval: Duration) -> Self
+    where
+        C: Component,
+    {
+        self.app.add_replication_interval::<C>(interval);
+        self
+    }
+
+    /// Send the updates of the component reliably, in the same channel as the entity actions
+    /// (spawns, inserts, removals).
+    ///
+    /// By default updates are sent unreliably: a lost update is only corrected when the component changes again.
+    /// This is useful for components that rarely change but whose updates must not be lost (e.g. `Inventory`, `Name`).
+    pub fn with_reliable_updates(self) -> Self
+    where
+        C: Component,
+    {
+        self.app.add_reliable_updates::<C>();
+        self
+    }
+
+    /// Quantize the floats of the component to fixed-point integers when it is serialized,
+    /// instead of sending them with full precision.
+    ///
+    /// See [`Quantize`] for more details.
+    pub fn add_quantization(self, config: QuantizationConfig) -> Self
+    where
+        C: Component + Quantize,
+    {
+        self.app.add_quantization::<C>(config);
+        self
+    }
 }
 
 impl AppComponentExt for App {
@@ -1095,6 +1722,17 @@ impl AppComponentExt for App {
         }
     }
 
+    fn register_components<T: ComponentTuple>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> ComponentsRegistration<'_, T> {
+        T::register(self, direction);
+        ComponentsRegistration {
+            app: self,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
     // TODO: move this away from protocol? since it doesn't even use the registry at all
     //  maybe put this in the PredictionPlugin?
     fn add_rollback<C: Component + PartialEq + Clone>(&mut self) {
@@ -1175,6 +1813,25 @@ impl AppComponentExt for App {
         registry.set_interpolation::<C>(interpolation_fn);
     }
 
+    fn add_extrapolation<C: SyncComponent, V: Component>(
+        &mut self,
+        extrapolation_fn: ExtrapolateFn<C, V>,
+    ) {
+        if !self
+            .world()
+            .resource::<ComponentRegistry>()
+            .is_interpolated::<C>()
+        {
+            self.add_interpolation::<C>(ComponentSyncMode::Full);
+        }
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_extrapolation::<C, V>(extrapolation_fn);
+        let is_client = self.world().get_resource::<ClientConfig>().is_some();
+        if is_client {
+            add_extrapolation_systems::<C, V>(self);
+        }
+    }
+
     fn add_delta_compression<C: Component + PartialEq + Diffable>(&mut self)
     where
         C::Delta: Serialize + DeserializeOwned,
@@ -1182,6 +1839,39 @@ impl AppComponentExt for App {
         let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
         registry.set_delta_compression::<C>();
     }
+
+    fn add_tombstones<C: Component>(&mut self, retention: Duration) {
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_tombstone_retention::<C>(retention);
+        let is_client = self.world().get_resource::<ClientConfig>().is_some();
+        let is_server = self.world().get_resource::<ServerConfig>().is_some();
+        if is_client {
+            crate::client::replication::receive::register_tombstone_events::<C>(self);
+        }
+        if is_server {
+            crate::server::replication::send::register_tombstones::<C>(self);
+        }
+    }
+
+    fn add_refresh_interval<C: Component>(&mut self, interval: Duration) {
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_refresh_interval::<C>(interval);
+    }
+
+    fn add_replication_interval<C: Component>(&mut self, interval: Duration) {
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_replication_interval::<C>(interval);
+    }
+
+    fn add_reliable_updates<C: Component>(&mut self) {
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_reliable_updates::<C>();
+    }
+
+    fn add_quantization<C: Component + Quantize>(&mut self, config: QuantizationConfig) {
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.add_quantization::<C>(config);
+    }
 }
 
 /// [`ComponentKind`] is an internal wrapper around the type of the component
@@ -1207,6 +1897,7 @@ mod tests {
     use super::*;
     use crate::serialize::writer::Writer;
     use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
 
     #[test]
     fn test_custom_serde() {
@@ -1229,4 +1920,42 @@ mod tests {
             .unwrap();
         assert_eq!(component, read);
     }
+
+    /// All the instantiations of a generic component can be registered at once
+    #[test]
+    fn test_register_components_tuple() {
+        let mut stepper = BevyStepper::default();
+        let registry = stepper.client_app.world().resource::<ComponentRegistry>();
+        assert!(registry.is_registered::<ComponentGeneric<u32>>());
+        assert!(registry.is_registered::<ComponentGeneric<f32>>());
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                crate::prelude::server::Replicate::default(),
+                ComponentGeneric(1u32),
+                ComponentGeneric(2.0f32),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<crate::prelude::client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        let client_world = stepper.client_app.world();
+        assert_eq!(
+            client_world.get::<ComponentGeneric<u32>>(client_entity),
+            Some(&ComponentGeneric(1u32))
+        );
+        assert_eq!(
+            client_world.get::<ComponentGeneric<f32>>(client_entity),
+            Some(&ComponentGeneric(2.0f32))
+        );
+    }
 }
@@ -0,0 +1,246 @@
+//! Protocol extensions let mods or DLC add networked messages and components on top of the base protocol.
+//!
+//! The types registered in an extension get network ids in a range that is reserved for that extension, instead
+//! of the next ids of the base protocol. The ids of the base protocol and its [`ProtocolHash`](crate::protocol::version::ProtocolHash)
+//! are unchanged, so clients that don't have the extension (e.g. vanilla clients) can still connect to a server
+//! that has it, and the other way around.
+//!
+//! After connecting, the client sends the list of the extensions it registered, along with a hash of their types.
+//! The server replies with the extensions that it also registered with the same types. The messages and components
+//! of an extension are only sent to the clients that enabled it, and a client only sends them once the server
+//! has accepted the extension.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::*;
+//!
+//! app.add_protocol_extension(ProtocolExtensionId(1), |app| {
+//!     app.register_message::<DlcMessage>(ChannelDirection::Bidirectional);
+//!     app.register_component::<DlcComponent>(ChannelDirection::ServerToClient);
+//! });
+//! ```
+use std::hash::{Hash, Hasher};
+
+use bevy::app::App;
+use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::protocol::component::ComponentRegistry;
+use crate::protocol::message::MessageRegistry;
+use crate::protocol::registry::NetId;
+use crate::serialize::reader::Reader;
+use crate::serialize::varint::{varint_len, VarIntReadExt, VarIntWriteExt};
+use crate::serialize::{SerializationError, ToBytes};
+
+/// First network id of the range reserved for the types of the protocol extensions
+pub(crate) const EXTENSION_NET_ID_START: NetId = 1 << 14;
+/// Number of network ids reserved for each protocol extension (for messages and components separately)
+pub(crate) const EXTENSION_NET_ID_RANGE: NetId = 128;
+
+/// Identifier of a protocol extension. It must be the same on the client and on the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProtocolExtensionId(pub u8);
+
+impl ProtocolExtensionId {
+    /// Returns the extension that owns the network id, or `None` if it belongs to the base protocol
+    pub(crate) fn of(net_id: NetId) -> Option<Self> {
+        (net_id >= EXTENSION_NET_ID_START)
+            .then(|| Self(((net_id - EXTENSION_NET_ID_START) / EXTENSION_NET_ID_RANGE) as u8))
+    }
+
+    /// First network id of the range reserved for this extension
+    pub(crate) fn first_net_id(self) -> NetId {
+        EXTENSION_NET_ID_START + self.0 as NetId * EXTENSION_NET_ID_RANGE
+    }
+}
+
+/// Extensions registered by a peer, along with the hash of their types.
+///
+/// The client sends the extensions it registered to the server, which replies with the extensions that it accepted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ProtocolExtensionHashes(pub(crate) Vec<(ProtocolExtensionId, u64)>);
+
+impl ProtocolExtensionHashes {
+    pub(crate) fn new(
+        message_registry: &MessageRegistry,
+        component_registry: &ComponentRegistry,
+    ) -> Self {
+        let mut extensions: Vec<ProtocolExtensionId> = message_registry
+            .kind_map
+            .extension_next_net_ids
+            .keys()
+            .chain(component_registry.kind_map.extension_next_net_ids.keys())
+            .copied()
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        Self(
+            extensions
+                .into_iter()
+                .map(|extension| {
+                    // the SeaHasher is deterministic across processes, which is not the case of the default hasher
+                    let mut hasher = seahash::SeaHasher::new();
+                    let first = extension.first_net_id();
+                    let next = message_registry
+                        .kind_map
+                        .extension_next_net_ids
+                        .get(&extension)
+                        .copied()
+                        .unwrap_or(first);
+                    for net_id in first..next {
+                        let name = message_registry
+                            .kind_map
+                            .kind(net_id)
+                            .and_then(|kind| message_registry.name(*kind));
+                        name.hash(&mut hasher);
+                    }
+                    let next = component_registry
+                        .kind_map
+                        .extension_next_net_ids
+                        .get(&extension)
+                        .copied()
+                        .unwrap_or(first);
+                    for net_id in first..next {
+                        let name = component_registry
+                            .kind_map
+                            .kind(net_id)
+                            .map(|kind| component_registry.name(*kind));
+                        name.hash(&mut hasher);
+                    }
+                    (extension, hasher.finish())
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the extensions of `self` that are also present in `other` with the same types
+    pub(crate) fn intersection(&self, other: &Self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter(|extension| other.0.contains(extension))
+                .copied()
+                .collect(),
+        )
+    }
+
+    pub(crate) fn ids(&self) -> impl Iterator<Item = ProtocolExtensionId> + '_ {
+        self.0.iter().map(|(id, _)| *id)
+    }
+}
+
+impl ToBytes for ProtocolExtensionHashes {
+    fn len(&self) -> usize {
+        varint_len(self.0.len() as u64) + self.0.len() * 9
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        buffer.write_varint(self.0.len() as u64)?;
+        for (id, hash) in &self.0 {
+            buffer.write_u8(id.0)?;
+            buffer.write_u64::<NetworkEndian>(*hash)?;
+        }
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        let len = buffer.read_varint()? as usize;
+        let mut extensions = Vec::with_capacity(len.min(u8::MAX as usize + 1));
+        for _ in 0..len {
+            let id = ProtocolExtensionId(buffer.read_u8()?);
+            let hash = buffer.read_u64::<NetworkEndian>()?;
+            extensions.push((id, hash));
+        }
+        Ok(Self(extensions))
+    }
+}
+
+/// Register messages and components in a protocol extension
+pub trait AppProtocolExtensionExt {
+    /// All the messages and components registered in `register` are part of the extension `id`.
+    ///
+    /// They use network ids from the range reserved for the extension, so the base protocol is unchanged.
+    /// Each extension can contain up to 128 messages and 128 components.
+    fn add_protocol_extension(
+        &mut self,
+        id: ProtocolExtensionId,
+        register: impl FnOnce(&mut App),
+    ) -> &mut Self;
+}
+
+impl AppProtocolExtensionExt for App {
+    fn add_protocol_extension(
+        &mut self,
+        id: ProtocolExtensionId,
+        register: impl FnOnce(&mut App),
+    ) -> &mut Self {
+        self.world_mut()
+            .resource_mut::<MessageRegistry>()
+            .kind_map
+            .current_extension = Some(id);
+        self.world_mut()
+            .resource_mut::<ComponentRegistry>()
+            .kind_map
+            .current_extension = Some(id);
+        register(self);
+        self.world_mut()
+            .resource_mut::<MessageRegistry>()
+            .kind_map
+            .current_extension = None;
+        self.world_mut()
+            .resource_mut::<ComponentRegistry>()
+            .kind_map
+            .current_extension = None;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::message::MessageType;
+    use crate::tests::protocol::{ComponentSyncModeFull, StringMessage};
+
+    #[test]
+    fn test_extension_net_ids() {
+        let mut message_registry = MessageRegistry::default();
+        let mut component_registry = ComponentRegistry::default();
+        message_registry.kind_map.current_extension = Some(ProtocolExtensionId(2));
+        message_registry.add_message::<StringMessage>(MessageType::Normal);
+        component_registry.register_component::<ComponentSyncModeFull>();
+
+        let net_id = *message_registry
+            .kind_map
+            .net_id(&crate::protocol::message::MessageKind::of::<StringMessage>())
+            .unwrap();
+        assert_eq!(net_id, EXTENSION_NET_ID_START + 2 * EXTENSION_NET_ID_RANGE);
+        assert_eq!(
+            ProtocolExtensionId::of(net_id),
+            Some(ProtocolExtensionId(2))
+        );
+        // types registered outside of an extension belong to the base protocol
+        assert_eq!(
+            ProtocolExtensionId::of(component_registry.net_id::<ComponentSyncModeFull>()),
+            None
+        );
+
+        let hashes = ProtocolExtensionHashes::new(&message_registry, &component_registry);
+        assert_eq!(
+            hashes.ids().collect::<Vec<_>>(),
+            vec![ProtocolExtensionId(2)]
+        );
+        // an extension is only accepted if both peers registered the same types
+        let other = ProtocolExtensionHashes(vec![(ProtocolExtensionId(2), 0)]);
+        assert!(hashes.intersection(&other).0.is_empty());
+        assert_eq!(hashes.intersection(&hashes), hashes);
+
+        let mut writer = crate::serialize::writer::Writer::default();
+        hashes.to_bytes(&mut writer).unwrap();
+        let mut reader = Reader::from(writer.split());
+        assert_eq!(
+            ProtocolExtensionHashes::from_bytes(&mut reader).unwrap(),
+            hashes
+        );
+    }
+}
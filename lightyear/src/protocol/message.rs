@@ -1,12 +1,14 @@
 use bevy::ecs::entity::MapEntities;
+use bevy::ptr::PtrMut;
 use std::any::TypeId;
 use std::fmt::Debug;
 
 use crate::client::config::ClientConfig;
 use crate::client::message::add_client_receive_message_from_server;
 use crate::prelude::{client, server};
-use bevy::prelude::{App, Resource, TypePath};
+use bevy::prelude::{App, Event, Resource, TypePath};
 use bevy::utils::HashMap;
+use bytes::Bytes;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use tracing::{debug, error};
@@ -16,12 +18,17 @@ use crate::prelude::server::ServerConfig;
 use crate::prelude::ChannelDirection;
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
 use crate::protocol::serialize::{ErasedSerializeFns, SerializeFns};
+use crate::protocol::version::{ProtocolVersions, TypeVersion, VersionChain};
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
 use crate::serialize::ToBytes;
 use crate::server::message::add_server_receive_message_from_client;
+use crate::shared::events::components::UnknownTypeKind;
+use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::replication::entity_map::{ReceiveEntityMap, SendEntityMap};
-use crate::shared::replication::resources::DespawnResource;
+use crate::shared::replication::events::{register_event_systems, EventMessage, EventRegistration};
+use crate::shared::replication::resources::send::add_predicted_resource_send_systems;
+use crate::shared::replication::resources::{DespawnResource, PredictedResourceUpdate};
 
 #[derive(thiserror::Error, Debug)]
 pub enum MessageError {
@@ -42,6 +49,8 @@ pub(crate) enum MessageType {
     NativeInput,
     /// This is not an input message, but a regular [`Message`]
     Normal,
+    /// This message wraps another message that should only be emitted once the receiver reaches a given tick
+    TickTargeted,
 }
 
 /// A [`Resource`] that will keep track of all the [`Message`]s that can be sent over the network.
@@ -99,7 +108,11 @@ pub(crate) enum MessageType {
 pub struct MessageRegistry {
     typed_map: HashMap<MessageKind, MessageType>,
     serialize_fns_map: HashMap<MessageKind, ErasedSerializeFns>,
+    /// Previous versions of the messages (see [`version`](crate::protocol::version))
+    versions: HashMap<MessageKind, VersionChain>,
     pub(crate) kind_map: TypeMapper<MessageKind>,
+    /// If true, messages with an unknown [`NetId`] are skipped instead of returning an error
+    pub(crate) skip_unknown_types: bool,
 }
 
 fn register_message_send<M: Message>(app: &mut App, direction: ChannelDirection) {
@@ -147,6 +160,9 @@ fn register_resource_send<R: Resource + Message>(app: &mut App, direction: Chann
                     R,
                     server::ConnectionManager,
                 >(app);
+                crate::shared::replication::resources::send::add_per_client_resource_send_systems::<
+                    R,
+                >(app);
             }
             if is_client {
                 crate::shared::replication::resources::receive::add_resource_receive_systems::<
@@ -161,6 +177,9 @@ fn register_resource_send<R: Resource + Message>(app: &mut App, direction: Chann
                     R,
                     server::ConnectionManager,
                 >(app);
+                crate::shared::replication::resources::send::add_per_client_resource_send_systems::<
+                    R,
+                >(app);
                 crate::shared::replication::resources::receive::add_resource_receive_systems::<
                     R,
                     server::ConnectionManager,
@@ -198,6 +217,28 @@ impl<M> MessageRegistration<'_, M> {
         registry.add_map_entities::<M>();
         self
     }
+
+    /// Register a previous version of the message, so that the server can communicate with clients that
+    /// still use that version (see [`version`](crate::protocol::version)).
+    ///
+    /// `upgrade` migrates a value of this version to the next version `Next`, and `downgrade` does the opposite.
+    /// The previous versions must be added from the most recent (where `Next` is `M`) to the oldest,
+    /// and the current version of `M` is the version after the most recent previous version.
+    pub fn add_previous_version<Old, Next>(
+        self,
+        version: TypeVersion,
+        upgrade: fn(Old) -> Next,
+        downgrade: fn(&Next) -> Old,
+    ) -> Self
+    where
+        M: 'static,
+        Old: Message + Serialize + DeserializeOwned,
+        Next: 'static,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<MessageRegistry>();
+        registry.add_previous_version::<M, Old, Next>(version, upgrade, downgrade);
+        self
+    }
 }
 
 pub(crate) trait AppMessageInternalExt {
@@ -290,6 +331,29 @@ pub trait AppMessageExt {
         direction: ChannelDirection,
         serialize_fns: SerializeFns<R>,
     );
+
+    /// Registers a bevy [`Event`] so that it is replicated over the network.
+    ///
+    /// Every event written locally is sent to the remote peer along with the current tick,
+    /// and the remote peer emits it when it reaches that tick.
+    /// See [`events`](crate::shared::replication::events) for more details.
+    fn register_event<E: Event + Message + Clone + Serialize + DeserializeOwned>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> EventRegistration<'_, E>;
+
+    /// Enable client-side prediction for a resource that is replicated from the server.
+    ///
+    /// The client keeps a history of the predicted values of the resource; the server updates
+    /// are compared against that history, and a rollback is triggered if there is a mismatch.
+    /// The resource must also be registered with [`register_resource`](AppMessageExt::register_resource),
+    /// and this must be called on both the client and the server: the server then sends the updates of the
+    /// resource along with their server tick.
+    fn add_resource_prediction<
+        R: Resource + Message + PartialEq + Clone + Serialize + DeserializeOwned,
+    >(
+        &mut self,
+    );
 }
 
 impl AppMessageExt for App {
@@ -328,20 +392,80 @@ impl AppMessageExt for App {
         self.register_message::<DespawnResource<R>>(direction);
         register_resource_send::<R>(self, direction)
     }
+
+    fn register_event<E: Event + Message + Clone + Serialize + DeserializeOwned>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> EventRegistration<'_, E> {
+        self.register_message::<EventMessage<E>>(direction);
+        register_event_systems::<E>(self, direction);
+        EventRegistration {
+            app: self,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn add_resource_prediction<
+        R: Resource + Message + PartialEq + Clone + Serialize + DeserializeOwned,
+    >(
+        &mut self,
+    ) {
+        self.register_message::<PredictedResourceUpdate<R>>(ChannelDirection::ServerToClient);
+        let is_client = self.world().get_resource::<ClientConfig>().is_some();
+        let is_server = self.world().get_resource::<ServerConfig>().is_some();
+        if is_server {
+            add_predicted_resource_send_systems::<R>(self);
+        }
+        if is_client {
+            crate::client::prediction::plugin::add_resource_prediction_systems::<R>(self);
+        }
+    }
 }
 
 impl MessageRegistry {
-    pub(crate) fn message_type(&self, net_id: NetId) -> MessageType {
-        let kind = self.kind_map.kind(net_id).unwrap();
-        self.typed_map
-            .get(kind)
-            .map_or(MessageType::Normal, |message_type| *message_type)
+    /// Returns the [`MessageType`] of the message with the given [`NetId`].
+    ///
+    /// If the message is not registered in the protocol, returns `None` if unknown types should be
+    /// skipped (the unknown type is recorded in the `events`), or an error otherwise.
+    pub(crate) fn message_type(
+        &self,
+        net_id: NetId,
+        events: &mut ConnectionEvents,
+    ) -> Result<Option<MessageType>, MessageError> {
+        let Some(kind) = self.kind_map.kind(net_id) else {
+            if self.skip_unknown_types {
+                events.push_unknown_type(UnknownTypeKind::Message, net_id);
+                return Ok(None);
+            }
+            return Err(MessageError::NotRegistered);
+        };
+        Ok(Some(
+            self.typed_map
+                .get(kind)
+                .map_or(MessageType::Normal, |message_type| *message_type),
+        ))
+    }
+
+    /// Check that the serialized message starts with the [`NetId`] of a registered message,
+    /// so that the receiver can route it
+    pub(crate) fn check_raw(&self, message_bytes: &Bytes) -> Result<NetId, MessageError> {
+        let mut reader = Reader::from(message_bytes.clone());
+        let net_id = NetId::from_bytes(&mut reader)?;
+        self.kind_map
+            .kind(net_id)
+            .ok_or(MessageError::NotRegistered)?;
+        Ok(net_id)
     }
 
     pub fn is_registered<M: 'static>(&self) -> bool {
         self.kind_map.net_id(&MessageKind::of::<M>()).is_some()
     }
 
+    /// Return the name of the message from the [`MessageKind`]
+    pub(crate) fn name(&self, kind: MessageKind) -> Option<&'static str> {
+        self.serialize_fns_map.get(&kind).map(|fns| fns.type_name)
+    }
+
     pub(crate) fn add_message<M: Message + Serialize + DeserializeOwned>(
         &mut self,
         message_type: MessageType,
@@ -381,6 +505,56 @@ impl MessageRegistry {
         erased_fns.add_map_entities::<M>();
     }
 
+    pub(crate) fn add_previous_version<M: 'static, Old, Next: 'static>(
+        &mut self,
+        version: TypeVersion,
+        upgrade: fn(Old) -> Next,
+        downgrade: fn(&Next) -> Old,
+    ) where
+        Old: Message + Serialize + DeserializeOwned,
+    {
+        let kind = MessageKind::of::<M>();
+        assert!(
+            self.serialize_fns_map.contains_key(&kind),
+            "the message is not part of the protocol"
+        );
+        self.versions
+            .entry(kind)
+            .or_default()
+            .push::<M, Old, Next>(version, upgrade, downgrade);
+    }
+
+    /// Current version of the message with the given [`NetId`]
+    pub(crate) fn current_version(&self, net_id: NetId) -> TypeVersion {
+        self.kind_map
+            .kind(net_id)
+            .and_then(|kind| self.versions.get(kind))
+            .map_or(0, |chain| chain.current())
+    }
+
+    /// Returns true if the message with the given [`NetId`] can be sent and received in the given version
+    pub(crate) fn supports_version(&self, net_id: NetId, version: TypeVersion) -> bool {
+        self.kind_map
+            .kind(net_id)
+            .and_then(|kind| self.versions.get(kind))
+            .map_or(version == 0, |chain| chain.supports(version))
+    }
+
+    /// Current version of all the messages that have previous versions
+    pub(crate) fn versions(&self) -> ProtocolVersions {
+        let mut versions: Vec<_> = self
+            .versions
+            .iter()
+            .filter_map(|(kind, chain)| {
+                self.kind_map
+                    .net_id(kind)
+                    .map(|net_id| (*net_id, chain.current()))
+            })
+            .collect();
+        versions.sort();
+        ProtocolVersions(versions)
+    }
+
     /// Returns true if we have a registered `map_entities` function for this message type
     pub(crate) fn is_map_entities<M: 'static>(&self) -> bool {
         let kind = MessageKind::of::<M>();
@@ -411,6 +585,72 @@ impl MessageRegistry {
         Ok(())
     }
 
+    /// Serialize the message in a previous version, if `version` is not the current version of the message
+    pub(crate) fn serialize_version<M: Message>(
+        &self,
+        message: &M,
+        writer: &mut Writer,
+        entity_map: Option<&mut SendEntityMap>,
+        version: TypeVersion,
+    ) -> Result<(), MessageError> {
+        let kind = MessageKind::of::<M>();
+        let Some(chain) = self
+            .versions
+            .get(&kind)
+            .filter(|chain| chain.current() != version)
+        else {
+            return self.serialize(message, writer, entity_map);
+        };
+        let erased_fns = self
+            .serialize_fns_map
+            .get(&kind)
+            .ok_or(MessageError::MissingSerializationFns)?;
+        let net_id = self.kind_map.net_id(&kind).unwrap();
+        net_id.to_bytes(writer)?;
+        // map the entities of the current version before migrating it
+        let mapped = match (erased_fns.send_map_entities, entity_map) {
+            (Some(map_entities), Some(entity_map)) => {
+                // SAFETY: the ErasedSerializeFns was created for the type M
+                let clone: fn(&M) -> M =
+                    unsafe { std::mem::transmute(erased_fns.erased_clone.unwrap()) };
+                let mut mapped = clone(message);
+                unsafe { map_entities(PtrMut::from(&mut mapped), entity_map) };
+                Some(mapped)
+            }
+            _ => None,
+        };
+        chain.serialize(mapped.as_ref().unwrap_or(message), version, writer)?;
+        Ok(())
+    }
+
+    /// Deserialize a message that was serialized in the given version, and migrate it to the current version
+    pub(crate) fn deserialize_version<M: Message>(
+        &self,
+        reader: &mut Reader,
+        entity_map: &mut ReceiveEntityMap,
+        version: TypeVersion,
+    ) -> Result<M, MessageError> {
+        let kind = MessageKind::of::<M>();
+        let Some(chain) = self
+            .versions
+            .get(&kind)
+            .filter(|chain| chain.current() != version)
+        else {
+            return self.deserialize(reader, entity_map);
+        };
+        let erased_fns = self
+            .serialize_fns_map
+            .get(&kind)
+            .ok_or(MessageError::MissingSerializationFns)?;
+        let _ = NetId::from_bytes(reader)?;
+        let mut message = chain.deserialize::<M>(reader, version)?;
+        if let Some(map_entities) = erased_fns.receive_map_entities {
+            // SAFETY: the ErasedSerializeFns was created for the type M
+            unsafe { map_entities(PtrMut::from(&mut message), entity_map) };
+        }
+        Ok(message)
+    }
+
     pub(crate) fn deserialize<M: Message>(
         &self,
         reader: &mut Reader,
@@ -473,6 +713,31 @@ mod tests {
         assert_eq!(message, read);
     }
 
+    #[test]
+    fn test_unknown_message_type() {
+        let mut registry = MessageRegistry::default();
+        registry.add_message::<Resource1>(MessageType::Normal);
+        let unknown_net_id = 10;
+        let mut events = ConnectionEvents::default();
+        assert!(registry.message_type(unknown_net_id, &mut events).is_err());
+        assert!(events.unknown_types.is_empty());
+
+        registry.skip_unknown_types = true;
+        assert_eq!(
+            registry.message_type(unknown_net_id, &mut events).unwrap(),
+            None
+        );
+        assert_eq!(
+            registry.message_type(0, &mut events).unwrap(),
+            Some(MessageType::Normal)
+        );
+        assert_eq!(
+            events.unknown_types,
+            vec![(UnknownTypeKind::Message, unknown_net_id)]
+        );
+        assert!(!events.is_empty());
+    }
+
     #[test]
     fn test_serde_map() {
         let mut registry = MessageRegistry::default();
@@ -20,10 +20,18 @@ pub(crate) mod component;
 pub(crate) mod message;
 
 pub(crate) mod delta;
+
+/// Lets mods or DLC add messages and components on top of the base protocol
+pub(crate) mod extension;
+/// Quantizes the floats of a component to reduce its size on the network
+pub(crate) mod quantize;
 /// Provides a mapping from a type to a unique identifier that can be serialized
 pub(crate) mod registry;
 pub(crate) mod serialize;
 
+/// Checks that the client and the server use the same protocol
+pub(crate) mod version;
+
 /// Data that can be used in an Event
 /// Same as `Event`, but we implement it automatically for all compatible types
 pub trait EventContext: Send + Sync + 'static {}
@@ -0,0 +1,247 @@
+//! Quantize the floats of a component to fixed-point integers before serializing them.
+//!
+//! Floats are serialized with 4 bytes each, even though most games don't need that much precision for
+//! positions or rotations. With [`AppComponentExt::add_quantization`](crate::prelude::AppComponentExt::add_quantization),
+//! each float is clamped to a [`QuantizationConfig::range`], rounded to a multiple of the [`QuantizationConfig::precision`],
+//! and sent as the number of steps from the start of the range, using the smallest integer that can hold all the steps.
+//! For example a position in `-300.0..=300.0` with a precision of `0.01` is sent with 2 bytes per float instead of 4.
+//!
+//! The component must implement [`Quantize`]. It is implemented for `f32` and for the `bevy` math types;
+//! for a newtype component, delegate to the inner value:
+//!
+//! ```rust,ignore
+//! #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+//! struct Position(Vec2);
+//!
+//! impl Quantize for Position {
+//!     fn quantize(&self, config: &QuantizationConfig, writer: &mut Writer) -> Result<(), SerializationError> {
+//!         self.0.quantize(config, writer)
+//!     }
+//!     fn dequantize(config: &QuantizationConfig, reader: &mut Reader) -> Result<Self, SerializationError> {
+//!         Vec2::dequantize(config, reader).map(Position)
+//!     }
+//! }
+//!
+//! app.register_component::<Position>(ChannelDirection::ServerToClient)
+//!     .add_quantization(QuantizationConfig { precision: 0.01, range: -300.0..=300.0 });
+//! ```
+use std::ops::RangeInclusive;
+
+use bevy::math::{Quat, Vec2, Vec3, Vec4};
+use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::serialize::reader::Reader;
+use crate::serialize::writer::Writer;
+use crate::serialize::SerializationError;
+
+/// How the floats of a component are quantized
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizationConfig {
+    /// Difference between two consecutive values that can be represented
+    pub precision: f32,
+    /// Values outside of this range are clamped
+    pub range: RangeInclusive<f32>,
+}
+
+impl QuantizationConfig {
+    /// Number of steps of `precision` needed to cover the range
+    fn steps(&self) -> u32 {
+        ((self.range.end() - self.range.start()) / self.precision)
+            .ceil()
+            .clamp(0.0, u32::MAX as f32) as u32
+    }
+
+    /// Write a single float
+    pub fn write_f32(&self, value: f32, writer: &mut Writer) -> Result<(), SerializationError> {
+        let start = *self.range.start();
+        let step = ((value.clamp(start, *self.range.end()) - start) / self.precision)
+            .round()
+            .min(self.steps() as f32) as u32;
+        match self.steps() {
+            s if s <= u8::MAX as u32 => writer.write_u8(step as u8)?,
+            s if s <= u16::MAX as u32 => writer.write_u16::<NetworkEndian>(step as u16)?,
+            _ => writer.write_u32::<NetworkEndian>(step)?,
+        }
+        Ok(())
+    }
+
+    /// Read a single float
+    pub fn read_f32(&self, reader: &mut Reader) -> Result<f32, SerializationError> {
+        let step = match self.steps() {
+            s if s <= u8::MAX as u32 => reader.read_u8()? as u32,
+            s if s <= u16::MAX as u32 => reader.read_u16::<NetworkEndian>()? as u32,
+            _ => reader.read_u32::<NetworkEndian>()?,
+        };
+        Ok(self.range.start() + step as f32 * self.precision)
+    }
+}
+
+/// A type whose floats can be quantized with a [`QuantizationConfig`]
+pub trait Quantize: Sized {
+    fn quantize(
+        &self,
+        config: &QuantizationConfig,
+        writer: &mut Writer,
+    ) -> Result<(), SerializationError>;
+
+    fn dequantize(
+        config: &QuantizationConfig,
+        reader: &mut Reader,
+    ) -> Result<Self, SerializationError>;
+}
+
+impl Quantize for f32 {
+    fn quantize(
+        &self,
+        config: &QuantizationConfig,
+        writer: &mut Writer,
+    ) -> Result<(), SerializationError> {
+        config.write_f32(*self, writer)
+    }
+
+    fn dequantize(
+        config: &QuantizationConfig,
+        reader: &mut Reader,
+    ) -> Result<Self, SerializationError> {
+        config.read_f32(reader)
+    }
+}
+
+macro_rules! impl_quantize_vec {
+    ($ty:ty, $n:literal) => {
+        impl Quantize for $ty {
+            fn quantize(
+                &self,
+                config: &QuantizationConfig,
+                writer: &mut Writer,
+            ) -> Result<(), SerializationError> {
+                for value in self.to_array() {
+                    config.write_f32(value, writer)?;
+                }
+                Ok(())
+            }
+
+            fn dequantize(
+                config: &QuantizationConfig,
+                reader: &mut Reader,
+            ) -> Result<Self, SerializationError> {
+                let mut values = [0.0; $n];
+                for value in values.iter_mut() {
+                    *value = config.read_f32(reader)?;
+                }
+                Ok(Self::from_array(values))
+            }
+        }
+    };
+}
+
+impl_quantize_vec!(Vec2, 2);
+impl_quantize_vec!(Vec3, 3);
+impl_quantize_vec!(Vec4, 4);
+
+/// The components of the quaternion are in `-1.0..=1.0`, so the range of the config should be (at most) that range.
+/// The quaternion is normalized after being dequantized.
+impl Quantize for Quat {
+    fn quantize(
+        &self,
+        config: &QuantizationConfig,
+        writer: &mut Writer,
+    ) -> Result<(), SerializationError> {
+        Vec4::from(*self).quantize(config, writer)
+    }
+
+    fn dequantize(
+        config: &QuantizationConfig,
+        reader: &mut Reader,
+    ) -> Result<Self, SerializationError> {
+        let quat = Quat::from_vec4(Vec4::dequantize(config, reader)?);
+        Ok(if quat.length_squared() > 0.0 {
+            quat.normalize()
+        } else {
+            Quat::IDENTITY
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::ComponentRegistry;
+    use crate::shared::replication::entity_map::ReceiveEntityMap;
+    use crate::tests::protocol::ComponentSyncModeFull;
+
+    #[test]
+    fn test_quantize_vec2() {
+        let config = QuantizationConfig {
+            precision: 0.01,
+            range: -300.0..=300.0,
+        };
+        let value = Vec2::new(12.3456, -500.0);
+        let mut writer = Writer::default();
+        value.quantize(&config, &mut writer).unwrap();
+        let bytes = writer.to_bytes();
+        // 60000 steps fit in a u16
+        assert_eq!(bytes.len(), 4);
+        let decoded = Vec2::dequantize(&config, &mut Reader::from(bytes)).unwrap();
+        assert!((decoded.x - 12.35).abs() < 0.001);
+        // out of range values are clamped
+        assert_eq!(decoded.y, -300.0);
+    }
+
+    #[test]
+    fn test_quantize_quat() {
+        let config = QuantizationConfig {
+            precision: 0.01,
+            range: -1.0..=1.0,
+        };
+        let value = Quat::from_rotation_z(1.0);
+        let mut writer = Writer::default();
+        value.quantize(&config, &mut writer).unwrap();
+        let bytes = writer.to_bytes();
+        assert_eq!(bytes.len(), 4);
+        let decoded = Quat::dequantize(&config, &mut Reader::from(bytes)).unwrap();
+        assert!(decoded.angle_between(value) < 0.02);
+    }
+
+    impl Quantize for ComponentSyncModeFull {
+        fn quantize(
+            &self,
+            config: &QuantizationConfig,
+            writer: &mut Writer,
+        ) -> Result<(), SerializationError> {
+            self.0.quantize(config, writer)
+        }
+
+        fn dequantize(
+            config: &QuantizationConfig,
+            reader: &mut Reader,
+        ) -> Result<Self, SerializationError> {
+            f32::dequantize(config, reader).map(ComponentSyncModeFull)
+        }
+    }
+
+    #[test]
+    fn test_registry_quantization() {
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<ComponentSyncModeFull>();
+        registry.add_quantization::<ComponentSyncModeFull>(QuantizationConfig {
+            precision: 0.5,
+            range: 0.0..=100.0,
+        });
+        let mut component = ComponentSyncModeFull(10.3);
+        let mut writer = Writer::default();
+        registry
+            .serialize(&mut component, &mut writer, None)
+            .unwrap();
+        let bytes = writer.to_bytes();
+        // net id + a single byte for the 200 steps
+        assert_eq!(bytes.len(), 2);
+        let decoded = registry
+            .deserialize::<ComponentSyncModeFull>(
+                &mut Reader::from(bytes),
+                &mut ReceiveEntityMap::default(),
+            )
+            .unwrap();
+        assert_eq!(decoded, ComponentSyncModeFull(10.5));
+    }
+}
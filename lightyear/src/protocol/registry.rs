@@ -1,3 +1,6 @@
+use crate::protocol::extension::{
+    ProtocolExtensionId, EXTENSION_NET_ID_RANGE, EXTENSION_NET_ID_START,
+};
 use crate::serialize::reader::Reader;
 use crate::serialize::varint::{varint_len, VarIntReadExt, VarIntWriteExt};
 use crate::serialize::{SerializationError, ToBytes};
@@ -35,6 +38,10 @@ pub struct TypeMapper<K: TypeKind> {
     pub(crate) next_net_id: NetId,
     pub(crate) kind_map: HashMap<K, NetId>,
     pub(crate) id_map: HashMap<NetId, K>,
+    /// Extension whose types are currently being registered
+    pub(crate) current_extension: Option<ProtocolExtensionId>,
+    /// Next network id of each protocol extension
+    pub(crate) extension_next_net_ids: HashMap<ProtocolExtensionId, NetId>,
 }
 
 impl<K: TypeKind> Default for TypeMapper<K> {
@@ -49,6 +56,8 @@ impl<K: TypeKind> TypeMapper<K> {
             next_net_id: 0,
             kind_map: HashMap::new(),
             id_map: HashMap::new(),
+            current_extension: None,
+            extension_next_net_ids: HashMap::new(),
         }
     }
 
@@ -58,10 +67,50 @@ impl<K: TypeKind> TypeMapper<K> {
         if self.kind_map.contains_key(&kind) {
             panic!("Type {:?} already registered", std::any::type_name::<T>());
         }
-        let net_id = self.next_net_id;
+        let net_id = match self.current_extension {
+            // the types of an extension use the range of ids reserved for that extension
+            Some(extension) => {
+                let next_net_id = self
+                    .extension_next_net_ids
+                    .entry(extension)
+                    .or_insert(extension.first_net_id());
+                assert!(
+                    *next_net_id < extension.first_net_id() + EXTENSION_NET_ID_RANGE,
+                    "Too many types registered in the protocol extension {:?}",
+                    extension
+                );
+                *next_net_id += 1;
+                *next_net_id - 1
+            }
+            None => {
+                assert!(
+                    self.next_net_id < EXTENSION_NET_ID_START,
+                    "Too many types registered in the protocol"
+                );
+                self.next_net_id += 1;
+                self.next_net_id - 1
+            }
+        };
+        self.kind_map.insert(kind, net_id);
+        self.id_map.insert(net_id, kind);
+        kind
+    }
+
+    /// Register a new type with a network id that was chosen by the caller
+    pub(crate) fn add_with_net_id<T: 'static>(&mut self, net_id: NetId) -> K {
+        let kind = K::from(TypeId::of::<T>());
+        if self.kind_map.contains_key(&kind) {
+            panic!("Type {:?} already registered", std::any::type_name::<T>());
+        }
+        if self.id_map.contains_key(&net_id) {
+            panic!(
+                "Cannot register {:?}: the network id {} is already used",
+                std::any::type_name::<T>(),
+                net_id
+            );
+        }
         self.kind_map.insert(kind, net_id);
         self.id_map.insert(net_id, kind);
-        self.next_net_id += 1;
         kind
     }
 
@@ -1,4 +1,5 @@
 use crate::prelude::{ComponentRegistry, Message, MessageRegistry};
+use crate::protocol::quantize::{QuantizationConfig, Quantize};
 use crate::serialize::{reader::Reader, writer::Writer, SerializationError};
 use crate::shared::replication::entity_map::{EntityMap, ReceiveEntityMap, SendEntityMap};
 use bevy::app::App;
@@ -21,6 +22,59 @@ pub struct ErasedSerializeFns {
     pub map_entities: Option<ErasedMapEntitiesFn>,
     pub send_map_entities: Option<ErasedSendMapEntitiesFn>,
     pub receive_map_entities: Option<ErasedReceiveMapEntitiesFn>,
+    /// If set, the value is quantized instead of using the `serialize` and `deserialize` functions
+    pub(crate) quantization: Option<ErasedQuantization>,
+}
+
+/// Quantization config of a type, along with its type-erased [`Quantize`] functions
+#[derive(Clone, Debug)]
+pub(crate) struct ErasedQuantization {
+    config: QuantizationConfig,
+    quantize: unsafe fn(),
+    dequantize: unsafe fn(),
+}
+
+impl PartialEq for ErasedQuantization {
+    fn eq(&self, other: &Self) -> bool {
+        // the functions are determined by the type, which is already compared by ErasedSerializeFns
+        self.config == other.config
+    }
+}
+
+type QuantizeFn<M> = fn(
+    message: &M,
+    config: &QuantizationConfig,
+    writer: &mut Writer,
+) -> Result<(), SerializationError>;
+type DequantizeFn<M> =
+    fn(config: &QuantizationConfig, reader: &mut Reader) -> Result<M, SerializationError>;
+
+impl ErasedQuantization {
+    fn new<M: Quantize>(config: QuantizationConfig) -> Self {
+        let quantize: QuantizeFn<M> = M::quantize;
+        let dequantize: DequantizeFn<M> = M::dequantize;
+        Self {
+            config,
+            quantize: unsafe { std::mem::transmute(quantize) },
+            dequantize: unsafe { std::mem::transmute(dequantize) },
+        }
+    }
+
+    /// SAFETY: the ErasedQuantization must be created for the type M
+    unsafe fn quantize<M>(
+        &self,
+        message: &M,
+        writer: &mut Writer,
+    ) -> Result<(), SerializationError> {
+        let quantize: QuantizeFn<M> = std::mem::transmute(self.quantize);
+        quantize(message, &self.config, writer)
+    }
+
+    /// SAFETY: the ErasedQuantization must be created for the type M
+    unsafe fn dequantize<M>(&self, reader: &mut Reader) -> Result<M, SerializationError> {
+        let dequantize: DequantizeFn<M> = std::mem::transmute(self.dequantize);
+        dequantize(&self.config, reader)
+    }
 }
 
 pub struct SerializeFns<M> {
@@ -68,6 +122,9 @@ unsafe fn erased_serialize_fn<M: Message>(
     writer: &mut Writer,
     entity_map: Option<&mut SendEntityMap>,
 ) -> Result<(), SerializationError> {
+    if let Some(quantization) = &erased_serialize_fn.quantization {
+        return quantization.quantize(message.deref::<M>(), writer);
+    }
     let typed_serialize_fns = erased_serialize_fn.typed::<M>();
     if let Some(map_entities) = erased_serialize_fn.send_map_entities {
         let serialize_map_entities = typed_serialize_fns.serialize_map_entities.unwrap();
@@ -87,7 +144,7 @@ unsafe fn erased_serialize_fn<M: Message>(
 }
 
 /// Default serialize function using bincode
-fn default_serialize<M: Message + Serialize>(
+pub(crate) fn default_serialize<M: Message + Serialize>(
     message: &M,
     buffer: &mut Writer,
 ) -> Result<(), SerializationError> {
@@ -96,7 +153,7 @@ fn default_serialize<M: Message + Serialize>(
 }
 
 /// Default deserialize function using bincode
-fn default_deserialize<M: Message + DeserializeOwned>(
+pub(crate) fn default_deserialize<M: Message + DeserializeOwned>(
     buffer: &mut Reader,
 ) -> Result<M, SerializationError> {
     let data = bincode::serde::decode_from_std_read(buffer, bincode::config::standard())?;
@@ -166,6 +223,7 @@ impl ErasedSerializeFns {
             map_entities: None,
             send_map_entities: None,
             receive_map_entities: None,
+            quantization: None,
         }
     }
 
@@ -180,6 +238,7 @@ impl ErasedSerializeFns {
             map_entities: None,
             send_map_entities: None,
             receive_map_entities: None,
+            quantization: None,
         }
     }
 
@@ -221,6 +280,16 @@ impl ErasedSerializeFns {
         self.erased_clone = Some(unsafe { std::mem::transmute(clone_fn) });
     }
 
+    /// Quantize the floats of the type instead of using the serialize functions
+    pub(crate) fn add_quantization<M: Quantize + 'static>(&mut self, config: QuantizationConfig) {
+        assert!(
+            self.map_entities.is_none(),
+            "The type {} cannot be quantized because it contains entities",
+            self.type_name
+        );
+        self.quantization = Some(ErasedQuantization::new::<M>(config));
+    }
+
     pub(crate) fn map_entities<M: 'static>(&self, message: &mut M, entity_map: &mut EntityMap) {
         let ptr = PtrMut::from(message);
         if let Some(map_entities_fn) = self.map_entities {
@@ -240,6 +309,9 @@ impl ErasedSerializeFns {
         writer: &mut Writer,
         entity_map: Option<&mut SendEntityMap>,
     ) -> Result<(), SerializationError> {
+        if let Some(quantization) = &self.quantization {
+            return quantization.quantize(message, writer);
+        }
         let fns = unsafe { self.typed::<M>() };
         if let Some(map_entities) = self.send_map_entities {
             let serialize_map_entities = fns.serialize_map_entities.unwrap();
@@ -264,8 +336,10 @@ impl ErasedSerializeFns {
         reader: &mut Reader,
         entity_map: &mut ReceiveEntityMap,
     ) -> Result<M, SerializationError> {
-        let fns = unsafe { self.typed::<M>() };
-        let mut message = (fns.deserialize)(reader)?;
+        let mut message = match &self.quantization {
+            Some(quantization) => quantization.dequantize::<M>(reader)?,
+            None => (unsafe { self.typed::<M>() }.deserialize)(reader)?,
+        };
         if let Some(map_entities) = self.receive_map_entities {
             map_entities(PtrMut::from(&mut message), entity_map);
         }
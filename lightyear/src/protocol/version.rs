@@ -0,0 +1,582 @@
+//! Check that the client and the server use the same protocol.
+//!
+//! When a client connects, it sends the [`ProtocolHash`] of its registered channels, messages and components
+//! to the server. If it does not match the hash of the server's protocol, the server emits a
+//! [`ProtocolMismatchEvent`](crate::server::events::ProtocolMismatchEvent) and disconnects the client,
+//! instead of letting it send data that would be deserialized incorrectly.
+//!
+//! If [`SharedConfig::skip_unknown_types`](crate::prelude::SharedConfig::skip_unknown_types) is enabled,
+//! clients with a different protocol are expected, so they are not disconnected (the event is still emitted).
+//! In that case, new types must only be appended at the end of the protocol, since the network ids are assigned in
+//! registration order.
+//!
+//! ### Message and component versions
+//!
+//! The content of a message or component can change between two releases of a game while keeping the same protocol,
+//! as long as the type keeps the same name. To keep supporting the clients of the previous release, the previous
+//! versions of the type can be registered, along with the functions to migrate a value from one version to the next:
+//!
+//! ```rust,ignore
+//! // version 0 was `ChatV0`, version 1 was `ChatV1`, the current version (2) is `Chat`
+//! app.register_message::<Chat>(ChannelDirection::Bidirectional)
+//!     .add_previous_version::<ChatV1, Chat>(1, ChatV1::upgrade, Chat::downgrade)
+//!     .add_previous_version::<ChatV0, ChatV1>(0, ChatV0::upgrade, ChatV1::downgrade);
+//! app.register_component::<Health>(ChannelDirection::ServerToClient)
+//!     .add_previous_version::<HealthV0, Health>(0, HealthV0::upgrade, Health::downgrade);
+//! ```
+//!
+//! Each client sends the current version of its messages and components along with its [`ProtocolHash`]. The server
+//! then sends each message and replicated component to a client in the version used by that client, and upgrades the
+//! messages and components received from the client through the chain of migrations. The server must therefore be at
+//! least as recent as its clients.
+//! The previous versions are serialized with `bincode`. Components that use delta compression cannot have previous versions.
+use std::any::{Any, TypeId};
+use std::hash::{Hash, Hasher};
+
+use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::packet::message::Message;
+use crate::protocol::channel::ChannelRegistry;
+use crate::protocol::component::ComponentRegistry;
+use crate::protocol::message::MessageRegistry;
+use crate::protocol::registry::NetId;
+use crate::protocol::serialize::{default_deserialize, default_serialize};
+use crate::serialize::reader::Reader;
+use crate::serialize::varint::{varint_len, VarIntReadExt, VarIntWriteExt};
+use crate::serialize::writer::Writer;
+use crate::serialize::{SerializationError, ToBytes};
+
+/// Hash of the channels, messages and components registered in the protocol, along with their network ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProtocolHash(pub u64);
+
+impl ProtocolHash {
+    pub(crate) fn new(
+        channel_registry: &ChannelRegistry,
+        message_registry: &MessageRegistry,
+        component_registry: &ComponentRegistry,
+    ) -> Self {
+        // the SeaHasher is deterministic across processes, which is not the case of the default hasher
+        let mut hasher = seahash::SeaHasher::new();
+        // the types are hashed in the order of their network id, since that is what needs to match
+        for net_id in 0..channel_registry.kind_map.next_net_id {
+            let name = channel_registry
+                .kind_map
+                .kind(net_id)
+                .and_then(|kind| channel_registry.name_map.get(kind));
+            name.hash(&mut hasher);
+        }
+        for net_id in 0..message_registry.kind_map.next_net_id {
+            let name = message_registry
+                .kind_map
+                .kind(net_id)
+                .and_then(|kind| message_registry.name(*kind));
+            name.hash(&mut hasher);
+        }
+        for net_id in 0..component_registry.kind_map.next_net_id {
+            let name = component_registry
+                .kind_map
+                .kind(net_id)
+                .map(|kind| component_registry.name(*kind));
+            name.hash(&mut hasher);
+        }
+        Self(hasher.finish())
+    }
+}
+
+impl ToBytes for ProtocolHash {
+    fn len(&self) -> usize {
+        8
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        buffer.write_u64::<NetworkEndian>(self.0)?;
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        Ok(Self(buffer.read_u64::<NetworkEndian>()?))
+    }
+}
+
+/// Version of a message or component type
+pub type TypeVersion = u16;
+
+/// A previous version of a type, along with the functions to migrate it to and from the next version.
+///
+/// The functions are type-erased so that the versions of a type can be stored in a single chain.
+#[derive(Clone, Debug)]
+pub(crate) struct PreviousVersion {
+    pub(crate) version: TypeVersion,
+    /// Type of this version
+    type_id: TypeId,
+    /// Type of the next version
+    next_type_id: TypeId,
+    upgrade: unsafe fn(),
+    downgrade: unsafe fn(),
+    erased_upgrade: ErasedUpgradeFn,
+    erased_downgrade: ErasedDowngradeFn,
+    serialize: fn(&dyn Any, &mut Writer) -> Result<(), SerializationError>,
+    deserialize: fn(&mut Reader) -> Result<Box<dyn Any>, SerializationError>,
+}
+
+type ErasedUpgradeFn = unsafe fn(
+    upgrade: unsafe fn(),
+    value: Box<dyn Any>,
+) -> Result<Box<dyn Any>, SerializationError>;
+type ErasedDowngradeFn =
+    unsafe fn(downgrade: unsafe fn(), value: &dyn Any) -> Result<Box<dyn Any>, SerializationError>;
+
+impl PartialEq for PreviousVersion {
+    fn eq(&self, other: &Self) -> bool {
+        // the functions are determined by the types
+        self.version == other.version
+            && self.type_id == other.type_id
+            && self.next_type_id == other.next_type_id
+    }
+}
+
+impl PreviousVersion {
+    fn new<Old: Message + Serialize + DeserializeOwned, Next: 'static>(
+        version: TypeVersion,
+        upgrade: fn(Old) -> Next,
+        downgrade: fn(&Next) -> Old,
+    ) -> Self {
+        Self {
+            version,
+            type_id: TypeId::of::<Old>(),
+            next_type_id: TypeId::of::<Next>(),
+            upgrade: unsafe { std::mem::transmute(upgrade) },
+            downgrade: unsafe { std::mem::transmute(downgrade) },
+            erased_upgrade: erased_upgrade::<Old, Next>,
+            erased_downgrade: erased_downgrade::<Old, Next>,
+            serialize: erased_serialize::<Old>,
+            deserialize: erased_deserialize::<Old>,
+        }
+    }
+}
+
+/// SAFETY: `upgrade` must be a `fn(Old) -> Next`
+unsafe fn erased_upgrade<Old: 'static, Next: 'static>(
+    upgrade: unsafe fn(),
+    value: Box<dyn Any>,
+) -> Result<Box<dyn Any>, SerializationError> {
+    let upgrade: fn(Old) -> Next = std::mem::transmute(upgrade);
+    let value = value
+        .downcast::<Old>()
+        .map_err(|_| SerializationError::InvalidValue)?;
+    Ok(Box::new(upgrade(*value)))
+}
+
+/// SAFETY: `downgrade` must be a `fn(&Next) -> Old`
+unsafe fn erased_downgrade<Old: 'static, Next: 'static>(
+    downgrade: unsafe fn(),
+    value: &dyn Any,
+) -> Result<Box<dyn Any>, SerializationError> {
+    let downgrade: fn(&Next) -> Old = std::mem::transmute(downgrade);
+    let value = value
+        .downcast_ref::<Next>()
+        .ok_or(SerializationError::InvalidValue)?;
+    Ok(Box::new(downgrade(value)))
+}
+
+fn erased_serialize<M: Message + Serialize>(
+    value: &dyn Any,
+    writer: &mut Writer,
+) -> Result<(), SerializationError> {
+    let value = value
+        .downcast_ref::<M>()
+        .ok_or(SerializationError::InvalidValue)?;
+    default_serialize(value, writer)
+}
+
+fn erased_deserialize<M: Message + DeserializeOwned>(
+    reader: &mut Reader,
+) -> Result<Box<dyn Any>, SerializationError> {
+    Ok(Box::new(default_deserialize::<M>(reader)?))
+}
+
+/// Previous versions of a type, from the most recent to the oldest
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct VersionChain(Vec<PreviousVersion>);
+
+impl VersionChain {
+    /// The current version of the type is the one after its most recent previous version
+    pub(crate) fn current(&self) -> TypeVersion {
+        self.0.first().map_or(0, |previous| previous.version + 1)
+    }
+
+    /// Returns true if a value can be migrated to or from the given version
+    pub(crate) fn supports(&self, version: TypeVersion) -> bool {
+        version == self.current() || self.index(version).is_some()
+    }
+
+    fn index(&self, version: TypeVersion) -> Option<usize> {
+        self.0
+            .iter()
+            .position(|previous| previous.version == version)
+    }
+
+    /// Add a version that is older than all the versions of the chain.
+    ///
+    /// Panics if `Next` is not the oldest type of the chain (or the current type `M` if the chain is empty),
+    /// or if the version does not directly precede the oldest version of the chain.
+    pub(crate) fn push<M: 'static, Old: Message + Serialize + DeserializeOwned, Next: 'static>(
+        &mut self,
+        version: TypeVersion,
+        upgrade: fn(Old) -> Next,
+        downgrade: fn(&Next) -> Old,
+    ) {
+        let (next_type_id, next_version) = match self.0.last() {
+            Some(oldest) => (oldest.type_id, Some(oldest.version)),
+            None => (TypeId::of::<M>(), None),
+        };
+        assert_eq!(
+            TypeId::of::<Next>(),
+            next_type_id,
+            "the previous versions of {} must be added from the most recent to the oldest",
+            std::any::type_name::<M>()
+        );
+        if let Some(next_version) = next_version {
+            assert_eq!(
+                Some(version),
+                next_version.checked_sub(1),
+                "the versions of {} must be consecutive",
+                std::any::type_name::<M>()
+            );
+        }
+        self.0.push(PreviousVersion::new::<Old, Next>(
+            version, upgrade, downgrade,
+        ));
+    }
+
+    /// Migrate the value down to the given version and serialize it
+    pub(crate) fn serialize<M: 'static>(
+        &self,
+        value: &M,
+        version: TypeVersion,
+        writer: &mut Writer,
+    ) -> Result<(), SerializationError> {
+        let index = self
+            .index(version)
+            .ok_or(SerializationError::UnsupportedVersion(version))?;
+        // SAFETY: the functions of each version were created for the types of the chain
+        let mut value = unsafe { (self.0[0].erased_downgrade)(self.0[0].downgrade, value)? };
+        for previous in &self.0[1..=index] {
+            value = unsafe { (previous.erased_downgrade)(previous.downgrade, value.as_ref())? };
+        }
+        (self.0[index].serialize)(value.as_ref(), writer)
+    }
+
+    /// Deserialize a value of the given version and migrate it up to the current version
+    pub(crate) fn deserialize<M: 'static>(
+        &self,
+        reader: &mut Reader,
+        version: TypeVersion,
+    ) -> Result<M, SerializationError> {
+        let index = self
+            .index(version)
+            .ok_or(SerializationError::UnsupportedVersion(version))?;
+        let mut value = (self.0[index].deserialize)(reader)?;
+        for previous in self.0[..=index].iter().rev() {
+            // SAFETY: the functions of each version were created for the types of the chain
+            value = unsafe { (previous.erased_upgrade)(previous.upgrade, value)? };
+        }
+        value
+            .downcast::<M>()
+            .map(|value| *value)
+            .map_err(|_| SerializationError::InvalidValue)
+    }
+}
+
+/// Current version of the messages (or components) that have previous versions, sent by the client to the server
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ProtocolVersions(pub(crate) Vec<(NetId, TypeVersion)>);
+
+impl ToBytes for ProtocolVersions {
+    fn len(&self) -> usize {
+        varint_len(self.0.len() as u64)
+            + self
+                .0
+                .iter()
+                .map(|(net_id, version)| varint_len(*net_id as u64) + varint_len(*version as u64))
+                .sum::<usize>()
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        buffer.write_varint(self.0.len() as u64)?;
+        for (net_id, version) in &self.0 {
+            buffer.write_varint(*net_id as u64)?;
+            buffer.write_varint(*version as u64)?;
+        }
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        let len = buffer.read_varint()? as usize;
+        let mut versions = Vec::with_capacity(len.min(NetId::MAX as usize));
+        for _ in 0..len {
+            let net_id = buffer.read_varint()? as NetId;
+            let version = buffer.read_varint()? as TypeVersion;
+            versions.push((net_id, version));
+        }
+        Ok(Self(versions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::component::ComponentKind;
+    use crate::protocol::message::MessageType;
+    use crate::tests::protocol::{ComponentSyncModeFull, StringMessage};
+    use bevy::prelude::Component;
+    use bevy::ptr::Ptr;
+    use bevy::utils::{Duration, HashMap};
+    use serde::Deserialize;
+
+    /// Current version of the message
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Chat {
+        text: String,
+        channel: u8,
+        color: u32,
+    }
+
+    /// Version 1: the color did not exist
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct ChatV1 {
+        text: String,
+        channel: u8,
+    }
+
+    /// Version 0: there was only one chat channel
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct ChatV0 {
+        text: String,
+    }
+
+    fn registry() -> MessageRegistry {
+        let mut message_registry = MessageRegistry::default();
+        message_registry.add_message::<Chat>(MessageType::Normal);
+        message_registry.add_previous_version::<Chat, ChatV1, Chat>(
+            1,
+            |v1| Chat {
+                text: v1.text,
+                channel: v1.channel,
+                color: 0,
+            },
+            |chat| ChatV1 {
+                text: chat.text.clone(),
+                channel: chat.channel,
+            },
+        );
+        message_registry.add_previous_version::<Chat, ChatV0, ChatV1>(
+            0,
+            |v0| ChatV1 {
+                text: v0.text,
+                channel: 0,
+            },
+            |v1| ChatV0 {
+                text: v1.text.clone(),
+            },
+        );
+        message_registry
+    }
+
+    #[test]
+    fn test_message_versions() {
+        let message_registry = registry();
+        let net_id = *message_registry
+            .kind_map
+            .net_id(&crate::protocol::message::MessageKind::of::<Chat>())
+            .unwrap();
+        assert_eq!(message_registry.current_version(net_id), 2);
+        assert!(message_registry.supports_version(net_id, 0));
+        assert!(!message_registry.supports_version(net_id, 3));
+        assert_eq!(
+            message_registry.versions(),
+            ProtocolVersions(vec![(net_id, 2)])
+        );
+
+        let chat = Chat {
+            text: "hello".to_string(),
+            channel: 3,
+            color: 7,
+        };
+        let mut writer = Writer::default();
+        let mut entity_map = Default::default();
+
+        // the current version is serialized as usual
+        message_registry
+            .serialize_version(&chat, &mut writer, None, 2)
+            .unwrap();
+        let mut reader = Reader::from(writer.split());
+        assert_eq!(
+            message_registry
+                .deserialize_version::<Chat>(&mut reader, &mut entity_map, 2)
+                .unwrap(),
+            chat
+        );
+
+        // a client at version 0 only receives the text, and the missing fields get default values
+        message_registry
+            .serialize_version(&chat, &mut writer, None, 0)
+            .unwrap();
+        let bytes = writer.split();
+        let mut reader = Reader::from(bytes.clone());
+        let _ = NetId::from_bytes(&mut reader).unwrap();
+        assert_eq!(
+            default_deserialize::<ChatV0>(&mut reader).unwrap(),
+            ChatV0 {
+                text: "hello".to_string()
+            }
+        );
+        let mut reader = Reader::from(bytes);
+        assert_eq!(
+            message_registry
+                .deserialize_version::<Chat>(&mut reader, &mut entity_map, 0)
+                .unwrap(),
+            Chat {
+                text: "hello".to_string(),
+                channel: 0,
+                color: 0,
+            }
+        );
+
+        let mut writer = Writer::default();
+        message_registry.versions().to_bytes(&mut writer).unwrap();
+        let mut reader = Reader::from(writer.split());
+        assert_eq!(
+            ProtocolVersions::from_bytes(&mut reader).unwrap(),
+            message_registry.versions()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_message_versions_out_of_order() {
+        let mut message_registry = MessageRegistry::default();
+        message_registry.add_message::<Chat>(MessageType::Normal);
+        // the most recent previous version must be added first
+        message_registry.add_previous_version::<Chat, ChatV0, ChatV1>(
+            0,
+            |v0| ChatV1 {
+                text: v0.text,
+                channel: 0,
+            },
+            |v1| ChatV0 {
+                text: v1.text.clone(),
+            },
+        );
+    }
+
+    /// Current version of the component
+    #[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Health {
+        current: u32,
+        max: u32,
+    }
+
+    /// Version 0: the maximum health was always 100
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct HealthV0 {
+        current: u32,
+    }
+
+    #[test]
+    fn test_component_versions() {
+        let mut component_registry = ComponentRegistry::default();
+        component_registry.register_component::<Health>();
+        component_registry.add_previous_version::<Health, HealthV0, Health>(
+            0,
+            |v0| Health {
+                current: v0.current,
+                max: 100,
+            },
+            |health| HealthV0 {
+                current: health.current,
+            },
+        );
+        let net_id = component_registry.net_id::<Health>();
+        assert_eq!(component_registry.current_version(net_id), 1);
+        assert_eq!(
+            component_registry.versions(),
+            ProtocolVersions(vec![(net_id, 1)])
+        );
+
+        // the server replicates the component to a client at version 0 in that version
+        let health = Health {
+            current: 30,
+            max: 50,
+        };
+        let mut writer = Writer::default();
+        component_registry
+            .erased_serialize_version(
+                Ptr::from(&health),
+                &mut writer,
+                ComponentKind::of::<Health>(),
+                None,
+                0,
+            )
+            .unwrap();
+        let bytes = writer.split();
+        let mut reader = Reader::from(bytes.clone());
+        let _ = NetId::from_bytes(&mut reader).unwrap();
+        assert_eq!(
+            default_deserialize::<HealthV0>(&mut reader).unwrap(),
+            HealthV0 { current: 30 }
+        );
+
+        // the component replicated by the client is upgraded to the current version
+        let mut components = [bytes];
+        component_registry
+            .upgrade_components(
+                &mut components,
+                &HashMap::from_iter([(net_id, 0)]),
+                &mut writer,
+            )
+            .unwrap();
+        let mut reader = Reader::from(components[0].clone());
+        assert_eq!(
+            component_registry
+                .deserialize::<Health>(&mut reader, &mut Default::default())
+                .unwrap(),
+            Health {
+                current: 30,
+                max: 100,
+            }
+        );
+    }
+
+    #[test]
+    fn test_protocol_hash() {
+        let channel_registry = ChannelRegistry::new(Duration::default());
+        let mut message_registry = MessageRegistry::default();
+        let mut component_registry = ComponentRegistry::default();
+        let hash = ProtocolHash::new(&channel_registry, &message_registry, &component_registry);
+        assert_eq!(
+            hash,
+            ProtocolHash::new(&channel_registry, &message_registry, &component_registry)
+        );
+
+        message_registry.add_message::<StringMessage>(MessageType::Normal);
+        let message_hash =
+            ProtocolHash::new(&channel_registry, &message_registry, &component_registry);
+        assert_ne!(hash, message_hash);
+
+        component_registry.register_component::<ComponentSyncModeFull>();
+        assert_ne!(
+            message_hash,
+            ProtocolHash::new(&channel_registry, &message_registry, &component_registry)
+        );
+    }
+}
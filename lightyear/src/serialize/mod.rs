@@ -30,6 +30,8 @@ pub enum SerializationError {
     BincodeDecode(#[from] bincode::error::DecodeError),
     #[error("The message is too big ({0} bytes) to be sent. We can split a message only up to 256 fragments.")]
     MessageTooBig(usize),
+    #[error("Version {0} of the type is not supported")]
+    UnsupportedVersion(u16),
 }
 
 #[allow(clippy::len_without_is_empty)]
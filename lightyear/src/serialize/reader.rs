@@ -51,6 +51,11 @@ impl Reader {
         bytes
     }
 
+    /// Returns the bytes that have not been read yet, without advancing the reader
+    pub(crate) fn peek_remaining(&self) -> Bytes {
+        self.0.get_ref().slice(self.0.position() as usize..)
+    }
+
     pub(crate) fn has_remaining(&self) -> bool {
         self.0.has_remaining()
     }
@@ -0,0 +1,418 @@
+//! Limits on the entities that a client can replicate to the server.
+//!
+//! Without limits, a malicious client could spawn an unlimited number of entities on the server.
+//! The [`ReplicationBudget`] lets you cap, for each client:
+//! - the number of entities that the client has replicated to the server
+//! - the rate at which the client can spawn new entities
+//! - the number of component bytes that the client can send per tick
+//!
+//! The limits are enforced when the replication messages are received, before they are applied to the World.
+//! Every time a limit is exceeded, a [`ReplicationBudgetExceededEvent`] is emitted and the
+//! [`BudgetOverflowPolicy`] is applied.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//!
+//! let config = ServerConfig {
+//!     replication_budget: ReplicationBudget {
+//!         max_entities: Some(100),
+//!         max_spawn_rate: Some(10.0),
+//!         max_component_bytes_per_tick: Some(4096),
+//!         overflow_policy: BudgetOverflowPolicy::Disconnect,
+//!     },
+//!     ..default()
+//! };
+//! ```
+use bevy::ecs::entity::{Entities, EntityHashSet};
+use bevy::prelude::{Entity, Event, RemovedComponents, ResMut};
+use bevy::utils::Duration;
+
+use crate::connection::id::ClientId;
+use crate::prelude::Replicated;
+use crate::server::connection::ConnectionManager;
+use crate::shared::replication::{
+    EntityActions, EntityActionsMessage, EntityUpdatesMessage, SpawnAction,
+};
+use crate::shared::tick_manager::Tick;
+
+/// Limits on the entities that each client can replicate to the server.
+///
+/// All the limits are disabled by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplicationBudget {
+    /// Maximum number of entities that a client can have replicated on the server at the same time
+    pub max_entities: Option<usize>,
+    /// Maximum number of entities that a client can spawn per second
+    pub max_spawn_rate: Option<f32>,
+    /// Maximum number of component bytes (inserts and updates) that a client can send per tick
+    pub max_component_bytes_per_tick: Option<usize>,
+    /// What to do when a client exceeds its budget
+    pub overflow_policy: BudgetOverflowPolicy,
+}
+
+impl ReplicationBudget {
+    pub fn with_max_entities(mut self, max_entities: usize) -> Self {
+        self.max_entities = Some(max_entities);
+        self
+    }
+
+    pub fn with_max_spawn_rate(mut self, max_spawn_rate: f32) -> Self {
+        self.max_spawn_rate = Some(max_spawn_rate);
+        self
+    }
+
+    pub fn with_max_component_bytes_per_tick(mut self, max_bytes: usize) -> Self {
+        self.max_component_bytes_per_tick = Some(max_bytes);
+        self
+    }
+
+    pub fn with_overflow_policy(mut self, overflow_policy: BudgetOverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.max_entities.is_none()
+            && self.max_spawn_rate.is_none()
+            && self.max_component_bytes_per_tick.is_none()
+    }
+}
+
+/// What to do when a client exceeds its [`ReplicationBudget`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BudgetOverflowPolicy {
+    /// Drop the spawns and component data that exceed the budget
+    #[default]
+    Drop,
+    /// Drop the spawns and component data that exceed the budget, and disconnect the client
+    Disconnect,
+}
+
+/// The limit of the [`ReplicationBudget`] that was exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetExceeded {
+    MaxEntities,
+    SpawnRate,
+    ComponentBytes,
+}
+
+/// Event emitted when a client exceeds its [`ReplicationBudget`]
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct ReplicationBudgetExceededEvent {
+    pub client_id: ClientId,
+    pub reason: BudgetExceeded,
+}
+
+/// Tracks how much of its [`ReplicationBudget`] a client has used
+#[derive(Debug, Default)]
+pub(crate) struct BudgetTracker {
+    pub(crate) config: ReplicationBudget,
+    /// Remote entities spawned by the client that are still alive
+    entities: EntityHashSet,
+    /// Remote entities whose spawn was rejected: all their actions and updates are dropped
+    rejected: EntityHashSet,
+    /// Number of spawns available (token bucket refilled at `max_spawn_rate`)
+    spawn_tokens: f32,
+    /// Server tick for which we are counting the component bytes.
+    ///
+    /// We cannot use the tick of the replication messages because it is chosen by the client.
+    bytes_tick: Option<Tick>,
+    bytes: usize,
+    /// Limits that were exceeded since the last time the violations were drained
+    pub(crate) violations: Vec<BudgetExceeded>,
+}
+
+impl BudgetTracker {
+    pub(crate) fn new(config: ReplicationBudget) -> Self {
+        Self {
+            config,
+            spawn_tokens: config.max_spawn_rate.map_or(0.0, |rate| rate.max(1.0)),
+            ..Default::default()
+        }
+    }
+
+    /// Refill the spawn tokens
+    pub(crate) fn update(&mut self, delta: Duration) {
+        if let Some(rate) = self.config.max_spawn_rate {
+            self.spawn_tokens = (self.spawn_tokens + rate * delta.as_secs_f32()).min(rate.max(1.0));
+        }
+    }
+
+    fn violation(&mut self, reason: BudgetExceeded) {
+        if !self.violations.contains(&reason) {
+            self.violations.push(reason);
+        }
+    }
+
+    /// Returns true if the bytes fit in the budget of the current server tick
+    fn consume_bytes(&mut self, bytes: usize, server_tick: Tick) -> bool {
+        let Some(max_bytes) = self.config.max_component_bytes_per_tick else {
+            return true;
+        };
+        if self.bytes_tick != Some(server_tick) {
+            self.bytes_tick = Some(server_tick);
+            self.bytes = 0;
+        }
+        if self.bytes + bytes > max_bytes {
+            self.violation(BudgetExceeded::ComponentBytes);
+            return false;
+        }
+        self.bytes += bytes;
+        true
+    }
+
+    /// Returns true if the client is allowed to spawn a new entity
+    fn can_spawn(&mut self) -> bool {
+        if self
+            .config
+            .max_entities
+            .is_some_and(|max| self.entities.len() >= max)
+        {
+            self.violation(BudgetExceeded::MaxEntities);
+            return false;
+        }
+        if self.config.max_spawn_rate.is_some() {
+            if self.spawn_tokens < 1.0 {
+                self.violation(BudgetExceeded::SpawnRate);
+                return false;
+            }
+            self.spawn_tokens -= 1.0;
+        }
+        true
+    }
+
+    /// The entity spawned by the client was despawned on the server: it doesn't count towards the budget anymore
+    pub(crate) fn release(&mut self, remote_entity: Entity) {
+        self.entities.remove(&remote_entity);
+    }
+
+    /// Remove the spawns and component data that exceed the budget from an actions message.
+    ///
+    /// The message itself is kept (even if it is empty) because the actions are sequenced.
+    pub(crate) fn check_actions(&mut self, message: &mut EntityActionsMessage, server_tick: Tick) {
+        if self.config.is_unlimited() {
+            return;
+        }
+        message.actions.retain_mut(|(entity, actions)| {
+            if self.rejected.contains(entity) {
+                if actions.spawn == SpawnAction::Despawn {
+                    self.rejected.remove(entity);
+                }
+                return false;
+            }
+            match actions.spawn {
+                SpawnAction::Spawn => {
+                    if !self.can_spawn() {
+                        self.rejected.insert(*entity);
+                        return false;
+                    }
+                    self.entities.insert(*entity);
+                }
+                SpawnAction::Despawn => {
+                    self.entities.remove(entity);
+                }
+                _ => {}
+            }
+            let bytes = component_bytes(actions);
+            if bytes > 0 && !self.consume_bytes(bytes, server_tick) {
+                actions.insert.clear();
+                actions.updates.clear();
+            }
+            true
+        });
+    }
+
+    /// Remove the updates that exceed the budget from an updates message
+    pub(crate) fn check_updates(&mut self, message: &mut EntityUpdatesMessage, server_tick: Tick) {
+        if self.config.is_unlimited() {
+            return;
+        }
+        message.updates.retain(|(entity, components)| {
+            !self.rejected.contains(entity)
+                && self.consume_bytes(components.iter().map(|c| c.len()).sum(), server_tick)
+        });
+    }
+}
+
+/// Release the budget of the entities replicated by the clients that were despawned on the server
+pub(crate) fn release_despawned_entities(
+    entities: &Entities,
+    mut removed: RemovedComponents<Replicated>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    for entity in removed.read() {
+        if entities.contains(entity) {
+            continue;
+        }
+        for connection in connection_manager.connections.values_mut() {
+            if let Some(remote_entity) = connection
+                .replication_receiver
+                .remote_entity_map
+                .get_remote(entity)
+            {
+                connection.budget.release(remote_entity);
+            }
+        }
+    }
+}
+
+fn component_bytes(actions: &EntityActions) -> usize {
+    actions
+        .insert
+        .iter()
+        .chain(actions.updates.iter())
+        .map(|c| c.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Events;
+
+    use super::*;
+    use crate::prelude::{client, server, Replicated};
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    #[test]
+    fn test_max_entities() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .connection_mut(client_id)
+            .unwrap()
+            .budget = BudgetTracker::new(ReplicationBudget::default().with_max_entities(1));
+
+        let client_entity_1 = stepper
+            .client_app
+            .world_mut()
+            .spawn((client::Replicate::default(), ComponentSyncModeSimple(1.0)))
+            .id();
+        let client_entity_2 = stepper
+            .client_app
+            .world_mut()
+            .spawn((client::Replicate::default(), ComponentSyncModeSimple(2.0)))
+            .id();
+        let mut exceeded = false;
+        for _ in 0..10 {
+            stepper.frame_step();
+            let events = stepper
+                .server_app
+                .world()
+                .resource::<Events<ReplicationBudgetExceededEvent>>();
+            exceeded |= events.get_reader().read(events).any(|event| {
+                event
+                    == &ReplicationBudgetExceededEvent {
+                        client_id,
+                        reason: BudgetExceeded::MaxEntities,
+                    }
+            });
+        }
+        assert!(exceeded);
+
+        // only one of the two entities was spawned on the server
+        let manager = stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>();
+        let entity_map = &manager
+            .connection(client_id)
+            .unwrap()
+            .replication_receiver
+            .remote_entity_map;
+        assert_eq!(
+            [client_entity_1, client_entity_2]
+                .iter()
+                .filter(|e| entity_map.get_local(**e).is_some())
+                .count(),
+            1
+        );
+        assert_eq!(
+            stepper
+                .server_app
+                .world_mut()
+                .query::<&Replicated>()
+                .iter(stepper.server_app.world())
+                .count(),
+            1
+        );
+    }
+
+    /// Check that the component bytes are counted per server tick, whatever the tick of the messages
+    #[test]
+    fn test_component_bytes_per_server_tick() {
+        let mut tracker =
+            BudgetTracker::new(ReplicationBudget::default().with_max_component_bytes_per_tick(10));
+        // the client picks a different tick for each message
+        assert!(tracker.consume_bytes(8, Tick(1)));
+        assert!(!tracker.consume_bytes(8, Tick(1)));
+        assert_eq!(tracker.violations, vec![BudgetExceeded::ComponentBytes]);
+        // the budget is reset on the next server tick
+        assert!(tracker.consume_bytes(8, Tick(2)));
+    }
+
+    /// Check that the entities despawned by the server don't count towards the budget of the client
+    #[test]
+    fn test_release_despawned_entities() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .connection_mut(client_id)
+            .unwrap()
+            .budget = BudgetTracker::new(ReplicationBudget::default().with_max_entities(1));
+
+        let client_entity = stepper
+            .client_app
+            .world_mut()
+            .spawn((client::Replicate::default(), ComponentSyncModeSimple(1.0)))
+            .id();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let server_entity = stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .connection(client_id)
+            .unwrap()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(client_entity)
+            .unwrap();
+
+        // the server despawns the entity: the client can spawn a new one
+        stepper.server_app.world_mut().despawn(server_entity);
+        stepper.frame_step();
+        stepper
+            .client_app
+            .world_mut()
+            .spawn((client::Replicate::default(), ComponentSyncModeSimple(2.0)));
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world_mut()
+                .query::<&Replicated>()
+                .iter(stepper.server_app.world())
+                .count(),
+            1
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .connection(client_id)
+            .unwrap()
+            .budget
+            .violations
+            .is_empty());
+    }
+}
@@ -1,14 +1,17 @@
 //! Defines server-specific configuration options
 use bevy::prelude::Resource;
+use bevy::utils::Duration;
 use governor::Quota;
 use nonzero_ext::nonzero;
 use std::sync::Arc;
 
-use crate::connection::netcode::{Key, PRIVATE_KEY_BYTES};
+use crate::connection::netcode::{Key, MAX_PACKET_SIZE, PRIVATE_KEY_BYTES};
 use crate::connection::server::{
     ConnectionRequestHandler, DefaultConnectionRequestHandler, NetConfig,
 };
 use crate::prelude::ReplicationConfig;
+use crate::server::budget::ReplicationBudget;
+use crate::server::send_budget::SendBudget;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
 
@@ -67,6 +70,25 @@ pub struct PacketConfig {
     pub per_client_send_bandwidth_cap: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub bandwidth_cap_enabled: bool,
+    /// Maximum number of bytes in a packet. Messages that don't fit in a single packet are fragmented.
+    ///
+    /// The default is [`MAX_PACKET_SIZE`], which is safe for UDP. Lower it if the transport has a smaller
+    /// practical limit (for example WebTransport datagrams, which must also fit the QUIC overhead).
+    /// The value is clamped between 256 and [`MAX_PACKET_SIZE`].
+    pub max_packet_size: usize,
+    /// Maximum number of bytes of messages that are kept to be sent later when the bandwidth cap is reached.
+    ///
+    /// Only the messages of the unordered unreliable channels (without a `max_age`) are deferred: when the
+    /// bandwidth is limited, the messages of the low-priority channels (chat, telemetry, etc.) are sent a bit
+    /// later instead of being lost, while the inputs and the replication messages are sent first.
+    pub max_deferred_bytes: usize,
+    /// If true, an entity that is spawned for a client (because it was just created, or because it just
+    /// became visible to the client via rooms or [`NetworkRelevanceMode`](crate::prelude::NetworkRelevanceMode))
+    /// is sent immediately at maximum priority, even if the bandwidth cap is reached, instead of waiting for
+    /// the accumulated priority of its replication group.
+    ///
+    /// Only relevant if the bandwidth cap is enabled.
+    pub spawn_priority_boost: bool,
 }
 
 impl Default for PacketConfig {
@@ -76,6 +98,9 @@ impl Default for PacketConfig {
             // 56 KB/s bandwidth cap
             per_client_send_bandwidth_cap: Quota::per_second(nonzero!(56000u32)),
             bandwidth_cap_enabled: false,
+            max_packet_size: MAX_PACKET_SIZE,
+            max_deferred_bytes: 16 * 1024,
+            spawn_priority_boost: false,
         }
     }
 }
@@ -96,6 +121,18 @@ impl PacketConfig {
         self.bandwidth_cap_enabled = true;
         self
     }
+
+    /// Send newly spawned or newly visible entities at maximum priority
+    pub fn enable_spawn_priority_boost(mut self) -> Self {
+        self.spawn_priority_boost = true;
+        self
+    }
+
+    /// Set the maximum number of bytes in a packet sent on the connection
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
 }
 
 /// Configuration for the server plugin.
@@ -113,6 +150,30 @@ pub struct ServerConfig {
     pub packet: PacketConfig,
     pub replication: ReplicationConfig,
     pub ping: PingConfig,
+    /// How long the [`ConnectionMetadata`](crate::server::metadata::ConnectionMetadata) of a client is kept after it disconnects,
+    /// so that it can be restored if the client reconnects.
+    ///
+    /// The default is zero: the metadata is dropped as soon as the client disconnects.
+    pub metadata_retention: Duration,
+    /// How long the server keeps the connection of a client that lost its connection, so that the
+    /// session can be resumed if the client reconnects with the same [`ClientId`](crate::prelude::ClientId).
+    ///
+    /// When a client connects, the server issues it a random session token; the session is only resumed
+    /// if the reconnected client sends back that token, otherwise the client is disconnected.
+    /// A resumed client keeps its entity mappings and replication acks, so it only receives the
+    /// changes that it has not acked yet instead of the entire world.
+    /// While the session is suspended, the messages and replication updates for the client keep being
+    /// buffered but the packets are dropped, as if the network was down; the
+    /// [`DisconnectEvent`](crate::server::events::DisconnectEvent) is only emitted once the grace period expires.
+    ///
+    /// The client must keep its own state as well, see [`ClientConfig::session_resumption`](crate::client::config::ClientConfig::session_resumption).
+    ///
+    /// The default is zero: the connection is dropped as soon as the client disconnects.
+    pub session_grace_period: Duration,
+    /// Limits on the entities that each client can replicate to the server
+    pub replication_budget: ReplicationBudget,
+    /// Limits on the bytes that the server sends to the clients every frame
+    pub send_budget: SendBudget,
 }
 
 #[cfg(test)]
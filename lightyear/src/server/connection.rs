@@ -1,17 +1,22 @@
 //! Specify how a Server sends/receives messages with a Client
+use std::sync::Arc;
+
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::{EntityHash, MapEntities};
 use bevy::prelude::{Component, Entity, Resource, World};
 use bevy::ptr::Ptr;
-use bevy::utils::{Duration, HashMap};
+use bevy::utils::{Duration, HashMap, HashSet};
+use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 use bytes::Bytes;
+use crossbeam_channel::Receiver;
 use hashbrown::hash_map::Entry;
-use tracing::{debug, info, info_span, trace, trace_span};
+use tracing::{debug, error, info, info_span, trace, trace_span, warn};
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
 use crate::channel::builder::{
-    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel,
+    DisconnectChannel, EntityUpdatesChannel, OrderedResourceChannel, PingChannel, PongChannel,
+    ProtocolCheckChannel, SessionChannel,
 };
 
 use crate::channel::receivers::ChannelReceive;
@@ -19,7 +24,10 @@ use crate::channel::senders::ChannelSend;
 use crate::client::message::ClientMessage;
 use crate::connection::id::ClientId;
 use crate::connection::netcode::MAX_PACKET_SIZE;
-use crate::packet::message_manager::MessageManager;
+use crate::packet::message::MessageId;
+use crate::packet::message_manager::{
+    is_valid_priority, FragmentationStats, MessageManager, DEFAULT_MESSAGE_PRIORITY,
+};
 use crate::packet::packet_builder::{Payload, RecvPayload};
 use crate::prelude::server::{DisconnectEvent, RoomId, RoomManager};
 use crate::prelude::{
@@ -30,32 +38,90 @@ use crate::protocol::channel::ChannelRegistry;
 use crate::protocol::component::{
     ComponentError, ComponentKind, ComponentNetId, ComponentRegistry,
 };
-use crate::protocol::message::{MessageError, MessageRegistry, MessageType};
+use crate::protocol::extension::{ProtocolExtensionHashes, ProtocolExtensionId};
+use crate::protocol::message::{MessageError, MessageKind, MessageRegistry, MessageType};
 use crate::protocol::registry::NetId;
+use crate::protocol::version::{ProtocolHash, ProtocolVersions, TypeVersion};
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
+use crate::server::budget::{BudgetTracker, ReplicationBudget};
 use crate::server::config::PacketConfig;
 use crate::server::error::ServerError;
 use crate::server::events::{ConnectEvent, ServerEvents};
+use crate::server::metadata::{ConnectionMetadata, RetainedMetadata};
+use crate::server::ownership::OwnershipTransfer;
 use crate::server::relevance::error::RelevanceError;
+use crate::server::send_budget::{ClientSendStats, SendBudget, SendScheduler};
 use crate::shared::events::connection::ConnectionEvents;
-use crate::shared::message::MessageSend;
+use crate::shared::message::{MessageSend, TickTargetedMessage};
 use crate::shared::ping::manager::{PingConfig, PingManager};
 use crate::shared::ping::message::{Ping, Pong};
+use crate::shared::replication::capture::CapturedReplicationMessage;
 use crate::shared::replication::components::ReplicationGroupId;
 use crate::shared::replication::delta::DeltaManager;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
+use crate::shared::replication::strategy::{DefaultReplicationStrategy, ReplicationSendStrategy};
 use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage, ReplicationPeer};
 use crate::shared::replication::{ReplicationReceive, ReplicationSend};
 use crate::shared::sets::ServerMarker;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
+use crate::transport::io::IoStats;
 
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
+type EntityHashSet<K> = hashbrown::HashSet<K, EntityHash>;
+
+/// Remove the client from the list of clients of the entity's component.
+///
+/// Returns true if the client was in the list.
+fn remove_client(
+    components: &mut EntityHashMap<Entity, HashMap<ComponentKind, Vec<ClientId>>>,
+    entity: Entity,
+    kind: ComponentKind,
+    client_id: ClientId,
+) -> bool {
+    let Some(kinds) = components.get_mut(&entity) else {
+        return false;
+    };
+    let Some(clients) = kinds.get_mut(&kind) else {
+        return false;
+    };
+    let len = clients.len();
+    clients.retain(|c| *c != client_id);
+    let removed = clients.len() != len;
+    if clients.is_empty() {
+        kinds.remove(&kind);
+        if kinds.is_empty() {
+            components.remove(&entity);
+        }
+    }
+    removed
+}
+
+/// Send state of a component that has a replication interval
+/// (see [`ComponentRegistration::with_replication_interval`](crate::protocol::component::ComponentRegistration::with_replication_interval))
+#[derive(Debug, Default)]
+pub(crate) struct ThrottledComponent {
+    /// Tick at which the last update of the component was sent
+    last_sent: Option<Tick>,
+    /// The component changed since the last update was sent
+    pending: bool,
+}
+
+/// Result of [`ConnectionManager::throttle_component_update`]
+#[derive(Debug, PartialEq)]
+pub(crate) enum ThrottledUpdate {
+    /// The component changed and the interval elapsed: the update must be sent now
+    Send,
+    /// The component changed but the interval did not elapse yet: the update is sent later
+    Pending,
+    /// The component did not change since the last update was sent
+    Idle,
+}
 
 #[derive(Resource)]
 pub struct ConnectionManager {
@@ -69,11 +135,36 @@ pub struct ConnectionManager {
     // (we want to keep track of them because we need to replicate the entire world state to them)
     pub(crate) new_clients: Vec<ClientId>,
     pub(crate) writer: Writer,
+    /// Components that should not be replicated to some clients
+    pub(crate) hidden_components: EntityHashMap<Entity, HashMap<ComponentKind, Vec<ClientId>>>,
+    /// Components that were hidden and have to be replicated again to some clients.
+    ///
+    /// The entries are consumed during the next replication pass.
+    pub(crate) shown_components: EntityHashMap<Entity, HashMap<ComponentKind, Vec<ClientId>>>,
+    /// Send state of the components that have a replication interval
+    pub(crate) throttled_components:
+        EntityHashMap<Entity, HashMap<ComponentKind, ThrottledComponent>>,
+    /// Ownership transfers that will be applied during the next replication pass
+    pub(crate) pending_ownership_transfers: Vec<OwnershipTransfer>,
+    /// Entities whose replication was paused with [`ReplicationPaused`](crate::prelude::ReplicationPaused)
+    pub(crate) paused_entities: EntityHashSet<Entity>,
+    /// Metadata of the clients that recently disconnected
+    retained_metadata: RetainedMetadata,
+    /// How long the connection of a client that lost its connection is kept
+    session_grace_period: Duration,
+    /// Map from the entities that were loaded in the server World to the entity ids that
+    /// are used on the network (see [`ConnectionManager::set_entity_remapping`])
+    entity_remapping: bevy::ecs::entity::EntityHashMap<Entity>,
+    /// Strategy used to send the replication messages to the clients
+    replication_strategy: Arc<dyn ReplicationSendStrategy>,
 
     // CONFIG
     replication_config: ReplicationConfig,
     packet_config: PacketConfig,
     ping_config: PingConfig,
+    replication_budget: ReplicationBudget,
+    pub(crate) send_budget: SendBudget,
+    pub(crate) send_scheduler: SendScheduler,
 }
 
 // This is useful in cases where we need to temporarily store a fake ConnectionManager
@@ -85,6 +176,10 @@ impl Default for ConnectionManager {
             ReplicationConfig::default(),
             PacketConfig::default(),
             PingConfig::default(),
+            Duration::default(),
+            Duration::default(),
+            ReplicationBudget::default(),
+            SendBudget::default(),
         )
     }
 }
@@ -96,6 +191,10 @@ impl ConnectionManager {
         replication_config: ReplicationConfig,
         packet_config: PacketConfig,
         ping_config: PingConfig,
+        metadata_retention: Duration,
+        session_grace_period: Duration,
+        replication_budget: ReplicationBudget,
+        send_budget: SendBudget,
     ) -> Self {
         Self {
             connections: HashMap::default(),
@@ -105,9 +204,54 @@ impl ConnectionManager {
             delta_manager: DeltaManager::default(),
             new_clients: vec![],
             writer: Writer::with_capacity(MAX_PACKET_SIZE),
+            hidden_components: EntityHashMap::default(),
+            shown_components: EntityHashMap::default(),
+            throttled_components: EntityHashMap::default(),
+            paused_entities: EntityHashSet::default(),
+            pending_ownership_transfers: vec![],
+            retained_metadata: RetainedMetadata::new(metadata_retention),
+            session_grace_period,
+            entity_remapping: bevy::ecs::entity::EntityHashMap::default(),
+            replication_strategy: Arc::new(DefaultReplicationStrategy),
             replication_config,
             packet_config,
             ping_config,
+            replication_budget,
+            send_budget,
+            send_scheduler: SendScheduler::default(),
+        }
+    }
+
+    /// Keep the entity ids seen by the clients stable when the server World is reloaded,
+    /// for example after restarting the server from a save.
+    ///
+    /// `old_to_new` maps the id that an entity had before the restart to the id of the entity
+    /// that was spawned when loading the save. The server keeps using the old ids on the network:
+    /// - the entities are replicated to the clients with their old ids, so any entity id stored by the
+    ///   clients stays valid after they reconnect
+    /// - the entities received from the clients (in messages, inputs, etc.) are mapped back to the new ids
+    ///
+    /// The remapping replaces any previous remapping, and applies to the clients that are already connected
+    /// as well as the clients that connect later.
+    pub fn set_entity_remapping(&mut self, old_to_new: impl IntoIterator<Item = (Entity, Entity)>) {
+        self.entity_remapping = old_to_new
+            .into_iter()
+            .map(|(old, new)| (new, old))
+            .collect();
+        for connection in self.connections.values_mut() {
+            if !connection.is_local_client() {
+                connection.apply_entity_remapping(&self.entity_remapping);
+            }
+        }
+    }
+
+    /// Replace the [`ReplicationSendStrategy`] used to send the replication messages to the clients.
+    ///
+    /// The strategy applies to the clients that are already connected as well as the clients that connect later.
+    pub fn set_replication_strategy(&mut self, strategy: impl ReplicationSendStrategy) {
+        self.replication_strategy = Arc::new(strategy);
+        for connection in self.connections.values_mut() {
+            connection.replication_sender.strategy = self.replication_strategy.clone();
         }
     }
 
@@ -137,13 +281,76 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Map an entity from the world of the provided client to an existing local entity.
+    ///
+    /// When the client replicates `remote_entity`, the replicated components will be inserted on
+    /// `local_entity` instead of spawning a new entity.
+    pub fn map_remote_entity(
+        &mut self,
+        client_id: ClientId,
+        remote_entity: Entity,
+        local_entity: Entity,
+    ) -> Result<(), ServerError> {
+        self.connection_mut(client_id)?
+            .replication_receiver
+            .map_remote_entity(remote_entity, local_entity);
+        Ok(())
+    }
+
+    /// Disconnect a client, after sending it a final message that explains the reason of the disconnection.
+    ///
+    /// The reason is sent reliably, and the connection is closed once the client has acknowledged it
+    /// (or after [`DISCONNECT_TIMEOUT`]). On the client, the reason is available in the
+    /// [`DisconnectEvent`](crate::client::events::DisconnectEvent) as a
+    /// [`DisconnectReason::Kicked`](crate::connection::client::DisconnectReason::Kicked).
+    ///
+    /// The message must be registered in the protocol so that it can be sent from the server to the client.
+    /// This has no effect on the local client when running in HostServer mode.
+    pub fn disconnect<M: Message>(
+        &mut self,
+        client_id: ClientId,
+        mut reason: M,
+    ) -> Result<(), ServerError> {
+        let acks = self
+            .connection_mut(client_id)?
+            .message_manager
+            .channels
+            .get_mut(&ChannelKind::of::<DisconnectChannel>())
+            .unwrap()
+            .sender
+            .subscribe_acks();
+        self.send_message::<DisconnectChannel, M>(client_id, &mut reason)?;
+        let connection = self.connection_mut(client_id)?;
+        connection.pending_disconnect = Some(PendingDisconnect {
+            acks,
+            timeout: DISCONNECT_TIMEOUT,
+        });
+        connection.resumable = false;
+        Ok(())
+    }
+
     /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`]
     pub fn send_message_to_target<C: Channel, M: Message>(
         &mut self,
         message: &mut M,
         target: NetworkTarget,
     ) -> Result<(), ServerError> {
-        self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target)
+        self.send_message_to_target_with_priority::<C, M>(message, target, DEFAULT_MESSAGE_PRIORITY)
+    }
+
+    /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`], with a given priority.
+    ///
+    /// See [`send_message_with_priority`](Self::send_message_with_priority)
+    pub fn send_message_to_target_with_priority<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        target: NetworkTarget,
+        priority: f32,
+    ) -> Result<(), ServerError> {
+        if !is_valid_priority(priority) {
+            return Err(ServerError::InvalidPriority(priority));
+        }
+        self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target, priority)
     }
 
     /// Send a message to all clients in a room
@@ -169,6 +376,173 @@ impl ConnectionManager {
         self.send_message_to_target::<C, M>(message, NetworkTarget::Single(client_id))
     }
 
+    /// Queues up a message to be sent to a client, with a given priority.
+    ///
+    /// Messages with a higher priority are sent before the other messages buffered on the same channel
+    /// (the default priority is 1.0), for example so that an urgent message does not wait behind bulk messages.
+    /// The priority must be finite and non-negative.
+    /// Note that the priority does not change the order in which messages are received on
+    /// ordered channels, and is ignored on sequenced channels.
+    pub fn send_message_with_priority<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &mut M,
+        priority: f32,
+    ) -> Result<(), ServerError> {
+        self.send_message_to_target_with_priority::<C, M>(
+            message,
+            NetworkTarget::Single(client_id),
+            priority,
+        )
+    }
+
+    /// Queues up a message to be sent to a client, that the client will only emit once it reaches the given `tick`.
+    ///
+    /// This can be used to trigger something at the same time on every client (for example a cinematic,
+    /// or the start of a countdown). The tick is on the client's timeline; if the client has already
+    /// reached it when the message is received, the message is emitted right away.
+    pub fn send_message_at_tick<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &mut M,
+        tick: Tick,
+    ) -> Result<(), ServerError> {
+        self.send_message_to_target_at_tick::<C, M>(message, tick, NetworkTarget::Single(client_id))
+    }
+
+    /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`], that the clients
+    /// will only emit once they reach the given `tick`.
+    ///
+    /// See [`send_message_at_tick`](Self::send_message_at_tick)
+    pub fn send_message_to_target_at_tick<C: Channel, M: Message>(
+        &mut self,
+        message: &mut M,
+        tick: Tick,
+        target: NetworkTarget,
+    ) -> Result<(), ServerError> {
+        let channel = ChannelKind::of::<C>();
+        self.connections
+            .iter_mut()
+            .filter(|(id, _)| target.targets(id))
+            .try_for_each(|(_, c)| {
+                self.message_registry.serialize(
+                    message,
+                    &mut self.writer,
+                    Some(&mut c.replication_receiver.remote_entity_map.local_to_remote),
+                )?;
+                let message = TickTargetedMessage {
+                    tick,
+                    message: self.writer.split(),
+                };
+                self.message_registry
+                    .serialize(&message, &mut self.writer, None)?;
+                let message_bytes = self.writer.split();
+                // for local clients, we don't want to buffer messages in the MessageManager since
+                // there is no io
+                if c.is_local_client() {
+                    c.local_messages_to_send.push((message_bytes, channel));
+                } else {
+                    c.buffer_message(message_bytes, channel, DEFAULT_MESSAGE_PRIORITY)?;
+                }
+                Ok::<(), ServerError>(())
+            })
+    }
+
+    /// Return the tick that the client is currently rendering for its interpolated entities.
+    ///
+    /// This is derived from the current server tick and the interpolation delay reported by the client,
+    /// which can be used for lag compensation or to know if the client could already see an event.
+    /// Returns `None` if the client hasn't reported its interpolation delay yet.
+    pub fn interpolated_tick(
+        &self,
+        client_id: ClientId,
+        tick_manager: &TickManager,
+    ) -> Result<Option<Tick>, ServerError> {
+        let Some(delay) = self.connection(client_id)?.interpolation_delay() else {
+            return Ok(None);
+        };
+        Ok(Some(
+            tick_manager.interpolation_tick(tick_manager.tick(), delay),
+        ))
+    }
+
+    /// Change the interval at which the messages buffered on the [`Channel`] are sent to a client.
+    ///
+    /// This overrides the [`send_frequency`](crate::prelude::ChannelSettings::send_frequency) of the channel
+    /// for this client; a zero interval sends the messages as soon as possible.
+    /// If the interval is shorter than the replication send interval, the replication messages are also
+    /// buffered at that interval so that the channel is not limited by the replication send interval.
+    pub fn set_channel_send_interval<C: Channel>(
+        &mut self,
+        client_id: ClientId,
+        send_interval: Duration,
+    ) -> Result<(), ServerError> {
+        self.connection_mut(client_id)?
+            .message_manager
+            .set_channel_send_interval(ChannelKind::of::<C>(), send_interval)?;
+        Ok(())
+    }
+
+    /// Add a channel that was registered after startup to all the connections
+    pub(crate) fn add_dynamic_channel(&mut self, kind: ChannelKind, registry: &ChannelRegistry) {
+        self.channel_registry = registry.clone();
+        for connection in self.connections.values_mut() {
+            connection.message_manager.add_channel(kind, registry);
+        }
+    }
+
+    /// Number of messages sent to a client on the [`Channel`] that were dropped because they stayed buffered
+    /// for longer than the channel's [`max_age`](crate::prelude::ChannelSettings::max_age)
+    pub fn stale_messages_dropped<C: Channel>(
+        &self,
+        client_id: ClientId,
+    ) -> Result<usize, ServerError> {
+        Ok(self
+            .connection(client_id)?
+            .message_manager
+            .stale_messages_dropped(ChannelKind::of::<C>())?)
+    }
+
+    /// Serialize a [`Message`] so that it can be sent later (possibly multiple times) with
+    /// [`send_raw`](Self::send_raw) or [`broadcast_raw`](Self::broadcast_raw).
+    ///
+    /// The entities in the message are not mapped to the clients' worlds.
+    pub fn serialize_message<M: Message>(&mut self, message: &M) -> Result<Bytes, ServerError> {
+        self.message_registry
+            .serialize(message, &mut self.writer, None)?;
+        Ok(self.writer.split())
+    }
+
+    /// Send an already serialized message to a client using a specific [`Channel`]
+    ///
+    /// The bytes must contain a message serialized with [`serialize_message`](Self::serialize_message)
+    /// (or by any peer using the same protocol): the receiver uses the message kind at the start of the bytes
+    /// to deserialize the message.
+    pub fn send_raw<C: Channel>(
+        &mut self,
+        client_id: ClientId,
+        message_bytes: Bytes,
+    ) -> Result<(), ServerError> {
+        self.broadcast_raw::<C>(message_bytes, NetworkTarget::Single(client_id))
+    }
+
+    /// Send an already serialized message to all clients matching the [`NetworkTarget`] using a specific [`Channel`]
+    ///
+    /// See [`send_raw`](Self::send_raw)
+    pub fn broadcast_raw<C: Channel>(
+        &mut self,
+        message_bytes: Bytes,
+        target: NetworkTarget,
+    ) -> Result<(), ServerError> {
+        self.message_registry.check_raw(&message_bytes)?;
+        self.buffer_message_bytes(
+            message_bytes,
+            ChannelKind::of::<C>(),
+            target,
+            DEFAULT_MESSAGE_PRIORITY,
+        )
+    }
+
     /// Update the priority of a `ReplicationGroup` that is replicated to a given client
     pub fn update_priority(
         &mut self,
@@ -188,6 +562,180 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Get the [`ConnectionMetadata`] of the client
+    pub fn metadata(&self, client_id: ClientId) -> Result<&ConnectionMetadata, ServerError> {
+        self.connection(client_id).map(|c| &c.metadata)
+    }
+
+    /// Get a mutable reference to the [`ConnectionMetadata`] of the client,
+    /// which can be used to store arbitrary data about the client
+    pub fn metadata_mut(
+        &mut self,
+        client_id: ClientId,
+    ) -> Result<&mut ConnectionMetadata, ServerError> {
+        self.connection_mut(client_id).map(|c| &mut c.metadata)
+    }
+
+    /// Start recording the replication messages that are generated for the client.
+    ///
+    /// This is meant to be used in tests, to make assertions on the exact messages that are sent.
+    /// The recorded messages can be retrieved with [`take_captured_replication`](Self::take_captured_replication).
+    pub fn capture_replication(&mut self, client_id: ClientId) -> Result<(), ServerError> {
+        self.connection_mut(client_id)?
+            .replication_sender
+            .capture
+            .get_or_insert_with(Vec::new);
+        Ok(())
+    }
+
+    /// Take the replication messages recorded for the client since the last call.
+    ///
+    /// Returns an empty list if [`capture_replication`](Self::capture_replication) was not called.
+    pub fn take_captured_replication(
+        &mut self,
+        client_id: ClientId,
+    ) -> Result<Vec<CapturedReplicationMessage>, ServerError> {
+        Ok(self
+            .connection_mut(client_id)?
+            .replication_sender
+            .capture
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default())
+    }
+
+    /// Stop replicating the component `C` of the entity to the given client, without modifying
+    /// the replication settings of the entity for the other clients.
+    ///
+    /// The client keeps the last value of the component that it received, but won't receive any
+    /// inserts, updates or removals for that component until [`show_component`](Self::show_component) is called.
+    pub fn hide_component<C: Component>(
+        &mut self,
+        client_id: ClientId,
+        entity: Entity,
+    ) -> Result<(), ServerError> {
+        self.connection(client_id)?;
+        let kind = ComponentKind::of::<C>();
+        debug!(?client_id, ?entity, ?kind, "Hide component");
+        remove_client(&mut self.shown_components, entity, kind, client_id);
+        let clients = self
+            .hidden_components
+            .entry(entity)
+            .or_default()
+            .entry(kind)
+            .or_default();
+        if !clients.contains(&client_id) {
+            clients.push(client_id);
+        }
+        Ok(())
+    }
+
+    /// Resume replicating the component `C` of the entity to the given client after it was hidden
+    /// with [`hide_component`](Self::hide_component).
+    ///
+    /// The current value of the component is sent to the client during the next replication pass,
+    /// or the component is removed on the client if the entity doesn't have it anymore.
+    pub fn show_component<C: Component>(
+        &mut self,
+        client_id: ClientId,
+        entity: Entity,
+    ) -> Result<(), ServerError> {
+        self.connection(client_id)?;
+        let kind = ComponentKind::of::<C>();
+        if remove_client(&mut self.hidden_components, entity, kind, client_id) {
+            debug!(?client_id, ?entity, ?kind, "Show component");
+            self.shown_components
+                .entry(entity)
+                .or_default()
+                .entry(kind)
+                .or_default()
+                .push(client_id);
+        }
+        Ok(())
+    }
+
+    /// Transfer the ownership of the entity from the client `from` to the client `to`.
+    ///
+    /// The transfer is applied during the next replication pass: the inputs of `to` are accepted for the
+    /// entity instead of the inputs of `from`, and `to` starts predicting the entity while `from`
+    /// interpolates it. See the [`ownership`](crate::server::ownership) module for more details.
+    pub fn transfer_authority(
+        &mut self,
+        entity: Entity,
+        from: ClientId,
+        to: ClientId,
+    ) -> Result<(), ServerError> {
+        self.connection(to)?;
+        debug!(?entity, ?from, ?to, "Queue ownership transfer");
+        self.pending_ownership_transfers
+            .push(OwnershipTransfer { entity, from, to });
+        Ok(())
+    }
+
+    /// Returns true if the component `C` of the entity is hidden from the given client
+    pub fn is_component_hidden<C: Component>(&self, client_id: ClientId, entity: Entity) -> bool {
+        self.hidden_components
+            .get(&entity)
+            .and_then(|components| components.get(&ComponentKind::of::<C>()))
+            .is_some_and(|clients| clients.contains(&client_id))
+    }
+
+    /// Record whether a component that is sent at most once every `interval_ticks` changed,
+    /// and return whether its update should be sent during this replication pass.
+    pub(crate) fn throttle_component_update(
+        &mut self,
+        entity: Entity,
+        kind: ComponentKind,
+        changed: bool,
+        tick: Tick,
+        interval_ticks: i16,
+    ) -> ThrottledUpdate {
+        let state = self
+            .throttled_components
+            .entry(entity)
+            .or_default()
+            .entry(kind)
+            .or_default();
+        state.pending |= changed;
+        if !state.pending {
+            return ThrottledUpdate::Idle;
+        }
+        if state
+            .last_sent
+            .is_some_and(|last_sent| tick - last_sent < interval_ticks)
+        {
+            return ThrottledUpdate::Pending;
+        }
+        state.last_sent = Some(tick);
+        state.pending = false;
+        ThrottledUpdate::Send
+    }
+
+    /// Returns true if the protocol extension is enabled for the client
+    pub fn has_protocol_extension(
+        &self,
+        client_id: ClientId,
+        extension: ProtocolExtensionId,
+    ) -> Result<bool, ServerError> {
+        Ok(self
+            .connection(client_id)?
+            .protocol_extensions
+            .contains(&extension))
+    }
+
+    /// Returns the clients that cannot receive the type with this network id, because it is part of
+    /// a protocol extension that they did not enable
+    pub(crate) fn clients_without_net_id(&self, net_id: NetId) -> Option<NetworkTarget> {
+        ProtocolExtensionId::of(net_id)?;
+        Some(NetworkTarget::Only(
+            self.connections
+                .iter()
+                .filter(|(_, connection)| !connection.is_net_id_enabled(net_id))
+                .map(|(client_id, _)| *client_id)
+                .collect(),
+        ))
+    }
+
     /// Find the list of connected clients that match the provided [`NetworkTarget`]
     pub(crate) fn connected_targets(
         &self,
@@ -235,6 +783,26 @@ impl ConnectionManager {
         }
     }
 
+    /// Return the latest estimate of the round-trip time to a client
+    pub fn rtt(&self, client_id: ClientId) -> Result<Duration, ServerError> {
+        Ok(self.connection(client_id)?.rtt())
+    }
+
+    /// Return the latest estimate of the jitter of the round-trip time to a client
+    pub fn jitter(&self, client_id: ClientId) -> Result<Duration, ServerError> {
+        Ok(self.connection(client_id)?.jitter())
+    }
+
+    /// Estimate of the fraction of packets sent to a client that were lost (between 0.0 and 1.0)
+    pub fn packet_loss(&self, client_id: ClientId) -> Result<f32, ServerError> {
+        Ok(self.connection(client_id)?.packet_loss())
+    }
+
+    /// Estimate of the fraction of packets sent by a client that were lost (between 0.0 and 1.0)
+    pub fn incoming_packet_loss(&self, client_id: ClientId) -> Result<f32, ServerError> {
+        Ok(self.connection(client_id)?.incoming_packet_loss())
+    }
+
     pub fn connection(&self, client_id: ClientId) -> Result<&Connection, ServerError> {
         self.connections
             .get(&client_id)
@@ -256,6 +824,48 @@ impl ConnectionManager {
         self.connections.values_mut().for_each(|connection| {
             connection.update(world_tick, time_manager, tick_manager);
         });
+        self.retained_metadata.update(time_manager.delta());
+
+        // drop the connections of the clients that did not resume their session before the end of the grace period
+        let expired = self
+            .connections
+            .iter_mut()
+            .filter(|(_, connection)| connection.resumable)
+            .filter_map(|(client_id, connection)| {
+                let remaining = connection.suspended.as_mut()?;
+                *remaining = remaining.saturating_sub(time_manager.delta());
+                (*remaining == Duration::ZERO).then_some(*client_id)
+            })
+            .collect::<Vec<_>>();
+        for client_id in expired {
+            info!(?client_id, "The session of the disconnected client expired");
+            let connection = self.connections.get_mut(&client_id).unwrap();
+            if connection.resuming {
+                // the client reconnected but did not send its session token: the connection
+                // is closed once the client is disconnected
+                connection.reject_session();
+            } else {
+                self.close(client_id);
+            }
+        }
+    }
+
+    /// Returns true if the client lost its connection but can still resume its session
+    pub fn is_suspended(&self, client_id: ClientId) -> bool {
+        self.connections
+            .get(&client_id)
+            .is_some_and(|connection| connection.is_suspended())
+    }
+
+    /// Returns true if all the changes detected in previous replication passes have been buffered,
+    /// so that the replicated archetypes that did not change since then can be skipped.
+    pub(crate) fn can_skip_unchanged_archetypes(&self) -> bool {
+        self.new_clients.is_empty()
+            && self.shown_components.is_empty()
+            && self
+                .connections
+                .values()
+                .all(|connection| connection.replication_sender.all_changes_buffered())
     }
 
     /// Add a new [`Connection`] to the list of connections with the given [`ClientId`]
@@ -265,14 +875,31 @@ impl ConnectionManager {
             metrics::gauge!("connected_clients").increment(1.0);
 
             info!("New connection from id: {}", client_id);
-            let connection = Connection::new(
+            let mut connection = Connection::new(
                 client_id,
                 client_entity,
                 &self.channel_registry,
                 self.replication_config,
                 self.packet_config,
                 self.ping_config,
+                self.replication_budget,
             );
+            // restore the metadata if the client reconnected quickly
+            if let Some(metadata) = self.retained_metadata.take(client_id) {
+                debug!(
+                    ?client_id,
+                    "Restoring the metadata of the reconnected client"
+                );
+                connection.metadata = metadata;
+            }
+            connection.apply_entity_remapping(&self.entity_remapping);
+            connection.replication_sender.strategy = self.replication_strategy.clone();
+            if self.session_grace_period > Duration::ZERO {
+                // the client will need this token to resume its session
+                let _ = connection
+                    .issue_session_token()
+                    .inspect_err(|e| error!(?client_id, "Could not send the session token: {e}"));
+            }
             self.events.add_connect_event(ConnectEvent {
                 client_id,
                 entity: client_entity,
@@ -284,9 +911,57 @@ impl ConnectionManager {
         }
     }
 
+    /// Start resuming the session of a client that reconnected before the end of the grace period.
+    ///
+    /// The session is only resumed once the client has sent back the [`SessionToken`] that was issued to it;
+    /// until then the connection stays suspended.
+    ///
+    /// Returns false if there is no suspended session for this client.
+    pub(crate) fn resume(&mut self, client_id: ClientId) -> bool {
+        let Some(connection) = self
+            .connections
+            .get_mut(&client_id)
+            .filter(|connection| connection.is_suspended() && connection.resumable)
+        else {
+            return false;
+        };
+        debug!(
+            ?client_id,
+            "Client reconnected, waiting for its session token"
+        );
+        connection.resuming = true;
+        true
+    }
+
     /// Remove the connection associated with the given [`ClientId`],
     /// and returns the [`Entity`] associated with the client
+    ///
+    /// If [`ServerConfig::session_grace_period`](crate::server::config::ServerConfig::session_grace_period)
+    /// is set, the connection is suspended instead so that the client can resume its session.
     pub(crate) fn remove(&mut self, client_id: ClientId) -> Entity {
+        if self.session_grace_period > Duration::ZERO {
+            // clients that were disconnected by the server cannot resume their session
+            if let Some(connection) = self
+                .connections
+                .get_mut(&client_id)
+                .filter(|connection| !connection.is_local_client() && connection.resumable)
+            {
+                info!(
+                    ?client_id,
+                    grace_period = ?self.session_grace_period,
+                    "Client lost its connection, suspending its session"
+                );
+                connection.suspended = Some(self.session_grace_period);
+                connection.resuming = false;
+                return connection.entity;
+            }
+        }
+        self.close(client_id)
+    }
+
+    /// Drop the connection associated with the given [`ClientId`],
+    /// and returns the [`Entity`] associated with the client
+    fn close(&mut self, client_id: ClientId) -> Entity {
         #[cfg(feature = "metrics")]
         metrics::gauge!("connected_clients").decrement(1.0);
 
@@ -296,7 +971,19 @@ impl ConnectionManager {
             .expect("client entity not found");
         self.events
             .add_disconnect_event(DisconnectEvent { client_id, entity });
-        self.connections.remove(&client_id);
+        if let Some(connection) = self.connections.remove(&client_id) {
+            self.retained_metadata
+                .retain(client_id, connection.metadata);
+        }
+        for components in [&mut self.hidden_components, &mut self.shown_components] {
+            components.retain(|_, kinds| {
+                kinds.retain(|_, clients| {
+                    clients.retain(|c| *c != client_id);
+                    !clients.is_empty()
+                });
+                !kinds.is_empty()
+            });
+        }
         entity
     }
 
@@ -305,6 +992,7 @@ impl ConnectionManager {
         message: Bytes,
         channel: ChannelKind,
         target: NetworkTarget,
+        priority: f32,
     ) -> Result<(), ServerError> {
         self.connections
             .iter_mut()
@@ -313,10 +1001,10 @@ impl ConnectionManager {
                 // for local clients, we don't want to buffer messages in the MessageManager since
                 // there is no io
                 if c.is_local_client() {
-                    c.local_messages_to_send.push(message.clone())
+                    c.local_messages_to_send.push((message.clone(), channel))
                 } else {
                     // NOTE: this clone is O(1), it just increments the reference count
-                    c.buffer_message(message.clone(), channel)?;
+                    c.buffer_message(message.clone(), channel, priority)?;
                 }
                 Ok::<(), ServerError>(())
             })
@@ -330,6 +1018,7 @@ impl ConnectionManager {
         message: &M,
         channel: ChannelKind,
         target: NetworkTarget,
+        priority: f32,
     ) -> Result<(), ServerError> {
         self.connections
             .iter_mut()
@@ -344,14 +1033,39 @@ impl ConnectionManager {
                 // for local clients, we don't want to buffer messages in the MessageManager since
                 // there is no io
                 if c.is_local_client() {
-                    c.local_messages_to_send.push(message_bytes);
+                    c.local_messages_to_send.push((message_bytes, channel));
                 } else {
-                    c.buffer_message(message_bytes, channel)?;
+                    c.buffer_message(message_bytes, channel, priority)?;
                 }
                 Ok::<(), ServerError>(())
             })
     }
 
+    /// Serialize the message in the version used by each client and buffer it
+    fn buffer_previous_version_message<M: Message>(
+        &mut self,
+        message: &M,
+        net_id: NetId,
+        channel: ChannelKind,
+        target: NetworkTarget,
+        priority: f32,
+    ) -> Result<(), ServerError> {
+        self.connections
+            .iter_mut()
+            .filter(|(id, _)| target.targets(id))
+            .try_for_each(|(_, c)| {
+                let version = c.message_version(net_id, &self.message_registry);
+                self.message_registry.serialize_version(
+                    message,
+                    &mut self.writer,
+                    Some(&mut c.replication_receiver.remote_entity_map.local_to_remote),
+                    version,
+                )?;
+                c.buffer_message(self.writer.split(), channel, priority)?;
+                Ok::<(), ServerError>(())
+            })
+    }
+
     /// Serialize the message and buffer it to be sent in each `Connection`.
     ///
     /// - If the message is not `MapEntities`, we can serialize it once and reuse the same bytes
@@ -361,15 +1075,55 @@ impl ConnectionManager {
         &mut self,
         message: &M,
         channel_kind: ChannelKind,
-        target: NetworkTarget,
+        mut target: NetworkTarget,
+        priority: f32,
     ) -> Result<(), ServerError> {
+        // do not send the message to clients that don't have its protocol extension
+        if let Some(excluded) = self
+            .message_registry
+            .kind_map
+            .net_id(&MessageKind::of::<M>())
+            .and_then(|net_id| self.clients_without_net_id(*net_id))
+        {
+            target.exclude(&excluded);
+        }
+        // send the message in their version to the clients that use a previous version
+        if let Some(net_id) = self
+            .message_registry
+            .kind_map
+            .net_id(&MessageKind::of::<M>())
+            .copied()
+            .filter(|net_id| self.message_registry.current_version(*net_id) > 0)
+        {
+            let current = self.message_registry.current_version(net_id);
+            let previous: Vec<ClientId> = self
+                .connections
+                .iter()
+                .filter(|(client_id, connection)| {
+                    target.targets(client_id)
+                        && connection.message_version(net_id, &self.message_registry) != current
+                })
+                .map(|(client_id, _)| *client_id)
+                .collect();
+            if !previous.is_empty() {
+                let previous = NetworkTarget::Only(previous);
+                target.exclude(&previous);
+                self.buffer_previous_version_message(
+                    message,
+                    net_id,
+                    channel_kind,
+                    previous,
+                    priority,
+                )?;
+            }
+        }
         if self.message_registry.is_map_entities::<M>() {
-            self.buffer_map_entities_message(message, channel_kind, target)?;
+            self.buffer_map_entities_message(message, channel_kind, target, priority)?;
         } else {
             self.message_registry
                 .serialize(message, &mut self.writer, None)?;
             let message_bytes = self.writer.split();
-            self.buffer_message_bytes(message_bytes, channel_kind, target)?;
+            self.buffer_message_bytes(message_bytes, channel_kind, target, priority)?;
         }
         Ok(())
     }
@@ -390,6 +1144,9 @@ impl ConnectionManager {
             .try_for_each(move |c| c.buffer_replication_messages(tick, bevy_tick, time_manager))
     }
 
+    /// Read the messages received from each client and apply them to the world.
+    ///
+    /// Returns the errors that happened, along with the client that caused them
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     pub(crate) fn receive(
         &mut self,
@@ -398,33 +1155,42 @@ impl ConnectionManager {
         message_registry: &MessageRegistry,
         time_manager: &TimeManager,
         tick_manager: &TickManager,
-    ) -> Result<(), ServerError> {
+    ) -> Vec<(Option<ClientId>, ServerError)> {
+        let mut errors = vec![];
         let mut messages_to_rebroadcast = vec![];
         // TODO: do this in parallel
         self.connections
             .iter_mut()
-            .try_for_each(|(client_id, connection)| {
+            .for_each(|(client_id, connection)| {
                 let _span = trace_span!("receive", ?client_id).entered();
                 // receive events on the connection
-                let events = connection.receive(
+                // (an error on one connection does not prevent receiving from the other connections)
+                match connection.receive(
                     world,
                     component_registry,
                     message_registry,
                     time_manager,
                     tick_manager,
-                )?;
-                // move the events from the connection to the connection manager
-                self.events.push_events(*client_id, events);
+                ) {
+                    Ok(events) => {
+                        // move the events from the connection to the connection manager
+                        self.events.push_events(*client_id, events);
+                    }
+                    Err(e) => errors.push((Some(*client_id), e)),
+                }
 
                 // rebroadcast messages
                 messages_to_rebroadcast
                     .extend(std::mem::take(&mut connection.messages_to_rebroadcast));
-                Ok::<(), ServerError>(())
-            })?;
+            });
         for (message, target, channel_kind) in messages_to_rebroadcast {
-            self.buffer_message_bytes(message, channel_kind, target)?;
+            if let Err(e) =
+                self.buffer_message_bytes(message, channel_kind, target, DEFAULT_MESSAGE_PRIORITY)
+            {
+                errors.push((None, e));
+            }
         }
-        Ok(())
+        errors
     }
 }
 
@@ -445,13 +1211,63 @@ impl ConnectionManager {
         // We store the Bytes in a hashmap, maybe more efficient to write the replication message directly?
         component_registry.serialize(data, &mut self.writer, None)?;
         let raw_data = self.writer.split();
-        self.connection_mut(client_id)?
+        let connection = self.connection_mut(client_id)?;
+        let network_entity = connection
+            .replication_receiver
+            .remote_entity_map
+            .local_to_remote
+            .network_entity(entity);
+        connection
             .replication_sender
-            .prepare_component_insert(entity, group_id, raw_data);
+            .prepare_component_insert(network_entity, group_id, raw_data);
         Ok(())
     }
 }
 
+/// Random token issued to a client when it connects, that it must send back to resume its session
+/// after losing its connection (see [`ServerConfig::session_grace_period`](crate::server::config::ServerConfig::session_grace_period))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SessionToken(pub(crate) u64);
+
+impl ToBytes for SessionToken {
+    fn len(&self) -> usize {
+        8
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        buffer.write_u64::<NetworkEndian>(self.0)?;
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        Ok(Self(buffer.read_u64::<NetworkEndian>()?))
+    }
+}
+
+/// Maximum amount of time that we wait for a client to acknowledge the reason of its disconnection
+/// before closing the connection. See [`ConnectionManager::disconnect`]
+pub const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A client that will be disconnected once it has received the reason of the disconnection
+#[derive(Debug)]
+pub(crate) struct PendingDisconnect {
+    /// Notified when the client acknowledges the disconnection reason
+    acks: Receiver<MessageId>,
+    /// Time left before we disconnect the client even if it did not acknowledge the reason
+    timeout: Duration,
+}
+
+impl PendingDisconnect {
+    /// Returns true if the connection with the client can now be closed
+    pub(crate) fn update(&mut self, delta: Duration) -> bool {
+        self.timeout = self.timeout.saturating_sub(delta);
+        self.acks.try_recv().is_ok() || self.timeout.is_zero()
+    }
+}
+
 /// Wrapper that handles the connection between the server and a client
 pub struct Connection {
     client_id: ClientId,
@@ -477,7 +1293,44 @@ pub struct Connection {
     /// True if this connection corresponds to a local client when running in host-server mode
     is_local_client: bool,
     /// Messages to send to the local client (we don't buffer them in the MessageManager because there is no io)
-    pub(crate) local_messages_to_send: Vec<Bytes>,
+    pub(crate) local_messages_to_send: Vec<(Bytes, ChannelKind)>,
+    /// User data attached to the connection
+    pub(crate) metadata: ConnectionMetadata,
+    /// Latest interpolation delay reported by the client
+    pub(crate) interpolation_delay: Option<Duration>,
+    /// Number of bytes and packets exchanged with the client since the connection was established
+    pub(crate) stats: IoStats,
+    /// Set if the client is being disconnected by the server
+    pub(crate) pending_disconnect: Option<PendingDisconnect>,
+    /// Limits on the entities that the client can replicate to the server
+    pub(crate) budget: BudgetTracker,
+    /// Remaining grace period if the client lost its connection but can still resume its session
+    pub(crate) suspended: Option<Duration>,
+    /// Token that the client must send back to resume its session
+    pub(crate) session_token: Option<SessionToken>,
+    /// False if the client was disconnected by the server, so that it cannot resume its session
+    pub(crate) resumable: bool,
+    /// True if the client reconnected during the grace period and we are waiting for its session token
+    pub(crate) resuming: bool,
+    /// Protocol extensions that are enabled for this client
+    pub(crate) protocol_extensions: HashSet<ProtocolExtensionId>,
+    /// True if the next replication send should be bundled in a join snapshot
+    pending_join_snapshot: bool,
+    /// Bytes sent to the client during the latest frame
+    pub(crate) send_stats: ClientSendStats,
+    /// True if the replication is held until the client has generated its world
+    /// (see [`WorldSeedPlugin`](crate::shared::world_seed::WorldSeedPlugin))
+    pub(crate) awaiting_world_ready: bool,
+    /// Notifications of the messages sent on the [`OrderedResourceChannel`] that were received by the client
+    ordered_resource_acks: Receiver<MessageId>,
+    /// Messages sent on the [`OrderedResourceChannel`] that the client hasn't received yet.
+    /// The replication is held until they are received.
+    unacked_ordered_resources: HashSet<MessageId>,
+    /// Versions of the messages used by the client (see [`version`](crate::protocol::version)).
+    /// `None` until the client has sent them.
+    message_versions: Option<HashMap<NetId, TypeVersion>>,
+    /// Versions of the components for which the client uses a previous version
+    pub(crate) previous_component_versions: HashMap<ComponentNetId, TypeVersion>,
 }
 
 impl Connection {
@@ -488,6 +1341,7 @@ impl Connection {
         replication_config: ReplicationConfig,
         packet_config: PacketConfig,
         ping_config: PingConfig,
+        replication_budget: ReplicationBudget,
     ) -> Self {
         let bandwidth_cap_enabled = packet_config.bandwidth_cap_enabled;
         // create the message manager and the channels
@@ -496,6 +1350,7 @@ impl Connection {
             packet_config.nack_rtt_multiple,
             packet_config.into(),
         );
+        message_manager.set_max_packet_size(packet_config.max_packet_size);
         // get notified about acks/nacks for replication-update messages
         let entity_updates_sender = &mut message_manager
             .channels
@@ -504,16 +1359,24 @@ impl Connection {
             .sender;
         let update_nacks_receiver = entity_updates_sender.subscribe_nacks();
         let update_acks_receiver = entity_updates_sender.subscribe_acks();
+        // get notified when the ordered resource updates are received
+        let ordered_resource_acks = message_manager
+            .channels
+            .get_mut(&ChannelKind::of::<OrderedResourceChannel>())
+            .unwrap()
+            .sender
+            .subscribe_acks();
         // get a channel to get notified when a replication update message gets actually send (to update priority)
         let replication_update_send_receiver =
             message_manager.get_replication_update_send_receiver();
-        let replication_sender = ReplicationSender::new(
+        let mut replication_sender = ReplicationSender::new(
             update_acks_receiver,
             update_nacks_receiver,
             replication_update_send_receiver,
             replication_config,
             bandwidth_cap_enabled,
         );
+        replication_sender.spawn_priority_boost = packet_config.spawn_priority_boost;
         let replication_receiver = ReplicationReceiver::new();
         Self {
             client_id,
@@ -531,12 +1394,94 @@ impl Connection {
             messages_to_rebroadcast: vec![],
             is_local_client: false,
             local_messages_to_send: vec![],
+            metadata: ConnectionMetadata::default(),
+            interpolation_delay: None,
+            stats: IoStats::default(),
+            pending_disconnect: None,
+            budget: BudgetTracker::new(replication_budget),
+            suspended: None,
+            session_token: None,
+            resumable: true,
+            resuming: false,
+            protocol_extensions: HashSet::default(),
+            pending_join_snapshot: replication_config.join_snapshot,
+            send_stats: ClientSendStats::default(),
+            awaiting_world_ready: false,
+            ordered_resource_acks,
+            unacked_ordered_resources: HashSet::default(),
+            message_versions: None,
+            previous_component_versions: HashMap::default(),
         }
     }
 
+    /// Version of the message with the given [`NetId`] used by the client.
+    ///
+    /// The messages that don't have previous versions in the client's protocol are at version 0.
+    pub(crate) fn message_version(
+        &self,
+        net_id: NetId,
+        message_registry: &MessageRegistry,
+    ) -> TypeVersion {
+        match &self.message_versions {
+            // the local client shares the protocol of the server
+            Some(versions) if !self.is_local_client => versions.get(&net_id).copied().unwrap_or(0),
+            _ => message_registry.current_version(net_id),
+        }
+    }
+
+    /// Keep track of the components for which the client uses a previous version.
+    ///
+    /// The components that don't have previous versions in the client's protocol are at version 0.
+    fn set_component_versions(
+        &mut self,
+        client_versions: ProtocolVersions,
+        component_registry: &ComponentRegistry,
+    ) {
+        for (net_id, version) in client_versions.0.iter() {
+            if !component_registry.supports_version(*net_id, *version) {
+                warn!(
+                    client_id = ?self.client_id,
+                    ?net_id,
+                    version,
+                    "The client uses a version of a component that the server does not support"
+                );
+            }
+        }
+        let client_versions: HashMap<_, _> = client_versions.0.into_iter().collect();
+        self.previous_component_versions = component_registry
+            .versions()
+            .0
+            .into_iter()
+            .filter_map(|(net_id, current)| {
+                let version = client_versions.get(&net_id).copied().unwrap_or(0);
+                (version != current).then_some((net_id, version))
+            })
+            .collect();
+    }
+
+    /// Returns true if the type with this network id can be sent to the client,
+    /// i.e. it is part of the base protocol or of an extension that the client enabled
+    pub(crate) fn is_net_id_enabled(&self, net_id: NetId) -> bool {
+        // the local client shares the protocol of the server
+        self.is_local_client
+            || ProtocolExtensionId::of(net_id).map_or(true, |extension| {
+                self.protocol_extensions.contains(&extension)
+            })
+    }
+
     /// Update the connection to make clear that it corresponds to the local client
     pub(crate) fn set_local_client(&mut self) {
         self.is_local_client = true;
+        self.pending_join_snapshot = false;
+        // the local client shares the server World, so it must see the actual entity ids
+        self.replication_receiver.remote_entity_map.clear();
+    }
+
+    /// Use the network ids of the remapped entities when communicating with this client
+    fn apply_entity_remapping(&mut self, new_to_old: &bevy::ecs::entity::EntityHashMap<Entity>) {
+        self.replication_receiver
+            .remote_entity_map
+            .set_aliases(new_to_old);
     }
 
     /// Returns true if this connection corresponds to the local client in HostServer mode
@@ -544,6 +1489,52 @@ impl Connection {
         self.is_local_client
     }
 
+    /// Returns true if the client lost its connection but can still resume its session
+    pub(crate) fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    /// Generate a new [`SessionToken`] and send it to the client
+    pub(crate) fn issue_session_token(&mut self) -> Result<(), ServerError> {
+        let token = SessionToken(rand::random());
+        token.to_bytes(&mut self.writer)?;
+        self.message_manager
+            .buffer_send(self.writer.split(), ChannelKind::of::<SessionChannel>())?;
+        self.session_token = Some(token);
+        Ok(())
+    }
+
+    /// Check the token sent by a client that reconnected during the grace period.
+    ///
+    /// If the token is valid the session is resumed and a new token is issued, otherwise
+    /// the client is disconnected.
+    pub(crate) fn check_session_token(&mut self, token: SessionToken) -> bool {
+        if self.session_token != Some(token) {
+            warn!(client_id = ?self.client_id, "Client sent an invalid session token");
+            self.reject_session();
+            return false;
+        }
+        info!(client_id = ?self.client_id, "Resuming the session of the reconnected client");
+        self.suspended = None;
+        self.resuming = false;
+        let _ = self.issue_session_token().inspect_err(
+            |e| error!(client_id = ?self.client_id, "Could not send a new session token: {e}"),
+        );
+        true
+    }
+
+    /// Disconnect the client without letting it resume its session.
+    ///
+    /// The connection is closed once the transport reports the disconnection.
+    fn reject_session(&mut self) {
+        self.resumable = false;
+        self.resuming = false;
+        self.pending_disconnect = Some(PendingDisconnect {
+            acks: crossbeam_channel::never(),
+            timeout: Duration::ZERO,
+        });
+    }
+
     /// Return the latest estimate of rtt
     pub fn rtt(&self) -> Duration {
         self.ping_manager.rtt()
@@ -554,6 +1545,43 @@ impl Connection {
         self.ping_manager.jitter()
     }
 
+    /// Estimate of the fraction of packets sent to the client that were lost (between 0.0 and 1.0)
+    pub fn packet_loss(&self) -> f32 {
+        self.message_manager.packet_loss()
+    }
+
+    /// Estimate of the fraction of packets sent by the client that were lost (between 0.0 and 1.0)
+    pub fn incoming_packet_loss(&self) -> f32 {
+        self.message_manager.incoming_packet_loss()
+    }
+
+    /// Statistics about the messages sent to the client that had to be fragmented
+    pub fn fragmentation_stats(&self) -> FragmentationStats {
+        self.message_manager.fragmentation_stats()
+    }
+
+    /// Return the total number of bytes and packets exchanged with the client since the connection was established
+    pub fn stats(&self) -> &IoStats {
+        &self.stats
+    }
+
+    /// Return the number of bytes sent to the client during the latest frame
+    pub fn send_stats(&self) -> &ClientSendStats {
+        &self.send_stats
+    }
+
+    /// Return the latest interpolation delay reported by the client: how far behind the server
+    /// the client's interpolation timeline is.
+    ///
+    /// Returns `None` if the client hasn't reported it yet (i.e. it is not synced yet).
+    /// The local client in HostServer mode does not interpolate, so its delay is zero.
+    pub fn interpolation_delay(&self) -> Option<Duration> {
+        if self.is_local_client() {
+            return Some(Duration::default());
+        }
+        self.interpolation_delay
+    }
+
     pub(crate) fn update(
         &mut self,
         world_tick: BevyTick,
@@ -567,12 +1595,14 @@ impl Connection {
             .update(time_manager, &self.ping_manager, tick_manager);
         self.replication_sender.update(world_tick);
         self.ping_manager.update(time_manager);
+        self.budget.update(time_manager.delta());
     }
 
     pub(crate) fn buffer_message(
         &mut self,
         message: Bytes,
         channel: ChannelKind,
+        priority: f32,
     ) -> Result<(), ServerError> {
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
@@ -582,7 +1612,12 @@ impl Connection {
             .name(&channel)
             .ok_or::<ServerError>(MessageError::NotRegistered.into())?;
         // message.emit_send_logs(&channel_name);
-        self.message_manager.buffer_send(message, channel)?;
+        let message_id = self
+            .message_manager
+            .buffer_send_with_priority(message, channel, priority)?;
+        if channel == ChannelKind::of::<OrderedResourceChannel>() {
+            self.unacked_ordered_resources.extend(message_id);
+        }
         Ok(())
     }
 
@@ -593,16 +1628,35 @@ impl Connection {
         bevy_tick: BevyTick,
         time_manager: &TimeManager,
     ) -> Result<(), ServerError> {
-        self.replication_sender.accumulate_priority(time_manager);
-        self.replication_sender.send_actions_messages(
-            tick,
-            bevy_tick,
-            &mut self.writer,
-            &mut self.message_manager,
-        )?;
-        self.replication_sender.send_updates_messages(
+        // the replication messages stay buffered in the sender until the client's world is ready
+        if self.awaiting_world_ready {
+            return Ok(());
+        }
+        // the entity updates are only sent once the client has received the ordered resource updates,
+        // so that they are applied after them
+        while let Ok(message_id) = self.ordered_resource_acks.try_recv() {
+            self.unacked_ordered_resources.remove(&message_id);
+        }
+        if !self.unacked_ordered_resources.is_empty() {
+            trace!(
+                client_id = ?self.client_id,
+                "holding replication until the ordered resource updates are received"
+            );
+            return Ok(());
+        }
+        // the entities that are replicated to the client when it connects are sent in a single snapshot
+        if std::mem::take(&mut self.pending_join_snapshot) {
+            self.replication_sender.send_join_snapshot(
+                tick,
+                bevy_tick,
+                &mut self.writer,
+                &mut self.message_manager,
+            )?;
+        }
+        self.replication_sender.buffer_messages(
             tick,
             bevy_tick,
+            time_manager,
             &mut self.writer,
             &mut self.message_manager,
         )?;
@@ -672,6 +1726,54 @@ impl Connection {
         tick_manager: &TickManager,
     ) -> Result<ConnectionEvents, ServerError> {
         let _span = trace_span!("receive").entered();
+        if self.is_suspended() {
+            // the messages of a client that reconnected are only read once it has resumed its session
+            if self.resuming {
+                let session_channel = self
+                    .message_manager
+                    .channels
+                    .get_mut(&ChannelKind::of::<SessionChannel>())
+                    .unwrap();
+                if let Some((_, data)) = session_channel.receiver.read_message() {
+                    let token = SessionToken::from_bytes(&mut Reader::from(data))?;
+                    if !self.check_session_token(token) {
+                        return Err(ServerError::InvalidSessionToken);
+                    }
+                }
+            }
+            if self.is_suspended() {
+                return Ok(std::mem::take(&mut self.events));
+            }
+        }
+        let mut client_protocol_hash = None;
+        let mut client_extensions = None;
+        let mut client_versions = None;
+        // read the protocol of the client first, so that we know the versions of its components
+        // before reading its replication messages
+        while let Some((_, data)) = self
+            .message_manager
+            .channels
+            .get_mut(&ChannelKind::of::<ProtocolCheckChannel>())
+            .unwrap()
+            .receiver
+            .read_message()
+        {
+            let mut reader = Reader::from(data);
+            client_protocol_hash = Some(ProtocolHash::from_bytes(&mut reader)?);
+            // the list of extensions and the versions are optional
+            if reader.has_remaining() {
+                client_extensions = Some(ProtocolExtensionHashes::from_bytes(&mut reader)?);
+            }
+            if reader.has_remaining() {
+                client_versions = Some(ProtocolVersions::from_bytes(&mut reader)?);
+            }
+            let client_component_versions = if reader.has_remaining() {
+                ProtocolVersions::from_bytes(&mut reader)?
+            } else {
+                ProtocolVersions::default()
+            };
+            self.set_component_versions(client_component_versions, component_registry);
+        }
         self.message_manager
             .channels
             .iter_mut()
@@ -699,14 +1801,45 @@ impl Connection {
                         // process the pong
                         self.ping_manager
                             .process_pong(&pong, time_manager.current_time());
-                    } else if channel_kind == &ChannelKind::of::<EntityActionsChannel>() {
-                        let actions = EntityActionsMessage::from_bytes(&mut reader)?;
+                    } else if channel_kind == &ChannelKind::of::<SessionChannel>() {
+                        // the session was already resumed
+                        trace!("ignoring session token");
+                    } else if self
+                        .message_manager
+                        .channel_registry
+                        .is_replication_actions_channel(channel_kind)
+                    {
+                        let mut actions = EntityActionsMessage::from_bytes(&mut reader)?;
                         trace!(?tick, ?actions, "received replication actions message");
+                        // drop the spawns and components that exceed the budget of the client
+                        self.budget.check_actions(&mut actions, tick_manager.tick());
+                        if !self.previous_component_versions.is_empty() {
+                            for (_, entity_actions) in actions.actions.iter_mut() {
+                                for components in [&mut entity_actions.insert, &mut entity_actions.updates] {
+                                    component_registry.upgrade_components(components, &self.previous_component_versions, &mut self.writer)?;
+                                }
+                            }
+                        }
                         // buffer the replication message
-                        self.replication_receiver.recv_actions(actions, tick);
+                        if self
+                            .message_manager
+                            .channel_registry
+                            .is_sequenced(channel_kind)
+                        {
+                            self.replication_receiver
+                                .recv_sequenced_actions(actions, tick);
+                        } else {
+                            self.replication_receiver.recv_actions(actions, tick);
+                        }
                     } else if channel_kind == &ChannelKind::of::<EntityUpdatesChannel>() {
-                        let updates = EntityUpdatesMessage::from_bytes(&mut reader)?;
+                        let mut updates = EntityUpdatesMessage::from_bytes(&mut reader)?;
                         trace!(?tick, ?updates, "received replication updates message");
+                        self.budget.check_updates(&mut updates, tick_manager.tick());
+                        if !self.previous_component_versions.is_empty() {
+                            for (_, components) in updates.updates.iter_mut() {
+                                component_registry.upgrade_components(components, &self.previous_component_versions, &mut self.writer)?;
+                            }
+                        }
                         // buffer the replication message
                         self.replication_receiver.recv_updates(updates, tick);
                     } else {
@@ -728,7 +1861,12 @@ impl Connection {
                         //  or it matters for input messages?
                         // TODO: avoid clone with Arc<[u8]>?
                         let data = (reader.consume(), target, *channel_kind);
-                        match message_registry.message_type(net_id) {
+                        let Some(message_type) =
+                            message_registry.message_type(net_id, &mut self.events)?
+                        else {
+                            continue;
+                        };
+                        match message_type {
                             #[cfg(feature = "leafwing")]
                             MessageType::LeafwingInput => self
                                 .received_leafwing_input_messages
@@ -744,10 +1882,13 @@ impl Connection {
                             MessageType::Normal => {
                                 self.received_messages.entry(net_id).or_default().push(data);
                             }
+                            MessageType::TickTargeted => {
+                                error!("Tick-targeted messages can only be sent from the server to the clients");
+                            }
                         }
                     }
                 }
-                Ok::<(), SerializationError>(())
+                Ok::<(), ServerError>(())
             })?;
 
         // Check if we have any replication messages we can apply to the World (and emit events)
@@ -759,10 +1900,52 @@ impl Connection {
             &mut self.events,
         );
 
+        // enable the protocol extensions that both the client and the server have
+        if let Some(client_extensions) = client_extensions {
+            let accepted = client_extensions.intersection(&ProtocolExtensionHashes::new(
+                message_registry,
+                component_registry,
+            ));
+            debug!(client_id = ?self.client_id, ?accepted, "Accepted protocol extensions");
+            self.protocol_extensions = accepted.ids().collect();
+            accepted.to_bytes(&mut self.writer)?;
+            self.message_manager.buffer_send(
+                self.writer.split(),
+                ChannelKind::of::<ProtocolCheckChannel>(),
+            )?;
+        }
+
+        // use the versions of the messages of the client
+        if let Some(client_versions) = client_versions {
+            for (net_id, version) in client_versions.0.iter() {
+                if !message_registry.supports_version(*net_id, *version) {
+                    warn!(
+                        client_id = ?self.client_id,
+                        ?net_id,
+                        version,
+                        "The client uses a version of a message that the server does not support"
+                    );
+                }
+            }
+            self.message_versions = Some(client_versions.0.into_iter().collect());
+        }
+
+        // check that the client uses the same protocol as us
+        if let Some(client) = client_protocol_hash {
+            let server = ProtocolHash::new(
+                &self.message_manager.channel_registry,
+                message_registry,
+                component_registry,
+            );
+            if client != server {
+                return Err(ServerError::ProtocolMismatch { client, server });
+            }
+        }
+
         // TODO: do i really need this? I could just create events in this function directly?
         //  why do i need to make events a field of the connection?
         //  is it because of push_connection?
-        Ok(std::mem::replace(&mut self.events, ConnectionEvents::new()))
+        Ok(std::mem::take(&mut self.events))
     }
 
     /// Receive bytes for a single message.
@@ -773,7 +1956,7 @@ impl Connection {
         mut reader: Reader,
         channel_kind: ChannelKind,
         message_registry: &MessageRegistry,
-    ) -> Result<(), SerializationError> {
+    ) -> Result<(), MessageError> {
         // TODO: we only get RawData here, does that mean we're deserializing multiple times?
         //  instead just read the bytes for the target!!
         let ClientMessage { message, target } = ClientMessage::from_bytes(&mut reader)?;
@@ -788,7 +1971,10 @@ impl Connection {
         //  or it matters for input messages?
         // TODO: avoid clone with Arc<[u8]>?
         let data = (reader.consume(), target, channel_kind);
-        match message_registry.message_type(net_id) {
+        let Some(message_type) = message_registry.message_type(net_id, &mut self.events)? else {
+            return Ok(());
+        };
+        match message_type {
             #[cfg(feature = "leafwing")]
             MessageType::LeafwingInput => self
                 .received_leafwing_input_messages
@@ -804,6 +1990,9 @@ impl Connection {
             MessageType::Normal => {
                 self.received_messages.entry(net_id).or_default().push(data);
             }
+            MessageType::TickTargeted => {
+                error!("Tick-targeted messages can only be sent from the server to the clients");
+            }
         }
         Ok(())
     }
@@ -859,8 +2048,11 @@ impl ConnectionManager {
         mut entity: Entity,
         kind: ComponentNetId,
         group: &ReplicationGroup,
-        target: NetworkTarget,
+        mut target: NetworkTarget,
     ) -> Result<(), ServerError> {
+        if let Some(excluded) = self.clients_without_net_id(kind) {
+            target.exclude(&excluded);
+        }
         let group_id = group.group_id(Some(entity));
         debug!(?entity, ?kind, "Sending RemoveComponent");
         self.connected_targets(target).try_for_each(|client_id| {
@@ -960,8 +2152,33 @@ impl ConnectionManager {
             };
             raw_data = Some(self.writer.split());
         }
+        let net_id = component_registry.kind_map.net_id(&kind).copied();
         self.connected_targets(actual_target)
             .try_for_each(|client_id| {
+                let connection = self
+                    .connections
+                    .get_mut(&client_id)
+                    .ok_or(ServerError::ClientIdNotFound(client_id))?;
+                // the client uses a previous version of the component
+                if let Some(version) =
+                    net_id.and_then(|net_id| connection.previous_component_versions.get(&net_id))
+                {
+                    let entity_map = &mut connection.replication_receiver.remote_entity_map;
+                    component_registry.erased_serialize_version(
+                        component_data,
+                        &mut self.writer,
+                        kind,
+                        Some(&mut entity_map.local_to_remote),
+                        *version,
+                    )?;
+                    let network_entity = entity_map.local_to_remote.network_entity(entity);
+                    connection.replication_sender.prepare_component_insert(
+                        network_entity,
+                        group_id,
+                        self.writer.split(),
+                    );
+                    return Ok(());
+                }
                 let entity = self
                     .connection_mut(client_id)?
                     .replication_receiver
@@ -1025,9 +2242,18 @@ impl ConnectionManager {
                 //     .entry(group)
                 //     .or_default()
                 //     .update_collect_changes_since_this_tick(system_current_tick);
-                self.connection_mut(client_id)?
-                    .replication_sender
-                    .prepare_component_insert(entity, group_id, raw_data.clone().unwrap());
+                let connection = self.connection_mut(client_id)?;
+                // use the network id of the entity, in case it was remapped
+                let network_entity = connection
+                    .replication_receiver
+                    .remote_entity_map
+                    .local_to_remote
+                    .network_entity(entity);
+                connection.replication_sender.prepare_component_insert(
+                    network_entity,
+                    group_id,
+                    raw_data.clone().unwrap(),
+                );
                 Ok(())
             })
     }
@@ -1048,14 +2274,12 @@ impl ConnectionManager {
     ) -> Result<(), ServerError> {
         let mut num_targets = 0;
         let mut existing_bytes: Option<Bytes> = None;
+        let net_id = registry.kind_map.net_id(&kind).copied();
         self.connected_targets(target).try_for_each(|client_id| {
             let connection = self.connections.get_mut(&client_id).ok_or(ServerError::ClientIdNotFound(client_id))?;
             let send_tick = connection
                 .replication_sender
-                .group_channels
-                .entry(group_id)
-                .or_default()
-                .send_tick;
+                .gather_changes_since(group_id);
             // send the update for all changes newer than the last send_tick for the group
             debug!(
                 ?kind,
@@ -1080,20 +2304,30 @@ impl ConnectionManager {
                 if delta_compression {
                     connection.replication_sender.prepare_delta_component_update(entity, group_id, kind, component, registry, &mut self.writer, &mut self.delta_manager, tick, &mut connection.replication_receiver.remote_entity_map)?;
                 } else {
-                    // we serialize once and re-use the result for all clients
-                    // serialize only if there is at least one client that needs the update
-                    if existing_bytes.is_none() || registry.erased_is_map_entities(kind) {
-                        registry.erased_serialize(component, &mut self.writer, kind, Some(&mut connection.replication_receiver.remote_entity_map.local_to_remote))?;
-                        // we re-serialize every time if there is entity mapping
-                        existing_bytes = Some(self.writer.split());
-                    }
-                    let raw_data = existing_bytes.clone().unwrap();
+                    let raw_data = if let Some(version) = net_id.and_then(|net_id| connection.previous_component_versions.get(&net_id)) {
+                        // the client uses a previous version of the component
+                        registry.erased_serialize_version(component, &mut self.writer, kind, Some(&mut connection.replication_receiver.remote_entity_map.local_to_remote), *version)?;
+                        self.writer.split()
+                    } else {
+                        // we serialize once and re-use the result for all clients
+                        // serialize only if there is at least one client that needs the update
+                        if existing_bytes.is_none() || registry.erased_is_map_entities(kind) {
+                            registry.erased_serialize(component, &mut self.writer, kind, Some(&mut connection.replication_receiver.remote_entity_map.local_to_remote))?;
+                            // we re-serialize every time if there is entity mapping
+                            existing_bytes = Some(self.writer.split());
+                        }
+                        existing_bytes.clone().unwrap()
+                    };
                     // use the network entity
                     let entity = connection
                         .replication_receiver
                         .remote_entity_map
                         .to_remote(entity);
-                    connection.replication_sender.prepare_component_update(entity, group_id, raw_data);
+                    if registry.has_reliable_updates(kind) {
+                        connection.replication_sender.prepare_reliable_component_update(entity, group_id, raw_data);
+                    } else {
+                        connection.replication_sender.prepare_component_update(entity, group_id, raw_data);
+                    }
                 }
             }
             Ok::<(), ServerError>(())
@@ -1136,7 +2370,7 @@ impl MessageSend for ConnectionManager {
         channel_kind: ChannelKind,
         target: NetworkTarget,
     ) -> Result<(), ServerError> {
-        self.erased_send_message_to_target(message, channel_kind, target)
+        self.erased_send_message_to_target(message, channel_kind, target, DEFAULT_MESSAGE_PRIORITY)
     }
 }
 
@@ -1170,6 +2404,13 @@ impl ReplicationSend for ConnectionManager {
         self.new_clients.clone()
     }
 
+    fn fastest_channel_send_interval(&self) -> Option<Duration> {
+        self.connections
+            .values()
+            .filter_map(|connection| connection.message_manager.fastest_send_interval_override())
+            .min()
+    }
+
     fn cleanup(&mut self, tick: Tick) {
         debug!("Running replication send cleanup");
         for connection in self.connections.values_mut() {
@@ -1177,3 +2418,293 @@ impl ReplicationSend for ConnectionManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::client::DisconnectReason;
+    use crate::server::events::ProtocolMismatchEvent;
+    use crate::shared::replication::plugin::send::SendIntervalTimer;
+    use crate::tests::protocol::StringMessage;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::prelude::{EventReader, Events, Mut, ResMut, Update};
+
+    /// A channel whose send interval is shorter than the replication send interval makes the
+    /// replication messages be buffered at the rate of that channel
+    #[test]
+    fn test_channel_send_interval_faster_than_replication() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<SendIntervalTimer<ConnectionManager>>()
+            .set_send_interval(Duration::from_secs(10));
+        let tick_duration = stepper.tick_duration;
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .set_channel_send_interval::<EntityUpdatesChannel>(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                tick_duration,
+            )
+            .unwrap();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<SendIntervalTimer<ConnectionManager>>()
+                .timer
+                .as_ref()
+                .map(|timer| timer.duration()),
+            Some(tick_duration)
+        );
+
+        // the entity is replicated without waiting for the replication send interval
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(crate::prelude::server::Replicate::default())
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .resource::<crate::prelude::client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .is_some());
+    }
+
+    /// Check that remapped entities are replicated with their old ids
+    #[test]
+    fn test_entity_remapping() {
+        let mut stepper = BevyStepper::default();
+        let old_entity = Entity::from_raw(1000);
+        let server_entity = stepper.server_app.world_mut().spawn_empty().id();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .set_entity_remapping([(old_entity, server_entity)]);
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(crate::prelude::server::Replicate::default());
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_map = &stepper
+            .client_app
+            .world()
+            .resource::<crate::prelude::client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map;
+        assert!(client_map.get_local(old_entity).is_some());
+        assert!(client_map.get_local(server_entity).is_none());
+    }
+
+    /// A malformed packet returns an error instead of panicking, and the connection keeps working
+    #[test]
+    fn test_malformed_packet() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        stepper.server_app.world_mut().resource_scope(
+            |world, mut manager: Mut<ConnectionManager>| {
+                let manager = &mut *manager;
+                let connection = manager.connections.get_mut(&client_id).unwrap();
+                assert!(connection
+                    .recv_packet(
+                        Bytes::from_static(&[255, 255, 255]),
+                        world.resource::<TickManager>(),
+                        world.resource::<ComponentRegistry>(),
+                        &mut manager.delta_manager,
+                    )
+                    .is_err());
+            },
+        );
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .connections
+            .contains_key(&client_id));
+    }
+
+    /// A client that uses a different protocol is disconnected
+    #[test]
+    fn test_protocol_mismatch() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let mut writer = Writer::default();
+        ProtocolHash(0).to_bytes(&mut writer).unwrap();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<crate::prelude::client::ConnectionManager>()
+            .message_manager
+            .buffer_send(writer.split(), ChannelKind::of::<ProtocolCheckChannel>())
+            .unwrap();
+        let mut mismatch = false;
+        for _ in 0..10 {
+            stepper.frame_step();
+            mismatch |= stepper
+                .server_app
+                .world()
+                .resource::<Events<ProtocolMismatchEvent>>()
+                .iter_current_update_events()
+                .any(|event| event.client_id == client_id);
+        }
+        assert!(mismatch);
+        assert!(!stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .connections
+            .contains_key(&client_id));
+    }
+
+    /// A client that lost its connection keeps its connection state during the grace period
+    #[test]
+    fn test_session_resumption() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>();
+        manager.session_grace_period = Duration::from_millis(50);
+
+        let connection = manager.connection_mut(client_id).unwrap();
+        connection.issue_session_token().unwrap();
+        let token = connection.session_token.unwrap();
+
+        // the connection is suspended instead of being dropped
+        manager.remove(client_id);
+        assert!(manager.is_suspended(client_id));
+        // the client can resume its session once it sends back its token,
+        // without receiving the entire world again
+        assert!(manager.resume(client_id));
+        assert!(manager.is_suspended(client_id));
+        let connection = manager.connection_mut(client_id).unwrap();
+        assert!(connection.check_session_token(token));
+        // a new token is issued for the next resumption
+        assert_ne!(connection.session_token, Some(token));
+        assert!(!manager.is_suspended(client_id));
+        assert!(manager.new_clients.is_empty());
+
+        // the connection is dropped once the grace period expires
+        manager.remove(client_id);
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let manager = stepper.server_app.world().resource::<ConnectionManager>();
+        assert!(!manager.connections.contains_key(&client_id));
+    }
+
+    /// A client that reconnects with an invalid session token is disconnected
+    #[test]
+    fn test_session_resumption_invalid_token() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>();
+        manager.session_grace_period = Duration::from_millis(50);
+        let connection = manager.connection_mut(client_id).unwrap();
+        connection.issue_session_token().unwrap();
+        let token = connection.session_token.unwrap();
+
+        manager.remove(client_id);
+        assert!(manager.resume(client_id));
+        let connection = manager.connection_mut(client_id).unwrap();
+        assert!(!connection.check_session_token(SessionToken(token.0.wrapping_add(1))));
+        // the session stays suspended until the client is disconnected, and cannot be resumed anymore
+        assert!(connection.pending_disconnect.is_some());
+        assert!(manager.is_suspended(client_id));
+        assert!(!manager.resume(client_id));
+    }
+
+    #[test]
+    fn test_interpolated_tick() {
+        let mut stepper = BevyStepper::default();
+        // the client reports its interpolation delay at the same frequency as the pings
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        let interpolated_tick = stepper
+            .server_app
+            .world_mut()
+            .resource_scope(|world, manager: Mut<ConnectionManager>| {
+                manager
+                    .interpolated_tick(
+                        ClientId::Netcode(TEST_CLIENT_ID),
+                        world.resource::<TickManager>(),
+                    )
+                    .unwrap()
+            })
+            .unwrap();
+        assert!(interpolated_tick < stepper.server_tick());
+        assert!((interpolated_tick - stepper.interpolation_tick()).abs() <= 2);
+    }
+
+    #[derive(Resource, Default)]
+    struct ReceivedKickReason(Option<String>);
+
+    fn store_kick_reason(
+        mut events: EventReader<crate::client::events::DisconnectEvent>,
+        mut received: ResMut<ReceivedKickReason>,
+    ) {
+        for event in events.read() {
+            if let Some(DisconnectReason::Kicked(reason)) = &event.reason {
+                received.0 = reason.get::<StringMessage>().map(|m| m.0.clone());
+            }
+        }
+    }
+
+    /// Check that the client receives the reason of the disconnection in its `DisconnectEvent`
+    #[test]
+    fn test_disconnect_with_reason() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .init_resource::<ReceivedKickReason>()
+            .add_systems(Update, store_kick_reason);
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .disconnect(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                StringMessage("kicked".to_string()),
+            )
+            .unwrap();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ReceivedKickReason>()
+                .0,
+            Some("kicked".to_string())
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .connection(ClientId::Netcode(TEST_CLIENT_ID))
+            .is_err());
+    }
+}
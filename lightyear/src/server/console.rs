@@ -0,0 +1,341 @@
+/*! Console commands for the remote administration of the server
+
+The server registers named commands with a handler system. A command can then be run:
+- by an authorized client, which sends a [`ConsoleCommandRequest`] on the [`AdminChannel`] with
+  [`ConnectionManager::send_console_command`](crate::client::connection::ConnectionManager::send_console_command)
+  and receives a [`ConsoleCommandResponse`] with the output of the command
+- from the standard input of a headless server, if [`ConsolePlugin::read_stdin`] is enabled
+
+Clients are not allowed to run any command until they are authorized with [`ConsoleCommands::authorize`]
+(for example after checking their credentials in the [`ConnectEvent`](crate::server::events::ConnectEvent) handler).
+
+## Example
+
+```rust,ignore
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use lightyear::prelude::server::*;
+
+/// Disconnect a client: `kick <client_id>`
+fn kick(
+    In(input): In<ConsoleCommandInput>,
+    mut connections: ResMut<ServerConnections>,
+) -> ConsoleCommandResult {
+    let client_id = ClientId::Netcode(input.arg(0)?);
+    connections.disconnect(client_id).map_err(|e| e.to_string())?;
+    Ok(format!("kicked {client_id}"))
+}
+
+fn setup(app: &mut App) {
+    app.register_console_command("kick", kick);
+}
+```
+*/
+use std::str::FromStr;
+
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use crossbeam_channel::Receiver;
+use tracing::{error, info, warn};
+
+use crate::channel::builder::AdminChannel;
+use crate::connection::id::ClientId;
+use crate::prelude::server::is_started;
+use crate::server::connection::ConnectionManager;
+use crate::server::events::{DisconnectEvent, MessageEvent};
+use crate::shared::console::{parse_command_line, ConsoleCommandRequest, ConsoleCommandResponse};
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+
+/// Output of a console command, or the reason why it failed
+pub type ConsoleCommandResult = Result<String, String>;
+
+/// Where a console command comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleSource {
+    /// The command was sent by a client
+    Client(ClientId),
+    /// The command was typed in the standard input of the server
+    Stdin,
+}
+
+/// Input that is passed to the handler of a console command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleCommandInput {
+    /// Who sent the command
+    pub source: ConsoleSource,
+    /// The name of the command
+    pub name: String,
+    /// The arguments that follow the name of the command
+    pub args: Vec<String>,
+}
+
+impl ConsoleCommandInput {
+    /// Parse the argument at `index`, with an error message suitable for the console output
+    pub fn arg<T: FromStr>(&self, index: usize) -> Result<T, String> {
+        let arg = self
+            .args
+            .get(index)
+            .ok_or_else(|| format!("{}: missing argument {}", self.name, index + 1))?;
+        arg.parse()
+            .map_err(|_| format!("{}: invalid argument {}: {arg}", self.name, index + 1))
+    }
+}
+
+/// Resource that holds the registered console commands, and the clients that are allowed to run them
+#[derive(Resource, Default, Debug)]
+pub struct ConsoleCommands {
+    handlers: HashMap<String, SystemId<ConsoleCommandInput, ConsoleCommandResult>>,
+    authorized: HashSet<ClientId>,
+}
+
+impl ConsoleCommands {
+    /// Allow a client to run console commands
+    ///
+    /// The authorization is removed when the client disconnects.
+    pub fn authorize(&mut self, client_id: ClientId) {
+        self.authorized.insert(client_id);
+    }
+
+    /// Prevent a client from running console commands
+    pub fn revoke(&mut self, client_id: ClientId) {
+        self.authorized.remove(&client_id);
+    }
+
+    /// Returns true if the source is allowed to run console commands
+    pub fn is_authorized(&self, source: ConsoleSource) -> bool {
+        match source {
+            ConsoleSource::Client(client_id) => self.authorized.contains(&client_id),
+            ConsoleSource::Stdin => true,
+        }
+    }
+
+    /// Iterate over the names of the registered commands
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// Parse the command line and run the handler of the command
+    fn run(&self, world: &mut World, source: ConsoleSource, line: &str) -> ConsoleCommandResult {
+        if !self.is_authorized(source) {
+            warn!(?source, "Unauthorized console command: {line}");
+            return Err("not authorized".to_string());
+        }
+        let mut args = parse_command_line(line)?;
+        if args.is_empty() {
+            return Err("empty command".to_string());
+        }
+        let name = args.remove(0);
+        let Some(handler) = self.handlers.get(&name).copied() else {
+            return Err(format!("unknown command: {name}"));
+        };
+        info!(?source, "Running console command: {line}");
+        let input = ConsoleCommandInput { source, name, args };
+        world
+            .run_system_with_input(handler, input)
+            .unwrap_or_else(|e| Err(format!("error running the command: {:?}", e)))
+    }
+}
+
+pub trait AppConsoleCommandExt {
+    /// Register a console command.
+    ///
+    /// The handler is a system that takes the [`ConsoleCommandInput`] as input (via [`In`]), and returns
+    /// the output of the command.
+    ///
+    /// Registering a new handler for the same command replaces the previous one.
+    fn register_console_command<Marker>(
+        &mut self,
+        name: impl Into<String>,
+        handler: impl IntoSystem<ConsoleCommandInput, ConsoleCommandResult, Marker> + 'static,
+    ) -> &mut Self;
+}
+
+impl AppConsoleCommandExt for App {
+    fn register_console_command<Marker>(
+        &mut self,
+        name: impl Into<String>,
+        handler: impl IntoSystem<ConsoleCommandInput, ConsoleCommandResult, Marker> + 'static,
+    ) -> &mut Self {
+        let system = self.world_mut().register_system(handler);
+        let previous = self
+            .world_mut()
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .handlers
+            .insert(name.into(), system);
+        if let Some(previous) = previous {
+            let _ = self.world_mut().remove_system(previous);
+        }
+        self
+    }
+}
+
+/// Plugin that runs the console commands sent by the clients, and optionally the ones typed in
+/// the standard input of the server
+#[derive(Default)]
+pub struct ConsolePlugin {
+    /// If true, the lines of the standard input are run as console commands.
+    /// This is useful for headless servers.
+    pub read_stdin: bool,
+}
+
+/// Lines read from the standard input by a background thread
+#[derive(Resource)]
+struct StdinLines(Receiver<String>);
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.init_resource::<ConsoleCommands>();
+        #[cfg(not(target_family = "wasm"))]
+        if self.read_stdin {
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            std::thread::spawn(move || {
+                for line in std::io::stdin().lines() {
+                    let Ok(line) = line else {
+                        return;
+                    };
+                    if sender.send(line).is_err() {
+                        return;
+                    }
+                }
+            });
+            app.insert_resource(StdinLines(receiver));
+        }
+        // SYSTEMS
+        app.add_systems(
+            PreUpdate,
+            (
+                handle_console_requests
+                    .after(InternalMainSet::<ServerMarker>::EmitEvents)
+                    .run_if(is_started),
+                handle_stdin_commands.run_if(resource_exists::<StdinLines>),
+            ),
+        );
+        app.observe(handle_client_disconnect);
+    }
+}
+
+/// Run the console commands sent by the clients, and send back the responses
+fn handle_console_requests(world: &mut World) {
+    let Some(mut events) = world.get_resource_mut::<Events<MessageEvent<ConsoleCommandRequest>>>()
+    else {
+        return;
+    };
+    if events.is_empty() {
+        return;
+    }
+    let requests: Vec<_> = events.drain().collect();
+    world.resource_scope(|world, commands: Mut<ConsoleCommands>| {
+        for event in requests {
+            let client_id = event.context;
+            let line = event.message.line;
+            let result = commands.run(world, ConsoleSource::Client(client_id), &line);
+            let _ = world
+                .resource_mut::<ConnectionManager>()
+                .send_message::<AdminChannel, _>(
+                    client_id,
+                    &mut ConsoleCommandResponse { line, result },
+                )
+                .inspect_err(|e| error!("Error sending the console response: {:?}", e));
+        }
+    });
+}
+
+/// Run the console commands typed in the standard input
+fn handle_stdin_commands(world: &mut World) {
+    let lines: Vec<_> = world.resource::<StdinLines>().0.try_iter().collect();
+    if lines.is_empty() {
+        return;
+    }
+    world.resource_scope(|world, commands: Mut<ConsoleCommands>| {
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match commands.run(world, ConsoleSource::Stdin, &line) {
+                Ok(output) => println!("{output}"),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+    });
+}
+
+/// Forget the authorization of clients that disconnected
+fn handle_client_disconnect(
+    trigger: Trigger<DisconnectEvent>,
+    mut commands: ResMut<ConsoleCommands>,
+) {
+    commands.revoke(trigger.event().client_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    fn add(In(input): In<ConsoleCommandInput>) -> ConsoleCommandResult {
+        Ok((input.arg::<i32>(0)? + input.arg::<i32>(1)?).to_string())
+    }
+
+    fn send_commands(stepper: &mut BevyStepper, lines: &[&str]) -> Vec<ConsoleCommandResponse> {
+        for line in lines {
+            stepper
+                .client_app
+                .world_mut()
+                .resource_mut::<client::ConnectionManager>()
+                .send_console_command(*line)
+                .unwrap();
+        }
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Events<client::MessageEvent<ConsoleCommandResponse>>>()
+            .drain()
+            .map(|event| event.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_console_commands() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.register_console_command("add", add);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        // the client is not authorized yet
+        assert_eq!(
+            send_commands(&mut stepper, &["add 1 2"]),
+            vec![ConsoleCommandResponse {
+                line: "add 1 2".to_string(),
+                result: Err("not authorized".to_string()),
+            }]
+        );
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConsoleCommands>()
+            .authorize(client_id);
+        assert_eq!(
+            send_commands(&mut stepper, &["add 1 2", "add 1", "kick 1"]),
+            vec![
+                ConsoleCommandResponse {
+                    line: "add 1 2".to_string(),
+                    result: Ok("3".to_string()),
+                },
+                ConsoleCommandResponse {
+                    line: "add 1".to_string(),
+                    result: Err("add: missing argument 2".to_string()),
+                },
+                ConsoleCommandResponse {
+                    line: "kick 1".to_string(),
+                    result: Err("unknown command: kick".to_string()),
+                },
+            ]
+        );
+    }
+}
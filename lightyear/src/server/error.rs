@@ -24,4 +24,13 @@ pub enum ServerError {
     RelevanceError(#[from] crate::server::relevance::error::RelevanceError),
     #[error(transparent)]
     ReplicationError(#[from] crate::shared::replication::error::ReplicationError),
+    #[error("the client uses a different protocol (client: {client:?}, server: {server:?})")]
+    ProtocolMismatch {
+        client: crate::protocol::version::ProtocolHash,
+        server: crate::protocol::version::ProtocolHash,
+    },
+    #[error("the client sent an invalid session token")]
+    InvalidSessionToken,
+    #[error("the message priority {0} must be a finite, non-negative number")]
+    InvalidPriority(f32),
 }
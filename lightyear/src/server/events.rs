@@ -5,10 +5,14 @@ use bevy::utils::HashMap;
 
 use crate::connection::id::ClientId;
 use crate::prelude::ComponentRegistry;
+use crate::protocol::registry::NetId;
+use crate::protocol::version::ProtocolHash;
+use crate::server::budget::ReplicationBudgetExceededEvent;
 use crate::server::connection::ConnectionManager;
+use crate::shared::events::components::UnknownTypeKind;
 use crate::shared::events::connection::{
     ConnectionEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
-    IterEntityDespawnEvent, IterEntitySpawnEvent,
+    IterEntityDespawnEvent, IterEntitySpawnEvent, IterUnknownTypeEvent,
 };
 use crate::shared::events::plugin::EventsPlugin;
 use crate::shared::events::systems::push_component_events;
@@ -26,6 +30,9 @@ impl Plugin for ServerEventsPlugin {
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<ReplicationBudgetExceededEvent>()
+            .add_event::<NetworkErrorEvent>()
+            .add_event::<ProtocolMismatchEvent>()
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default())
             // SYSTEMS
@@ -204,6 +211,19 @@ impl IterEntityDespawnEvent<ClientId> for ServerEvents {
     }
 }
 
+impl IterUnknownTypeEvent<ClientId> for ServerEvents {
+    fn drain_unknown_type(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (UnknownTypeKind, NetId, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            let client_id = *client_id;
+            events
+                .drain_unknown_type()
+                .map(move |(kind, net_id, _)| (kind, net_id, client_id))
+        }))
+    }
+}
+
 impl IterComponentUpdateEvent<ClientId> for ServerEvents {
     fn iter_component_update<'a, 'b: 'a, C: Component>(
         &'a mut self,
@@ -263,6 +283,31 @@ pub struct DisconnectEvent {
     pub entity: Entity,
 }
 
+/// Bevy [`Event`] emitted on the server when an error happens while receiving or sending packets.
+///
+/// The errors are isolated per connection: an error with one client does not prevent
+/// the server from handling the other clients.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct NetworkErrorEvent {
+    /// The client whose connection caused the error, if the error is specific to a client
+    pub client_id: Option<ClientId>,
+    /// Description of the error
+    pub error: String,
+}
+
+/// Bevy [`Event`] emitted on the server when a client uses a different protocol than the server.
+///
+/// The client is disconnected, unless [`SharedConfig::skip_unknown_types`](crate::prelude::SharedConfig::skip_unknown_types)
+/// is enabled.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ProtocolMismatchEvent {
+    pub client_id: ClientId,
+    /// Hash of the protocol of the client
+    pub client: ProtocolHash,
+    /// Hash of the protocol of the server
+    pub server: ProtocolHash,
+}
+
 /// Bevy [`Event`] emitted on the server on the frame where an input message from a client is received
 pub type InputEvent<I> = crate::shared::events::components::InputEvent<I, ClientId>;
 /// Bevy [`Event`] emitted on the server on the frame where a EntitySpawn replication message is received
@@ -281,6 +326,8 @@ pub type ComponentRemoveEvent<C> =
 
 /// Bevy [`Event`] emitted on the server on the frame where a (non-replication) message is received
 pub type MessageEvent<M> = crate::shared::events::components::MessageEvent<M, ClientId>;
+/// Bevy [`Event`] emitted on the server on the frame where a message or component of an unknown type is skipped
+pub type UnknownTypeEvent = crate::shared::events::components::UnknownTypeEvent<ClientId>;
 
 #[cfg(test)]
 mod tests {
@@ -7,8 +7,10 @@ use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
 use crate::inputs::leafwing::LeafwingUserAction;
-use crate::prelude::server::MessageEvent;
-use crate::prelude::{server::is_started, InputMessage, MessageRegistry, Mode, TickManager};
+use crate::prelude::server::{ControlledBy, MessageEvent};
+use crate::prelude::{
+    server::is_started, ClientId, InputMessage, MessageRegistry, Mode, Tick, TickManager,
+};
 use crate::protocol::message::MessageKind;
 use crate::serialize::reader::Reader;
 use crate::server::config::ServerConfig;
@@ -28,6 +30,43 @@ impl<A> Default for LeafwingInputPlugin<A> {
     }
 }
 
+/// Resource to validate the [`ActionState`]s received from the clients before they are used by the server.
+///
+/// The callback is called once per tick for each entity controlled by the client, when the inputs are received
+/// and before they are buffered. It can modify the [`ActionState`], or return `false` to reject it: the
+/// [`ActionState`] of the previous tick is then used for this tick, and an [`InvalidInputEvent`] is emitted.
+///
+/// ```rust,ignore
+/// app.insert_resource(InputValidator::<PlayerActions>::new(|client_id, tick, action_state| {
+///     !action_state.pressed(&PlayerActions::Teleport)
+/// }));
+/// ```
+#[derive(Resource)]
+pub struct InputValidator<A: LeafwingUserAction> {
+    validate: Box<dyn FnMut(ClientId, Tick, &mut ActionState<A>) -> bool + Send + Sync>,
+}
+
+impl<A: LeafwingUserAction> InputValidator<A> {
+    pub fn new(
+        validate: impl FnMut(ClientId, Tick, &mut ActionState<A>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            validate: Box::new(validate),
+        }
+    }
+}
+
+/// Event emitted when an [`ActionState`] received from a client was rejected by the [`InputValidator`]
+#[derive(Event, Debug, Clone)]
+pub struct InvalidInputEvent<A: LeafwingUserAction> {
+    pub client_id: ClientId,
+    /// Entity whose [`ActionState`] was rejected
+    pub entity: Entity,
+    /// Tick for which the input was sent
+    pub tick: Tick,
+    pub action_state: ActionState<A>,
+}
+
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum InputSystemSet {
     /// Add the ActionDiffBuffers to new entities that have an [`ActionState`]
@@ -43,6 +82,8 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
         // RESOURCES
         // app.init_resource::<GlobalActions<A>>();
         // TODO: (global action states) add a resource tracking the action-state of all clients
+        // EVENTS
+        app.add_event::<InvalidInputEvent<A>>();
         // SETS
         app.configure_sets(
             PreUpdate,
@@ -95,9 +136,11 @@ fn receive_input_message<A: LeafwingUserAction>(
     message_registry: Res<MessageRegistry>,
     mut connection_manager: ResMut<ConnectionManager>,
     // TODO: currently we do not handle entities that are controlled by multiple clients
-    mut query: Query<Option<&mut InputBuffer<A>>>,
+    mut query: Query<(Option<&mut InputBuffer<A>>, Option<&ControlledBy>)>,
     mut commands: Commands,
     mut events: EventWriter<MessageEvent<InputMessage<A>>>,
+    mut validator: Option<ResMut<InputValidator<A>>>,
+    mut invalid_input_events: EventWriter<InvalidInputEvent<A>>,
 ) {
     let kind = MessageKind::of::<InputMessage<A>>();
     let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
@@ -134,7 +177,16 @@ fn receive_input_message<A: LeafwingUserAction>(
                                     // TODO Don't update input buffer if inputs arrived too late?
                                     debug!("received input for entity: {:?}", entity);
 
-                                    if let Ok(buffer) = query.get_mut(*entity) {
+                                    if let Ok((buffer, controlled_by)) = query.get_mut(*entity) {
+                                        // ignore the inputs of clients that don't control the entity (for example
+                                        // after the ownership was transferred to another client)
+                                        if controlled_by.is_some_and(|controlled_by| {
+                                            !controlled_by.target.is_empty()
+                                                && !controlled_by.targets(client_id)
+                                        }) {
+                                            debug!(?client_id, ?entity, "ignoring input from a client that does not control the entity");
+                                            continue;
+                                        }
                                         if let Some(mut buffer) = buffer {
                                             debug!(
                                                 ?target,
@@ -142,11 +194,39 @@ fn receive_input_message<A: LeafwingUserAction>(
                                                 buffer.as_ref(),
                                                 message
                                             );
-                                            buffer.update_from_message(
-                                                message.end_tick,
-                                                start,
-                                                diffs,
-                                            );
+                                            match validator.as_mut() {
+                                                Some(validator) => buffer
+                                                    .update_from_message_with_validation(
+                                                        message.end_tick,
+                                                        start,
+                                                        diffs,
+                                                        |tick, action_state| {
+                                                            let valid = (validator.validate)(
+                                                                *client_id,
+                                                                tick,
+                                                                action_state,
+                                                            );
+                                                            if !valid {
+                                                                debug!(?client_id, ?tick, ?entity, "Rejected invalid client ActionState");
+                                                                invalid_input_events.send(
+                                                                    InvalidInputEvent {
+                                                                        client_id: *client_id,
+                                                                        entity: *entity,
+                                                                        tick,
+                                                                        action_state: action_state
+                                                                            .clone(),
+                                                                    },
+                                                                );
+                                                            }
+                                                            valid
+                                                        },
+                                                    ),
+                                                None => buffer.update_from_message(
+                                                    message.end_tick,
+                                                    start,
+                                                    diffs,
+                                                ),
+                                            }
                                         } else {
                                             debug!("Adding InputBuffer and ActionState which are missing on the entity");
                                             commands.entity(*entity).insert((
@@ -237,7 +317,7 @@ mod tests {
     use crate::prelude::client;
     use crate::prelude::server::*;
     use crate::tests::protocol::*;
-    use crate::tests::stepper::BevyStepper;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
 
     #[test]
     fn test_leafwing_inputs() {
@@ -341,4 +421,72 @@ mod tests {
             .unwrap()
             .released(&LeafwingInput1::Jump));
     }
+
+    #[test]
+    fn test_input_validator() {
+        let mut stepper = BevyStepper::default();
+        // reject the ActionStates where Jump is pressed
+        stepper
+            .server_app
+            .insert_resource(InputValidator::<LeafwingInput1>::new(
+                |_, _, action_state| !action_state.pressed(&LeafwingInput1::Jump),
+            ));
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                ActionState::<LeafwingInput1>::default(),
+                Replicate::default(),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(InputMap::<LeafwingInput1>::new([(
+                LeafwingInput1::Jump,
+                KeyCode::KeyA,
+            )]));
+        stepper.frame_step();
+
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyA);
+        stepper.frame_step();
+        let client_tick = stepper.client_tick();
+
+        // the ActionState was rejected before being buffered
+        assert!(stepper
+            .server_app
+            .world()
+            .entity(server_entity)
+            .get::<InputBuffer<LeafwingInput1>>()
+            .unwrap()
+            .get(client_tick)
+            .map_or(true, |action_state| !action_state
+                .pressed(&LeafwingInput1::Jump)));
+        let invalid: Vec<_> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<InvalidInputEvent<LeafwingInput1>>>()
+            .drain()
+            .collect();
+        assert!(invalid
+            .iter()
+            .any(|event| event.client_id == ClientId::Netcode(TEST_CLIENT_ID)
+                && event.entity == server_entity
+                && event.tick == client_tick));
+    }
 }
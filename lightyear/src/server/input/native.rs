@@ -5,7 +5,9 @@ use bevy::utils::HashMap;
 use crate::inputs::native::input_buffer::InputBuffer;
 use crate::inputs::native::InputMessage;
 use crate::prelude::server::DisconnectEvent;
-use crate::prelude::{server::is_started, ClientId, MessageRegistry, TickManager, UserAction};
+use crate::prelude::{
+    server::is_started, ClientId, MessageRegistry, Tick, TickManager, UserAction,
+};
 use crate::protocol::message::MessageKind;
 use crate::serialize::reader::Reader;
 use crate::server::connection::ConnectionManager;
@@ -19,19 +21,135 @@ pub struct InputPlugin<A> {
 
 #[derive(Resource, Debug)]
 pub struct InputBuffers<A> {
-    /// The first element stores the last input we have received from the client.
+    /// The first element stores the last input we have received from the client, and its tick.
     /// In case we are missing the client input for a tick, we will fallback to using this.
-    buffers: HashMap<ClientId, (Option<A>, InputBuffer<A>)>,
+    buffers: HashMap<ClientId, (Option<(Tick, A)>, InputBuffer<A>)>,
+    /// Number of input messages that arrived too late for each client, i.e. the most recent input
+    /// contained in the message was for a tick that the server had already processed.
+    late_messages: HashMap<ClientId, u32>,
 }
 
 impl<A> Default for InputBuffers<A> {
     fn default() -> Self {
         Self {
             buffers: HashMap::default(),
+            late_messages: HashMap::default(),
         }
     }
 }
 
+impl<A> InputBuffers<A> {
+    /// Number of input messages from this client that arrived too late to be used by the server.
+    ///
+    /// If this keeps increasing, the client should send its inputs with more redundancy
+    /// (see [`InputConfig`](crate::prelude::client::InputConfig)).
+    pub fn late_input_messages(&self, client_id: ClientId) -> u32 {
+        self.late_messages
+            .get(&client_id)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Resource that specifies which input the server uses for the ticks where the input
+/// of a client is missing (for example because the client runs at a lower frame rate than the
+/// server's tick rate, or because the input message was lost).
+///
+/// It can be configured separately for each input type.
+///
+/// ```rust,ignore
+/// app.insert_resource(MissingInputPolicy::<PlayerInput>::Interpolate(|start, end, t| PlayerInput {
+///     direction: start.direction.lerp(end.direction, t),
+///     ..end.clone()
+/// }));
+/// ```
+#[derive(Resource, Default)]
+pub enum MissingInputPolicy<A> {
+    /// Use the last input received from the client
+    #[default]
+    HoldLast,
+    /// Use no input: the [`InputEvent`] will contain `None`
+    Zero,
+    /// Interpolate between the last input received from the client and the next input
+    /// that is already buffered. Useful for analog inputs (joystick axes, mouse aim, etc.)
+    ///
+    /// The function receives the start input, the end input and the interpolation ratio between 0.0 and 1.0.
+    /// If no future input is buffered, the last input is used.
+    Interpolate(fn(&A, &A, f32) -> A),
+}
+
+impl<A: UserAction> MissingInputPolicy<A> {
+    /// Compute the input to use for a tick where the client input is missing
+    fn fill(
+        &self,
+        tick: Tick,
+        last_input: Option<&(Tick, A)>,
+        input_buffer: &InputBuffer<A>,
+    ) -> Option<A> {
+        match self {
+            MissingInputPolicy::HoldLast => last_input.map(|(_, input)| input.clone()),
+            MissingInputPolicy::Zero => None,
+            MissingInputPolicy::Interpolate(interpolate) => {
+                let (start_tick, start) = last_input?;
+                // the buffer starts right after the current tick
+                let next = input_buffer.start_tick.and_then(|buffer_start| {
+                    input_buffer
+                        .buffer
+                        .iter()
+                        .enumerate()
+                        .find_map(|(i, input)| {
+                            input.as_ref().map(|input| (buffer_start + i as i16, input))
+                        })
+                });
+                match next {
+                    Some((end_tick, end)) if end_tick > tick && tick > *start_tick => {
+                        let ratio = (tick - *start_tick) as f32 / (end_tick - *start_tick) as f32;
+                        Some(interpolate(start, end, ratio))
+                    }
+                    _ => Some(start.clone()),
+                }
+            }
+        }
+    }
+}
+
+/// Resource to validate the inputs received from the clients before they are used by the server.
+///
+/// The callback is called once for each client input, when it is received and before it is buffered.
+/// It can modify the input (for example to clamp a movement speed), or return `false` to reject it
+/// (for example if the client fires twice within the weapon cooldown). A rejected input is handled like a
+/// missing input (see [`MissingInputPolicy`]), and an [`InvalidInputEvent`] is emitted.
+///
+/// ```rust,ignore
+/// app.insert_resource(InputValidator::<PlayerInput>::new(|client_id, tick, input| {
+///     input.speed = input.speed.min(MAX_SPEED);
+///     true
+/// }));
+/// ```
+#[derive(Resource)]
+pub struct InputValidator<A> {
+    validate: Box<dyn FnMut(ClientId, Tick, &mut A) -> bool + Send + Sync>,
+}
+
+impl<A> InputValidator<A> {
+    pub fn new(
+        validate: impl FnMut(ClientId, Tick, &mut A) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            validate: Box::new(validate),
+        }
+    }
+}
+
+/// Event emitted when an input received from a client was rejected by the [`InputValidator`]
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct InvalidInputEvent<A> {
+    pub client_id: ClientId,
+    /// Tick for which the input was sent
+    pub tick: Tick,
+    pub input: A,
+}
+
 impl<A> Default for InputPlugin<A> {
     fn default() -> Self {
         Self {
@@ -54,8 +172,10 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
     fn build(&self, app: &mut App) {
         // RESOURCES
         app.init_resource::<InputBuffers<A>>();
+        app.init_resource::<MissingInputPolicy<A>>();
         // EVENTS
         app.add_event::<InputEvent<A>>();
+        app.add_event::<InvalidInputEvent<A>>();
         // SETS
         app.configure_sets(
             PreUpdate,
@@ -94,14 +214,21 @@ fn handle_client_disconnect<A: UserAction>(
     mut input_buffers: ResMut<InputBuffers<A>>,
 ) {
     input_buffers.buffers.remove(&trigger.event().client_id);
+    input_buffers
+        .late_messages
+        .remove(&trigger.event().client_id);
 }
 
 /// Read the message received from the client and emit the MessageEvent event
 fn receive_input_message<A: UserAction>(
     message_registry: Res<MessageRegistry>,
+    tick_manager: Res<TickManager>,
     mut connection_manager: ResMut<ConnectionManager>,
     mut input_buffers: ResMut<InputBuffers<A>>,
+    mut validator: Option<ResMut<InputValidator<A>>>,
+    mut invalid_input_events: EventWriter<InvalidInputEvent<A>>,
 ) {
+    let tick = tick_manager.tick();
     let kind = MessageKind::of::<InputMessage<A>>();
     let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
         error!(
@@ -123,12 +250,27 @@ fn receive_input_message<A: UserAction>(
                 ) {
                     Ok(message) => {
                         debug!("Received input message: {:?}", message);
-                        input_buffers
-                            .buffers
-                            .entry(*client_id)
-                            .or_default()
-                            .1
-                            .update_from_message(message);
+                        if message.end_tick < tick {
+                            // all the inputs in the message are for ticks that were already processed
+                            debug!(
+                                ?client_id,
+                                ?tick,
+                                end_tick = ?message.end_tick,
+                                "Received late input message"
+                            );
+                            *input_buffers.late_messages.entry(*client_id).or_default() += 1;
+                            #[cfg(feature = "metrics")]
+                            {
+                                metrics::counter!("inputs.late_messages").increment(1);
+                            }
+                        }
+                        buffer_input_message(
+                            *client_id,
+                            message,
+                            &mut input_buffers.buffers.entry(*client_id).or_default().1,
+                            validator.as_deref_mut(),
+                            &mut invalid_input_events,
+                        );
                         if target != NetworkTarget::None {
                             // NOTE: we can re-send the same bytes directly because InputMessage does not include any Entity references
                             connection.messages_to_rebroadcast.push((
@@ -148,11 +290,38 @@ fn receive_input_message<A: UserAction>(
     }
 }
 
+/// Write the inputs of the message in the client's buffer, after validating them with the [`InputValidator`]
+fn buffer_input_message<A: UserAction>(
+    client_id: ClientId,
+    message: InputMessage<A>,
+    input_buffer: &mut InputBuffer<A>,
+    validator: Option<&mut InputValidator<A>>,
+    invalid_input_events: &mut EventWriter<InvalidInputEvent<A>>,
+) {
+    let Some(validator) = validator else {
+        input_buffer.update_from_message(message);
+        return;
+    };
+    input_buffer.update_from_message_with_validation(message, |tick, input| {
+        let valid = (validator.validate)(client_id, tick, input);
+        if !valid {
+            debug!(?client_id, ?tick, ?input, "Rejected invalid client input");
+            invalid_input_events.send(InvalidInputEvent {
+                client_id,
+                tick,
+                input: input.clone(),
+            });
+        }
+        valid
+    });
+}
+
 // Create a system that reads from the input buffer and returns the inputs of all clients for the current tick.
 // The only tricky part is that events are cleared every frame, but we want to clear every tick instead
 // Do it in this system because we want an input for every tick
 fn write_input_event<A: UserAction>(
     tick_manager: Res<TickManager>,
+    policy: Res<MissingInputPolicy<A>>,
     mut input_buffers: ResMut<InputBuffers<A>>,
     mut input_events: EventWriter<InputEvent<A>>,
 ) {
@@ -168,9 +337,9 @@ fn write_input_event<A: UserAction>(
             // NOTE: if there is no input for this tick, we should use the last input that we have
             //  as a best-effort fallback.
             let input = match received_input {
-                None => last_input.clone(),
+                None => policy.fill(tick, last_input.as_ref(), input_buffer),
                 Some(i) => {
-                    *last_input = Some(i.clone());
+                    *last_input = Some((tick, i.clone()));
                     Some(i)
                 }
             };
@@ -195,3 +364,105 @@ fn write_input_event<A: UserAction>(
 fn clear_input_events<A: UserAction>(mut input_events: EventReader<InputEvent<A>>) {
     input_events.clear();
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::inputs::native::input_buffer::InputData;
+
+    use super::*;
+
+    fn buffer_with_input(tick: Tick, input: f32) -> InputBuffer<f32> {
+        let mut buffer = InputBuffer::default();
+        buffer.set(tick, Some(input));
+        buffer
+    }
+
+    #[test]
+    fn test_missing_input_policy() {
+        let last_input = (Tick(10), 0.0);
+        // the next input received from the client is for tick 14
+        let buffer = buffer_with_input(Tick(14), 4.0);
+
+        let policy = MissingInputPolicy::<f32>::HoldLast;
+        assert_eq!(policy.fill(Tick(11), Some(&last_input), &buffer), Some(0.0));
+
+        let policy = MissingInputPolicy::<f32>::Zero;
+        assert_eq!(policy.fill(Tick(11), Some(&last_input), &buffer), None);
+
+        let policy =
+            MissingInputPolicy::<f32>::Interpolate(|start, end, t| start + (end - start) * t);
+        assert_eq!(policy.fill(Tick(11), Some(&last_input), &buffer), Some(1.0));
+        assert_eq!(policy.fill(Tick(13), Some(&last_input), &buffer), Some(3.0));
+        // no future input: hold the last input
+        assert_eq!(
+            policy.fill(Tick(11), Some(&last_input), &InputBuffer::default()),
+            Some(0.0)
+        );
+        assert_eq!(policy.fill(Tick(11), None, &buffer), None);
+    }
+
+    #[test]
+    fn test_input_validator() {
+        let client_id = ClientId::Netcode(0);
+        let mut app = App::new();
+        app.add_event::<InvalidInputEvent<f32>>();
+        // clamp the inputs to 5.0, and reject negative inputs
+        let mut validator = InputValidator::<f32>::new(|_, _, input| {
+            *input = input.min(5.0);
+            *input >= 0.0
+        });
+        let input_buffer = app.world_mut().run_system_once(
+            move |mut invalid_input_events: EventWriter<InvalidInputEvent<f32>>| {
+                let mut input_buffer = InputBuffer::default();
+                let message = InputMessage {
+                    end_tick: Tick(1),
+                    inputs: vec![InputData::Input(10.0), InputData::Input(-1.0)],
+                };
+                buffer_input_message(
+                    client_id,
+                    message,
+                    &mut input_buffer,
+                    Some(&mut validator),
+                    &mut invalid_input_events,
+                );
+                // the inputs are sent again for redundancy: they are only validated once
+                let message = InputMessage {
+                    end_tick: Tick(2),
+                    inputs: vec![
+                        InputData::Input(10.0),
+                        InputData::Input(-1.0),
+                        InputData::Input(1.0),
+                    ],
+                };
+                buffer_input_message(
+                    client_id,
+                    message,
+                    &mut input_buffer,
+                    Some(&mut validator),
+                    &mut invalid_input_events,
+                );
+                input_buffer
+            },
+        );
+
+        // the first input is clamped, the second one is rejected
+        assert_eq!(input_buffer.get(Tick(0)), Some(&5.0));
+        assert_eq!(input_buffer.get(Tick(1)), None);
+        assert_eq!(input_buffer.get(Tick(2)), Some(&1.0));
+        let invalid: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<InvalidInputEvent<f32>>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![InvalidInputEvent {
+                client_id,
+                tick: Tick(1),
+                input: -1.0,
+            }]
+        );
+    }
+}
@@ -11,9 +11,11 @@ use crate::transport::middleware::compression::zstd::compression::ZstdCompressor
 use crate::transport::middleware::compression::zstd::decompression::ZstdDecompressor;
 use crate::transport::middleware::conditioner::LinkConditioner;
 use crate::transport::middleware::PacketReceiverWrapper;
+#[cfg(not(target_family = "wasm"))]
+use crate::transport::tcp::TcpServerSocketBuilder;
 use crate::transport::udp::UdpSocketBuilder;
 #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
-use crate::transport::websocket::server::WebSocketServerSocketBuilder;
+use crate::transport::websocket::server::{WebSocketIdentity, WebSocketServerSocketBuilder};
 #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
 use crate::transport::webtransport::server::WebTransportServerSocketBuilder;
 use crate::transport::BoxedReceiver;
@@ -36,7 +38,18 @@ pub enum ServerTransport {
     },
     /// Use [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket) as a transport
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
-    WebSocketServer { server_addr: SocketAddr },
+    WebSocketServer {
+        server_addr: SocketAddr,
+        /// Certificate that will be used to accept TLS (`wss://`) connections.
+        /// If `None`, the server only accepts plain (`ws://`) connections.
+        certificate: Option<WebSocketIdentity>,
+    },
+    /// Accept TCP connections, for the clients that cannot use UDP.
+    ///
+    /// Each packet is prefixed by its length. This can be added as an additional [`NetConfig`](crate::connection::server::NetConfig)
+    /// next to the UDP one, so that clients on networks that block UDP can still connect.
+    #[cfg(not(target_family = "wasm"))]
+    TcpServer { server_addr: SocketAddr },
     /// Use a crossbeam_channel as a transport. This is useful for testing.
     /// This is server-only: each tuple corresponds to a different client.
     Channels {
@@ -50,7 +63,7 @@ pub enum ServerTransport {
     Dummy,
 }
 
-/// We provide a manual implementation because wtranport's `Identity` does not implement Clone
+/// We provide a manual implementation because wtranport's `Identity` and `WebSocketIdentity` do not implement Clone
 impl Clone for ServerTransport {
     #[inline]
     fn clone(&self) -> ServerTransport {
@@ -69,8 +82,16 @@ impl Clone for ServerTransport {
             #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
             ServerTransport::WebSocketServer {
                 server_addr: __self_0,
+                certificate: __self_1,
             } => ServerTransport::WebSocketServer {
                 server_addr: Clone::clone(__self_0),
+                certificate: __self_1.as_ref().map(WebSocketIdentity::clone_identity),
+            },
+            #[cfg(not(target_family = "wasm"))]
+            ServerTransport::TcpServer {
+                server_addr: __self_0,
+            } => ServerTransport::TcpServer {
+                server_addr: Clone::clone(__self_0),
             },
             ServerTransport::Channels { channels: __self_0 } => ServerTransport::Channels {
                 channels: Clone::clone(__self_0),
@@ -95,10 +116,16 @@ impl ServerTransport {
                 certificate,
             }),
             #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
-            ServerTransport::WebSocketServer { server_addr } => {
-                ServerTransportBuilderEnum::WebSocketServer(WebSocketServerSocketBuilder {
-                    server_addr,
-                })
+            ServerTransport::WebSocketServer {
+                server_addr,
+                certificate,
+            } => ServerTransportBuilderEnum::WebSocketServer(WebSocketServerSocketBuilder {
+                server_addr,
+                identity: certificate,
+            }),
+            #[cfg(not(target_family = "wasm"))]
+            ServerTransport::TcpServer { server_addr } => {
+                ServerTransportBuilderEnum::TcpServer(TcpServerSocketBuilder { server_addr })
             }
             ServerTransport::Channels { channels } => {
                 ServerTransportBuilderEnum::Channels(Channels::new(channels))
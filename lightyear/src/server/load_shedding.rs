@@ -0,0 +1,370 @@
+//! Degrade the networking gracefully when the server cannot keep up with its tick rate.
+//!
+//! When a frame of the server takes longer than the tick budget for [`LoadSheddingPlugin::overrun_ticks`]
+//! consecutive frames, the server enters the load-shedding mode:
+//! - replication messages are buffered [`LoadSheddingPlugin::replication_interval_multiplier`] times less often
+//! - the channels whose priority is lower than [`LoadSheddingPlugin::low_priority_threshold`] are paused; they only
+//!   keep the most recent [`LoadSheddingPlugin::paused_channel_max_bytes`] of messages buffered
+//! - [`LoadShedding::interest_scale`] returns [`LoadSheddingPlugin::interest_scale`], so that the gameplay code
+//!   can shrink the radius it uses to compute the network relevance of the entities
+//! - a [`LoadSheddingEvent::Activated`] event is emitted, so that the gameplay can degrade as well
+//!   (e.g. spawn fewer projectiles)
+//!
+//! Everything is restored to its previous state (and a [`LoadSheddingEvent::Deactivated`] event is emitted) once the
+//! frames fit in the budget again for [`LoadSheddingPlugin::recovery_ticks`] consecutive frames.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//!
+//! app.add_plugins(LoadSheddingPlugin::default());
+//!
+//! fn spawn_projectiles(load_shedding: Res<LoadShedding>) {
+//!     let max_projectiles = if load_shedding.is_active() { 10 } else { 100 };
+//! }
+//! ```
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::connection::id::ClientId;
+use crate::prelude::{ChannelKind, ChannelRegistry, TickManager};
+use crate::server::connection::ConnectionManager;
+use crate::server::events::ConnectEvent;
+use crate::shared::replication::plugin::send::SendIntervalTimer;
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+
+/// Plugin that enables the load-shedding mode of the server
+#[derive(Clone, Debug)]
+pub struct LoadSheddingPlugin {
+    /// A frame overruns if it takes longer than this budget. Uses the tick duration if `None`
+    pub tick_budget: Option<Duration>,
+    /// Number of consecutive frames that must overrun the budget to enter the load-shedding mode
+    pub overrun_ticks: u32,
+    /// Number of consecutive frames that must fit in the budget to leave the load-shedding mode
+    pub recovery_ticks: u32,
+    /// How many times less often the replication messages are buffered
+    pub replication_interval_multiplier: u32,
+    /// Factor returned by [`LoadShedding::interest_scale`] while the mode is active
+    pub interest_scale: f32,
+    /// Channels with a priority lower than this are paused while the mode is active
+    pub low_priority_threshold: f32,
+    /// Maximum number of bytes that a paused channel keeps buffered for each client; the oldest messages are
+    /// dropped first (reliable channels never drop messages)
+    pub paused_channel_max_bytes: usize,
+}
+
+impl Default for LoadSheddingPlugin {
+    fn default() -> Self {
+        Self {
+            tick_budget: None,
+            overrun_ticks: 10,
+            recovery_ticks: 60,
+            replication_interval_multiplier: 2,
+            interest_scale: 0.5,
+            low_priority_threshold: 1.0,
+            paused_channel_max_bytes: 16 * 1024,
+        }
+    }
+}
+
+/// Event emitted when the server enters or leaves the load-shedding mode
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadSheddingEvent {
+    Activated,
+    Deactivated,
+}
+
+/// State of the load-shedding mode
+#[derive(Resource, Debug)]
+pub struct LoadShedding {
+    config: LoadSheddingPlugin,
+    active: bool,
+    /// Number of consecutive frames that overran the budget (or fit in the budget, if the mode is active)
+    consecutive_ticks: u32,
+    frame_start: Option<Instant>,
+    last_frame_duration: Duration,
+    /// Replication send interval before the mode was activated, restored when the mode is deactivated
+    replication_send_interval: Option<Duration>,
+}
+
+impl LoadShedding {
+    /// Returns true if the server is in the load-shedding mode
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Factor by which the interest radii should be multiplied (1.0 if the mode is not active)
+    pub fn interest_scale(&self) -> f32 {
+        if self.active {
+            self.config.interest_scale
+        } else {
+            1.0
+        }
+    }
+
+    /// Time spent in the latest frame
+    pub fn last_frame_duration(&self) -> Duration {
+        self.last_frame_duration
+    }
+
+    /// Record the duration of a frame, and return the event to emit if the mode changed
+    fn update(&mut self, frame_duration: Duration, budget: Duration) -> Option<LoadSheddingEvent> {
+        self.last_frame_duration = frame_duration;
+        let overrun = frame_duration > budget;
+        // count the frames that go against the current mode
+        if overrun != self.active {
+            self.consecutive_ticks += 1;
+        } else {
+            self.consecutive_ticks = 0;
+        }
+        let threshold = if self.active {
+            self.config.recovery_ticks
+        } else {
+            self.config.overrun_ticks
+        };
+        if self.consecutive_ticks < threshold.max(1) {
+            return None;
+        }
+        self.consecutive_ticks = 0;
+        self.active = !self.active;
+        Some(if self.active {
+            LoadSheddingEvent::Activated
+        } else {
+            LoadSheddingEvent::Deactivated
+        })
+    }
+}
+
+impl Plugin for LoadSheddingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LoadShedding {
+            config: self.clone(),
+            active: false,
+            consecutive_ticks: 0,
+            frame_start: None,
+            last_frame_duration: Duration::default(),
+            replication_send_interval: None,
+        });
+        app.add_event::<LoadSheddingEvent>();
+        app.add_systems(First, start_frame);
+        app.add_systems(Last, end_frame);
+        app.add_systems(
+            PreUpdate,
+            pause_channels_of_new_clients
+                .after(InternalMainSet::<ServerMarker>::EmitEvents)
+                .run_if(|load_shedding: Res<LoadShedding>| load_shedding.is_active()),
+        );
+    }
+}
+
+fn start_frame(mut load_shedding: ResMut<LoadShedding>) {
+    load_shedding.frame_start = Some(Instant::now());
+}
+
+fn end_frame(
+    mut load_shedding: ResMut<LoadShedding>,
+    tick_manager: Res<TickManager>,
+    channel_registry: Res<ChannelRegistry>,
+    connection_manager: Option<ResMut<ConnectionManager>>,
+    send_timer: Option<ResMut<SendIntervalTimer<ConnectionManager>>>,
+    mut events: EventWriter<LoadSheddingEvent>,
+) {
+    let Some(frame_start) = load_shedding.frame_start.take() else {
+        return;
+    };
+    let budget = load_shedding
+        .config
+        .tick_budget
+        .unwrap_or(tick_manager.config.tick_duration);
+    let Some(event) = load_shedding.update(frame_start.elapsed(), budget) else {
+        return;
+    };
+    let active = load_shedding.is_active();
+    match event {
+        LoadSheddingEvent::Activated => warn!(
+            frame_duration = ?load_shedding.last_frame_duration,
+            ?budget,
+            "Server is overloaded, entering load-shedding mode"
+        ),
+        LoadSheddingEvent::Deactivated => info!("Server recovered, leaving load-shedding mode"),
+    }
+
+    // replication frequency
+    if let Some(mut send_timer) = send_timer {
+        if active {
+            let send_interval = send_timer.send_interval;
+            load_shedding.replication_send_interval = Some(send_interval);
+            let base = if send_interval == Duration::default() {
+                tick_manager.config.tick_duration
+            } else {
+                send_interval
+            };
+            send_timer.set_send_interval(
+                base * load_shedding.config.replication_interval_multiplier.max(1),
+            );
+        } else if let Some(send_interval) = load_shedding.replication_send_interval.take() {
+            send_timer.set_send_interval(send_interval);
+        }
+    }
+
+    // low-priority channels
+    if let Some(mut connection_manager) = connection_manager {
+        let clients: Vec<_> = connection_manager.connected_clients().collect();
+        for (kind, _) in
+            channel_registry.channels_below_priority(load_shedding.config.low_priority_threshold)
+        {
+            set_channel_paused(
+                &mut connection_manager,
+                &clients,
+                *kind,
+                active.then_some(load_shedding.config.paused_channel_max_bytes),
+            );
+        }
+    }
+    events.send(event);
+}
+
+/// The clients that connect while the mode is active must also have their low-priority channels paused
+fn pause_channels_of_new_clients(
+    load_shedding: Res<LoadShedding>,
+    channel_registry: Res<ChannelRegistry>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut connect_events: EventReader<ConnectEvent>,
+) {
+    let clients: Vec<_> = connect_events.read().map(|event| event.client_id).collect();
+    if clients.is_empty() {
+        return;
+    }
+    for (kind, _) in
+        channel_registry.channels_below_priority(load_shedding.config.low_priority_threshold)
+    {
+        set_channel_paused(
+            &mut connection_manager,
+            &clients,
+            *kind,
+            Some(load_shedding.config.paused_channel_max_bytes),
+        );
+    }
+}
+
+/// Pause the channel for the clients (keeping at most `max_buffered_bytes` buffered), or resume it if `None`
+fn set_channel_paused(
+    connection_manager: &mut ConnectionManager,
+    clients: &[ClientId],
+    kind: ChannelKind,
+    max_buffered_bytes: Option<usize>,
+) {
+    for client_id in clients {
+        if let Ok(connection) = connection_manager.connection_mut(*client_id) {
+            match max_buffered_bytes {
+                Some(max_buffered_bytes) => {
+                    let _ = connection
+                        .message_manager
+                        .pause_channel(kind, max_buffered_bytes);
+                }
+                None => connection.message_manager.resume_channel(kind),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_load_shedding_hysteresis() {
+        let mut load_shedding = LoadShedding {
+            config: LoadSheddingPlugin {
+                overrun_ticks: 2,
+                recovery_ticks: 3,
+                ..default()
+            },
+            active: false,
+            consecutive_ticks: 0,
+            frame_start: None,
+            last_frame_duration: Duration::default(),
+            replication_send_interval: None,
+        };
+        let budget = Duration::from_millis(10);
+        let slow = Duration::from_millis(20);
+        let fast = Duration::from_millis(5);
+        assert_eq!(load_shedding.update(slow, budget), None);
+        // a single fast frame resets the count
+        assert_eq!(load_shedding.update(fast, budget), None);
+        assert_eq!(load_shedding.update(slow, budget), None);
+        assert_eq!(
+            load_shedding.update(slow, budget),
+            Some(LoadSheddingEvent::Activated)
+        );
+        assert_eq!(load_shedding.interest_scale(), 0.5);
+        assert_eq!(load_shedding.update(fast, budget), None);
+        assert_eq!(load_shedding.update(fast, budget), None);
+        assert_eq!(
+            load_shedding.update(fast, budget),
+            Some(LoadSheddingEvent::Deactivated)
+        );
+        assert_eq!(load_shedding.interest_scale(), 1.0);
+    }
+
+    #[test]
+    fn test_load_shedding_reduces_replication_frequency() {
+        let mut stepper = BevyStepper::default();
+        let initial_interval = stepper
+            .server_app
+            .world()
+            .resource::<SendIntervalTimer<ConnectionManager>>()
+            .send_interval;
+        stepper.server_app.add_plugins(LoadSheddingPlugin {
+            // every frame overruns
+            tick_budget: Some(Duration::ZERO),
+            overrun_ticks: 2,
+            ..default()
+        });
+        stepper.frame_step();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<LoadShedding>()
+            .is_active());
+        let timer = stepper
+            .server_app
+            .world()
+            .resource::<SendIntervalTimer<ConnectionManager>>();
+        assert_eq!(
+            timer.timer.as_ref().map(|timer| timer.duration()),
+            Some(stepper.tick_duration * 2)
+        );
+
+        // the previous interval is restored once the server recovers
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<LoadShedding>()
+            .config
+            .tick_budget = Some(Duration::MAX);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<LoadShedding>()
+            .config
+            .recovery_ticks = 1;
+        stepper.frame_step();
+        assert!(!stepper
+            .server_app
+            .world()
+            .resource::<LoadShedding>()
+            .is_active());
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<SendIntervalTimer<ConnectionManager>>()
+                .send_interval,
+            initial_interval
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::ops::DerefMut;
 use crate::prelude::{server::is_started, Message};
 use crate::protocol::message::{MessageKind, MessageRegistry};
 use crate::serialize::reader::Reader;
+use crate::serialize::writer::Writer;
 use crate::server::connection::ConnectionManager;
 use crate::server::events::MessageEvent;
 use crate::shared::replication::network_target::NetworkTarget;
@@ -29,23 +30,43 @@ fn read_message<M: Message>(
     let connection_manager = connection_manager.deref_mut();
     for (client_id, connection) in connection_manager.connections.iter_mut() {
         if let Some(message_list) = connection.received_messages.remove(&net) {
+            // the client might use a previous version of the message
+            let version = connection.message_version(net, &message_registry);
+            let is_current_version = version == message_registry.current_version(net);
             for (message_bytes, target, channel_kind) in message_list {
                 let mut reader = Reader::from(message_bytes);
-                match message_registry.deserialize::<M>(
+                match message_registry.deserialize_version::<M>(
                     &mut reader,
                     &mut connection
                         .replication_receiver
                         .remote_entity_map
                         .remote_to_local,
+                    version,
                 ) {
                     Ok(message) => {
                         // rebroadcast
                         if target != NetworkTarget::None {
-                            connection.messages_to_rebroadcast.push((
-                                reader.consume(),
-                                target,
-                                channel_kind,
-                            ));
+                            // the other clients receive the current version of the message
+                            let message_bytes = if is_current_version {
+                                Ok(reader.consume())
+                            } else {
+                                let mut writer = Writer::default();
+                                message_registry
+                                    .serialize(&message, &mut writer, None)
+                                    .map(|_| writer.split())
+                            };
+                            match message_bytes {
+                                Ok(message_bytes) => connection.messages_to_rebroadcast.push((
+                                    message_bytes,
+                                    target,
+                                    channel_kind,
+                                )),
+                                Err(e) => error!(
+                                    "Could not serialize message {} to rebroadcast it: {:?}",
+                                    std::any::type_name::<M>(),
+                                    e
+                                ),
+                            }
                         }
                         event.send(MessageEvent::new(message, *client_id));
                         trace!("Received message: {:?}", std::any::type_name::<M>());
@@ -180,9 +201,10 @@ pub(crate) fn add_server_receive_message_from_client<M: Message>(app: &mut App)
 
 #[cfg(test)]
 mod tests {
-    use crate::prelude::NetworkTarget;
+    use crate::prelude::{ClientId, NetworkTarget};
     use crate::tests::host_server_stepper::HostServerStepper;
     use crate::tests::protocol::{Channel1, StringMessage};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
     use bevy::app::Update;
     use bevy::prelude::{EventReader, ResMut, Resource};
 
@@ -234,4 +256,33 @@ mod tests {
         // verify that the other client received the message
         assert_eq!(stepper.client_app.world().resource::<Counter>().0, 1);
     }
+
+    /// The client only emits a tick-targeted message once it reaches the tick
+    #[test]
+    fn server_send_message_at_tick() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<Counter>();
+        stepper.client_app.add_systems(Update, count_messages);
+
+        let target_tick = stepper.client_tick() + 20;
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<crate::prelude::server::ConnectionManager>()
+            .send_message_at_tick::<Channel1, StringMessage>(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                &mut StringMessage("a".to_string()),
+                target_tick,
+            )
+            .unwrap();
+        for _ in 0..30 {
+            stepper.frame_step();
+            let received = stepper.client_app.world().resource::<Counter>().0;
+            if stepper.client_tick() < target_tick {
+                assert_eq!(received, 0);
+            } else {
+                assert_eq!(received, 1);
+            }
+        }
+    }
 }
@@ -0,0 +1,206 @@
+//! Typed storage for user data attached to a client connection.
+//!
+//! Instead of maintaining a separate `HashMap<ClientId, T>` resource (which has to be cleaned up manually
+//! when the client disconnects), you can store data directly on the connection via
+//! [`ConnectionManager::metadata_mut`](crate::server::connection::ConnectionManager::metadata_mut).
+//! The data is dropped when the client disconnects, unless [`ServerConfig::metadata_retention`](crate::server::config::ServerConfig::metadata_retention)
+//! is set, in which case it is restored if the client reconnects quickly enough.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::server::*;
+//!
+//! struct PlayerName(String);
+//!
+//! fn on_connect(mut events: EventReader<ConnectEvent>, mut manager: ResMut<ConnectionManager>) {
+//!     for event in events.read() {
+//!         if let Ok(metadata) = manager.metadata_mut(event.client_id) {
+//!             metadata.insert(PlayerName(format!("Player {}", event.client_id)));
+//!         }
+//!     }
+//! }
+//! ```
+use std::any::{Any, TypeId};
+
+use bevy::utils::{Duration, HashMap};
+
+use crate::connection::id::ClientId;
+
+/// Key-value store where the keys are types: it can hold at most one value of each type.
+#[derive(Default)]
+pub struct ConnectionMetadata {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl std::fmt::Debug for ConnectionMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionMetadata")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+impl ConnectionMetadata {
+    /// Insert a value, and return the previous value of the same type if there was one
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|previous| *previous.downcast::<T>().unwrap())
+    }
+
+    /// Get the value of type `T`
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// Get a mutable reference to the value of type `T`
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.values
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut::<T>())
+    }
+
+    /// Get a mutable reference to the value of type `T`, inserting the default value if there is none
+    pub fn get_or_default<T: Default + Send + Sync + 'static>(&mut self) -> &mut T {
+        self.values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<T>::default())
+            .downcast_mut::<T>()
+            .unwrap()
+    }
+
+    /// Remove the value of type `T`, and return it
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .map(|value| *value.downcast::<T>().unwrap())
+    }
+
+    /// Returns true if there is a value of type `T`
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Metadata of clients that disconnected, kept in case they reconnect
+#[derive(Debug, Default)]
+pub(crate) struct RetainedMetadata {
+    retention: Duration,
+    /// The metadata of each disconnected client, along with the remaining retention duration
+    metadata: HashMap<ClientId, (ConnectionMetadata, Duration)>,
+}
+
+impl RetainedMetadata {
+    pub(crate) fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            metadata: HashMap::default(),
+        }
+    }
+
+    /// Keep the metadata of a client that disconnected
+    pub(crate) fn retain(&mut self, client_id: ClientId, metadata: ConnectionMetadata) {
+        if self.retention > Duration::ZERO && !metadata.is_empty() {
+            self.metadata.insert(client_id, (metadata, self.retention));
+        }
+    }
+
+    /// Take the metadata of a client that reconnected
+    pub(crate) fn take(&mut self, client_id: ClientId) -> Option<ConnectionMetadata> {
+        self.metadata
+            .remove(&client_id)
+            .map(|(metadata, _)| metadata)
+    }
+
+    /// Drop the metadata that has been retained for longer than the retention duration
+    pub(crate) fn update(&mut self, delta: Duration) {
+        if self.metadata.is_empty() {
+            return;
+        }
+        self.metadata.retain(|_, (_, remaining)| {
+            *remaining = remaining.saturating_sub(delta);
+            *remaining > Duration::ZERO
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::ConnectionManager;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    #[derive(Debug, PartialEq)]
+    struct PlayerName(String);
+
+    #[test]
+    fn test_metadata() {
+        let mut metadata = ConnectionMetadata::default();
+        assert!(metadata.insert(PlayerName("a".to_string())).is_none());
+        assert_eq!(
+            metadata.insert(PlayerName("b".to_string())),
+            Some(PlayerName("a".to_string()))
+        );
+        *metadata.get_or_default::<u32>() += 1;
+        assert_eq!(metadata.get::<u32>(), Some(&1));
+        assert_eq!(
+            metadata.remove::<PlayerName>(),
+            Some(PlayerName("b".to_string()))
+        );
+        assert!(!metadata.contains::<PlayerName>());
+    }
+
+    #[test]
+    fn test_metadata_cleaned_up_on_disconnect() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .metadata_mut(client_id)
+            .unwrap()
+            .insert(PlayerName("a".to_string()));
+
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>();
+        manager.remove(client_id);
+        assert!(manager.metadata(client_id).is_err());
+        // the metadata is not retained by default
+        manager.add(client_id, bevy::prelude::Entity::PLACEHOLDER);
+        assert!(manager.metadata(client_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_metadata_retained_on_reconnect() {
+        let mut retained = RetainedMetadata::new(Duration::from_secs(1));
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let mut metadata = ConnectionMetadata::default();
+        metadata.insert(PlayerName("a".to_string()));
+        retained.retain(client_id, metadata);
+
+        retained.update(Duration::from_millis(500));
+        let metadata = retained.take(client_id).unwrap();
+        assert_eq!(
+            metadata.get::<PlayerName>(),
+            Some(&PlayerName("a".to_string()))
+        );
+
+        // the metadata is dropped after the retention duration
+        retained.retain(client_id, metadata);
+        retained.update(Duration::from_secs(1));
+        assert!(retained.take(client_id).is_none());
+    }
+}
@@ -3,10 +3,14 @@
 //! # Server
 //! The server module contains all the code that is used to run the server.
 
+pub mod budget;
+
 pub mod config;
 
 pub mod connection;
 
+pub mod console;
+
 pub mod error;
 
 pub mod events;
@@ -15,13 +19,22 @@ pub mod input;
 
 pub(crate) mod io;
 
+pub mod metadata;
+
 pub mod plugin;
 
 pub(crate) mod message;
 pub(crate) mod prediction;
 
 pub mod clients;
+pub mod load_shedding;
 pub(crate) mod networking;
+pub mod ownership;
 pub mod relevance;
+pub mod replay;
 pub mod replication;
 pub mod run_conditions;
+pub mod scene;
+pub mod send_budget;
+#[cfg(all(feature = "status_endpoint", not(target_family = "wasm")))]
+pub mod status;
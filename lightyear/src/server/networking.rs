@@ -1,21 +1,31 @@
 //! Defines the server bevy systems and run conditions
+use crate::connection::client::ClientConnection;
 use crate::connection::server::{IoConfig, NetServer, ServerConnection, ServerConnections};
+use crate::packet::packet_builder::Payload;
 use crate::prelude::{
     is_host_server, server::is_started, ChannelRegistry, MainSet, MessageRegistry, TickManager,
     TimeManager,
 };
 use crate::protocol::component::ComponentRegistry;
 use crate::serialize::reader::Reader;
+use crate::server::budget::{
+    release_despawned_entities, BudgetOverflowPolicy, ReplicationBudgetExceededEvent,
+};
 use crate::server::clients::ControlledEntities;
 use crate::server::config::ServerConfig;
 use crate::server::connection::ConnectionManager;
 use crate::server::error::ServerError;
+use crate::server::events::{MessageEvent, NetworkErrorEvent, ProtocolMismatchEvent};
 use crate::server::io::ServerIoEvent;
+use crate::server::send_budget::ClientSendStats;
+use crate::shared::memory::enforce_server_memory_limits;
 use crate::shared::sets::{InternalMainSet, ServerMarker};
+use crate::shared::sync::InterpolationDelayMessage;
 use async_channel::TryRecvError;
 use bevy::ecs::system::{RunSystemOnce, SystemChangeTick};
 use bevy::prelude::*;
-use tracing::{debug, error, trace};
+use std::collections::VecDeque;
+use tracing::{debug, error, trace, warn};
 
 /// Plugin handling the server networking systems: sending/receiving packets to clients
 #[derive(Default)]
@@ -55,10 +65,24 @@ impl Plugin for ServerNetworkingPlugin {
                     .chain()
                     .in_set(InternalMainSet::<ServerMarker>::Receive),
             )
+            .add_systems(
+                PreUpdate,
+                receive_interpolation_delay
+                    .after(InternalMainSet::<ServerMarker>::EmitEvents)
+                    .run_if(is_started),
+            )
             .add_systems(
                 PostUpdate,
-                (send, send_host_server.run_if(is_host_server))
-                    .in_set(InternalMainSet::<ServerMarker>::Send),
+                (
+                    (send, send_host_server.run_if(is_host_server))
+                        .in_set(InternalMainSet::<ServerMarker>::Send),
+                    enforce_server_memory_limits
+                        .after(InternalMainSet::<ServerMarker>::Send)
+                        .run_if(is_started),
+                    release_despawned_entities
+                        .before(InternalMainSet::<ServerMarker>::Send)
+                        .run_if(is_started),
+                ),
             );
 
         // ON_START
@@ -90,8 +114,11 @@ pub(crate) fn receive_packets(
     component_registry: Res<ComponentRegistry>,
     message_registry: Res<MessageRegistry>,
     system_change_tick: SystemChangeTick,
+    mut network_errors: EventWriter<NetworkErrorEvent>,
 ) {
     trace!("Receive client packets");
+    #[cfg(feature = "alloc_audit")]
+    let _audit = crate::utils::alloc_audit::enter(crate::utils::alloc_audit::HotPath::Receive);
     let delta = virtual_time.delta();
     // UPDATE: update server state, send keep-alives, receive packets from io
     // update time manager
@@ -126,11 +153,20 @@ pub(crate) fn receive_packets(
             }
         }
 
-        let _ = netserver
-            .try_update(delta.as_secs_f64())
-            .map_err(|e| error!("Error updating netcode server: {:?}", e));
+        let _ = netserver.try_update(delta.as_secs_f64()).map_err(|e| {
+            error!("Error updating netcode server: {:?}", e);
+            network_errors.send(NetworkErrorEvent {
+                client_id: None,
+                error: e.to_string(),
+            });
+        });
         for client_id in netserver.new_connections().iter().copied() {
             netservers.client_server_map.insert(client_id, server_idx);
+            // the client reconnected before its session expired: keep using its previous connection
+            // once it has sent back its session token
+            if connection_manager.resume(client_id) {
+                continue;
+            }
             // spawn an entity for the client
             let client_entity = commands
                 .spawn((ControlledEntities::default(), Name::new("Client")))
@@ -179,14 +215,23 @@ pub(crate) fn receive_packets(
             // packets from a client
             // TODO: use connection to apply on BOTH message manager and replication manager
             if let Some(connection) = connection_manager.connections.get_mut(&client_id) {
-                connection
+                connection.stats.bytes_received += payload.len();
+                connection.stats.packets_received += 1;
+                // a malformed packet only affects the connection of the client that sent it
+                let _ = connection
                     .recv_packet(
                         payload,
                         tick_manager.as_ref(),
                         component_registry.as_ref(),
                         &mut connection_manager.delta_manager,
                     )
-                    .expect("could not receive packet");
+                    .inspect_err(|e| {
+                        error!(?client_id, "Error receiving packet: {}", e);
+                        network_errors.send(NetworkErrorEvent {
+                            client_id: Some(client_id),
+                            error: e.to_string(),
+                        });
+                    });
             } else {
                 // it's still possible to receive some packets from a client that just disconnected.
                 // (multiple packets arrived at the same time from that client)
@@ -211,6 +256,8 @@ pub(crate) fn receive(
     // time_manager: Res<TimeManager>,
     // tick_manager: Res<TickManager>,
 ) {
+    #[cfg(feature = "alloc_audit")]
+    let _audit = crate::utils::alloc_audit::enter(crate::utils::alloc_audit::HotPath::Receive);
     let unsafe_world = world.as_unsafe_world_cell();
 
     // TODO: an alternative would be to use `Commands + EntityMut` which both don't conflict with resources
@@ -223,17 +270,77 @@ pub(crate) fn receive(
     let time_manager = unsafe { unsafe_world.get_resource::<TimeManager>() }.unwrap();
     let tick_manager = unsafe { unsafe_world.get_resource::<TickManager>() }.unwrap();
     // RECEIVE: read messages and parse them into events
-    connection_manager
-        .receive(
-            unsafe { unsafe_world.world_mut() },
-            component_registry,
-            message_registry,
-            time_manager,
-            tick_manager,
-        )
-        .unwrap_or_else(|e| {
-            error!("Error during receive: {}", e);
+    let errors = connection_manager.receive(
+        unsafe { unsafe_world.world_mut() },
+        component_registry,
+        message_registry,
+        time_manager,
+        tick_manager,
+    );
+
+    // handle the clients that exceeded their replication budget
+    let mut violations = vec![];
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        let disconnect =
+            connection.budget.config.overflow_policy == BudgetOverflowPolicy::Disconnect;
+        for reason in connection.budget.violations.drain(..) {
+            violations.push((*client_id, reason, disconnect));
+        }
+    }
+    let skip_unknown_types = message_registry.skip_unknown_types;
+    let mut protocol_mismatches = vec![];
+    for (client_id, e) in errors {
+        error!(?client_id, "Error during receive: {}", e);
+        if let (Some(client_id), ServerError::ProtocolMismatch { client, server }) = (client_id, &e)
+        {
+            protocol_mismatches.push(ProtocolMismatchEvent {
+                client_id,
+                client: *client,
+                server: *server,
+            });
+        }
+        world.send_event(NetworkErrorEvent {
+            client_id,
+            error: e.to_string(),
         });
+    }
+    for event in protocol_mismatches {
+        let client_id = event.client_id;
+        world.send_event(event);
+        // the client would send data that we cannot deserialize correctly
+        if !skip_unknown_types {
+            let _ = world
+                .resource_mut::<ServerConnections>()
+                .disconnect(client_id)
+                .inspect_err(|e| error!("Error disconnecting client {:?}: {}", client_id, e));
+        }
+    }
+    for (client_id, reason, disconnect) in violations {
+        warn!(
+            ?client_id,
+            ?reason,
+            "Client exceeded its replication budget"
+        );
+        world.send_event(ReplicationBudgetExceededEvent { client_id, reason });
+        if disconnect {
+            let _ = world
+                .resource_mut::<ServerConnections>()
+                .disconnect(client_id)
+                .inspect_err(|e| error!("Error disconnecting client {:?}: {}", client_id, e));
+        }
+    }
+}
+
+/// Store the interpolation delays reported by the clients
+fn receive_interpolation_delay(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventReader<MessageEvent<InterpolationDelayMessage>>,
+) {
+    for event in events.read() {
+        if let Ok(connection) = connection_manager.connection_mut(event.context) {
+            connection.interpolation_delay = Some(event.message.delay);
+        }
+    }
 }
 
 // or do additional send stuff here
@@ -243,32 +350,141 @@ pub(crate) fn send(
     mut connection_manager: ResMut<ConnectionManager>,
     tick_manager: Res<TickManager>,
     time_manager: Res<TimeManager>,
+    mut network_errors: EventWriter<NetworkErrorEvent>,
 ) {
     trace!("Send packets to clients");
+    #[cfg(feature = "alloc_audit")]
+    let _audit = crate::utils::alloc_audit::enter(crate::utils::alloc_audit::HotPath::Send);
     // SEND_PACKETS: send buffered packets to io
     let span = info_span!("send_packets").entered();
-    connection_manager
+    let connection_manager = connection_manager.as_mut();
+    let elapsed_ticks = connection_manager
+        .send_scheduler
+        .elapsed_ticks(tick_manager.tick());
+    // 1. collect the packets of each client
+    // (an error while sending to one client should not prevent sending to the other clients)
+    let mut clients = vec![];
+    let mut queues = vec![];
+    for (client_id, connection) in connection_manager
         .connections
         .iter_mut()
         .filter(|(_, connection)| !connection.is_local_client())
-        .try_for_each(|(client_id, connection)| {
-            let client_span =
-                info_span!("send_packets_to_client", client_id = ?client_id).entered();
+    {
+        let _client_span = info_span!("send_packets_to_client", client_id = ?client_id).entered();
+        connection.send_stats = ClientSendStats::default();
+        match (|| -> Result<Option<(usize, usize, VecDeque<Payload>)>, ServerError> {
+            if connection.is_suspended() {
+                // the client lost its connection: drop the packets as if the network was down,
+                // so that they get resent or nacked once the session is resumed
+                for packet_byte in connection.send_packets(&time_manager, &tick_manager)? {
+                    connection.message_manager.recycle_payload(packet_byte);
+                }
+                return Ok(None);
+            }
             let netserver_idx = *netservers
                 .client_server_map
                 .get(client_id)
                 .ok_or(ServerError::ServerConnectionNotFound)?;
-            let netserver = netservers
-                .servers
-                .get_mut(netserver_idx)
-                .ok_or(ServerError::ServerConnectionNotFound)?;
-            for packet_byte in connection.send_packets(&time_manager, &tick_manager)? {
-                netserver.send(packet_byte.as_slice(), *client_id)?;
+            // packets containing reliable messages that could not be sent during the previous frame are retried once
+            let mut queue: VecDeque<Payload> =
+                connection.message_manager.take_retry_payloads().into();
+            let num_retries = queue.len();
+            queue.extend(connection.send_packets(&time_manager, &tick_manager)?);
+            Ok(Some((netserver_idx, num_retries, queue)))
+        })() {
+            Ok(Some((netserver_idx, num_retries, queue))) => {
+                clients.push((*client_id, netserver_idx, num_retries, None));
+                queues.push(queue);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!(?client_id, "Error sending packets: {}", e);
+                network_errors.send(NetworkErrorEvent {
+                    client_id: Some(*client_id),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    // 2. send the packets in a round-robin order, within the send budget
+    let scheduled = connection_manager
+        .send_scheduler
+        .schedule(&connection_manager.send_budget, &mut queues);
+    for (i, packet_byte) in scheduled {
+        let (client_id, netserver_idx, num_retries, send_error) = &mut clients[i];
+        // SAFETY: we collected the packets from this connection
+        let connection = connection_manager.connections.get_mut(client_id).unwrap();
+        let is_retry = *num_retries > 0;
+        *num_retries = num_retries.saturating_sub(1);
+        let Some(netserver) = netservers.servers.get_mut(*netserver_idx) else {
+            send_error.get_or_insert(ServerError::ServerConnectionNotFound);
+            connection.message_manager.recycle_payload(packet_byte);
+            continue;
+        };
+        match netserver.send(packet_byte.as_slice(), *client_id) {
+            Ok(()) => {
+                connection.stats.bytes_sent += packet_byte.len();
+                connection.stats.packets_sent += 1;
+                connection.send_stats.bytes_sent += packet_byte.len();
+                connection.message_manager.recycle_payload(packet_byte);
+            }
+            Err(e) => {
+                connection.stats.send_errors += 1;
+                send_error.get_or_insert(e.into());
+                if is_retry {
+                    connection.message_manager.recycle_payload(packet_byte);
+                } else {
+                    // the socket buffer might be full: keep the packet to retry it next frame instead of dropping it
+                    connection.message_manager.retry_payload(packet_byte);
+                }
+            }
+        }
+    }
+
+    // 3. the packets that exceed the budget are deferred to the next frame
+    for ((client_id, _, _, send_error), queue) in clients.into_iter().zip(queues) {
+        // SAFETY: we collected the packets from this connection
+        let connection = connection_manager.connections.get_mut(&client_id).unwrap();
+        connection.send_stats.deferred_packets = queue.len();
+        for packet_byte in queue {
+            connection.message_manager.retry_payload(packet_byte);
+        }
+        connection.send_stats.bytes_per_tick =
+            connection.send_stats.bytes_sent as f32 / elapsed_ticks as f32;
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!("send_bytes_per_tick", "client_id" => client_id.to_string())
+                .set(connection.send_stats.bytes_per_tick as f64);
+            metrics::counter!("send_deferred_packets", "client_id" => client_id.to_string())
+                .increment(connection.send_stats.deferred_packets as u64);
+        }
+        if let Some(e) = send_error {
+            error!(?client_id, "Error sending packets: {}", e);
+            network_errors.send(NetworkErrorEvent {
+                client_id: Some(client_id),
+                error: e.to_string(),
+            });
+        }
+    }
+
+    // close the connections of the clients that were disconnected by the server,
+    // once they have received the reason of the disconnection
+    connection_manager
+        .connections
+        .iter_mut()
+        .filter(|(_, connection)| !connection.is_local_client())
+        .for_each(|(client_id, connection)| {
+            if connection
+                .pending_disconnect
+                .as_mut()
+                .is_some_and(|pending| pending.update(time_manager.delta()))
+            {
+                connection.pending_disconnect = None;
+                let _ = netservers
+                    .disconnect(*client_id)
+                    .inspect_err(|e| error!("Error disconnecting client {:?}: {}", client_id, e));
             }
-            Ok(())
-        })
-        .unwrap_or_else(|e: ServerError| {
-            error!("Error sending packets: {}", e);
         });
 }
 
@@ -277,16 +493,26 @@ pub(crate) fn send(
 pub(crate) fn send_host_server(
     mut connection_manager: ResMut<ConnectionManager>,
     mut client_manager: ResMut<crate::client::connection::ConnectionManager>,
+    mut netclient: ResMut<ClientConnection>,
+    channel_registry: Res<ChannelRegistry>,
 ) {
+    let mut conditioner = netclient.local_conditioner_mut();
     let _ = connection_manager
         .connections
         .iter_mut()
         .filter(|(_, connection)| connection.is_local_client())
         .try_for_each(|(_, connection)| {
-            connection
-                .local_messages_to_send
-                .drain(..)
-                .try_for_each(|message| client_manager.receive_message(Reader::from(message)))
+            let messages = connection.local_messages_to_send.drain(..);
+            // simulate the network conditions between the server and the local client
+            let messages = match conditioner.as_mut() {
+                Some(conditioner) => {
+                    conditioner.condition_to_client(messages, channel_registry.as_ref())
+                }
+                None => messages.collect(),
+            };
+            messages
+                .into_iter()
+                .try_for_each(|(message, _)| client_manager.receive_message(Reader::from(message)))
         })
         .inspect_err(|e| error!("Error sending messages to local client: {:?}", e));
 }
@@ -320,6 +546,10 @@ fn rebuild_server_connections(world: &mut World) {
         server_config.replication,
         server_config.packet,
         server_config.ping,
+        server_config.metadata_retention,
+        server_config.session_grace_period,
+        server_config.replication_budget,
+        server_config.send_budget,
     );
     // // make sure the previous replication metadata is ported over to the new manager
     // if let Some(mut previous_manager) = world.get_resource_mut::<ConnectionManager>() {
@@ -0,0 +1,342 @@
+//! Transfer the ownership of an entity from one client to another.
+//!
+//! The owner of an entity is the client that controls it: for example the driver of a vehicle, or the master of a pet.
+//! [`ConnectionManager::transfer_authority`] switches the owner of an entity during the next replication pass:
+//! - the [`ControlledBy`] component of the entity is updated, so that the inputs of the previous owner for
+//!   that entity are not accepted anymore
+//! - if the previous owner had [`AuthorityPeer`] over the entity, the authority is transferred to the new owner
+//!   (so that the replication updates of the new owner are accepted instead)
+//! - the [`SyncTarget`] of the entity is updated: the new owner starts predicting the entity, and the previous
+//!   owner interpolates it instead (if it was predicting it)
+//! - both clients are notified: the new owner receives the [`Controlled`] marker, the previous owner loses it, and
+//!   the clients despawn their predicted/interpolated entity to switch to the other mode
+//!
+//! An [`OwnershipTransferEvent`] is emitted once the transfer has been applied.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//!
+//! fn enter_vehicle(mut connection_manager: ResMut<ConnectionManager>, vehicle: Res<Vehicle>) {
+//!     connection_manager
+//!         .transfer_authority(vehicle.entity, vehicle.driver, new_driver)
+//!         .unwrap();
+//! }
+//! ```
+use bevy::prelude::*;
+use tracing::{debug, error, warn};
+
+use crate::prelude::server::{AuthorityCommandExt, ControlledBy, SyncTarget};
+use crate::prelude::{
+    ClientId, ComponentRegistry, NetworkTarget, ReplicationGroup, ShouldBePredicted,
+};
+use crate::server::clients::ControlledEntities;
+use crate::server::connection::ConnectionManager;
+use crate::server::error::ServerError;
+use crate::shared::replication::authority::AuthorityPeer;
+use crate::shared::replication::components::{
+    Controlled, ReplicationGroupId, ShouldBeInterpolated,
+};
+
+/// A transfer of ownership that will be applied during the next replication pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OwnershipTransfer {
+    pub(crate) entity: Entity,
+    pub(crate) from: ClientId,
+    pub(crate) to: ClientId,
+}
+
+/// Event emitted when the ownership of an entity was transferred from one client to another
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct OwnershipTransferEvent {
+    pub entity: Entity,
+    pub from: ClientId,
+    pub to: ClientId,
+}
+
+/// Apply the transfers that were requested with [`ConnectionManager::transfer_authority`]
+pub(crate) fn apply_ownership_transfers(
+    mut commands: Commands,
+    component_registry: Res<ComponentRegistry>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut query: Query<(
+        Option<&mut ControlledBy>,
+        Option<&mut SyncTarget>,
+        Option<&ReplicationGroup>,
+        Option<&AuthorityPeer>,
+    )>,
+    mut client_query: Query<&mut ControlledEntities>,
+    mut events: EventWriter<OwnershipTransferEvent>,
+) {
+    if connection_manager.pending_ownership_transfers.is_empty() {
+        return;
+    }
+    let transfers = std::mem::take(&mut connection_manager.pending_ownership_transfers);
+    for OwnershipTransfer { entity, from, to } in transfers {
+        let Ok((controlled_by, sync_target, group, authority_peer)) = query.get_mut(entity) else {
+            warn!(
+                ?entity,
+                "Cannot transfer the ownership of an entity that does not exist"
+            );
+            continue;
+        };
+        debug!(?entity, ?from, ?to, "Transfer ownership");
+
+        // inputs
+        match controlled_by {
+            Some(mut controlled_by) => {
+                controlled_by.target.exclude(&NetworkTarget::Single(from));
+                controlled_by.target.union(&NetworkTarget::Single(to));
+            }
+            None => {
+                commands.entity(entity).insert(ControlledBy {
+                    target: NetworkTarget::Single(to),
+                    ..default()
+                });
+            }
+        }
+        if let Some(mut controlled_entities) = connection_manager
+            .client_entity(from)
+            .ok()
+            .and_then(|client_entity| client_query.get_mut(client_entity).ok())
+        {
+            controlled_entities.remove(&entity);
+        }
+
+        // replication updates
+        if authority_peer == Some(&AuthorityPeer::Client(from)) {
+            commands
+                .entity(entity)
+                .transfer_authority(AuthorityPeer::Client(to));
+        }
+
+        // prediction/interpolation
+        let mut from_interpolates = false;
+        let mut to_predicts = false;
+        if let Some(mut sync_target) = sync_target {
+            if sync_target.prediction.targets(&from) {
+                sync_target.prediction.exclude(&NetworkTarget::Single(from));
+                sync_target
+                    .interpolation
+                    .union(&NetworkTarget::Single(from));
+                from_interpolates = true;
+            }
+            if !sync_target.prediction.targets(&to) {
+                sync_target.prediction.union(&NetworkTarget::Single(to));
+                sync_target
+                    .interpolation
+                    .exclude(&NetworkTarget::Single(to));
+                to_predicts = true;
+            }
+        }
+
+        let group_id = group.map_or(ReplicationGroupId::default(), |group| {
+            group.group_id(Some(entity))
+        });
+        let _ = notify_new_owner(
+            &mut connection_manager,
+            &component_registry,
+            entity,
+            group_id,
+            to,
+            to_predicts,
+        )
+        .and_then(|_| {
+            notify_previous_owner(
+                &mut connection_manager,
+                &component_registry,
+                entity,
+                group_id,
+                from,
+                from_interpolates,
+            )
+        })
+        .inspect_err(|e| error!(?entity, "error notifying the ownership transfer: {:?}", e));
+        events.send(OwnershipTransferEvent { entity, from, to });
+    }
+}
+
+/// Returns true if the entity was already replicated to the client.
+///
+/// If it wasn't, the client will receive the entity with the updated settings, so there is nothing to notify.
+fn is_replicated_to(
+    connection_manager: &ConnectionManager,
+    group_id: ReplicationGroupId,
+    client_id: ClientId,
+) -> bool {
+    connection_manager
+        .connection(client_id)
+        .is_ok_and(|connection| {
+            connection
+                .replication_sender
+                .group_channels
+                .contains_key(&group_id)
+        })
+}
+
+fn notify_new_owner(
+    connection_manager: &mut ConnectionManager,
+    component_registry: &ComponentRegistry,
+    entity: Entity,
+    group_id: ReplicationGroupId,
+    client_id: ClientId,
+    predict: bool,
+) -> Result<(), ServerError> {
+    if !is_replicated_to(connection_manager, group_id, client_id) {
+        return Ok(());
+    }
+    connection_manager.prepare_typed_component_insert(
+        entity,
+        group_id,
+        client_id,
+        component_registry,
+        &mut Controlled,
+    )?;
+    if predict {
+        connection_manager.prepare_typed_component_insert(
+            entity,
+            group_id,
+            client_id,
+            component_registry,
+            &mut ShouldBePredicted,
+        )?;
+    }
+    Ok(())
+}
+
+fn notify_previous_owner(
+    connection_manager: &mut ConnectionManager,
+    component_registry: &ComponentRegistry,
+    entity: Entity,
+    group_id: ReplicationGroupId,
+    client_id: ClientId,
+    interpolate: bool,
+) -> Result<(), ServerError> {
+    if !is_replicated_to(connection_manager, group_id, client_id) {
+        return Ok(());
+    }
+    if let Some(net_id) = component_registry.get_net_id::<Controlled>() {
+        let connection = connection_manager.connection_mut(client_id)?;
+        let network_entity = connection
+            .replication_receiver
+            .remote_entity_map
+            .local_to_remote
+            .network_entity(entity);
+        connection
+            .replication_sender
+            .prepare_component_remove(network_entity, group_id, net_id);
+    }
+    if interpolate {
+        connection_manager.prepare_typed_component_insert(
+            entity,
+            group_id,
+            client_id,
+            component_registry,
+            &mut ShouldBeInterpolated,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client::{Confirmed, Interpolated, Predicted};
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{client, server};
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
+    use crate::tests::protocol::ComponentSyncModeFull;
+
+    fn confirmed(app: &App, server_entity: Entity) -> Confirmed {
+        let client_entity = app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        app.world()
+            .get::<Confirmed>(client_entity)
+            .expect("the entity should be Confirmed")
+            .clone()
+    }
+
+    #[test]
+    fn test_transfer_ownership() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        prediction: NetworkTarget::Single(client_1),
+                        interpolation: NetworkTarget::AllExceptSingle(client_1),
+                    },
+                    controlled_by: ControlledBy {
+                        target: NetworkTarget::Single(client_1),
+                        ..default()
+                    },
+                    ..default()
+                },
+                ComponentSyncModeFull(1.0),
+            ))
+            .id();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        let predicted = confirmed(&stepper.client_app_1, server_entity)
+            .predicted
+            .unwrap();
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get::<Predicted>(predicted)
+            .is_some());
+        assert!(confirmed(&stepper.client_app_2, server_entity)
+            .interpolated
+            .is_some());
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .transfer_authority(server_entity, client_1, client_2)
+            .unwrap();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        let controlled_by = stepper
+            .server_app
+            .world()
+            .get::<ControlledBy>(server_entity)
+            .unwrap();
+        assert!(controlled_by.targets(&client_2));
+        assert!(!controlled_by.targets(&client_1));
+
+        // the previous owner interpolates the entity
+        let confirmed_1 = confirmed(&stepper.client_app_1, server_entity);
+        assert!(confirmed_1.predicted.is_none());
+        let interpolated = confirmed_1.interpolated.unwrap();
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get::<Interpolated>(interpolated)
+            .is_some());
+        assert!(stepper.client_app_1.world().get_entity(predicted).is_none());
+
+        // the new owner predicts the entity, with the current value of the components
+        let confirmed_2 = confirmed(&stepper.client_app_2, server_entity);
+        assert!(confirmed_2.interpolated.is_none());
+        let predicted = confirmed_2.predicted.unwrap();
+        assert_eq!(
+            stepper
+                .client_app_2
+                .world()
+                .get::<ComponentSyncModeFull>(predicted),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+    }
+}
@@ -12,9 +12,11 @@ use crate::server::clients::ClientsMetadataPlugin;
 use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
+use crate::server::console::ConsolePlugin;
 use crate::server::events::ServerEventsPlugin;
 use crate::server::networking::ServerNetworkingPlugin;
 use crate::server::relevance::immediate::NetworkRelevancePlugin;
+use crate::server::relevance::interest::InterestPlugin;
 use crate::server::relevance::room::RoomPlugin;
 use crate::server::replication::{
     receive::ServerReplicationReceivePlugin, send::ServerReplicationSendPlugin,
@@ -31,6 +33,9 @@ use super::config::ServerConfig;
 /// - [`ServerNetworkingPlugin`]: Handles the network state (starting/stopping the server, sending/receiving packets)
 /// - [`NetworkRelevancePlugin`]: Handles the network relevance systems. This can be disabled if you don't need fine-grained interest management.
 /// - [`RoomPlugin`]: Handles the room system, which is an addition to the visibility system. This can be disabled if you don't need rooms.
+/// - [`InterestPlugin`]: Handles the subscription requests sent by clients to join interest sets. Requires the [`RoomPlugin`].
+/// - [`ConsolePlugin`]: Runs the console commands sent by authorized clients. Can be replaced with
+///   `ConsolePlugin { read_stdin: true }` to also run the commands typed in the standard input.
 /// - [`ServerReplicationReceivePlugin`]: Handles the replication of entities and resources from clients to the server. This can be
 ///   disabled if you don't need client to server replication.
 /// - [`ServerReplicationSendPlugin`]: Handles the replication of entities and resources from the server to the client. This can be
@@ -63,6 +68,8 @@ impl PluginGroup for ServerPlugins {
             .add(ServerNetworkingPlugin)
             .add(NetworkRelevancePlugin)
             .add(RoomPlugin)
+            .add(InterestPlugin)
+            .add(ConsolePlugin::default())
             .add(ClientsMetadataPlugin)
             .add(ServerReplicationReceivePlugin { tick_interval })
             .add(ServerReplicationSendPlugin { tick_interval })
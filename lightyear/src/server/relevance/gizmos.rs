@@ -0,0 +1,212 @@
+/*! Gizmo overlay to debug the network relevance of entities on the server
+
+The overlay draws, for a selected client:
+- a circle around each replicated entity, whose color shows if the entity is currently relevant to the client
+- a vertical bar above each relevant entity, whose height is the accumulated priority of its replication group
+- the boundaries of the rooms, highlighting the rooms that the client is in
+
+Rooms don't have a spatial extent, so only the rooms whose bounds are provided in [`RelevanceGizmos::room_bounds`]
+are drawn (for example the cells of a grid used for interest management).
+The entities are drawn at the position of their [`GlobalTransform`], and the rooms in the XY plane.
+
+The overlay requires the bevy `GizmoPlugin` (included in the `DefaultPlugins`), so it works on a server
+with a window or in host-server mode.
+
+```rust,ignore
+use bevy::prelude::*;
+use lightyear::prelude::server::*;
+
+app.add_plugins(RelevanceGizmosPlugin);
+
+fn select_client(mut gizmos: ResMut<RelevanceGizmos>) {
+    gizmos.client = Some(ClientId::Netcode(1));
+    gizmos.room_bounds.insert(RoomId(0), Rect::new(0.0, 0.0, 100.0, 100.0));
+}
+```
+*/
+use bevy::color::palettes::css::{DARK_GRAY, GRAY, LIME, RED, YELLOW};
+use bevy::gizmos::config::GizmoConfigStore;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::connection::id::ClientId;
+use crate::server::connection::ConnectionManager;
+use crate::server::relevance::immediate::CachedNetworkRelevance;
+use crate::server::relevance::room::{RoomId, RoomManager};
+use crate::shared::replication::components::{
+    NetworkRelevanceMode, ReplicationGroup, ReplicationTarget,
+};
+
+/// Plugin that draws the network relevance of entities for the client selected in [`RelevanceGizmos`]
+#[derive(Default)]
+pub struct RelevanceGizmosPlugin;
+
+/// Configuration of the relevance overlay
+#[derive(Resource, Debug, Clone)]
+pub struct RelevanceGizmos {
+    /// Whether the overlay is drawn
+    pub enabled: bool,
+    /// The client whose relevance is drawn. If `None`, the first connected client is used.
+    pub client: Option<ClientId>,
+    /// Bounds of the rooms to draw, in the XY plane
+    pub room_bounds: HashMap<RoomId, Rect>,
+    /// Radius of the circle drawn around each entity
+    pub entity_radius: f32,
+    /// Height of the priority bar per unit of accumulated priority
+    pub priority_scale: f32,
+    /// Color of the entities that are relevant to the client
+    pub relevant_color: Color,
+    /// Color of the entities that are replicated, but not to this client
+    pub irrelevant_color: Color,
+    /// Color of the priority bar
+    pub priority_color: Color,
+    /// Color of the rooms that the client is in
+    pub client_room_color: Color,
+    /// Color of the other rooms
+    pub room_color: Color,
+}
+
+impl Default for RelevanceGizmos {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            client: None,
+            room_bounds: HashMap::default(),
+            entity_radius: 10.0,
+            priority_scale: 5.0,
+            relevant_color: LIME.into(),
+            irrelevant_color: RED.into(),
+            priority_color: YELLOW.into(),
+            client_room_color: GRAY.into(),
+            room_color: DARK_GRAY.into(),
+        }
+    }
+}
+
+impl Plugin for RelevanceGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.init_resource::<RelevanceGizmos>();
+        // SYSTEMS
+        app.add_systems(
+            PostUpdate,
+            draw_relevance_gizmos.run_if(
+                resource_exists::<GizmoConfigStore>
+                    .and_then(resource_exists::<ConnectionManager>)
+                    .and_then(|config: Res<RelevanceGizmos>| config.enabled),
+            ),
+        );
+    }
+}
+
+/// Returns true if the entity is currently replicated to the client
+fn is_relevant(
+    client_id: ClientId,
+    target: &ReplicationTarget,
+    mode: Option<&NetworkRelevanceMode>,
+    cached_relevance: Option<&CachedNetworkRelevance>,
+) -> bool {
+    target.target.targets(&client_id)
+        && match mode {
+            Some(NetworkRelevanceMode::InterestManagement) => {
+                cached_relevance.is_some_and(|cache| cache.clients_cache.contains_key(&client_id))
+            }
+            _ => true,
+        }
+}
+
+fn draw_relevance_gizmos(
+    config: Res<RelevanceGizmos>,
+    connection_manager: Res<ConnectionManager>,
+    room_manager: Option<Res<RoomManager>>,
+    query: Query<(
+        Entity,
+        &GlobalTransform,
+        &ReplicationTarget,
+        &ReplicationGroup,
+        Option<&NetworkRelevanceMode>,
+        Option<&CachedNetworkRelevance>,
+    )>,
+    mut gizmos: Gizmos,
+) {
+    let Some(client_id) = config
+        .client
+        .or_else(|| connection_manager.connected_clients().next())
+    else {
+        return;
+    };
+    let Ok(connection) = connection_manager.connection(client_id) else {
+        return;
+    };
+
+    // rooms
+    for (room_id, bounds) in config.room_bounds.iter() {
+        let client_in_room = room_manager
+            .as_ref()
+            .is_some_and(|rooms| rooms.has_client_id(client_id, *room_id));
+        let color = if client_in_room {
+            config.client_room_color
+        } else {
+            config.room_color
+        };
+        gizmos.rect_2d(bounds.center(), 0.0, bounds.size(), color);
+    }
+
+    // entities
+    for (entity, transform, target, group, mode, cached_relevance) in query.iter() {
+        let position = transform.translation();
+        if !is_relevant(client_id, target, mode, cached_relevance) {
+            gizmos.circle(
+                position,
+                Dir3::Z,
+                config.entity_radius,
+                config.irrelevant_color,
+            );
+            continue;
+        }
+        gizmos.circle(
+            position,
+            Dir3::Z,
+            config.entity_radius,
+            config.relevant_color,
+        );
+        let group_id = group.group_id(Some(entity));
+        if let Some(channel) = connection.replication_sender.group_channels.get(&group_id) {
+            let top = position
+                + Vec3::Y
+                    * (config.entity_radius + channel.accumulated_priority * config.priority_scale);
+            gizmos.line(
+                position + Vec3::Y * config.entity_radius,
+                top,
+                config.priority_color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::NetworkTarget;
+    use crate::server::relevance::immediate::ClientRelevance;
+
+    #[test]
+    fn test_is_relevant() {
+        let client_id = ClientId::Netcode(1);
+        let other = ClientId::Netcode(2);
+        let target = ReplicationTarget {
+            target: NetworkTarget::Single(client_id),
+        };
+        assert!(is_relevant(client_id, &target, None, None));
+        assert!(!is_relevant(other, &target, None, None));
+
+        // with interest management, the entity must also be in the relevance cache of the client
+        let mode = NetworkRelevanceMode::InterestManagement;
+        assert!(!is_relevant(client_id, &target, Some(&mode), None));
+        let mut cache = CachedNetworkRelevance::default();
+        cache
+            .clients_cache
+            .insert(client_id, ClientRelevance::Maintained);
+        assert!(is_relevant(client_id, &target, Some(&mode), Some(&cache)));
+    }
+}
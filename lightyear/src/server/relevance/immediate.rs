@@ -192,6 +192,16 @@ pub(super) mod systems {
     /// - Relevance Lost gets removed from the cache
     pub fn update_cached_relevance(mut query: Query<(Entity, &mut CachedNetworkRelevance)>) {
         for (entity, mut replicate) in query.iter_mut() {
+            // avoid triggering change detection if there is nothing to update, so that the
+            // replication systems can skip archetypes that did not change
+            if replicate
+                .bypass_change_detection()
+                .clients_cache
+                .values()
+                .all(|relevance| matches!(relevance, ClientRelevance::Maintained))
+            {
+                continue;
+            }
             replicate
                 .clients_cache
                 .retain(|client_id, relevance| match relevance {
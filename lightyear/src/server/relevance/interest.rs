@@ -0,0 +1,403 @@
+/*! Client-initiated subscriptions to interest sets
+
+An interest set is a named set of [`Room`](super::room::Room)s that a client can request to join, for example
+to display a minimap or to spectate another player. The server registers each interest set with a
+validator system that decides if the client is allowed to subscribe, and which rooms it should be added to.
+
+Clients send their requests with [`ConnectionManager::subscribe`](crate::client::connection::ConnectionManager::subscribe)
+and receive an [`InterestResponse`] message once the server has handled the request.
+
+## Example
+
+```rust,ignore
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use lightyear::prelude::server::*;
+
+/// Let clients spectate any player, with requests like "spectate:42"
+fn spectate(In(request): In<InterestSetRequest>) -> Option<Vec<RoomId>> {
+    let player = request.argument?.parse::<u64>().ok()?;
+    Some(vec![RoomId(player)])
+}
+
+fn setup(app: &mut App) {
+    app.add_interest_set("spectate", spectate);
+}
+```
+*/
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use tracing::{error, warn};
+
+use crate::channel::builder::InterestChannel;
+use crate::connection::id::ClientId;
+use crate::prelude::server::is_started;
+use crate::server::connection::ConnectionManager;
+use crate::server::events::{DisconnectEvent, MessageEvent};
+use crate::server::relevance::room::{RoomId, RoomManager};
+use crate::shared::interest::{InterestRequest, InterestResponse};
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+
+/// Subscription request that is passed to the validator of an interest set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterestSetRequest {
+    /// The client that requested the subscription
+    pub client_id: ClientId,
+    /// The name of the interest set
+    pub set: String,
+    /// The argument of the request (the part after the `:` in "spectate:player42")
+    pub argument: Option<String>,
+}
+
+/// Resource that holds the interest sets defined by the server, and the subscriptions of each client
+#[derive(Resource, Default, Debug)]
+pub struct InterestSets {
+    validators: HashMap<String, SystemId<InterestSetRequest, Option<Vec<RoomId>>>>,
+    /// For each client, the rooms that were joined for each subscription.
+    ///
+    /// Rooms that the client was already in when it subscribed are not included.
+    subscriptions: HashMap<ClientId, HashMap<String, Vec<RoomId>>>,
+}
+
+impl InterestSets {
+    /// Returns true if the client is subscribed to the given subscription (e.g. "spectate:player42")
+    pub fn is_subscribed(&self, client_id: ClientId, name: &str) -> bool {
+        self.subscriptions
+            .get(&client_id)
+            .is_some_and(|subscriptions| subscriptions.contains_key(name))
+    }
+
+    /// Iterate over the subscriptions of a client
+    pub fn subscriptions(&self, client_id: ClientId) -> impl Iterator<Item = &str> {
+        self.subscriptions
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|subscriptions| subscriptions.keys().map(String::as_str))
+    }
+
+    /// Returns true if the client is in the room because of one of its subscriptions
+    fn joined_by_interest(&self, client_id: ClientId, room_id: RoomId) -> bool {
+        self.subscriptions
+            .get(&client_id)
+            .is_some_and(|subscriptions| {
+                subscriptions.values().any(|rooms| rooms.contains(&room_id))
+            })
+    }
+
+    /// Validate the request and add the client to the rooms of the interest set.
+    ///
+    /// Returns true if the client is subscribed.
+    fn subscribe(
+        &mut self,
+        world: &mut World,
+        client_id: ClientId,
+        request: &InterestRequest,
+    ) -> bool {
+        if self.is_subscribed(client_id, &request.name) {
+            return true;
+        }
+        let (set, argument) = request.parts();
+        let Some(validator) = self.validators.get(set).copied() else {
+            warn!(
+                ?client_id,
+                "Client requested a subscription to an unknown interest set: {set}"
+            );
+            return false;
+        };
+        let input = InterestSetRequest {
+            client_id,
+            set: set.to_string(),
+            argument: argument.map(str::to_string),
+        };
+        let rooms = match world.run_system_with_input(validator, input) {
+            Ok(Some(rooms)) => rooms,
+            Ok(None) => return false,
+            Err(e) => {
+                error!(
+                    "Error running the validator for interest set {set}: {:?}",
+                    e
+                );
+                return false;
+            }
+        };
+        let mut room_manager = world.resource_mut::<RoomManager>();
+        // only track the rooms that the client joins because of an interest request, so that unsubscribing
+        // doesn't remove the client from the rooms that the server added it to
+        let rooms: Vec<RoomId> = rooms
+            .into_iter()
+            .filter(|room_id| {
+                self.joined_by_interest(client_id, *room_id)
+                    || !room_manager.has_client_id(client_id, *room_id)
+            })
+            .collect();
+        for room_id in &rooms {
+            room_manager.add_client(client_id, *room_id);
+        }
+        self.subscriptions
+            .entry(client_id)
+            .or_default()
+            .insert(request.name.clone(), rooms);
+        true
+    }
+
+    /// Remove the client from the rooms that were joined for this subscription.
+    ///
+    /// The client stays in the rooms that it was already in before subscribing, or that another subscription uses.
+    fn unsubscribe(&mut self, world: &mut World, client_id: ClientId, name: &str) {
+        let Some(subscriptions) = self.subscriptions.get_mut(&client_id) else {
+            return;
+        };
+        let Some(rooms) = subscriptions.remove(name) else {
+            return;
+        };
+        let mut room_manager = world.resource_mut::<RoomManager>();
+        for room_id in rooms {
+            // keep the client in the room if another subscription also uses it
+            if !subscriptions.values().any(|rooms| rooms.contains(&room_id)) {
+                room_manager.remove_client(client_id, room_id);
+            }
+        }
+    }
+}
+
+pub trait AppInterestSetExt {
+    /// Define an interest set that clients can subscribe to.
+    ///
+    /// The validator is a system that takes the [`InterestSetRequest`] as input (via [`In`]), and returns
+    /// the rooms that the client should be added to, or `None` if the request is denied.
+    ///
+    /// Registering a new validator for the same interest set replaces the previous one.
+    fn add_interest_set<Marker>(
+        &mut self,
+        name: impl Into<String>,
+        validator: impl IntoSystem<InterestSetRequest, Option<Vec<RoomId>>, Marker> + 'static,
+    ) -> &mut Self;
+}
+
+impl AppInterestSetExt for App {
+    fn add_interest_set<Marker>(
+        &mut self,
+        name: impl Into<String>,
+        validator: impl IntoSystem<InterestSetRequest, Option<Vec<RoomId>>, Marker> + 'static,
+    ) -> &mut Self {
+        let system = self.world_mut().register_system(validator);
+        let previous = self
+            .world_mut()
+            .get_resource_or_insert_with(InterestSets::default)
+            .validators
+            .insert(name.into(), system);
+        if let Some(previous) = previous {
+            let _ = self.world_mut().remove_system(previous);
+        }
+        self
+    }
+}
+
+/// Plugin that handles the [`InterestRequest`]s sent by the clients
+#[derive(Default)]
+pub struct InterestPlugin;
+
+impl Plugin for InterestPlugin {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.init_resource::<InterestSets>();
+        // SYSTEMS
+        app.add_systems(
+            PreUpdate,
+            handle_interest_requests
+                .after(InternalMainSet::<ServerMarker>::EmitEvents)
+                .run_if(is_started),
+        );
+        app.observe(handle_client_disconnect);
+    }
+}
+
+/// Handle the subscription requests received from the clients, and send back the responses
+fn handle_interest_requests(world: &mut World) {
+    let Some(mut events) = world.get_resource_mut::<Events<MessageEvent<InterestRequest>>>() else {
+        return;
+    };
+    if events.is_empty() {
+        return;
+    }
+    let requests: Vec<_> = events.drain().collect();
+    world.resource_scope(|world, mut sets: Mut<InterestSets>| {
+        for event in requests {
+            let client_id = event.context;
+            let request = event.message;
+            let subscribed = if request.subscribe {
+                sets.subscribe(world, client_id, &request)
+            } else {
+                sets.unsubscribe(world, client_id, &request.name);
+                false
+            };
+            let _ = world
+                .resource_mut::<ConnectionManager>()
+                .send_message::<InterestChannel, _>(
+                    client_id,
+                    &mut InterestResponse {
+                        name: request.name,
+                        subscribed,
+                    },
+                )
+                .inspect_err(|e| error!("Error sending the interest response: {:?}", e));
+        }
+    });
+}
+
+/// Forget the subscriptions of clients that disconnected
+/// (the clients are removed from the rooms by the [`RoomManager`])
+fn handle_client_disconnect(trigger: Trigger<DisconnectEvent>, mut sets: ResMut<InterestSets>) {
+    sets.subscriptions.remove(&trigger.event().client_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    fn spectate(In(request): In<InterestSetRequest>) -> Option<Vec<RoomId>> {
+        let player = request.argument?.parse::<u64>().ok()?;
+        Some(vec![RoomId(player)])
+    }
+
+    fn responses(stepper: &mut BevyStepper) -> Vec<InterestResponse> {
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Events<client::MessageEvent<InterestResponse>>>()
+            .drain()
+            .map(|event| event.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_interest_subscription() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.add_interest_set("spectate", spectate);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        // valid request
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .subscribe("spectate:1")
+            .unwrap();
+        // invalid requests
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .subscribe("spectate:abc")
+            .unwrap();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .subscribe("minimap")
+            .unwrap();
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<RoomManager>()
+            .has_client_id(client_id, RoomId(1)));
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<InterestSets>()
+            .is_subscribed(client_id, "spectate:1"));
+        assert_eq!(
+            responses(&mut stepper),
+            vec![
+                InterestResponse {
+                    name: "spectate:1".to_string(),
+                    subscribed: true,
+                },
+                InterestResponse {
+                    name: "spectate:abc".to_string(),
+                    subscribed: false,
+                },
+                InterestResponse {
+                    name: "minimap".to_string(),
+                    subscribed: false,
+                },
+            ]
+        );
+
+        // unsubscribe
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .unsubscribe("spectate:1")
+            .unwrap();
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+        assert!(!stepper
+            .server_app
+            .world()
+            .resource::<RoomManager>()
+            .has_client_id(client_id, RoomId(1)));
+        assert_eq!(
+            responses(&mut stepper),
+            vec![InterestResponse {
+                name: "spectate:1".to_string(),
+                subscribed: false,
+            }]
+        );
+    }
+
+    /// Unsubscribing doesn't remove the client from the rooms that the server added it to
+    #[test]
+    fn test_unsubscribe_keeps_server_rooms() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.add_interest_set("spectate", spectate);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RoomManager>()
+            .add_client(client_id, RoomId(1));
+
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .subscribe("spectate:1")
+            .unwrap();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .subscribe("spectate:2")
+            .unwrap();
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .unsubscribe("spectate:1")
+            .unwrap();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .unsubscribe("spectate:2")
+            .unwrap();
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+        let room_manager = stepper.server_app.world().resource::<RoomManager>();
+        assert!(room_manager.has_client_id(client_id, RoomId(1)));
+        assert!(!room_manager.has_client_id(client_id, RoomId(2)));
+    }
+}
@@ -1,4 +1,7 @@
 pub mod immediate;
 
 pub mod error;
+#[cfg(feature = "gizmos")]
+pub mod gizmos;
+pub mod interest;
 pub mod room;
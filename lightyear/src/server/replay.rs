@@ -0,0 +1,261 @@
+//! Play a recorded [`Replay`] in the server World, so that it is re-broadcast to the connected clients.
+//!
+//! The replay is applied to the server World through a replication receiver, as if the server was a client of
+//! the recorded session. The entities that it spawns are replicated to the clients with [`Replicate::default()`],
+//! so real clients can watch the session (e.g. for e-sports spectating) or connect to it for debugging.
+//!
+//! The playback is controlled with the [`ServerReplay`] resource:
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::client::Replay;
+//! use lightyear::prelude::server::*;
+//!
+//! app.add_plugins(ServerReplayPlugin);
+//! app.insert_resource(ServerReplay::new(Replay::load("match.replay")?));
+//!
+//! fn controls(mut replay: ResMut<ServerReplay>) {
+//!     replay.set_speed(2.0);
+//!     replay.seek(Tick(1000));
+//!     replay.pause();
+//! }
+//! ```
+use bevy::prelude::*;
+use tracing::error;
+
+use crate::client::replay::{Replay, ReplayMessageKind};
+use crate::prelude::{ComponentRegistry, Tick, TickManager};
+use crate::serialize::reader::Reader;
+use crate::serialize::ToBytes;
+use crate::server::replication::send::Replicate;
+use crate::shared::events::connection::{ConnectionEvents, IterEntitySpawnEvent};
+use crate::shared::replication::receive::ReplicationReceiver;
+use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage};
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+
+/// Playback state of a [`Replay`] that is played in the server World
+#[derive(Resource)]
+pub struct ServerReplay {
+    replay: Replay,
+    receiver: ReplicationReceiver,
+    /// Index of the next frame to play
+    cursor: usize,
+    /// Tick of the recorded session that is currently being played
+    playback_tick: Option<Tick>,
+    /// Local tick during the previous playback update
+    last_local_tick: Option<Tick>,
+    /// Fraction of a tick that was not played yet, when the speed is not an integer
+    remainder: f32,
+    paused: bool,
+    speed: f32,
+    seek: Option<Tick>,
+}
+
+impl ServerReplay {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            receiver: ReplicationReceiver::new(),
+            cursor: 0,
+            playback_tick: None,
+            last_local_tick: None,
+            remainder: 0.0,
+            paused: false,
+            speed: 1.0,
+            seek: None,
+        }
+    }
+
+    /// Stop advancing the playback
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume the playback
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Set the number of recorded ticks that are played for each server tick (1.0 is real-time)
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Jump to the given tick of the recorded session.
+    ///
+    /// Seeking backwards despawns the entities of the replay and plays it again from the start,
+    /// up to the requested tick.
+    pub fn seek(&mut self, tick: Tick) {
+        self.seek = Some(tick);
+    }
+
+    /// The tick of the recorded session that is currently being played
+    pub fn playback_tick(&self) -> Option<Tick> {
+        self.playback_tick
+    }
+
+    /// Returns true if all the messages of the replay have been played
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.frames.len()
+    }
+
+    /// Despawn the entities of the replay and go back to the start of the recording
+    fn rewind(&mut self, world: &mut World) {
+        for entity in self.receiver.remote_entity_map.remote_to_local.values() {
+            if let Some(entity_mut) = world.get_entity_mut(*entity) {
+                entity_mut.despawn_recursive();
+            }
+        }
+        self.receiver = ReplicationReceiver::new();
+        self.cursor = 0;
+        self.playback_tick = None;
+    }
+
+    /// Feed the recorded messages up to the playback tick to the receiver, and apply them to the World
+    fn play(
+        &mut self,
+        world: &mut World,
+        registry: &ComponentRegistry,
+        events: &mut ConnectionEvents,
+    ) {
+        let Some(playback_tick) = self.playback_tick else {
+            return;
+        };
+        while let Some(frame) = self.replay.frames.get(self.cursor) {
+            if frame.tick > playback_tick {
+                break;
+            }
+            let mut reader = Reader::from(frame.data.clone());
+            let result = match frame.kind {
+                ReplayMessageKind::Actions => EntityActionsMessage::from_bytes(&mut reader)
+                    .map(|actions| self.receiver.recv_actions(actions, frame.tick)),
+                ReplayMessageKind::SequencedActions => {
+                    EntityActionsMessage::from_bytes(&mut reader)
+                        .map(|actions| self.receiver.recv_sequenced_actions(actions, frame.tick))
+                }
+                ReplayMessageKind::Updates => EntityUpdatesMessage::from_bytes(&mut reader)
+                    .map(|updates| self.receiver.recv_updates(updates, frame.tick)),
+            };
+            if let Err(e) = result {
+                error!(tick = ?frame.tick, "Could not read replay message: {:?}", e);
+            }
+            self.cursor += 1;
+            // apply each message separately, because only one actions message per group is applied at a time
+            self.receiver
+                .apply_world(world, None, registry, playback_tick, events);
+        }
+        self.receiver
+            .apply_world(world, None, registry, playback_tick, events);
+    }
+}
+
+/// Plugin that plays the [`ServerReplay`] resource in the server World, if it exists
+#[derive(Default)]
+pub struct ServerReplayPlugin;
+
+impl Plugin for ServerReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            play_server_replay
+                .before(InternalMainSet::<ServerMarker>::Send)
+                .run_if(resource_exists::<ServerReplay>),
+        );
+    }
+}
+
+fn play_server_replay(world: &mut World) {
+    let local_tick = world.resource::<TickManager>().tick();
+    world.resource_scope(|world, mut replay: Mut<ServerReplay>| {
+        let Some(first_tick) = replay.replay.frames.first().map(|frame| frame.tick) else {
+            return;
+        };
+        // advance the playback by the number of ticks that elapsed locally
+        let elapsed = replay
+            .last_local_tick
+            .map_or(0, |last_local_tick| local_tick - last_local_tick);
+        replay.last_local_tick = Some(local_tick);
+        if let Some(target) = replay.seek.take() {
+            if replay.playback_tick.is_some_and(|tick| target < tick) {
+                replay.rewind(world);
+            }
+            replay.playback_tick = Some(target);
+        } else if replay.playback_tick.is_none() {
+            replay.playback_tick = Some(first_tick);
+        } else if !replay.paused {
+            let ticks = elapsed as f32 * replay.speed + replay.remainder;
+            replay.remainder = ticks.fract();
+            replay.playback_tick = replay.playback_tick.map(|tick| tick + ticks.trunc() as i16);
+        }
+
+        world.resource_scope(|world, registry: Mut<ComponentRegistry>| {
+            let mut events = ConnectionEvents::default();
+            replay.play(world, &registry, &mut events);
+            // re-broadcast the entities of the replay to the clients
+            for (entity, _) in events.into_iter_entity_spawn() {
+                if let Some(mut entity_mut) = world.get_entity_mut(entity) {
+                    entity_mut.insert(Replicate::default());
+                }
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::replay::ReplayRecorder;
+    use crate::prelude::Replicated;
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_server_replay() {
+        // record a session on the client
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<ReplayRecorder>();
+        stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)));
+        stepper.frame_step();
+        stepper.frame_step();
+        let replay = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ReplayRecorder>()
+            .take();
+
+        // play it on another server
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.add_plugins(ServerReplayPlugin);
+        stepper
+            .server_app
+            .insert_resource(ServerReplay::new(replay));
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<ServerReplay>()
+            .is_finished());
+
+        // the entity of the replay is spawned on the server and re-broadcast to the client
+        let mut query = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<&ComponentSyncModeFull, With<Replicated>>();
+        assert_eq!(
+            query.single(stepper.client_app.world()),
+            &ComponentSyncModeFull(1.0)
+        );
+    }
+}
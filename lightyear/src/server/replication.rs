@@ -51,13 +51,17 @@ pub(crate) mod receive {
 
 pub(crate) mod send {
     use super::*;
+    use crate::channel::builder::EntityActionsChannel;
     use crate::prelude::{
         is_host_server, ClientId, ComponentRegistry, DisabledComponent, NetworkRelevanceMode,
         OverrideTargetComponent, ReplicateHierarchy, Replicated, ReplicationGroup,
         ShouldBePredicted, TargetEntity, Tick, TickManager, TimeManager,
     };
+    use crate::protocol::channel::ChannelKind;
     use crate::protocol::component::ComponentKind;
+    use crate::server::connection::ThrottledUpdate;
     use crate::server::error::ServerError;
+    use crate::server::ownership::{apply_ownership_transfers, OwnershipTransferEvent};
     use crate::server::prediction::handle_pre_predicted;
     use crate::server::relevance::immediate::{CachedNetworkRelevance, ClientRelevance};
     use crate::shared::replication::archetypes::{
@@ -65,10 +69,11 @@ pub(crate) mod send {
     };
     use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
     use crate::shared::replication::components::{
-        Cached, Controlled, Replicating, ReplicationGroupId, ReplicationTarget,
-        ShouldBeInterpolated,
+        AdditionalReplicationGroups, Cached, Controlled, Replicating, ReplicationGroupId,
+        ReplicationPaused, ReplicationTarget, ShouldBeInterpolated,
     };
     use crate::shared::replication::network_target::NetworkTarget;
+    use crate::shared::replication::tombstone::{prune_tombstones, record_tombstones};
     use crate::shared::replication::ReplicationSend;
     use bevy::ecs::component::ComponentTicks;
     use bevy::ecs::system::SystemChangeTick;
@@ -90,6 +95,8 @@ pub(crate) mod send {
             app
                 // REFLECTION
                 .register_type::<Replicate>()
+                // EVENTS
+                .add_event::<OwnershipTransferEvent>()
                 // PLUGIN
                 .add_plugins(ReplicationSendPlugin::<ConnectionManager>::new(
                     self.tick_interval,
@@ -123,9 +130,13 @@ pub(crate) mod send {
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferComponentUpdates),
                     (
                         handle_replication_target_update,
+                        handle_replication_group_update,
                         buffer_replication_messages,
                     )
                         .in_set(InternalReplicationSet::<ServerMarker>::AfterBuffer),
+                    prune_tombstones.in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
+                    apply_ownership_transfers
+                        .in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
                 ),
             );
             // HOST-SERVER
@@ -351,6 +362,33 @@ pub(crate) mod send {
         }
     }
 
+    /// Keep a cached version of the [`ReplicationGroupId`] of the entity so that we can detect
+    /// when the entity is moved to a different [`ReplicationGroup`].
+    ///
+    /// This needs to run after the `replicate` system runs
+    pub(crate) fn handle_replication_group_update(
+        mut commands: Commands,
+        mut query: Query<
+            (
+                Entity,
+                &ReplicationGroup,
+                Option<&mut Cached<ReplicationGroupId>>,
+            ),
+            Changed<ReplicationGroup>,
+        >,
+    ) {
+        for (entity, group, cached) in query.iter_mut() {
+            let group_id = group.group_id(Some(entity));
+            if let Some(mut cached) = cached {
+                if cached.value != group_id {
+                    cached.value = group_id;
+                }
+            } else {
+                commands.entity(entity).insert(Cached { value: group_id });
+            }
+        }
+    }
+
     /// Add HasAuthority component to a newly replicated entity if the server has
     /// authority over it
     fn add_has_authority_component(
@@ -374,6 +412,7 @@ pub(crate) mod send {
 
     pub(crate) fn replicate(
         tick_manager: Res<TickManager>,
+        server_config: Res<ServerConfig>,
         component_registry: Res<ComponentRegistry>,
         mut replicated_archetypes: Local<ServerReplicatedArchetypes>,
         system_ticks: SystemChangeTick,
@@ -384,9 +423,28 @@ pub(crate) mod send {
 
         let mut sender = std::mem::take(&mut *set.p1());
         let world = set.p0();
+        // the entities whose replication resumed send all their components again
+        let resumed_entities: Vec<Entity> = sender
+            .paused_entities
+            .iter()
+            .filter(|entity| {
+                world.get_entity(**entity).map_or(true, |entity_ref| {
+                    !entity_ref.contains::<ReplicationPaused>()
+                })
+            })
+            .copied()
+            .collect();
+        // we can only skip the archetypes that did not change since the last run if every change
+        // that happened before the last run has already been buffered
+        let skip_unchanged = sender.can_skip_unchanged_archetypes() && resumed_entities.is_empty();
+        resumed_entities.iter().for_each(|entity| {
+            sender.paused_entities.remove(entity);
+        });
+        // entities with shown components whose group is not sending this time
+        let mut deferred_shown_entities: Vec<Entity> = vec![];
 
         // 2. go through all the archetypes that should be replicated
-        for replicated_archetype in replicated_archetypes.archetypes.iter() {
+        for replicated_archetype in replicated_archetypes.archetypes.iter_mut() {
             // SAFETY: update() makes sure that we have a valid archetype
             let archetype = unsafe {
                 world
@@ -401,6 +459,22 @@ pub(crate) mod send {
                     .get(archetype.table_id())
                     .unwrap_unchecked()
             };
+            // SAFETY: we got the archetype and table from the replicated archetype's id
+            if skip_unchanged
+                && unsafe {
+                    replicated_archetype.is_unchanged(
+                        archetype,
+                        table,
+                        &world.storages().sparse_sets,
+                        system_ticks.last_run(),
+                        system_ticks.this_run(),
+                    )
+                }
+            {
+                continue;
+            }
+            replicated_archetype.pending = false;
+            replicated_archetype.len = archetype.len();
 
             // a. add all entity despawns from entities that were despawned locally
             // (done in a separate system)
@@ -414,7 +488,20 @@ pub(crate) mod send {
                 let group_id = group.map_or(ReplicationGroupId::default(), |g| {
                     g.group_id(Some(entity.id()))
                 });
+                // the entity was moved to a different replication group since the last replication pass
+                let previous_group_id = entity_ref
+                    .get::<Cached<ReplicationGroupId>>()
+                    .map(|cached| cached.value)
+                    .filter(|previous_group_id| *previous_group_id != group_id);
+                let group_changed = previous_group_id.is_some();
                 let priority = group.map_or(1.0, |g| g.priority());
+                let additional_groups = entity_ref
+                    .get::<AdditionalReplicationGroups>()
+                    .map_or(&[][..], |groups| groups.0.as_slice());
+                let actions_channel = group.map_or(
+                    ChannelKind::of::<EntityActionsChannel>(),
+                    ReplicationGroup::actions_channel,
+                );
                 let cached_replication_target = entity_ref.get::<Cached<ReplicationTarget>>();
                 let visibility = entity_ref.get::<CachedNetworkRelevance>();
                 let sync_target = entity_ref.get::<SyncTarget>();
@@ -465,6 +552,7 @@ pub(crate) mod send {
                     replicated,
                     group_id,
                     priority,
+                    actions_channel,
                     controlled_by,
                     sync_target,
                     target_entity,
@@ -474,12 +562,41 @@ pub(crate) mod send {
                     &system_ticks,
                 );
 
+                // d. move the entity to its new replication group
+                if let Some(previous_group_id) = previous_group_id {
+                    replicate_entity_group_change(
+                        entity.id(),
+                        group_id,
+                        previous_group_id,
+                        priority,
+                        actions_channel,
+                        &replication_target,
+                        authority_peer,
+                        visibility,
+                        &mut sender,
+                    );
+                }
+
                 // If the group is not set to send, skip sending updates for this entity
-                if group.is_some_and(|g| !g.should_send) {
+                // (unless the entity just changed group, in which case the components must be sent
+                // in the new group right away)
+                if !group_changed && group.is_some_and(|g| !g.should_send) {
+                    // the changes will have to be sent later, so the archetype cannot be skipped
+                    replicated_archetype.pending = true;
+                    if sender.shown_components.contains_key(&entity.id()) {
+                        deferred_shown_entities.push(entity.id());
+                    }
                     continue;
                 }
 
-                // d. all components that were added or changed
+                // e. all components that were added or changed
+                // (only the inserts are sent while the replication of the entity is paused)
+                let paused = entity_ref.contains::<ReplicationPaused>();
+                if paused {
+                    sender.paused_entities.insert(entity.id());
+                }
+                let resumed = resumed_entities.contains(&entity.id());
+                let mut throttle_pending = false;
                 for replicated_component in replicated_archetype.components.iter() {
                     let (data, component_ticks) = unsafe {
                         get_erased_component(
@@ -497,6 +614,33 @@ pub(crate) mod send {
                             // the OverrideTarget<C> component has the same memory layout as NetworkTarget
                             .map(|ptr| unsafe { ptr.deref::<NetworkTarget>() })
                     });
+                    let refresh = replicated_component
+                        .refresh_interval
+                        .is_some_and(|interval| {
+                            is_refresh_due(
+                                tick_manager.tick(),
+                                entity.id(),
+                                interval,
+                                tick_manager.config.tick_duration,
+                                server_config.replication.send_interval,
+                            )
+                        });
+                    // components with a replication interval only send their latest change
+                    // once the interval has elapsed
+                    let throttled = replicated_component.replication_interval.map(|interval| {
+                        let interval_ticks = (interval.as_nanos()
+                            / tick_manager.config.tick_duration.as_nanos().max(1))
+                        .min(i16::MAX as u128) as i16;
+                        sender.throttle_component_update(
+                            entity.id(),
+                            replicated_component.kind,
+                            component_ticks
+                                .is_changed(system_ticks.last_run(), system_ticks.this_run()),
+                            tick_manager.tick(),
+                            interval_ticks,
+                        )
+                    });
+                    throttle_pending |= throttled == Some(ThrottledUpdate::Pending);
 
                     replicate_component_updates(
                         tick_manager.tick(),
@@ -506,8 +650,13 @@ pub(crate) mod send {
                         data,
                         component_ticks,
                         &replication_target,
+                        cached_replication_target,
                         sync_target,
                         group_id,
+                        additional_groups,
+                        group_changed,
+                        refresh || resumed || throttled == Some(ThrottledUpdate::Send),
+                        paused || throttled == Some(ThrottledUpdate::Pending),
                         authority_peer,
                         visibility,
                         replicated_component.delta_compression,
@@ -517,11 +666,55 @@ pub(crate) mod send {
                         &mut sender,
                     );
                 }
+                // the pending updates will have to be sent later, so the archetype cannot be skipped
+                if throttle_pending {
+                    replicated_archetype.pending = true;
+                }
+                // the components that were shown again but were removed from the entity while they
+                // were hidden have to be removed on the clients
+                if let Some(kinds) = sender.shown_components.remove(&entity.id()) {
+                    if let Some(group) = group {
+                        for (kind, clients) in kinds {
+                            // the component is still present but was not sent (for example if it is
+                            // only replicated once)
+                            if world
+                                .components()
+                                .get_id(kind.0)
+                                .is_some_and(|id| entity_ref.contains_id(id))
+                            {
+                                continue;
+                            }
+                            let Some(net_id) = component_registry.kind_map.net_id(&kind).copied()
+                            else {
+                                continue;
+                            };
+                            let _ = sender
+                                .prepare_component_remove(
+                                    entity.id(),
+                                    net_id,
+                                    group,
+                                    NetworkTarget::Only(clients),
+                                )
+                                .inspect_err(|e| {
+                                    error!("error sending component remove: {:?}", e);
+                                });
+                        }
+                    }
+                }
 
-                // e. add all removed components
+                // f. add all removed components
             }
         }
 
+        // the shown components of entities that are not replicated anymore are forgotten, so that
+        // they don't prevent skipping the unchanged archetypes
+        sender
+            .shown_components
+            .retain(|entity, _| deferred_shown_entities.contains(entity));
+        sender
+            .connections
+            .values_mut()
+            .for_each(|connection| connection.replication_sender.send_ticks_rewound = false);
         *set.p1() = sender;
     }
 
@@ -538,6 +731,7 @@ pub(crate) mod send {
         replicated: Option<&Replicated>,
         group_id: ReplicationGroupId,
         priority: f32,
+        actions_channel: ChannelKind,
         controlled_by: Option<&ControlledBy>,
         sync_target: Option<&SyncTarget>,
         target_entity: Option<&TargetEntity>,
@@ -566,8 +760,14 @@ pub(crate) mod send {
                                 }
                                 ClientRelevance::Lost => {}
                                 ClientRelevance::Maintained => {
-                                    // only try to replicate if the replicate component was just added
-                                    if replication_target.is_added() {
+                                    // only try to replicate if the replicate component was just added,
+                                    // or if the client was just added to the replication target
+                                    if replication_target.is_added()
+                                        || (replication_target.is_changed()
+                                            && cached_replication_target.is_some_and(|cached| {
+                                                !cached.value.target.targets(client_id)
+                                            }))
+                                    {
                                         trace!(
                                             ?entity,
                                             ?client_id,
@@ -674,17 +874,22 @@ pub(crate) mod send {
                         .replication_sender
                         .prepare_entity_spawn_reuse(entity, group_id, *remote_entity);
                 } else {
-                    sender
-                        .connection_mut(client_id)?
+                    let connection = sender.connection_mut(client_id)?;
+                    // use the network id of the entity, in case it was remapped
+                    let network_entity = connection
+                        .replication_receiver
+                        .remote_entity_map
+                        .local_to_remote
+                        .network_entity(entity);
+                    connection
                         .replication_sender
-                        .prepare_entity_spawn(entity, group_id);
+                        .prepare_entity_spawn(network_entity, group_id);
                 }
 
-                // also set the priority for the group when we spawn it
-                sender
-                    .connection_mut(client_id)?
-                    .replication_sender
-                    .update_base_priority(group_id, priority);
+                // also set the priority and the actions channel for the group when we spawn it
+                let replication_sender = &mut sender.connection_mut(client_id)?.replication_sender;
+                replication_sender.update_base_priority(group_id, priority);
+                replication_sender.update_actions_channel(group_id, actions_channel);
                 Ok(())
             })
             .inspect_err(|e: &ServerError| {
@@ -710,6 +915,10 @@ pub(crate) mod send {
         mut sender: ResMut<ConnectionManager>,
     ) {
         let entity = trigger.entity();
+        sender.hidden_components.remove(&entity);
+        sender.shown_components.remove(&entity);
+        sender.throttled_components.remove(&entity);
+        sender.paused_entities.remove(&entity);
         if let Ok((replication_group, network_target, cached_relevance)) = query.get(entity) {
             trace!(?entity, "Replicate entity despawn");
             // only send the despawn to clients who were in the target of the entity
@@ -808,8 +1017,13 @@ pub(crate) mod send {
         component_data: Ptr,
         component_ticks: ComponentTicks,
         replication_target: &Ref<ReplicationTarget>,
+        cached_replication_target: Option<&Cached<ReplicationTarget>>,
         sync_target: Option<&SyncTarget>,
         group_id: ReplicationGroupId,
+        additional_groups: &[ReplicationGroupId],
+        group_changed: bool,
+        refresh: bool,
+        throttled: bool,
         authority_peer: Option<&AuthorityPeer>,
         visibility: Option<&CachedNetworkRelevance>,
         delta_compression: bool,
@@ -823,9 +1037,20 @@ pub(crate) mod send {
         let target = override_target.map_or(&replication_target.target, |override_target| {
             override_target
         });
-        // TODO: is this correct?
-        // to be safe, if the replication target is added, we force an insert
-        let force_insert = replication_target.is_changed();
+        // if the replication target is added, we force an insert.
+        // If it changed (for example from [1] to [1, 2]), we only force an insert for the clients
+        // that were added to the target
+        let force_insert_target = if replication_target.is_added() {
+            target.clone()
+        } else if replication_target.is_changed() {
+            let mut new_target = target.clone();
+            if let Some(cached_target) = cached_replication_target {
+                new_target.exclude(&cached_target.value.target);
+            }
+            new_target
+        } else {
+            NetworkTarget::None
+        };
         let (mut insert_target, mut update_target): (NetworkTarget, NetworkTarget) =
             match visibility {
                 Some(visibility) => {
@@ -846,7 +1071,7 @@ pub(crate) mod send {
                                         if component_ticks.is_added(
                                             system_ticks.last_run(),
                                             system_ticks.this_run(),
-                                        ) || force_insert
+                                        ) || force_insert_target.targets(client_id)
                                         {
                                             insert_clients.push(*client_id);
                                         } else {
@@ -878,23 +1103,26 @@ pub(crate) mod send {
                     //  Otherwise another solution would be to also insert the component on ComponentUpdate if it's missing
                     //  Or should we just have ComponentInsert and ComponentUpdate be the same thing? Or we check
                     //  on the receiver's entity world mut to know if we emit a ComponentInsert or a ComponentUpdate?
-                    if component_ticks.is_added(system_ticks.last_run(), system_ticks.this_run())
-                        || force_insert
-                    {
-                        trace!("component is added or replication_target is added");
+                    if component_ticks.is_added(system_ticks.last_run(), system_ticks.this_run()) {
+                        trace!("component is added");
                         insert_target.union(target);
                     } else {
+                        // the clients that were just added to the replication target need an insert
+                        insert_target.union(&force_insert_target);
                         // do not send updates for these components, only inserts/removes
                         if replicate_once {
-                            trace!(?entity,
-                                "not replicating updates for {:?} because it is marked as replicate_once",
-                                "COMPONENT_KIND"
-                            );
-                            return;
+                            if insert_target.is_empty() {
+                                trace!(?entity,
+                                    "not replicating updates for {:?} because it is marked as replicate_once",
+                                    "COMPONENT_KIND"
+                                );
+                                return;
+                            }
+                        } else {
+                            // otherwise send an update for all components that changed since the
+                            // last update we have ack-ed
+                            update_target.union(target);
                         }
-                        // otherwise send an update for all components that changed since the
-                        // last update we have ack-ed
-                        update_target.union(target);
                     }
 
                     let new_connected_clients = sender.new_connected_clients();
@@ -910,14 +1138,59 @@ pub(crate) mod send {
                 }
             };
 
+        // do not send the component to clients from which it is hidden
+        if let Some(hidden) = sender
+            .hidden_components
+            .get(&entity)
+            .and_then(|kinds| kinds.get(&component_kind))
+        {
+            let hidden = NetworkTarget::Only(hidden.clone());
+            insert_target.exclude(&hidden);
+            update_target.exclude(&hidden);
+        }
+        // send the component again to clients from which it is not hidden anymore
+        if let Some(kinds) = sender.shown_components.get_mut(&entity) {
+            if let Some(shown) = kinds.remove(&component_kind) {
+                if kinds.is_empty() {
+                    sender.shown_components.remove(&entity);
+                }
+                let mut shown = NetworkTarget::Only(shown);
+                shown.intersection(target);
+                if let Some(visibility) = visibility {
+                    shown.intersection(&NetworkTarget::Only(
+                        visibility
+                            .clients_cache
+                            .iter()
+                            .filter(|(_, relevance)| **relevance != ClientRelevance::Lost)
+                            .map(|(client_id, _)| *client_id)
+                            .collect(),
+                    ));
+                }
+                insert_target.union(&shown);
+            }
+        }
+
         // we don't send messages to the client that has authority
         if let Some(AuthorityPeer::Client(c)) = authority_peer {
             insert_target.exclude(&NetworkTarget::Single(*c));
             update_target.exclude(&NetworkTarget::Single(*c));
         }
+        // do not send the component to clients that don't have its protocol extension
+        if let Some(excluded) = component_registry
+            .kind_map
+            .net_id(&component_kind)
+            .and_then(|net_id| sender.clients_without_net_id(*net_id))
+        {
+            insert_target.exclude(&excluded);
+            update_target.exclude(&excluded);
+        }
 
         // do not send a component as both update and insert
         update_target.exclude(&insert_target);
+        // the update of a throttled component is sent later, once its replication interval has elapsed
+        if throttled {
+            update_target = NetworkTarget::None;
+        }
 
         if !insert_target.is_empty() || !update_target.is_empty() {
             if !insert_target.is_empty() {
@@ -938,26 +1211,114 @@ pub(crate) mod send {
                     });
             }
             if !update_target.is_empty() {
-                let _ = sender
-                    .prepare_component_update(
-                        entity,
-                        component_kind,
-                        component_data,
-                        component_registry,
-                        group_id,
-                        update_target,
-                        component_ticks.last_changed_tick(),
-                        system_ticks.this_run(),
-                        current_tick,
-                        delta_compression,
-                    )
-                    .inspect_err(|e| {
-                        error!("error sending component update: {:?}", e);
-                    });
+                // the updates are also sent through the additional groups of the entity, at most once per group
+                let additional_groups = additional_groups
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, g)| **g != group_id && !additional_groups[..*j].contains(g))
+                    .map(|(_, g)| *g);
+                for (i, group_id) in std::iter::once(group_id)
+                    .chain(additional_groups)
+                    .enumerate()
+                {
+                    // if the entity just moved to a new group, send all the components in that group.
+                    // Components that are due for a periodic refresh are sent even if they did not change
+                    let change_tick = if (group_changed && i == 0) || refresh {
+                        system_ticks.this_run()
+                    } else {
+                        component_ticks.last_changed_tick()
+                    };
+                    let _ = sender
+                        .prepare_component_update(
+                            entity,
+                            component_kind,
+                            component_data,
+                            component_registry,
+                            group_id,
+                            update_target.clone(),
+                            change_tick,
+                            system_ticks.this_run(),
+                            current_tick,
+                            delta_compression,
+                        )
+                        .inspect_err(|e| {
+                            error!("error sending component update: {:?}", e);
+                        });
+                }
             }
         }
     }
 
+    /// Returns true if a component that is refreshed every `interval` should be sent again during
+    /// this replication pass.
+    ///
+    /// The replication pass only runs every `send_interval`, so the refresh is due if the current tick
+    /// falls within one send interval of a refresh tick. The refreshes are staggered across entities
+    /// so that they are not all sent on the same tick.
+    pub(crate) fn is_refresh_due(
+        tick: Tick,
+        entity: Entity,
+        interval: Duration,
+        tick_duration: Duration,
+        send_interval: Duration,
+    ) -> bool {
+        let tick_nanos = tick_duration.as_nanos().max(1);
+        let interval_ticks = (interval.as_nanos() / tick_nanos).max(1) as u32;
+        let window = (send_interval.as_nanos() / tick_nanos).max(1) as u32;
+        (tick.0 as u32 + entity.index()) % interval_ticks < window
+    }
+
+    /// Send an entity actions message in the new [`ReplicationGroup`] of an entity that
+    /// changed group, so that the clients move the entity to that group.
+    ///
+    /// The entity keeps its existing components on the client; they are sent again in the new group.
+    pub(crate) fn replicate_entity_group_change(
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        previous_group_id: ReplicationGroupId,
+        priority: f32,
+        actions_channel: ChannelKind,
+        replication_target: &ReplicationTarget,
+        authority_peer: Option<&AuthorityPeer>,
+        visibility: Option<&CachedNetworkRelevance>,
+        sender: &mut ConnectionManager,
+    ) {
+        let mut target = replication_target.target.clone();
+        if let Some(visibility) = visibility {
+            target.intersection(&NetworkTarget::Only(
+                visibility
+                    .clients_cache
+                    .iter()
+                    .filter(|(_, relevance)| **relevance == ClientRelevance::Maintained)
+                    .map(|(client_id, _)| *client_id)
+                    .collect(),
+            ));
+        }
+        // we don't send messages to the client that has authority
+        if let Some(AuthorityPeer::Client(c)) = authority_peer {
+            target.exclude(&NetworkTarget::Single(*c));
+        }
+        debug!(
+            ?entity,
+            ?previous_group_id,
+            ?group_id,
+            ?target,
+            "Moving entity to a new replication group"
+        );
+        let _ = sender
+            .connected_targets(target)
+            .try_for_each(|client_id| {
+                let replication_sender = &mut sender.connection_mut(client_id)?.replication_sender;
+                replication_sender.prepare_entity_group_change(entity, group_id, previous_group_id);
+                replication_sender.update_base_priority(group_id, priority);
+                replication_sender.update_actions_channel(group_id, actions_channel);
+                Ok::<(), ServerError>(())
+            })
+            .inspect_err(|e| {
+                error!("error sending entity group change: {:?}", e);
+            });
+    }
+
     /// This system sends updates for all components that were removed
     pub(crate) fn send_component_removed<C: Component>(
         registry: Res<ComponentRegistry>,
@@ -1022,6 +1383,15 @@ pub(crate) mod send {
                 if let Some(AuthorityPeer::Client(c)) = authority_peer {
                     target.exclude(&NetworkTarget::Single(*c));
                 }
+                // the clients from which the component is hidden will receive the removal when the
+                // component is shown again
+                if let Some(hidden) = sender
+                    .hidden_components
+                    .get(&entity)
+                    .and_then(|kinds| kinds.get(&ComponentKind::of::<C>()))
+                {
+                    target.exclude(&NetworkTarget::Only(hidden.clone()));
+                }
                 if target.is_empty() {
                     return;
                 }
@@ -1049,6 +1419,17 @@ pub(crate) mod send {
         );
     }
 
+    /// Register the systems that record the [`ComponentTombstones`](crate::prelude::ComponentTombstones)
+    /// of the component `C`
+    pub(crate) fn register_tombstones<C: Component>(app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            // NOTE: this needs to run every frame because it relies on RemovedComponents
+            record_tombstones::<C>
+                .in_set(InternalReplicationSet::<ServerMarker>::BufferDespawnsAndRemovals),
+        );
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -1060,12 +1441,17 @@ pub(crate) mod send {
             Replicated,
         };
         use crate::server::replication::send::SyncTarget;
-        use crate::shared::replication::components::{Controlled, ReplicationGroupId};
+        use crate::shared::events::connection::ConnectionEvents;
+        use crate::shared::replication::capture::CapturedMessageKind;
+        use crate::shared::replication::components::{
+            AdditionalReplicationGroups, Controlled, ReplicationGroupId,
+        };
         use crate::shared::replication::delta::DeltaComponentHistory;
         use crate::shared::replication::systems;
         use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
         use crate::tests::protocol::*;
         use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+        use bevy::ecs::component::Tick as BevyTick;
         use bevy::ecs::system::RunSystemOnce;
         use bevy::prelude::{default, EventReader, Resource, Update};
         use bevy::utils::HashSet;
@@ -1312,6 +1698,93 @@ pub(crate) mod send {
             // TODO: check that client 1 did not receive another entity-spawn message
         }
 
+        /// Check that adding or removing a single client from the replication target only
+        /// sends messages to that client
+        #[test]
+        fn test_replication_target_add_remove_target() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    ComponentSyncModeFull(1.0),
+                    Replicate {
+                        target: ReplicationTarget {
+                            target: NetworkTarget::Single(client_1),
+                        },
+                        ..default()
+                    },
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .capture_replication(client_1)
+                .unwrap();
+
+            // add client 2 to the target
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ReplicationTarget>(server_entity)
+                .unwrap()
+                .add_target(client_2);
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity_2 = stepper
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 2");
+            assert_eq!(
+                stepper
+                    .client_app_2
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity_2)
+                    .unwrap(),
+                &ComponentSyncModeFull(1.0)
+            );
+            // client 1 did not receive any new message
+            assert!(stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .take_captured_replication(client_1)
+                .unwrap()
+                .is_empty());
+
+            // remove client 2 from the target
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ReplicationTarget>(server_entity)
+                .unwrap()
+                .remove_target(client_2);
+            stepper.frame_step();
+            stepper.frame_step();
+            assert!(stepper
+                .client_app_2
+                .world()
+                .get_entity(client_entity_2)
+                .is_none());
+            assert!(stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .take_captured_replication(client_1)
+                .unwrap()
+                .is_empty());
+        }
+
         #[test]
         fn test_entity_despawn() {
             let mut stepper = BevyStepper::default();
@@ -1972,80 +2445,800 @@ pub(crate) mod send {
             );
         }
 
-        /// Test that replicating updates works even if the update happens after tick wrapping
+        /// Test that the updates are not sent while the replication of the entity is paused,
+        /// and that the latest state is sent when it resumes
         #[test]
-        fn test_component_update_after_tick_wrap() {
+        fn test_component_update_paused() {
             let mut stepper = BevyStepper::default();
 
             let server_entity = stepper
                 .server_app
                 .world_mut()
-                .spawn((ComponentSyncModeFull(0.0), Replicate::default()))
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
                 .id();
-
-            // replicate to client
             stepper.frame_step();
             stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
 
-            // we increase the ticks in 2 steps (otherwise we would directly go over tick wrapping)
-            let tick_delta = (u16::MAX / 3 + 10) as i16;
-            stepper.set_client_tick(stepper.client_tick() + tick_delta);
-            stepper.set_server_tick(stepper.server_tick() + tick_delta);
-
+            // pause the replication and update the component
             stepper
                 .server_app
                 .world_mut()
-                .run_system_once(systems::send_cleanup::<server::ConnectionManager>);
-            stepper
-                .client_app
-                .world_mut()
-                .run_system_once(systems::receive_cleanup::<client::ConnectionManager>);
+                .entity_mut(server_entity)
+                .insert((ReplicationPaused, ComponentSyncModeFull(2.0)));
+            stepper.frame_step();
+            stepper.frame_step();
 
-            stepper.set_client_tick(stepper.client_tick() + tick_delta);
-            stepper.set_server_tick(stepper.server_tick() + tick_delta);
+            // the entity is still on the client, but the update was not sent
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .entity(client_entity)
+                    .get::<ComponentSyncModeFull>()
+                    .expect("component missing"),
+                &ComponentSyncModeFull(1.0)
+            );
 
-            // update the component on the server
+            // resume the replication: the current state is sent
             stepper
                 .server_app
                 .world_mut()
                 .entity_mut(server_entity)
-                .insert(ComponentSyncModeFull(1.0));
-
-            // make sure the client receives the replication message
+                .remove::<ReplicationPaused>();
             stepper.frame_step();
             stepper.frame_step();
-
-            let client_entity = stepper
-                .client_app
-                .world()
-                .resource::<client::ConnectionManager>()
-                .replication_receiver
-                .remote_entity_map
-                .get_local(server_entity)
-                .unwrap();
-            // check that the component got updated
             assert_eq!(
                 stepper
                     .client_app
                     .world()
-                    .get::<ComponentSyncModeFull>(client_entity)
-                    .unwrap(),
-                &ComponentSyncModeFull(1.0)
+                    .entity(client_entity)
+                    .get::<ComponentSyncModeFull>()
+                    .expect("component missing"),
+                &ComponentSyncModeFull(2.0)
             );
         }
 
+        /// Test that a custom [`ReplicationSendStrategy`] can replicate all the components in every pass
         #[test]
-        fn test_component_update_send_frequency() {
-            let mut stepper = BevyStepper::default();
+        fn test_custom_replication_strategy() {
+            use crate::shared::replication::strategy::{
+                ReplicationSendStrategy, ReplicationSender,
+            };
 
-            // spawn an entity on server
+            #[derive(Debug)]
+            struct FullSnapshot;
+
+            impl ReplicationSendStrategy for FullSnapshot {
+                fn gather_all(&self, _: &ReplicationSender) -> bool {
+                    true
+                }
+            }
+
+            let mut stepper = BevyStepper::default();
+            let client_id = ClientId::Netcode(TEST_CLIENT_ID);
             let server_entity = stepper
                 .server_app
                 .world_mut()
-                .spawn((
-                    Replicate {
-                        // replicate every 4 ticks
-                        group: ReplicationGroup::new_from_entity()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .capture_replication(client_id)
+                .unwrap();
+
+            // by default, the component is not sent again if it doesn't change
+            stepper.frame_step();
+            stepper.frame_step();
+            assert!(stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .take_captured_replication(client_id)
+                .unwrap()
+                .is_empty());
+
+            // the component is sent in every replication pass
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .set_replication_strategy(FullSnapshot);
+            stepper.frame_step();
+            stepper.frame_step();
+            let captured = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .take_captured_replication(client_id)
+                .unwrap();
+            let registry = stepper.server_app.world().resource::<ComponentRegistry>();
+            assert_eq!(captured.len(), 2);
+            assert!(captured.iter().all(|message| {
+                message.updated::<ComponentSyncModeFull>(server_entity, registry)
+                    == Some(ComponentSyncModeFull(1.0))
+            }));
+        }
+
+        /// Test that the updates of an entity are also sent through its additional groups
+        #[test]
+        fn test_component_update_additional_groups() {
+            let mut stepper = BevyStepper::default();
+            let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+            let match_group = ReplicationGroupId(100);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ComponentSyncModeFull(1.0),
+                    // the duplicate group id is only sent once
+                    AdditionalReplicationGroups(vec![match_group, match_group]),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .capture_replication(client_id)
+                .unwrap();
+
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ComponentSyncModeFull(2.0));
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // the update is sent through the main group and through the additional group
+            let captured = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .take_captured_replication(client_id)
+                .unwrap();
+            let update_groups: HashSet<_> = captured
+                .iter()
+                .filter(|m| m.kind == CapturedMessageKind::Updates)
+                .map(|m| m.group_id)
+                .collect();
+            assert_eq!(
+                update_groups,
+                HashSet::from_iter([ReplicationGroupId(server_entity.to_bits()), match_group])
+            );
+            let match_group_updates: usize = captured
+                .iter()
+                .filter(|m| m.kind == CapturedMessageKind::Updates && m.group_id == match_group)
+                .flat_map(|m| m.entities.iter())
+                .map(|(_, actions)| actions.updates.len())
+                .sum();
+            assert_eq!(match_group_updates, 1);
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeFull(2.0)
+            );
+        }
+
+        /// Test that an entity can be moved to a different replication group without being respawned
+        #[test]
+        fn test_change_replication_group() {
+            let mut stepper = BevyStepper::default();
+            let new_group = ReplicationGroupId(100);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // move the entity to another group
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ReplicationGroup::default().set_id(new_group.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            let receiver = &stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver;
+            assert_eq!(
+                receiver.remote_entity_to_group.get(&server_entity),
+                Some(&new_group)
+            );
+            assert_eq!(
+                receiver.remote_entity_map.get_local(server_entity),
+                Some(client_entity)
+            );
+
+            // updates are now received through the new group
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ComponentSyncModeFull(2.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeFull(2.0)
+            );
+
+            // despawning the previous group does not despawn the entity
+            stepper.client_app.world_mut().resource_scope(
+                |world, mut manager: Mut<client::ConnectionManager>| {
+                    let mut events = ConnectionEvents::default();
+                    manager.replication_receiver.despawn_group(
+                        ReplicationGroupId(server_entity.to_bits()),
+                        crate::packet::message::MessageId(0),
+                        Tick(0),
+                        world,
+                        &mut events,
+                    );
+                },
+            );
+            assert!(stepper
+                .client_app
+                .world()
+                .get_entity(client_entity)
+                .is_some());
+        }
+
+        /// Test that a component with a refresh interval is sent again even if it did not change,
+        /// so that the client recovers from a diverging value
+        #[test]
+        fn test_component_refresh_interval() {
+            let mut stepper = BevyStepper::default();
+            let interval = Duration::from_millis(100);
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<crate::prelude::ComponentRegistry>()
+                .set_refresh_interval::<ComponentSyncModeSimple>(interval);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeSimple(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // the client value diverges from the server value (for example because an update was lost)
+            stepper
+                .client_app
+                .world_mut()
+                .entity_mut(client_entity)
+                .insert(ComponentSyncModeSimple(5.0));
+
+            // the component is sent again even though it did not change on the server
+            let mut elapsed = Duration::default();
+            while elapsed <= interval * 2 {
+                stepper.frame_step();
+                elapsed += stepper.frame_duration;
+            }
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeSimple>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeSimple(1.0)
+            );
+        }
+
+        /// Test that the updates of a component with a replication interval are sent at most
+        /// once per interval, and that the latest change is sent once the interval has elapsed
+        #[test]
+        fn test_component_replication_interval() {
+            let mut stepper = BevyStepper::default();
+            let interval = Duration::from_millis(200);
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<crate::prelude::ComponentRegistry>()
+                .set_replication_interval::<ComponentSyncModeFull>(interval);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = *stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // the first update is sent right away
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0 = 2.0;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeFull(2.0)
+            );
+
+            // the next update is held back until the interval has elapsed
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0 = 3.0;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeFull(2.0)
+            );
+
+            let mut elapsed = Duration::default();
+            while elapsed <= interval {
+                stepper.frame_step();
+                elapsed += stepper.frame_duration;
+            }
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeFull(3.0)
+            );
+        }
+
+        /// Test that updates are still replicated after the archetype was skipped
+        /// because it did not change for a while
+        #[test]
+        fn test_component_update_unchanged_archetype() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            // the archetype is unchanged during these frames
+            for _ in 0..5 {
+                stepper.frame_step();
+            }
+            let client_entity = *stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // update the component
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0 = 2.0;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .expect("component missing"),
+                &ComponentSyncModeFull(2.0)
+            );
+
+            // spawn a new entity in the same archetype
+            let new_server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(3.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let new_client_entity = *stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(new_server_entity)
+                .expect("entity was not replicated to client");
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(new_client_entity)
+                    .expect("component missing"),
+                &ComponentSyncModeFull(3.0)
+            );
+        }
+
+        /// Check that an archetype is not skipped if the replication target, the override target of a component
+        /// or the network relevance of one of its entities changed
+        #[test]
+        fn test_unchanged_archetype_metadata_changes() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate {
+                        relevance_mode: NetworkRelevanceMode::InterestManagement,
+                        ..default()
+                    },
+                    ComponentSyncModeFull(1.0),
+                    OverrideTargetComponent::<ComponentSyncModeFull>::new(NetworkTarget::All),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let world = stepper.server_app.world_mut();
+            let mut replicated_archetypes = ServerReplicatedArchetypes::server(world);
+            world.resource_scope(|world, registry: Mut<ComponentRegistry>| {
+                replicated_archetypes.update(world, &registry);
+            });
+            let location = world.entity(server_entity).location();
+            let replicated_archetype = replicated_archetypes
+                .archetypes
+                .iter_mut()
+                .find(|archetype| archetype.id == location.archetype_id)
+                .unwrap();
+            replicated_archetype.pending = false;
+            replicated_archetype.len = world.archetypes().get(location.archetype_id).unwrap().len();
+
+            let is_unchanged = |world: &World, last_run: BevyTick| {
+                let this_run = world.change_tick();
+                let archetype = world.archetypes().get(location.archetype_id).unwrap();
+                let table = world.storages().tables.get(location.table_id).unwrap();
+                // SAFETY: the archetype and the table are the ones of the replicated archetype
+                unsafe {
+                    replicated_archetype.is_unchanged(
+                        archetype,
+                        table,
+                        &world.storages().sparse_sets,
+                        last_run,
+                        this_run,
+                    )
+                }
+            };
+
+            let last_run = world.increment_change_tick();
+            assert!(is_unchanged(world, last_run));
+
+            world
+                .get_mut::<ReplicationTarget>(server_entity)
+                .unwrap()
+                .set_changed();
+            assert!(!is_unchanged(world, last_run));
+
+            let last_run = world.increment_change_tick();
+            world
+                .get_mut::<OverrideTargetComponent<ComponentSyncModeFull>>(server_entity)
+                .unwrap()
+                .set_changed();
+            assert!(!is_unchanged(world, last_run));
+
+            let last_run = world.increment_change_tick();
+            world
+                .get_mut::<CachedNetworkRelevance>(server_entity)
+                .unwrap()
+                .set_changed();
+            assert!(!is_unchanged(world, last_run));
+        }
+
+        /// Check that a component can be hidden from a single client
+        #[test]
+        fn test_hide_component() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_id_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity_1 = *stepper
+                .client_app_1
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 1");
+            let client_entity_2 = *stepper
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 2");
+
+            // hide the component from client 1 and update it
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .hide_component::<ComponentSyncModeFull>(client_id_1, server_entity)
+                .unwrap();
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0 = 2.0;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app_1
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity_1)
+                    .unwrap(),
+                &ComponentSyncModeFull(1.0)
+            );
+            assert_eq!(
+                stepper
+                    .client_app_2
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity_2)
+                    .unwrap(),
+                &ComponentSyncModeFull(2.0)
+            );
+
+            // show the component again: the current value is sent even if it didn't change
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .show_component::<ComponentSyncModeFull>(client_id_1, server_entity)
+                .unwrap();
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app_1
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity_1)
+                    .unwrap(),
+                &ComponentSyncModeFull(2.0)
+            );
+            assert!(stepper
+                .server_app
+                .world()
+                .resource::<ConnectionManager>()
+                .shown_components
+                .is_empty());
+        }
+
+        /// Check that the removal of a hidden component is only sent to the client once the
+        /// component is shown again
+        #[test]
+        fn test_hide_component_removal() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_id_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity_1 = *stepper
+                .client_app_1
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 1");
+            let client_entity_2 = *stepper
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 2");
+
+            // hide the component from client 1 and remove it
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .hide_component::<ComponentSyncModeFull>(client_id_1, server_entity)
+                .unwrap();
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .remove::<ComponentSyncModeFull>();
+            stepper.frame_step();
+            stepper.frame_step();
+            assert!(stepper
+                .client_app_1
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity_1)
+                .is_some());
+            assert!(stepper
+                .client_app_2
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity_2)
+                .is_none());
+
+            // show the component again: the removal is sent
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .show_component::<ComponentSyncModeFull>(client_id_1, server_entity)
+                .unwrap();
+            stepper.frame_step();
+            stepper.frame_step();
+            assert!(stepper
+                .client_app_1
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity_1)
+                .is_none());
+            assert!(stepper
+                .server_app
+                .world()
+                .resource::<ConnectionManager>()
+                .shown_components
+                .is_empty());
+        }
+
+        /// Test that replicating updates works even if the update happens after tick wrapping
+        #[test]
+        fn test_component_update_after_tick_wrap() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((ComponentSyncModeFull(0.0), Replicate::default()))
+                .id();
+
+            // replicate to client
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // we increase the ticks in 2 steps (otherwise we would directly go over tick wrapping)
+            let tick_delta = (u16::MAX / 3 + 10) as i16;
+            stepper.set_client_tick(stepper.client_tick() + tick_delta);
+            stepper.set_server_tick(stepper.server_tick() + tick_delta);
+
+            stepper
+                .server_app
+                .world_mut()
+                .run_system_once(systems::send_cleanup::<server::ConnectionManager>);
+            stepper
+                .client_app
+                .world_mut()
+                .run_system_once(systems::receive_cleanup::<client::ConnectionManager>);
+
+            stepper.set_client_tick(stepper.client_tick() + tick_delta);
+            stepper.set_server_tick(stepper.server_tick() + tick_delta);
+
+            // update the component on the server
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ComponentSyncModeFull(1.0));
+
+            // make sure the client receives the replication message
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .unwrap();
+            // check that the component got updated
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap(),
+                &ComponentSyncModeFull(1.0)
+            );
+        }
+
+        #[test]
+        fn test_component_update_send_frequency() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate {
+                        // replicate every 4 ticks
+                        group: ReplicationGroup::new_from_entity()
                             .set_send_frequency(Duration::from_millis(40)),
                         ..default()
                     },
@@ -2238,6 +3431,58 @@ pub(crate) mod send {
                 .is_none());
         }
 
+        /// Check that the partial updates of a component deriving `DeltaReplicate` are merged on the client
+        #[test]
+        fn test_component_update_delta_fields() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ComponentDeltaFields {
+                        health: 100,
+                        name: "player".to_string(),
+                        position: (1.0, 2.0),
+                    },
+                    DeltaCompression::<ComponentDeltaFields>::default(),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // only update one field
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentDeltaFields>(server_entity)
+                .unwrap()
+                .health = 50;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentDeltaFields>(client_entity)
+                    .expect("component missing"),
+                &ComponentDeltaFields {
+                    health: 50,
+                    name: "player".to_string(),
+                    position: (1.0, 2.0),
+                }
+            );
+        }
+
         /// One component is delta, the other is not
         /// This fails to work if we don't have an ack tick specific to the delta component
         #[test]
@@ -3090,11 +4335,16 @@ pub(crate) mod send {
 }
 
 pub(crate) mod commands {
-    use crate::channel::builder::AuthorityChannel;
-    use crate::prelude::{Replicating, ServerConnectionManager};
+    use crate::channel::builder::{AuthorityChannel, DespawnGroupsChannel};
+    use crate::prelude::server::{RoomId, RoomManager};
+    use crate::prelude::{Replicating, ReplicationGroup, ServerConnectionManager, TickManager};
     use crate::shared::replication::authority::{AuthorityChange, AuthorityPeer, HasAuthority};
+    use crate::shared::replication::components::ReplicationGroupId;
+    use crate::shared::replication::DespawnGroupsMessage;
     use bevy::ecs::system::EntityCommands;
-    use bevy::prelude::{Entity, World};
+    use bevy::prelude::{Commands, Entity, With, World};
+    use bevy::utils::{HashMap, HashSet};
+    use tracing::error;
 
     pub trait AuthorityCommandExt {
         /// This command is used to transfer the authority of an entity to a different peer.
@@ -3237,9 +4487,120 @@ pub(crate) mod commands {
         }
     }
 
+    fn despawn_replication_groups(world: &mut World, group_ids: Vec<ReplicationGroupId>) {
+        let groups: HashSet<ReplicationGroupId> = group_ids.iter().copied().collect();
+        let entities: Vec<Entity> = world
+            .query_filtered::<(Entity, &ReplicationGroup), With<Replicating>>()
+            .iter(world)
+            .filter(|(entity, group)| groups.contains(&group.group_id(Some(*entity))))
+            .map(|(entity, _)| entity)
+            .collect();
+        // the entities are despawned without replication, the despawn is replicated
+        // for the entire group at once instead
+        for entity in entities {
+            despawn_without_replication(entity, world);
+        }
+        let tick = world.resource::<TickManager>().tick();
+        let mut connection_manager = world.resource_mut::<ServerConnectionManager>();
+        let client_ids: Vec<_> = connection_manager.connected_clients().collect();
+        for client_id in client_ids {
+            let Ok(connection) = connection_manager.connection(client_id) else {
+                continue;
+            };
+            // the actions that were already sent for these groups must not be applied after the despawn
+            let groups: Vec<_> = group_ids
+                .iter()
+                .filter_map(|group_id| {
+                    connection
+                        .replication_sender
+                        .group_channels
+                        .get(group_id)
+                        .map(|channel| (*group_id, channel.actions_next_send_message_id))
+                })
+                .collect();
+            if groups.is_empty() {
+                continue;
+            }
+            let _ = connection_manager
+                .send_message::<DespawnGroupsChannel, _>(
+                    client_id,
+                    &mut DespawnGroupsMessage { groups, tick },
+                )
+                .inspect_err(|e| {
+                    error!(
+                        ?client_id,
+                        "error sending replication group despawn: {:?}", e
+                    );
+                });
+        }
+    }
+
+    fn despawn_room_entities(world: &mut World, room_id: RoomId) {
+        let Some(room_entities) = world
+            .get_resource::<RoomManager>()
+            .and_then(|manager| manager.get_room(room_id))
+            .map(|room| room.entities.clone())
+        else {
+            error!(
+                ?room_id,
+                "cannot despawn the entities of a room that does not exist"
+            );
+            return;
+        };
+        // only the groups whose entities are all in the room can be despawned with a single message
+        let mut groups: HashMap<ReplicationGroupId, bool> = HashMap::default();
+        for (entity, group) in world
+            .query_filtered::<(Entity, &ReplicationGroup), With<Replicating>>()
+            .iter(world)
+        {
+            let in_room = room_entities.contains(&entity);
+            groups
+                .entry(group.group_id(Some(entity)))
+                .and_modify(|all_in_room| *all_in_room &= in_room)
+                .or_insert(in_room);
+        }
+        let group_ids = groups
+            .into_iter()
+            .filter_map(|(group_id, all_in_room)| all_in_room.then_some(group_id))
+            .collect();
+        despawn_replication_groups(world, group_ids);
+        // the remaining entities share a group with entities outside the room,
+        // so their despawn is replicated individually
+        for entity in room_entities {
+            if let Some(entity_mut) = world.get_entity_mut(entity) {
+                entity_mut.despawn();
+            }
+        }
+    }
+
+    pub trait DespawnReplicationGroupsCommandExt {
+        /// Despawn all the replicated entities of the given [`ReplicationGroup`]s.
+        ///
+        /// Instead of replicating one despawn per entity, a single message is sent to the clients,
+        /// who then despawn all the entities of the groups at once. This is useful to despawn a large
+        /// number of entities in the same frame (for example at the end of a match).
+        fn despawn_replication_groups(&mut self, group_ids: Vec<ReplicationGroupId>);
+
+        /// Despawn all the entities of a room.
+        ///
+        /// The replication groups that only contain entities of the room are despawned
+        /// with a single message (see [`despawn_replication_groups`](DespawnReplicationGroupsCommandExt::despawn_replication_groups)).
+        fn despawn_room_entities(&mut self, room_id: RoomId);
+    }
+
+    impl DespawnReplicationGroupsCommandExt for Commands<'_, '_> {
+        fn despawn_replication_groups(&mut self, group_ids: Vec<ReplicationGroupId>) {
+            self.add(move |world: &mut World| despawn_replication_groups(world, group_ids));
+        }
+
+        fn despawn_room_entities(&mut self, room_id: RoomId) {
+            self.add(move |world: &mut World| despawn_room_entities(world, room_id));
+        }
+    }
+
     #[cfg(test)]
     mod tests {
-        use bevy::prelude::With;
+        use bevy::prelude::{default, With};
 
         use crate::prelude::server::Replicate;
         use crate::tests::protocol::*;
@@ -3310,5 +4671,76 @@ pub(crate) mod commands {
                 .get_single(stepper.client_app.world())
                 .is_ok());
         }
+
+        #[test]
+        fn test_despawn_replication_groups() {
+            let mut stepper = BevyStepper::default();
+
+            for i in 0..3 {
+                stepper.server_app.world_mut().spawn((
+                    ComponentSyncModeFull(i as f32),
+                    Replicate {
+                        group: ReplicationGroup::new_id(1),
+                        ..default()
+                    },
+                ));
+            }
+            let other_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((ComponentSyncModeSimple(1.0), Replicate::default()))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world_mut()
+                    .query::<&ComponentSyncModeFull>()
+                    .iter(stepper.client_app.world())
+                    .count(),
+                3
+            );
+
+            stepper
+                .server_app
+                .world_mut()
+                .commands()
+                .despawn_replication_groups(vec![ReplicationGroupId(1)]);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // all the entities of the group were despawned on the server and the client
+            assert_eq!(
+                stepper
+                    .server_app
+                    .world_mut()
+                    .query::<&ComponentSyncModeFull>()
+                    .iter(stepper.server_app.world())
+                    .count(),
+                0
+            );
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world_mut()
+                    .query::<&ComponentSyncModeFull>()
+                    .iter(stepper.client_app.world())
+                    .count(),
+                0
+            );
+            // entities from other groups are not affected
+            assert!(stepper
+                .server_app
+                .world()
+                .get_entity(other_entity)
+                .is_some());
+            assert!(stepper
+                .client_app
+                .world_mut()
+                .query::<&ComponentSyncModeSimple>()
+                .get_single(stepper.client_app.world())
+                .is_ok());
+        }
     }
 }
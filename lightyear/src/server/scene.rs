@@ -0,0 +1,223 @@
+//! Save the replicated entities of the server World, and load them back.
+//!
+//! A [`NetworkScene`] contains every entity that is replicated by the server, along with the value of
+//! all its components that are registered in the [`ComponentRegistry`]. It can be serialized to bytes,
+//! which is useful for persistence, server restarts or match replays.
+//!
+//! When a scene is loaded, the entities are spawned again with a [`Replicate`] bundle so that they are
+//! replicated to the clients as fresh spawns. The entity references inside the components (for components
+//! that implement `MapEntities`) are mapped to the newly spawned entities.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//! use lightyear::serialize::ToBytes;
+//!
+//! // save
+//! let scene = NetworkScene::from_world(world)?;
+//! let mut bytes = Vec::new();
+//! scene.to_bytes(&mut bytes)?;
+//!
+//! // load
+//! let scene = NetworkScene::from_bytes(&mut bytes.into())?;
+//! let old_to_new = scene.load(world)?;
+//! // optional: keep the entity ids seen by the clients stable
+//! world.resource_mut::<ConnectionManager>().set_entity_remapping(old_to_new);
+//! ```
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::{Bundle, Entity, Mut, World};
+use byteorder::WriteBytesExt;
+use bytes::Bytes;
+
+use crate::prelude::{ComponentRegistry, Tick};
+use crate::protocol::component::ComponentError;
+use crate::serialize::reader::Reader;
+use crate::serialize::writer::Writer;
+use crate::serialize::{SerializationError, ToBytes};
+use crate::server::replication::send::Replicate;
+use crate::shared::events::connection::ConnectionEvents;
+use crate::shared::replication::components::ReplicationTarget;
+use crate::shared::replication::entity_map::ReceiveEntityMap;
+use crate::shared::tick_manager::TickManager;
+
+/// Snapshot of the replicated entities of the server World
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkScene {
+    /// The serialized components of each entity (each component starts with its network id)
+    entities: Vec<(Entity, Vec<Bytes>)>,
+}
+
+impl NetworkScene {
+    /// Snapshot all the entities that are replicated by the server.
+    ///
+    /// Only the components that are registered for replication are saved.
+    /// Components that are disabled with [`DisabledComponent`](crate::prelude::DisabledComponent) are skipped.
+    pub fn from_world(world: &World) -> Result<Self, ComponentError> {
+        let registry = world.resource::<ComponentRegistry>();
+        let mut writer = Writer::default();
+        let mut entities = world
+            .iter_entities()
+            .filter(|entity_ref| entity_ref.contains::<ReplicationTarget>())
+            .map(|entity_ref| {
+                let mut components = vec![];
+                for (kind, metadata) in registry.replication_map.iter() {
+                    if entity_ref.contains_id(metadata.disabled_id) {
+                        continue;
+                    }
+                    let Some(component) = entity_ref.get_by_id(metadata.component_id) else {
+                        continue;
+                    };
+                    // SAFETY: the component_id corresponds to the kind
+                    registry.erased_serialize(component, &mut writer, *kind, None)?;
+                    components.push(writer.split());
+                }
+                Ok((entity_ref.id(), components))
+            })
+            .collect::<Result<Vec<_>, ComponentError>>()?;
+        entities.sort_by_key(|(entity, _)| *entity);
+        Ok(Self { entities })
+    }
+
+    /// Entities of the scene, identified by the id they had when the scene was saved
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().map(|(entity, _)| *entity)
+    }
+
+    /// Spawn the entities of the scene in the server World, replicated to all clients with [`Replicate::default()`].
+    ///
+    /// Returns the map from the id that each entity had when the scene was saved to its new id.
+    pub fn load(&self, world: &mut World) -> Result<EntityHashMap<Entity>, ComponentError> {
+        self.load_with(world, |_| Replicate::default())
+    }
+
+    /// Spawn the entities of the scene in the server World, and insert the bundle returned by `replicate`
+    /// (called with the new id of each entity) to start replicating them.
+    ///
+    /// Returns the map from the id that each entity had when the scene was saved to its new id.
+    pub fn load_with<B: Bundle>(
+        &self,
+        world: &mut World,
+        mut replicate: impl FnMut(Entity) -> B,
+    ) -> Result<EntityHashMap<Entity>, ComponentError> {
+        // spawn all the entities first so that the entity references between them can be mapped
+        let mut entity_map = ReceiveEntityMap::default();
+        for (entity, _) in &self.entities {
+            entity_map.insert(*entity, world.spawn_empty().id());
+        }
+        let tick = world
+            .get_resource::<TickManager>()
+            .map_or(Tick(0), |tick_manager| tick_manager.tick());
+        world.resource_scope(|world, registry: Mut<ComponentRegistry>| {
+            // the scene is not received from a remote peer, so there is no need to emit replication events
+            let mut events = ConnectionEvents::default();
+            for (entity, components) in &self.entities {
+                let local_entity = *entity_map.get(entity).unwrap();
+                let mut entity_world_mut = world.entity_mut(local_entity);
+                for component in components {
+                    let mut reader = Reader::from(component.clone());
+                    registry.raw_write(
+                        &mut reader,
+                        &mut entity_world_mut,
+                        tick,
+                        &mut entity_map,
+                        &mut events,
+                    )?;
+                }
+                entity_world_mut.insert(replicate(local_entity));
+            }
+            Ok::<(), ComponentError>(())
+        })?;
+        Ok(entity_map.map)
+    }
+}
+
+impl ToBytes for NetworkScene {
+    fn len(&self) -> usize {
+        ToBytes::len(&self.entities)
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        self.entities.to_bytes(buffer)
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            entities: Vec::from_bytes(buffer)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client;
+    use crate::tests::protocol::{ComponentMapEntities, ComponentSyncModeFull};
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_save_and_load_scene() {
+        let mut stepper = BevyStepper::default();
+        let server_entity_1 = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        let server_entity_2 = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentMapEntities(server_entity_1)))
+            .id();
+        // entities that are not replicated are not saved
+        stepper
+            .server_app
+            .world_mut()
+            .spawn(ComponentSyncModeFull(2.0));
+        stepper.frame_step();
+
+        let scene = NetworkScene::from_world(stepper.server_app.world()).unwrap();
+        assert_eq!(
+            scene.entities().collect::<Vec<_>>(),
+            vec![server_entity_1, server_entity_2]
+        );
+        let mut bytes = Vec::new();
+        scene.to_bytes(&mut bytes).unwrap();
+        let scene = NetworkScene::from_bytes(&mut Reader::from(bytes)).unwrap();
+
+        // restart the server world
+        stepper.server_app.world_mut().despawn(server_entity_1);
+        stepper.server_app.world_mut().despawn(server_entity_2);
+        stepper.frame_step();
+
+        let old_to_new = scene.load(stepper.server_app.world_mut()).unwrap();
+        let new_entity_1 = old_to_new[&server_entity_1];
+        let new_entity_2 = old_to_new[&server_entity_2];
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentMapEntities>(new_entity_2),
+            Some(&ComponentMapEntities(new_entity_1))
+        );
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the loaded entities are replicated as fresh spawns
+        let client_entity_1 = *stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(new_entity_1)
+            .expect("entity was not replicated to client");
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity_1),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+    }
+}
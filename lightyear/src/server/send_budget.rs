@@ -0,0 +1,187 @@
+//! Limits on the bytes that the server sends to its clients every frame.
+//!
+//! Without limits, the server writes all the packets of a client before moving to the next client, so a congested
+//! client that has a lot of reliable messages to resend can hog the socket and delay the packets of everyone else.
+//!
+//! The server instead sends the packets of all the clients in a round-robin order (one packet per client per round,
+//! starting from a different client every frame), and stops sending to a client once it reaches the
+//! [`SendBudget`]. The packets that exceed the budget are deferred to the next frame if they contain reliable
+//! messages, and dropped otherwise (as if they were lost).
+//!
+//! The bytes sent to each client are reported in [`ClientSendStats`].
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//!
+//! let config = ServerConfig {
+//!     send_budget: SendBudget::default()
+//!         .with_max_bytes_per_client(16 * 1024)
+//!         .with_max_total_bytes(256 * 1024),
+//!     ..default()
+//! };
+//! ```
+use std::collections::VecDeque;
+
+use crate::packet::packet_builder::Payload;
+use crate::shared::tick_manager::Tick;
+
+/// Limits on the number of bytes that the server sends every frame.
+///
+/// All the limits are disabled by default. A client always receives at least one packet per frame,
+/// so that it cannot be starved completely.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SendBudget {
+    /// Maximum number of bytes sent to a single client per frame
+    pub max_bytes_per_client: Option<usize>,
+    /// Maximum number of bytes sent to all the clients per frame.
+    ///
+    /// The budget is shared fairly between the clients: the clients that need less than their share
+    /// leave the rest of the budget to the others.
+    pub max_total_bytes: Option<usize>,
+}
+
+impl SendBudget {
+    pub fn with_max_bytes_per_client(mut self, max_bytes: usize) -> Self {
+        self.max_bytes_per_client = Some(max_bytes);
+        self
+    }
+
+    pub fn with_max_total_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_total_bytes = Some(max_bytes);
+        self
+    }
+}
+
+/// Bytes sent to a client during the latest frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClientSendStats {
+    /// Number of bytes sent to the client during the latest frame
+    pub bytes_sent: usize,
+    /// Average number of bytes sent to the client per tick during the latest frame
+    pub bytes_per_tick: f32,
+    /// Number of packets that exceeded the budget during the latest frame
+    pub deferred_packets: usize,
+}
+
+/// Round-robin scheduler for the packets that are sent to the clients
+#[derive(Debug, Default)]
+pub(crate) struct SendScheduler {
+    /// Index of the client that sends first during the next frame
+    offset: usize,
+    /// Tick of the previous frame, to compute the bytes sent per tick
+    last_tick: Option<Tick>,
+}
+
+impl SendScheduler {
+    /// Number of ticks that elapsed since the previous frame (at least 1)
+    pub(crate) fn elapsed_ticks(&mut self, tick: Tick) -> u16 {
+        let elapsed = self
+            .last_tick
+            .map_or(1, |last_tick| (tick - last_tick).max(1) as u16);
+        self.last_tick = Some(tick);
+        elapsed
+    }
+
+    /// Interleave the packets of the clients.
+    ///
+    /// Returns the packets to send, along with the index of their client in `queues`.
+    /// The packets that exceed the budget are left in the queues.
+    pub(crate) fn schedule(
+        &mut self,
+        budget: &SendBudget,
+        queues: &mut [VecDeque<Payload>],
+    ) -> Vec<(usize, Payload)> {
+        let num_clients = queues.len();
+        let mut scheduled = vec![];
+        if num_clients == 0 {
+            return scheduled;
+        }
+        let start = self.offset % num_clients;
+        self.offset = self.offset.wrapping_add(1);
+        let mut client_bytes = vec![0; num_clients];
+        let mut total_bytes = 0;
+        let mut full = vec![false; num_clients];
+        loop {
+            let mut sent_in_round = false;
+            for i in (0..num_clients).map(|i| (start + i) % num_clients) {
+                if full[i] {
+                    continue;
+                }
+                let Some(size) = queues[i].front().map(Vec::len) else {
+                    continue;
+                };
+                // every client gets at least one packet per frame
+                if client_bytes[i] > 0 {
+                    let over_client = budget
+                        .max_bytes_per_client
+                        .is_some_and(|max| client_bytes[i] + size > max);
+                    let over_total = budget
+                        .max_total_bytes
+                        .is_some_and(|max| total_bytes + size > max);
+                    if over_client || over_total {
+                        full[i] = true;
+                        continue;
+                    }
+                }
+                let payload = queues[i].pop_front().unwrap();
+                client_bytes[i] += size;
+                total_bytes += size;
+                scheduled.push((i, payload));
+                sent_in_round = true;
+            }
+            if !sent_in_round {
+                return scheduled;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin() {
+        let mut scheduler = SendScheduler::default();
+        let mut queues = vec![
+            VecDeque::from(vec![vec![0; 10]; 3]),
+            VecDeque::from(vec![vec![1; 10]; 1]),
+        ];
+        let scheduled = scheduler.schedule(&SendBudget::default(), &mut queues);
+        assert_eq!(
+            scheduled.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![0, 1, 0, 0]
+        );
+
+        // the next frame starts with the next client
+        let mut queues = vec![
+            VecDeque::from(vec![vec![0; 10]; 1]),
+            VecDeque::from(vec![vec![1; 10]; 1]),
+        ];
+        let scheduled = scheduler.schedule(&SendBudget::default(), &mut queues);
+        assert_eq!(
+            scheduled.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![1, 0]
+        );
+    }
+
+    #[test]
+    fn test_budget_is_shared_fairly() {
+        let mut scheduler = SendScheduler::default();
+        // a congested client with a lot of packets does not starve the others
+        let mut queues = vec![
+            VecDeque::from(vec![vec![0; 10]; 100]),
+            VecDeque::from(vec![vec![1; 10]; 2]),
+            VecDeque::from(vec![vec![2; 10]; 2]),
+        ];
+        let budget = SendBudget::default()
+            .with_max_total_bytes(100)
+            .with_max_bytes_per_client(50);
+        let scheduled = scheduler.schedule(&budget, &mut queues);
+        let count = |client| scheduled.iter().filter(|(i, _)| *i == client).count();
+        assert_eq!(count(0), 5);
+        assert_eq!(count(1), 2);
+        assert_eq!(count(2), 2);
+        assert_eq!(queues[0].len(), 95);
+    }
+}
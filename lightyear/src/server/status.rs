@@ -0,0 +1,397 @@
+//! Lightweight HTTP endpoint that exposes the status of a dedicated server as JSON.
+//!
+//! This is useful for orchestration tools (for example Kubernetes liveness probes or fleet dashboards)
+//! that need to check the health of the server without a custom sidecar.
+//!
+//! The endpoint serves:
+//! - `GET /health`: returns `200 OK` if the server is healthy, and `503 Service Unavailable` otherwise
+//! - any other path: the latest [`ServerStatus`] as JSON
+//!
+//! Both return `503 Service Unavailable` if the status was not refreshed for
+//! [`StatusEndpointPlugin::stale_after`], for example because the app is stuck.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//!
+//! app.add_plugins(StatusEndpointPlugin {
+//!     bind_addr: "0.0.0.0:9090".parse().unwrap(),
+//!     ..default()
+//! });
+//! ```
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_real_timer;
+use bevy::utils::{Duration, HashMap, Instant};
+use serde::Serialize;
+use tracing::{error, info, trace};
+
+use crate::connection::id::ClientId;
+use crate::connection::server::ServerConnections;
+use crate::prelude::{Replicating, Tick, TickManager};
+use crate::server::connection::ConnectionManager;
+use crate::server::run_conditions::is_started;
+
+/// Plugin that serves the [`ServerStatus`] of the server over HTTP
+#[derive(Clone, Debug)]
+pub struct StatusEndpointPlugin {
+    /// Address on which the HTTP endpoint listens
+    pub bind_addr: SocketAddr,
+    /// How often the status is refreshed
+    pub update_interval: Duration,
+    /// The server is considered unhealthy if the measured tick rate falls below this fraction
+    /// of the configured tick rate
+    pub min_tick_rate_ratio: f64,
+    /// The server is considered unhealthy if the status was not refreshed for this long
+    pub stale_after: Duration,
+    /// Timeout for reading the request and writing the response of a single HTTP connection
+    pub io_timeout: Duration,
+}
+
+impl Default for StatusEndpointPlugin {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::from(([0, 0, 0, 0], 9090)),
+            update_interval: Duration::from_secs(1),
+            min_tick_rate_ratio: 0.9,
+            stale_after: Duration::from_secs(5),
+            io_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Status of the server, as exposed by the HTTP endpoint
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ServerStatus {
+    /// False if the server is not started or is not able to keep up with its tick rate
+    pub healthy: bool,
+    /// Current server tick
+    pub tick: u16,
+    pub tick_rate: TickRateStatus,
+    /// Number of connected clients
+    pub num_clients: usize,
+    pub clients: Vec<ClientStatus>,
+    pub replication: ReplicationStatus,
+}
+
+/// Health of the server's tick rate
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct TickRateStatus {
+    /// Configured number of ticks per second
+    pub target_hz: f64,
+    /// Number of ticks per second measured since the previous status update.
+    ///
+    /// `None` for the first update.
+    pub measured_hz: Option<f64>,
+}
+
+/// Status of the connection with a client
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClientStatus {
+    pub client_id: ClientId,
+    pub rtt_ms: f64,
+    pub jitter_ms: f64,
+    /// Estimate of the fraction of packets sent to the client that were lost
+    pub packet_loss: f32,
+    /// Estimate of the fraction of packets sent by the client that were lost
+    pub incoming_packet_loss: f32,
+    /// Bandwidth used to send packets to the client since the previous status update, in KB/s
+    pub send_kbps: f64,
+    /// Bandwidth used to receive packets from the client since the previous status update, in KB/s
+    pub recv_kbps: f64,
+    /// Total number of bytes sent to the client
+    pub bytes_sent: usize,
+    /// Total number of bytes received from the client
+    pub bytes_received: usize,
+    /// Number of replication groups that are replicated to the client
+    pub replication_groups: usize,
+    /// Average number of bytes sent to the client per tick during the latest frame
+    pub send_bytes_per_tick: f32,
+    /// Number of packets that exceeded the [`SendBudget`](crate::server::send_budget::SendBudget) during the latest frame
+    pub deferred_packets: usize,
+}
+
+/// Replication statistics
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ReplicationStatus {
+    /// Number of entities that are replicated by the server
+    pub replicated_entities: usize,
+}
+
+/// Handle to the HTTP status endpoint
+#[derive(Resource, Debug)]
+pub struct StatusEndpoint {
+    local_addr: SocketAddr,
+    status: ServerStatus,
+    /// Serialized status that is shared with the HTTP thread
+    shared: Arc<RwLock<SharedStatus>>,
+    min_tick_rate_ratio: f64,
+    /// Time, tick and per-client totals (bytes sent, bytes received) at the previous update,
+    /// used to compute rates
+    last_update: Option<(Instant, Tick, HashMap<ClientId, (usize, usize)>)>,
+}
+
+#[derive(Debug, Default)]
+struct SharedStatus {
+    healthy: bool,
+    json: String,
+    /// Time of the latest status update
+    updated_at: Option<Instant>,
+}
+
+impl StatusEndpoint {
+    /// Address on which the HTTP endpoint is listening
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Latest status served by the endpoint
+    pub fn status(&self) -> &ServerStatus {
+        &self.status
+    }
+}
+
+impl Plugin for StatusEndpointPlugin {
+    fn build(&self, app: &mut App) {
+        let listener = match TcpListener::bind(self.bind_addr) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!(
+                    "Could not start the status endpoint on {:?}: {:?}",
+                    self.bind_addr, e
+                );
+                return;
+            }
+        };
+        let local_addr = listener.local_addr().unwrap_or(self.bind_addr);
+        info!("Serving the server status on http://{}", local_addr);
+        let shared = Arc::new(RwLock::new(SharedStatus::default()));
+        let thread_shared = shared.clone();
+        let (stale_after, io_timeout) = (self.stale_after, self.io_timeout);
+        std::thread::Builder::new()
+            .name("lightyear-status-endpoint".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = handle_request(stream, &thread_shared, stale_after, io_timeout)
+                    {
+                        trace!("Error while serving the status endpoint: {:?}", e);
+                    }
+                }
+            })
+            .expect("could not spawn the status endpoint thread");
+
+        app.insert_resource(StatusEndpoint {
+            local_addr,
+            status: ServerStatus::default(),
+            shared,
+            min_tick_rate_ratio: self.min_tick_rate_ratio,
+            last_update: None,
+        });
+        app.add_systems(
+            PostUpdate,
+            update_status.run_if(on_real_timer(self.update_interval)),
+        );
+    }
+}
+
+/// Respond to a single HTTP request with the latest status
+fn handle_request(
+    stream: TcpStream,
+    shared: &RwLock<SharedStatus>,
+    stale_after: Duration,
+    io_timeout: Duration,
+) -> std::io::Result<()> {
+    // a slow client must not block the endpoint
+    stream.set_read_timeout(Some(io_timeout))?;
+    stream.set_write_timeout(Some(io_timeout))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // consume the headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (status_line, content_type, body) = {
+        let shared = shared.read().unwrap();
+        let stale = !shared
+            .updated_at
+            .is_some_and(|updated_at| updated_at.elapsed() <= stale_after);
+        match (path, shared.healthy && !stale) {
+            ("/health", true) => ("200 OK", "text/plain", "ok".to_string()),
+            ("/health", false) => (
+                "503 Service Unavailable",
+                "text/plain",
+                if stale { "stale" } else { "unhealthy" }.to_string(),
+            ),
+            _ if stale => (
+                "503 Service Unavailable",
+                "application/json",
+                shared.json.clone(),
+            ),
+            _ => ("200 OK", "application/json", shared.json.clone()),
+        }
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status_line}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Compute the latest [`ServerStatus`] and share it with the HTTP thread
+fn update_status(
+    mut endpoint: ResMut<StatusEndpoint>,
+    started: Option<Res<ServerConnections>>,
+    connection_manager: Res<ConnectionManager>,
+    tick_manager: Res<TickManager>,
+    replicated: Query<(), With<Replicating>>,
+) {
+    let endpoint = endpoint.as_mut();
+    let now = Instant::now();
+    let tick = tick_manager.tick();
+    let elapsed = endpoint
+        .last_update
+        .as_ref()
+        .map(|(last_time, last_tick, _)| (now - *last_time, tick - *last_tick));
+    let target_hz = 1.0 / tick_manager.config.tick_duration.as_secs_f64();
+    let measured_hz = elapsed
+        .filter(|(duration, _)| !duration.is_zero())
+        .map(|(duration, ticks)| ticks as f64 / duration.as_secs_f64());
+
+    let mut totals = HashMap::default();
+    let mut clients: Vec<ClientStatus> = connection_manager
+        .connections
+        .iter()
+        .map(|(client_id, connection)| {
+            let stats = connection.stats();
+            let (previous_sent, previous_received) = endpoint
+                .last_update
+                .as_ref()
+                .and_then(|(_, _, totals)| totals.get(client_id).copied())
+                .unwrap_or_default();
+            let kbps = |bytes: usize| match elapsed {
+                Some((duration, _)) if !duration.is_zero() => {
+                    (bytes as f64 / 1000.0) / duration.as_secs_f64()
+                }
+                _ => 0.0,
+            };
+            totals.insert(*client_id, (stats.bytes_sent, stats.bytes_received));
+            ClientStatus {
+                client_id: *client_id,
+                rtt_ms: connection.rtt().as_secs_f64() * 1000.0,
+                jitter_ms: connection.jitter().as_secs_f64() * 1000.0,
+                packet_loss: connection.packet_loss(),
+                incoming_packet_loss: connection.incoming_packet_loss(),
+                send_kbps: kbps(stats.bytes_sent.saturating_sub(previous_sent)),
+                recv_kbps: kbps(stats.bytes_received.saturating_sub(previous_received)),
+                bytes_sent: stats.bytes_sent,
+                bytes_received: stats.bytes_received,
+                replication_groups: connection.replication_sender.group_channels.len(),
+                send_bytes_per_tick: connection.send_stats().bytes_per_tick,
+                deferred_packets: connection.send_stats().deferred_packets,
+            }
+        })
+        .collect();
+    clients.sort_by_key(|client| client.client_id.to_bits());
+
+    let healthy = is_started(started)
+        && measured_hz.map_or(true, |hz| hz >= target_hz * endpoint.min_tick_rate_ratio);
+    endpoint.status = ServerStatus {
+        healthy,
+        tick: tick.0,
+        tick_rate: TickRateStatus {
+            target_hz,
+            measured_hz,
+        },
+        num_clients: clients.len(),
+        clients,
+        replication: ReplicationStatus {
+            replicated_entities: replicated.iter().count(),
+        },
+    };
+    endpoint.last_update = Some((now, tick, totals));
+    match serde_json::to_string(&endpoint.status) {
+        Ok(json) => {
+            let mut shared = endpoint.shared.write().unwrap();
+            shared.healthy = healthy;
+            shared.json = json;
+            shared.updated_at = Some(now);
+        }
+        Err(e) => error!("Could not serialize the server status: {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_status_endpoint() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.add_plugins(StatusEndpointPlugin {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            update_interval: Duration::ZERO,
+            ..default()
+        });
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let endpoint = stepper.server_app.world().resource::<StatusEndpoint>();
+        let status = endpoint.status();
+        assert_eq!(status.num_clients, 1);
+        assert_eq!(
+            status.clients[0].client_id,
+            ClientId::Netcode(TEST_CLIENT_ID)
+        );
+        assert!(status.clients[0].bytes_sent > 0);
+
+        let addr = endpoint.local_addr();
+        let response = get(addr, "/status");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"num_clients\":1"));
+        let response = get(addr, "/health");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+
+    /// The endpoint reports the server as unhealthy if the status is not refreshed anymore
+    #[test]
+    fn test_status_endpoint_stale() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.add_plugins(StatusEndpointPlugin {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            update_interval: Duration::ZERO,
+            stale_after: Duration::from_millis(50),
+            ..default()
+        });
+        stepper.frame_step();
+        stepper.frame_step();
+        let addr = stepper
+            .server_app
+            .world()
+            .resource::<StatusEndpoint>()
+            .local_addr();
+        assert!(get(addr, "/health").starts_with("HTTP/1.1 200 OK"));
+
+        // the app stops updating
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(get(addr, "/health").starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(get(addr, "/status").starts_with("HTTP/1.1 503 Service Unavailable"));
+    }
+}
@@ -2,6 +2,7 @@
 use bevy::reflect::Reflect;
 use bevy::utils::Duration;
 
+use crate::shared::memory::MemoryConfig;
 use crate::shared::tick_manager::TickConfig;
 
 /// Configuration that has to be the same between the server and the client.
@@ -13,6 +14,23 @@ pub struct SharedConfig {
     /// configuration for the [`FixedUpdate`](bevy::prelude::FixedUpdate) schedule
     pub tick: TickConfig,
     pub mode: Mode,
+    /// If true, messages and components whose type is not registered in the protocol are skipped
+    /// (and an [`UnknownTypeEvent`](crate::shared::events::components::UnknownTypeEvent) is emitted)
+    /// instead of returning an error.
+    ///
+    /// This is useful during rolling upgrades, where the server and the clients can briefly run
+    /// different versions of the protocol.
+    ///
+    /// Types are identified on the network by an id that is assigned in registration order, not by name.
+    /// For the older and newer versions of the protocol to agree on the ids of the types that they share,
+    /// new messages and components must be registered after all the existing ones, and existing types
+    /// must not be removed or reordered. Otherwise, data would be deserialized as the wrong type instead of
+    /// being skipped.
+    pub skip_unknown_types: bool,
+    /// Limits on the memory used by the networking buffers.
+    ///
+    /// Unlike the rest of the configuration, the limits don't have to be the same on the server and the client.
+    pub memory: MemoryConfig,
 }
 
 // TODO: maybe the modes should just be
@@ -42,6 +60,8 @@ impl Default for SharedConfig {
             server_replication_send_interval: Duration::from_millis(0),
             tick: TickConfig::new(Duration::from_millis(16)),
             mode: Mode::default(),
+            skip_unknown_types: false,
+            memory: MemoryConfig::default(),
         }
     }
 }
@@ -0,0 +1,84 @@
+//! Messages used by clients to run the console commands registered on the server.
+//!
+//! The server registers commands with
+//! [`register_console_command`](crate::server::console::AppConsoleCommandExt::register_console_command).
+//! An authorized client can then run them with
+//! [`ConnectionManager::send_console_command`](crate::client::connection::ConnectionManager::send_console_command);
+//! the output of each command is sent back in a [`ConsoleCommandResponse`] message.
+use serde::{Deserialize, Serialize};
+
+/// Message sent by a client to run a console command on the server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleCommandRequest {
+    /// The full command line, e.g. `kick 42 "spamming the chat"`
+    pub line: String,
+}
+
+/// Message sent by the server with the result of a [`ConsoleCommandRequest`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleCommandResponse {
+    /// The command line, as sent in the [`ConsoleCommandRequest`]
+    pub line: String,
+    /// The output of the command, or the reason why it failed
+    pub result: Result<String, String>,
+}
+
+/// Split a command line into its arguments.
+///
+/// Arguments are separated by whitespace. An argument containing whitespace can be wrapped in
+/// single or double quotes, and a backslash escapes the next character.
+///
+/// e.g. `kick 42 "spamming the chat"` is split into `["kick", "42", "spamming the chat"]`
+pub fn parse_command_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| "trailing backslash".to_string())?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            c if Some(c) == quote => quote = None,
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                // an empty quoted string is still an argument
+                current.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && quote.is_none() => {
+                args.extend(current.take());
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(quote) = quote {
+        return Err(format!("unterminated quote {quote}"));
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(
+            parse_command_line(r#"  kick 42 "spamming the chat" 'a b'\ c "" "#),
+            Ok(vec![
+                "kick".to_string(),
+                "42".to_string(),
+                "spamming the chat".to_string(),
+                "a b c".to_string(),
+                "".to_string(),
+            ])
+        );
+        assert_eq!(parse_command_line(""), Ok(vec![]));
+        assert!(parse_command_line(r#"kick "42"#).is_err());
+        assert!(parse_command_line(r"kick \").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Register the network statistics of the connection (RTT, jitter, packet loss in both directions) as Bevy [`Diagnostics`]
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::{not, resource_exists, Condition, IntoSystemConfigs, Res};
+use bevy::time::common_conditions::on_timer;
+use bevy::utils::Duration;
+
+use crate::client::connection::ConnectionManager as ClientConnectionManager;
+use crate::client::run_conditions::is_connected;
+use crate::server::connection::ConnectionManager as ServerConnectionManager;
+use crate::shared::run_conditions::is_host_server;
+
+/// Plugin that registers the RTT, jitter and packet loss of the connection as [`Diagnostics`].
+///
+/// On the client, the diagnostics are the statistics of the connection to the server.
+/// On the server, they are averaged over all the connected clients; the statistics of each client
+/// can be read with [`ConnectionManager::rtt`](crate::server::connection::ConnectionManager::rtt),
+/// [`ConnectionManager::jitter`](crate::server::connection::ConnectionManager::jitter),
+/// [`ConnectionManager::packet_loss`](crate::server::connection::ConnectionManager::packet_loss) and
+/// [`ConnectionManager::incoming_packet_loss`](crate::server::connection::ConnectionManager::incoming_packet_loss).
+pub struct NetworkDiagnosticsPlugin {
+    pub history_len: usize,
+    pub flush_interval: Duration,
+}
+
+impl Default for NetworkDiagnosticsPlugin {
+    fn default() -> Self {
+        Self {
+            history_len: 60,
+            flush_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl NetworkDiagnosticsPlugin {
+    /// Round Trip Time (RTT)
+    pub const RTT: DiagnosticPath = DiagnosticPath::const_new("network.rtt.ms");
+
+    /// Jitter of the RTT
+    pub const JITTER: DiagnosticPath = DiagnosticPath::const_new("network.jitter.ms");
+
+    /// Percentage of the packets sent that were lost
+    pub const PACKET_LOSS: DiagnosticPath = DiagnosticPath::const_new("network.packet_loss");
+
+    /// Percentage of the packets sent by the remote peer that were lost
+    pub const INCOMING_PACKET_LOSS: DiagnosticPath =
+        DiagnosticPath::const_new("network.incoming_packet_loss");
+
+    fn add_measurements(
+        rtt: Duration,
+        jitter: Duration,
+        packet_loss: f32,
+        incoming_packet_loss: f32,
+        diagnostics: &mut Diagnostics,
+    ) {
+        diagnostics.add_measurement(&Self::RTT, || rtt.as_secs_f64() * 1000.0);
+        diagnostics.add_measurement(&Self::JITTER, || jitter.as_secs_f64() * 1000.0);
+        diagnostics.add_measurement(&Self::PACKET_LOSS, || packet_loss as f64 * 100.0);
+        diagnostics.add_measurement(&Self::INCOMING_PACKET_LOSS, || {
+            incoming_packet_loss as f64 * 100.0
+        });
+    }
+}
+
+fn client_network_diagnostics_system(
+    connection: Res<ClientConnectionManager>,
+    mut diagnostics: Diagnostics,
+) {
+    NetworkDiagnosticsPlugin::add_measurements(
+        connection.rtt(),
+        connection.jitter(),
+        connection.packet_loss(),
+        connection.incoming_packet_loss(),
+        &mut diagnostics,
+    );
+}
+
+fn server_network_diagnostics_system(
+    connection_manager: Res<ServerConnectionManager>,
+    mut diagnostics: Diagnostics,
+) {
+    // the local client in HostServer mode does not go through the network
+    let connections: Vec<_> = connection_manager
+        .connections
+        .values()
+        .filter(|connection| !connection.is_local_client() && !connection.is_suspended())
+        .collect();
+    if connections.is_empty() {
+        return;
+    }
+    let count = connections.len() as u32;
+    NetworkDiagnosticsPlugin::add_measurements(
+        connections.iter().map(|c| c.rtt()).sum::<Duration>() / count,
+        connections.iter().map(|c| c.jitter()).sum::<Duration>() / count,
+        connections.iter().map(|c| c.packet_loss()).sum::<f32>() / count as f32,
+        connections
+            .iter()
+            .map(|c| c.incoming_packet_loss())
+            .sum::<f32>()
+            / count as f32,
+        &mut diagnostics,
+    );
+}
+
+impl Plugin for NetworkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(
+            Diagnostic::new(Self::RTT)
+                .with_suffix("ms")
+                .with_max_history_length(self.history_len),
+        );
+        app.register_diagnostic(
+            Diagnostic::new(Self::JITTER)
+                .with_suffix("ms")
+                .with_max_history_length(self.history_len),
+        );
+        app.register_diagnostic(
+            Diagnostic::new(Self::PACKET_LOSS)
+                .with_suffix("%")
+                .with_max_history_length(self.history_len),
+        );
+        app.register_diagnostic(
+            Diagnostic::new(Self::INCOMING_PACKET_LOSS)
+                .with_suffix("%")
+                .with_max_history_length(self.history_len),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                client_network_diagnostics_system.run_if(
+                    on_timer(self.flush_interval)
+                        .and_then(resource_exists::<ClientConnectionManager>)
+                        .and_then(is_connected.and_then(not(is_host_server))),
+                ),
+                server_network_diagnostics_system.run_if(
+                    on_timer(self.flush_interval)
+                        .and_then(resource_exists::<ServerConnectionManager>),
+                ),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::diagnostic::DiagnosticsStore;
+
+    use super::*;
+    use crate::prelude::client::ClientConfig;
+    use crate::prelude::{SharedConfig, TickConfig};
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_network_diagnostics() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, ClientConfig::default(), frame_duration);
+        stepper.client_app.add_plugins(NetworkDiagnosticsPlugin {
+            history_len: 60,
+            flush_interval: Duration::default(),
+        });
+        stepper.server_app.add_plugins(NetworkDiagnosticsPlugin {
+            history_len: 60,
+            flush_interval: Duration::default(),
+        });
+        stepper.init();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        for app in [&stepper.client_app, &stepper.server_app] {
+            let store = app.world().resource::<DiagnosticsStore>();
+            for path in [
+                NetworkDiagnosticsPlugin::RTT,
+                NetworkDiagnosticsPlugin::JITTER,
+                NetworkDiagnosticsPlugin::PACKET_LOSS,
+            ] {
+                assert!(store.get(&path).unwrap().measurement().is_some());
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::marker::PhantomData;
 use bevy::prelude::{Component, Entity, Event};
 
 use crate::packet::message::Message;
+use crate::protocol::registry::NetId;
 
 /// This event is emitted whenever we receive a message from the remote
 #[derive(Event, Debug)]
@@ -170,3 +171,44 @@ impl<C: Component, Ctx> ComponentRemoveEvent<C, Ctx> {
         &self.context
     }
 }
+
+/// The kind of a type that is not registered in the local protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnknownTypeKind {
+    Message,
+    Component,
+}
+
+/// Event emitted whenever we receive a message or a component whose [`NetId`] is not registered
+/// in the local protocol, and that was skipped.
+///
+/// This is only emitted if [`SharedConfig::skip_unknown_types`](crate::prelude::SharedConfig::skip_unknown_types)
+/// is enabled.
+#[derive(Event, Debug)]
+pub struct UnknownTypeEvent<Ctx = ()> {
+    kind: UnknownTypeKind,
+    net_id: NetId,
+    context: Ctx,
+}
+
+impl<Ctx> UnknownTypeEvent<Ctx> {
+    pub fn new(kind: UnknownTypeKind, net_id: NetId, context: Ctx) -> Self {
+        Self {
+            kind,
+            net_id,
+            context,
+        }
+    }
+
+    pub fn kind(&self) -> UnknownTypeKind {
+        self.kind
+    }
+
+    pub fn net_id(&self) -> NetId {
+        self.net_id
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
@@ -8,7 +8,9 @@ use tracing::trace;
 
 use crate::prelude::{ComponentRegistry, Tick};
 use crate::protocol::component::ComponentNetId;
+use crate::protocol::registry::NetId;
 use crate::protocol::EventContext;
+use crate::shared::events::components::UnknownTypeKind;
 
 // TODO: don't make fields pub but instead make accessors
 #[derive(Debug, Resource)]
@@ -36,6 +38,8 @@ pub struct ConnectionEvents {
 
     // How can i easily get the events (inserts/adds/removes) for a given entity? add components on that entity
     // that track that?
+    /// Messages or components that were received with a [`NetId`] that is not registered in the protocol
+    pub unknown_types: Vec<(UnknownTypeKind, NetId)>,
     empty: bool,
 }
 
@@ -50,6 +54,7 @@ impl ClearEvents for ConnectionEvents {
         self.component_inserts.clear();
         self.component_removes.clear();
         self.component_updates.clear();
+        self.unknown_types.clear();
         self.empty = true;
     }
 }
@@ -69,6 +74,7 @@ impl ConnectionEvents {
             component_inserts: Default::default(),
             component_removes: Default::default(),
             component_updates: Default::default(),
+            unknown_types: Vec::new(),
             // bookkeeping
             empty: true,
         }
@@ -158,6 +164,35 @@ impl ConnectionEvents {
         // .push((entity, tick));
         self.empty = false;
     }
+
+    pub(crate) fn push_unknown_type(&mut self, kind: UnknownTypeKind, net_id: NetId) {
+        trace!(?kind, ?net_id, "Received unknown type");
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("unknown_type", "kind" => format!("{kind:?}")).increment(1);
+        }
+        self.unknown_types.push((kind, net_id));
+        self.empty = false;
+    }
+}
+
+pub trait IterUnknownTypeEvent<Ctx: EventContext = ()> {
+    fn drain_unknown_type(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (UnknownTypeKind, NetId, Ctx)> + '_>;
+}
+
+impl IterUnknownTypeEvent for ConnectionEvents {
+    fn drain_unknown_type(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (UnknownTypeKind, NetId, ())> + '_> {
+        let unknown_types = std::mem::take(&mut self.unknown_types);
+        Box::new(
+            unknown_types
+                .into_iter()
+                .map(|(kind, net_id)| (kind, net_id, ())),
+        )
+    }
 }
 
 pub trait IterEntitySpawnEvent<Ctx: EventContext = ()> {
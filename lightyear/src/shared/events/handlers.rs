@@ -0,0 +1,149 @@
+//! Dispatch the received messages to handlers that run automatically.
+//!
+//! Instead of adding a system with an `EventReader<MessageEvent<M>>` for every message type, you can register
+//! a handler for a message type with [`add_message_handler`](AppMessageHandlerExt::add_message_handler).
+//! The handler is a one-shot system that takes the received [`MessageEvent`] as input, and will be run
+//! once for every message received.
+//!
+//! This makes it easy to have many message types that share a single behaviour. For example, you can define
+//! a `GameCommand` trait implemented by many message types, and register the same generic handler
+//! for each of them:
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//! use lightyear::prelude::server::*;
+//!
+//! trait GameCommand: Message {
+//!     fn apply(self, client_id: ClientId, commands: &mut Commands);
+//! }
+//!
+//! fn apply_command<C: GameCommand>(In(event): In<MessageEvent<C>>, mut commands: Commands) {
+//!     event.message.apply(event.context, &mut commands);
+//! }
+//!
+//! fn add_handlers(app: &mut App) {
+//!     app.add_message_handler(apply_command::<SpawnUnit>);
+//!     app.add_message_handler(apply_command::<MoveUnit>);
+//! }
+//! ```
+//!
+//! The messages are consumed by the handler, so they won't be available via an `EventReader<MessageEvent<M>>`.
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+use tracing::error;
+
+use crate::packet::message::Message;
+use crate::protocol::EventContext;
+use crate::shared::events::components::MessageEvent;
+use crate::shared::sets::MainSet;
+
+/// Stores the handler registered for the message `M`
+#[derive(Resource)]
+struct MessageHandler<M: Message, Ctx: EventContext> {
+    system: SystemId<MessageEvent<M, Ctx>>,
+}
+
+pub trait AppMessageHandlerExt {
+    /// Register a handler that will run for every message `M` received from the remote peer.
+    ///
+    /// The handler is a system that takes the [`MessageEvent`] as input (via [`In`]).
+    /// Use [`ClientMessageEvent`](crate::prelude::ClientMessageEvent) for messages received
+    /// by the client and [`ServerMessageEvent`](crate::prelude::ServerMessageEvent) for messages received by the server.
+    ///
+    /// Only one handler can be registered per message type; registering a new handler replaces the previous one.
+    fn add_message_handler<M: Message, Ctx: EventContext, Marker>(
+        &mut self,
+        handler: impl IntoSystem<MessageEvent<M, Ctx>, (), Marker> + 'static,
+    ) -> &mut Self;
+}
+
+impl AppMessageHandlerExt for App {
+    fn add_message_handler<M: Message, Ctx: EventContext, Marker>(
+        &mut self,
+        handler: impl IntoSystem<MessageEvent<M, Ctx>, (), Marker> + 'static,
+    ) -> &mut Self {
+        let system = self.world_mut().register_system(handler);
+        if let Some(previous) = self.world_mut().remove_resource::<MessageHandler<M, Ctx>>() {
+            let _ = self.world_mut().remove_system(previous.system);
+        } else {
+            self.add_systems(
+                PreUpdate,
+                dispatch_messages::<M, Ctx>.after(MainSet::EmitEvents),
+            );
+        }
+        self.insert_resource(MessageHandler { system });
+        self
+    }
+}
+
+/// Run the handler for each message `M` that was received in this frame
+fn dispatch_messages<M: Message, Ctx: EventContext>(world: &mut World) {
+    let Some(system) = world
+        .get_resource::<MessageHandler<M, Ctx>>()
+        .map(|handler| handler.system)
+    else {
+        return;
+    };
+    // the message events are only registered if the message can be received by this peer
+    let Some(mut events) = world.get_resource_mut::<Events<MessageEvent<M, Ctx>>>() else {
+        return;
+    };
+    let events: Vec<_> = events.drain().collect();
+    for event in events {
+        if let Err(e) = world.run_system_with_input(system, event) {
+            error!(
+                "Error running the handler for message {}: {:?}",
+                std::any::type_name::<M>(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{client, ClientId, ServerMessageEvent};
+    use crate::tests::protocol::{Channel1, StringMessage};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    #[derive(Resource, Default)]
+    struct Received(Vec<(ClientId, String)>);
+
+    fn handle_string_message(
+        In(event): In<ServerMessageEvent<StringMessage>>,
+        mut received: ResMut<Received>,
+    ) {
+        received.0.push((event.context, event.message.0));
+    }
+
+    #[test]
+    fn test_message_handler() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<Received>();
+        stepper
+            .server_app
+            .add_message_handler(handle_string_message);
+
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .send_message::<Channel1, StringMessage>(&mut StringMessage("a".to_string()))
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert_eq!(
+            stepper.server_app.world().resource::<Received>().0,
+            vec![(ClientId::Netcode(TEST_CLIENT_ID), "a".to_string())]
+        );
+        // the message was consumed by the handler
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<Events<ServerMessageEvent<StringMessage>>>()
+            .is_empty());
+    }
+}
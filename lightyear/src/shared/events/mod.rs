@@ -1,5 +1,6 @@
 //! This module defines bevy [`Events`](bevy::prelude::Events) related to networking events
 pub mod components;
 pub(crate) mod connection;
+pub mod handlers;
 pub mod plugin;
 pub mod systems;
@@ -3,8 +3,8 @@
 use bevy::app::{App, PreUpdate};
 use bevy::prelude::{IntoSystemConfigs, Plugin};
 
-use crate::shared::events::components::{EntityDespawnEvent, EntitySpawnEvent};
-use crate::shared::events::systems::{clear_events, push_entity_events};
+use crate::shared::events::components::{EntityDespawnEvent, EntitySpawnEvent, UnknownTypeEvent};
+use crate::shared::events::systems::{clear_events, push_entity_events, push_unknown_type_events};
 use crate::shared::replication::ReplicationReceive;
 use crate::shared::sets::InternalMainSet;
 
@@ -24,11 +24,13 @@ impl<R: ReplicationReceive> Plugin for EventsPlugin<R> {
     fn build(&self, app: &mut App) {
         // EVENTS
         app.add_event::<EntitySpawnEvent<R::EventContext>>()
-            .add_event::<EntityDespawnEvent<R::EventContext>>();
+            .add_event::<EntityDespawnEvent<R::EventContext>>()
+            .add_event::<UnknownTypeEvent<R::EventContext>>();
         // SYSTEMS
         app.add_systems(
             PreUpdate,
-            push_entity_events::<R>.in_set(InternalMainSet::<R::SetMarker>::EmitEvents),
+            (push_entity_events::<R>, push_unknown_type_events::<R>)
+                .in_set(InternalMainSet::<R::SetMarker>::EmitEvents),
         );
         app.add_systems(
             PreUpdate,
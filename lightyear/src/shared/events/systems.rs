@@ -3,11 +3,11 @@ use bevy::prelude::{Component, EventWriter, Res, ResMut};
 use crate::prelude::ComponentRegistry;
 use crate::shared::events::components::{
     ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, EntityDespawnEvent,
-    EntitySpawnEvent,
+    EntitySpawnEvent, UnknownTypeEvent,
 };
 use crate::shared::events::connection::{
     ClearEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
-    IterEntityDespawnEvent, IterEntitySpawnEvent,
+    IterEntityDespawnEvent, IterEntitySpawnEvent, IterUnknownTypeEvent,
 };
 use crate::shared::replication::ReplicationReceive;
 
@@ -59,6 +59,19 @@ pub(crate) fn push_entity_events<R: ReplicationReceive>(
     );
 }
 
+/// System that sends the unknown types that were skipped by the local host to bevy Events
+pub(crate) fn push_unknown_type_events<R: ReplicationReceive>(
+    mut connection_manager: ResMut<R>,
+    mut unknown_type_events: EventWriter<UnknownTypeEvent<R::EventContext>>,
+) {
+    unknown_type_events.send_batch(
+        connection_manager
+            .events()
+            .drain_unknown_type()
+            .map(|(kind, net_id, ctx)| UnknownTypeEvent::new(kind, net_id, ctx)),
+    );
+}
+
 pub(crate) fn clear_events<R: ReplicationReceive>(mut connection_manager: ResMut<R>) {
     connection_manager.events().clear()
 }
@@ -1,7 +1,8 @@
 //! Plugin to register and handle user inputs.
 
 use bevy::app::{App, Plugin};
-use leafwing_input_manager::prelude::ActionState;
+use bevy::utils::HashMap;
+use leafwing_input_manager::prelude::{ActionState, InputMap};
 
 use crate::client::config::ClientConfig;
 use crate::client::input::leafwing::LeafwingInputConfig;
@@ -9,19 +10,33 @@ use crate::prelude::{AppComponentExt, ChannelDirection, InputMessage, LeafwingUs
 use crate::protocol::message::AppMessageInternalExt;
 use crate::protocol::message::MessageType;
 use crate::server::config::ServerConfig;
+use crate::shared::replication::components::LocalPlayerId;
 
-pub struct LeafwingInputPlugin<A> {
+pub struct LeafwingInputPlugin<A: LeafwingUserAction> {
     pub config: LeafwingInputConfig<A>,
+    /// The [`InputMap`] of each player that uses this client (for split-screen games)
+    pub local_players: HashMap<LocalPlayerId, InputMap<A>>,
 }
 
-impl<A> Default for LeafwingInputPlugin<A> {
+impl<A: LeafwingUserAction> Default for LeafwingInputPlugin<A> {
     fn default() -> Self {
         Self {
             config: Default::default(),
+            local_players: Default::default(),
         }
     }
 }
 
+impl<A: LeafwingUserAction> LeafwingInputPlugin<A> {
+    /// Register the [`InputMap`] of a local player.
+    ///
+    /// On the client, it will be inserted on the entities controlled by this client that have the same [`LocalPlayerId`].
+    pub fn with_local_player(mut self, id: LocalPlayerId, input_map: InputMap<A>) -> Self {
+        self.local_players.insert(id, input_map);
+        self
+    }
+}
+
 impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
     fn build(&self, app: &mut App) {}
 
@@ -48,9 +63,11 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
         let is_client = app.world().get_resource::<ClientConfig>().is_some();
         let is_server = app.world().get_resource::<ServerConfig>().is_some();
         if is_client {
-            app.add_plugins(
+            let client_plugin = self.local_players.iter().fold(
                 crate::client::input::leafwing::LeafwingInputPlugin::<A>::new(self.config),
+                |plugin, (id, input_map)| plugin.with_local_player(*id, input_map.clone()),
             );
+            app.add_plugins(client_plugin);
         }
         if is_server {
             app.add_plugins(crate::server::input::leafwing::LeafwingInputPlugin::<A>::default());
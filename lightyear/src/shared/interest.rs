@@ -0,0 +1,41 @@
+//! Messages used by clients to subscribe to the interest sets defined by the server.
+//!
+//! The server defines named interest sets (e.g. "minimap", or "spectate" for requests like "spectate:player42")
+//! with [`add_interest_set`](crate::server::relevance::interest::AppInterestSetExt::add_interest_set).
+//! A client can then request a subscription with
+//! [`ConnectionManager::subscribe`](crate::client::connection::ConnectionManager::subscribe); the server validates
+//! the request and adds the client to the corresponding rooms.
+//!
+//! The client is notified of the result of each request with an [`InterestResponse`] message.
+use serde::{Deserialize, Serialize};
+
+/// Message sent by a client to subscribe to (or unsubscribe from) an interest set
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InterestRequest {
+    /// Name of the subscription. The part before the first `:` is the name of the interest set,
+    /// the rest is an argument that is passed to the server's validator.
+    pub name: String,
+    /// True to subscribe, false to unsubscribe
+    pub subscribe: bool,
+}
+
+/// Message sent by the server to notify the client of the result of an [`InterestRequest`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InterestResponse {
+    /// Name of the subscription, as sent in the [`InterestRequest`]
+    pub name: String,
+    /// True if the client is subscribed after handling the request
+    pub subscribed: bool,
+}
+
+impl InterestRequest {
+    /// Split the name of the subscription into the name of the interest set and the optional argument
+    ///
+    /// e.g. "spectate:player42" is split into `("spectate", Some("player42"))`
+    pub fn parts(&self) -> (&str, Option<&str>) {
+        match self.name.split_once(':') {
+            Some((set, argument)) => (set, Some(argument)),
+            None => (&self.name, None),
+        }
+    }
+}
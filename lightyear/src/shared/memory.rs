@@ -0,0 +1,471 @@
+//! Bounded memory mode: limits on the memory used by the networking buffers.
+//!
+//! Most of the buffers used by lightyear grow with the network conditions: the send queues of the channels
+//! grow when the bandwidth is too low, the prediction histories grow when no server updates are received,
+//! the replication receiver buffers the updates that are waiting for a missing message, etc.
+//! Some platforms (consoles, mobile) require the memory usage of an application to stay bounded.
+//!
+//! The [`MemoryConfig`] (in the [`SharedConfig`](crate::prelude::SharedConfig)) sets a soft [`MemoryLimit`] for
+//! each subsystem. When a buffer goes over its limit, items are evicted according to the [`EvictionPolicy`]:
+//! - send queues (per channel): buffered messages of the unreliable channels are dropped
+//! - receive queues (per channel): received messages of the unreliable channels that were not read yet are dropped
+//! - prediction histories (per component of each predicted entity): the history entries are dropped. A missing
+//!   history entry triggers a rollback when the next server update is received.
+//! - interpolation buffers (per component of each interpolated entity): the buffered server updates are dropped
+//! - replication groups (per group of each connection): nothing is evicted, the connection is closed instead
+//!
+//! The buffers of the reliable channels are never evicted, because dropping them would break the guarantees
+//! of the channel, but they are counted in the usage. The same goes for the entity updates that are buffered
+//! to be sent: the replication sender considers them sent as soon as they are buffered.
+//!
+//! The replication messages that are waiting to be applied cannot be evicted either: the updates were already
+//! acked at the packet level, so the remote peer would never send the changes they contain again and the entities
+//! would silently stay out of sync. When a replication group goes over its limit, the connection is closed.
+//!
+//! The current usage and the number of evictions of each subsystem are reported in the [`MemoryStats`]
+//! resource, and can be registered as [`Diagnostics`] with the [`MemoryDiagnosticsPlugin`].
+//! The memory used by a buffer is estimated from the size of the serialized messages, or from the size of the
+//! items of the buffer; the heap allocations of the components are not included.
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::*;
+//!
+//! let shared = SharedConfig {
+//!     memory: MemoryConfig::default()
+//!         .with_max_total_bytes(8 * 1024 * 1024)
+//!         .with_send_queues(MemoryLimit::new(256 * 1024))
+//!         .with_prediction_history(MemoryLimit::new(4 * 1024)),
+//!     ..default()
+//! };
+//! ```
+use std::mem::size_of;
+
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::{IntoSystemConfigs, NextState, Res, ResMut, Resource};
+use bevy::reflect::Reflect;
+use bevy::time::common_conditions::on_timer;
+use bevy::utils::{Duration, HashMap};
+use tracing::{error, warn};
+
+use crate::client::config::ClientConfig;
+use crate::client::connection::ConnectionManager as ClientConnectionManager;
+use crate::client::networking::NetworkingState;
+use crate::connection::server::ServerConnections;
+use crate::protocol::component::ComponentKind;
+use crate::server::config::ServerConfig;
+use crate::server::connection::ConnectionManager as ServerConnectionManager;
+use crate::utils::ready_buffer::{ItemWithReadyKey, ReadyBuffer};
+
+/// Which items are dropped when a buffer goes over its [`MemoryLimit`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum EvictionPolicy {
+    /// Drop the oldest items of the buffer
+    #[default]
+    DropOldest,
+    /// Drop the most recent items of the buffer
+    DropNewest,
+}
+
+/// Soft limit on the memory used by each buffer of a subsystem
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct MemoryLimit {
+    /// Maximum number of bytes of a buffer. `None` means that the buffer is unbounded.
+    pub max_bytes: Option<usize>,
+    /// Which items are dropped when the limit is exceeded
+    pub policy: EvictionPolicy,
+}
+
+impl MemoryLimit {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            policy: EvictionPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns true if a buffer using `bytes` is over the limit
+    pub(crate) fn is_exceeded(&self, bytes: usize) -> bool {
+        self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes)
+    }
+}
+
+/// Limits on the memory used by the networking buffers.
+///
+/// All the limits are disabled by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct MemoryConfig {
+    /// Soft limit on the total memory used by all the subsystems. Nothing is evicted when it is exceeded,
+    /// but a warning is logged and [`MemoryStats::is_over_budget`] returns true.
+    pub max_total_bytes: Option<usize>,
+    /// Limit on the messages buffered to be sent, for each channel of each connection
+    pub send_queues: MemoryLimit,
+    /// Limit on the messages received but not read yet, for each channel of each connection
+    pub receive_queues: MemoryLimit,
+    /// Limit on the prediction history of each component of each predicted entity
+    pub prediction_history: MemoryLimit,
+    /// Limit on the buffered server updates of each component of each interpolated entity
+    pub interpolation_buffers: MemoryLimit,
+    /// Limit on the replication messages that are waiting to be applied, for each replication group
+    /// of each connection.
+    ///
+    /// The connection is closed when a group goes over the limit; the [`EvictionPolicy`] is not used.
+    pub replication_groups: MemoryLimit,
+}
+
+impl MemoryConfig {
+    pub fn with_max_total_bytes(mut self, max_total_bytes: usize) -> Self {
+        self.max_total_bytes = Some(max_total_bytes);
+        self
+    }
+
+    pub fn with_send_queues(mut self, limit: MemoryLimit) -> Self {
+        self.send_queues = limit;
+        self
+    }
+
+    pub fn with_receive_queues(mut self, limit: MemoryLimit) -> Self {
+        self.receive_queues = limit;
+        self
+    }
+
+    pub fn with_prediction_history(mut self, limit: MemoryLimit) -> Self {
+        self.prediction_history = limit;
+        self
+    }
+
+    pub fn with_interpolation_buffers(mut self, limit: MemoryLimit) -> Self {
+        self.interpolation_buffers = limit;
+        self
+    }
+
+    pub fn with_replication_groups(mut self, limit: MemoryLimit) -> Self {
+        self.replication_groups = limit;
+        self
+    }
+}
+
+/// The subsystems whose memory usage is bounded by the [`MemoryConfig`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum MemorySubsystem {
+    SendQueues,
+    ReceiveQueues,
+    PredictionHistory,
+    InterpolationBuffers,
+    ReplicationGroups,
+}
+
+impl MemorySubsystem {
+    pub const ALL: [MemorySubsystem; 5] = [
+        MemorySubsystem::SendQueues,
+        MemorySubsystem::ReceiveQueues,
+        MemorySubsystem::PredictionHistory,
+        MemorySubsystem::InterpolationBuffers,
+        MemorySubsystem::ReplicationGroups,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemorySubsystem::SendQueues => "send_queues",
+            MemorySubsystem::ReceiveQueues => "receive_queues",
+            MemorySubsystem::PredictionHistory => "prediction_history",
+            MemorySubsystem::InterpolationBuffers => "interpolation_buffers",
+            MemorySubsystem::ReplicationGroups => "replication_groups",
+        }
+    }
+}
+
+/// Memory used by the buffers of a subsystem, measured while enforcing the limits
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct MemoryTally {
+    pub(crate) bytes: usize,
+    pub(crate) evictions: usize,
+}
+
+/// Current memory usage of the networking buffers, and number of items evicted because of the [`MemoryConfig`]
+#[derive(Resource, Debug, Default)]
+pub struct MemoryStats {
+    /// Bytes used by each subsystem. The histories are measured separately for each component.
+    bytes: HashMap<(MemorySubsystem, Option<ComponentKind>), usize>,
+    evictions: HashMap<MemorySubsystem, usize>,
+    over_budget: bool,
+}
+
+impl MemoryStats {
+    /// Estimated number of bytes currently used by the subsystem
+    pub fn bytes(&self, subsystem: MemorySubsystem) -> usize {
+        self.bytes
+            .iter()
+            .filter(|((s, _), _)| *s == subsystem)
+            .map(|(_, bytes)| *bytes)
+            .sum()
+    }
+
+    /// Total number of items that were evicted from the buffers of the subsystem
+    pub fn evictions(&self, subsystem: MemorySubsystem) -> usize {
+        self.evictions.get(&subsystem).copied().unwrap_or_default()
+    }
+
+    /// Estimated number of bytes currently used by all the subsystems
+    pub fn total_bytes(&self) -> usize {
+        self.bytes.values().sum()
+    }
+
+    /// Returns true if the total usage was over [`MemoryConfig::max_total_bytes`] the last time it was checked
+    pub fn is_over_budget(&self) -> bool {
+        self.over_budget
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        subsystem: MemorySubsystem,
+        kind: Option<ComponentKind>,
+        tally: MemoryTally,
+    ) {
+        self.bytes.insert((subsystem, kind), tally.bytes);
+        if tally.evictions > 0 {
+            *self.evictions.entry(subsystem).or_default() += tally.evictions;
+        }
+    }
+
+    /// Check the total usage against the global budget, and warn when the budget starts being exceeded
+    pub(crate) fn check_budget(&mut self, max_total_bytes: Option<usize>) {
+        let total_bytes = self.total_bytes();
+        let over_budget = max_total_bytes.is_some_and(|max| total_bytes > max);
+        if over_budget && !self.over_budget {
+            warn!(
+                ?total_bytes,
+                ?max_total_bytes,
+                "The networking buffers are using more memory than the budget"
+            );
+        }
+        self.over_budget = over_budget;
+    }
+}
+
+/// Evict items from a [`ReadyBuffer`] until it fits in the limit, assuming that all the items have the same size
+pub(crate) fn evict_ready_buffer<K: Ord + Clone, T: PartialEq>(
+    buffer: &mut ReadyBuffer<K, T>,
+    limit: &MemoryLimit,
+    tally: &mut MemoryTally,
+) {
+    let item_bytes = size_of::<ItemWithReadyKey<K, T>>();
+    while limit.is_exceeded(buffer.len() * item_bytes) {
+        let evicted = match limit.policy {
+            EvictionPolicy::DropOldest => buffer.pop_oldest(),
+            EvictionPolicy::DropNewest => buffer.pop_newest(),
+        };
+        if evicted.is_none() {
+            break;
+        }
+        tally.evictions += 1;
+    }
+    tally.bytes += buffer.len() * item_bytes;
+}
+
+/// Enforce the memory limits on the buffers of the connection to the server
+pub(crate) fn enforce_client_memory_limits(
+    config: Res<ClientConfig>,
+    mut connection: ResMut<ClientConnectionManager>,
+    mut stats: ResMut<MemoryStats>,
+    mut next_state: ResMut<NextState<NetworkingState>>,
+) {
+    let config = &config.shared.memory;
+    let mut send = MemoryTally::default();
+    let mut receive = MemoryTally::default();
+    let mut groups = MemoryTally::default();
+    let connection = connection.as_mut();
+    connection
+        .message_manager
+        .enforce_memory_limits(config, &mut send, &mut receive);
+    if connection
+        .replication_receiver
+        .enforce_memory_limit(&config.replication_groups, &mut groups)
+    {
+        error!("The replication messages received from the server exceed the memory limit, disconnecting");
+        next_state.set(NetworkingState::Disconnected);
+    }
+    stats.record(MemorySubsystem::SendQueues, None, send);
+    stats.record(MemorySubsystem::ReceiveQueues, None, receive);
+    stats.record(MemorySubsystem::ReplicationGroups, None, groups);
+    stats.check_budget(config.max_total_bytes);
+}
+
+/// Enforce the memory limits on the buffers of all the client connections
+pub(crate) fn enforce_server_memory_limits(
+    config: Res<ServerConfig>,
+    mut connection_manager: ResMut<ServerConnectionManager>,
+    mut netservers: ResMut<ServerConnections>,
+    mut stats: ResMut<MemoryStats>,
+) {
+    let config = &config.shared.memory;
+    let mut send = MemoryTally::default();
+    let mut receive = MemoryTally::default();
+    let mut groups = MemoryTally::default();
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        connection
+            .message_manager
+            .enforce_memory_limits(config, &mut send, &mut receive);
+        if connection
+            .replication_receiver
+            .enforce_memory_limit(&config.replication_groups, &mut groups)
+        {
+            error!(
+                ?client_id,
+                "The replication messages received from the client exceed the memory limit, disconnecting"
+            );
+            let _ = netservers
+                .disconnect(*client_id)
+                .inspect_err(|e| error!(?client_id, "Could not disconnect the client: {e:?}"));
+        }
+    }
+    stats.record(MemorySubsystem::SendQueues, None, send);
+    stats.record(MemorySubsystem::ReceiveQueues, None, receive);
+    stats.record(MemorySubsystem::ReplicationGroups, None, groups);
+    stats.check_budget(config.max_total_bytes);
+}
+
+/// Plugin that registers the [`MemoryStats`] as [`Diagnostics`]: the bytes used and the number of evictions
+/// of each [`MemorySubsystem`], and the total bytes used.
+pub struct MemoryDiagnosticsPlugin {
+    pub history_len: usize,
+    pub flush_interval: Duration,
+}
+
+impl Default for MemoryDiagnosticsPlugin {
+    fn default() -> Self {
+        Self {
+            history_len: 60,
+            flush_interval: Duration::from_millis(200),
+        }
+    }
+}
+
+impl MemoryDiagnosticsPlugin {
+    /// Total number of bytes used by the networking buffers
+    pub const TOTAL_BYTES: DiagnosticPath = DiagnosticPath::const_new("memory.total_bytes");
+
+    /// Path of the diagnostic that tracks the number of bytes used by the subsystem
+    pub fn bytes(subsystem: MemorySubsystem) -> DiagnosticPath {
+        DiagnosticPath::new(format!("memory.{}.bytes", subsystem.name()))
+    }
+
+    /// Path of the diagnostic that tracks the number of items evicted from the subsystem
+    pub fn evictions(subsystem: MemorySubsystem) -> DiagnosticPath {
+        DiagnosticPath::new(format!("memory.{}.evictions", subsystem.name()))
+    }
+
+    fn add_measurements(stats: Res<MemoryStats>, mut diagnostics: Diagnostics) {
+        for subsystem in MemorySubsystem::ALL {
+            diagnostics.add_measurement(&Self::bytes(subsystem), || stats.bytes(subsystem) as f64);
+            diagnostics.add_measurement(&Self::evictions(subsystem), || {
+                stats.evictions(subsystem) as f64
+            });
+        }
+        diagnostics.add_measurement(&Self::TOTAL_BYTES, || stats.total_bytes() as f64);
+    }
+}
+
+impl Plugin for MemoryDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(
+            Diagnostic::new(Self::TOTAL_BYTES)
+                .with_suffix("B")
+                .with_max_history_length(self.history_len),
+        );
+        for subsystem in MemorySubsystem::ALL {
+            app.register_diagnostic(
+                Diagnostic::new(Self::bytes(subsystem))
+                    .with_suffix("B")
+                    .with_max_history_length(self.history_len),
+            );
+            app.register_diagnostic(
+                Diagnostic::new(Self::evictions(subsystem))
+                    .with_suffix("")
+                    .with_max_history_length(self.history_len),
+            );
+        }
+        app.init_resource::<MemoryStats>();
+        app.add_systems(
+            PostUpdate,
+            Self::add_measurements.run_if(on_timer(self.flush_interval)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::Tick;
+
+    #[test]
+    fn test_evict_ready_buffer() {
+        let item_bytes = size_of::<ItemWithReadyKey<Tick, u64>>();
+        let mut buffer = ReadyBuffer::<Tick, u64>::new();
+        for i in 0..10 {
+            buffer.push(Tick(i), i as u64);
+        }
+
+        // drop the oldest items
+        let mut tally = MemoryTally::default();
+        evict_ready_buffer(&mut buffer, &MemoryLimit::new(8 * item_bytes), &mut tally);
+        assert_eq!(
+            tally,
+            MemoryTally {
+                bytes: 8 * item_bytes,
+                evictions: 2,
+            }
+        );
+        assert_eq!(buffer.pop_oldest(), Some((Tick(2), 2)));
+
+        // drop the most recent items
+        let mut tally = MemoryTally::default();
+        let limit = MemoryLimit::new(5 * item_bytes).with_policy(EvictionPolicy::DropNewest);
+        evict_ready_buffer(&mut buffer, &limit, &mut tally);
+        assert_eq!(tally.evictions, 2);
+        assert_eq!(buffer.pop_newest(), Some((Tick(7), 7)));
+    }
+
+    #[test]
+    fn test_memory_stats() {
+        let mut stats = MemoryStats::default();
+        stats.record(
+            MemorySubsystem::SendQueues,
+            None,
+            MemoryTally {
+                bytes: 100,
+                evictions: 3,
+            },
+        );
+        stats.record(
+            MemorySubsystem::PredictionHistory,
+            Some(ComponentKind::of::<u32>()),
+            MemoryTally {
+                bytes: 50,
+                evictions: 0,
+            },
+        );
+        // the usage is replaced, the evictions are accumulated
+        stats.record(
+            MemorySubsystem::SendQueues,
+            None,
+            MemoryTally {
+                bytes: 20,
+                evictions: 1,
+            },
+        );
+        assert_eq!(stats.bytes(MemorySubsystem::SendQueues), 20);
+        assert_eq!(stats.evictions(MemorySubsystem::SendQueues), 4);
+        assert_eq!(stats.total_bytes(), 70);
+
+        stats.check_budget(Some(60));
+        assert!(stats.is_over_budget());
+        stats.check_budget(None);
+        assert!(!stats.is_over_budget());
+    }
+}
@@ -1,6 +1,14 @@
 use crate::prelude::{Channel, ChannelKind, Message};
+use crate::protocol::registry::NetId;
+use crate::serialize::reader::Reader;
+use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::replication::network_target::NetworkTarget;
+use crate::shared::tick_manager::Tick;
+use crate::utils::ready_buffer::ReadyBuffer;
 use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+use byteorder::WriteBytesExt;
+use bytes::Bytes;
 use std::error::Error;
 
 /// Shared trait between client and server to send messages to a target
@@ -19,3 +27,66 @@ pub(crate) trait MessageSend: Resource {
         target: NetworkTarget,
     ) -> Result<(), Self::Error>;
 }
+
+/// Message that wraps another serialized message, which should only be emitted
+/// once the receiver reaches the given tick
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TickTargetedMessage {
+    pub(crate) tick: Tick,
+    /// The serialized message, starting with its [`NetId`]
+    pub(crate) message: Bytes,
+}
+
+impl ToBytes for TickTargetedMessage {
+    fn len(&self) -> usize {
+        self.tick.len() + self.message.len()
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        self.tick.to_bytes(buffer)?;
+        // NOTE: we just write the message bytes directly! We don't provide the length
+        buffer.write_all(&self.message)?;
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        let tick = Tick::from_bytes(buffer)?;
+        // NOTE: this only works if the reader only contains the TickTargetedMessage bytes!
+        let remaining = buffer.remaining();
+        let message = buffer.split_len(remaining);
+        Ok(Self { tick, message })
+    }
+}
+
+/// Messages that were received with a target tick, and that are held back until that tick is reached
+#[derive(Debug, Default)]
+pub(crate) struct ScheduledMessages {
+    messages: HashMap<NetId, ReadyBuffer<Tick, Bytes>>,
+}
+
+impl ScheduledMessages {
+    /// Buffer the bytes of a [`TickTargetedMessage`]
+    pub(crate) fn receive(&mut self, data: Bytes) -> Result<(), SerializationError> {
+        let message = TickTargetedMessage::from_bytes(&mut Reader::from(data))?;
+        let net_id = NetId::from_bytes(&mut Reader::from(message.message.clone()))?;
+        self.messages
+            .entry(net_id)
+            .or_default()
+            .push(message.tick, message.message);
+        Ok(())
+    }
+
+    /// Return the messages of the given type whose target tick is older or equal to `tick`
+    pub(crate) fn drain(&mut self, net_id: NetId, tick: Tick) -> Vec<Bytes> {
+        self.messages.get_mut(&net_id).map_or(vec![], |buffer| {
+            buffer
+                .drain_until(&tick)
+                .into_iter()
+                .map(|(_, message)| message)
+                .collect()
+        })
+    }
+}
@@ -2,20 +2,38 @@
 
 pub mod config;
 
+pub mod console;
+
+pub mod diagnostics;
+
 pub mod events;
 
+pub mod interest;
+
 pub mod log;
 
+pub mod memory;
+
 pub mod ping;
 
+pub mod proximity;
+
 pub mod plugin;
 
+pub mod presentation;
+
 pub mod replication;
 
+pub mod rng;
+
 pub mod sets;
 
+pub mod sync;
+
 pub mod tick_manager;
 
+pub mod world_seed;
+
 pub mod input;
 pub(crate) mod message;
 pub mod run_conditions;
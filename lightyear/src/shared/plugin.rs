@@ -8,12 +8,23 @@ use bevy::utils::Duration;
 use crate::prelude::client::ComponentSyncMode;
 use crate::prelude::{
     AppComponentExt, AppMessageExt, ChannelDirection, ChannelRegistry, ComponentRegistry,
-    LinkConditionerConfig, MessageRegistry, Mode, ParentSync, PingConfig, PrePredicted,
-    PreSpawnedPlayerObject, ShouldBePredicted, TickConfig,
+    ComponentTombstones, LinkConditionerConfig, MessageRegistry, Mode, ParentSync, PingConfig,
+    PrePredicted, PreSpawnedPlayerObject, PredictionExclusions, ShouldBePredicted, TickConfig,
 };
+use crate::protocol::message::MessageType;
+use crate::protocol::serialize::SerializeFns;
+use crate::serialize::ToBytes;
 use crate::shared::config::SharedConfig;
+use crate::shared::console::{ConsoleCommandRequest, ConsoleCommandResponse};
+use crate::shared::interest::{InterestRequest, InterestResponse};
+use crate::shared::memory::{MemoryConfig, MemoryStats};
+use crate::shared::message::TickTargetedMessage;
 use crate::shared::replication::authority::AuthorityChange;
-use crate::shared::replication::components::{Controlled, ShouldBeInterpolated};
+use crate::shared::replication::components::{
+    Controlled, LocalPlayerId, ReplacesEntity, ShouldBeInterpolated,
+};
+use crate::shared::replication::DespawnGroupsMessage;
+use crate::shared::sync::InterpolationDelayMessage;
 use crate::shared::tick_manager::TickManagerPlugin;
 use crate::shared::time_manager::TimePlugin;
 use crate::transport::io::{IoState, IoStats};
@@ -103,7 +114,8 @@ impl Plugin for SharedPlugin {
             .register_type::<IoStats>()
             .register_type::<IoState>()
             .register_type::<LinkConditionerConfig>()
-            .register_type::<CompressionConfig>();
+            .register_type::<CompressionConfig>()
+            .register_type::<MemoryConfig>();
 
         // PLUGINS
         #[cfg(feature = "avian2d")]
@@ -122,8 +134,13 @@ impl Plugin for SharedPlugin {
                 Duration::default()
             };
         app.insert_resource(ChannelRegistry::new(input_send_interval));
-        app.insert_resource(ComponentRegistry::default());
-        app.insert_resource(MessageRegistry::default());
+        app.init_resource::<MemoryStats>();
+        let mut component_registry = ComponentRegistry::default();
+        component_registry.skip_unknown_types = self.config.skip_unknown_types;
+        app.insert_resource(component_registry);
+        let mut message_registry = MessageRegistry::default();
+        message_registry.skip_unknown_types = self.config.skip_unknown_types;
+        app.insert_resource(message_registry);
         // NOTE: this tick duration must be the same as any previous existing fixed timesteps
         app.insert_resource(Time::<Fixed>::from_seconds(
             self.config.tick.tick_duration.as_secs_f64(),
@@ -148,14 +165,39 @@ impl Plugin for SharedPlugin {
         app.register_component::<PrePredicted>(ChannelDirection::Bidirectional);
         app.register_component::<ShouldBePredicted>(ChannelDirection::ServerToClient);
         app.register_component::<ShouldBeInterpolated>(ChannelDirection::ServerToClient);
+        app.register_component::<ReplacesEntity>(ChannelDirection::ServerToClient)
+            .add_map_entities();
         app.register_component::<ParentSync>(ChannelDirection::Bidirectional)
             .add_map_entities();
         app.register_component::<Controlled>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Once)
             .add_interpolation(ComponentSyncMode::Once);
+        app.register_component::<LocalPlayerId>(ChannelDirection::ServerToClient)
+            .add_prediction(ComponentSyncMode::Once)
+            .add_interpolation(ComponentSyncMode::Once);
+        app.register_component::<ComponentTombstones>(ChannelDirection::ServerToClient);
+        app.register_component::<PredictionExclusions>(ChannelDirection::ServerToClient);
 
         app.register_message::<AuthorityChange>(ChannelDirection::ServerToClient)
             .add_map_entities();
+        app.register_message::<DespawnGroupsMessage>(ChannelDirection::ServerToClient);
+        app.register_message::<InterestRequest>(ChannelDirection::ClientToServer);
+        app.register_message::<InterestResponse>(ChannelDirection::ServerToClient);
+        app.register_message::<ConsoleCommandRequest>(ChannelDirection::ClientToServer);
+        app.register_message::<ConsoleCommandResponse>(ChannelDirection::ServerToClient);
+        app.register_message::<InterpolationDelayMessage>(ChannelDirection::ClientToServer);
+        // the wrapped message is emitted by the receiver's `ConnectionManager`, so we don't need the
+        // systems added by `register_message`
+        app.world_mut()
+            .resource_mut::<MessageRegistry>()
+            .add_message_custom_serde::<TickTargetedMessage>(
+                MessageType::TickTargeted,
+                SerializeFns {
+                    serialize: |message, writer| message.to_bytes(writer),
+                    deserialize: TickTargetedMessage::from_bytes,
+                    serialize_map_entities: None,
+                },
+            );
 
         // check that the protocol was built correctly
         app.world().resource::<ComponentRegistry>().check();
@@ -0,0 +1,302 @@
+//! Cosmetic events (animations, sounds, particles, etc.) sent from the server to the clients.
+//!
+//! These events are usually tiny and numerous, and it doesn't matter if a few of them are lost or arrive a bit late.
+//! Sending them on a reliable channel wastes bandwidth (every lost packet is resent long after the event became
+//! irrelevant), and sending each of them as a separate message adds a lot of overhead.
+//!
+//! The [`PresentationEventsPlugin`] sends the events on the [`PresentationChannel`], an unreliable channel with a
+//! low priority where the messages that could not be sent in time are dropped. On the server, the events are
+//! buffered in the [`PresentationEvents`] resource:
+//! - identical events emitted for the same entity during the same frame are only sent once
+//! - all the events for a given set of clients are batched in a single message
+//!
+//! On the client, each event is emitted as a [`PresentationEvent`], with the entity mapped to the local entity.
+//! The events for entities that have not been replicated to the client are discarded.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//!
+//! #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+//! enum Cosmetic {
+//!     Footstep,
+//!     Hit { damage: u8 },
+//! }
+//!
+//! // add the plugin to both the client and the server apps, after the lightyear plugins
+//! app.add_plugins(PresentationEventsPlugin::<Cosmetic>::default());
+//!
+//! // server
+//! fn footsteps(mut events: ResMut<PresentationEvents<Cosmetic>>, query: Query<Entity, With<Walking>>) {
+//!     for entity in query.iter() {
+//!         events.send(entity, Cosmetic::Footstep);
+//!     }
+//! }
+//!
+//! // client
+//! fn play_sounds(mut events: EventReader<PresentationEvent<Cosmetic>>) {
+//!     for event in events.read() {
+//!         play_sound(event.entity, &event.event);
+//!     }
+//! }
+//! ```
+use std::marker::PhantomData;
+
+use bevy::ecs::entity::MapEntities;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{error, trace};
+
+use crate::channel::builder::PresentationChannel;
+use crate::client::config::ClientConfig;
+use crate::prelude::server::is_started;
+use crate::prelude::{
+    client, server, AppMessageExt, ChannelDirection, Message, NetworkTarget, Replicated,
+};
+use crate::server::config::ServerConfig;
+use crate::shared::sets::{ClientMarker, InternalMainSet, ServerMarker};
+
+/// Batch of presentation events sent in a single message
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PresentationBatch<E> {
+    pub events: Vec<(Entity, E)>,
+}
+
+impl<E> MapEntities for PresentationBatch<E> {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.events.iter_mut().for_each(|(entity, _)| {
+            *entity = entity_mapper.map_entity(*entity);
+        });
+    }
+}
+
+/// Buffer of the presentation events that will be sent by the server at the end of the frame
+#[derive(Resource, Debug)]
+pub struct PresentationEvents<E> {
+    buffer: Vec<(NetworkTarget, Entity, E)>,
+}
+
+impl<E> Default for PresentationEvents<E> {
+    fn default() -> Self {
+        Self { buffer: Vec::new() }
+    }
+}
+
+impl<E: PartialEq> PresentationEvents<E> {
+    /// Send an event about an entity to all the clients
+    pub fn send(&mut self, entity: Entity, event: E) {
+        self.send_to_target(entity, event, NetworkTarget::All);
+    }
+
+    /// Send an event about an entity to some clients.
+    ///
+    /// The event is ignored if the same event was already sent for this entity and these clients during the frame.
+    pub fn send_to_target(&mut self, entity: Entity, event: E, target: NetworkTarget) {
+        if self
+            .buffer
+            .iter()
+            .any(|(t, e, ev)| *e == entity && *ev == event && *t == target)
+        {
+            return;
+        }
+        self.buffer.push((target, entity, event));
+    }
+
+    /// Number of events that will be sent at the end of the frame
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+}
+
+/// Event emitted on the client when a presentation event is received
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct PresentationEvent<E> {
+    /// The local entity that the event is about
+    pub entity: Entity,
+    pub event: E,
+}
+
+/// Plugin that sends the presentation events of type `E` from the server to the clients.
+///
+/// It must be added to both the client and the server apps, after the lightyear plugins, because it
+/// registers the [`PresentationBatch`] message in the protocol.
+pub struct PresentationEventsPlugin<E> {
+    _marker: PhantomData<E>,
+}
+
+impl<E> Default for PresentationEventsPlugin<E> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E: Message + Serialize + DeserializeOwned + Clone + PartialEq> Plugin
+    for PresentationEventsPlugin<E>
+{
+    fn build(&self, app: &mut App) {
+        // PROTOCOL
+        app.register_message::<PresentationBatch<E>>(ChannelDirection::ServerToClient)
+            .add_map_entities();
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        let is_client = app.world().get_resource::<ClientConfig>().is_some();
+        if is_server {
+            app.init_resource::<PresentationEvents<E>>();
+            app.add_systems(
+                PostUpdate,
+                send_presentation_events::<E>
+                    .before(InternalMainSet::<ServerMarker>::Send)
+                    .run_if(is_started),
+            );
+        } else if is_client {
+            app.add_event::<PresentationEvent<E>>();
+            app.add_systems(
+                PreUpdate,
+                receive_presentation_events::<E>.after(InternalMainSet::<ClientMarker>::EmitEvents),
+            );
+        }
+    }
+}
+
+/// Send one batch per target with all the events buffered during the frame
+fn send_presentation_events<E: Message + Serialize + DeserializeOwned + Clone + PartialEq>(
+    mut events: ResMut<PresentationEvents<E>>,
+    mut connection_manager: ResMut<server::ConnectionManager>,
+) {
+    let mut batches: Vec<(NetworkTarget, PresentationBatch<E>)> = vec![];
+    for (target, entity, event) in events.buffer.drain(..) {
+        match batches.iter_mut().find(|(t, _)| *t == target) {
+            Some((_, batch)) => batch.events.push((entity, event)),
+            None => batches.push((
+                target,
+                PresentationBatch {
+                    events: vec![(entity, event)],
+                },
+            )),
+        }
+    }
+    for (target, mut batch) in batches {
+        let _ = connection_manager
+            .send_message_to_target::<PresentationChannel, _>(&mut batch, target)
+            .inspect_err(|e| error!("Could not send the presentation events: {:?}", e));
+    }
+}
+
+fn receive_presentation_events<E: Message + Clone>(
+    mut messages: EventReader<client::MessageEvent<PresentationBatch<E>>>,
+    mut events: EventWriter<PresentationEvent<E>>,
+    replicated: Query<(), With<Replicated>>,
+) {
+    for message in messages.read() {
+        for (entity, event) in message.message().events.iter() {
+            if !replicated.contains(*entity) {
+                trace!(
+                    ?entity,
+                    "Discarding a presentation event for an entity that is not replicated"
+                );
+                continue;
+            }
+            events.send(PresentationEvent {
+                entity: *entity,
+                event: event.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{SharedConfig, TickConfig};
+    use crate::tests::stepper::BevyStepper;
+    use bevy::utils::Duration;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Cosmetic {
+        Footstep,
+        Hit(u8),
+    }
+
+    #[derive(Resource, Default)]
+    struct Received(Vec<PresentationEvent<Cosmetic>>);
+
+    fn collect(
+        mut events: EventReader<PresentationEvent<Cosmetic>>,
+        mut received: ResMut<Received>,
+    ) {
+        received.0.extend(events.read().cloned());
+    }
+
+    #[test]
+    fn test_presentation_events() {
+        let tick_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            },
+            ClientConfig::default(),
+            tick_duration,
+        );
+        stepper
+            .client_app
+            .add_plugins(PresentationEventsPlugin::<Cosmetic>::default());
+        stepper
+            .server_app
+            .add_plugins(PresentationEventsPlugin::<Cosmetic>::default());
+        stepper.client_app.init_resource::<Received>();
+        stepper.client_app.add_systems(Update, collect);
+        stepper.init();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate::default())
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        {
+            let mut events = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<PresentationEvents<Cosmetic>>();
+            events.send(server_entity, Cosmetic::Footstep);
+            // duplicate events are coalesced
+            events.send(server_entity, Cosmetic::Footstep);
+            events.send(server_entity, Cosmetic::Hit(3));
+            assert_eq!(events.len(), 2);
+        }
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert_eq!(
+            stepper.client_app.world().resource::<Received>().0,
+            vec![
+                PresentationEvent {
+                    entity: client_entity,
+                    event: Cosmetic::Footstep,
+                },
+                PresentationEvent {
+                    entity: client_entity,
+                    event: Cosmetic::Hit(3),
+                },
+            ]
+        );
+    }
+}
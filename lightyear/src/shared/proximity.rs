@@ -0,0 +1,238 @@
+//! Low-rate stream of the players that are near each client, for positional voice chat or minimaps.
+//!
+//! The server periodically computes, for each client, the list of the other players that are relevant to it
+//! (according to the network relevance: rooms, interest sets, etc.) and within [`ProximityPlugin::max_distance`].
+//! Only the ids, distances and orientations are sent, so these features can be built without replicating the
+//! full transforms of the entities that are outside of the client's visual interest.
+//!
+//! The player entities are marked on the server with the [`ProximityPlayer`] component, and their position is
+//! read from their [`GlobalTransform`]. The clients receive the list in the [`ProximityMetadata`] resource.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//!
+//! // add the plugin to both the client and the server apps, after the lightyear plugins
+//! app.add_plugins(ProximityPlugin::default());
+//!
+//! // server: mark the entity of each player
+//! commands.entity(player).insert(ProximityPlayer(client_id));
+//!
+//! // client: set the volume of each voice stream
+//! fn update_voice(proximity: Res<ProximityMetadata>) {
+//!     for player in &proximity.players {
+//!         let volume = 1.0 / (1.0 + player.distance);
+//!     }
+//! }
+//! ```
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::channel::builder::ProximityChannel;
+use crate::client::config::ClientConfig;
+use crate::client::events::MessageEvent;
+use crate::connection::id::ClientId;
+use crate::prelude::server::is_started;
+use crate::prelude::{AppMessageExt, ChannelDirection};
+use crate::server::config::ServerConfig;
+use crate::server::connection::ConnectionManager;
+use crate::server::relevance::immediate::{CachedNetworkRelevance, ClientRelevance};
+use crate::shared::replication::components::ReplicationTarget;
+use crate::shared::sets::{ClientMarker, InternalMainSet, ServerMarker};
+
+/// Marks the entity of a player on the server, so that it is included in the [`ProximityMetadata`]
+/// of the clients that are near it
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProximityPlayer(pub ClientId);
+
+/// A player that is near the client
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct NearbyPlayer {
+    pub client_id: ClientId,
+    /// Distance between the player and the client's own player
+    pub distance: f32,
+    /// Position of the player relative to the client's own player, in the local frame of the client's player
+    /// (e.g. to pan the voice of the player to the left or right)
+    pub offset: Vec3,
+    /// Rotation of the player in world space
+    pub rotation: Quat,
+}
+
+/// The players that are near the client, sent periodically by the server
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct ProximityMetadata {
+    /// Sorted by increasing distance
+    pub players: Vec<NearbyPlayer>,
+}
+
+/// Plugin that streams the [`ProximityMetadata`] to the clients.
+///
+/// It must be added to both the client and the server apps, after the lightyear plugins, because it
+/// registers the [`ProximityMetadata`] message in the protocol.
+#[derive(Clone, Debug)]
+pub struct ProximityPlugin {
+    /// How often the server sends the nearby players to each client
+    pub send_interval: Duration,
+    /// Players further than this distance are not included
+    pub max_distance: f32,
+}
+
+impl Default for ProximityPlugin {
+    fn default() -> Self {
+        Self {
+            send_interval: Duration::from_millis(200),
+            max_distance: 50.0,
+        }
+    }
+}
+
+/// Timer and configuration of the proximity stream on the server
+#[derive(Resource, Debug)]
+struct ProximityConfig {
+    timer: Timer,
+    max_distance: f32,
+}
+
+impl Plugin for ProximityPlugin {
+    fn build(&self, app: &mut App) {
+        // PROTOCOL
+        app.register_message::<ProximityMetadata>(ChannelDirection::ServerToClient);
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        let is_client = app.world().get_resource::<ClientConfig>().is_some();
+        if is_server {
+            app.insert_resource(ProximityConfig {
+                timer: Timer::new(self.send_interval, TimerMode::Repeating),
+                max_distance: self.max_distance,
+            });
+            app.add_systems(
+                PostUpdate,
+                send_proximity_metadata
+                    .before(InternalMainSet::<ServerMarker>::Send)
+                    .run_if(is_started),
+            );
+        }
+        if is_client {
+            app.register_type::<ProximityMetadata>();
+            app.init_resource::<ProximityMetadata>();
+            app.add_systems(
+                PreUpdate,
+                receive_proximity_metadata.after(InternalMainSet::<ClientMarker>::EmitEvents),
+            );
+        }
+    }
+}
+
+/// Returns true if the player entity is currently replicated to the client
+fn is_relevant(
+    client_id: ClientId,
+    target: Option<&ReplicationTarget>,
+    relevance: Option<&CachedNetworkRelevance>,
+) -> bool {
+    target.is_some_and(|target| target.target.targets(&client_id))
+        && relevance.map_or(true, |relevance| {
+            relevance
+                .clients_cache
+                .get(&client_id)
+                .is_some_and(|relevance| *relevance != ClientRelevance::Lost)
+        })
+}
+
+fn send_proximity_metadata(
+    time: Res<Time>,
+    mut config: ResMut<ProximityConfig>,
+    players: Query<(
+        &ProximityPlayer,
+        &GlobalTransform,
+        Option<&ReplicationTarget>,
+        Option<&CachedNetworkRelevance>,
+    )>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    if !config.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (listener, listener_transform, _, _) in players.iter() {
+        let client_id = listener.0;
+        if connection_manager.connection(client_id).is_err() {
+            continue;
+        }
+        let (_, listener_rotation, listener_position) =
+            listener_transform.to_scale_rotation_translation();
+        let mut nearby: Vec<NearbyPlayer> = players
+            .iter()
+            .filter(|(player, _, target, relevance)| {
+                player.0 != client_id && is_relevant(client_id, *target, *relevance)
+            })
+            .filter_map(|(player, transform, _, _)| {
+                let (_, rotation, position) = transform.to_scale_rotation_translation();
+                let distance = position.distance(listener_position);
+                (distance <= config.max_distance).then(|| NearbyPlayer {
+                    client_id: player.0,
+                    distance,
+                    offset: listener_rotation.inverse() * (position - listener_position),
+                    rotation,
+                })
+            })
+            .collect();
+        nearby.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        let _ = connection_manager
+            .send_message::<ProximityChannel, _>(
+                client_id,
+                &mut ProximityMetadata { players: nearby },
+            )
+            .inspect_err(|e| error!(?client_id, "Could not send proximity metadata: {:?}", e));
+    }
+}
+
+fn receive_proximity_metadata(
+    mut events: EventReader<MessageEvent<ProximityMetadata>>,
+    mut proximity: ResMut<ProximityMetadata>,
+) {
+    // only the most recent list is relevant
+    if let Some(event) = events.read().last() {
+        *proximity = event.message().clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    #[test]
+    fn test_proximity_metadata() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.add_plugins(ProximityPlugin::default());
+        stepper.server_app.add_plugins(ProximityPlugin::default());
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let other_id = ClientId::Netcode(1000);
+        stepper.server_app.world_mut().spawn((
+            ProximityPlayer(client_id),
+            GlobalTransform::from_xyz(0.0, 0.0, 0.0),
+        ));
+        stepper.server_app.world_mut().spawn((
+            ProximityPlayer(other_id),
+            GlobalTransform::from_xyz(3.0, 4.0, 0.0),
+            Replicate::default(),
+        ));
+        // players that are too far are not included
+        stepper.server_app.world_mut().spawn((
+            ProximityPlayer(ClientId::Netcode(1001)),
+            GlobalTransform::from_xyz(100.0, 0.0, 0.0),
+            Replicate::default(),
+        ));
+
+        let mut elapsed = Duration::default();
+        while elapsed <= ProximityPlugin::default().send_interval * 2 {
+            stepper.frame_step();
+            elapsed += stepper.frame_duration;
+        }
+        let proximity = stepper.client_app.world().resource::<ProximityMetadata>();
+        assert_eq!(proximity.players.len(), 1);
+        assert_eq!(proximity.players[0].client_id, other_id);
+        assert_eq!(proximity.players[0].distance, 5.0);
+    }
+}
@@ -2,13 +2,15 @@
 use std::mem;
 
 use crate::client::replication::send::ReplicateToServer;
-use crate::prelude::{ComponentRegistry, Replicating, ReplicationTarget};
+use crate::prelude::{ComponentRegistry, Replicating, ReplicationGroup, ReplicationTarget};
 use crate::protocol::component::ComponentKind;
+use crate::server::relevance::immediate::CachedNetworkRelevance;
 use crate::shared::replication::authority::HasAuthority;
-use bevy::ecs::archetype::ArchetypeEntity;
-use bevy::ecs::component::{ComponentTicks, StorageType};
+use bevy::ecs::archetype::{Archetype, ArchetypeEntity};
+use bevy::ecs::component::{ComponentTicks, StorageType, Tick as BevyTick};
 use bevy::ecs::storage::{SparseSets, Table};
 use bevy::ptr::Ptr;
+use bevy::utils::Duration;
 use bevy::{
     ecs::{
         archetype::{ArchetypeGeneration, ArchetypeId},
@@ -38,6 +40,12 @@ pub(crate) struct ReplicatedArchetypes<C: Component> {
     /// On the server, we still send replication updates even if we don't have authority, because
     /// we need to relay the changes to other clients.
     has_authority_component_id: Option<ComponentId>,
+    /// ID of the [`CachedNetworkRelevance`] component. Changes in network relevance can trigger
+    /// replication messages, so we need to check this component when skipping unchanged archetypes.
+    relevance_component_id: Option<ComponentId>,
+    /// ID of the [`ReplicationGroup`] component. An entity can be moved to a different group at runtime,
+    /// which triggers replication messages for the new group.
+    group_component_id: Option<ComponentId>,
     /// Highest processed archetype ID.
     generation: ArchetypeGeneration,
 
@@ -67,6 +75,8 @@ impl<C: Component> ReplicatedArchetypes<C> {
             replication_component_id: world.init_component::<ReplicateToServer>(),
             replicating_component_id: world.init_component::<Replicating>(),
             has_authority_component_id: Some(world.init_component::<HasAuthority>()),
+            relevance_component_id: None,
+            group_component_id: None,
             generation: ArchetypeGeneration::initial(),
             archetypes: Vec::new(),
             marker: Default::default(),
@@ -78,6 +88,8 @@ impl<C: Component> ReplicatedArchetypes<C> {
             replication_component_id: world.init_component::<ReplicationTarget>(),
             replicating_component_id: world.init_component::<Replicating>(),
             has_authority_component_id: None,
+            relevance_component_id: Some(world.init_component::<CachedNetworkRelevance>()),
+            group_component_id: Some(world.init_component::<ReplicationGroup>()),
             generation: ArchetypeGeneration::initial(),
             archetypes: Vec::new(),
             marker: Default::default(),
@@ -89,11 +101,74 @@ impl<C: Component> ReplicatedArchetypes<C> {
 pub(crate) struct ReplicatedArchetype {
     pub(crate) id: ArchetypeId,
     pub(crate) components: Vec<ReplicatedComponent>,
+    /// Non-replicated components whose changes can trigger replication messages
+    /// (the replication component, the [`CachedNetworkRelevance`] component, the [`ReplicationGroup`] and
+    /// the `OverrideTargetComponent<C>` of the replicated components)
+    pub(crate) metadata_components: Vec<(ComponentId, StorageType)>,
+    /// True if some changes of the archetype might not have been buffered during the last replication pass
+    /// (for example because the replication group of an entity was not ready to send)
+    pub(crate) pending: bool,
+    /// Number of entities in the archetype during the last replication pass
+    pub(crate) len: usize,
+    /// True if some components of the archetype are sent periodically even if they did not change
+    pub(crate) has_refresh: bool,
+}
+
+impl ReplicatedArchetype {
+    /// Returns true if none of the components that are relevant for replication changed since `last_run`,
+    /// in which case the archetype can be skipped entirely.
+    ///
+    /// The change ticks of table components are read column by column, which is much cheaper than
+    /// fetching the components of each entity individually.
+    ///
+    /// # Safety
+    ///
+    /// `archetype` must be the archetype identified by `self.id`, and `table` must be its table
+    pub(crate) unsafe fn is_unchanged(
+        &self,
+        archetype: &Archetype,
+        table: &Table,
+        sparse_sets: &SparseSets,
+        last_run: BevyTick,
+        this_run: BevyTick,
+    ) -> bool {
+        // components that are refreshed periodically must be checked even if they did not change
+        if self.pending || archetype.len() != self.len || self.has_refresh {
+            return false;
+        }
+        self.components
+            .iter()
+            .map(|component| (component.id, component.storage_type))
+            .chain(self.metadata_components.iter().copied())
+            .all(|(id, storage_type)| match storage_type {
+                StorageType::Table => {
+                    // the table can be shared with other archetypes, which just makes the check more conservative
+                    let column = table.get_column(id).unwrap_unchecked();
+                    column
+                        .get_changed_ticks_slice()
+                        .iter()
+                        .all(|tick| !(*tick.get()).is_newer_than(last_run, this_run))
+                }
+                StorageType::SparseSet => {
+                    let sparse_set = sparse_sets.get(id).unwrap_unchecked();
+                    archetype.entities().iter().all(|entity| {
+                        !sparse_set
+                            .get_ticks(entity.id())
+                            .unwrap_unchecked()
+                            .is_changed(last_run, this_run)
+                    })
+                }
+            })
+    }
 }
 
 pub(crate) struct ReplicatedComponent {
     pub(crate) delta_compression: bool,
     pub(crate) replicate_once: bool,
+    /// Interval at which the component is sent again even if it did not change
+    pub(crate) refresh_interval: Option<Duration>,
+    /// Minimum interval between two updates of the component
+    pub(crate) replication_interval: Option<Duration>,
     pub(crate) override_target: Option<ComponentId>,
     pub(crate) id: ComponentId,
     pub(crate) kind: ComponentKind,
@@ -150,7 +225,23 @@ impl<C: Component> ReplicatedArchetypes<C> {
             let mut replicated_archetype = ReplicatedArchetype {
                 id: archetype.id(),
                 components: Vec::new(),
+                metadata_components: Vec::new(),
+                // make sure that the archetype is fully checked the first time
+                pending: true,
+                len: 0,
+                has_refresh: false,
             };
+            // SAFETY: component IDs obtained from this archetype.
+            std::iter::once(self.replication_component_id)
+                .chain(self.relevance_component_id)
+                .chain(self.group_component_id)
+                .filter(|id| archetype.contains(*id))
+                .for_each(|id| {
+                    let storage_type = unsafe { archetype.get_storage_type(id).unwrap_unchecked() };
+                    replicated_archetype
+                        .metadata_components
+                        .push((id, storage_type));
+                });
             // TODO: pause inserts/updates if Replicating is not present on the entity!
             // add all components of the archetype that are present in the ComponentRegistry, and:
             // - ignore component if the component is disabled (DisabledComponent<C>) is present
@@ -195,9 +286,25 @@ impl<C: Component> ReplicatedArchetypes<C> {
                     // SAFETY: component ID obtained from this archetype.
                     let storage_type =
                         unsafe { archetype.get_storage_type(component).unwrap_unchecked() };
+                    // a change of the override target changes the clients the component is sent to
+                    if let Some(override_target_id) = override_target {
+                        // SAFETY: component ID obtained from this archetype.
+                        let override_storage_type = unsafe {
+                            archetype
+                                .get_storage_type(override_target_id)
+                                .unwrap_unchecked()
+                        };
+                        replicated_archetype
+                            .metadata_components
+                            .push((override_target_id, override_storage_type));
+                    }
+                    let refresh_interval = registry.refresh_interval(kind);
+                    replicated_archetype.has_refresh |= refresh_interval.is_some();
                     replicated_archetype.components.push(ReplicatedComponent {
                         delta_compression,
                         replicate_once,
+                        refresh_interval,
+                        replication_interval: registry.replication_interval(kind),
                         override_target,
                         id: component,
                         kind,
@@ -0,0 +1,220 @@
+//! Capture the replication messages sent to a remote peer, to write assertions in tests.
+//!
+//! Instead of stepping the apps and inspecting the remote world, integration tests can inspect
+//! the exact replication messages that were generated for a client:
+//!
+//! ```rust,ignore
+//! use lightyear::prelude::server::*;
+//!
+//! stepper.server_connection_manager_mut().capture_replication(client_id).unwrap();
+//! stepper.frame_step();
+//! let messages = stepper
+//!     .server_connection_manager_mut()
+//!     .take_captured_replication(client_id)
+//!     .unwrap();
+//! let registry = stepper.server_app.world().resource::<ComponentRegistry>();
+//! assert!(messages[0].is_spawned(entity));
+//! assert_eq!(messages[0].inserted::<Position>(entity, registry), Some(Position(1.0)));
+//! ```
+use bevy::prelude::{Component, Entity};
+use bytes::Bytes;
+
+use crate::packet::message::MessageId;
+use crate::prelude::{ComponentRegistry, Tick};
+use crate::protocol::component::ComponentNetId;
+use crate::serialize::reader::Reader;
+use crate::serialize::ToBytes;
+use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::replication::entity_map::ReceiveEntityMap;
+use crate::shared::replication::{EntityActions, SpawnAction};
+
+/// Type of a captured replication message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapturedMessageKind {
+    /// Message sent on the reliable actions channel (spawns, despawns, inserts, removals, and updates
+    /// of the same group)
+    Actions { message_id: MessageId },
+    /// Message sent on the unreliable updates channel
+    Updates,
+}
+
+/// A replication message that was buffered for a remote peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedReplicationMessage {
+    /// Tick at which the message was buffered
+    pub tick: Tick,
+    pub group_id: ReplicationGroupId,
+    pub kind: CapturedMessageKind,
+    /// The actions for each entity of the group (for update messages, only the `updates` are present)
+    pub(crate) entities: Vec<(Entity, EntityActions)>,
+}
+
+impl CapturedReplicationMessage {
+    pub(crate) fn actions<'a>(
+        tick: Tick,
+        group_id: ReplicationGroupId,
+        message_id: MessageId,
+        actions: impl Iterator<Item = (&'a Entity, &'a EntityActions)>,
+    ) -> Self {
+        Self {
+            tick,
+            group_id,
+            kind: CapturedMessageKind::Actions { message_id },
+            entities: actions
+                .map(|(entity, actions)| (*entity, actions.clone()))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn updates<'a>(
+        tick: Tick,
+        group_id: ReplicationGroupId,
+        updates: impl Iterator<Item = (&'a Entity, &'a Vec<Bytes>)>,
+    ) -> Self {
+        Self {
+            tick,
+            group_id,
+            kind: CapturedMessageKind::Updates,
+            entities: updates
+                .map(|(entity, updates)| {
+                    (
+                        *entity,
+                        EntityActions {
+                            updates: updates.clone(),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// The entities that are included in the message
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().map(|(entity, _)| *entity)
+    }
+
+    fn entity(&self, entity: Entity) -> Option<&EntityActions> {
+        self.entities
+            .iter()
+            .find(|(e, _)| *e == entity)
+            .map(|(_, actions)| actions)
+    }
+
+    /// Returns true if the message spawns the entity
+    pub fn is_spawned(&self, entity: Entity) -> bool {
+        self.entity(entity).is_some_and(|actions| {
+            matches!(actions.spawn, SpawnAction::Spawn | SpawnAction::Reuse(_))
+        })
+    }
+
+    /// Returns true if the message despawns the entity
+    pub fn is_despawned(&self, entity: Entity) -> bool {
+        self.entity(entity)
+            .is_some_and(|actions| actions.spawn == SpawnAction::Despawn)
+    }
+
+    /// The value of the component `C` if it is inserted on the entity by this message
+    pub fn inserted<C: Component>(
+        &self,
+        entity: Entity,
+        registry: &ComponentRegistry,
+    ) -> Option<C> {
+        self.entity(entity)
+            .and_then(|actions| find_component(&actions.insert, registry))
+    }
+
+    /// The value of the component `C` if it is updated on the entity by this message
+    ///
+    /// Delta-compressed updates are ignored.
+    pub fn updated<C: Component>(&self, entity: Entity, registry: &ComponentRegistry) -> Option<C> {
+        self.entity(entity)
+            .and_then(|actions| find_component(&actions.updates, registry))
+    }
+
+    /// Returns true if the component `C` is removed from the entity by this message
+    pub fn is_removed<C: Component>(&self, entity: Entity, registry: &ComponentRegistry) -> bool {
+        let net_id = registry.net_id::<C>();
+        self.entity(entity)
+            .is_some_and(|actions| actions.remove.contains(&net_id))
+    }
+}
+
+/// Find the serialized value of the component `C` in a list of serialized components
+fn find_component<C: Component>(components: &[Bytes], registry: &ComponentRegistry) -> Option<C> {
+    let net_id = registry.net_id::<C>();
+    components.iter().find_map(|bytes| {
+        let mut reader = Reader::from(bytes.clone());
+        // delta-compressed values are serialized with the net id of the delta type, so they are skipped
+        if ComponentNetId::from_bytes(&mut reader).ok()? != net_id {
+            return None;
+        }
+        registry
+            .raw_deserialize::<C>(&mut reader, net_id, &mut ReceiveEntityMap::default())
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::{ConnectionManager, Replicate};
+    use crate::prelude::ClientId;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    fn take_captured(stepper: &mut BevyStepper) -> Vec<CapturedReplicationMessage> {
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .take_captured_replication(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_capture_replication() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .capture_replication(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        let entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(1.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+
+        let messages = take_captured(&mut stepper);
+        assert_eq!(messages.len(), 1);
+        let registry = stepper.server_app.world().resource::<ComponentRegistry>();
+        assert!(matches!(
+            messages[0].kind,
+            CapturedMessageKind::Actions { .. }
+        ));
+        assert!(messages[0].is_spawned(entity));
+        assert_eq!(
+            messages[0].inserted::<ComponentSyncModeFull>(entity, registry),
+            Some(ComponentSyncModeFull(1.0))
+        );
+        assert_eq!(messages[0].tick, stepper.server_tick());
+
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(entity)
+            .insert(ComponentSyncModeFull(2.0));
+        stepper.frame_step();
+        let messages = take_captured(&mut stepper);
+        assert_eq!(messages.len(), 1);
+        let registry = stepper.server_app.world().resource::<ComponentRegistry>();
+        assert_eq!(messages[0].kind, CapturedMessageKind::Updates);
+        assert_eq!(
+            messages[0].updated::<ComponentSyncModeFull>(entity, registry),
+            Some(ComponentSyncModeFull(2.0))
+        );
+    }
+}
@@ -1,11 +1,15 @@
 //! Components used for replication
+use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::ecs::reflect::ReflectComponent;
 use bevy::prelude::{Component, Entity, Reflect};
 use bevy::time::{Timer, TimerMode};
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 use serde::{Deserialize, Serialize};
 
+use crate::channel::builder::{Channel, EntityActionsChannel};
 use crate::connection::id::ClientId;
+use crate::protocol::channel::ChannelKind;
+use crate::protocol::component::{ComponentNetId, ComponentRegistry};
 use crate::serialize::reader::Reader;
 use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::replication::network_target::NetworkTarget;
@@ -32,6 +36,17 @@ impl Replicated {
 #[reflect(Component)]
 pub struct Controlled;
 
+/// Identifies which of the local players of a client controls the entity.
+///
+/// Used for split-screen games, where multiple players share the same client connection: the server
+/// spawns one entity per local player with the same [`ControlledBy`](crate::prelude::server::ControlledBy)
+/// and a different [`LocalPlayerId`], and the client uses it to pick the input map of the player.
+#[derive(
+    Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Component)]
+pub struct LocalPlayerId(pub u8);
+
 /// Marker component to indicate that updates for this entity are being replicated.
 ///
 /// If this component gets removed, the replication will pause.
@@ -39,6 +54,19 @@ pub struct Controlled;
 #[reflect(Component)]
 pub struct Replicating;
 
+/// Marker component that pauses the replication of the updates of an entity from the server, without
+/// despawning it on the clients.
+///
+/// While the entity is paused, its component updates are not sent; the entity spawn/despawn and the
+/// component inserts/removals are still replicated. When the marker is removed, the current value of all
+/// the replicated components is sent again.
+///
+/// This can be used to save bandwidth for entities that are sleeping (physics sleeping, off-screen AI, etc.).
+/// To pause a [`ReplicationGroup`], add the marker to all the entities of the group.
+#[derive(Component, Clone, Copy, Default, PartialEq, Debug, Reflect)]
+#[reflect(Component)]
+pub struct ReplicationPaused;
+
 /// Component that indicates which clients the entity should be replicated to.
 #[derive(Component, Clone, Debug, PartialEq, Reflect)]
 #[reflect(Component)]
@@ -63,6 +91,23 @@ impl Default for ReplicationTarget {
     }
 }
 
+impl ReplicationTarget {
+    /// Start replicating the entity to an additional client.
+    ///
+    /// Only the added client receives the entity spawn and the replicated components; the clients that were
+    /// already in the target are not affected.
+    pub fn add_target(&mut self, client_id: ClientId) {
+        self.target.union(&NetworkTarget::Single(client_id));
+    }
+
+    /// Stop replicating the entity to a client.
+    ///
+    /// Only the removed client receives an entity despawn; the other clients are not affected.
+    pub fn remove_target(&mut self, client_id: ClientId) {
+        self.target.exclude(&NetworkTarget::Single(client_id));
+    }
+}
+
 /// Defines the target entity for the replication.
 ///
 /// This can be used if you want to replicate this entity on an entity that already
@@ -188,6 +233,9 @@ pub enum ReplicationGroupIdBuilder {
 ///
 /// If multiple entities are part of the same replication group, they will be sent together in the same message.
 /// It is guaranteed that these entities will be updated at the same time on the remote world.
+///
+/// On the server, the group of an entity that is already replicated can be changed at runtime by updating
+/// this component: the entity is moved to the new group on the clients, without being despawned.
 #[derive(Component, Debug, Clone, PartialEq, Reflect)]
 #[reflect(Component)]
 pub struct ReplicationGroup {
@@ -211,6 +259,10 @@ pub struct ReplicationGroup {
     /// This is to avoid having to track the send_tick for each replication group separately)
     // TODO: maybe buffer the updates exactly at 30ms, 60ms, 90ms and include the send_tick in the message?
     pub(crate) should_send: bool,
+    /// Channel used to send the entity actions of this group.
+    /// If `None`, the default [`EntityActionsChannel`] is used.
+    #[reflect(ignore)]
+    actions_channel: Option<ChannelKind>,
 }
 
 impl Default for ReplicationGroup {
@@ -220,6 +272,7 @@ impl Default for ReplicationGroup {
             base_priority: 1.0,
             send_frequency: None,
             should_send: true,
+            actions_channel: None,
         }
     }
 }
@@ -231,6 +284,7 @@ impl ReplicationGroup {
             base_priority: 1.0,
             send_frequency: None,
             should_send: true,
+            actions_channel: None,
         }
     }
 
@@ -240,6 +294,7 @@ impl ReplicationGroup {
             base_priority: 1.0,
             send_frequency: None,
             should_send: true,
+            actions_channel: None,
         }
     }
 
@@ -256,6 +311,17 @@ impl ReplicationGroup {
         self.base_priority
     }
 
+    pub(crate) fn actions_channel(&self) -> ChannelKind {
+        self.actions_channel
+            .unwrap_or(ChannelKind::of::<EntityActionsChannel>())
+    }
+
+    /// Set the base priority of the group.
+    ///
+    /// When the bandwidth is limited, the priority of a group accumulates every time it could not be sent,
+    /// and is reset when a message for the group is sent. Groups that have the same priority are sent
+    /// in a round-robin fashion: the group that was sent the least recently goes first, so that every group
+    /// makes progress.
     pub fn set_priority(mut self, priority: f32) -> Self {
         self.base_priority = priority;
         self
@@ -278,8 +344,53 @@ impl ReplicationGroup {
         self.send_frequency = Some(Timer::new(send_frequency, TimerMode::Repeating));
         self
     }
+
+    /// Sets the channel used to send the entity actions (spawns, despawns, component inserts and removals)
+    /// of this [`ReplicationGroup`].
+    ///
+    /// The channel must have been registered with [`add_replication_actions_channel`](crate::prelude::AppChannelExt::add_replication_actions_channel).
+    ///
+    /// By default, actions are sent on the [`EntityActionsChannel`] and the receiver applies them in order.
+    /// A group with frequent spawns/despawns (for example projectiles) could use a
+    /// [`SequencedReliable`](crate::prelude::ChannelMode::SequencedReliable) channel with a lower resend rate instead:
+    /// the receiver will not wait for older actions messages that were superseded by a newer one.
+    ///
+    /// With a sequenced channel, an older actions message that arrives after a newer one is dropped,
+    /// so the actions of an entity must not be split across messages: the entity must be spawned
+    /// with all its replicated components at once, and components inserted or removed after the spawn
+    /// can be lost. Groups whose entities get components inserted or removed over their lifetime
+    /// should use an ordered channel.
+    pub fn set_actions_channel<C: Channel>(mut self) -> Self {
+        self.actions_channel = Some(ChannelKind::of::<C>());
+        self
+    }
 }
 
+/// Additional replication groups that the entity is a member of, on top of its main [`ReplicationGroup`].
+///
+/// This lets groups overlap without duplicating entities: for example an entity can be in its own group
+/// for frequent position updates, and also be part of a "match state" group, so that the changes of the members
+/// of the match state that happen on the same tick are also sent together in one message of that group.
+/// This does not make the updates atomic: the receiver can still apply the update of one member through
+/// its own group before receiving the update of the match state group.
+/// The additional groups are identified by their [`ReplicationGroupId`], which is `ReplicationGroupId(id)`
+/// for a group created with [`ReplicationGroup::new_id(id)`](ReplicationGroup::new_id).
+///
+/// Conflict rules:
+/// - entity actions (spawns, despawns, component inserts and removals) are only sent through the main group
+/// - component updates are sent through the main group and through each additional group. Each update is
+///   therefore sent (and costs bandwidth) once per group, so an entity in `N` additional groups sends its
+///   updates `N + 1` times. Duplicate group ids, or the id of the main group, are only sent once.
+/// - the receiver applies an update received through an additional group only if the entity was already spawned
+///   by its main group, and if the update is more recent than everything already applied to the entity.
+///   Likewise, updates received through the main group are ignored if a more recent update was already applied
+///   through an additional group.
+///
+/// Only the server supports replicating entities through additional groups.
+#[derive(Component, Default, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct AdditionalReplicationGroups(pub Vec<ReplicationGroupId>);
+
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect)]
 pub struct ReplicationGroupId(pub u64);
 
@@ -325,6 +436,24 @@ pub enum NetworkRelevanceMode {
 #[reflect(Component)]
 pub struct ShouldBeInterpolated;
 
+/// Insert this component on a newly replicated entity to indicate that it replaces another
+/// replicated entity (for example when a character is swapped with a ragdoll).
+///
+/// Instead of spawning a new interpolated entity, the client hands over the interpolated entity of the
+/// replaced entity (along with its interpolation history) to the new entity, so that there is no visual pop.
+/// The replaced entity must still exist on the client when the new entity is received, so it should be
+/// despawned on the server a bit after the new entity is spawned (for example one send_interval later).
+/// Otherwise the client falls back to spawning a new interpolated entity.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct ReplacesEntity(pub Entity);
+
+impl MapEntities for ReplacesEntity {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
+
 /// Indicates that an entity was pre-predicted
 // NOTE: we do not map entities for this component, we want to receive the entities as is
 //  because we already do the mapping at other steps
@@ -338,3 +467,41 @@ pub struct PrePredicted {
 #[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Reflect)]
 #[reflect(Component)]
 pub struct ShouldBePredicted;
+
+/// Components of a predicted entity that the client should not predict.
+///
+/// The excluded components are copied from the Confirmed entity to the Predicted entity every time they are
+/// updated (like with [`ComponentSyncMode::Simple`](crate::client::components::ComponentSyncMode::Simple)):
+/// they don't have a prediction history, and a mismatch with the server state never triggers a rollback.
+/// This can be used to avoid rollback storms in crowded scenes where every client predicts every entity,
+/// by only predicting the components that matter for the gameplay of a client.
+///
+/// To exclude only some clients from predicting the components, combine it with an
+/// [`OverrideTargetComponent`] so that the exclusions are only replicated to these clients:
+/// ```rust,ignore
+/// commands.entity(entity).insert((
+///     PredictionExclusions::default().exclude::<Rotation>(&component_registry),
+///     OverrideTargetComponent::<PredictionExclusions>::new(NetworkTarget::AllExceptSingle(owner)),
+/// ));
+/// ```
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct PredictionExclusions {
+    components: Vec<ComponentNetId>,
+}
+
+impl PredictionExclusions {
+    /// Exclude the component `C` from prediction
+    pub fn exclude<C: Component>(mut self, component_registry: &ComponentRegistry) -> Self {
+        let net_id = component_registry.net_id::<C>();
+        if !self.components.contains(&net_id) {
+            self.components.push(net_id);
+        }
+        self
+    }
+
+    /// Returns true if the component `C` is excluded from prediction
+    pub fn is_excluded<C: Component>(&self, component_registry: &ComponentRegistry) -> bool {
+        self.components.contains(&component_registry.net_id::<C>())
+    }
+}
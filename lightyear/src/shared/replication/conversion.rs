@@ -0,0 +1,194 @@
+//! Replicate a component through another representation.
+//!
+//! Some projects move their entities with the bevy [`Transform`](bevy::prelude::Transform), others with the
+//! `Position`/`Rotation` components of a physics engine, and mixed setups end up replicating both representations.
+//! A [`ReplicationConversion`] lets the sender keep its own components while only one representation (the "wire"
+//! component, which must be registered in the protocol) is replicated:
+//! - on the entities that are replicated by the local peer, the wire component is updated from the local component
+//!   whenever the local component changes
+//! - on the entities that are received from the remote peer, the local component is updated from the wire component
+//!   whenever the wire component changes
+//!
+//! Several local components can write into different parts of the same wire component:
+//! ```rust,ignore
+//! use lightyear::prelude::*;
+//!
+//! app.register_component::<Transform>(ChannelDirection::ServerToClient);
+//! app.add_replication_conversion(ReplicationConversion::<Position, Transform> {
+//!     to_wire: |position, transform| transform.translation = position.extend(0.0),
+//!     from_wire: |transform, position| position.0 = transform.translation.truncate(),
+//! });
+//! app.add_replication_conversion(ReplicationConversion::<Rotation, Transform> {
+//!     to_wire: |rotation, transform| transform.rotation = Quat::from_rotation_z(rotation.as_radians()),
+//!     from_wire: |transform, rotation| *rotation = Rotation::radians(transform.rotation.to_euler(EulerRot::XYZ).2),
+//! });
+//! ```
+//! Conversions between the avian components and [`Transform`](bevy::prelude::Transform) are available in
+//! `lightyear::utils::avian2d` and `lightyear::utils::avian3d`, with the corresponding features.
+use bevy::prelude::*;
+
+use crate::shared::replication::components::Replicating;
+use crate::shared::sets::{ClientMarker, InternalReplicationSet, MainSet, ServerMarker};
+
+/// Mapping between a local component `L` and the component `W` that is replicated in its place
+pub struct ReplicationConversion<L, W> {
+    /// Update the wire component from the local component, on the sender
+    pub to_wire: fn(&L, &mut W),
+    /// Update the local component from the wire component, on the receiver
+    pub from_wire: fn(&W, &mut L),
+}
+
+impl<L, W> Clone for ReplicationConversion<L, W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L, W> Copy for ReplicationConversion<L, W> {}
+
+impl<L, W> ReplicationConversion<L, W> {
+    /// The same mapping, where `W` is the local component and `L` is replicated
+    pub fn reverse(self) -> ReplicationConversion<W, L> {
+        ReplicationConversion {
+            to_wire: self.from_wire,
+            from_wire: self.to_wire,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Conversion<L, W>(ReplicationConversion<L, W>);
+
+pub trait AppReplicationConversionExt {
+    /// Replicate the component `L` through the component `W`.
+    ///
+    /// `W` must be registered in the protocol; `L` does not need to be.
+    fn add_replication_conversion<L, W>(&mut self, conversion: ReplicationConversion<L, W>)
+    where
+        L: Component + Clone + Default,
+        W: Component + Default;
+}
+
+impl AppReplicationConversionExt for App {
+    fn add_replication_conversion<L, W>(&mut self, conversion: ReplicationConversion<L, W>)
+    where
+        L: Component + Clone + Default,
+        W: Component + Default,
+    {
+        self.insert_resource(Conversion(conversion));
+        self.add_systems(
+            PreUpdate,
+            local_from_wire::<L, W>.after(MainSet::EmitEvents),
+        );
+        self.add_systems(
+            PostUpdate,
+            wire_from_local::<L, W>
+                .before(InternalReplicationSet::<ServerMarker>::Buffer)
+                .before(InternalReplicationSet::<ClientMarker>::Buffer),
+        );
+    }
+}
+
+/// Update the wire component of the entities replicated by the local peer
+fn wire_from_local<L: Component + Clone, W: Component + Default>(
+    conversion: Res<Conversion<L, W>>,
+    mut commands: Commands,
+    mut query: Query<(Entity, Ref<L>, Option<&mut W>), With<Replicating>>,
+) {
+    let to_wire = conversion.0.to_wire;
+    for (entity, local, wire) in query.iter_mut() {
+        match wire {
+            Some(mut wire) => {
+                if local.is_changed() {
+                    to_wire(&local, &mut wire);
+                }
+            }
+            None => {
+                // another conversion might insert the same wire component this frame, so we check again
+                // when the command is applied
+                let local = local.clone();
+                commands
+                    .entity(entity)
+                    .add(
+                        move |mut entity_mut: EntityWorldMut| match entity_mut.get_mut::<W>() {
+                            Some(mut wire) => to_wire(&local, &mut wire),
+                            None => {
+                                let mut wire = W::default();
+                                to_wire(&local, &mut wire);
+                                entity_mut.insert(wire);
+                            }
+                        },
+                    );
+            }
+        }
+    }
+}
+
+/// Update the local component of the entities received from the remote peer
+fn local_from_wire<L: Component + Default, W: Component>(
+    conversion: Res<Conversion<L, W>>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &W, Option<&mut L>), (Changed<W>, Without<Replicating>)>,
+) {
+    let from_wire = conversion.0.from_wire;
+    for (entity, wire, local) in query.iter_mut() {
+        match local {
+            Some(mut local) => from_wire(wire, &mut local),
+            None => {
+                let mut local = L::default();
+                from_wire(wire, &mut local);
+                commands.entity(entity).insert(local);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::Replicated;
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    /// Component that is not registered in the protocol
+    #[derive(Component, Clone, Default, Debug, PartialEq)]
+    struct LocalValue(f32);
+
+    #[test]
+    fn test_replication_conversion() {
+        let mut stepper = BevyStepper::default();
+        let conversion = ReplicationConversion::<LocalValue, ComponentSyncModeFull> {
+            to_wire: |local, wire| wire.0 = local.0,
+            from_wire: |wire, local| local.0 = wire.0,
+        };
+        stepper.server_app.add_replication_conversion(conversion);
+        stepper.client_app.add_replication_conversion(conversion);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), LocalValue(1.0)))
+            .id();
+        for _ in 0..3 {
+            stepper.frame_step();
+        }
+        let mut query = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<&LocalValue, With<Replicated>>();
+        assert_eq!(query.single(stepper.client_app.world()), &LocalValue(1.0));
+
+        // changes of the local component are replicated
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<LocalValue>(server_entity)
+            .unwrap()
+            .0 = 2.0;
+        for _ in 0..3 {
+            stepper.frame_step();
+        }
+        assert_eq!(query.single(stepper.client_app.world()), &LocalValue(2.0));
+    }
+}
@@ -0,0 +1,279 @@
+//! Identify the replicated entities in the logs, to debug the replication.
+//!
+//! The same entity has a different [`Entity`] in the server world and in each client world, which makes it hard
+//! to correlate the logs of the client and the server for a given entity.
+//!
+//! When the [`ReplicationDebugPlugin`] is added:
+//! - the server assigns a stable [`NetworkEntityId`] to every replicated entity, which is replicated to the clients
+//! - the entities received from a remote peer are tagged with the [`RemoteEntity`] component, which contains
+//!   the [`Entity`] in the remote world
+//! - the [`NetworkEntityIndex`] resource maps the [`NetworkEntityId`]s to the local entities, and back
+//!
+//! The replication receiver logs every entity action or update in a `replicated_entity` span that contains the
+//! `remote_entity`, the `local_entity`, and the `network_id` of the entity (if it has one), so you can filter the
+//! logs of the client and the server on the same `network_id`.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//!
+//! // add the plugin to both the client and the server apps, after the lightyear plugins
+//! app.add_plugins(ReplicationDebugPlugin);
+//!
+//! fn inspect(index: Res<NetworkEntityIndex>) {
+//!     if let Some(entity) = index.entity(NetworkEntityId(3)) {
+//!         info!(?entity, "local entity with network id 3");
+//!     }
+//! }
+//! ```
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::client::config::ClientConfig;
+use crate::prelude::{client, server, AppComponentExt, ChannelDirection, Replicated, Replicating};
+use crate::server::config::ServerConfig;
+use crate::shared::sets::{ClientMarker, InternalMainSet, InternalReplicationSet, ServerMarker};
+
+/// Stable identifier of a replicated entity, assigned by the server.
+///
+/// It is identical on the server and on all the clients, unlike the [`Entity`].
+#[derive(
+    Component,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Reflect,
+)]
+#[reflect(Component)]
+pub struct NetworkEntityId(pub u64);
+
+/// The [`Entity`] in the remote world that a replicated entity corresponds to
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct RemoteEntity(pub Entity);
+
+/// Lookups between the [`NetworkEntityId`]s and the local entities
+#[derive(Resource, Default, Debug)]
+pub struct NetworkEntityIndex {
+    to_local: HashMap<NetworkEntityId, Entity>,
+    to_network: EntityHashMap<NetworkEntityId>,
+    /// Id that will be assigned to the next replicated entity (only used on the server)
+    next_id: u64,
+}
+
+impl NetworkEntityIndex {
+    /// Get the local entity that has the given [`NetworkEntityId`]
+    pub fn entity(&self, id: NetworkEntityId) -> Option<Entity> {
+        self.to_local.get(&id).copied()
+    }
+
+    /// Get the [`NetworkEntityId`] of a local entity
+    pub fn network_id(&self, entity: Entity) -> Option<NetworkEntityId> {
+        self.to_network.get(&entity).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.to_local.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_local.is_empty()
+    }
+
+    fn insert(&mut self, id: NetworkEntityId, entity: Entity) {
+        if let Some(previous) = self.to_network.insert(entity, id) {
+            self.to_local.remove(&previous);
+        }
+        self.to_local.insert(id, entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(id) = self.to_network.remove(&entity) {
+            self.to_local.remove(&id);
+        }
+    }
+}
+
+/// Plugin that tags the replicated entities with ids that can be used to debug the replication.
+///
+/// It must be added to both the client and the server apps, after the lightyear plugins, because it
+/// registers the [`NetworkEntityId`] component in the protocol.
+#[derive(Default)]
+pub struct ReplicationDebugPlugin;
+
+impl Plugin for ReplicationDebugPlugin {
+    fn build(&self, app: &mut App) {
+        // TYPES
+        app.register_type::<(NetworkEntityId, RemoteEntity)>();
+        // PROTOCOL
+        app.register_component::<NetworkEntityId>(ChannelDirection::ServerToClient);
+        app.init_resource::<NetworkEntityIndex>();
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        let is_client = app.world().get_resource::<ClientConfig>().is_some();
+        if is_server {
+            app.add_systems(
+                PreUpdate,
+                tag_server_remote_entities.after(InternalMainSet::<ServerMarker>::Receive),
+            );
+            app.add_systems(
+                PostUpdate,
+                (assign_network_ids, update_index)
+                    .chain()
+                    .before(InternalReplicationSet::<ServerMarker>::All),
+            );
+        } else if is_client {
+            app.add_systems(
+                PreUpdate,
+                (tag_client_remote_entities, update_index)
+                    .after(InternalMainSet::<ClientMarker>::Receive),
+            );
+        }
+    }
+}
+
+/// Assign a [`NetworkEntityId`] to the entities that start being replicated
+fn assign_network_ids(
+    mut commands: Commands,
+    mut index: ResMut<NetworkEntityIndex>,
+    query: Query<Entity, (Added<Replicating>, Without<NetworkEntityId>)>,
+) {
+    for entity in query.iter() {
+        let id = NetworkEntityId(index.next_id);
+        index.next_id += 1;
+        commands.entity(entity).insert(id);
+    }
+}
+
+/// Keep the [`NetworkEntityIndex`] in sync with the [`NetworkEntityId`] components
+fn update_index(
+    mut index: ResMut<NetworkEntityIndex>,
+    query: Query<(Entity, &NetworkEntityId), Changed<NetworkEntityId>>,
+    mut removed: RemovedComponents<NetworkEntityId>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+    for (entity, id) in query.iter() {
+        index.insert(*id, entity);
+    }
+}
+
+fn tag_client_remote_entities(
+    mut commands: Commands,
+    connection_manager: Res<client::ConnectionManager>,
+    query: Query<Entity, (Added<Replicated>, Without<RemoteEntity>)>,
+) {
+    for entity in query.iter() {
+        if let Some(remote_entity) = connection_manager
+            .replication_receiver
+            .remote_entity_map
+            .get_remote(entity)
+        {
+            commands.entity(entity).insert(RemoteEntity(remote_entity));
+        }
+    }
+}
+
+fn tag_server_remote_entities(
+    mut commands: Commands,
+    connection_manager: Res<server::ConnectionManager>,
+    query: Query<(Entity, &Replicated), (Added<Replicated>, Without<RemoteEntity>)>,
+) {
+    for (entity, replicated) in query.iter() {
+        if let Some(remote_entity) = replicated
+            .from
+            .and_then(|client_id| connection_manager.connection(client_id).ok())
+            .and_then(|connection| {
+                connection
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_remote(entity)
+            })
+        {
+            commands.entity(entity).insert(RemoteEntity(remote_entity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{SharedConfig, TickConfig};
+    use crate::tests::stepper::BevyStepper;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn test_network_entity_ids() {
+        let tick_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            },
+            ClientConfig::default(),
+            tick_duration,
+        );
+        stepper.client_app.add_plugins(ReplicationDebugPlugin);
+        stepper.server_app.add_plugins(ReplicationDebugPlugin);
+        stepper.init();
+
+        let server_entity_a = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate::default())
+            .id();
+        let server_entity_b = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate::default())
+            .id();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        let server_index = stepper.server_app.world().resource::<NetworkEntityIndex>();
+        let id_a = server_index.network_id(server_entity_a).unwrap();
+        let id_b = server_index.network_id(server_entity_b).unwrap();
+        assert_ne!(id_a, id_b);
+        assert_eq!(server_index.entity(id_a), Some(server_entity_a));
+
+        // the client finds the same entity from the network id
+        let client_index = stepper.client_app.world().resource::<NetworkEntityIndex>();
+        let client_entity_a = client_index.entity(id_a).unwrap();
+        assert_eq!(client_index.network_id(client_entity_a), Some(id_a));
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<RemoteEntity>(client_entity_a),
+            Some(&RemoteEntity(server_entity_a))
+        );
+
+        // the index is updated when the entity is despawned
+        stepper.server_app.world_mut().despawn(server_entity_a);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<NetworkEntityIndex>()
+                .entity(id_a),
+            None
+        );
+        let client_index = stepper.client_app.world().resource::<NetworkEntityIndex>();
+        assert_eq!(client_index.entity(id_a), None);
+        assert_eq!(client_index.len(), 1);
+    }
+}
@@ -40,6 +40,8 @@ pub struct DeltaMessage<M> {
 /// - your component contains a hashmap, and your delta is `Add(key, value)` and `Remove(key)`
 /// - your component is a struct with multiple fields, and your delta only contains data for the fields that changed.
 ///   (to avoid sending the full struct every time over the network)
+///
+/// The last case can be derived with [`DeltaReplicate`](crate::prelude::DeltaReplicate).
 pub trait Diffable: Clone {
     // /// Set to true if the Deltas are idempotent (applying the same delta multiple times has no effect)
     // const IDEMPOTENT: bool;
@@ -1,5 +1,5 @@
 //! Map between local and remote entities
-use bevy::ecs::entity::{EntityHashMap, EntityMapper};
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet, EntityMapper};
 use bevy::prelude::{Deref, DerefMut, Entity, EntityWorldMut, World};
 use bevy::reflect::Reflect;
 
@@ -16,31 +16,62 @@ impl EntityMapper for EntityMap {
 }
 
 #[derive(Default, Debug, Reflect, Deref, DerefMut)]
-pub struct SendEntityMap(pub(crate) EntityHashMap<Entity>);
+pub struct SendEntityMap {
+    #[deref]
+    pub(crate) map: EntityHashMap<Entity>,
+    /// Ids used on the network for some of our local entities (for example to keep the ids
+    /// stable after the server World was reloaded)
+    pub(crate) aliases: EntityHashMap<Entity>,
+}
+
+impl SendEntityMap {
+    /// Return the id that is used on the network for one of our local entities
+    pub(crate) fn network_entity(&self, local_entity: Entity) -> Entity {
+        self.aliases
+            .get(&local_entity)
+            .copied()
+            .unwrap_or(local_entity)
+    }
+}
 
 impl EntityMapper for SendEntityMap {
     /// Try to map the entity using the map, or return the initial entity if it doesn't work
     fn map_entity(&mut self, entity: Entity) -> Entity {
         // if the entity was mapped, mark it as mapped so we don't map it again on the receive side
-        if let Some(mapped) = self.0.get(&entity) {
+        if let Some(mapped) = self.map.get(&entity) {
             RemoteEntityMap::mark_mapped(*mapped)
         } else {
-            entity
+            self.network_entity(entity)
         }
     }
 }
 
 #[derive(Default, Debug, Reflect, Deref, DerefMut)]
-pub struct ReceiveEntityMap(pub(crate) EntityHashMap<Entity>);
+pub struct ReceiveEntityMap {
+    #[deref]
+    pub(crate) map: EntityHashMap<Entity>,
+    /// Map from the network id of some of our local entities to the local entity
+    pub(crate) aliases: EntityHashMap<Entity>,
+}
+
+impl ReceiveEntityMap {
+    /// Return the local entity corresponding to an entity id that we sent on the network
+    pub(crate) fn local_entity(&self, network_entity: Entity) -> Entity {
+        self.aliases
+            .get(&network_entity)
+            .copied()
+            .unwrap_or(network_entity)
+    }
+}
 
 impl EntityMapper for ReceiveEntityMap {
     /// Try to map the entity using the map, or return the initial entity if it doesn't work
     fn map_entity(&mut self, entity: Entity) -> Entity {
         // if the entity was already mapped on the send side, we don't need to map it again
         if RemoteEntityMap::is_mapped(entity) {
-            RemoteEntityMap::mark_unmapped(entity)
+            self.local_entity(RemoteEntityMap::mark_unmapped(entity))
         } else {
-            self.0.get(&entity).copied().unwrap_or(entity)
+            self.map.get(&entity).copied().unwrap_or(entity)
         }
     }
 }
@@ -50,6 +81,22 @@ impl EntityMapper for ReceiveEntityMap {
 pub struct RemoteEntityMap {
     pub(crate) remote_to_local: ReceiveEntityMap,
     pub(crate) local_to_remote: SendEntityMap,
+    /// If true, the changes to the mapping are recorded in `changes`
+    pub(crate) record_changes: bool,
+    /// Mappings that were added or removed since the last time the changes were drained
+    #[reflect(ignore)]
+    pub(crate) changes: Vec<EntityMapChange>,
+    /// Remote entities that were mapped manually to a local entity, and for which
+    /// we haven't received the spawn yet
+    #[reflect(ignore)]
+    pub(crate) manual: EntityHashSet,
+}
+
+/// A change to the [`RemoteEntityMap`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum EntityMapChange {
+    Inserted { remote: Entity, local: Entity },
+    Removed { remote: Entity, local: Entity },
 }
 
 #[derive(Default, Debug, Reflect)]
@@ -72,6 +119,39 @@ impl RemoteEntityMap {
     pub fn insert(&mut self, remote_entity: Entity, local_entity: Entity) {
         self.remote_to_local.insert(remote_entity, local_entity);
         self.local_to_remote.insert(local_entity, remote_entity);
+        if self.record_changes {
+            self.changes.push(EntityMapChange::Inserted {
+                remote: remote_entity,
+                local: local_entity,
+            });
+        }
+    }
+
+    /// Insert a mapping between a remote entity and an existing local entity, before the remote entity
+    /// has been replicated.
+    ///
+    /// When the spawn for the remote entity is received, it will be applied on the local entity.
+    pub(crate) fn insert_manual(&mut self, remote_entity: Entity, local_entity: Entity) {
+        // remove any previous mapping so that the maps stay consistent
+        if let Some(previous_local) = self.remote_to_local.get(&remote_entity).copied() {
+            self.local_to_remote.remove(&previous_local);
+        }
+        if let Some(previous_remote) = self.local_to_remote.get(&local_entity).copied() {
+            self.remote_to_local.remove(&previous_remote);
+            self.manual.remove(&previous_remote);
+        }
+        self.insert(remote_entity, local_entity);
+        self.manual.insert(remote_entity);
+    }
+
+    /// If the remote entity was mapped manually and has not been spawned yet, return the local entity
+    /// it was mapped to.
+    pub(crate) fn take_manual_mapping(&mut self, remote_entity: Entity) -> Option<Entity> {
+        if self.manual.remove(&remote_entity) {
+            self.remote_to_local.get(&remote_entity).copied()
+        } else {
+            None
+        }
     }
 
     // pub(crate) fn get_to_remote_mapper(&self) -> Box<dyn EntityMapper + '_> {
@@ -92,7 +172,7 @@ impl RemoteEntityMap {
         // the remote_entity is actually local, because it has already been mapped!
         let unmapped = Self::mark_unmapped(remote_entity);
         if Self::is_mapped(remote_entity) {
-            return Some(unmapped);
+            return Some(self.remote_to_local.local_entity(unmapped));
         };
         self.remote_to_local.get(&unmapped).copied()
     }
@@ -129,7 +209,7 @@ impl RemoteEntityMap {
         if let Some(remote_entity) = self.local_to_remote.get(&local_entity) {
             Self::mark_mapped(*remote_entity)
         } else {
-            local_entity
+            self.local_to_remote.network_entity(local_entity)
         }
     }
 
@@ -156,22 +236,46 @@ impl RemoteEntityMap {
             let local = Self::mark_unmapped(remote_entity);
             if let Some(remote) = self.local_to_remote.remove(&local) {
                 self.remote_to_local.remove(&remote);
+                self.record_removal(remote, local);
                 return Some(local);
             }
         } else if let Some(local) = self.remote_to_local.remove(&remote_entity) {
             self.local_to_remote.remove(&local);
+            self.manual.remove(&remote_entity);
+            self.record_removal(remote_entity, local);
             return Some(local);
         }
         None
     }
 
+    fn record_removal(&mut self, remote: Entity, local: Entity) {
+        if self.record_changes {
+            self.changes
+                .push(EntityMapChange::Removed { remote, local });
+        }
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.remote_to_local.is_empty() && self.local_to_remote.is_empty()
     }
 
-    fn clear(&mut self) {
+    /// Use different ids on the network for some of our local entities.
+    ///
+    /// `local_to_network` maps the local entity to the id that is used on the network.
+    pub(crate) fn set_aliases(&mut self, local_to_network: &EntityHashMap<Entity>) {
+        self.local_to_remote.aliases = local_to_network.clone();
+        self.remote_to_local.aliases = local_to_network
+            .iter()
+            .map(|(local, network)| (*network, *local))
+            .collect();
+    }
+
+    pub(crate) fn clear(&mut self) {
         self.local_to_remote.clear();
         self.remote_to_local.clear();
+        self.local_to_remote.aliases.clear();
+        self.remote_to_local.aliases.clear();
+        self.manual.clear();
     }
 }
 
@@ -181,7 +285,7 @@ mod tests {
     use crate::prelude::*;
     use crate::tests::protocol::*;
     use crate::tests::stepper::BevyStepper;
-    use bevy::prelude::Entity;
+    use bevy::prelude::{Entity, With};
 
     /// Test marking entities as mapped or not
     #[test]
@@ -257,4 +361,67 @@ mod tests {
             &ComponentMapEntities(client_entity)
         );
     }
+
+    /// A remote entity that was manually mapped to a local entity should be replicated onto
+    /// that local entity instead of spawning a new one
+    #[test]
+    fn test_manual_entity_mapping() {
+        let mut stepper = BevyStepper::default();
+
+        let client_entity = stepper.client_app.world_mut().spawn_empty().id();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(1.0), Replicate::default()))
+            .id();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .map_remote_entity(server_entity, client_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the components were replicated on the mapped entity
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .entity(client_entity)
+                .get::<ComponentSyncModeFull>(),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+        assert!(stepper
+            .client_app
+            .world()
+            .entity(client_entity)
+            .contains::<Replicated>());
+        // no duplicate entity was spawned
+        assert_eq!(
+            stepper
+                .client_app
+                .world_mut()
+                .query_filtered::<Entity, With<ComponentSyncModeFull>>()
+                .iter(stepper.client_app.world())
+                .count(),
+            1
+        );
+
+        // updates are also applied on the mapped entity
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(ComponentSyncModeFull(2.0));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .entity(client_entity)
+                .get::<ComponentSyncModeFull>(),
+            Some(&ComponentSyncModeFull(2.0))
+        );
+    }
 }
@@ -0,0 +1,517 @@
+//! Module to replicate bevy [`Event`]s over the network
+//!
+//! An event registered with [`register_event`](crate::prelude::AppMessageExt::register_event) is
+//! sent to the remote peer every time it is written locally, along with the tick at which it was written.
+//! The remote peer buffers the event until it reaches that tick, and then writes it to its own [`Events<E>`].
+//!
+//! - on the client, the server events are usually emitted as soon as they are received, since the
+//!   client timeline is ahead of the server timeline
+//! - on the server, the client events are emitted on the frame where the server reaches the tick at which
+//!   the client wrote them
+//!
+//! Events that were received from the network are not sent back to the remote peer. In particular the server
+//! does not forward the events received from a client to the other clients.
+//!
+//! Events can also be scheduled for a future tick with [`ReplicatedEventBuffer::schedule_network_event`]:
+//! the event is announced to the remote peer immediately, and is written on both peers when they reach that tick.
+//! In the meantime, the remote peer can inspect the upcoming events with [`ReplicatedEventBuffer::pending`]
+//! (for example to start a door-opening animation, or to display a round timer).
+//!
+//! On the server, [`ReplicatedEventBuffer::sender`] returns the client that sent an event. The events whose tick is
+//! more than [`EventRegistration::set_max_future_ticks`] ticks ahead of the current tick are rejected, so that a
+//! remote peer cannot fill the buffer with events that will never be emitted.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//!
+//! #[derive(Event, Serialize, Deserialize, Clone)]
+//! struct Explosion(Vec2);
+//!
+//! app.register_event::<Explosion>(ChannelDirection::ServerToClient)
+//!     // keep the events of the last 20 ticks, and emit them again during rollbacks
+//!     .add_rollback_buffer(20);
+//! ```
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use bevy::app::App;
+use bevy::ecs::event::EventId;
+use bevy::prelude::{
+    not, Event, EventReader, EventWriter, Events, FixedPreUpdate, IntoSystemConfigs, PostUpdate,
+    PreUpdate, Res, ResMut, Resource,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{trace, warn};
+
+use crate::channel::builder::EventChannel;
+use crate::client::config::ClientConfig;
+use crate::client::prediction::plugin::is_in_rollback;
+use crate::client::prediction::rollback::Rollback;
+use crate::connection::client::{ClientConnection, NetClient};
+use crate::prelude::server::ServerConfig;
+use crate::prelude::{client, server, ChannelDirection, ChannelKind, ClientId, Message};
+use crate::protocol::EventContext;
+use crate::shared::events::components::MessageEvent;
+use crate::shared::message::MessageSend;
+use crate::shared::replication::network_target::NetworkTarget;
+use crate::shared::run_conditions::is_host_server;
+use crate::shared::sets::{ClientMarker, InternalReplicationSet, ServerMarker};
+use crate::shared::tick_manager::{Tick, TickManager};
+
+/// Message used to send a bevy [`Event`] to the remote peer, along with the tick at which it was written
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventMessage<E> {
+    pub tick: Tick,
+    pub event: E,
+}
+
+/// Returned by [`register_event`](crate::prelude::AppMessageExt::register_event) to customize the
+/// replication of the event
+pub struct EventRegistration<'a, E> {
+    pub(crate) app: &'a mut App,
+    pub(crate) _marker: PhantomData<E>,
+}
+
+impl<E: Event> EventRegistration<'_, E> {
+    /// Keep the events received from the remote peer for `ticks` ticks after they were emitted.
+    ///
+    /// If the client rolls back to one of these ticks, the events are emitted again during the rollback.
+    pub fn add_rollback_buffer(self, ticks: u16) -> Self {
+        if let Some(mut buffer) = self
+            .app
+            .world_mut()
+            .get_resource_mut::<ReplicatedEventBuffer<E>>()
+        {
+            buffer.rollback_ticks = ticks;
+        }
+        self
+    }
+
+    /// Reject the events received from the remote peer whose tick is more than `ticks` ticks
+    /// ahead of the current tick (by default [`DEFAULT_MAX_FUTURE_TICKS`]).
+    ///
+    /// Events scheduled further in the future with [`ReplicatedEventBuffer::schedule_network_event`]
+    /// are rejected by the remote peer as well.
+    pub fn set_max_future_ticks(self, ticks: u16) -> Self {
+        if let Some(mut buffer) = self
+            .app
+            .world_mut()
+            .get_resource_mut::<ReplicatedEventBuffer<E>>()
+        {
+            buffer.max_future_ticks = ticks;
+        }
+        self
+    }
+}
+
+/// Default maximum number of ticks by which a received event can be ahead of the current tick
+pub const DEFAULT_MAX_FUTURE_TICKS: u16 = 1024;
+
+/// An event that was received or scheduled but not emitted yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEvent<E> {
+    /// Tick at which the event will be emitted
+    pub tick: Tick,
+    /// Client that sent the event, if it was received by the server
+    pub sender: Option<ClientId>,
+    pub event: E,
+}
+
+/// Buffer of the events of type `E` that were received from the remote peer
+#[derive(Resource, Debug)]
+pub struct ReplicatedEventBuffer<E> {
+    /// Events that were received but whose tick has not been reached yet
+    pending: Vec<PendingEvent<E>>,
+    /// Events that were emitted during the last `rollback_ticks` ticks
+    history: VecDeque<(Tick, E)>,
+    rollback_ticks: u16,
+    max_future_ticks: u16,
+    /// Ids of the events that were emitted this frame (so that they are not sent back to the remote peer),
+    /// along with the client that sent them
+    emitted: Vec<(usize, Option<ClientId>)>,
+    /// Events that were scheduled locally but not announced to the remote peer yet
+    to_announce: Vec<(Tick, E)>,
+}
+
+impl<E> Default for ReplicatedEventBuffer<E> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            history: VecDeque::new(),
+            rollback_ticks: 0,
+            max_future_ticks: DEFAULT_MAX_FUTURE_TICKS,
+            emitted: Vec::new(),
+            to_announce: Vec::new(),
+        }
+    }
+}
+
+impl<E: Clone> ReplicatedEventBuffer<E> {
+    /// Schedule the event to be written at the given `tick`, locally and on the remote peer.
+    ///
+    /// The event is sent right away on a reliable channel, so that the remote peer can anticipate it.
+    /// (only if the event can be sent in that direction, according to the [`ChannelDirection`] used to register it)
+    /// If the tick has already been reached, the event is written on the next frame.
+    pub fn schedule_network_event(&mut self, tick: Tick, event: E) {
+        self.to_announce.push((tick, event.clone()));
+        self.pending.push(PendingEvent {
+            tick,
+            sender: None,
+            event,
+        });
+    }
+}
+
+impl<E: Event> ReplicatedEventBuffer<E> {
+    /// Events that were received or scheduled but not emitted yet
+    pub fn pending(&self) -> &[PendingEvent<E>] {
+        &self.pending
+    }
+
+    /// Client that sent the event with the given id, if the event was received by the server
+    /// and emitted during this frame
+    pub fn sender(&self, event_id: EventId<E>) -> Option<ClientId> {
+        self.emitted
+            .iter()
+            .find(|(id, _)| *id == event_id.id)
+            .and_then(|(_, sender)| *sender)
+    }
+
+    /// Events that were emitted recently, along with their tick.
+    ///
+    /// This is only populated if a rollback buffer was added with [`EventRegistration::add_rollback_buffer`]
+    pub fn history(&self) -> impl Iterator<Item = &(Tick, E)> {
+        self.history.iter()
+    }
+}
+
+/// Add the systems that send and receive the event `E`, depending on the direction of the event
+pub(crate) fn register_event_systems<E: Event + Message + Clone>(
+    app: &mut App,
+    direction: ChannelDirection,
+) {
+    let is_client = app.world().get_resource::<ClientConfig>().is_some();
+    let is_server = app.world().get_resource::<ServerConfig>().is_some();
+    if !app.world().contains_resource::<ReplicatedEventBuffer<E>>() {
+        app.add_event::<E>();
+        app.init_resource::<ReplicatedEventBuffer<E>>();
+        app.add_systems(
+            PreUpdate,
+            emit_events::<E>
+                .after(InternalReplicationSet::<ServerMarker>::ReceiveResourceUpdates)
+                .after(InternalReplicationSet::<ClientMarker>::ReceiveResourceUpdates),
+        );
+        if is_client {
+            app.add_systems(
+                FixedPreUpdate,
+                emit_rollback_events::<E>.run_if(is_in_rollback),
+            );
+        }
+    }
+    let (client_send, server_send) = match direction {
+        ChannelDirection::ClientToServer => (true, false),
+        ChannelDirection::ServerToClient => (false, true),
+        ChannelDirection::Bidirectional => (true, true),
+    };
+    // events are cleared every frame, so they are sent every frame instead of every send_interval
+    if is_client {
+        if client_send {
+            app.add_systems(
+                PostUpdate,
+                // in host-server mode, the client shares the World of the server
+                send_events::<E, client::ConnectionManager>
+                    .run_if(not(is_host_server))
+                    .in_set(InternalReplicationSet::<ClientMarker>::BufferDespawnsAndRemovals),
+            );
+        }
+        if server_send {
+            app.add_systems(
+                PreUpdate,
+                receive_events::<E, ()>
+                    .in_set(InternalReplicationSet::<ClientMarker>::ReceiveResourceUpdates),
+            );
+        }
+    }
+    if is_server {
+        if server_send {
+            app.add_systems(
+                PostUpdate,
+                send_events::<E, server::ConnectionManager>
+                    .in_set(InternalReplicationSet::<ServerMarker>::BufferDespawnsAndRemovals),
+            );
+        }
+        if client_send {
+            app.add_systems(
+                PreUpdate,
+                receive_events::<E, crate::prelude::ClientId>
+                    .in_set(InternalReplicationSet::<ServerMarker>::ReceiveResourceUpdates),
+            );
+        }
+    }
+}
+
+/// Send the events that were written locally to the remote peer
+fn send_events<E: Event + Message + Clone, S: MessageSend>(
+    mut connection_manager: ResMut<S>,
+    mut events: EventReader<E>,
+    mut buffer: ResMut<ReplicatedEventBuffer<E>>,
+    tick_manager: Res<TickManager>,
+    local_client_connection: Option<Res<ClientConnection>>,
+) {
+    let tick = tick_manager.tick();
+    let mut target = NetworkTarget::All;
+    // if running in host-server mode, the local client already has access to the events
+    if let Some(local_client) = local_client_connection.as_ref() {
+        target.exclude(&NetworkTarget::Single(local_client.client.id()));
+    }
+    for (tick, event) in std::mem::take(&mut buffer.to_announce) {
+        trace!(?tick, "announcing event {:?}", std::any::type_name::<E>());
+        let _ = connection_manager.erased_send_message_to_target(
+            &mut EventMessage { tick, event },
+            ChannelKind::of::<EventChannel>(),
+            target.clone(),
+        );
+    }
+    for (event, event_id) in events.read_with_id() {
+        // do not send back the events that we received from the remote peer
+        if buffer.emitted.iter().any(|(id, _)| *id == event_id.id) {
+            continue;
+        }
+        trace!(?tick, "sending event {:?}", std::any::type_name::<E>());
+        let _ = connection_manager.erased_send_message_to_target(
+            &mut EventMessage {
+                tick,
+                event: event.clone(),
+            },
+            ChannelKind::of::<EventChannel>(),
+            target.clone(),
+        );
+    }
+}
+
+/// Context of a received message from which we can get the client that sent it
+trait EventSender {
+    fn sender(&self) -> Option<ClientId>;
+}
+
+impl EventSender for () {
+    fn sender(&self) -> Option<ClientId> {
+        None
+    }
+}
+
+impl EventSender for ClientId {
+    fn sender(&self) -> Option<ClientId> {
+        Some(*self)
+    }
+}
+
+/// Move the events received from the remote peer to the [`ReplicatedEventBuffer`]
+fn receive_events<E: Event + Message, Ctx: EventContext + EventSender>(
+    mut messages: ResMut<Events<MessageEvent<EventMessage<E>, Ctx>>>,
+    mut buffer: ResMut<ReplicatedEventBuffer<E>>,
+    tick_manager: Res<TickManager>,
+) {
+    let horizon = tick_manager.tick() + buffer.max_future_ticks.min(i16::MAX as u16) as i16;
+    for message in messages.drain() {
+        let sender = message.context.sender();
+        if message.message.tick > horizon {
+            warn!(
+                ?sender,
+                tick = ?message.message.tick,
+                ?horizon,
+                "Rejecting event {:?} that is too far in the future",
+                std::any::type_name::<E>()
+            );
+            continue;
+        }
+        buffer.pending.push(PendingEvent {
+            tick: message.message.tick,
+            sender,
+            event: message.message.event,
+        });
+    }
+}
+
+/// Emit the buffered events whose tick has been reached
+fn emit_events<E: Event + Clone>(
+    tick_manager: Res<TickManager>,
+    mut buffer: ResMut<ReplicatedEventBuffer<E>>,
+    mut events: EventWriter<E>,
+) {
+    // the tick will be incremented during this frame's FixedUpdate
+    let tick = tick_manager.tick() + 1;
+    let buffer = buffer.as_mut();
+    buffer.emitted.clear();
+    let mut i = 0;
+    while i < buffer.pending.len() {
+        if buffer.pending[i].tick > tick {
+            i += 1;
+            continue;
+        }
+        let PendingEvent {
+            tick: event_tick,
+            sender,
+            event,
+        } = buffer.pending.remove(i);
+        if buffer.rollback_ticks > 0 {
+            buffer.history.push_back((event_tick, event.clone()));
+        }
+        buffer.emitted.push((events.send(event).id, sender));
+    }
+    while buffer
+        .history
+        .front()
+        .is_some_and(|(event_tick, _)| tick - *event_tick > buffer.rollback_ticks as i16)
+    {
+        buffer.history.pop_front();
+    }
+}
+
+/// During a rollback, emit again the events of the tick that is being replayed
+fn emit_rollback_events<E: Event + Clone>(
+    rollback: Res<Rollback>,
+    mut buffer: ResMut<ReplicatedEventBuffer<E>>,
+    mut events: EventWriter<E>,
+) {
+    let Some(rollback_tick) = rollback.get_rollback_tick() else {
+        return;
+    };
+    let buffer = buffer.as_mut();
+    for (event_tick, event) in buffer.history.iter() {
+        if *event_tick == rollback_tick {
+            buffer.emitted.push((events.send(event.clone()).id, None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::prelude::{Events, World};
+
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+
+    use super::{PendingEvent, ReplicatedEventBuffer};
+    use crate::prelude::ClientId;
+    use crate::tests::stepper::TEST_CLIENT_ID;
+
+    fn read_events(reader: &mut ManualEventReader<Event1>, world: &World) -> Vec<Event1> {
+        reader
+            .read(world.resource::<Events<Event1>>())
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_event_replication() {
+        let mut stepper = BevyStepper::default();
+        let mut server_reader = ManualEventReader::<Event1>::default();
+        let mut client_reader = ManualEventReader::<Event1>::default();
+
+        // server to client
+        stepper.server_app.world_mut().send_event(Event1(1));
+        let mut server_received = vec![];
+        let mut client_received = vec![];
+        for _ in 0..20 {
+            stepper.frame_step();
+            server_received.extend(read_events(&mut server_reader, stepper.server_app.world()));
+            client_received.extend(read_events(&mut client_reader, stepper.client_app.world()));
+        }
+        assert_eq!(server_received, vec![Event1(1)]);
+        assert_eq!(client_received, vec![Event1(1)]);
+
+        // client to server: the event is not sent back to the client
+        stepper.client_app.world_mut().send_event(Event1(2));
+        let mut server_received = vec![];
+        let mut client_received = vec![];
+        for _ in 0..20 {
+            stepper.frame_step();
+            server_received.extend(read_events(&mut server_reader, stepper.server_app.world()));
+            client_received.extend(read_events(&mut client_reader, stepper.client_app.world()));
+        }
+        assert_eq!(server_received, vec![Event1(2)]);
+        assert_eq!(client_received, vec![Event1(2)]);
+    }
+
+    #[test]
+    fn test_scheduled_event() {
+        let mut stepper = BevyStepper::default();
+        let mut server_reader = ManualEventReader::<Event1>::default();
+        let mut client_reader = ManualEventReader::<Event1>::default();
+
+        let scheduled_tick = stepper.server_tick() + 30;
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ReplicatedEventBuffer<Event1>>()
+            .schedule_network_event(scheduled_tick, Event1(3));
+
+        // the event is announced to the client before it is written
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ReplicatedEventBuffer<Event1>>()
+                .pending(),
+            &[PendingEvent {
+                tick: scheduled_tick,
+                sender: None,
+                event: Event1(3),
+            }]
+        );
+
+        // the server writes the event when it reaches the tick
+        let mut server_received = vec![];
+        let mut client_received = vec![];
+        for _ in 0..40 {
+            stepper.frame_step();
+            let events = read_events(&mut server_reader, stepper.server_app.world());
+            if !events.is_empty() {
+                assert_eq!(stepper.server_tick(), scheduled_tick);
+            }
+            server_received.extend(events);
+            client_received.extend(read_events(&mut client_reader, stepper.client_app.world()));
+        }
+        assert_eq!(server_received, vec![Event1(3)]);
+        assert_eq!(client_received, vec![Event1(3)]);
+    }
+
+    /// The server knows which client sent an event, and rejects the events that are too far in the future
+    #[test]
+    fn test_received_event_sender_and_horizon() {
+        let mut stepper = BevyStepper::default();
+        let mut server_reader = ManualEventReader::<Event1>::default();
+
+        // an event scheduled too far in the future is rejected by the server
+        let client_tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ReplicatedEventBuffer<Event1>>()
+            .schedule_network_event(client_tick + 2000, Event1(4));
+        stepper.client_app.world_mut().send_event(Event1(5));
+        let mut senders = vec![];
+        for _ in 0..20 {
+            stepper.frame_step();
+            let world = stepper.server_app.world();
+            let buffer = world.resource::<ReplicatedEventBuffer<Event1>>();
+            assert!(buffer
+                .pending()
+                .iter()
+                .all(|pending| pending.event != Event1(4)));
+            for (event, event_id) in server_reader.read_with_id(world.resource::<Events<Event1>>())
+            {
+                senders.push((event.clone(), buffer.sender(event_id)));
+            }
+        }
+        assert_eq!(
+            senders,
+            vec![(Event1(5), Some(ClientId::Netcode(TEST_CLIENT_ID)))]
+        );
+    }
+}
@@ -0,0 +1,513 @@
+//! Detect when the confirmed world of a client diverges from the world of the server.
+//!
+//! Replication bugs (an update that is applied to the wrong entity, a component that is never removed, etc.)
+//! are often silent: the client simply displays a slightly wrong world. When the [`DesyncDetectionPlugin`] is added:
+//! - the server periodically sends, for each [`ReplicationGroup`], a checksum of the components that were
+//!   registered with [`AppDesyncCheckExt::add_desync_check`], along with the tick at which it was computed
+//! - the client computes the same checksum on its confirmed entities (the entities with the [`Replicated`] component),
+//!   and emits a [`DesyncDetectedEvent`] if the checksums don't match once it has received the updates of the group
+//!   up to that tick
+//!
+//! This is meant to be used during development. Some setups cause spurious desyncs:
+//! - components that contain entities are hashed before the entities are mapped, so they are always different
+//! - the server assumes that all the changes of the group were sent this frame, which is not the case if the group
+//!   has a lower send frequency, or if the updates were deferred because of the bandwidth cap
+//! - if an update is lost, the client only receives the corresponding changes with a more recent tick
+//! - entities of the same group that are only relevant to some clients, or components with a per-client override
+//!   ([`OverrideTargetComponent`](crate::prelude::OverrideTargetComponent)) are not taken into account
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//!
+//! // add the plugin to both the client and the server apps, after the lightyear plugins
+//! app.add_plugins(DesyncDetectionPlugin::default());
+//! app.add_desync_check::<Position>();
+//!
+//! // client
+//! fn log_desyncs(mut events: EventReader<DesyncDetectedEvent>) {
+//!     for event in events.read() {
+//!         error!(group = ?event.group, tick = ?event.tick, "the client world is out of sync");
+//!     }
+//! }
+//! ```
+use std::collections::VecDeque;
+use std::io::Write;
+
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::channel::builder::IntegrityChannel;
+use crate::client::config::ClientConfig;
+use crate::prelude::server::is_started;
+use crate::prelude::{
+    client, server, AppMessageExt, ChannelDirection, ComponentRegistry, DisabledComponent,
+    NetworkTarget, Replicated, Replicating, ReplicationGroup, ReplicationGroupId, Tick,
+    TickManager,
+};
+use crate::serialize::writer::Writer;
+use crate::server::config::ServerConfig;
+use crate::shared::sets::{ClientMarker, InternalMainSet, InternalReplicationSet, ServerMarker};
+
+/// Number of checksums that the client keeps for each group
+const HISTORY_LEN: usize = 64;
+
+/// Checksum of the components of a replication group, computed by the server at a given tick
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GroupChecksum {
+    pub group: ReplicationGroupId,
+    pub tick: Tick,
+    pub checksum: u64,
+}
+
+/// Event emitted on the client when the confirmed entities of a replication group don't match the server's
+/// entities at the given (server) tick
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct DesyncDetectedEvent {
+    pub group: ReplicationGroupId,
+    pub tick: Tick,
+}
+
+/// Plugin that checks that the confirmed world of the client matches the world of the server.
+///
+/// It must be added to both the client and the server apps, after the lightyear plugins, because it
+/// registers the [`GroupChecksum`] message in the protocol.
+#[derive(Clone, Debug)]
+pub struct DesyncDetectionPlugin {
+    /// How often the server sends the checksums of the replication groups
+    pub interval: Duration,
+    /// How long the client waits for the updates of a group before comparing the checksums.
+    ///
+    /// The server only sends updates for a group when it changes, so if the group didn't change the client
+    /// compares its current state with the server's checksum after this delay.
+    pub timeout: Duration,
+}
+
+impl Default for DesyncDetectionPlugin {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DesyncCheckSet {
+    /// Select the groups for which the checksum must be computed
+    Prepare,
+    /// Add the components of each entity to the checksum of its group
+    Accumulate,
+    /// Send (on the server) or compare (on the client) the checksums
+    Check,
+}
+
+/// Checksums being computed during the current frame
+#[derive(Resource, Debug, Default)]
+struct GroupChecksums {
+    checksums: HashMap<ReplicationGroupId, u64>,
+}
+
+/// Timer of the checksums on the server
+#[derive(Resource, Debug)]
+struct DesyncCheckTimer(Timer);
+
+/// Checksums received from the server, and recent checksums of the confirmed groups on the client
+#[derive(Resource, Debug)]
+struct ClientChecksums {
+    timeout: Duration,
+    /// Server checksums that have not been compared yet, with the time at which they were received
+    pending: Vec<(Duration, GroupChecksum)>,
+    /// For each group, the checksum of the confirmed entities after applying the update of a given remote tick
+    history: HashMap<ReplicationGroupId, VecDeque<(Tick, u64)>>,
+}
+
+impl Plugin for DesyncDetectionPlugin {
+    fn build(&self, app: &mut App) {
+        // PROTOCOL
+        app.register_message::<GroupChecksum>(ChannelDirection::ServerToClient);
+        app.init_resource::<GroupChecksums>();
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        let is_client = app.world().get_resource::<ClientConfig>().is_some();
+        if is_server {
+            app.insert_resource(DesyncCheckTimer(Timer::new(
+                self.interval,
+                TimerMode::Repeating,
+            )));
+            app.configure_sets(
+                PostUpdate,
+                (
+                    DesyncCheckSet::Prepare,
+                    DesyncCheckSet::Accumulate.run_if(has_checksums),
+                    DesyncCheckSet::Check,
+                )
+                    .chain()
+                    .after(InternalReplicationSet::<ServerMarker>::All)
+                    .before(InternalMainSet::<ServerMarker>::Send)
+                    .run_if(is_started),
+            );
+            app.add_systems(
+                PostUpdate,
+                (
+                    prepare_server_checksums.in_set(DesyncCheckSet::Prepare),
+                    send_checksums.in_set(DesyncCheckSet::Check),
+                ),
+            );
+        } else if is_client {
+            app.add_event::<DesyncDetectedEvent>();
+            app.insert_resource(ClientChecksums {
+                timeout: self.timeout,
+                pending: Vec::new(),
+                history: HashMap::default(),
+            });
+            app.configure_sets(
+                PreUpdate,
+                (
+                    DesyncCheckSet::Prepare,
+                    DesyncCheckSet::Accumulate.run_if(has_checksums),
+                    DesyncCheckSet::Check,
+                )
+                    .chain()
+                    .after(InternalMainSet::<ClientMarker>::EmitEvents),
+            );
+            app.add_systems(
+                PreUpdate,
+                (
+                    prepare_client_checksums.in_set(DesyncCheckSet::Prepare),
+                    compare_checksums.in_set(DesyncCheckSet::Check),
+                ),
+            );
+        }
+    }
+}
+
+/// Extension to include a component in the checksums of the [`DesyncDetectionPlugin`]
+pub trait AppDesyncCheckExt {
+    /// Include the component `C` in the checksums of the replication groups.
+    ///
+    /// The component must be registered in the protocol. This must be called on both the client and the server apps,
+    /// after the lightyear plugins.
+    fn add_desync_check<C: Component + Clone>(&mut self) -> &mut Self;
+}
+
+impl AppDesyncCheckExt for App {
+    fn add_desync_check<C: Component + Clone>(&mut self) -> &mut Self {
+        let is_server = self.world().get_resource::<ServerConfig>().is_some();
+        let is_client = self.world().get_resource::<ClientConfig>().is_some();
+        if is_server {
+            self.add_systems(
+                PostUpdate,
+                accumulate_server_checksums::<C>.in_set(DesyncCheckSet::Accumulate),
+            );
+        } else if is_client {
+            self.add_systems(
+                PreUpdate,
+                accumulate_client_checksums::<C>.in_set(DesyncCheckSet::Accumulate),
+            );
+        }
+        self
+    }
+}
+
+fn has_checksums(checksums: Res<GroupChecksums>) -> bool {
+    !checksums.checksums.is_empty()
+}
+
+/// Hash of a component of an entity; the hashes of all the components of a group are added together
+/// so that the checksum doesn't depend on the iteration order
+fn component_hash<C: Component + Clone>(
+    registry: &ComponentRegistry,
+    writer: &mut Writer,
+    remote_entity: Entity,
+    component: &C,
+) -> Option<u64> {
+    writer
+        .write_all(&remote_entity.to_bits().to_le_bytes())
+        .ok()?;
+    registry
+        .serialize(&mut component.clone(), writer, None)
+        .inspect_err(|e| error!("Could not serialize the component for the desync check: {e:?}"))
+        .ok()?;
+    Some(seahash::hash(&writer.split()))
+}
+
+/// When the timer finishes, start computing the checksums of all the replicated groups
+fn prepare_server_checksums(
+    time: Res<Time>,
+    mut timer: ResMut<DesyncCheckTimer>,
+    mut checksums: ResMut<GroupChecksums>,
+    query: Query<(Entity, &ReplicationGroup), With<Replicating>>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (entity, group) in query.iter() {
+        checksums.checksums.insert(group.group_id(Some(entity)), 0);
+    }
+}
+
+fn accumulate_server_checksums<C: Component + Clone>(
+    registry: Res<ComponentRegistry>,
+    mut checksums: ResMut<GroupChecksums>,
+    query: Query<
+        (Entity, &C, &ReplicationGroup),
+        (With<Replicating>, Without<DisabledComponent<C>>),
+    >,
+) {
+    let mut writer = Writer::default();
+    for (entity, component, group) in query.iter() {
+        let Some(checksum) = checksums.checksums.get_mut(&group.group_id(Some(entity))) else {
+            continue;
+        };
+        if let Some(hash) = component_hash(&registry, &mut writer, entity, component) {
+            *checksum = checksum.wrapping_add(hash);
+        }
+    }
+}
+
+fn send_checksums(
+    tick_manager: Res<TickManager>,
+    mut checksums: ResMut<GroupChecksums>,
+    mut connection_manager: ResMut<server::ConnectionManager>,
+) {
+    let tick = tick_manager.tick();
+    for (group, checksum) in checksums.checksums.drain() {
+        let _ = connection_manager
+            .send_message_to_target::<IntegrityChannel, _>(
+                &mut GroupChecksum {
+                    group,
+                    tick,
+                    checksum,
+                },
+                NetworkTarget::All,
+            )
+            .inspect_err(|e| error!(?group, "Could not send the group checksum: {:?}", e));
+    }
+}
+
+/// Buffer the checksums received from the server, then select the groups whose checksum must be computed:
+/// the groups that received an update, and the groups with pending checksums (the confirmed entities
+/// could have been modified locally)
+fn prepare_client_checksums(
+    time: Res<Time>,
+    mut messages: EventReader<client::MessageEvent<GroupChecksum>>,
+    mut client_checksums: ResMut<ClientChecksums>,
+    mut checksums: ResMut<GroupChecksums>,
+    connection_manager: Res<client::ConnectionManager>,
+) {
+    let now = time.elapsed();
+    client_checksums
+        .pending
+        .extend(messages.read().map(|message| (now, *message.message())));
+    for (group, channel) in connection_manager
+        .replication_receiver
+        .group_channels
+        .iter()
+    {
+        if channel.latest_tick.is_none() {
+            continue;
+        }
+        let is_pending = client_checksums
+            .pending
+            .iter()
+            .any(|(_, pending)| pending.group == *group);
+        let latest_checksum_tick = client_checksums
+            .history
+            .get(group)
+            .and_then(|history| history.back())
+            .map(|(tick, _)| *tick);
+        if is_pending || latest_checksum_tick != channel.latest_tick {
+            checksums.checksums.insert(*group, 0);
+        }
+    }
+}
+
+fn accumulate_client_checksums<C: Component + Clone>(
+    registry: Res<ComponentRegistry>,
+    mut checksums: ResMut<GroupChecksums>,
+    connection_manager: Res<client::ConnectionManager>,
+    query: Query<(Entity, &C), With<Replicated>>,
+) {
+    let receiver = &connection_manager.replication_receiver;
+    let mut writer = Writer::default();
+    for (entity, component) in query.iter() {
+        let Some(remote_entity) = receiver.remote_entity_map.get_remote(entity) else {
+            continue;
+        };
+        let Some(checksum) = receiver
+            .remote_entity_to_group
+            .get(&remote_entity)
+            .and_then(|group| checksums.checksums.get_mut(group))
+        else {
+            continue;
+        };
+        if let Some(hash) = component_hash(&registry, &mut writer, remote_entity, component) {
+            *checksum = checksum.wrapping_add(hash);
+        }
+    }
+}
+
+/// Store the checksums computed this frame, then compare the server checksums that are ready
+fn compare_checksums(
+    time: Res<Time>,
+    mut client_checksums: ResMut<ClientChecksums>,
+    mut checksums: ResMut<GroupChecksums>,
+    connection_manager: Res<client::ConnectionManager>,
+    mut events: EventWriter<DesyncDetectedEvent>,
+) {
+    let receiver = &connection_manager.replication_receiver;
+    for (group, checksum) in checksums.checksums.drain() {
+        let Some(tick) = receiver
+            .group_channels
+            .get(&group)
+            .and_then(|channel| channel.latest_tick)
+        else {
+            continue;
+        };
+        let history = client_checksums.history.entry(group).or_default();
+        // the entities could have been modified locally since the last update, keep the latest value
+        if history.back().is_some_and(|(t, _)| *t == tick) {
+            history.pop_back();
+        }
+        history.push_back((tick, checksum));
+        if history.len() > HISTORY_LEN {
+            history.pop_front();
+        }
+    }
+
+    let now = time.elapsed();
+    let timeout = client_checksums.timeout;
+    let ClientChecksums {
+        pending, history, ..
+    } = client_checksums.as_mut();
+    pending.retain(|(received_at, server)| {
+        let Some(channel) = receiver.group_channels.get(&server.group) else {
+            // the group is not replicated to this client
+            return false;
+        };
+        let received_update = channel
+            .latest_tick
+            .is_some_and(|latest_tick| latest_tick >= server.tick);
+        if !received_update && now.saturating_sub(*received_at) < timeout {
+            return true;
+        }
+        // compare with the state of the group after the most recent update that is not after the server tick
+        let Some((tick, checksum)) = history.get(&server.group).and_then(|history| {
+            history
+                .iter()
+                .rev()
+                .find(|(tick, _)| *tick <= server.tick)
+                .copied()
+        }) else {
+            return false;
+        };
+        if checksum != server.checksum {
+            warn!(
+                group = ?server.group,
+                server_tick = ?server.tick,
+                client_tick = ?tick,
+                "Desync detected: the confirmed entities don't match the server"
+            );
+            events.send(DesyncDetectedEvent {
+                group: server.group,
+                tick: server.tick,
+            });
+        }
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+    use bevy::ecs::event::Events;
+
+    fn desync_events(stepper: &BevyStepper) -> Vec<DesyncDetectedEvent> {
+        stepper
+            .client_app
+            .world()
+            .resource::<Events<DesyncDetectedEvent>>()
+            .iter_current_update_events()
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_desync_detection() {
+        let tick_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            },
+            ClientConfig::default(),
+            tick_duration,
+        );
+        let plugin = DesyncDetectionPlugin {
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(50),
+        };
+        stepper.client_app.add_plugins(plugin.clone());
+        stepper.server_app.add_plugins(plugin);
+        stepper
+            .client_app
+            .add_desync_check::<ComponentSyncModeFull>();
+        stepper
+            .server_app
+            .add_desync_check::<ComponentSyncModeFull>();
+        stepper.init();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        let group = ReplicationGroupId(server_entity.to_bits());
+
+        // the client world matches the server world
+        for _ in 0..30 {
+            stepper.frame_step();
+            assert!(desync_events(&stepper).is_empty());
+        }
+        // the server updates are taken into account
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(server_entity)
+            .unwrap()
+            .0 = 2.0;
+        for _ in 0..30 {
+            stepper.frame_step();
+            assert!(desync_events(&stepper).is_empty());
+        }
+
+        // modify the confirmed entity on the client without going through the replication
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(client_entity)
+            .unwrap()
+            .0 = 3.0;
+        let mut detected = vec![];
+        for _ in 0..30 {
+            stepper.frame_step();
+            detected.extend(desync_events(&stepper));
+        }
+        assert!(!detected.is_empty());
+        assert!(detected.iter().all(|event| event.group == group));
+    }
+}
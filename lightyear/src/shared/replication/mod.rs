@@ -4,9 +4,11 @@ use std::fmt::Debug;
 use std::hash::Hash;
 
 use bevy::prelude::{Entity, Resource};
+use bevy::utils::Duration;
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 use bytes::Bytes;
 use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
 
 use crate::connection::id::ClientId;
 use crate::packet::message::MessageId;
@@ -19,25 +21,33 @@ use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::events::connection::{
     ClearEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
-    IterEntityDespawnEvent, IterEntitySpawnEvent,
+    IterEntityDespawnEvent, IterEntitySpawnEvent, IterUnknownTypeEvent,
 };
 use crate::shared::replication::components::ReplicationGroupId;
 
+pub mod capture;
 pub mod components;
+pub mod conversion;
 
 pub(crate) mod archetypes;
 pub(crate) mod authority;
+pub mod debug;
 pub mod delta;
 pub mod entity_map;
 pub mod error;
+pub mod events;
 pub(crate) mod hierarchy;
+pub mod integrity;
 pub mod network_target;
 pub(crate) mod plugin;
 pub(crate) mod prespawn;
 pub(crate) mod receive;
 pub(crate) mod resources;
 pub(crate) mod send;
+pub(crate) mod snapshot;
+pub mod strategy;
 pub(crate) mod systems;
+pub mod tombstone;
 
 /// Serialize Entity as two varints for the index and generation (because they will probably be low).
 /// Revisit this when relations comes out
@@ -69,6 +79,20 @@ impl ToBytes for Entity {
     }
 }
 
+/// Message used to despawn all the entities of some replication groups at once,
+/// instead of sending one despawn action per entity.
+///
+/// The message is not sent on the actions channel of the groups, so it can arrive before some of the
+/// actions that were sent before it (for example the spawn of an entity of the group). For each group,
+/// we include the id of the next actions message of the group: the receiver ignores all the actions
+/// messages with an older id, so that those entities are never spawned.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub(crate) struct DespawnGroupsMessage {
+    pub(crate) groups: Vec<(ReplicationGroupId, MessageId)>,
+    /// Tick at which the groups were despawned
+    pub(crate) tick: Tick,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct EntityActions {
     pub(crate) spawn: SpawnAction,
@@ -111,6 +135,14 @@ pub(crate) enum SpawnAction {
     Despawn,
     // the u64 is the entity's bits (we cannot use Entity directly because it doesn't implement Encode/Decode)
     Reuse(Entity),
+    /// The entity was moved from the group `from` to the group of this message.
+    ///
+    /// `after` is the id of the next actions message of the group `from` when the entity was moved; the move
+    /// is only applied once all the older actions messages of `from` (for example the spawn) have been applied.
+    Move {
+        from: ReplicationGroupId,
+        after: MessageId,
+    },
 }
 
 impl ToBytes for SpawnAction {
@@ -120,6 +152,7 @@ impl ToBytes for SpawnAction {
             SpawnAction::Spawn => 1,
             SpawnAction::Despawn => 1,
             SpawnAction::Reuse(entity) => 1 + entity.len(),
+            SpawnAction::Move { from, after } => 1 + from.len() + after.len(),
         }
     }
 
@@ -132,6 +165,11 @@ impl ToBytes for SpawnAction {
                 buffer.write_u8(3)?;
                 entity.to_bytes(buffer)?;
             }
+            SpawnAction::Move { from, after } => {
+                buffer.write_u8(4)?;
+                from.to_bytes(buffer)?;
+                after.to_bytes(buffer)?;
+            }
         }
         Ok(())
     }
@@ -145,6 +183,10 @@ impl ToBytes for SpawnAction {
             1 => Ok(SpawnAction::Spawn),
             2 => Ok(SpawnAction::Despawn),
             3 => Ok(SpawnAction::Reuse(Entity::from_bytes(buffer)?)),
+            4 => Ok(SpawnAction::Move {
+                from: ReplicationGroupId::from_bytes(buffer)?,
+                after: MessageId::from_bytes(buffer)?,
+            }),
             _ => Err(SerializationError::InvalidPacketType),
         }
     }
@@ -307,6 +349,7 @@ pub(crate) trait ReplicationPeer: Resource {
         + IterComponentUpdateEvent<Self::EventContext>
         + IterEntitySpawnEvent<Self::EventContext>
         + IterEntityDespawnEvent<Self::EventContext>
+        + IterUnknownTypeEvent<Self::EventContext>
         + ClearEvents;
     /// Type of the context associated with the events emitted/received by this replication peer
     type EventContext: EventContext;
@@ -340,6 +383,10 @@ pub(crate) trait ReplicationSend: Resource + ReplicationPeer {
     /// (this is used to send the initial state of the world to new clients)
     fn new_connected_clients(&self) -> Vec<ClientId>;
 
+    /// Shortest send interval among the channels whose interval was changed with
+    /// `set_channel_send_interval`, if any
+    fn fastest_channel_send_interval(&self) -> Option<Duration>;
+
     /// Do some regular cleanup on the internals of replication
     /// - account for tick wrapping by resetting some internal ticks for each replication group
     fn cleanup(&mut self, tick: Tick);
@@ -20,6 +20,11 @@ pub struct ReplicationConfig {
     ///
     /// Set to `Duration::default()` to send updates every frame.
     pub send_interval: Duration,
+    /// If true, the server sends all the entities that are replicated to a newly connected client in a single
+    /// compressed snapshot on a reliable channel, which is applied at once on the client.
+    ///
+    /// Otherwise, each replication group is sent in its own message, which can take many packets to arrive.
+    pub join_snapshot: bool,
 }
 
 #[derive(Clone, Copy, Debug, Reflect)]
@@ -47,6 +52,7 @@ impl Default for ReplicationConfig {
         Self {
             send_updates_mode: SendUpdatesMode::SinceLastAck,
             send_interval: Duration::default(),
+            join_snapshot: false,
         }
     }
 }
@@ -98,10 +104,44 @@ pub(crate) mod send {
 
     #[derive(Resource, Debug)]
     pub(crate) struct SendIntervalTimer<R: Send + Sync + 'static> {
+        /// Interval at which the replication messages are buffered
+        pub(crate) send_interval: Duration,
+        /// Timer that fires at the replication send interval, or faster if a channel was set to
+        /// send its messages more often than that. `None` if the messages are buffered every frame.
         pub(crate) timer: Option<Timer>,
         _marker: std::marker::PhantomData<R>,
     }
 
+    impl<R: Send + Sync + 'static> SendIntervalTimer<R> {
+        pub(crate) fn new(send_interval: Duration) -> Self {
+            let mut timer = Self {
+                send_interval,
+                timer: None,
+                _marker: std::marker::PhantomData,
+            };
+            timer.set_timer_duration(send_interval);
+            timer
+        }
+
+        /// Change the interval at which the replication messages are buffered
+        pub(crate) fn set_send_interval(&mut self, send_interval: Duration) {
+            self.send_interval = send_interval;
+            self.set_timer_duration(send_interval);
+        }
+
+        fn set_timer_duration(&mut self, duration: Duration) {
+            if duration == Duration::default() {
+                self.timer = None;
+                return;
+            }
+            match &mut self.timer {
+                Some(timer) if timer.duration() == duration => {}
+                Some(timer) => timer.set_duration(duration),
+                None => self.timer = Some(Timer::new(duration, TimerMode::Repeating)),
+            }
+        }
+    }
+
     impl<R: Send + Sync + 'static> ReplicationSendPlugin<R> {
         pub(crate) fn new(tick_interval: Duration, send_interval: Duration) -> Self {
             Self {
@@ -115,8 +155,18 @@ pub(crate) mod send {
         /// Tick the timer that controls when we buffer replication updates
         fn tick_send_interval_timer(
             time_manager: Res<TimeManager>,
+            sender: Option<Res<R>>,
             mut timer: ResMut<SendIntervalTimer<R>>,
-        ) {
+        ) where
+            R: ReplicationSend,
+        {
+            // the messages must be buffered at least as often as the fastest channel sends them
+            let send_interval = sender
+                .and_then(|sender| sender.fastest_channel_send_interval())
+                .map_or(timer.send_interval, |interval| {
+                    interval.min(timer.send_interval)
+                });
+            timer.set_timer_duration(send_interval);
             if let Some(timer) = &mut timer.timer {
                 timer.tick(time_manager.delta());
             }
@@ -160,14 +210,7 @@ pub(crate) mod send {
                 .add_plugins(HierarchySendPlugin::<R>::default());
 
             // RESOURCES
-            app.insert_resource(SendIntervalTimer::<R> {
-                timer: if self.send_interval == Duration::default() {
-                    None
-                } else {
-                    Some(Timer::new(self.send_interval, TimerMode::Repeating))
-                },
-                _marker: std::marker::PhantomData,
-            });
+            app.insert_resource(SendIntervalTimer::<R>::new(self.send_interval));
 
             // SETS
             app.configure_sets(
@@ -253,11 +296,12 @@ pub(crate) mod shared {
     };
     use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
     use crate::shared::replication::components::{
-        Controlled, Replicating, ReplicationGroupId, ReplicationGroupIdBuilder,
-        ShouldBeInterpolated,
+        AdditionalReplicationGroups, Controlled, LocalPlayerId, ReplacesEntity, Replicating,
+        ReplicationGroupId, ReplicationGroupIdBuilder, ReplicationPaused, ShouldBeInterpolated,
     };
     use crate::shared::replication::entity_map::{InterpolatedEntityMap, PredictedEntityMap};
     use crate::shared::replication::network_target::NetworkTarget;
+    use crate::shared::replication::tombstone::ComponentTombstones;
     use bevy::prelude::{App, Plugin};
 
     pub(crate) struct SharedPlugin;
@@ -268,23 +312,28 @@ pub(crate) mod shared {
             app.register_type::<TargetEntity>()
                 .register_type::<Replicated>()
                 .register_type::<Controlled>()
+                .register_type::<LocalPlayerId>()
                 .register_type::<Replicating>()
+                .register_type::<ReplicationPaused>()
                 .register_type::<ReplicationTarget>()
                 .register_type::<ReplicateToServer>()
                 .register_type::<ReplicateHierarchy>()
                 .register_type::<ReplicationGroupIdBuilder>()
                 .register_type::<ReplicationGroup>()
+                .register_type::<AdditionalReplicationGroups>()
                 .register_type::<ReplicationConfig>()
                 .register_type::<ReplicationGroupId>()
                 .register_type::<NetworkRelevanceMode>()
                 .register_type::<NetworkTarget>()
                 .register_type::<ShouldBeInterpolated>()
+                .register_type::<ReplacesEntity>()
                 .register_type::<PrePredicted>()
                 .register_type::<ShouldBePredicted>()
                 .register_type::<RemoteEntityMap>()
                 .register_type::<PredictedEntityMap>()
                 .register_type::<HasAuthority>()
                 .register_type::<AuthorityPeer>()
+                .register_type::<ComponentTombstones>()
                 .register_type::<InterpolatedEntityMap>();
         }
     }
@@ -1,5 +1,5 @@
 //! General struct handling replication
-use std::collections::BTreeMap;
+use std::collections::VecDeque;
 
 use super::entity_map::RemoteEntityMap;
 use super::{EntityActionsMessage, EntityUpdatesMessage, SpawnAction};
@@ -8,15 +8,19 @@ use crate::prelude::client::Confirmed;
 use crate::prelude::{ClientConnectionManager, ClientId, ServerConnectionManager, Tick};
 use crate::protocol::component::ComponentRegistry;
 use crate::serialize::reader::Reader;
+use crate::serialize::ToBytes;
 use crate::shared::events::connection::ConnectionEvents;
+use crate::shared::memory::{MemoryLimit, MemoryTally};
 use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
 use crate::shared::replication::components::{Replicated, ReplicationGroupId};
+use crate::shared::replication::debug::NetworkEntityId;
 #[cfg(test)]
 use crate::utils::captures::Captures;
 use bevy::ecs::entity::EntityHash;
 use bevy::prelude::{DespawnRecursiveExt, Entity, EntityWorldMut, World};
-use bevy::utils::HashSet;
-use tracing::{debug, error, info, trace, warn};
+use bevy::utils::{HashMap, HashSet};
+use bytes::Bytes;
+use tracing::{debug, debug_span, error, field, info, trace, warn, Span};
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
@@ -35,6 +39,18 @@ pub struct ReplicationReceiver {
     // BOTH
     /// Buffer to so that we have an ordered receiver per group
     pub(crate) group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
+
+    /// Updates received through an additional replication group of the entity.
+    /// They are applied after the updates of the entity's main group.
+    alias_updates: Vec<AliasUpdate>,
+}
+
+/// Update for an entity received through one of its [`AdditionalReplicationGroups`](super::components::AdditionalReplicationGroups)
+#[derive(Debug)]
+pub(crate) struct AliasUpdate {
+    remote_tick: Tick,
+    remote_entity: Entity,
+    components: Vec<Bytes>,
 }
 
 /// Get `ConnectionEvents` depending on whether we receive from a client or a server
@@ -66,7 +82,28 @@ impl ReplicationReceiver {
             remote_entity_to_group: Default::default(),
             // BOTH
             group_channels: Default::default(),
+            alias_updates: Vec::new(),
+        }
+    }
+
+    /// Add the bytes used by the messages that are waiting to be applied in each group to the tally,
+    /// and return true if one of the groups exceeds the memory limit.
+    ///
+    /// The buffered messages are never evicted: the actions are sent reliably, and the updates were already
+    /// acked at the packet level, so the remote peer would never send the changes that they contain again.
+    pub(crate) fn enforce_memory_limit(
+        &self,
+        limit: &MemoryLimit,
+        tally: &mut MemoryTally,
+    ) -> bool {
+        let mut exceeded = false;
+        for channel in self.group_channels.values() {
+            let bytes =
+                channel.actions_recv_message_buffer.bytes() + channel.buffered_updates.bytes();
+            exceeded |= limit.is_exceeded(bytes);
+            tally.bytes += bytes;
         }
+        exceeded
     }
 
     /// Buffer a received [`EntityActionsMessage`].
@@ -103,6 +140,26 @@ impl ReplicationReceiver {
         trace!(?channel, "group channel after buffering");
     }
 
+    /// Buffer a received [`EntityActionsMessage`] that was sent on a sequenced channel.
+    ///
+    /// Older messages might never be delivered, so we don't wait for them. This means that the
+    /// actions of a skipped message (for example an insert sent after the spawn) are lost.
+    pub(crate) fn recv_sequenced_actions(
+        &mut self,
+        actions: EntityActionsMessage,
+        remote_tick: Tick,
+    ) {
+        let channel = self.group_channels.entry(actions.group_id).or_default();
+        if actions.sequence_id > channel.actions_pending_recv_message_id {
+            trace!(message_id = ?actions.sequence_id, pending_message_id = ?channel.actions_pending_recv_message_id, "skipping older actions messages");
+            channel
+                .actions_recv_message_buffer
+                .skip_to(actions.sequence_id);
+            channel.actions_pending_recv_message_id = actions.sequence_id;
+        }
+        self.recv_actions(actions, remote_tick);
+    }
+
     /// Buffer a received [`EntityUpdatesMessage`].
     ///
     /// The remote_tick is the tick at which the message was buffered and sent by the remote client.
@@ -172,6 +229,19 @@ impl ReplicationReceiver {
             .flat_map(|(group_id, channel)| channel.read_updates())
     }
 
+    /// Map a remote entity to an existing local entity.
+    ///
+    /// This is useful if you already know which local entity corresponds to a remote entity
+    /// (for example via a handshake that exchanges stable ids) before it gets replicated.
+    /// When the spawn of the remote entity is received, the replicated components will be
+    /// inserted on `local_entity` instead of spawning a new entity.
+    ///
+    /// If `local_entity` has been despawned by the time the spawn is received, a new entity is spawned.
+    pub fn map_remote_entity(&mut self, remote_entity: Entity, local_entity: Entity) {
+        self.remote_entity_map
+            .insert_manual(remote_entity, local_entity);
+    }
+
     /// Gets the tick at which the provided confirmed entity currently is
     /// (i.e. the latest server tick at which we received an update for that entity)
     pub(crate) fn get_confirmed_tick(&self, confirmed_entity: Entity) -> Option<Tick> {
@@ -206,6 +276,67 @@ impl ReplicationReceiver {
             .and_then(|group_id| self.group_channels.get(group_id))
     }
 
+    /// Despawn all the entities of a replication group in a single pass.
+    ///
+    /// This is used when the remote peer sends a single [`DespawnGroupsMessage`](super::DespawnGroupsMessage)
+    /// instead of one despawn action per entity.
+    /// The group channel is kept so that the group can be re-used later.
+    ///
+    /// `next_message_id` is the id of the next actions message that the remote sent for this group after the despawn:
+    /// the older actions messages (that might still be in flight) are ignored, so that the entities they spawn
+    /// are not leaked.
+    pub(crate) fn despawn_group(
+        &mut self,
+        group_id: ReplicationGroupId,
+        next_message_id: MessageId,
+        remote_tick: Tick,
+        world: &mut World,
+        events: &mut ConnectionEvents,
+    ) {
+        let channel = self.group_channels.entry(group_id).or_default();
+        debug!(?group_id, ?next_message_id, "Received group despawn");
+        if next_message_id > channel.actions_pending_recv_message_id {
+            channel.actions_recv_message_buffer.skip_to(next_message_id);
+            channel.actions_pending_recv_message_id = next_message_id;
+        }
+        // the updates that depend on the skipped actions can be applied (they will target unknown entities)
+        if !channel.latest_tick.is_some_and(|tick| tick >= remote_tick) {
+            channel.latest_tick = Some(remote_tick);
+        }
+        // the entities spawned by the actions that are still buffered are not part of the despawn
+        let mut remote_entities = std::mem::take(&mut channel.remote_entities);
+        for (_, message) in channel
+            .actions_recv_message_buffer
+            .messages
+            .iter()
+            .flatten()
+        {
+            for (remote_entity, _) in message.actions.iter() {
+                if remote_entities.remove(remote_entity) {
+                    channel.remote_entities.insert(*remote_entity);
+                }
+            }
+        }
+        for remote_entity in remote_entities {
+            // the entity was moved to another group in the meantime
+            if self
+                .remote_entity_to_group
+                .get(&remote_entity)
+                .is_some_and(|entity_group_id| *entity_group_id != group_id)
+            {
+                continue;
+            }
+            self.remote_entity_to_group.remove(&remote_entity);
+            if let Some(local_entity) = self.remote_entity_map.remove_by_remote(remote_entity) {
+                // TODO: we despawn all children as well right now, but that might not be what we want?
+                if let Some(entity_mut) = world.get_entity_mut(local_entity) {
+                    entity_mut.despawn_recursive();
+                }
+                events.push_despawn(local_entity);
+            }
+        }
+    }
+
     /// Do some internal bookkeeping:
     /// - handle tick wrapping
     pub(crate) fn cleanup(&mut self, tick: Tick) {
@@ -254,6 +385,21 @@ impl ReplicationReceiver {
             match actions.spawn {
                 SpawnAction::Spawn => {
                     self.remote_entity_to_group.insert(*remote_entity, group_id);
+                    if let Some(local_entity) =
+                        self.remote_entity_map.take_manual_mapping(*remote_entity)
+                    {
+                        if let Some(mut entity_mut) = world.get_entity_mut(local_entity) {
+                            entity_mut.insert(Replicated { from: remote });
+                            debug!(
+                                ?remote_entity,
+                                ?local_entity,
+                                "Received spawn for a manually mapped entity"
+                            );
+                            events.push_spawn(local_entity);
+                            continue;
+                        }
+                        self.remote_entity_map.remove_by_remote(*remote_entity);
+                    }
                     if let Some(local_entity) = self.remote_entity_map.get_local(*remote_entity) {
                         if world.get_entity(local_entity).is_some() {
                             warn!("Received spawn for an entity that already exists");
@@ -363,8 +509,11 @@ impl ReplicationReceiver {
             // removals
             trace!(remote_entity = ?entity, ?actions.remove, "Received RemoveComponent");
             for kind in actions.remove {
-                events.push_remove_component(local_entity_mut.id(), kind, Tick(0));
-                component_registry.raw_remove(kind, &mut local_entity_mut);
+                let _ = component_registry
+                    .raw_remove(kind, &mut local_entity_mut, events)
+                    .inspect_err(|e| {
+                        error!("could not remove the component from the entity: {:?}", e)
+                    });
             }
 
             // updates
@@ -516,9 +665,24 @@ impl ReplicationReceiver {
         // });
 
         trace!(?current_tick, ?self.group_channels, "applying replication actions messages");
+        // groups whose next actions message moves an entity from a group that hasn't applied the
+        // older actions of that entity yet (for example its spawn)
+        let waiting_groups: Vec<ReplicationGroupId> = self
+            .group_channels
+            .iter()
+            .filter(|(_, channel)| channel.is_waiting_for_moved_entities(&self.group_channels))
+            .map(|(group_id, _)| *group_id)
+            .collect();
         self.group_channels
             .iter_mut()
             .for_each(|(group_id, channel)| {
+                if waiting_groups.contains(group_id) {
+                    trace!(
+                        ?group_id,
+                        "waiting for the previous group of a moved entity before applying actions"
+                    );
+                    return;
+                }
                 let Some((remote_tick, _)) = channel
                     .actions_recv_message_buffer
                     .get(&channel.actions_pending_recv_message_id)
@@ -587,9 +751,30 @@ impl ReplicationReceiver {
                         message,
                         events,
                         &mut self.remote_entity_map,
+                        &self.remote_entity_to_group,
+                        &mut self.alias_updates,
                     );
                 }
-            })
+            });
+
+        // then apply the updates that were received through an additional group of the entity
+        for update in std::mem::take(&mut self.alias_updates) {
+            let Some(channel) = self
+                .remote_entity_to_group
+                .get(&update.remote_entity)
+                .and_then(|group_id| self.group_channels.get_mut(group_id))
+            else {
+                continue;
+            };
+            channel.apply_alias_update(
+                world,
+                remote,
+                component_registry,
+                update,
+                events,
+                &mut self.remote_entity_map,
+            );
+        }
     }
 }
 
@@ -601,11 +786,14 @@ pub struct GroupChannel {
     remote_entities: HashSet<Entity>,
     // actions
     pub(crate) actions_pending_recv_message_id: MessageId,
-    pub(crate) actions_recv_message_buffer: BTreeMap<MessageId, (Tick, EntityActionsMessage)>,
+    pub(crate) actions_recv_message_buffer: ActionsBuffer,
     // updates
     pub(crate) buffered_updates: UpdatesBuffer,
     /// remote tick of the latest update/action that we applied to the local group
     pub latest_tick: Option<Tick>,
+    /// For entities of this group that also received updates through another group,
+    /// remote tick of the latest update that we applied to the entity
+    alias_update_ticks: EntityHashMap<Entity, Tick>,
 }
 
 impl Default for GroupChannel {
@@ -613,9 +801,10 @@ impl Default for GroupChannel {
         Self {
             remote_entities: HashSet::default(),
             actions_pending_recv_message_id: MessageId(0),
-            actions_recv_message_buffer: BTreeMap::new(),
+            actions_recv_message_buffer: ActionsBuffer::default(),
             buffered_updates: UpdatesBuffer::default(),
             latest_tick: None,
+            alias_update_ticks: EntityHashMap::default(),
         }
     }
 }
@@ -666,6 +855,124 @@ impl<'a> Iterator for ActionsIterator<'a> {
     }
 }
 
+/// Stores the [`EntityActionsMessage`] for a given [`ReplicationGroup`](crate::prelude::ReplicationGroup) that
+/// are waiting to be applied, indexed by their [`MessageId`].
+///
+/// Messages are always removed in order (starting from the next expected [`MessageId`]), so we can use a ring buffer
+/// instead of a `BTreeMap`: the allocation is kept when the buffer becomes empty, so receiving messages in order
+/// doesn't allocate.
+///
+/// The ring buffer only covers the next [`MAX_ACTIONS_BUFFER_LEN`] messages; messages that arrive further ahead
+/// are stored in a sparse map until the buffer catches up with them, so that a single message with a large gap
+/// cannot grow the ring buffer to `i16::MAX` slots.
+#[derive(Debug, Default)]
+pub(crate) struct ActionsBuffer {
+    /// [`MessageId`] of the message at the front of the buffer
+    start: MessageId,
+    messages: VecDeque<Option<(Tick, EntityActionsMessage)>>,
+    /// Messages that are too far ahead of `start` to be stored in the ring buffer
+    overflow: HashMap<MessageId, (Tick, EntityActionsMessage)>,
+}
+
+/// Maximum number of slots of the [`ActionsBuffer`] ring buffer
+const MAX_ACTIONS_BUFFER_LEN: usize = 64;
+
+impl ActionsBuffer {
+    /// Index of the message in the buffer, if the message is not older than the start of the buffer
+    fn index(&self, message_id: MessageId) -> Option<usize> {
+        let diff = message_id - self.start;
+        (diff >= 0).then_some(diff as usize)
+    }
+
+    /// Buffer a message. Messages older than the next expected message are ignored.
+    pub(crate) fn insert(&mut self, message_id: MessageId, message: (Tick, EntityActionsMessage)) {
+        let Some(index) = self.index(message_id) else {
+            return;
+        };
+        if index >= MAX_ACTIONS_BUFFER_LEN {
+            self.overflow.insert(message_id, message);
+            return;
+        }
+        if index >= self.messages.len() {
+            self.messages.resize_with(index + 1, || None);
+        }
+        self.messages[index] = Some(message);
+    }
+
+    pub(crate) fn get(&self, message_id: &MessageId) -> Option<&(Tick, EntityActionsMessage)> {
+        let index = self.index(*message_id)?;
+        if index >= MAX_ACTIONS_BUFFER_LEN {
+            return self.overflow.get(message_id);
+        }
+        self.messages.get(index)?.as_ref()
+    }
+
+    pub(crate) fn remove(
+        &mut self,
+        message_id: &MessageId,
+    ) -> Option<(Tick, EntityActionsMessage)> {
+        let index = self.index(*message_id)?;
+        if index >= MAX_ACTIONS_BUFFER_LEN {
+            return self.overflow.remove(message_id);
+        }
+        let message = self.messages.get_mut(index)?.take();
+        // advance the start of the buffer to the next expected message
+        if index == 0 && message.is_some() {
+            self.messages.pop_front();
+            self.start += 1;
+            self.move_overflow_in_window();
+        }
+        message
+    }
+
+    /// Drop all the messages older than `message_id`, which becomes the next expected message
+    pub(crate) fn skip_to(&mut self, message_id: MessageId) {
+        let Some(index) = self.index(message_id) else {
+            return;
+        };
+        self.messages.drain(..index.min(self.messages.len()));
+        self.start = message_id;
+        self.overflow.retain(|id, _| *id >= message_id);
+        self.move_overflow_in_window();
+    }
+
+    /// Move the overflow messages that are now close enough to `start` into the ring buffer
+    fn move_overflow_in_window(&mut self) {
+        if self.overflow.is_empty() {
+            return;
+        }
+        let start = self.start;
+        let in_window: Vec<MessageId> = self
+            .overflow
+            .keys()
+            .filter(|id| ((**id - start) as usize) < MAX_ACTIONS_BUFFER_LEN)
+            .copied()
+            .collect();
+        for message_id in in_window {
+            let message = self.overflow.remove(&message_id).unwrap();
+            self.insert(message_id, message);
+        }
+    }
+
+    pub(crate) fn contains_key(&self, message_id: &MessageId) -> bool {
+        self.get(message_id).is_some()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.overflow.is_empty() && self.messages.iter().all(Option::is_none)
+    }
+
+    /// Number of bytes of the serialized messages in the buffer
+    fn bytes(&self) -> usize {
+        self.messages
+            .iter()
+            .flatten()
+            .chain(self.overflow.values())
+            .map(|(_, message)| message.len())
+            .sum()
+    }
+}
+
 // TODO: try a sequence buffer?
 /// Stores the [`EntityUpdatesMessage`] for a given [`ReplicationGroup`](crate::prelude::ReplicationGroup), sorted
 /// in descending remote tick order (the most recent tick first, the oldest tick last)
@@ -737,6 +1044,11 @@ impl UpdatesBuffer {
     fn pop_oldest(&mut self) -> Option<(Tick, EntityUpdatesMessage)> {
         self.0.pop()
     }
+
+    /// Number of bytes of the serialized messages in the buffer
+    fn bytes(&self) -> usize {
+        self.0.iter().map(|(_, message)| message.len()).sum()
+    }
 }
 
 /// Iterator that returns all the available [`EntityUpdatesMessage`] for the current [`GroupChannel`]
@@ -805,6 +1117,29 @@ impl GroupChannel {
         }
     }
 
+    /// Returns true if the next actions message moves an entity from another group, and that group
+    /// hasn't applied all the actions that were sent before the move yet.
+    fn is_waiting_for_moved_entities(
+        &self,
+        group_channels: &EntityHashMap<ReplicationGroupId, GroupChannel>,
+    ) -> bool {
+        let Some((_, message)) = self
+            .actions_recv_message_buffer
+            .get(&self.actions_pending_recv_message_id)
+        else {
+            return false;
+        };
+        message
+            .actions
+            .iter()
+            .any(|(_, actions)| match actions.spawn {
+                SpawnAction::Move { from, after } => !group_channels
+                    .get(&from)
+                    .is_some_and(|channel| channel.actions_pending_recv_message_id >= after),
+                _ => false,
+            })
+    }
+
     /// Apply actions for channel
     pub(crate) fn apply_actions_message(
         &mut self,
@@ -831,6 +1166,27 @@ impl GroupChannel {
                     remote_entity_to_group.insert(*remote_entity, group_id);
                     // TODO ABOVE
 
+                    // the remote entity was mapped manually to an existing local entity: replicate onto it
+                    // instead of spawning a new entity
+                    if let Some(local_entity) =
+                        remote_entity_map.take_manual_mapping(*remote_entity)
+                    {
+                        if let Some(mut entity_mut) = world.get_entity_mut(local_entity) {
+                            entity_mut.insert(Replicated { from: remote });
+                            if let Some(client) = remote {
+                                entity_mut.insert(AuthorityPeer::Client(client));
+                            }
+                            debug!(
+                                ?remote_entity,
+                                ?local_entity,
+                                "Received spawn for a manually mapped entity"
+                            );
+                            events.push_spawn(local_entity);
+                            continue;
+                        }
+                        // the local entity was despawned in the meantime, spawn a new entity instead
+                        remote_entity_map.remove_by_remote(*remote_entity);
+                    }
                     if let Some(local_entity) = remote_entity_map.get_local(*remote_entity) {
                         if world.get_entity(local_entity).is_some() {
                             warn!(
@@ -884,6 +1240,21 @@ impl GroupChannel {
                     // update the entity mapping
                     remote_entity_map.insert(*remote_entity, local_entity);
                 }
+                SpawnAction::Move { .. } => {
+                    // the entity was moved from another group to this group
+                    if let Some(previous_group_id) = remote_entity_to_group
+                        .get_mut(remote_entity)
+                        .filter(|previous_group_id| **previous_group_id != group_id)
+                    {
+                        debug!(
+                            ?remote_entity,
+                            ?previous_group_id,
+                            ?group_id,
+                            "Entity moved to a different replication group"
+                        );
+                        *previous_group_id = group_id;
+                    }
+                }
                 _ => {}
             }
         }
@@ -902,6 +1273,7 @@ impl GroupChannel {
                     }
                     events.push_despawn(local_entity);
                     remote_entity_to_group.remove(&entity);
+                    self.alias_update_ticks.remove(&entity);
                 } else {
                     error!("Received despawn for an entity that does not exist")
                 }
@@ -913,6 +1285,7 @@ impl GroupChannel {
                 error!(?entity, "cannot find entity");
                 continue;
             };
+            let _span = Self::entity_span(&local_entity_mut, entity).entered();
             if !Self::authority_check(&mut local_entity_mut, remote) {
                 trace!("Ignored a replication action received from peer {:?} that does not have authority over the entity: {:?}", remote, entity);
                 continue;
@@ -953,8 +1326,11 @@ impl GroupChannel {
             // removals
             trace!(remote_entity = ?entity, ?actions.remove, "Received RemoveComponent");
             for kind in actions.remove {
-                events.push_remove_component(local_entity_mut.id(), kind, Tick(0));
-                component_registry.raw_remove(kind, &mut local_entity_mut);
+                let _ = component_registry
+                    .raw_remove(kind, &mut local_entity_mut, events)
+                    .inspect_err(|e| {
+                        error!("could not remove the component from the entity: {:?}", e)
+                    });
             }
 
             // updates
@@ -994,6 +1370,24 @@ impl GroupChannel {
         }
     }
 
+    /// Span that identifies the entity in the logs, to correlate the logs of the remote and local peers.
+    ///
+    /// The `network_id` is only recorded if the [`ReplicationDebugPlugin`](super::debug::ReplicationDebugPlugin)
+    /// is enabled.
+    fn entity_span(entity_mut: &EntityWorldMut, remote_entity: Entity) -> Span {
+        let span = debug_span!(
+            "replicated_entity",
+            ?remote_entity,
+            local_entity = ?entity_mut.id(),
+            network_id = field::Empty,
+        );
+        if let Some(network_id) = entity_mut.get::<NetworkEntityId>() {
+            span.record("network_id", network_id.0);
+        }
+        span
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn apply_updates_message(
         &mut self,
         world: &mut World,
@@ -1004,6 +1398,8 @@ impl GroupChannel {
         message: EntityUpdatesMessage,
         events: &mut ConnectionEvents,
         remote_entity_map: &mut RemoteEntityMap,
+        remote_entity_to_group: &EntityHashMap<Entity, ReplicationGroupId>,
+        alias_updates: &mut Vec<AliasUpdate>,
     ) {
         let group_id = message.group_id;
         debug!(?remote_tick, ?message, "Received replication updates");
@@ -1014,39 +1410,135 @@ impl GroupChannel {
         }
         for (entity, components) in message.updates.into_iter() {
             debug!(?components, remote_entity = ?entity, "Received UpdateComponent");
-            let Some(mut local_entity_mut) = remote_entity_map.get_by_remote(world, entity) else {
-                // we can get a few buffered updates after the entity has been despawned
-                // those are the updates that we received before the despawn action message, but with a tick
-                // later than the despawn action message
-                info!(remote_entity = ?entity, "update for entity that doesn't exist?");
-                continue;
-            };
-            if !Self::authority_check(&mut local_entity_mut, remote) {
-                trace!("Ignored a replication update received from peer {:?} that does not have authority over the entity: {:?}", remote, entity);
-                continue;
-            };
-            if !Self::authority_check(&mut local_entity_mut, remote) {
-                debug!("authority check failed for entity: {:?}", entity);
+            // the entity belongs to another group, and this group is one of its additional groups:
+            // the update will be applied after the updates of the entity's main group
+            if remote_entity_to_group
+                .get(&entity)
+                .is_some_and(|main_group_id| *main_group_id != group_id)
+            {
+                alias_updates.push(AliasUpdate {
+                    remote_tick,
+                    remote_entity: entity,
+                    components,
+                });
                 continue;
             }
-            for component in components {
-                let mut reader = Reader::from(component);
-                let _ = component_registry
-                    .raw_write(
-                        &mut reader,
-                        &mut local_entity_mut,
-                        remote_tick,
-                        &mut remote_entity_map.remote_to_local,
-                        events,
-                    )
-                    .inspect_err(|e| {
-                        error!("could not write the component to the entity: {:?}", e)
-                    });
+            // we already applied a more recent update through one of the entity's additional groups
+            if let Some(alias_tick) = self.alias_update_ticks.get_mut(&entity) {
+                if *alias_tick >= remote_tick {
+                    trace!(remote_entity = ?entity, "Ignored an update older than the latest update received through an additional group");
+                    continue;
+                }
+                *alias_tick = remote_tick;
             }
+            self.apply_entity_update(
+                world,
+                remote,
+                component_registry,
+                remote_tick,
+                entity,
+                components,
+                events,
+                remote_entity_map,
+            );
         }
         self.update_confirmed_tick(world, group_id, remote_tick, remote_entity_map);
     }
 
+    /// Apply an update for an entity of this group that was received through one of the entity's additional groups.
+    ///
+    /// The update is only applied if it is more recent than any actions or updates that were already
+    /// applied to the entity.
+    fn apply_alias_update(
+        &mut self,
+        world: &mut World,
+        remote: Option<ClientId>,
+        component_registry: &ComponentRegistry,
+        update: AliasUpdate,
+        events: &mut ConnectionEvents,
+        remote_entity_map: &mut RemoteEntityMap,
+    ) {
+        let AliasUpdate {
+            remote_tick,
+            remote_entity,
+            components,
+        } = update;
+        if self.latest_tick.is_some_and(|t| remote_tick <= t)
+            || self
+                .alias_update_ticks
+                .get(&remote_entity)
+                .is_some_and(|t| remote_tick <= *t)
+        {
+            trace!(
+                ?remote_entity,
+                ?remote_tick,
+                "Ignored an outdated update received through an additional group"
+            );
+            return;
+        }
+        self.alias_update_ticks.insert(remote_entity, remote_tick);
+        if self.apply_entity_update(
+            world,
+            remote,
+            component_registry,
+            remote_tick,
+            remote_entity,
+            components,
+            events,
+            remote_entity_map,
+        ) {
+            if let Some(mut local_entity_mut) =
+                remote_entity_map.get_by_remote(world, remote_entity)
+            {
+                if let Some(mut confirmed) = local_entity_mut.get_mut::<Confirmed>() {
+                    confirmed.tick = remote_tick;
+                }
+            }
+        }
+    }
+
+    /// Write the component updates to the local entity.
+    ///
+    /// Returns false if the update could not be applied to the entity.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_entity_update(
+        &mut self,
+        world: &mut World,
+        remote: Option<ClientId>,
+        component_registry: &ComponentRegistry,
+        remote_tick: Tick,
+        entity: Entity,
+        components: Vec<Bytes>,
+        events: &mut ConnectionEvents,
+        remote_entity_map: &mut RemoteEntityMap,
+    ) -> bool {
+        let Some(mut local_entity_mut) = remote_entity_map.get_by_remote(world, entity) else {
+            // we can get a few buffered updates after the entity has been despawned
+            // those are the updates that we received before the despawn action message, but with a tick
+            // later than the despawn action message
+            info!(remote_entity = ?entity, "update for entity that doesn't exist?");
+            return false;
+        };
+        let _span = Self::entity_span(&local_entity_mut, entity).entered();
+        if !Self::authority_check(&mut local_entity_mut, remote) {
+            trace!("Ignored a replication update received from peer {:?} that does not have authority over the entity: {:?}", remote, entity);
+            return false;
+        };
+        for component in components {
+            let mut reader = Reader::from(component);
+            let _ = component_registry
+                .raw_write(
+                    &mut reader,
+                    &mut local_entity_mut,
+                    remote_tick,
+                    &mut remote_entity_map.remote_to_local,
+                    events,
+                )
+                .inspect_err(|e| error!("could not write the component to the entity: {:?}", e));
+        }
+        true
+    }
+
     /// Update the Confirmed tick for all entities in the replication group
     /// so that Predicted/Interpolated entities can be notified
     ///
@@ -1229,6 +1721,192 @@ mod tests {
         assert!(it.next().is_none());
     }
 
+    /// Check that actions messages received on a sequenced channel don't wait for
+    /// older messages that were not delivered
+    #[test]
+    fn test_recv_sequenced_actions() {
+        let mut manager = ReplicationReceiver::new();
+        let group_id = ReplicationGroupId(0);
+
+        // buffer message 1 (message 0 was not delivered)
+        manager.recv_actions(
+            EntityActionsMessage {
+                group_id,
+                sequence_id: MessageId(1),
+                actions: Default::default(),
+            },
+            Tick(1),
+        );
+        // receive message 2 on a sequenced channel: messages 0 and 1 are skipped
+        manager.recv_sequenced_actions(
+            EntityActionsMessage {
+                group_id,
+                sequence_id: MessageId(2),
+                actions: Default::default(),
+            },
+            Tick(2),
+        );
+        let channel = manager.group_channels.get(&group_id).unwrap();
+        assert_eq!(channel.actions_pending_recv_message_id, MessageId(2));
+        assert!(!channel
+            .actions_recv_message_buffer
+            .contains_key(&MessageId(1)));
+        assert!(channel
+            .actions_recv_message_buffer
+            .contains_key(&MessageId(2)));
+
+        // an older message received afterwards is ignored
+        manager.recv_sequenced_actions(
+            EntityActionsMessage {
+                group_id,
+                sequence_id: MessageId(1),
+                actions: Default::default(),
+            },
+            Tick(1),
+        );
+        let channel = manager.group_channels.get(&group_id).unwrap();
+        assert_eq!(channel.actions_pending_recv_message_id, MessageId(2));
+        assert!(!channel
+            .actions_recv_message_buffer
+            .contains_key(&MessageId(1)));
+    }
+
+    /// Check that the buffered updates are not evicted when a group exceeds the memory limit
+    #[test]
+    fn test_memory_limit_does_not_evict_updates() {
+        let mut manager = ReplicationReceiver::new();
+        let group_id = ReplicationGroupId(0);
+        // the update waits for an actions message that was not received yet
+        manager.recv_updates(
+            EntityUpdatesMessage {
+                group_id,
+                last_action_tick: Some(Tick(3)),
+                updates: vec![(Entity::from_raw(1), vec![Bytes::from_static(&[0; 16])])],
+            },
+            Tick(4),
+        );
+        let mut tally = MemoryTally::default();
+        assert!(!manager.enforce_memory_limit(&MemoryLimit::default(), &mut tally));
+        let mut tally = MemoryTally::default();
+        assert!(manager.enforce_memory_limit(&MemoryLimit::new(1), &mut tally));
+        assert!(tally.bytes > 1);
+        assert_eq!(tally.evictions, 0);
+        assert_eq!(
+            manager
+                .group_channels
+                .get(&group_id)
+                .unwrap()
+                .buffered_updates
+                .len(),
+            1
+        );
+    }
+
+    /// Check that the spawn of an entity that arrives after the despawn of its group is ignored
+    #[test]
+    fn test_despawn_group_before_spawn() {
+        let mut manager = ReplicationReceiver::new();
+        let mut world = World::new();
+        let mut events = ConnectionEvents::default();
+        let group_id = ReplicationGroupId(0);
+        let remote_entity = Entity::from_raw(1000);
+
+        // the remote sent the spawn in actions message 0, then despawned the group
+        manager.despawn_group(group_id, MessageId(1), Tick(5), &mut world, &mut events);
+        let channel = manager.group_channels.get(&group_id).unwrap();
+        assert_eq!(channel.actions_pending_recv_message_id, MessageId(1));
+        assert_eq!(channel.latest_tick, Some(Tick(5)));
+
+        // the spawn is received afterwards
+        manager.recv_actions(
+            EntityActionsMessage {
+                group_id,
+                sequence_id: MessageId(0),
+                actions: vec![(
+                    remote_entity,
+                    EntityActions {
+                        spawn: SpawnAction::Spawn,
+                        insert: vec![],
+                        remove: Default::default(),
+                        updates: vec![],
+                    },
+                )],
+            },
+            Tick(4),
+        );
+        let channel = manager.group_channels.get(&group_id).unwrap();
+        assert!(channel.actions_recv_message_buffer.is_empty());
+        assert!(channel.remote_entities.is_empty());
+    }
+
+    /// Check that an entity that is moved to another group is not moved before its spawn
+    /// in the previous group has been applied
+    #[test]
+    fn test_move_entity_before_spawn() {
+        let mut manager = ReplicationReceiver::new();
+        let mut world = World::new();
+        let component_registry = ComponentRegistry::default();
+        let mut events = ConnectionEvents::default();
+        let previous_group = ReplicationGroupId(0);
+        let new_group = ReplicationGroupId(1);
+        let remote_entity = Entity::from_raw(1000);
+        let actions = |spawn| {
+            vec![(
+                remote_entity,
+                EntityActions {
+                    spawn,
+                    insert: vec![],
+                    remove: Default::default(),
+                    updates: vec![],
+                },
+            )]
+        };
+
+        // the move is received before the spawn
+        manager.recv_actions(
+            EntityActionsMessage {
+                group_id: new_group,
+                sequence_id: MessageId(0),
+                actions: actions(SpawnAction::Move {
+                    from: previous_group,
+                    after: MessageId(1),
+                }),
+            },
+            Tick(2),
+        );
+        manager.apply_world(&mut world, None, &component_registry, Tick(3), &mut events);
+        assert!(manager.remote_entity_to_group.get(&remote_entity).is_none());
+        assert_eq!(
+            manager
+                .group_channels
+                .get(&new_group)
+                .unwrap()
+                .actions_pending_recv_message_id,
+            MessageId(0)
+        );
+
+        // the spawn is received: it is applied first, then the move
+        manager.recv_actions(
+            EntityActionsMessage {
+                group_id: previous_group,
+                sequence_id: MessageId(0),
+                actions: actions(SpawnAction::Spawn),
+            },
+            Tick(1),
+        );
+        manager.apply_world(&mut world, None, &component_registry, Tick(3), &mut events);
+        assert_eq!(
+            manager.remote_entity_to_group.get(&remote_entity),
+            Some(&previous_group)
+        );
+        manager.apply_world(&mut world, None, &component_registry, Tick(3), &mut events);
+        assert_eq!(
+            manager.remote_entity_to_group.get(&remote_entity),
+            Some(&new_group)
+        );
+        assert!(manager.remote_entity_map.get_local(remote_entity).is_some());
+    }
+
     #[allow(clippy::get_first)]
     #[test]
     fn test_recv_replication_messages() {
@@ -1386,6 +2064,50 @@ mod tests {
         assert!(updates.next().is_none());
     }
 
+    #[test]
+    fn test_actions_buffer() {
+        let message = |id: u16| {
+            (
+                Tick(id),
+                EntityActionsMessage {
+                    group_id: ReplicationGroupId(0),
+                    sequence_id: MessageId(id),
+                    actions: Default::default(),
+                },
+            )
+        };
+        let mut buffer = ActionsBuffer::default();
+        // out of order messages wait for the previous ones
+        buffer.insert(MessageId(1), message(1));
+        assert!(buffer.get(&MessageId(0)).is_none());
+        assert!(buffer.remove(&MessageId(0)).is_none());
+        buffer.insert(MessageId(0), message(0));
+        assert_eq!(buffer.remove(&MessageId(0)), Some(message(0)));
+        assert_eq!(buffer.remove(&MessageId(1)), Some(message(1)));
+        assert!(buffer.is_empty());
+
+        // old messages are ignored
+        buffer.insert(MessageId(0), message(0));
+        assert!(buffer.is_empty());
+
+        // the allocation is kept when the buffer is emptied
+        let capacity = buffer.messages.capacity();
+        buffer.insert(MessageId(2), message(2));
+        assert_eq!(buffer.remove(&MessageId(2)), Some(message(2)));
+        assert_eq!(buffer.messages.capacity(), capacity);
+
+        // messages far ahead are not stored in the ring buffer
+        let far = MAX_ACTIONS_BUFFER_LEN as u16 + 10;
+        buffer.insert(MessageId(far), message(far));
+        assert!(buffer.messages.len() <= MAX_ACTIONS_BUFFER_LEN);
+        assert!(buffer.contains_key(&MessageId(far)));
+        // they are moved to the ring buffer once the buffer catches up with them
+        buffer.skip_to(MessageId(far - 1));
+        assert!(buffer.overflow.is_empty());
+        assert_eq!(buffer.remove(&MessageId(far)), Some(message(far)));
+        assert!(buffer.is_empty());
+    }
+
     /// Test applying to the world an EntityActionsMessage that uses SpawnReuse
     #[test]
     fn test_recv_spawn_reuse() {
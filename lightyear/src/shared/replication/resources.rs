@@ -1,4 +1,16 @@
 //! Module to handle the replication of bevy [`Resource`]s
+//!
+//! Resources are replicated with regular messages, so there is no ordering guarantee between the updates of
+//! a resource and the replication of the entities. If some entities depend on a resource (for example entities with
+//! a `TeamId` that refers to a `TeamsConfig` resource), the resource can be replicated on the
+//! [`OrderedResourceChannel`](crate::prelude::OrderedResourceChannel): the server then holds the replication of the
+//! entities to a client until the client has received the resource update, so that the entity updates of that tick
+//! are applied after the resource. This adds a round-trip of latency to the entity replication every time the
+//! resource changes, so it should be used for resources that rarely change.
+//!
+//! ```rust,ignore
+//! commands.replicate_resource::<TeamsConfig, OrderedResourceChannel>(NetworkTarget::All);
+//! ```
 
 use std::marker::PhantomData;
 
@@ -7,23 +19,25 @@ use bevy::prelude::{
     Commands, DetectChanges, IntoSystemConfigs, IntoSystemSetConfigs, Plugin, PostUpdate,
     PreUpdate, Res, ResMut, Resource,
 };
+use bevy::utils::{HashMap, HashSet};
 pub use command::{ReplicateResourceExt, StopReplicateResourceExt};
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::{ChannelKind, Message};
+use crate::prelude::{Channel, ChannelKind, ClientId, Message, Tick};
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::ReplicationSend;
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
 
 mod command {
     use super::*;
-    use crate::prelude::Channel;
 
     /// Extension trait to be able to replicate a resource to remote clients via [`Commands`].
     pub trait ReplicateResourceExt {
         /// Start replicating a resource to remote clients.
         ///
-        /// Any change to the resource will be replicated to the clients.
+        /// Any change to the resource will be replicated to the clients. Use the
+        /// [`OrderedResourceChannel`](crate::prelude::OrderedResourceChannel) if the resource updates must be
+        /// applied before the entity updates that follow them.
         fn replicate_resource<R: Resource, C: Channel>(&mut self, target: NetworkTarget);
     }
 
@@ -61,6 +75,72 @@ pub struct ReplicateResourceMetadata<R> {
     _marker: PhantomData<R>,
 }
 
+/// Per-client values of a resource `R`, replicated by the server to each client.
+///
+/// This can be used for configuration values that are specific to each client (interpolation delay hints,
+/// allowed send rate, feature toggles, etc.). Each client only receives its own value, as the resource `R`;
+/// it is inserted or updated like any replicated resource, so the client can react to changes with bevy's
+/// change detection (for example the `resource_changed::<R>` run condition).
+///
+/// The resource `R` must be registered with [`register_resource`](crate::prelude::AppMessageExt::register_resource)
+/// in the [`ChannelDirection::ServerToClient`](crate::prelude::ChannelDirection) direction.
+/// The value of a client is forgotten when the client disconnects.
+#[derive(Resource, Debug)]
+pub struct PerClientResource<R> {
+    channel: ChannelKind,
+    values: HashMap<ClientId, R>,
+    /// Clients whose value needs to be sent
+    changed: HashSet<ClientId>,
+    /// Clients whose value was removed
+    removed: HashSet<ClientId>,
+}
+
+impl<R> PerClientResource<R> {
+    /// Create the per-client resource; the values will be sent on the channel `C`
+    pub fn new<C: Channel>() -> Self {
+        Self {
+            channel: ChannelKind::of::<C>(),
+            values: HashMap::default(),
+            changed: HashSet::default(),
+            removed: HashSet::default(),
+        }
+    }
+
+    /// Get the value of the resource for a client
+    pub fn get(&self, client_id: ClientId) -> Option<&R> {
+        self.values.get(&client_id)
+    }
+
+    /// Get a mutable reference to the value of the resource for a client.
+    ///
+    /// The value will be sent to the client again, even if it is not modified.
+    pub fn get_mut(&mut self, client_id: ClientId) -> Option<&mut R> {
+        let value = self.values.get_mut(&client_id)?;
+        self.changed.insert(client_id);
+        Some(value)
+    }
+
+    /// Set the value of the resource for a client, and return the previous value
+    pub fn insert(&mut self, client_id: ClientId, value: R) -> Option<R> {
+        self.removed.remove(&client_id);
+        self.changed.insert(client_id);
+        self.values.insert(client_id, value)
+    }
+
+    /// Remove the value of the resource for a client; the resource is also removed on the client
+    pub fn remove(&mut self, client_id: ClientId) -> Option<R> {
+        let value = self.values.remove(&client_id)?;
+        self.changed.remove(&client_id);
+        self.removed.insert(client_id);
+        Some(value)
+    }
+
+    /// Iterate through the clients that have a value for the resource
+    pub fn iter(&self) -> impl Iterator<Item = (&ClientId, &R)> {
+        self.values.iter()
+    }
+}
+
 /// Message that indicates that a resource should be despawned
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DespawnResource<R> {
@@ -75,12 +155,38 @@ impl<R> Default for DespawnResource<R> {
     }
 }
 
+/// Update of a resource that is predicted by the clients.
+///
+/// The server sends the value along with the tick at which it was computed, so that the client can
+/// compare it with its predicted history for that same tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictedResourceUpdate<R> {
+    pub tick: Tick,
+    /// The new value of the resource, or `None` if the resource was removed
+    pub value: Option<R>,
+}
+
+/// Marker indicating that the resource `R` is predicted by the clients, so that its updates are sent
+/// as [`PredictedResourceUpdate`] messages instead of as the resource itself
+#[derive(Resource)]
+pub(crate) struct PredictedResourceMarker<R>(PhantomData<R>);
+
+impl<R> Default for PredictedResourceMarker<R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
 pub(crate) mod send {
     use super::*;
 
     use crate::connection::client::{ClientConnection, NetClient};
+    use crate::packet::message_manager::DEFAULT_MESSAGE_PRIORITY;
+    use crate::prelude::TickManager;
+    use crate::server;
     use crate::shared::message::MessageSend;
-    use bevy::prelude::resource_removed;
+    use crate::shared::sets::ServerMarker;
+    use bevy::prelude::{resource_removed, EventReader, Local};
     use tracing::trace;
 
     pub(crate) struct ResourceSendPlugin<R> {
@@ -119,7 +225,12 @@ pub(crate) mod send {
     fn send_resource_removal<R: Resource + Message, S: MessageSend>(
         mut connection_manager: ResMut<S>,
         replication_resource: Option<Res<ReplicateResourceMetadata<R>>>,
+        predicted: Option<Res<PredictedResourceMarker<R>>>,
     ) {
+        // predicted resources are sent by `send_predicted_resource_update`
+        if predicted.is_some() {
+            return;
+        }
         if let Some(replication_resource) = replication_resource {
             let _ = connection_manager.erased_send_message_to_target::<DespawnResource<R>>(
                 &mut DespawnResource::default(),
@@ -136,7 +247,12 @@ pub(crate) mod send {
         // TODO: support Res<R> by separating MapEntities from non-map-entities?
         mut resource: Option<ResMut<R>>,
         local_client_connection: Option<Res<ClientConnection>>,
+        predicted: Option<Res<PredictedResourceMarker<R>>>,
     ) {
+        // predicted resources are sent by `send_predicted_resource_update`
+        if predicted.is_some() {
+            return;
+        }
         // send the resource to newly connected clients
         let new_clients = connection_manager.new_connected_clients();
         if !new_clients.is_empty() {
@@ -177,6 +293,133 @@ pub(crate) mod send {
             }
         }
     }
+
+    pub(crate) fn add_predicted_resource_send_systems<R: Resource + Message + Clone>(
+        app: &mut App,
+    ) {
+        app.init_resource::<PredictedResourceMarker<R>>();
+        app.add_systems(
+            PostUpdate,
+            send_predicted_resource_update::<R>
+                .in_set(InternalReplicationSet::<ServerMarker>::BufferResourceUpdates),
+        );
+    }
+
+    /// Send the updates and the removal of a resource predicted by the clients, along with the
+    /// current server tick
+    fn send_predicted_resource_update<R: Resource + Message + Clone>(
+        mut connection_manager: ResMut<server::connection::ConnectionManager>,
+        replication_resource: Option<Res<ReplicateResourceMetadata<R>>>,
+        resource: Option<Res<R>>,
+        tick_manager: Res<TickManager>,
+        local_client_connection: Option<Res<ClientConnection>>,
+        // whether the resource existed during the previous run, to detect removals
+        mut existed: Local<bool>,
+    ) {
+        let was_present = std::mem::replace(&mut *existed, resource.is_some());
+        let Some(replication_resource) = replication_resource else {
+            return;
+        };
+        let new_clients = connection_manager.new_connected_clients();
+        let mut target = replication_resource.target.clone();
+        // if running in host-server mode, we don't want to replicate the resource to the local client
+        if let Some(local_client) = local_client_connection.as_ref() {
+            target.exclude(&NetworkTarget::Single(local_client.client.id()));
+        }
+        let tick = tick_manager.tick();
+        let Some(resource) = resource else {
+            if was_present {
+                let _ = connection_manager.erased_send_message_to_target(
+                    &PredictedResourceUpdate::<R> { tick, value: None },
+                    replication_resource.channel,
+                    target,
+                    DEFAULT_MESSAGE_PRIORITY,
+                );
+            }
+            return;
+        };
+        if !resource.is_changed() {
+            // only the newly connected clients need the resource
+            target.intersection(&NetworkTarget::Only(new_clients));
+        }
+        if target.is_empty() {
+            return;
+        }
+        trace!(
+            ?tick,
+            "sending predicted resource update: {:?}",
+            std::any::type_name::<R>()
+        );
+        let _ = connection_manager.erased_send_message_to_target(
+            &PredictedResourceUpdate {
+                tick,
+                value: Some(resource.as_ref().clone()),
+            },
+            replication_resource.channel,
+            target,
+            DEFAULT_MESSAGE_PRIORITY,
+        );
+    }
+
+    pub(crate) fn add_per_client_resource_send_systems<R: Resource + Message>(app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            send_per_client_resource_updates::<R>
+                .in_set(InternalReplicationSet::<ServerMarker>::BufferResourceUpdates),
+        );
+    }
+
+    /// Send the values of a [`PerClientResource`] that were updated or removed to their client
+    fn send_per_client_resource_updates<R: Resource + Message>(
+        mut connection_manager: ResMut<server::connection::ConnectionManager>,
+        resource: Option<ResMut<PerClientResource<R>>>,
+        mut disconnections: EventReader<server::events::DisconnectEvent>,
+    ) {
+        let Some(mut resource) = resource else {
+            return;
+        };
+        let PerClientResource {
+            channel,
+            values,
+            changed,
+            removed,
+        } = resource.as_mut();
+        for event in disconnections.read() {
+            values.remove(&event.client_id);
+            changed.remove(&event.client_id);
+            removed.remove(&event.client_id);
+        }
+        // send the current value to newly connected clients
+        changed.extend(
+            connection_manager
+                .new_connected_clients()
+                .into_iter()
+                .filter(|client_id| values.contains_key(client_id)),
+        );
+        for client_id in removed.drain() {
+            let _ = connection_manager.erased_send_message_to_target(
+                &DespawnResource::<R>::default(),
+                *channel,
+                NetworkTarget::Single(client_id),
+                DEFAULT_MESSAGE_PRIORITY,
+            );
+        }
+        for client_id in changed.drain() {
+            if let Some(value) = values.get(&client_id) {
+                trace!(
+                    ?client_id,
+                    "sending per-client resource update: {:?}",
+                    std::any::type_name::<R>()
+                );
+                let _ = connection_manager.erased_send_message_to_target(
+                    value,
+                    *channel,
+                    NetworkTarget::Single(client_id),
+                    DEFAULT_MESSAGE_PRIORITY,
+                );
+            }
+        }
+    }
 }
 
 pub(crate) mod receive {
@@ -305,12 +548,15 @@ pub(crate) mod receive {
 
 #[cfg(test)]
 mod tests {
-    use super::StopReplicateResourceExt;
+    use super::{PerClientResource, StopReplicateResourceExt};
+    use crate::prelude::server::Replicate;
+    use crate::prelude::ClientId;
+    use crate::prelude::OrderedResourceChannel;
     use crate::shared::replication::network_target::NetworkTarget;
     use crate::shared::replication::resources::ReplicateResourceExt;
     use crate::tests::host_server_stepper::HostServerStepper;
-    use crate::tests::protocol::{Channel1, Resource1, Resource2};
-    use crate::tests::stepper::BevyStepper;
+    use crate::tests::protocol::{Channel1, ComponentSyncModeFull, Resource1, Resource2};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
     use bevy::prelude::*;
 
     #[test]
@@ -402,6 +648,57 @@ mod tests {
         assert_eq!(stepper.client_app.world().resource::<Resource1>().0, 1.0);
     }
 
+    /// Check that the entity updates are applied after the resource updates sent on the OrderedResourceChannel
+    #[test]
+    fn test_ordered_resource_replication() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.world_mut().observe(
+            |trigger: Trigger<OnAdd, ComponentSyncModeFull>, resource: Option<Res<Resource1>>| {
+                assert_eq!(
+                    resource.map(|r| r.0),
+                    Some(1.0),
+                    "entity {:?} was replicated before the resource",
+                    trigger.entity()
+                );
+            },
+        );
+        let start_replicate_system =
+            stepper
+                .server_app
+                .world_mut()
+                .register_system(|mut commands: Commands| {
+                    commands.replicate_resource::<Resource1, OrderedResourceChannel>(
+                        NetworkTarget::All,
+                    );
+                });
+        let _ = stepper
+            .server_app
+            .world_mut()
+            .run_system(start_replicate_system);
+        stepper.frame_step();
+
+        // the resource and the entity are updated in the same frame
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(Resource1(1.0));
+        stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)));
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        let mut query = stepper
+            .client_app
+            .world_mut()
+            .query::<&ComponentSyncModeFull>();
+        assert_eq!(
+            query.single(stepper.client_app.world()),
+            &ComponentSyncModeFull(1.0)
+        );
+    }
+
     #[test]
     fn test_resource_replication_via_commands_host_server() {
         let mut stepper = HostServerStepper::default();
@@ -778,4 +1075,44 @@ mod tests {
         // check that the update was replicated to the server
         assert_eq!(stepper.server_app.world().resource::<Resource2>().0, 3.0);
     }
+
+    #[test]
+    fn test_per_client_resource() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let mut resource = PerClientResource::<Resource1>::new::<Channel1>();
+        resource.insert(client_id, Resource1(1.0));
+        // the value of other clients is not sent to this client
+        resource.insert(ClientId::Netcode(0), Resource1(5.0));
+        stepper.server_app.world_mut().insert_resource(resource);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.client_app.world().resource::<Resource1>().0, 1.0);
+
+        // update the value for the client
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<PerClientResource<Resource1>>()
+            .get_mut(client_id)
+            .unwrap()
+            .0 = 2.0;
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.client_app.world().resource::<Resource1>().0, 2.0);
+
+        // remove the value for the client
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<PerClientResource<Resource1>>()
+            .remove(client_id);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get_resource::<Resource1>()
+            .is_none());
+    }
 }
@@ -1,7 +1,8 @@
 //! General struct handling replication
 use std::iter::Extend;
+use std::sync::Arc;
 
-use crate::channel::builder::{EntityActionsChannel, EntityUpdatesChannel};
+use crate::channel::builder::{EntityActionsChannel, EntityUpdatesChannel, JoinSnapshotChannel};
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::EntityHash;
 use bevy::prelude::Entity;
@@ -9,23 +10,32 @@ use bevy::ptr::Ptr;
 use bevy::utils::{hashbrown, HashMap};
 use bytes::Bytes;
 use crossbeam_channel::Receiver;
-use tracing::{debug, error, trace};
+use tracing::{debug, error, trace, warn};
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
 use super::{EntityActions, SendEntityActionsMessage, SendEntityUpdatesMessage, SpawnAction};
 use crate::packet::message::MessageId;
 use crate::packet::message_manager::MessageManager;
+use crate::packet::packet::FRAGMENT_SIZE;
+use crate::packet::priority_manager::BYPASS_QUOTA_PRIORITY;
 use crate::prelude::{
     ChannelKind, ComponentRegistry, PacketError, RemoteEntityMap, Tick, TimeManager,
 };
 use crate::protocol::component::{ComponentKind, ComponentNetId};
+use crate::serialize::reader::Reader;
+use crate::serialize::varint::varint_len;
 use crate::serialize::writer::Writer;
-use crate::serialize::{SerializationError, ToBytes};
+use crate::serialize::ToBytes;
+use crate::shared::replication::capture::CapturedReplicationMessage;
 use crate::shared::replication::components::ReplicationGroupId;
 use crate::shared::replication::delta::DeltaManager;
 use crate::shared::replication::error::ReplicationError;
 use crate::shared::replication::plugin::{ReplicationConfig, SendUpdatesMode};
+use crate::shared::replication::snapshot::JoinSnapshot;
+use crate::shared::replication::strategy::{
+    DefaultReplicationStrategy, ReplicationSendContext, ReplicationSendStrategy,
+};
 #[cfg(test)]
 use {
     super::{EntityActionsMessage, EntityUpdatesMessage},
@@ -36,6 +46,10 @@ type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
 
 type EntityHashSet<K> = hashbrown::HashSet<K, EntityHash>;
 
+/// Maximum size of an [`EntityActionsMessage`](super::EntityActionsMessage): bigger messages would need more
+/// fragments than what a message can be split into. (we keep one fragment of margin for the message headers)
+pub(crate) const MAX_ACTIONS_MESSAGE_SIZE: usize = FRAGMENT_SIZE * (u8::MAX as usize - 1);
+
 /// When a [`EntityUpdatesMessage`](super::EntityUpdatesMessage) message gets buffered (and we have access to its [`MessageId`]),
 /// we keep track of some information related to this message.
 /// It is useful when we get notified that the message was acked or lost.
@@ -49,32 +63,50 @@ pub(crate) struct UpdateMessageMetadata {
     tick: Tick,
 }
 
+/// Keeps track of the replication changes that must be sent to a remote peer, per replication group.
+///
+/// The changes are gathered by the replication systems, and turned into messages by the
+/// [`ReplicationSendStrategy`] of the sender.
 #[derive(Debug)]
-pub(crate) struct ReplicationSender {
+pub struct ReplicationSender {
     /// Get notified whenever a message-id that was sent has been received by the remote
-    pub(crate) updates_ack_receiver: Receiver<MessageId>,
+    updates_ack_receiver: Receiver<MessageId>,
     /// Get notified whenever a message-id that was sent has been lost by the remote
-    pub(crate) updates_nack_receiver: Receiver<MessageId>,
+    updates_nack_receiver: Receiver<MessageId>,
 
     /// Map from message-id to the corresponding group-id that sent this update message, as well as the `send_tick` BevyTick
     /// when we buffered the message. (so that when it's acked, we know we only need to include updates that happened after that tick,
     /// for that replication group)
-    pub(crate) updates_message_id_to_group_id: HashMap<MessageId, UpdateMessageMetadata>,
+    updates_message_id_to_group_id: HashMap<MessageId, UpdateMessageMetadata>,
     /// Group channels that have at least 1 replication update or action buffered
-    pub group_with_actions: EntityHashSet<ReplicationGroupId>,
-    pub group_with_updates: EntityHashSet<ReplicationGroupId>,
+    group_with_actions: EntityHashSet<ReplicationGroupId>,
+    group_with_updates: EntityHashSet<ReplicationGroupId>,
     /// Buffer to so that we have an ordered receiver per group
-    pub group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
+    pub(crate) group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
 
     // PRIORITY
     /// Get notified whenever a message for a given ReplicationGroup was actually sent
     /// (sometimes they might not be sent because of bandwidth constraints)
     ///
     /// We update the `send_tick` only when the message was actually sent.
-    pub message_send_receiver: Receiver<MessageId>,
+    message_send_receiver: Receiver<MessageId>,
 
     replication_config: ReplicationConfig,
     bandwidth_cap_enabled: bool,
+    /// If true, action messages that spawn an entity on the remote are sent immediately, without waiting for
+    /// the accumulated priority of their replication group
+    pub(crate) spawn_priority_boost: bool,
+    /// True if the `send_tick` of a group was reset because an update message was lost,
+    /// since the last replication pass
+    pub(crate) send_ticks_rewound: bool,
+    /// If set, every replication message that is buffered is also stored here, so that tests can inspect them
+    pub(crate) capture: Option<Vec<CapturedReplicationMessage>>,
+    /// Incremented every time a message of a group is sent, to know which groups were sent the least recently
+    send_order: u64,
+    /// Actions messages bigger than this are split into several messages
+    pub(crate) max_actions_message_size: usize,
+    /// How the gathered changes are turned into messages
+    pub(crate) strategy: Arc<dyn ReplicationSendStrategy>,
 }
 
 impl ReplicationSender {
@@ -98,12 +130,97 @@ impl ReplicationSender {
             // PRIORITY
             message_send_receiver,
             bandwidth_cap_enabled,
+            spawn_priority_boost: false,
+            send_ticks_rewound: false,
+            capture: None,
+            send_order: 0,
+            max_actions_message_size: MAX_ACTIONS_MESSAGE_SIZE,
+            strategy: Arc::new(DefaultReplicationStrategy),
+        }
+    }
+
+    /// Get the tick since which the component changes of the group must be gathered.
+    ///
+    /// Returns `None` if all the components must be gathered, for example if the group was never sent or if the
+    /// [`ReplicationSendStrategy`] gathers all the components in every replication pass.
+    pub(crate) fn gather_changes_since(
+        &mut self,
+        group_id: ReplicationGroupId,
+    ) -> Option<BevyTick> {
+        let send_tick = self.group_channels.entry(group_id).or_default().send_tick;
+        if self.strategy.gather_all(self) {
+            return None;
         }
+        send_tick
+    }
+
+    /// Turn the gathered changes into messages, with the [`ReplicationSendStrategy`] of the sender
+    pub(crate) fn buffer_messages(
+        &mut self,
+        tick: Tick,
+        bevy_tick: BevyTick,
+        time_manager: &TimeManager,
+        writer: &mut Writer,
+        message_manager: &mut MessageManager,
+    ) -> Result<(), PacketError> {
+        let strategy = self.strategy.clone();
+        let mut context = ReplicationSendContext {
+            tick,
+            bevy_tick,
+            time_manager,
+            writer,
+            message_manager,
+            buffered_updates: Vec::new(),
+        };
+        let result = strategy.buffer_messages(self, &mut context);
+        // keep track of the update messages that were buffered directly by the strategy
+        for (group_id, message_id) in context.buffered_updates {
+            self.buffer_replication_update_message(group_id, message_id, bevy_tick, tick);
+        }
+        result
+    }
+
+    /// The [`GroupChannel`]s of all the replication groups
+    pub fn group_channels(&self) -> impl Iterator<Item = (&ReplicationGroupId, &GroupChannel)> {
+        self.group_channels.iter()
+    }
+
+    /// The [`GroupChannel`] of a replication group, whose pending changes can be consumed by a
+    /// [`ReplicationSendStrategy`]
+    pub fn group_channel_mut(&mut self, group_id: ReplicationGroupId) -> Option<&mut GroupChannel> {
+        self.group_channels.get_mut(&group_id)
+    }
+
+    /// Buffer the pending [`EntityActionsMessage`](super::EntityActionsMessage)s of all the groups,
+    /// on the actions channel of each group
+    pub fn buffer_actions(
+        &mut self,
+        context: &mut ReplicationSendContext,
+    ) -> Result<(), PacketError> {
+        self.send_actions_messages(
+            context.tick,
+            context.bevy_tick,
+            context.writer,
+            context.message_manager,
+        )
+    }
+
+    /// Buffer the pending [`EntityUpdatesMessage`](super::EntityUpdatesMessage)s of all the groups,
+    /// on the [`EntityUpdatesChannel`]
+    pub fn buffer_updates(
+        &mut self,
+        context: &mut ReplicationSendContext,
+    ) -> Result<(), PacketError> {
+        self.send_updates_messages(
+            context.tick,
+            context.bevy_tick,
+            context.writer,
+            context.message_manager,
+        )
     }
 
     /// Keep track of the message_id/bevy_tick/tick where a replication-update message has been sent
     /// for a given group
-    #[cfg(test)]
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     pub(crate) fn buffer_replication_update_message(
         &mut self,
@@ -140,6 +257,14 @@ impl ReplicationSender {
         })
     }
 
+    /// Returns true if every change that was detected in a previous replication pass has been buffered.
+    ///
+    /// This is not the case if the bandwidth cap is enabled (some messages might not have been sent),
+    /// or if the `send_tick` of a group was rewound because an update message was lost.
+    pub(crate) fn all_changes_buffered(&self) -> bool {
+        !self.bandwidth_cap_enabled && !self.send_ticks_rewound && !self.strategy.gather_all(self)
+    }
+
     /// Internal bookkeeping:
     /// 1. handle all nack update messages
     pub(crate) fn update(&mut self, world_tick: BevyTick) {
@@ -167,6 +292,7 @@ impl ReplicationSender {
                             .is_some_and(|ack_tick| bevy_tick.is_newer_than(ack_tick, world_tick))
                         {
                             channel.send_tick = channel.ack_bevy_tick;
+                            self.send_ticks_rewound = true;
                         }
 
                         // TODO: if all clients lost a given message, than we can immediately drop the delta-compression data
@@ -211,6 +337,8 @@ impl ReplicationSender {
                     );
                     channel.send_tick = Some(*bevy_tick);
                     channel.accumulated_priority = 0.0;
+                    self.send_order += 1;
+                    channel.last_send_order = self.send_order;
                 } else {
                     error!(?message_id, ?group_id, "Received a send message-id notification but the corresponding group channel does not exist");
                 }
@@ -297,6 +425,18 @@ impl ReplicationSender {
             .base_priority = priority;
     }
 
+    /// Update the channel used to send the actions of a given group
+    pub(crate) fn update_actions_channel(
+        &mut self,
+        group_id: ReplicationGroupId,
+        actions_channel: ChannelKind,
+    ) {
+        self.group_channels
+            .entry(group_id)
+            .or_default()
+            .actions_channel = actions_channel;
+    }
+
     // TODO: how can I emit metrics here that contain the channel kind?
     //  use a OnceCell that gets set with the channel name mapping when the protocol is finalized?
     //  the other option is to have wrappers in Connection, but that's pretty ugly
@@ -346,6 +486,39 @@ impl ReplicationSender {
             .spawn = SpawnAction::Despawn;
     }
 
+    /// The entity was moved to a different replication group: send an actions message for the entity
+    /// in the new group, so that the remote moves the entity to that group.
+    ///
+    /// The move is sequenced after the actions that were already sent in the previous group, so that the
+    /// remote doesn't apply it before the spawn of the entity.
+    #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
+    pub(crate) fn prepare_entity_group_change(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        previous_group_id: ReplicationGroupId,
+    ) {
+        let after = self
+            .group_channels
+            .get(&previous_group_id)
+            .map_or(MessageId(0), |channel| channel.actions_next_send_message_id);
+        self.group_with_actions.insert(group_id);
+        let actions = self
+            .group_channels
+            .entry(group_id)
+            .or_default()
+            .pending_actions
+            .entry(entity)
+            .or_default();
+        // if the entity is spawned in the new group (for example for a new client), there is nothing to move
+        if actions.spawn == SpawnAction::None {
+            actions.spawn = SpawnAction::Move {
+                from: previous_group_id,
+                after,
+            };
+        }
+    }
+
     // we want to send all component inserts that happen together for the same entity in a single message
     // (because otherwise the inserts might be received at different packets/ticks by the remote, and
     // the remote might expect the components insert to be received at the same time)
@@ -402,6 +575,26 @@ impl ReplicationSender {
             .push(raw_data);
     }
 
+    /// Buffer a component update that is sent in the entity actions message of the group,
+    /// so that it is delivered reliably.
+    #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
+    pub(crate) fn prepare_reliable_component_update(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        raw_data: Bytes,
+    ) {
+        self.group_with_actions.insert(group_id);
+        self.group_channels
+            .entry(group_id)
+            .or_default()
+            .pending_actions
+            .entry(entity)
+            .or_default()
+            .updates
+            .push(raw_data);
+    }
+
     /// Create a component update.
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
     #[allow(clippy::too_many_arguments)]
@@ -469,7 +662,11 @@ impl ReplicationSender {
         trace!(?kind, "Inserting pending update!");
         // use the network entity when serializing
         let entity = remote_entity_map.to_remote(entity);
-        self.prepare_component_update(entity, group_id, raw_data);
+        if registry.has_reliable_updates(kind) {
+            self.prepare_reliable_component_update(entity, group_id, raw_data);
+        } else {
+            self.prepare_component_update(entity, group_id, raw_data);
+        }
         Ok(())
     }
 
@@ -480,8 +677,9 @@ impl ReplicationSender {
         bevy_tick: BevyTick,
     ) -> Vec<(EntityActionsMessage, f32)> {
         // ) -> impl Iterator<Item = (EntityActionsMessage, f32)> + Captures<&()> {
-        self.group_with_actions
-            .drain()
+        let groups = self.group_with_actions.drain().collect();
+        self.groups_in_send_order(groups)
+            .into_iter()
             .map(|group_id| {
                 // SAFETY: we know that the group_channel exists since group_with_actions contains the group_id
                 let channel = self.group_channels.get_mut(&group_id).unwrap();
@@ -503,10 +701,12 @@ impl ReplicationSender {
                 // guaranteed to be sent at some point. (since the actions channel is reliable)
                 channel.send_tick = Some(bevy_tick);
                 channel.ack_tick = Some(tick);
-                let priority = channel.accumulated_priority;
+                let priority = Self::actions_priority(self.spawn_priority_boost, channel, &actions);
                 let message_id = channel.actions_next_send_message_id;
                 channel.actions_next_send_message_id += 1;
                 channel.last_action_tick = Some(tick);
+                self.send_order += 1;
+                channel.last_send_order = self.send_order;
                 let message = (
                     EntityActionsMessage {
                         sequence_id: message_id,
@@ -522,6 +722,45 @@ impl ReplicationSender {
             .collect()
     }
 
+    /// Sort the groups in the order in which their messages should be buffered: the groups whose messages
+    /// were sent the least recently come first.
+    ///
+    /// The [`PriorityManager`](crate::packet::priority_manager::PriorityManager) sends the messages that have
+    /// the same priority in the order in which they were buffered, so the groups that have the same priority
+    /// take turns (round-robin) when the bandwidth is limited, instead of the same groups always winning the
+    /// tie because of the hash ordering.
+    fn groups_in_send_order(&self, mut groups: Vec<ReplicationGroupId>) -> Vec<ReplicationGroupId> {
+        groups.sort_by_key(|group_id| {
+            (
+                self.group_channels
+                    .get(group_id)
+                    .map_or(0, |channel| channel.last_send_order),
+                group_id.0,
+            )
+        });
+        groups
+    }
+
+    /// Priority of the actions message of a group.
+    ///
+    /// If `spawn_priority_boost` is enabled and the message spawns an entity (because the entity was just created,
+    /// or just became visible to the remote), the message bypasses the bandwidth quota so that the entity doesn't
+    /// pop in late.
+    fn actions_priority(
+        spawn_priority_boost: bool,
+        channel: &GroupChannel,
+        actions: &EntityHashMap<Entity, EntityActions>,
+    ) -> f32 {
+        if spawn_priority_boost
+            && actions
+                .values()
+                .any(|action| matches!(action.spawn, SpawnAction::Spawn))
+        {
+            return channel.accumulated_priority.max(BYPASS_QUOTA_PRIORITY);
+        }
+        channel.accumulated_priority
+    }
+
     // TODO: the priority for entity actions should remain the base_priority,
     //  because the priority will get accumulated in the reliable channel
     //  For entity updates, we might want to use the multiplier, but not sure
@@ -529,7 +768,7 @@ impl ReplicationSender {
     /// Before sending replication messages, we accumulate the priority for all replication groups.
     ///
     /// (the priority starts at 0.0, and is accumulated for each group based on the base priority of the group)
-    pub(crate) fn accumulate_priority(&mut self, time_manager: &TimeManager) {
+    pub fn accumulate_priority(&mut self, time_manager: &TimeManager) {
         // let priority_multiplier = if self.replication_config.send_interval == Duration::default() {
         //     1.0
         // } else {
@@ -558,77 +797,164 @@ impl ReplicationSender {
         writer: &mut Writer,
         message_manager: &mut MessageManager,
     ) -> Result<(), PacketError> {
-        self.group_with_actions.drain().try_for_each(|group_id| {
-            // SAFETY: we know that the group_channel exists since group_with_actions contains the group_id
-            let channel = self.group_channels.get_mut(&group_id).unwrap();
-            let mut actions = std::mem::take(&mut channel.pending_actions);
-            // TODO: should we be careful about not mapping entities for actions if it's a Spawn action?
-            //  how could that happen?
-            // add any updates for that group
-            if self.group_with_updates.remove(&group_id) {
-                // drain so that we keep the allocated memory
-                for (entity, components) in channel.pending_updates.drain() {
-                    actions
-                        .entry(entity)
-                        .or_default()
-                        .updates
-                        .extend(components);
-                }
-            }
-
-            // update the send tick so that we don't send updates immediately after an insert messagex.
-            // (which would happen because the send_tick is only set to Some(x) after an Update message is sent, so
-            // when an entity is first spawned the send_tick is still None)
-            // This is ok to do even if we don't get an actual send notification because EntityActions messages are
-            // guaranteed to be sent at some point. (since the actions channel is reliable)
-            channel.send_tick = Some(bevy_tick);
-            //  We can consider that we received an ack for the current tick because the message is sent reliably,
-            //  so we know that we should eventually receive an ack.
-            //  Updates after this insert only get read if the insert was received, so this doesn't introduce any bad behaviour.
-            //  - For delta-compression: this is useful to compute future diffs from this Insert value immediately
-            //  - in general: this is useful to avoid sending too many unnecessary updates. For example:
-            //      - tick 3: C1 update
-            //      - tick 4: C2 insert. C1 update. (if we send all updates since last_ack) !!!! We need to update the ack from the Insert only AFTER all the Updates are prepared!!!
-            //      - tick 5: Before, we would send C1 update again, since we didn't receive an ack for C1 yet. But now we stop sending it because we know that the message from tick 4 will be received.
-            channel.ack_tick = Some(tick);
-            let priority = channel.accumulated_priority;
-            let message_id = channel.actions_next_send_message_id;
-            channel.actions_next_send_message_id += 1;
-            channel.last_action_tick = Some(tick);
-            // we use SendEntityActionsMessage so that we don't have to convert the hashmap into a vec
-            let message = SendEntityActionsMessage {
-                sequence_id: message_id,
-                group_id,
-                actions,
-            };
-            trace!("final action messages to send: {:?}", message);
+        let groups = self.group_with_actions.drain().collect();
+        let groups = self.groups_in_send_order(groups);
+        groups.into_iter().try_for_each(|group_id| {
+            let (messages, priority) = self.take_actions_message(group_id, tick, bevy_tick);
 
             // TODO: we had to put this here because of the borrow checker, but it's not ideal,
             //  the replication send should normally just an iterator of messages to send
             //  Maybe the ReplicationSender should not be in ConnectionManager?
 
             // buffer the message in the MessageManager
-
-            // message.emit_send_logs("EntityActionsChannel");
-            message.to_bytes(writer).map_err(SerializationError::from)?;
-            let message_bytes = writer.split();
-            let message_id = message_manager
-                // TODO: use const type_id?
-                .buffer_send_with_priority(
-                    message_bytes,
-                    ChannelKind::of::<EntityActionsChannel>(),
-                    priority,
-                )?
-                .expect("The entity actions channels should always return a message_id");
-
-            // restore the hashmap that we took out, so that we can reuse the allocated memory
-            channel.pending_actions = message.actions;
-            channel.pending_actions.clear();
+            // SAFETY: we know that the group_channel exists since group_with_actions contains the group_id
+            let channel = self.group_channels.get_mut(&group_id).unwrap();
+            for message in messages {
+                // message.emit_send_logs("EntityActionsChannel");
+                message.to_bytes(writer)?;
+                let message_bytes = writer.split();
+                message_manager
+                    .buffer_send_with_priority(message_bytes, channel.actions_channel, priority)?
+                    .expect("The entity actions channels should always return a message_id");
+
+                // restore the hashmap that we took out, so that we can reuse the allocated memory
+                if channel.pending_actions.capacity() < message.actions.capacity() {
+                    channel.pending_actions = message.actions;
+                    channel.pending_actions.clear();
+                }
+            }
 
             Ok::<(), PacketError>(())
         })
     }
 
+    /// Bundle all the pending [`EntityActionsMessage`](super::EntityActionsMessage) messages in a single
+    /// [`JoinSnapshot`] that is sent on the [`JoinSnapshotChannel`].
+    ///
+    /// The messages keep the sequence ids of their groups, so the following actions messages of each group
+    /// are applied after the snapshot.
+    pub(crate) fn send_join_snapshot(
+        &mut self,
+        tick: Tick,
+        bevy_tick: BevyTick,
+        writer: &mut Writer,
+        message_manager: &mut MessageManager,
+    ) -> Result<(), PacketError> {
+        let groups = self.group_with_actions.drain().collect();
+        let groups = self.groups_in_send_order(groups);
+        if groups.is_empty() {
+            return Ok(());
+        }
+        let mut snapshot = JoinSnapshot::default();
+        for group_id in groups {
+            let (messages, _) = self.take_actions_message(group_id, tick, bevy_tick);
+            // SAFETY: we know that the group_channel exists since group_with_actions contains the group_id
+            let channel = self.group_channels.get_mut(&group_id).unwrap();
+            for message in messages {
+                message.to_bytes(writer)?;
+                snapshot.messages.push(writer.split());
+                if channel.pending_actions.capacity() < message.actions.capacity() {
+                    channel.pending_actions = message.actions;
+                    channel.pending_actions.clear();
+                }
+            }
+        }
+        debug!(
+            num_groups = snapshot.messages.len(),
+            "Sending join snapshot"
+        );
+        let bytes = snapshot.to_compressed_bytes()?;
+        message_manager.buffer_send_with_priority(
+            bytes,
+            ChannelKind::of::<JoinSnapshotChannel>(),
+            BYPASS_QUOTA_PRIORITY,
+        )?;
+        Ok(())
+    }
+
+    /// Take the pending actions (and updates) of the group, to build the actions message to send.
+    ///
+    /// If the message would be too big to be fragmented, the actions are split across several consecutive
+    /// messages of the group.
+    ///
+    /// The caller must restore the `pending_actions` map of the group after the message is serialized,
+    /// to reuse the allocated memory.
+    fn take_actions_message(
+        &mut self,
+        group_id: ReplicationGroupId,
+        tick: Tick,
+        bevy_tick: BevyTick,
+    ) -> (Vec<SendEntityActionsMessage>, f32) {
+        // SAFETY: we know that the group_channel exists since group_with_actions contains the group_id
+        let channel = self.group_channels.get_mut(&group_id).unwrap();
+        let mut actions = std::mem::take(&mut channel.pending_actions);
+        // TODO: should we be careful about not mapping entities for actions if it's a Spawn action?
+        //  how could that happen?
+        // add any updates for that group
+        if self.group_with_updates.remove(&group_id) {
+            // drain so that we keep the allocated memory
+            for (entity, components) in channel.pending_updates.drain() {
+                actions
+                    .entry(entity)
+                    .or_default()
+                    .updates
+                    .extend(components);
+            }
+        }
+
+        // update the send tick so that we don't send updates immediately after an insert messagex.
+        // (which would happen because the send_tick is only set to Some(x) after an Update message is sent, so
+        // when an entity is first spawned the send_tick is still None)
+        // This is ok to do even if we don't get an actual send notification because EntityActions messages are
+        // guaranteed to be sent at some point. (since the actions channel is reliable)
+        channel.send_tick = Some(bevy_tick);
+        //  We can consider that we received an ack for the current tick because the message is sent reliably,
+        //  so we know that we should eventually receive an ack.
+        //  Updates after this insert only get read if the insert was received, so this doesn't introduce any bad behaviour.
+        //  - For delta-compression: this is useful to compute future diffs from this Insert value immediately
+        //  - in general: this is useful to avoid sending too many unnecessary updates. For example:
+        //      - tick 3: C1 update
+        //      - tick 4: C2 insert. C1 update. (if we send all updates since last_ack) !!!! We need to update the ack from the Insert only AFTER all the Updates are prepared!!!
+        //      - tick 5: Before, we would send C1 update again, since we didn't receive an ack for C1 yet. But now we stop sending it because we know that the message from tick 4 will be received.
+        channel.ack_tick = Some(tick);
+        let priority = Self::actions_priority(self.spawn_priority_boost, channel, &actions);
+        channel.last_action_tick = Some(tick);
+        self.send_order += 1;
+        channel.last_send_order = self.send_order;
+
+        let max_size = self.max_actions_message_size;
+        let chunks = if ToBytes::len(&actions) > max_size {
+            warn_oversized_actions(group_id, &actions, max_size);
+            split_actions(actions, max_size)
+        } else {
+            vec![actions]
+        };
+        let messages = chunks
+            .into_iter()
+            .map(|actions| {
+                let message_id = channel.actions_next_send_message_id;
+                channel.actions_next_send_message_id += 1;
+                // we use SendEntityActionsMessage so that we don't have to convert the hashmap into a vec
+                let message = SendEntityActionsMessage {
+                    sequence_id: message_id,
+                    group_id,
+                    actions,
+                };
+                trace!("final action messages to send: {:?}", message);
+                if let Some(capture) = &mut self.capture {
+                    capture.push(CapturedReplicationMessage::actions(
+                        tick,
+                        group_id,
+                        message_id,
+                        message.actions.iter(),
+                    ));
+                }
+                message
+            })
+            .collect();
+        (messages, priority)
+    }
+
     /// Prepare the [`EntityUpdateMessage`] to send
     #[cfg(test)]
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
@@ -637,7 +963,9 @@ impl ReplicationSender {
         tick: Tick,
         bevy_tick: BevyTick,
     ) -> impl Iterator<Item = (EntityUpdatesMessage, f32)> + Captures<&()> {
-        self.group_with_updates.drain().map(|group_id| {
+        let groups = self.group_with_updates.drain().collect();
+        let groups = self.groups_in_send_order(groups);
+        groups.into_iter().map(|group_id| {
             let channel = self.group_channels.get_mut(&group_id).unwrap();
             let updates = std::mem::take(&mut channel.pending_updates);
 
@@ -667,7 +995,9 @@ impl ReplicationSender {
         writer: &mut Writer,
         message_manager: &mut MessageManager,
     ) -> Result<(), PacketError> {
-        self.group_with_updates.drain().try_for_each(|group_id| {
+        let groups = self.group_with_updates.drain().collect();
+        let groups = self.groups_in_send_order(groups);
+        groups.into_iter().try_for_each(|group_id| {
             let channel = self.group_channels.get_mut(&group_id).unwrap();
             let updates = std::mem::take(&mut channel.pending_updates);
             trace!(?group_id, "pending updates: {:?}", updates);
@@ -682,9 +1012,16 @@ impl ReplicationSender {
                 last_action_tick: channel.last_action_tick,
                 updates,
             };
+            if let Some(capture) = &mut self.capture {
+                capture.push(CapturedReplicationMessage::updates(
+                    tick,
+                    group_id,
+                    message.updates.iter(),
+                ));
+            }
 
             // message.emit_send_logs("EntityUpdatesChannel");
-            message.to_bytes(writer).map_err(SerializationError::from)?;
+            message.to_bytes(writer)?;
             let message_bytes = writer.split();
             let message_id = message_manager
                 // TODO: use const type_id?
@@ -695,6 +1032,10 @@ impl ReplicationSender {
                 )?
                 .expect("The entity actions channels should always return a message_id");
 
+            // restore the hashmap that we took out, so that we can reuse the allocated memory
+            channel.pending_updates = message.updates;
+            channel.pending_updates.clear();
+
             // keep track of the message_id -> group mapping, so we can handle receiving an ACK for that message_id later
             debug!(
                 ?message_id,
@@ -703,30 +1044,96 @@ impl ReplicationSender {
                 ?tick,
                 "Send replication update"
             );
-            self.updates_message_id_to_group_id.insert(
-                message_id,
-                UpdateMessageMetadata {
-                    group_id,
-                    bevy_tick,
-                    tick,
-                },
-            );
             // If we don't have a bandwidth cap, buffering a message is equivalent to sending it
-            // so we can set the `send_tick` right away
+            // so the `send_tick` is set right away
             // TODO: but doesn't that mean we double send it?
-            if !self.bandwidth_cap_enabled {
-                channel.send_tick = Some(bevy_tick);
-            }
-
-            // restore the hashmap that we took out, so that we can reuse the allocated memory
-            channel.pending_updates = message.updates;
-            channel.pending_updates.clear();
+            self.buffer_replication_update_message(group_id, message_id, bevy_tick, tick);
             Ok(())
         })
         // TODO: also return for each message a list of the components that have delta-compression data?
     }
 }
 
+/// Log the components that make the actions of a group too big to be sent in a single message
+fn warn_oversized_actions(
+    group_id: ReplicationGroupId,
+    actions: &EntityHashMap<Entity, EntityActions>,
+    max_size: usize,
+) {
+    let mut components: Vec<_> = actions
+        .iter()
+        .flat_map(|(entity, actions)| {
+            actions
+                .insert
+                .iter()
+                .chain(actions.updates.iter())
+                .map(move |bytes| {
+                    let net_id = ComponentNetId::from_bytes(&mut Reader::from(bytes.clone())).ok();
+                    (*entity, net_id, bytes.len())
+                })
+        })
+        .collect();
+    components.sort_by_key(|(_, _, size)| std::cmp::Reverse(*size));
+    components.truncate(5);
+    warn!(
+        ?group_id,
+        size = ToBytes::len(actions),
+        max_size,
+        largest_components = ?components,
+        "The entity actions of the replication group are too big to be sent in a single message; \
+        splitting them across several messages"
+    );
+    #[cfg(feature = "metrics")]
+    metrics::counter!("replication::send::split_actions_messages").increment(1);
+}
+
+/// Split the actions of a group into several maps whose serialized size is at most `max_size`.
+///
+/// The spawn action and the removals of an entity stay in the first map that contains the entity, and
+/// the inserts/updates keep their order. A single component bigger than `max_size` is put in its own map.
+fn split_actions(
+    mut actions: EntityHashMap<Entity, EntityActions>,
+    max_size: usize,
+) -> Vec<EntityHashMap<Entity, EntityActions>> {
+    // the size of the map header is counted for each chunk
+    let empty_size = ToBytes::len(&EntityHashMap::<Entity, EntityActions>::default());
+    let mut chunks = vec![EntityHashMap::default()];
+    let mut chunk_size = empty_size;
+    for (entity, mut entity_actions) in actions.drain() {
+        let inserts = std::mem::take(&mut entity_actions.insert);
+        let updates = std::mem::take(&mut entity_actions.updates);
+        let mut current = entity_actions;
+        let mut current_size = entity.len() + current.len();
+        let components = inserts
+            .into_iter()
+            .map(|bytes| (true, bytes))
+            .chain(updates.into_iter().map(|bytes| (false, bytes)));
+        for (is_insert, bytes) in components {
+            // each component adds its length and the varint of its length
+            let size = bytes.len() + varint_len(bytes.len() as u64);
+            let current_is_empty = current.insert.is_empty() && current.updates.is_empty();
+            if chunk_size + current_size + size > max_size
+                && (chunk_size > empty_size || !current_is_empty)
+            {
+                chunks.last_mut().unwrap().insert(entity, current);
+                chunks.push(EntityHashMap::default());
+                chunk_size = empty_size;
+                current = EntityActions::default();
+                current_size = entity.len() + current.len();
+            }
+            if is_insert {
+                current.insert.push(bytes);
+            } else {
+                current.updates.push(bytes);
+            }
+            current_size += size;
+        }
+        chunks.last_mut().unwrap().insert(entity, current);
+        chunk_size += current_size;
+    }
+    chunks
+}
+
 /// Channel to keep track of sending replication messages for a given Group
 #[derive(Debug)]
 pub struct GroupChannel {
@@ -766,6 +1173,11 @@ pub struct GroupChannel {
     /// for this group because of the bandwidth cap, in which case it will be accumulated.
     pub accumulated_priority: f32,
     pub base_priority: f32,
+    /// Channel used to send the actions of the group
+    pub actions_channel: ChannelKind,
+    /// Value of the sender's send counter the last time a message of this group was sent.
+    /// Used to send the groups that have the same priority in a round-robin fashion.
+    pub last_send_order: u64,
 }
 
 impl Default for GroupChannel {
@@ -780,6 +1192,8 @@ impl Default for GroupChannel {
             last_action_tick: None,
             accumulated_priority: 0.0,
             base_priority: 1.0,
+            actions_channel: ChannelKind::of::<EntityActionsChannel>(),
+            last_send_order: 0,
         }
     }
 }
@@ -1067,6 +1481,132 @@ mod tests {
         assert_eq!(group.ack_bevy_tick, None);
     }
 
+    /// Groups with the same priority are buffered in a round-robin order: the groups that were sent
+    /// the least recently come first
+    #[test]
+    fn test_send_order_round_robin() {
+        let (_, rx_ack) = crossbeam_channel::unbounded();
+        let (_, rx_nack) = crossbeam_channel::unbounded();
+        let (tx_send, rx_send) = crossbeam_channel::unbounded();
+        let mut sender =
+            ReplicationSender::new(rx_ack, rx_nack, rx_send, ReplicationConfig::default(), true);
+        let group_1 = ReplicationGroupId(0);
+        let group_2 = ReplicationGroupId(1);
+        let group_3 = ReplicationGroupId(2);
+        for group in [group_1, group_2, group_3] {
+            sender.group_channels.insert(group, GroupChannel::default());
+        }
+        let groups = vec![group_3, group_2, group_1];
+        assert_eq!(
+            sender.groups_in_send_order(groups.clone()),
+            vec![group_1, group_2, group_3]
+        );
+
+        // group 1 and then group 2 get sent: they go to the back of the queue
+        sender.buffer_replication_update_message(group_1, MessageId(0), BevyTick::new(0), Tick(0));
+        sender.buffer_replication_update_message(group_2, MessageId(1), BevyTick::new(0), Tick(0));
+        tx_send.try_send(MessageId(0)).unwrap();
+        tx_send.try_send(MessageId(1)).unwrap();
+        sender.recv_send_notification();
+        assert_eq!(
+            sender.groups_in_send_order(groups),
+            vec![group_3, group_1, group_2]
+        );
+    }
+
+    /// The update messages buffered by a custom [`ReplicationSendStrategy`] are associated with their group,
+    /// like the ones buffered by the default strategy
+    #[test]
+    fn test_strategy_buffer_update() {
+        use crate::packet::priority_manager::PriorityConfig;
+        use crate::prelude::ChannelRegistry;
+
+        #[derive(Debug)]
+        struct RawUpdates;
+
+        impl ReplicationSendStrategy for RawUpdates {
+            fn buffer_messages(
+                &self,
+                _: &mut ReplicationSender,
+                context: &mut ReplicationSendContext,
+            ) -> Result<(), PacketError> {
+                context.buffer_update(ReplicationGroupId(0), Bytes::from_static(&[0]), 1.0)
+            }
+        }
+
+        let (_, rx_ack) = crossbeam_channel::unbounded();
+        let (_, rx_nack) = crossbeam_channel::unbounded();
+        let (_, rx_send) = crossbeam_channel::unbounded();
+        let mut sender = ReplicationSender::new(
+            rx_ack,
+            rx_nack,
+            rx_send,
+            ReplicationConfig::default(),
+            false,
+        );
+        sender.strategy = Arc::new(RawUpdates);
+        let group = ReplicationGroupId(0);
+        sender.group_channels.insert(group, GroupChannel::default());
+        let mut message_manager = MessageManager::new(
+            &ChannelRegistry::new(bevy::utils::Duration::default()),
+            1.5,
+            PriorityConfig::default(),
+        );
+
+        sender
+            .buffer_messages(
+                Tick(1),
+                BevyTick::new(1),
+                &TimeManager::default(),
+                &mut Writer::default(),
+                &mut message_manager,
+            )
+            .unwrap();
+        assert_eq!(
+            sender
+                .updates_message_id_to_group_id
+                .values()
+                .collect::<Vec<_>>(),
+            vec![&UpdateMessageMetadata {
+                group_id: group,
+                bevy_tick: BevyTick::new(1),
+                tick: Tick(1),
+            }]
+        );
+        // without bandwidth cap, buffering the message is equivalent to sending it
+        assert_eq!(
+            sender.group_channels.get(&group).unwrap().send_tick,
+            Some(BevyTick::new(1))
+        );
+    }
+
+    #[test]
+    fn test_spawn_priority_boost() {
+        let (_, rx_ack) = crossbeam_channel::unbounded();
+        let (_, rx_nack) = crossbeam_channel::unbounded();
+        let (_, rx_send) = crossbeam_channel::unbounded();
+        let mut sender =
+            ReplicationSender::new(rx_ack, rx_nack, rx_send, ReplicationConfig::default(), true);
+        sender.spawn_priority_boost = true;
+        let entity_1 = Entity::from_raw(0);
+        let entity_2 = Entity::from_raw(1);
+        let group_1 = ReplicationGroupId(0);
+        let group_2 = ReplicationGroupId(1);
+
+        // group 1 spawns an entity, group 2 only despawns one
+        sender.prepare_entity_spawn(entity_1, group_1);
+        sender.prepare_entity_despawn(entity_2, group_2);
+        sender.accumulate_priority(&TimeManager::default());
+
+        let priorities: HashMap<_, _> = sender
+            .actions_to_send(Tick(0), BevyTick::new(0))
+            .into_iter()
+            .map(|(message, priority)| (message.group_id, priority))
+            .collect();
+        assert_eq!(priorities[&group_1], BYPASS_QUOTA_PRIORITY);
+        assert_eq!(priorities[&group_2], 1.0);
+    }
+
     // TODO: add tests for replication with entity relations!
     /// Test calling the `finalize` method to create the final replication messages
     /// from the buffered actions and updates
@@ -1184,4 +1724,94 @@ mod tests {
             Some(Tick(2))
         );
     }
+
+    /// Actions that are too big to be sent in a single message are split across consecutive messages
+    #[test]
+    fn test_split_oversized_actions() {
+        let (_, rx_ack) = crossbeam_channel::unbounded();
+        let (_, rx_nack) = crossbeam_channel::unbounded();
+        let (_, rx_send) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::new(
+            rx_ack,
+            rx_nack,
+            rx_send,
+            ReplicationConfig::default(),
+            false,
+        );
+        manager.max_actions_message_size = 100;
+        let entity = Entity::from_raw(0);
+        let group = ReplicationGroupId(0);
+        manager
+            .group_channels
+            .insert(group, GroupChannel::default());
+        let raw: Vec<Bytes> = (0..3).map(|i| vec![i; 40].into()).collect();
+        manager.prepare_entity_spawn(entity, group);
+        for bytes in raw.iter() {
+            manager.prepare_component_insert(entity, group, bytes.clone());
+        }
+
+        let (messages, _) = manager.take_actions_message(group, Tick(1), BevyTick::new(1));
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].sequence_id, MessageId(0));
+        assert_eq!(messages[1].sequence_id, MessageId(1));
+        for message in messages.iter() {
+            assert!(ToBytes::len(message) <= 100);
+        }
+        // the entity is spawned with the first message, and the inserts keep their order
+        let first = &messages[0].actions[&entity];
+        let second = &messages[1].actions[&entity];
+        assert_eq!(first.spawn, SpawnAction::Spawn);
+        assert_eq!(first.insert, raw[..2].to_vec());
+        assert_eq!(second.spawn, SpawnAction::None);
+        assert_eq!(second.insert, raw[2..].to_vec());
+        assert_eq!(
+            manager.group_channels[&group].actions_next_send_message_id,
+            MessageId(2)
+        );
+    }
+
+    /// Reliable component updates are sent in the entity actions message of the group,
+    /// even if there are no other actions for the group
+    #[test]
+    fn test_reliable_component_update() {
+        let (_, rx_ack) = crossbeam_channel::unbounded();
+        let (_, rx_nack) = crossbeam_channel::unbounded();
+        let (_, rx_send) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::new(
+            rx_ack,
+            rx_nack,
+            rx_send,
+            ReplicationConfig::default(),
+            false,
+        );
+
+        let entity = Entity::from_raw(0);
+        let group = ReplicationGroupId(0);
+        let raw_1: Bytes = vec![0].into();
+        let raw_2: Bytes = vec![1].into();
+        manager.prepare_reliable_component_update(entity, group, raw_1.clone());
+        manager.prepare_component_update(entity, group, raw_2.clone());
+
+        let actions = manager.actions_to_send(Tick(2), BevyTick::new(2));
+        assert_eq!(actions.len(), 1);
+        assert_eq!(
+            actions[0].0.actions,
+            vec![(
+                entity,
+                EntityActions {
+                    spawn: SpawnAction::None,
+                    insert: vec![],
+                    remove: vec![],
+                    updates: vec![raw_1, raw_2],
+                }
+            )]
+        );
+        // the unreliable update was merged in the actions message
+        assert_eq!(
+            manager
+                .updates_to_send(Tick(2), BevyTick::new(2))
+                .collect::<Vec<_>>(),
+            vec![]
+        );
+    }
 }
@@ -0,0 +1,142 @@
+//! Snapshot of the replicated world that is sent to a client when it joins.
+//!
+//! Without a snapshot, a client that joins mid-match receives one entity actions message per replication group,
+//! which are scheduled (and possibly throttled by the bandwidth quota) like any other replication message, so the
+//! world can take many packets and ticks to appear.
+//!
+//! When [`ReplicationConfig::join_snapshot`](crate::prelude::ReplicationConfig::join_snapshot) is enabled on the server,
+//! all the entity actions messages of the first replication send to a newly connected client are bundled in a single
+//! [`JoinSnapshot`], compressed, and sent on the reliable [`JoinSnapshotChannel`](crate::channel::builder::JoinSnapshotChannel).
+//! The client feeds all the messages of the snapshot to its replication receiver at once, so they are applied
+//! in the same frame, before any of the normal replication messages for these groups.
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+
+use crate::serialize::reader::Reader;
+use crate::serialize::{SerializationError, ToBytes};
+
+/// Compression algorithm used for the snapshot, written in the first byte
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnapshotCompression {
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+/// The serialized [`EntityActionsMessage`](super::EntityActionsMessage) of every replication group
+/// that is spawned on the client when it joins
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct JoinSnapshot {
+    pub(crate) messages: Vec<Bytes>,
+}
+
+impl JoinSnapshot {
+    /// Serialize and compress the snapshot
+    pub(crate) fn to_compressed_bytes(&self) -> Result<Bytes, SerializationError> {
+        let mut raw = Vec::with_capacity(ToBytes::len(&self.messages));
+        self.messages.to_bytes(&mut raw)?;
+        let (compression, data) = Self::compress(raw)?;
+        let mut buffer = Vec::with_capacity(data.len() + 1);
+        buffer.write_u8(compression as u8)?;
+        buffer.extend_from_slice(&data);
+        Ok(Bytes::from(buffer))
+    }
+
+    /// Decompress and deserialize a snapshot
+    pub(crate) fn from_compressed_bytes(mut reader: Reader) -> Result<Self, SerializationError> {
+        let compression = reader.read_u8()?;
+        let data = reader.consume();
+        let raw = match compression {
+            0 => data,
+            #[cfg(feature = "lz4")]
+            1 => lz4_flex::decompress_size_prepended(&data)
+                .map_err(|_| SerializationError::InvalidValue)?
+                .into(),
+            #[cfg(feature = "zstd")]
+            2 => zstd::stream::decode_all(data.as_ref())?.into(),
+            _ => return Err(SerializationError::InvalidValue),
+        };
+        Ok(Self {
+            messages: Vec::from_bytes(&mut Reader::from(raw))?,
+        })
+    }
+
+    #[cfg(feature = "lz4")]
+    fn compress(raw: Vec<u8>) -> Result<(SnapshotCompression, Vec<u8>), SerializationError> {
+        Ok((
+            SnapshotCompression::Lz4,
+            lz4_flex::compress_prepend_size(&raw),
+        ))
+    }
+
+    #[cfg(all(feature = "zstd", not(feature = "lz4")))]
+    fn compress(raw: Vec<u8>) -> Result<(SnapshotCompression, Vec<u8>), SerializationError> {
+        Ok((
+            SnapshotCompression::Zstd,
+            zstd::stream::encode_all(raw.as_slice(), 0)?,
+        ))
+    }
+
+    #[cfg(not(any(feature = "zstd", feature = "lz4")))]
+    fn compress(raw: Vec<u8>) -> Result<(SnapshotCompression, Vec<u8>), SerializationError> {
+        Ok((SnapshotCompression::None, raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client::ClientConfig;
+    use crate::prelude::server::{Replicate, ServerConfig};
+    use crate::prelude::{Replicated, SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+    use bevy::prelude::With;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn test_join_snapshot_serde() {
+        let snapshot = JoinSnapshot {
+            messages: vec![Bytes::from(vec![1; 100]), Bytes::from(vec![2, 3])],
+        };
+        let bytes = snapshot.to_compressed_bytes().unwrap();
+        let decoded = JoinSnapshot::from_compressed_bytes(Reader::from(bytes)).unwrap();
+        assert_eq!(decoded, snapshot);
+    }
+
+    /// A client that joins after the entities were spawned receives all of them in the snapshot
+    #[test]
+    fn test_join_snapshot() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            ClientConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .replication
+            .join_snapshot = true;
+        for i in 0..10 {
+            stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(i as f32)));
+        }
+        stepper.init();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let mut query = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<&ComponentSyncModeFull, With<Replicated>>();
+        assert_eq!(query.iter(stepper.client_app.world()).count(), 10);
+    }
+}
@@ -0,0 +1,145 @@
+//! Customize how the replication messages are sent to a remote peer.
+//!
+//! Every replication pass, the replication systems gather the changes of the replicated entities
+//! (spawns, despawns, component inserts/removals/updates) in the [`ReplicationSender`] of each connection,
+//! grouped by [`ReplicationGroup`](crate::prelude::ReplicationGroup). The [`ReplicationSendStrategy`] of the
+//! connection then decides:
+//! - which changes are gathered: only the components that changed since they were last sent (the default),
+//!   or all the replicated components
+//! - how the gathered changes are serialized and buffered in the channels of the connection
+//!
+//! The channels, the packets and the receiving side (including the application of the messages to the
+//! remote world) are the same for every strategy.
+//!
+//! ```rust,ignore
+//! use lightyear::shared::replication::strategy::*;
+//!
+//! /// Send the full state of the world every tick, which is fine for a tiny game
+//! #[derive(Debug)]
+//! struct FullSnapshot;
+//!
+//! impl ReplicationSendStrategy for FullSnapshot {
+//!     fn gather_all(&self, _: &ReplicationSender) -> bool {
+//!         true
+//!     }
+//! }
+//!
+//! fn setup(mut connection_manager: ResMut<server::ConnectionManager>) {
+//!     connection_manager.set_replication_strategy(FullSnapshot);
+//! }
+//! ```
+use std::fmt::Debug;
+
+use bevy::ecs::component::Tick as BevyTick;
+use bytes::Bytes;
+
+use crate::channel::builder::EntityUpdatesChannel;
+use crate::packet::message::MessageId;
+use crate::packet::message_manager::MessageManager;
+use crate::prelude::{ChannelKind, PacketError, Tick, TimeManager};
+use crate::serialize::writer::Writer;
+use crate::shared::replication::components::ReplicationGroupId;
+
+pub use super::send::{GroupChannel, ReplicationSender};
+
+/// Strategy used by a [`ReplicationSender`] to gather the replication changes and turn them into messages.
+///
+/// The default implementation of every method matches the default behaviour of lightyear, so implementations
+/// only need to override what they want to change.
+pub trait ReplicationSendStrategy: Debug + Send + Sync + 'static {
+    /// Returns true if all the replicated components must be gathered during the next replication pass,
+    /// instead of only the components that changed since they were last sent.
+    fn gather_all(&self, _sender: &ReplicationSender) -> bool {
+        false
+    }
+
+    /// Serialize the changes that were gathered in the [`ReplicationSender`], and buffer the messages to send.
+    ///
+    /// The pending changes of each group are stored in the [`GroupChannel`]s of the sender; they must be
+    /// consumed, otherwise they will be sent again during the next pass. The update messages of a group
+    /// should be buffered with [`ReplicationSendContext::buffer_update`], so that lost updates are sent again.
+    fn buffer_messages(
+        &self,
+        sender: &mut ReplicationSender,
+        context: &mut ReplicationSendContext,
+    ) -> Result<(), PacketError> {
+        sender.accumulate_priority(context.time_manager);
+        sender.buffer_actions(context)?;
+        sender.buffer_updates(context)
+    }
+}
+
+/// The default [`ReplicationSendStrategy`]: only the changes since the last send are replicated, and each
+/// replication group is sent in its own messages, by order of priority.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultReplicationStrategy;
+
+impl ReplicationSendStrategy for DefaultReplicationStrategy {}
+
+/// Data that can be used by a [`ReplicationSendStrategy`] to buffer the replication messages of a connection
+pub struct ReplicationSendContext<'a> {
+    pub(crate) tick: Tick,
+    pub(crate) bevy_tick: BevyTick,
+    pub(crate) time_manager: &'a TimeManager,
+    pub(crate) writer: &'a mut Writer,
+    pub(crate) message_manager: &'a mut MessageManager,
+    /// Update messages buffered with [`buffer_update`](Self::buffer_update), that must be associated with
+    /// their replication group by the [`ReplicationSender`]
+    pub(crate) buffered_updates: Vec<(ReplicationGroupId, MessageId)>,
+}
+
+impl ReplicationSendContext<'_> {
+    /// The current tick
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+
+    /// The bevy tick of the replication pass, which is used to detect the component changes
+    pub fn bevy_tick(&self) -> BevyTick {
+        self.bevy_tick
+    }
+
+    pub fn time_manager(&self) -> &TimeManager {
+        self.time_manager
+    }
+
+    /// Writer that can be used to serialize the messages, to reuse its allocation
+    pub fn writer(&mut self) -> &mut Writer {
+        self.writer
+    }
+
+    /// Buffer a serialized message on one of the channels of the connection
+    pub fn buffer_message(
+        &mut self,
+        message: Bytes,
+        channel: ChannelKind,
+        priority: f32,
+    ) -> Result<(), PacketError> {
+        self.message_manager
+            .buffer_send_with_priority(message, channel, priority)?;
+        Ok(())
+    }
+
+    /// Buffer a serialized [`EntityUpdatesMessage`](crate::shared::replication::EntityUpdatesMessage) of the
+    /// replication group `group_id` on the [`EntityUpdatesChannel`].
+    ///
+    /// Contrary to [`buffer_message`](Self::buffer_message), the message is associated with the group so that
+    /// the `send_tick` of the group is updated when the message is sent, and rewound if the message is lost.
+    pub fn buffer_update(
+        &mut self,
+        group_id: ReplicationGroupId,
+        message: Bytes,
+        priority: f32,
+    ) -> Result<(), PacketError> {
+        let message_id = self
+            .message_manager
+            .buffer_send_with_priority(
+                message,
+                ChannelKind::of::<EntityUpdatesChannel>(),
+                priority,
+            )?
+            .expect("The entity updates channel should always return a message_id");
+        self.buffered_updates.push((group_id, message_id));
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+//! Keep track of the components that were removed from a replicated entity.
+//!
+//! When a component is removed on the sender, the remote peers that are connected at that time receive
+//! a `RemoveComponent` action, but peers that connect later never learn that the component existed.
+//! For components that opt-in via [`add_tombstones`](crate::prelude::ComponentRegistration::add_tombstones),
+//! the sender records the tick at which the component was removed in the [`ComponentTombstones`] component,
+//! which is itself replicated. This lets the receiver distinguish between "never had the component"
+//! and "had the component and it was removed at tick T".
+//!
+//! On the receiver side, the tombstones can be accessed via:
+//! - the [`ComponentTombstoneEvent<C>`] event, emitted every time a new tombstone for `C` is received
+//! - the [`TombstoneHistory`] system parameter, to query the removal tick of a component on a given entity
+use std::marker::PhantomData;
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+use crate::prelude::{ComponentRegistry, Replicated, Replicating, Tick, TickManager};
+use crate::protocol::component::ComponentNetId;
+
+/// Records the tick at which components were removed from an entity on the sender side.
+///
+/// Only components that were registered with [`add_tombstones`](crate::prelude::ComponentRegistration::add_tombstones)
+/// get a tombstone. A tombstone is cleared if the component gets inserted again, and is pruned
+/// once it is older than the retention duration specified for that component.
+///
+/// This component is replicated, so it is also available on the receiver side (including for clients
+/// that connected after the component was removed).
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct ComponentTombstones {
+    removed: HashMap<ComponentNetId, Tick>,
+}
+
+impl ComponentTombstones {
+    /// Returns the tick at which the component `C` was removed from the entity,
+    /// or `None` if there is no tombstone for `C`.
+    pub fn removed_at<C: Component>(&self, registry: &ComponentRegistry) -> Option<Tick> {
+        registry
+            .get_net_id::<C>()
+            .and_then(|net_id| self.removed.get(&net_id).copied())
+    }
+
+    /// Iterate through all the tombstones, as `(component_net_id, removal_tick)`
+    pub fn iter(&self) -> impl Iterator<Item = (ComponentNetId, Tick)> + '_ {
+        self.removed.iter().map(|(net_id, tick)| (*net_id, *tick))
+    }
+
+    /// Returns true if there are no tombstones
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+/// Event emitted on the receiver whenever a tombstone for the component `C` is received,
+/// i.e. the component `C` was removed from the entity on the sender at tick [`removed_at`](Self::removed_at)
+#[derive(Event, Debug)]
+pub struct ComponentTombstoneEvent<C: Component> {
+    entity: Entity,
+    removed_at: Tick,
+    marker: PhantomData<C>,
+}
+
+impl<C: Component> ComponentTombstoneEvent<C> {
+    pub fn new(entity: Entity, removed_at: Tick) -> Self {
+        Self {
+            entity,
+            removed_at,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// The tick at which the component was removed on the sender
+    pub fn removed_at(&self) -> Tick {
+        self.removed_at
+    }
+}
+
+/// [`SystemParam`] to query the history of component removals of the replicated entities.
+///
+/// ```rust,ignore
+/// fn system(history: TombstoneHistory, query: Query<Entity, Without<PlayerColor>>) {
+///     for entity in query.iter() {
+///         match history.removed_at::<PlayerColor>(entity) {
+///             Some(tick) => info!("the color of {entity:?} was removed at tick {tick:?}"),
+///             None => info!("{entity:?} never had a color"),
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct TombstoneHistory<'w, 's> {
+    registry: Res<'w, ComponentRegistry>,
+    tombstones: Query<'w, 's, (Entity, &'static ComponentTombstones)>,
+}
+
+impl TombstoneHistory<'_, '_> {
+    /// Returns the tick at which the component `C` was removed from the entity,
+    /// or `None` if there is no tombstone for `C` on this entity.
+    pub fn removed_at<C: Component>(&self, entity: Entity) -> Option<Tick> {
+        self.tombstones
+            .get(entity)
+            .ok()
+            .and_then(|(_, tombstones)| tombstones.removed_at::<C>(&self.registry))
+    }
+
+    /// Iterate through all the entities that have a tombstone for the component `C`,
+    /// as `(entity, removal_tick)`
+    pub fn iter<C: Component>(&self) -> impl Iterator<Item = (Entity, Tick)> + '_ {
+        let net_id = self.registry.get_net_id::<C>();
+        self.tombstones
+            .iter()
+            .filter_map(move |(entity, tombstones)| {
+                net_id
+                    .and_then(|net_id| tombstones.removed.get(&net_id))
+                    .map(|tick| (entity, *tick))
+            })
+    }
+}
+
+/// Emit a [`ComponentTombstoneEvent<C>`] for every new tombstone of `C` received from the remote
+pub(crate) fn emit_tombstone_events<C: Component>(
+    registry: Res<ComponentRegistry>,
+    query: Query<(Entity, &ComponentTombstones), (Changed<ComponentTombstones>, With<Replicated>)>,
+    mut removed: RemovedComponents<ComponentTombstones>,
+    // last tombstone tick of `C` for which an event was emitted, for each entity
+    mut emitted: Local<EntityHashMap<Tick>>,
+    mut events: EventWriter<ComponentTombstoneEvent<C>>,
+) {
+    for entity in removed.read() {
+        emitted.remove(&entity);
+    }
+    for (entity, tombstones) in query.iter() {
+        match tombstones.removed_at::<C>(&registry) {
+            Some(tick) => {
+                // the tombstones component changed because of another component
+                if emitted.insert(entity, tick) == Some(tick) {
+                    continue;
+                }
+                trace!(?entity, ?tick, "Received component tombstone");
+                events.send(ComponentTombstoneEvent::new(entity, tick));
+            }
+            None => {
+                emitted.remove(&entity);
+            }
+        }
+    }
+}
+
+/// Record a tombstone when `C` is removed from an entity that is being replicated,
+/// and clear the tombstone if `C` is inserted again.
+pub(crate) fn record_tombstones<C: Component>(
+    mut commands: Commands,
+    registry: Res<ComponentRegistry>,
+    tick_manager: Res<TickManager>,
+    mut removed: RemovedComponents<C>,
+    query: Query<Has<C>, With<Replicating>>,
+    mut re_added: Query<&mut ComponentTombstones, (Added<C>, With<Replicating>)>,
+) {
+    let net_id = registry.net_id::<C>();
+    let tick = tick_manager.tick();
+    for entity in removed.read() {
+        // the entity could have been despawned, or the component re-inserted in the same frame
+        if !query.get(entity).is_ok_and(|has_component| !has_component) {
+            continue;
+        }
+        trace!(?entity, ?net_id, ?tick, "Recording component tombstone");
+        // we use a command so that multiple tombstones added in the same frame do not overwrite each other
+        commands
+            .entity(entity)
+            .add(move |entity: Entity, world: &mut World| {
+                let Some(mut entity_mut) = world.get_entity_mut(entity) else {
+                    return;
+                };
+                if let Some(mut tombstones) = entity_mut.get_mut::<ComponentTombstones>() {
+                    tombstones.removed.insert(net_id, tick);
+                } else {
+                    let mut tombstones = ComponentTombstones::default();
+                    tombstones.removed.insert(net_id, tick);
+                    entity_mut.insert(tombstones);
+                }
+            });
+    }
+    for mut tombstones in re_added.iter_mut() {
+        if tombstones.removed.contains_key(&net_id) {
+            tombstones.removed.remove(&net_id);
+        }
+    }
+}
+
+/// Remove the tombstones that are older than the retention duration of their component
+pub(crate) fn prune_tombstones(
+    registry: Res<ComponentRegistry>,
+    tick_manager: Res<TickManager>,
+    mut query: Query<&mut ComponentTombstones, With<Replicating>>,
+) {
+    let tick = tick_manager.tick();
+    let tick_duration = tick_manager.config.tick_duration;
+    let is_expired = |net_id: &ComponentNetId, removed_tick: &Tick| {
+        registry
+            .tombstone_retention(*net_id)
+            .is_some_and(|retention| {
+                tick - *removed_tick > retention_ticks(retention, tick_duration)
+            })
+    };
+    for mut tombstones in query.iter_mut() {
+        // avoid triggering change detection (and re-replicating the component) if nothing expired
+        if !tombstones
+            .bypass_change_detection()
+            .removed
+            .iter()
+            .any(|(net_id, removed_tick)| is_expired(net_id, removed_tick))
+        {
+            continue;
+        }
+        tombstones
+            .removed
+            .retain(|net_id, removed_tick| !is_expired(net_id, removed_tick));
+    }
+}
+
+/// Convert the retention duration to a number of ticks.
+///
+/// The retention is capped at `i16::MAX` ticks because of tick wrapping.
+fn retention_ticks(retention: Duration, tick_duration: Duration) -> i16 {
+    if tick_duration.is_zero() {
+        return i16::MAX;
+    }
+    (retention.as_nanos() / tick_duration.as_nanos()).min(i16::MAX as u128) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client;
+    use crate::prelude::server::Replicate;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::BevyStepper;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_retention_ticks() {
+        assert_eq!(
+            retention_ticks(Duration::from_millis(100), Duration::from_millis(10)),
+            10
+        );
+        assert_eq!(
+            retention_ticks(Duration::from_secs(100_000), Duration::from_millis(10)),
+            i16::MAX
+        );
+    }
+
+    #[test]
+    fn test_tombstone_replicated() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeSimple(1.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = *stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentTombstones>(client_entity)
+            .is_none());
+
+        // remove the component on the server
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .remove::<ComponentSyncModeSimple>();
+        let removal_tick = stepper.server_tick();
+        stepper.frame_step();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the tombstone should have been replicated
+        let client_world = stepper.client_app.world();
+        let registry = client_world.resource::<ComponentRegistry>();
+        let removed_at = client_world
+            .get::<ComponentTombstones>(client_entity)
+            .expect("tombstones were not replicated")
+            .removed_at::<ComponentSyncModeSimple>(registry)
+            .expect("missing tombstone for ComponentSyncModeSimple");
+        assert!(removed_at >= removal_tick);
+
+        // re-inserting the component should clear the tombstone
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(ComponentSyncModeSimple(2.0));
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_world = stepper.client_app.world();
+        let registry = client_world.resource::<ComponentRegistry>();
+        assert!(client_world
+            .get::<ComponentTombstones>(client_entity)
+            .unwrap()
+            .removed_at::<ComponentSyncModeSimple>(registry)
+            .is_none());
+    }
+
+    #[test]
+    fn test_tombstone_event_and_history() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeSimple(1.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = *stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // remove the component on the server
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .remove::<ComponentSyncModeSimple>();
+        let mut received = vec![];
+        for _ in 0..3 {
+            stepper.frame_step();
+            received.extend(
+                stepper
+                    .client_app
+                    .world_mut()
+                    .resource_mut::<Events<ComponentTombstoneEvent<ComponentSyncModeSimple>>>()
+                    .drain()
+                    .map(|event| (event.entity(), event.removed_at())),
+            );
+        }
+        // a single event is emitted for the tombstone
+        assert_eq!(received.len(), 1);
+        let (entity, removed_at) = received[0];
+        assert_eq!(entity, client_entity);
+
+        // the removal tick can also be queried with the history
+        let history_removed_at =
+            stepper
+                .client_app
+                .world_mut()
+                .run_system_once(move |history: TombstoneHistory| {
+                    history.removed_at::<ComponentSyncModeSimple>(client_entity)
+                });
+        assert_eq!(history_removed_at, Some(removed_at));
+    }
+}
@@ -0,0 +1,231 @@
+//! Random number generator that produces the same values on the client and on the server for a given tick.
+//!
+//! The [`NetworkedRng`] is derived from a seed chosen by the server (the [`NetworkedRngSeed`] resource,
+//! which is replicated to the clients) and from the current tick. At the start of every tick, the RNG is reset
+//! to a state that only depends on the seed and the tick, so predicted logic (critical hits, bullet spread, etc.)
+//! draws the same values on the client and on the server, as long as the values are drawn in the same order
+//! during the tick.
+//!
+//! During a rollback, the RNG is reset to the state of each tick that is re-simulated, so no history
+//! needs to be stored.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//! use rand::Rng;
+//!
+//! // add the plugin to both the client and the server apps, after the lightyear plugins
+//! app.add_plugins(NetworkedRngPlugin::default());
+//!
+//! fn shoot(mut rng: ResMut<NetworkedRng>) {
+//!     let spread = rng.gen_range(-0.1..0.1);
+//!     let critical_hit = rng.gen_bool(0.05);
+//! }
+//! ```
+use bevy::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::channel::builder::RngChannel;
+use crate::client::prediction::rollback::Rollback;
+use crate::prelude::{AppMessageExt, ChannelDirection, NetworkTarget, Tick, TickManager};
+use crate::server::config::ServerConfig;
+use crate::shared::replication::resources::ReplicateResourceExt;
+use crate::shared::sets::FixedUpdateSet;
+
+/// Seed of the [`NetworkedRng`], chosen by the server and replicated to the clients
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkedRngSeed(pub u64);
+
+/// Random number generator whose state only depends on the [`NetworkedRngSeed`], the current tick, and
+/// the number of values drawn since the start of the tick.
+///
+/// It implements [`RngCore`], so all the methods of [`rand::Rng`] can be used.
+///
+/// The sequence of each tick repeats when the [`Tick`] wraps around.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkedRng {
+    seed: u64,
+    tick: Tick,
+    /// Number of values drawn during the current tick
+    counter: u64,
+}
+
+impl NetworkedRng {
+    /// Reset the RNG to the start of the given tick
+    pub fn reset(&mut self, seed: u64, tick: Tick) {
+        self.seed = seed;
+        self.tick = tick;
+        self.counter = 0;
+    }
+
+    /// The tick that the RNG is currently drawing values for
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+}
+
+/// SplitMix64 finalizer, used to turn the (seed, tick, counter) triple into a uniformly distributed value
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl RngCore for NetworkedRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let tick_state = mix(self.seed ^ mix(self.tick.0 as u64));
+        self.counter += 1;
+        mix(tick_state.wrapping_add(self.counter.wrapping_mul(0x9e3779b97f4a7c15)))
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Plugin that adds the [`NetworkedRng`] resource.
+///
+/// It must be added to both the client and the server apps, after the lightyear plugins, because it
+/// registers the [`NetworkedRngSeed`] resource in the protocol.
+#[derive(Default)]
+pub struct NetworkedRngPlugin {
+    /// The seed used by the server. If `None`, a random seed is picked.
+    pub seed: Option<u64>,
+}
+
+impl Plugin for NetworkedRngPlugin {
+    fn build(&self, app: &mut App) {
+        // PROTOCOL
+        app.register_resource::<NetworkedRngSeed>(ChannelDirection::ServerToClient);
+        // RESOURCES
+        app.init_resource::<NetworkedRng>();
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        if is_server {
+            app.insert_resource(NetworkedRngSeed(self.seed.unwrap_or_else(rand::random)));
+            app.add_systems(Startup, replicate_seed);
+        }
+        // SYSTEMS
+        app.add_systems(
+            FixedFirst,
+            reset_rng_for_tick.after(FixedUpdateSet::TickUpdate),
+        );
+    }
+}
+
+fn replicate_seed(mut commands: Commands) {
+    commands.replicate_resource::<NetworkedRngSeed, RngChannel>(NetworkTarget::All);
+}
+
+/// Reset the RNG at the start of each tick (including the ticks that are re-simulated during a rollback)
+fn reset_rng_for_tick(
+    tick_manager: Res<TickManager>,
+    rollback: Option<Res<Rollback>>,
+    seed: Option<Res<NetworkedRngSeed>>,
+    mut rng: ResMut<NetworkedRng>,
+) {
+    let tick = rollback.map_or(tick_manager.tick(), |rollback| {
+        tick_manager.tick_or_rollback_tick(rollback.as_ref())
+    });
+    // the clients use a seed of 0 until they receive the seed from the server
+    rng.reset(seed.map_or(0, |seed| seed.0), tick);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_networked_rng_is_deterministic() {
+        let mut rng = NetworkedRng::default();
+        rng.reset(1, Tick(10));
+        let values: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_ne!(values[0], values[1]);
+
+        // resetting to the same tick draws the same values
+        rng.reset(1, Tick(10));
+        assert_eq!(values, (0..3).map(|_| rng.next_u64()).collect::<Vec<_>>());
+        // other ticks or seeds draw other values
+        rng.reset(1, Tick(11));
+        assert_ne!(values[0], rng.next_u64());
+        rng.reset(2, Tick(10));
+        assert_ne!(values[0], rng.next_u64());
+    }
+
+    #[derive(Resource, Default)]
+    struct Rolls(Vec<(Tick, u32)>);
+
+    fn roll(mut rng: ResMut<NetworkedRng>, mut rolls: ResMut<Rolls>) {
+        let tick = rng.tick();
+        rolls.0.push((tick, rng.gen_range(0..1000)));
+    }
+
+    #[test]
+    fn test_networked_rng_client_server() {
+        let mut stepper = BevyStepper::default();
+        for app in [&mut stepper.client_app, &mut stepper.server_app] {
+            app.add_plugins(NetworkedRngPlugin { seed: Some(42) });
+            app.init_resource::<Rolls>();
+            app.add_systems(FixedUpdate, roll);
+        }
+        // the plugins were added after the apps were built
+        stepper.client_app.finish();
+        stepper.server_app.finish();
+        stepper
+            .server_app
+            .world_mut()
+            .run_system_once(replicate_seed);
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get_resource::<NetworkedRngSeed>(),
+            Some(&NetworkedRngSeed(42))
+        );
+
+        // the rolls made on the same tick are the same on the client and on the server
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Rolls>()
+            .0
+            .clear();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Rolls>()
+            .0
+            .clear();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        let client_rolls = &stepper.client_app.world().resource::<Rolls>().0;
+        let server_rolls = &stepper.server_app.world().resource::<Rolls>().0;
+        let mut matched = 0;
+        for (tick, value) in server_rolls {
+            if let Some((_, client_value)) = client_rolls.iter().find(|(t, _)| t == tick) {
+                assert_eq!(value, client_value);
+                matched += 1;
+            }
+        }
+        assert!(matched > 0);
+    }
+}
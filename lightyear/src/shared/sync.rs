@@ -0,0 +1,14 @@
+//! Messages used to share the synchronization state of the client with the server.
+//!
+//! The client periodically reports how far behind the server its interpolation timeline is, so that the
+//! server can know which ticks the client is currently rendering for interpolated entities
+//! (see [`ConnectionManager::interpolated_tick`](crate::server::connection::ConnectionManager::interpolated_tick)).
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Message sent by the client to report its current interpolation delay
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct InterpolationDelayMessage {
+    /// Duration between the client's estimate of the current server time and the client's interpolation time
+    pub delay: Duration,
+}
@@ -99,4 +99,122 @@ impl TickManager {
     pub fn tick_or_rollback_tick(&self, rollback_state: &Rollback) -> Tick {
         rollback_state.get_rollback_tick().unwrap_or(self.tick)
     }
+
+    /// Number of ticks from `from` to `to`, taking into account that the tick wraps around `u16::MAX`.
+    ///
+    /// The result is negative if `to` is before `from`. Ticks are only comparable if they are less than
+    /// `i16::MAX` ticks apart (about 9 minutes at 60Hz).
+    pub fn ticks_between(&self, from: Tick, to: Tick) -> i16 {
+        to - from
+    }
+
+    /// Duration of `ticks` ticks
+    pub fn tick_to_duration(&self, ticks: u16) -> Duration {
+        self.config.tick_duration * ticks as u32
+    }
+
+    /// Number of ticks in `duration`, rounded to the nearest tick.
+    ///
+    /// The result is capped at `i16::MAX` so that the ticks that are computed from it can still be
+    /// compared to the current tick.
+    pub fn duration_to_ticks(&self, duration: Duration) -> u16 {
+        if self.config.tick_duration.is_zero() {
+            return 0;
+        }
+        let ticks = (duration.as_secs_f64() / self.config.tick_duration.as_secs_f64()).round();
+        ticks.min(i16::MAX as f64) as u16
+    }
+
+    /// The tick that will be reached after `duration` has elapsed, starting from the current tick
+    pub fn tick_in(&self, duration: Duration) -> Tick {
+        self.tick + self.duration_to_ticks(duration) as i16
+    }
+
+    /// Returns true if the current tick is equal to or after `tick`
+    pub fn has_reached(&self, tick: Tick) -> bool {
+        self.tick >= tick
+    }
+
+    /// The tick rendered by a client whose interpolation timeline is `interpolation_delay` behind `server_tick`.
+    ///
+    /// This is the tick right before the interpolation time. The client and the server both use this
+    /// formula so that lag compensation rewinds to the same tick on both sides.
+    pub fn interpolation_tick(&self, server_tick: Tick, interpolation_delay: Duration) -> Tick {
+        if self.config.tick_duration.is_zero() {
+            return server_tick;
+        }
+        let delay_ticks = interpolation_delay
+            .as_nanos()
+            .div_ceil(self.config.tick_duration.as_nanos())
+            .min(i16::MAX as u128);
+        server_tick - delay_ticks as u16
+    }
+
+    /// Fraction of a tick elapsed between [`interpolation_tick`](Self::interpolation_tick) and the next tick
+    pub fn interpolation_overstep(&self, interpolation_delay: Duration) -> f32 {
+        if self.config.tick_duration.is_zero() {
+            return 0.0;
+        }
+        let delay_ticks =
+            interpolation_delay.as_secs_f64() / self.config.tick_duration.as_secs_f64();
+        (delay_ticks.ceil() - delay_ticks) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_arithmetic_wraps() {
+        let mut tick_manager = TickManager::from_config(TickConfig::new(Duration::from_millis(10)));
+        tick_manager.set_tick_to(Tick(u16::MAX - 10));
+        assert_eq!(tick_manager.ticks_between(Tick(u16::MAX - 10), Tick(5)), 16);
+        assert_eq!(
+            tick_manager.ticks_between(Tick(5), Tick(u16::MAX - 10)),
+            -16
+        );
+
+        assert_eq!(
+            tick_manager.duration_to_ticks(Duration::from_millis(104)),
+            10
+        );
+        assert_eq!(
+            tick_manager.tick_to_duration(10),
+            Duration::from_millis(100)
+        );
+        // the tick that is 1 second in the future wraps around
+        let future = tick_manager.tick_in(Duration::from_secs(1));
+        assert_eq!(future, Tick(89));
+        assert!(!tick_manager.has_reached(future));
+        tick_manager.set_tick_to(Tick(100));
+        assert!(tick_manager.has_reached(future));
+
+        // very long durations are capped so that the resulting tick is still in the future
+        assert_eq!(
+            tick_manager.duration_to_ticks(Duration::from_secs(3600)),
+            i16::MAX as u16
+        );
+    }
+
+    #[test]
+    fn test_interpolation_tick() {
+        let tick_manager = TickManager::from_config(TickConfig::new(Duration::from_millis(10)));
+        assert_eq!(
+            tick_manager.interpolation_tick(Tick(100), Duration::from_millis(30)),
+            Tick(97)
+        );
+        // a partial tick of delay means that the client renders the previous tick
+        assert_eq!(
+            tick_manager.interpolation_tick(Tick(100), Duration::from_millis(31)),
+            Tick(96)
+        );
+        assert!(
+            (tick_manager.interpolation_overstep(Duration::from_millis(31)) - 0.9).abs() < 1e-3
+        );
+        assert_eq!(
+            tick_manager.interpolation_tick(Tick(2), Duration::from_millis(50)),
+            Tick(u16::MAX - 2)
+        );
+    }
 }
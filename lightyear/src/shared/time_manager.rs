@@ -130,7 +130,6 @@ impl TimeManager {
         self.wrapped_time
     }
 
-    #[cfg(test)]
     pub(crate) fn set_current_time(&mut self, time: WrappedTime) {
         self.wrapped_time = time;
     }
@@ -0,0 +1,293 @@
+//! Seed of the procedurally generated world, sent by the server to each client before replication starts.
+//!
+//! When the [`WorldSeedPlugin`] is added, the server sends the [`WorldSeed`] (the seed and the version of the
+//! generation algorithm) to every client that connects, and holds the replication of the world for that client
+//! until the client reports that it has generated its world. This way the replicated entities never arrive
+//! before the terrain that they are standing on.
+//!
+//! On the client, the [`WorldSeed`] resource is inserted when the seed is received and the [`WorldGeneration`]
+//! resource switches to [`WorldGeneration::Generating`]. Once the local generation is complete (which can take
+//! several frames), set it to [`WorldGeneration::Ready`]: the server is notified and starts replicating the world.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//!
+//! // add the plugin to both the client and the server apps, after the lightyear plugins
+//! app.add_plugins(WorldSeedPlugin { seed: None, version: 3 });
+//!
+//! // client: generate the terrain from the seed
+//! fn generate_world(seed: Res<WorldSeed>, mut generation: ResMut<WorldGeneration>) {
+//!     assert_eq!(seed.version, 3, "the server uses another version of the world generation");
+//!     generate_terrain(seed.seed);
+//!     *generation = WorldGeneration::Ready;
+//! }
+//! app.add_systems(Update, generate_world.run_if(resource_added::<WorldSeed>));
+//! ```
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::channel::builder::WorldSeedChannel;
+use crate::client::config::ClientConfig;
+use crate::connection::id::ClientId;
+use crate::prelude::server::is_started;
+use crate::prelude::{client, server, AppMessageExt, ChannelDirection};
+use crate::server::config::ServerConfig;
+use crate::shared::sets::{ClientMarker, InternalMainSet, ServerMarker};
+
+/// Seed of the procedurally generated world, chosen by the server
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldSeed {
+    pub seed: u64,
+    /// Version of the generation algorithm, so that the clients can check that they generate the same world
+    pub version: u32,
+}
+
+/// Message sent by the client once its world has been generated
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldReady {
+    /// Version of the generation algorithm that was used by the client
+    pub version: u32,
+}
+
+/// State of the generation of the world on the client
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldGeneration {
+    /// The [`WorldSeed`] has not been received yet
+    #[default]
+    WaitingForSeed,
+    /// The [`WorldSeed`] has been received and the world is being generated
+    Generating,
+    /// The world has been generated; the server replicates the world to the client
+    Ready,
+}
+
+/// Event emitted on the server when a client has generated its world, and the replication to that client starts
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldReadyEvent {
+    pub client_id: ClientId,
+}
+
+/// Run condition that returns true once the world of the client has been generated
+pub fn is_world_ready(generation: Option<Res<WorldGeneration>>) -> bool {
+    generation.is_some_and(|generation| *generation == WorldGeneration::Ready)
+}
+
+/// Plugin that synchronizes the [`WorldSeed`] between the server and the clients.
+///
+/// It must be added to both the client and the server apps, after the lightyear plugins, because it
+/// registers the [`WorldSeed`] and [`WorldReady`] messages in the protocol.
+#[derive(Default)]
+pub struct WorldSeedPlugin {
+    /// The seed used by the server. If `None`, a random seed is picked.
+    pub seed: Option<u64>,
+    /// Version of the generation algorithm
+    pub version: u32,
+}
+
+impl Plugin for WorldSeedPlugin {
+    fn build(&self, app: &mut App) {
+        // PROTOCOL
+        app.register_message::<WorldSeed>(ChannelDirection::ServerToClient);
+        app.register_message::<WorldReady>(ChannelDirection::ClientToServer);
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        let is_client = app.world().get_resource::<ClientConfig>().is_some();
+        if is_server {
+            app.insert_resource(WorldSeed {
+                seed: self.seed.unwrap_or_else(rand::random),
+                version: self.version,
+            });
+            app.add_event::<WorldReadyEvent>();
+            app.add_systems(
+                PreUpdate,
+                (send_seed_to_new_clients, handle_world_ready)
+                    .after(InternalMainSet::<ServerMarker>::EmitEvents)
+                    .run_if(is_started),
+            );
+        } else if is_client {
+            // in HostServer mode, the local client shares the world of the server
+            app.init_resource::<WorldGeneration>();
+            app.add_systems(
+                PreUpdate,
+                receive_seed.after(InternalMainSet::<ClientMarker>::EmitEvents),
+            );
+            app.add_systems(
+                PostUpdate,
+                (
+                    send_world_ready.run_if(resource_changed::<WorldGeneration>),
+                    reset_on_disconnect,
+                )
+                    .before(InternalMainSet::<ClientMarker>::Send),
+            );
+        }
+    }
+}
+
+/// Send the seed to the clients that just connected, and hold the replication until they are ready
+fn send_seed_to_new_clients(
+    seed: Res<WorldSeed>,
+    mut connection_manager: ResMut<server::ConnectionManager>,
+    mut connect_events: EventReader<server::ConnectEvent>,
+) {
+    for event in connect_events.read() {
+        let client_id = event.client_id;
+        let Ok(connection) = connection_manager.connection_mut(client_id) else {
+            continue;
+        };
+        if connection.is_local_client() {
+            continue;
+        }
+        connection.awaiting_world_ready = true;
+        debug!(?client_id, ?seed, "Sending the world seed");
+        let _ = connection_manager
+            .send_message::<WorldSeedChannel, _>(client_id, &mut seed.clone())
+            .inspect_err(|e| error!(?client_id, "Could not send the world seed: {:?}", e));
+    }
+}
+
+/// Start the replication to the clients that have generated their world
+fn handle_world_ready(
+    seed: Res<WorldSeed>,
+    mut connection_manager: ResMut<server::ConnectionManager>,
+    mut messages: EventReader<server::MessageEvent<WorldReady>>,
+    mut events: EventWriter<WorldReadyEvent>,
+) {
+    for event in messages.read() {
+        let client_id = event.context;
+        if event.message.version != seed.version {
+            warn!(
+                ?client_id,
+                client_version = event.message.version,
+                server_version = seed.version,
+                "The client generated its world with another version of the generation algorithm"
+            );
+        }
+        if let Ok(connection) = connection_manager.connection_mut(client_id) {
+            if std::mem::take(&mut connection.awaiting_world_ready) {
+                debug!(
+                    ?client_id,
+                    "The client's world is ready, starting replication"
+                );
+                events.send(WorldReadyEvent { client_id });
+            }
+        }
+    }
+}
+
+fn receive_seed(
+    mut commands: Commands,
+    mut messages: EventReader<client::MessageEvent<WorldSeed>>,
+    mut generation: ResMut<WorldGeneration>,
+) {
+    if let Some(event) = messages.read().last() {
+        commands.insert_resource(*event.message());
+        *generation = WorldGeneration::Generating;
+    }
+}
+
+/// Notify the server once the world has been generated
+fn send_world_ready(
+    seed: Option<Res<WorldSeed>>,
+    generation: Res<WorldGeneration>,
+    mut connection_manager: ResMut<client::ConnectionManager>,
+) {
+    if *generation != WorldGeneration::Ready {
+        return;
+    }
+    let Some(seed) = seed else {
+        error!("The world cannot be ready before the world seed is received");
+        return;
+    };
+    let _ = connection_manager
+        .send_message::<WorldSeedChannel, _>(&mut WorldReady {
+            version: seed.version,
+        })
+        .inspect_err(|e| {
+            error!(
+                "Could not notify the server that the world is ready: {:?}",
+                e
+            )
+        });
+}
+
+/// The world is generated again when the client reconnects
+fn reset_on_disconnect(
+    mut commands: Commands,
+    mut disconnect_events: EventReader<client::DisconnectEvent>,
+    mut generation: ResMut<WorldGeneration>,
+) {
+    if disconnect_events.read().last().is_some() {
+        commands.remove_resource::<WorldSeed>();
+        *generation = WorldGeneration::WaitingForSeed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{Replicated, SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn test_replication_waits_for_world_generation() {
+        let tick_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            },
+            ClientConfig::default(),
+            tick_duration,
+        );
+        stepper.client_app.add_plugins(WorldSeedPlugin {
+            seed: None,
+            version: 1,
+        });
+        stepper.server_app.add_plugins(WorldSeedPlugin {
+            seed: Some(42),
+            version: 1,
+        });
+        stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)));
+        stepper.init();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+
+        // the seed was received, but the world is not replicated until it is generated
+        assert_eq!(
+            stepper.client_app.world().get_resource::<WorldSeed>(),
+            Some(&WorldSeed {
+                seed: 42,
+                version: 1
+            })
+        );
+        assert_eq!(
+            stepper.client_app.world().resource::<WorldGeneration>(),
+            &WorldGeneration::Generating
+        );
+        let mut query = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<&ComponentSyncModeFull, With<Replicated>>();
+        assert!(query.iter(stepper.client_app.world()).next().is_none());
+
+        *stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<WorldGeneration>() = WorldGeneration::Ready;
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            query.single(stepper.client_app.world()),
+            &ComponentSyncModeFull(1.0)
+        );
+    }
+}
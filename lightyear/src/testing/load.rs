@@ -0,0 +1,375 @@
+//! Headless load tests: N simulated clients connected to a real server over UDP.
+//!
+//! This is useful to validate the capacity of a server before launch: the clients can be driven with
+//! random or scripted inputs, and the [`LoadTestStats`] aggregate the CPU time spent updating the server
+//! and the bytes exchanged with each client.
+//!
+//! The time is controlled by the [`Stepper`], so the apps are updated as fast as possible instead of in real time.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//! use lightyear::testing::load::{LoadTest, LoadTestConfig};
+//! use lightyear::testing::StepperConfig;
+//! use rand::Rng;
+//!
+//! let mut load_test = LoadTest::new(
+//!     LoadTestConfig {
+//!         stepper: StepperConfig {
+//!             num_clients: 100,
+//!             ..default()
+//!         },
+//!         ..default()
+//!     },
+//!     |app| {
+//!         app.add_plugins(ProtocolPlugin);
+//!     },
+//! );
+//! load_test.init();
+//! // send a random input from every client on every frame
+//! let stats = load_test.run(1000, |_, app| {
+//!     let direction = rand::thread_rng().gen_range(0..4);
+//!     app.world_mut()
+//!         .resource_mut::<client::InputManager<Direction>>()
+//!         .add_input(Direction(direction), app.world().resource::<TickManager>().tick());
+//! });
+//! println!("{:?} per tick, {} bytes sent per client", stats.mean_update_per_tick(), stats.mean_bytes_sent_per_client());
+//! ```
+use std::net::{Ipv4Addr, SocketAddr};
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::{default, App, Commands, Real, Time};
+use bevy::state::app::StatesPlugin;
+use bevy::utils::{Duration, Instant};
+use bevy::MinimalPlugins;
+
+use crate::connection::netcode::generate_key;
+use crate::connection::server::NetServer;
+use crate::prelude::client::{
+    Authentication, ClientCommands, ClientConfig, ClientPlugins, ClientTransport,
+};
+use crate::prelude::server::{
+    ServerCommands, ServerConfig, ServerConnections, ServerPlugins, ServerTransport,
+};
+use crate::prelude::{client, server, ClientId};
+use crate::testing::{Stepper, StepperConfig};
+
+/// Configuration of a [`LoadTest`]
+#[derive(Clone)]
+pub struct LoadTestConfig {
+    /// Address on which the server listens. Use port 0 to pick any available port.
+    pub server_addr: SocketAddr,
+    /// Configuration of the apps.
+    ///
+    /// The `net` field of the client and server configs is overridden to connect the clients over UDP.
+    pub stepper: StepperConfig,
+}
+
+impl Default for LoadTestConfig {
+    fn default() -> Self {
+        Self {
+            server_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            stepper: StepperConfig::default(),
+        }
+    }
+}
+
+/// Statistics aggregated over the frames of a [`LoadTest::run`]
+#[derive(Debug, Clone, Default)]
+pub struct LoadTestStats {
+    /// Number of frames that were run
+    pub frames: usize,
+    /// Number of ticks of the server during the run
+    pub server_ticks: u32,
+    /// Total time spent updating the server app
+    pub server_update_total: Duration,
+    /// Longest update of the server app
+    pub server_update_max: Duration,
+    /// Network statistics of each client, as seen by the server
+    pub clients: Vec<ClientLoadStats>,
+}
+
+/// Network statistics of one client during a [`LoadTest::run`]
+#[derive(Debug, Clone)]
+pub struct ClientLoadStats {
+    pub client_id: ClientId,
+    /// Bytes sent by the server to the client
+    pub bytes_sent: usize,
+    /// Bytes received by the server from the client
+    pub bytes_received: usize,
+}
+
+impl LoadTestStats {
+    /// Mean CPU time spent updating the server per tick
+    pub fn mean_update_per_tick(&self) -> Duration {
+        self.server_update_total / self.server_ticks.max(1)
+    }
+
+    /// Mean number of bytes sent by the server to each client
+    pub fn mean_bytes_sent_per_client(&self) -> f64 {
+        self.clients.iter().map(|c| c.bytes_sent).sum::<usize>() as f64
+            / self.clients.len().max(1) as f64
+    }
+
+    /// Mean number of bytes received by the server from each client
+    pub fn mean_bytes_received_per_client(&self) -> f64 {
+        self.clients.iter().map(|c| c.bytes_received).sum::<usize>() as f64
+            / self.clients.len().max(1) as f64
+    }
+}
+
+/// Server app and N headless client apps connected over UDP
+pub struct LoadTest {
+    pub stepper: Stepper,
+}
+
+impl LoadTest {
+    /// Create the server and client apps.
+    ///
+    /// `setup` is called on every app after the lightyear plugins are added; use it to add your protocol.
+    /// The clients are not connected yet; call [`LoadTest::init`] to connect them.
+    pub fn new(config: LoadTestConfig, setup: impl Fn(&mut App)) -> Self {
+        let protocol_id = 0;
+        let private_key = generate_key();
+        let shared = config.stepper.shared;
+
+        let mut client_apps = vec![];
+        for i in 0..config.stepper.num_clients {
+            let mut client_app = App::new();
+            client_app.add_plugins((MinimalPlugins, StatesPlugin));
+            let client_config = ClientConfig {
+                shared,
+                net: client::NetConfig::Netcode {
+                    auth: Authentication::Manual {
+                        // updated in `init` once the server is listening
+                        server_addr: config.server_addr,
+                        protocol_id,
+                        private_key,
+                        client_id: i as u64,
+                    },
+                    config: default(),
+                    io: client::IoConfig::from_transport(ClientTransport::UdpSocket(
+                        SocketAddr::from((config.server_addr.ip(), 0)),
+                    )),
+                },
+                ..config.stepper.client.clone()
+            };
+            client_app.add_plugins(ClientPlugins::new(client_config));
+            setup(&mut client_app);
+            client_apps.push(client_app);
+        }
+
+        let mut server_app = App::new();
+        server_app.add_plugins((MinimalPlugins, StatesPlugin));
+        let server_config = ServerConfig {
+            shared,
+            net: vec![server::NetConfig::Netcode {
+                config: server::NetcodeConfig::default()
+                    .with_protocol_id(protocol_id)
+                    .with_key(private_key),
+                io: server::IoConfig::from_transport(ServerTransport::UdpSocket(
+                    config.server_addr,
+                )),
+            }],
+            ..config.stepper.server.clone()
+        };
+        server_app.add_plugins(ServerPlugins::new(server_config));
+        setup(&mut server_app);
+
+        // Initialize Real time (needed only for the first TimeSystem run)
+        let now = Instant::now();
+        for app in client_apps
+            .iter_mut()
+            .chain(std::iter::once(&mut server_app))
+        {
+            app.world_mut()
+                .resource_mut::<Time<Real>>()
+                .update_with_instant(now);
+        }
+        Self {
+            stepper: Stepper {
+                server_app,
+                client_apps,
+                frame_duration: config.stepper.frame_duration,
+                tick_duration: shared.tick.tick_duration,
+                current_time: now,
+            },
+        }
+    }
+
+    /// Finish building the apps, start the server and connect all the clients.
+    ///
+    /// The apps are stepped until all the clients are synced with the server.
+    pub fn init(&mut self) {
+        let stepper = &mut self.stepper;
+        for app in stepper
+            .client_apps
+            .iter_mut()
+            .chain(std::iter::once(&mut stepper.server_app))
+        {
+            app.finish();
+            app.cleanup();
+        }
+        stepper
+            .server_app
+            .world_mut()
+            .run_system_once(|mut commands: Commands| commands.start_server());
+        stepper.frame_step();
+
+        // the server might be listening on a port that was picked by the OS
+        let server_addr = stepper
+            .server_app
+            .world()
+            .resource::<ServerConnections>()
+            .servers[0]
+            .io()
+            .expect("the server is not listening")
+            .local_addr();
+        for client_app in stepper.client_apps.iter_mut() {
+            let mut config = client_app.world_mut().resource_mut::<ClientConfig>();
+            if let client::NetConfig::Netcode {
+                auth:
+                    Authentication::Manual {
+                        server_addr: addr, ..
+                    },
+                ..
+            } = &mut config.net
+            {
+                *addr = server_addr;
+            }
+            client_app
+                .world_mut()
+                .run_system_once(|mut commands: Commands| commands.connect_client());
+        }
+
+        // Advance the world to let the connection process complete
+        for _ in 0..100 {
+            if stepper.client_apps.iter().all(|client_app| {
+                client_app
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .is_synced()
+            }) {
+                return;
+            }
+            stepper.frame_step();
+        }
+    }
+
+    /// Run `frames` frames and return the statistics of the run.
+    ///
+    /// `drive` is called for every client app (with the index of the client) before it is updated;
+    /// use it to send random or scripted inputs.
+    pub fn run(&mut self, frames: usize, mut drive: impl FnMut(usize, &mut App)) -> LoadTestStats {
+        let stepper = &mut self.stepper;
+        let client_ids: Vec<_> = (0..stepper.client_apps.len())
+            .map(|i| stepper.client_id(i))
+            .collect();
+        let mut last_tick = stepper.server_tick();
+        let start_stats: Vec<_> = client_ids
+            .iter()
+            .map(|client_id| Self::client_stats(stepper, *client_id))
+            .collect();
+
+        let mut stats = LoadTestStats {
+            frames,
+            ..default()
+        };
+        for _ in 0..frames {
+            stepper.advance_time(stepper.frame_duration);
+            for (i, client_app) in stepper.client_apps.iter_mut().enumerate() {
+                drive(i, client_app);
+                client_app.update();
+            }
+            let start = Instant::now();
+            stepper.server_app.update();
+            let elapsed = start.elapsed();
+            stats.server_update_total += elapsed;
+            stats.server_update_max = stats.server_update_max.max(elapsed);
+            // count the ticks frame by frame, because the tick wraps around
+            let tick = stepper.server_tick();
+            stats.server_ticks += (tick - last_tick) as u32;
+            last_tick = tick;
+        }
+
+        stats.clients = client_ids
+            .iter()
+            .zip(start_stats)
+            .map(|(client_id, start)| {
+                let end = Self::client_stats(stepper, *client_id);
+                ClientLoadStats {
+                    client_id: *client_id,
+                    bytes_sent: end.bytes_sent - start.bytes_sent,
+                    bytes_received: end.bytes_received - start.bytes_received,
+                }
+            })
+            .collect();
+        stats
+    }
+
+    /// Total bytes exchanged by the server with a client since it connected
+    fn client_stats(stepper: &Stepper, client_id: ClientId) -> ClientLoadStats {
+        let (bytes_sent, bytes_received) = stepper
+            .server_connection_manager()
+            .connection(client_id)
+            .map_or((0, 0), |connection| {
+                (
+                    connection.stats().bytes_sent,
+                    connection.stats().bytes_received,
+                )
+            });
+        ClientLoadStats {
+            client_id,
+            bytes_sent,
+            bytes_received,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::protocol::*;
+
+    #[test]
+    fn test_load_test_over_udp() {
+        let mut load_test = LoadTest::new(
+            LoadTestConfig {
+                stepper: StepperConfig {
+                    num_clients: 3,
+                    ..default()
+                },
+                ..default()
+            },
+            |app| {
+                app.add_plugins(ProtocolPlugin);
+            },
+        );
+        load_test.init();
+        assert_eq!(
+            load_test
+                .stepper
+                .server_connection_manager()
+                .connections
+                .len(),
+            3
+        );
+
+        let mut driven = 0;
+        let stats = load_test.run(10, |i, app| {
+            driven += 1;
+            app.world_mut()
+                .resource_mut::<client::ConnectionManager>()
+                .send_message::<Channel1, _>(&mut StringMessage(format!("client {i}")))
+                .unwrap();
+        });
+        assert_eq!(driven, 30);
+        assert_eq!(stats.frames, 10);
+        assert_eq!(stats.server_ticks, 10);
+        assert_eq!(stats.clients.len(), 3);
+        for client in &stats.clients {
+            assert!(client.bytes_sent > 0);
+            assert!(client.bytes_received > 0);
+        }
+    }
+}
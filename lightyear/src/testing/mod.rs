@@ -0,0 +1,387 @@
+//! Helpers to write deterministic integration tests for apps using lightyear.
+//!
+//! The [`Stepper`] creates a server app and N client apps that are connected via local channels
+//! (no sockets are used), and lets you step the apps manually. The time is fully controlled by the
+//! stepper, so the tests don't depend on the wall-clock time and never need to sleep.
+//!
+//! ```rust,ignore
+//! use bevy::prelude::*;
+//! use lightyear::prelude::*;
+//! use lightyear::testing::{Stepper, StepperConfig};
+//!
+//! let mut stepper = Stepper::new(
+//!     StepperConfig {
+//!         num_clients: 2,
+//!         ..default()
+//!     },
+//!     // add your protocol to every app
+//!     |app| {
+//!         app.add_plugins(ProtocolPlugin);
+//!     },
+//! );
+//! // connect all the clients and wait until they are synced
+//! stepper.init();
+//!
+//! let entity = stepper
+//!     .server_app
+//!     .world_mut()
+//!     .spawn((PlayerPosition(Vec2::ZERO), server::Replicate::default()))
+//!     .id();
+//! stepper.frame_step();
+//! stepper.frame_step();
+//! // the entity was replicated to the first client
+//! let client_world = stepper.client_apps[0].world_mut();
+//! assert_eq!(
+//!     client_world
+//!         .query::<&PlayerPosition>()
+//!         .iter(client_world)
+//!         .count(),
+//!     1
+//! );
+//! ```
+use std::net::{Ipv4Addr, SocketAddr};
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::{default, App, Commands, Mut, Real, Time};
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::{Duration, Instant};
+use bevy::MinimalPlugins;
+
+use crate::connection::netcode::generate_key;
+use crate::prelude::client::{
+    Authentication, ClientCommands, ClientConfig, ClientPlugins, ClientTransport,
+};
+use crate::prelude::server::{ServerCommands, ServerConfig, ServerPlugins, ServerTransport};
+use crate::prelude::{client, server, ClientId, PingConfig, SharedConfig, Tick, TickConfig};
+use crate::prelude::{TickManager, TimeManager};
+use crate::shared::time_manager::WrappedTime;
+use crate::transport::LOCAL_SOCKET;
+
+#[cfg(not(target_family = "wasm"))]
+pub mod load;
+
+/// Maximum number of frames that [`Stepper::init`] steps while waiting for the clients to be synced
+pub const MAX_INIT_FRAMES: usize = 100;
+
+/// Configuration of the [`Stepper`]
+#[derive(Clone)]
+pub struct StepperConfig {
+    /// Number of clients connected to the server
+    pub num_clients: usize,
+    /// Duration by which the time is advanced on each [`Stepper::frame_step`]
+    pub frame_duration: Duration,
+    /// Configuration shared by the server and the clients
+    pub shared: SharedConfig,
+    /// Base configuration of the clients.
+    ///
+    /// The `shared` and `net` fields are overridden by the stepper.
+    pub client: ClientConfig,
+    /// Base configuration of the server.
+    ///
+    /// The `shared` and `net` fields are overridden by the stepper.
+    pub server: ServerConfig,
+}
+
+impl Default for StepperConfig {
+    fn default() -> Self {
+        let tick_duration = Duration::from_millis(10);
+        // send pings every frame, so that the acks are received every frame
+        let ping = PingConfig {
+            ping_interval: Duration::default(),
+            ..default()
+        };
+        Self {
+            num_clients: 1,
+            frame_duration: tick_duration,
+            shared: SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            },
+            client: ClientConfig { ping, ..default() },
+            server: ServerConfig { ping, ..default() },
+        }
+    }
+}
+
+/// Server app and client apps connected via local channels, that can be stepped manually.
+///
+/// The clients use the [`ClientId::Netcode`] ids `0..num_clients`.
+pub struct Stepper {
+    pub server_app: App,
+    pub client_apps: Vec<App>,
+    pub frame_duration: Duration,
+    /// fixed timestep duration
+    pub tick_duration: Duration,
+    pub current_time: Instant,
+}
+
+impl Stepper {
+    /// Create the server and client apps.
+    ///
+    /// `setup` is called on every app after the lightyear plugins are added; use it to add your protocol.
+    /// The clients are not connected yet; call [`Stepper::init`] to connect them.
+    pub fn new(config: StepperConfig, setup: impl Fn(&mut App)) -> Self {
+        let protocol_id = 0;
+        let private_key = generate_key();
+
+        let mut client_channels = vec![];
+        let mut client_apps = vec![];
+        for i in 0..config.num_clients {
+            // channels to receive a message from/to server
+            let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
+            let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
+            // the address is only used by the server to identify the client
+            let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 10000 + i as u16));
+            client_channels.push((client_addr, to_server_recv, from_server_send));
+
+            let mut client_app = App::new();
+            client_app.add_plugins((MinimalPlugins, StatesPlugin));
+            let client_config = ClientConfig {
+                shared: config.shared,
+                net: client::NetConfig::Netcode {
+                    auth: Authentication::Manual {
+                        // local channels only work with the LOCAL_SOCKET server address
+                        server_addr: LOCAL_SOCKET,
+                        protocol_id,
+                        private_key,
+                        client_id: i as u64,
+                    },
+                    config: default(),
+                    io: client::IoConfig::from_transport(ClientTransport::LocalChannel {
+                        send: to_server_send,
+                        recv: from_server_recv,
+                    }),
+                },
+                ..config.client.clone()
+            };
+            client_app.add_plugins(ClientPlugins::new(client_config));
+            setup(&mut client_app);
+            client_apps.push(client_app);
+        }
+
+        let mut server_app = App::new();
+        server_app.add_plugins((MinimalPlugins, StatesPlugin));
+        let server_config = ServerConfig {
+            shared: config.shared,
+            net: vec![server::NetConfig::Netcode {
+                config: server::NetcodeConfig::default()
+                    .with_protocol_id(protocol_id)
+                    .with_key(private_key),
+                io: server::IoConfig::from_transport(ServerTransport::Channels {
+                    channels: client_channels,
+                }),
+            }],
+            ..config.server.clone()
+        };
+        server_app.add_plugins(ServerPlugins::new(server_config));
+        setup(&mut server_app);
+
+        // Initialize Real time (needed only for the first TimeSystem run)
+        let now = Instant::now();
+        for app in client_apps
+            .iter_mut()
+            .chain(std::iter::once(&mut server_app))
+        {
+            app.world_mut()
+                .resource_mut::<Time<Real>>()
+                .update_with_instant(now);
+        }
+        Self {
+            server_app,
+            client_apps,
+            frame_duration: config.frame_duration,
+            tick_duration: config.shared.tick.tick_duration,
+            current_time: now,
+        }
+    }
+
+    /// Finish building the apps, start the server and connect all the clients.
+    ///
+    /// The apps are stepped until all the clients are synced with the server.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clients are still not synced after [`MAX_INIT_FRAMES`] frames.
+    pub fn init(&mut self) {
+        for app in self
+            .client_apps
+            .iter_mut()
+            .chain(std::iter::once(&mut self.server_app))
+        {
+            app.finish();
+            app.cleanup();
+        }
+        self.server_app
+            .world_mut()
+            .run_system_once(|mut commands: Commands| commands.start_server());
+        for client_app in self.client_apps.iter_mut() {
+            client_app
+                .world_mut()
+                .run_system_once(|mut commands: Commands| commands.connect_client());
+        }
+
+        // Advance the world to let the connection process complete
+        for _ in 0..MAX_INIT_FRAMES {
+            if self.client_apps.iter().all(|client_app| {
+                client_app
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .is_synced()
+            }) {
+                return;
+            }
+            self.frame_step();
+        }
+        let unsynced: Vec<ClientId> = (0..self.client_apps.len())
+            .filter(|i| !self.client_connection_manager(*i).is_synced())
+            .map(|i| self.client_id(i))
+            .collect();
+        panic!(
+            "clients {unsynced:?} are not synced with the server after {MAX_INIT_FRAMES} frames"
+        );
+    }
+
+    /// The [`ClientId`] of the client at the given index
+    pub fn client_id(&self, index: usize) -> ClientId {
+        assert!(index < self.client_apps.len(), "no client at index {index}");
+        ClientId::Netcode(index as u64)
+    }
+
+    /// The [`client::ConnectionManager`] of the client at the given index
+    pub fn client_connection_manager(&self, index: usize) -> &client::ConnectionManager {
+        self.client_apps[index]
+            .world()
+            .resource::<client::ConnectionManager>()
+    }
+
+    /// The mutable [`client::ConnectionManager`] of the client at the given index
+    pub fn client_connection_manager_mut(
+        &mut self,
+        index: usize,
+    ) -> Mut<'_, client::ConnectionManager> {
+        self.client_apps[index]
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+    }
+
+    /// The [`server::ConnectionManager`] of the server
+    pub fn server_connection_manager(&self) -> &server::ConnectionManager {
+        self.server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+    }
+
+    /// The mutable [`server::ConnectionManager`] of the server
+    pub fn server_connection_manager_mut(&mut self) -> Mut<'_, server::ConnectionManager> {
+        self.server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+    }
+
+    /// The current tick of the server
+    pub fn server_tick(&self) -> Tick {
+        self.server_app.world().resource::<TickManager>().tick()
+    }
+
+    /// The current tick of the client at the given index
+    pub fn client_tick(&self, index: usize) -> Tick {
+        self.client_apps[index]
+            .world()
+            .resource::<TickManager>()
+            .tick()
+    }
+
+    /// Set the tick (and the corresponding time) of the server
+    pub fn set_server_tick(&mut self, tick: Tick) {
+        let new_time = WrappedTime::from_duration(self.tick_duration * (tick.0 as u32));
+        self.server_app
+            .world_mut()
+            .resource_mut::<TimeManager>()
+            .set_current_time(new_time);
+        self.server_app
+            .world_mut()
+            .resource_mut::<TickManager>()
+            .set_tick_to(tick);
+    }
+
+    /// Advance the time of all the apps by the given duration, without updating them
+    pub fn advance_time(&mut self, duration: Duration) {
+        self.current_time += duration;
+        self.server_app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.current_time));
+        for client_app in self.client_apps.iter_mut() {
+            client_app.insert_resource(TimeUpdateStrategy::ManualInstant(self.current_time));
+        }
+    }
+
+    /// Update the clients, then the server
+    pub fn update(&mut self) {
+        for client_app in self.client_apps.iter_mut() {
+            client_app.update();
+        }
+        self.server_app.update();
+    }
+
+    /// Advance all the apps by one frame duration
+    pub fn frame_step(&mut self) {
+        self.advance_time(self.frame_duration);
+        self.update();
+    }
+
+    /// Advance all the apps by one fixed timestep duration
+    pub fn tick_step(&mut self) {
+        self.advance_time(self.tick_duration);
+        self.update();
+    }
+
+    /// Advance all the apps by `n` fixed timestep durations
+    pub fn tick_steps(&mut self, n: usize) {
+        for _ in 0..n {
+            self.tick_step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::tests::protocol::*;
+
+    #[test]
+    fn test_stepper_multiple_clients() {
+        let mut stepper = Stepper::new(
+            StepperConfig {
+                num_clients: 2,
+                ..default()
+            },
+            |app| {
+                app.add_plugins(ProtocolPlugin);
+            },
+        );
+        stepper.init();
+        assert_eq!(stepper.server_connection_manager().connections.len(), 2);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(1.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        for i in 0..2 {
+            assert!(stepper
+                .client_connection_manager(i)
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .is_some());
+        }
+
+        // manual tick advancement
+        let tick = stepper.server_tick();
+        stepper.tick_steps(5);
+        assert_eq!(stepper.server_tick(), tick + 5);
+    }
+}
@@ -18,6 +18,7 @@ use crate::prelude::client::{
 use crate::prelude::server::{NetcodeConfig, ServerCommands, ServerConfig, ServerTransport};
 use crate::prelude::*;
 use crate::shared::time_manager::WrappedTime;
+use crate::tests::network::NetworkFabric;
 use crate::tests::protocol::*;
 use crate::transport::LOCAL_SOCKET;
 
@@ -29,6 +30,7 @@ pub struct HostServerStepper {
     pub client_app: App,
     // App for the host-server (client + server)
     pub server_app: App,
+    pub network: NetworkFabric,
     pub frame_duration: Duration,
     pub tick_duration: Duration,
     pub current_time: bevy::utils::Instant,
@@ -65,27 +67,18 @@ impl HostServerStepper {
         //     .with_max_level(tracing::Level::INFO)
         //     .init();
 
-        // Use local channels instead of UDP for testing
+        // Use an in-memory network instead of UDP for testing
         let addr = LOCAL_SOCKET;
-        // channels to receive a message from/to server
-        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
-        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
-        let mut client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
-            send: to_server_send,
-            recv: from_server_recv,
-        });
-
-        let mut server_io = server::IoConfig::from_transport(ServerTransport::Channels {
-            channels: vec![(addr, to_server_recv, from_server_send)],
-        });
-
         let NetConfig::Netcode { io, .. } = client_config.net.clone() else {
             panic!("Only Netcode transport is supported in tests");
         };
-        if let Some(conditioner) = io.conditioner {
-            server_io = server_io.with_conditioner(conditioner.clone());
-            client_io = client_io.with_conditioner(conditioner.clone());
-        }
+        // the network conditions are simulated by the network, with its virtual time
+        let network = NetworkFabric::default();
+        let (client_transport, server_channel) = network.connect(io.conditioner);
+        let client_io = client::IoConfig::from_transport(client_transport);
+        let server_io = server::IoConfig::from_transport(ServerTransport::Channels {
+            channels: vec![server_channel],
+        });
 
         // Shared config
         let protocol_id = 0;
@@ -119,6 +112,7 @@ impl HostServerStepper {
         host_server_client_config.shared = shared_host_server;
         host_server_client_config.net = NetConfig::Local {
             id: LOCAL_CLIENT_ID,
+            conditioner: None,
         };
         host_server_client_config.ping = PingConfig {
             // send pings every tick, so that the acks are received every frame
@@ -181,6 +175,7 @@ impl HostServerStepper {
         Self {
             client_app,
             server_app,
+            network,
             frame_duration,
             tick_duration: shared_config.tick.tick_duration,
             current_time: now,
@@ -306,18 +301,23 @@ impl HostServerStepper {
         self.server_app
             .insert_resource(TimeUpdateStrategy::ManualInstant(self.current_time));
         mock_instant::global::MockClock::advance(duration);
+        self.network.advance(duration);
     }
 
     /// Advance the world by one frame duration
     pub(crate) fn frame_step(&mut self) {
         self.advance_time(self.frame_duration);
         self.client_app.update();
+        self.network.deliver();
         self.server_app.update();
+        self.network.deliver();
     }
 
     pub(crate) fn tick_step(&mut self) {
         self.advance_time(self.tick_duration);
         self.client_app.update();
+        self.network.deliver();
         self.server_app.update();
+        self.network.deliver();
     }
 }
@@ -6,5 +6,6 @@ pub(crate) mod host_server_stepper;
 mod integration;
 
 pub(crate) mod multi_stepper;
+pub(crate) mod network;
 pub mod protocol;
 pub(crate) mod stepper;
@@ -19,6 +19,7 @@ use crate::prelude::client::{
 };
 use crate::prelude::server::{NetcodeConfig, ServerCommands, ServerConfig, ServerTransport};
 use crate::prelude::*;
+use crate::tests::network::NetworkFabric;
 use crate::tests::protocol::*;
 use crate::tests::stepper::BevyStepper;
 use crate::transport::LOCAL_SOCKET;
@@ -32,6 +33,7 @@ pub struct MultiBevyStepper {
     // second client will use udp
     pub client_app_2: App,
     pub server_app: App,
+    pub network: NetworkFabric,
     pub frame_duration: Duration,
     /// fixed timestep duration
     pub tick_duration: Duration,
@@ -90,18 +92,15 @@ impl MultiBevyStepper {
             client_id: TEST_CLIENT_ID_2,
         };
 
-        // client net config 1: use local channels
-        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
-        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
-        let client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
-            recv: from_server_recv,
-            send: to_server_send,
-        });
-        let client_params = (LOCAL_SOCKET, to_server_recv, from_server_send);
+        // the clients are connected to the server via an in-memory network
+        let network = NetworkFabric::default();
+
+        // client net config 1
+        let (client_transport, client_params) = network.connect(None);
         let net_config_1 = NetConfig::Netcode {
             auth: auth_1,
             config: client::NetcodeConfig::default(),
-            io: client_io,
+            io: client::IoConfig::from_transport(client_transport),
         };
 
         // TODO: maybe we don't need the server Channels transport and instead we can just have multiple
@@ -110,18 +109,12 @@ impl MultiBevyStepper {
             channels: vec![client_params],
         });
 
-        // client net config 2: use local channels
-        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
-        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
-        let client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
-            recv: from_server_recv,
-            send: to_server_send,
-        });
-        let client_params = (LOCAL_SOCKET, to_server_recv, from_server_send);
+        // client net config 2
+        let (client_transport, client_params) = network.connect(None);
         let net_config_2 = NetConfig::Netcode {
             auth: auth_2,
             config: client::NetcodeConfig::default(),
-            io: client_io,
+            io: client::IoConfig::from_transport(client_transport),
         };
 
         let server_io_2 = server::IoConfig::from_transport(ServerTransport::Channels {
@@ -184,6 +177,7 @@ impl MultiBevyStepper {
             client_app_1: build_client(net_config_1),
             client_app_2: build_client(net_config_2),
             server_app,
+            network,
             frame_duration,
             tick_duration: shared_config.tick.tick_duration,
             current_time: now,
@@ -235,6 +229,7 @@ impl MultiBevyStepper {
         self.server_app
             .insert_resource(TimeUpdateStrategy::ManualInstant(self.current_time));
         mock_instant::global::MockClock::advance(duration);
+        self.network.advance(duration);
     }
 
     pub(crate) fn flush(&mut self) {
@@ -248,19 +243,17 @@ impl MultiBevyStepper {
         self.advance_time(self.frame_duration);
         self.client_app_1.update();
         self.client_app_2.update();
-        // sleep a bit to make sure that local io receives the packets
-        std::thread::sleep(Duration::from_millis(1));
+        self.network.deliver();
         self.server_app.update();
-        std::thread::sleep(Duration::from_millis(1));
+        self.network.deliver();
     }
 
     pub(crate) fn tick_step(&mut self) {
         self.advance_time(self.tick_duration);
         self.client_app_1.update();
         self.client_app_2.update();
-        // sleep a bit to make sure that local io receives the packets
-        std::thread::sleep(Duration::from_millis(1));
+        self.network.deliver();
         self.server_app.update();
-        std::thread::sleep(Duration::from_millis(1));
+        self.network.deliver();
     }
 }
@@ -0,0 +1,227 @@
+//! Deterministic in-memory network used to connect the apps of the test steppers.
+//!
+//! The apps exchange packets through crossbeam channels, but the packets are not forwarded directly:
+//! the [`NetworkFabric`] holds every packet until it is delivered by [`NetworkFabric::deliver`].
+//! Latency, jitter and packet loss are simulated with the [`LinkConditionerConfig`] of each link, using the
+//! virtual time of the fabric (advanced with [`NetworkFabric::advance`]) and a seeded random number generator,
+//! so that the tests don't depend on the wall clock or on the order in which parallel tests run.
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::Resource;
+use bevy::utils::Duration;
+use crossbeam_channel::{Receiver, Sender};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::prelude::client::ClientTransport;
+use crate::prelude::LinkConditionerConfig;
+use crate::utils::ready_buffer::ReadyBuffer;
+
+/// Packets sent on the server by one client connection: (address of the client, receiver, sender)
+pub(crate) type ServerChannel = (SocketAddr, Receiver<Vec<u8>>, Sender<Vec<u8>>);
+
+/// One direction of a connection between two apps
+struct Link {
+    recv: Receiver<Vec<u8>>,
+    send: Sender<Vec<u8>>,
+    conditions: Option<LinkConditionerConfig>,
+    /// Packets in flight, keyed by delivery time and by send order (to keep the order of packets
+    /// that are delivered at the same time)
+    in_flight: ReadyBuffer<(Duration, u64), Vec<u8>>,
+}
+
+struct Fabric {
+    now: Duration,
+    rng: StdRng,
+    num_packets: u64,
+    /// pairs of (client to server, server to client) links
+    connections: Vec<[Link; 2]>,
+}
+
+impl Fabric {
+    /// Compute when a packet sent now should be delivered, or None if the packet is lost
+    fn delivery_time(&mut self, conditions: &Option<LinkConditionerConfig>) -> Option<Duration> {
+        let Some(conditions) = conditions else {
+            return Some(self.now);
+        };
+        if self.rng.gen_range(0.0..1.0) < conditions.incoming_loss {
+            return None;
+        }
+        let mut latency = conditions.incoming_latency.as_secs_f32();
+        if conditions.incoming_jitter > Duration::default() {
+            let jitter = conditions.incoming_jitter.as_secs_f32();
+            latency += self.rng.gen_range(-jitter..jitter);
+        }
+        Some(self.now + Duration::from_secs_f32(latency.max(0.0)))
+    }
+
+    fn deliver(&mut self) {
+        for i in 0..self.connections.len() {
+            for direction in 0..2 {
+                // put the newly sent packets in flight
+                while let Ok(packet) = self.connections[i][direction].recv.try_recv() {
+                    let conditions = self.connections[i][direction].conditions.clone();
+                    if let Some(delivery_time) = self.delivery_time(&conditions) {
+                        self.num_packets += 1;
+                        self.connections[i][direction]
+                            .in_flight
+                            .push((delivery_time, self.num_packets), packet);
+                    }
+                }
+                // deliver the packets that have arrived
+                let link = &mut self.connections[i][direction];
+                while let Some((_, packet)) = link.in_flight.pop_item(&(self.now, u64::MAX)) {
+                    let _ = link.send.try_send(packet);
+                }
+            }
+        }
+    }
+}
+
+/// In-memory network that connects the client apps to the server app
+#[derive(Resource, Clone)]
+pub(crate) struct NetworkFabric(Arc<Mutex<Fabric>>);
+
+impl Default for NetworkFabric {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl NetworkFabric {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(Arc::new(Mutex::new(Fabric {
+            now: Duration::default(),
+            rng: StdRng::seed_from_u64(seed),
+            num_packets: 0,
+            connections: vec![],
+        })))
+    }
+
+    /// Create a new connection between a client and the server.
+    ///
+    /// Returns the transport of the client, and the channel to add to the server's
+    /// [`ServerTransport::Channels`](crate::prelude::server::ServerTransport::Channels). The conditions are
+    /// applied in both directions.
+    pub(crate) fn connect(
+        &self,
+        conditions: Option<LinkConditionerConfig>,
+    ) -> (ClientTransport, ServerChannel) {
+        let mut fabric = self.0.lock().unwrap();
+        let (client_send, client_to_fabric) = crossbeam_channel::unbounded();
+        let (fabric_to_server, server_recv) = crossbeam_channel::unbounded();
+        let (server_send, server_to_fabric) = crossbeam_channel::unbounded();
+        let (fabric_to_client, client_recv) = crossbeam_channel::unbounded();
+        let link = |recv, send| Link {
+            recv,
+            send,
+            conditions: conditions.clone(),
+            in_flight: ReadyBuffer::new(),
+        };
+        fabric.connections.push([
+            link(client_to_fabric, fabric_to_server),
+            link(server_to_fabric, fabric_to_client),
+        ]);
+        // every client gets a distinct address, so that the server can tell them apart
+        let client_addr = SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            fabric.connections.len() as u16,
+        );
+        (
+            ClientTransport::LocalChannel {
+                recv: client_recv,
+                send: client_send,
+            },
+            (client_addr, server_recv, server_send),
+        )
+    }
+
+    /// Update the network conditions of a connection (in the order in which they were created)
+    pub(crate) fn set_conditions(
+        &self,
+        connection: usize,
+        conditions: Option<LinkConditionerConfig>,
+    ) {
+        let mut fabric = self.0.lock().unwrap();
+        for link in fabric.connections[connection].iter_mut() {
+            link.conditions = conditions.clone();
+        }
+    }
+
+    /// Advance the virtual time of the network
+    pub(crate) fn advance(&self, duration: Duration) {
+        self.0.lock().unwrap().now += duration;
+    }
+
+    /// Deliver all the packets that have reached their destination
+    pub(crate) fn deliver(&self) {
+        self.0.lock().unwrap().deliver();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::LOCAL_SOCKET;
+
+    fn split(transport: ClientTransport) -> (Sender<Vec<u8>>, Receiver<Vec<u8>>) {
+        let ClientTransport::LocalChannel { send, recv } = transport else {
+            unreachable!()
+        };
+        (send, recv)
+    }
+
+    #[test]
+    fn test_latency() {
+        let network = NetworkFabric::new(0);
+        let conditions = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(50),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        };
+        let (client, (addr, server_recv, server_send)) = network.connect(Some(conditions));
+        assert_ne!(addr, LOCAL_SOCKET);
+        let (client_send, client_recv) = split(client);
+
+        client_send.send(vec![1]).unwrap();
+        client_send.send(vec![2]).unwrap();
+        network.deliver();
+        assert!(server_recv.try_recv().is_err());
+
+        network.advance(Duration::from_millis(50));
+        network.deliver();
+        // packets sent at the same time keep their order
+        assert_eq!(server_recv.try_recv(), Ok(vec![1]));
+        assert_eq!(server_recv.try_recv(), Ok(vec![2]));
+
+        server_send.send(vec![3]).unwrap();
+        network.deliver();
+        assert!(client_recv.try_recv().is_err());
+        network.advance(Duration::from_millis(50));
+        network.deliver();
+        assert_eq!(client_recv.try_recv(), Ok(vec![3]));
+    }
+
+    #[test]
+    fn test_loss_is_deterministic() {
+        let conditions = LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.5,
+        };
+        let received = || {
+            let network = NetworkFabric::new(42);
+            let (client, (_, server_recv, _)) = network.connect(Some(conditions.clone()));
+            let (client_send, _) = split(client);
+            for i in 0..100 {
+                client_send.send(vec![i]).unwrap();
+            }
+            network.deliver();
+            server_recv.try_iter().collect::<Vec<_>>()
+        };
+        let first = received();
+        assert!(!first.is_empty() && first.len() < 100);
+        assert_eq!(first, received());
+    }
+}
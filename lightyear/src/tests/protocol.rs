@@ -2,11 +2,11 @@ use std::ops::{Add, Mul};
 
 use bevy::app::{App, Plugin};
 use bevy::ecs::entity::MapEntities;
-use bevy::prelude::{default, Component, Entity, EntityMapper, Reflect, Resource};
-use bevy::utils::HashSet;
+use bevy::prelude::{default, Component, Entity, EntityMapper, Event, Reflect, Resource};
+use bevy::utils::{Duration, HashSet};
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 use cfg_if::cfg_if;
-use lightyear_macros::ChannelInternal;
+use lightyear_macros::{ChannelInternal, DeltaReplicateInternal};
 use serde::{Deserialize, Serialize};
 
 use crate::client::components::ComponentSyncMode;
@@ -30,8 +30,12 @@ impl MapEntities for EntityMessage {
     }
 }
 
+// Events
+#[derive(Event, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Event1(pub u32);
+
 // Components
-#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Reflect)]
 pub struct ComponentSyncModeFull(pub f32);
 
 impl Mul<f32> for &ComponentSyncModeFull {
@@ -100,6 +104,9 @@ impl Add<Self> for ComponentSyncModeFull2 {
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct ComponentDeltaCompression(pub Vec<usize>);
 
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentGeneric<T>(pub T);
+
 // NOTE: for the delta-compression to work, the components must have the same prefix, starting with [1]
 impl Diffable for ComponentDeltaCompression {
     // const IDEMPOTENT: bool = false;
@@ -143,6 +150,24 @@ impl Diffable for ComponentDeltaCompression2 {
     }
 }
 
+/// Component where only the fields that changed are replicated
+#[derive(
+    Component,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Reflect,
+    DeltaReplicateInternal,
+)]
+pub struct ComponentDeltaFields {
+    pub health: u32,
+    pub name: String,
+    pub position: (f32, f32),
+}
+
 #[derive(Component, Clone, Debug, PartialEq, Reflect)]
 pub struct ComponentRollback(pub f32);
 
@@ -150,6 +175,10 @@ pub struct ComponentRollback(pub f32);
 #[derive(Resource, Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
 pub struct Resource1(pub f32);
 
+/// Resource that is predicted on the client
+#[derive(Resource, Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
+pub struct ResourcePredicted(pub f32);
+
 /// Resource where we provide our own serialization/deserialization functions
 #[derive(Resource, Debug, PartialEq, Clone, Reflect)]
 pub struct Resource2(pub f32);
@@ -204,6 +233,8 @@ impl Plugin for ProtocolPlugin {
         app.register_message::<StringMessage>(ChannelDirection::Bidirectional);
         app.register_message::<EntityMessage>(ChannelDirection::Bidirectional)
             .add_map_entities();
+        // events
+        app.register_event::<Event1>(ChannelDirection::Bidirectional);
         // inputs
         app.add_plugins(InputPlugin::<MyInput>::default());
         // components
@@ -220,7 +251,8 @@ impl Plugin for ProtocolPlugin {
                 serialize_map_entities: None,
             },
         )
-        .add_prediction(ComponentSyncMode::Simple);
+        .add_prediction(ComponentSyncMode::Simple)
+        .add_tombstones(Duration::from_secs(1));
 
         app.register_component::<ComponentSyncModeOnce>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Once);
@@ -240,10 +272,20 @@ impl Plugin for ProtocolPlugin {
         app.register_component::<ComponentDeltaCompression2>(ChannelDirection::ServerToClient)
             .add_delta_compression();
 
+        app.register_component::<ComponentDeltaFields>(ChannelDirection::ServerToClient)
+            .add_delta_compression();
+
+        app.register_components::<(ComponentGeneric<u32>, ComponentGeneric<f32>)>(
+            ChannelDirection::ServerToClient,
+        )
+        .add_prediction(ComponentSyncMode::Simple);
+
         app.add_rollback::<ComponentRollback>();
 
         // resources
         app.register_resource::<Resource1>(ChannelDirection::ServerToClient);
+        app.register_resource::<ResourcePredicted>(ChannelDirection::ServerToClient);
+        app.add_resource_prediction::<ResourcePredicted>();
         app.register_resource_custom_serde::<Resource2>(
             ChannelDirection::Bidirectional,
             SerializeFns {
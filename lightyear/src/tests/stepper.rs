@@ -17,6 +17,7 @@ use crate::prelude::client::{
 use crate::prelude::server::{NetcodeConfig, ServerCommands, ServerConfig, ServerTransport};
 use crate::prelude::*;
 use crate::shared::time_manager::WrappedTime;
+use crate::tests::network::NetworkFabric;
 use crate::tests::protocol::*;
 use crate::transport::LOCAL_SOCKET;
 
@@ -25,6 +26,7 @@ pub const TEST_CLIENT_ID: u64 = 111;
 pub struct BevyStepper {
     pub client_app: App,
     pub server_app: App,
+    pub network: NetworkFabric,
     pub frame_duration: Duration,
     /// fixed timestep duration
     pub tick_duration: Duration,
@@ -58,27 +60,18 @@ impl BevyStepper {
         //     .with_max_level(tracing::Level::INFO)
         //     .init();
 
-        // Use local channels instead of UDP for testing
+        // Use an in-memory network instead of UDP for testing
         let addr = LOCAL_SOCKET;
-        // channels to receive a message from/to server
-        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
-        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
-        let mut client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
-            send: to_server_send,
-            recv: from_server_recv,
-        });
-
-        let mut server_io = server::IoConfig::from_transport(ServerTransport::Channels {
-            channels: vec![(addr, to_server_recv, from_server_send)],
-        });
-
         let NetConfig::Netcode { io, .. } = client_config.net else {
             panic!("Only Netcode transport is supported in tests");
         };
-        if let Some(conditioner) = io.conditioner {
-            server_io = server_io.with_conditioner(conditioner.clone());
-            client_io = client_io.with_conditioner(conditioner.clone());
-        }
+        // the network conditions are simulated by the network, with its virtual time
+        let network = NetworkFabric::default();
+        let (client_transport, server_channel) = network.connect(io.conditioner);
+        let client_io = client::IoConfig::from_transport(client_transport);
+        let server_io = server::IoConfig::from_transport(ServerTransport::Channels {
+            channels: vec![server_channel],
+        });
 
         // Shared config
         let protocol_id = 0;
@@ -156,6 +149,7 @@ impl BevyStepper {
         Self {
             client_app,
             server_app,
+            network,
             frame_duration,
             tick_duration: shared_config.tick.tick_duration,
             current_time: now,
@@ -277,6 +271,7 @@ impl BevyStepper {
         self.server_app
             .insert_resource(TimeUpdateStrategy::ManualInstant(self.current_time));
         mock_instant::global::MockClock::advance(duration);
+        self.network.advance(duration);
     }
 
     pub(crate) fn flush(&mut self) {
@@ -288,12 +283,16 @@ impl BevyStepper {
     pub(crate) fn frame_step(&mut self) {
         self.advance_time(self.frame_duration);
         self.client_app.update();
+        self.network.deliver();
         self.server_app.update();
+        self.network.deliver();
     }
 
     pub(crate) fn tick_step(&mut self) {
         self.advance_time(self.tick_duration);
         self.client_app.update();
+        self.network.deliver();
         self.server_app.update();
+        self.network.deliver();
     }
 }
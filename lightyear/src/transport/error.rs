@@ -12,6 +12,9 @@ pub enum Error {
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
     #[error(transparent)]
     WebSocket(#[from] tokio_tungstenite::tungstenite::error::Error),
+    #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
+    #[error(transparent)]
+    Tls(#[from] tokio_rustls::rustls::Error),
     #[error("could not send message via channel: {0}")]
     Channel(String),
     #[error("requested by user")]
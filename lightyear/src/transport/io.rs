@@ -31,6 +31,8 @@ pub struct IoStats {
     pub bytes_received: usize,
     pub packets_sent: usize,
     pub packets_received: usize,
+    /// Number of packets that the transport failed to send (socket buffer full, datagram too big, etc.)
+    pub send_errors: usize,
 }
 
 impl<T: Send + Sync> BaseIo<T> {
@@ -80,9 +82,18 @@ impl<T: Send + Sync> PacketSender for BaseIo<T> {
             metrics::counter!("transport.packets_sent").increment(1);
             metrics::gauge!("transport.bytes_sent").increment(payload.len() as f64);
         }
-        self.stats.bytes_sent += payload.len();
-        self.stats.packets_sent += 1;
-        self.sender.as_mut().send(payload, address)
+        self.sender
+            .as_mut()
+            .send(payload, address)
+            .inspect(|_| {
+                self.stats.bytes_sent += payload.len();
+                self.stats.packets_sent += 1;
+            })
+            .inspect_err(|_| {
+                #[cfg(feature = "metrics")]
+                metrics::counter!("transport.send_errors").increment(1);
+                self.stats.send_errors += 1;
+            })
     }
 }
 
@@ -98,6 +109,8 @@ impl IoDiagnosticsPlugin {
     pub const PACKETS_IN: DiagnosticPath = DiagnosticPath::const_new("packets received per second");
     /// How many bytes do we send per second
     pub const PACKETS_OUT: DiagnosticPath = DiagnosticPath::const_new("packets sent per second");
+    /// How many packets per second the transport failed to send
+    pub const SEND_ERRORS: DiagnosticPath = DiagnosticPath::const_new("send errors per second");
 
     /// Max diagnostic history length.
     pub const DIAGNOSTIC_HISTORY_LEN: usize = 60;
@@ -123,6 +136,9 @@ impl IoDiagnosticsPlugin {
         diagnostics.add_measurement(&Self::PACKETS_OUT, || {
             stats.packets_sent as f64 / delta_seconds
         });
+        diagnostics.add_measurement(&Self::SEND_ERRORS, || {
+            stats.send_errors as f64 / delta_seconds
+        });
         *stats = IoStats::default()
     }
 }
@@ -145,6 +161,10 @@ impl Plugin for IoDiagnosticsPlugin {
             Diagnostic::new(IoDiagnosticsPlugin::PACKETS_OUT)
                 .with_max_history_length(IoDiagnosticsPlugin::DIAGNOSTIC_HISTORY_LEN),
         );
+        app.register_diagnostic(
+            Diagnostic::new(IoDiagnosticsPlugin::SEND_ERRORS)
+                .with_max_history_length(IoDiagnosticsPlugin::DIAGNOSTIC_HISTORY_LEN),
+        );
     }
 }
 
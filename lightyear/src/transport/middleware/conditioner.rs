@@ -40,6 +40,8 @@ pub(crate) struct LinkConditioner<P: Eq> {
     config: LinkConditionerConfig,
     pub time_queue: ReadyBuffer<Instant, P>,
     last_packet: Option<P>,
+    /// Release time of the latest packet that must not be reordered
+    last_ordered_timestamp: Option<Instant>,
 }
 
 impl<P: Eq> LinkConditioner<P> {
@@ -48,30 +50,49 @@ impl<P: Eq> LinkConditioner<P> {
             config,
             time_queue: ReadyBuffer::new(),
             last_packet: None,
+            last_ordered_timestamp: None,
         }
     }
 
     /// Add latency/jitter/loss to a packet
-    fn condition_packet(&mut self, packet: P) {
+    pub(crate) fn condition_packet(&mut self, packet: P) {
         let mut rng = thread_rng();
         if rng.gen_range(0.0..1.0) <= self.config.incoming_loss {
             return;
         }
+        let packet_timestamp = self.packet_timestamp();
+        self.time_queue.push(packet_timestamp, packet);
+    }
+
+    /// Add latency/jitter to a packet, without dropping it.
+    ///
+    /// The packet will not be returned before any other packet that was added with this method.
+    pub(crate) fn condition_packet_ordered(&mut self, packet: P) {
+        let mut packet_timestamp = self.packet_timestamp();
+        if let Some(last) = self.last_ordered_timestamp {
+            // the heap does not preserve the insertion order of packets with the same timestamp
+            packet_timestamp = packet_timestamp.max(last + Duration::from_nanos(1));
+        }
+        self.last_ordered_timestamp = Some(packet_timestamp);
+        self.time_queue.push(packet_timestamp, packet);
+    }
+
+    fn packet_timestamp(&self) -> Instant {
         let mut latency: i32 = self.config.incoming_latency.as_millis() as i32;
         // TODO: how can i use the virtual time here?
         let mut packet_timestamp = Instant::now();
         if self.config.incoming_jitter > Duration::default() {
             let jitter: i32 = self.config.incoming_jitter.as_millis() as i32;
-            latency += rng.gen_range(-jitter..jitter);
+            latency += thread_rng().gen_range(-jitter..jitter);
         }
         if latency > 0 {
             packet_timestamp += Duration::from_millis(latency as u64);
         }
-        self.time_queue.push(packet_timestamp, packet);
+        packet_timestamp
     }
 
     /// Check if a packet is ready to be returned
-    fn pop_packet(&mut self) -> Option<P> {
+    pub(crate) fn pop_packet(&mut self) -> Option<P> {
         self.time_queue
             .pop_item(&Instant::now())
             .map(|(_, packet)| packet)
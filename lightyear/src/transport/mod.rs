@@ -13,6 +13,8 @@ use crate::server::io::transport::ServerTransportEnum;
 use crate::transport::channels::Channels;
 use crate::transport::dummy::DummyIo;
 use crate::transport::local::LocalChannel;
+#[cfg(not(target_family = "wasm"))]
+use crate::transport::tcp::{TcpClientSocket, TcpServerSocket};
 use crate::transport::udp::UdpSocket;
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::{WebSocketClientSocket, WebSocketClientSocketBuilder};
@@ -36,6 +38,10 @@ pub(crate) mod local;
 /// The transport is a UDP socket
 pub(crate) mod udp;
 
+/// The transport is a TCP stream (fallback for networks that block UDP)
+#[cfg(not(target_family = "wasm"))]
+pub(crate) mod tcp;
+
 /// The transport is a map of channels (used for server, during testing)
 pub(crate) mod channels;
 
@@ -0,0 +1,414 @@
+//! The transport is a plain TCP stream, with each packet prefixed by its length.
+//!
+//! This is meant as a last-resort fallback for networks that block UDP entirely (for example some
+//! corporate or school networks): put it at the end of
+//! [`ClientConfig::transport_fallbacks`](crate::client::config::ClientConfig::transport_fallbacks)
+//! so that it is only used if the other transports fail to connect.
+//!
+//! TCP retransmits lost packets and delivers them in order, so a single lost packet delays every packet
+//! sent after it (head-of-line blocking). The latency is worse than with UDP as soon as there is any packet loss.
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use bevy::utils::Duration;
+use tracing::{info, trace, warn};
+
+use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+use crate::client::io::{ClientIoEvent, ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::error::{Error, Result};
+use crate::transport::io::IoState;
+use crate::transport::{
+    BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, LOCAL_SOCKET, MTU,
+};
+
+/// Number of bytes used to write the length of each packet
+const HEADER_LEN: usize = 2;
+
+/// How long the client waits for the TCP connection to be established
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A non-blocking TCP stream that sends and receives length-prefixed packets
+struct FramedStream {
+    stream: TcpStream,
+    /// Bytes received that don't form a full packet yet
+    read_buffer: Vec<u8>,
+    /// Bytes that could not be written to the stream yet
+    write_buffer: Vec<u8>,
+}
+
+impl FramedStream {
+    fn new(stream: TcpStream) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        // packets should be sent as soon as possible instead of being coalesced
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            read_buffer: Vec::new(),
+            write_buffer: Vec::new(),
+        })
+    }
+
+    /// Queue a packet and write as much as possible to the stream
+    fn send(&mut self, payload: &[u8]) -> std::io::Result<()> {
+        let len = u16::try_from(payload.len())
+            .map_err(|_| std::io::Error::new(ErrorKind::InvalidInput, "packet too large"))?;
+        self.write_buffer.extend_from_slice(&len.to_be_bytes());
+        self.write_buffer.extend_from_slice(payload);
+        self.flush()
+    }
+
+    /// Write the queued bytes until the stream would block
+    fn flush(&mut self) -> std::io::Result<()> {
+        while !self.write_buffer.is_empty() {
+            match self.stream.write(&self.write_buffer) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.write_buffer.drain(..written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the next packet into `buffer`, and return its length.
+    ///
+    /// Returns Ok(None) if no full packet is available yet.
+    fn recv(&mut self, buffer: &mut [u8; MTU]) -> std::io::Result<Option<usize>> {
+        self.flush()?;
+        if self.next_packet_len().is_none() {
+            let mut chunk = [0; MTU];
+            loop {
+                match self.stream.read(&mut chunk) {
+                    Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                    Ok(read) => self.read_buffer.extend_from_slice(&chunk[..read]),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        let Some(len) = self.next_packet_len() else {
+            return Ok(None);
+        };
+        if len > MTU {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "received a packet larger than the MTU",
+            ));
+        }
+        if self.read_buffer.len() < HEADER_LEN + len {
+            return Ok(None);
+        }
+        buffer[..len].copy_from_slice(&self.read_buffer[HEADER_LEN..HEADER_LEN + len]);
+        self.read_buffer.drain(..HEADER_LEN + len);
+        Ok(Some(len))
+    }
+
+    fn next_packet_len(&self) -> Option<usize> {
+        let header = self.read_buffer.get(..HEADER_LEN)?;
+        Some(u16::from_be_bytes([header[0], header[1]]) as usize)
+    }
+}
+
+pub(crate) struct TcpClientSocketBuilder {
+    pub(crate) server_addr: SocketAddr,
+}
+
+impl ClientTransportBuilder for TcpClientSocketBuilder {
+    fn connect(
+        self,
+    ) -> Result<(
+        ClientTransportEnum,
+        IoState,
+        Option<ClientIoEventReceiver>,
+        Option<ClientNetworkEventSender>,
+    )> {
+        warn!(
+            server_addr = ?self.server_addr,
+            "Connecting over TCP: the latency will be higher than with UDP because of head-of-line blocking"
+        );
+        let state = Arc::new(Mutex::new(TcpClientState {
+            stream: None,
+            remote_addr: self.server_addr,
+        }));
+        let (status_tx, status_rx) = async_channel::bounded(1);
+        // connect in a separate thread to avoid blocking the app until the connection is established
+        let connecting_state = state.clone();
+        std::thread::spawn(move || {
+            let event = match TcpStream::connect_timeout(&self.server_addr, CONNECT_TIMEOUT)
+                .and_then(FramedStream::new)
+            {
+                Ok(stream) => {
+                    info!("TCP connection established");
+                    connecting_state.lock().unwrap().stream = Some(stream);
+                    ClientIoEvent::Connected
+                }
+                Err(e) => ClientIoEvent::Disconnected(e.into()),
+            };
+            let _ = status_tx.send_blocking(event);
+        });
+        Ok((
+            ClientTransportEnum::TcpClient(TcpClientSocket {
+                sender: TcpClientSocketSender {
+                    state: state.clone(),
+                },
+                receiver: TcpClientSocketReceiver {
+                    state,
+                    buffer: [0; MTU],
+                },
+            }),
+            IoState::Connecting,
+            Some(ClientIoEventReceiver(status_rx)),
+            None,
+        ))
+    }
+}
+
+struct TcpClientState {
+    /// The stream is None until the connection is established
+    stream: Option<FramedStream>,
+    /// The address that the packets are sent to. The received packets are reported as coming from this address,
+    /// so that the connection layer sees the same address as the one in its connect token.
+    remote_addr: SocketAddr,
+}
+
+pub struct TcpClientSocket {
+    sender: TcpClientSocketSender,
+    receiver: TcpClientSocketReceiver,
+}
+
+impl Transport for TcpClientSocket {
+    fn local_addr(&self) -> SocketAddr {
+        self.sender
+            .state
+            .lock()
+            .unwrap()
+            .stream
+            .as_ref()
+            .and_then(|framed| framed.stream.local_addr().ok())
+            .unwrap_or(LOCAL_SOCKET)
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(self.sender), Box::new(self.receiver))
+    }
+}
+
+struct TcpClientSocketSender {
+    state: Arc<Mutex<TcpClientState>>,
+}
+
+impl PacketSender for TcpClientSocketSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.remote_addr = *address;
+        let Some(stream) = state.stream.as_mut() else {
+            // the packets sent while connecting are dropped, the connection layer will send them again
+            trace!("Dropping packet sent before the TCP connection is established");
+            return Ok(());
+        };
+        stream.send(payload)?;
+        Ok(())
+    }
+}
+
+struct TcpClientSocketReceiver {
+    state: Arc<Mutex<TcpClientState>>,
+    buffer: [u8; MTU],
+}
+
+impl PacketReceiver for TcpClientSocketReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let mut state = self.state.lock().unwrap();
+        let remote_addr = state.remote_addr;
+        let Some(stream) = state.stream.as_mut() else {
+            return Ok(None);
+        };
+        match stream.recv(&mut self.buffer)? {
+            Some(len) => Ok(Some((&mut self.buffer[..len], remote_addr))),
+            None => Ok(None),
+        }
+    }
+}
+
+pub(crate) struct TcpServerSocketBuilder {
+    pub(crate) server_addr: SocketAddr,
+}
+
+impl ServerTransportBuilder for TcpServerSocketBuilder {
+    fn start(
+        self,
+    ) -> Result<(
+        ServerTransportEnum,
+        IoState,
+        Option<ServerIoEventReceiver>,
+        Option<ServerNetworkEventSender>,
+    )> {
+        let listener = TcpListener::bind(self.server_addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        info!("Listening for TCP connections on {}", local_addr);
+        let state = Arc::new(Mutex::new(TcpServerState {
+            listener,
+            streams: HashMap::new(),
+        }));
+        Ok((
+            ServerTransportEnum::TcpServer(TcpServerSocket {
+                local_addr,
+                sender: TcpServerSocketSender {
+                    state: state.clone(),
+                },
+                receiver: TcpServerSocketReceiver {
+                    state,
+                    buffer: [0; MTU],
+                },
+            }),
+            IoState::Connected,
+            None,
+            None,
+        ))
+    }
+}
+
+struct TcpServerState {
+    listener: TcpListener,
+    /// The stream of each connected client, identified by its address
+    streams: HashMap<SocketAddr, FramedStream>,
+}
+
+impl TcpServerState {
+    /// Accept the pending connections
+    fn accept(&mut self) -> std::io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    trace!(?addr, "Accepted TCP connection");
+                    self.streams.insert(addr, FramedStream::new(stream)?);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+pub struct TcpServerSocket {
+    local_addr: SocketAddr,
+    sender: TcpServerSocketSender,
+    receiver: TcpServerSocketReceiver,
+}
+
+impl Transport for TcpServerSocket {
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn split(self) -> (BoxedSender, BoxedReceiver) {
+        (Box::new(self.sender), Box::new(self.receiver))
+    }
+}
+
+struct TcpServerSocketSender {
+    state: Arc<Mutex<TcpServerState>>,
+}
+
+impl PacketSender for TcpServerSocketSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let Some(stream) = state.streams.get_mut(address) else {
+            return Err(Error::NotConnected);
+        };
+        if let Err(e) = stream.send(payload) {
+            // the client will be disconnected by the connection layer once it times out
+            state.streams.remove(address);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+struct TcpServerSocketReceiver {
+    state: Arc<Mutex<TcpServerState>>,
+    buffer: [u8; MTU],
+}
+
+impl PacketReceiver for TcpServerSocketReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let mut state = self.state.lock().unwrap();
+        state.accept()?;
+        let mut received = None;
+        let mut closed = vec![];
+        for (addr, stream) in state.streams.iter_mut() {
+            match stream.recv(&mut self.buffer) {
+                Ok(Some(len)) => {
+                    received = Some((len, *addr));
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    trace!(?addr, "TCP connection closed: {:?}", e);
+                    closed.push(*addr);
+                }
+            }
+        }
+        for addr in closed {
+            state.streams.remove(&addr);
+        }
+        Ok(received.map(|(len, addr)| (&mut self.buffer[..len], addr)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_tcp_socket() -> Result<()> {
+        let server_addr = SocketAddr::from_str("127.0.0.1:0").unwrap();
+        let (server_socket, _, _, _) = TcpServerSocketBuilder { server_addr }.start()?;
+        let server_addr = server_socket.local_addr();
+        let (client_socket, _, status_rx, _) = TcpClientSocketBuilder { server_addr }.connect()?;
+        assert!(matches!(
+            status_rx.unwrap().recv_blocking().unwrap(),
+            ClientIoEvent::Connected
+        ));
+        let (mut server_send, mut server_recv) = server_socket.split();
+        let (mut client_send, mut client_recv) = client_socket.split();
+
+        // several packets sent at once are received separately
+        client_send.send(b"hello", &server_addr)?;
+        client_send.send(b"world", &server_addr)?;
+        let mut packets = vec![];
+        let mut client_addr = None;
+        for _ in 0..100 {
+            if let Some((packet, addr)) = server_recv.recv()? {
+                packets.push(packet.to_vec());
+                client_addr = Some(addr);
+            }
+            if packets.len() == 2 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(packets, vec![b"hello".to_vec(), b"world".to_vec()]);
+
+        // the client reports the packets as coming from the address it sends to
+        server_send.send(b"pong", &client_addr.unwrap())?;
+        for _ in 0..100 {
+            if let Some((packet, addr)) = client_recv.recv()? {
+                assert_eq!(packet, b"pong");
+                assert_eq!(addr, server_addr);
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the client did not receive the packet");
+    }
+}
@@ -22,7 +22,7 @@ use tokio::{
     },
 };
 use tokio_tungstenite::{
-    connect_async, connect_async_with_config, tungstenite::Message, MaybeTlsStream,
+    client_async_tls_with_config, connect_async, tungstenite::Message, MaybeTlsStream,
 };
 use tracing::{debug, info, trace};
 use tracing_log::log::error;
@@ -37,6 +37,8 @@ use crate::transport::{
 
 pub(crate) struct WebSocketClientSocketBuilder {
     pub(crate) server_addr: SocketAddr,
+    /// If provided, connect using TLS (`wss://`) and validate the server certificate against this domain
+    pub(crate) tls_domain: Option<String>,
 }
 
 impl ClientTransportBuilder for WebSocketClientSocketBuilder {
@@ -63,12 +65,18 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
 
         IoTaskPool::get()
             .spawn(Compat::new(async move {
-                let ws_stream = match connect_async_with_config(
-                    format!("ws://{}/", self.server_addr),
-                    None,
-                    true,
-                )
-                .await
+                let url = match &self.tls_domain {
+                    Some(domain) => format!("wss://{}:{}/", domain, self.server_addr.port()),
+                    None => format!("ws://{}/", self.server_addr),
+                };
+                // connect to the server_addr directly; the domain in the url is only used to validate
+                // the server certificate
+                let ws_stream = match TcpStream::connect(self.server_addr)
+                    .map_err(Error::from)
+                    .and_then(|stream| {
+                        client_async_tls_with_config(url, stream, None, None).map_err(Error::from)
+                    })
+                    .await
                 {
                     Ok((ws_stream, _)) => ws_stream,
                     Err(e) => {
@@ -142,20 +150,6 @@ pub struct WebSocketClientSocket {
     receiver: WebSocketClientSocketReceiver,
 }
 
-impl WebSocketClientSocket {
-    /*fn get_tls_connector(&self) -> TlsConnector {
-        let root_store = RootCertStore {
-            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
-        };
-
-        let config = ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-
-        TlsConnector::from(Arc::new(config))
-    }*/
-}
-
 impl Transport for WebSocketClientSocket {
     fn local_addr(&self) -> SocketAddr {
         // TODO: get the local_addr
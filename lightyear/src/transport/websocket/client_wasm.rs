@@ -27,6 +27,8 @@ use crate::transport::{
 
 pub(crate) struct WebSocketClientSocketBuilder {
     pub(crate) server_addr: SocketAddr,
+    /// If provided, connect using TLS (`wss://`); the browser validates the server certificate against this domain
+    pub(crate) tls_domain: Option<String>,
 }
 
 impl ClientTransportBuilder for WebSocketClientSocketBuilder {
@@ -52,7 +54,11 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
 
         info!("Starting client websocket task");
 
-        let ws = WebSocket::new(&format!("ws://{}/", self.server_addr))
+        let url = match &self.tls_domain {
+            Some(domain) => format!("wss://{}:{}/", domain, self.server_addr.port()),
+            None => format!("ws://{}/", self.server_addr),
+        };
+        let ws = WebSocket::new(&url)
             .map_err(|e| Error::Io(std::io::Error::other("could not create websocket")))?;
 
         ws.set_binary_type(BinaryType::Arraybuffer);
@@ -1,3 +1,6 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 use std::{
     net::SocketAddr,
     sync::{Arc, Mutex},
@@ -11,11 +14,16 @@ use futures_util::{
     stream::{SplitSink, TryStreamExt},
     SinkExt, StreamExt, TryFutureExt,
 };
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc;
 use tokio::{
-    net::{TcpListener, TcpStream},
+    net::TcpListener,
     sync::mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender},
 };
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
 use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
 use tracing::{debug, info, trace};
 use tracing_log::log::error;
@@ -26,8 +34,66 @@ use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
 use crate::transport::{BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, MTU};
 
+/// Certificate chain and private key used by the WebSocket server to accept TLS (`wss://`) connections
+pub struct WebSocketIdentity {
+    certificate_chain: Vec<CertificateDer<'static>>,
+    private_key: PrivateKeyDer<'static>,
+}
+
+impl WebSocketIdentity {
+    pub fn new(
+        certificate_chain: Vec<CertificateDer<'static>>,
+        private_key: PrivateKeyDer<'static>,
+    ) -> Self {
+        Self {
+            certificate_chain,
+            private_key,
+        }
+    }
+
+    /// Load the certificate chain and the private key from PEM-encoded files
+    pub fn load_pemfiles(
+        certificate_path: impl AsRef<Path>,
+        private_key_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let certificate_chain =
+            rustls_pemfile::certs(&mut BufReader::new(File::open(certificate_path)?))
+                .collect::<std::io::Result<Vec<_>>>()?;
+        let private_key =
+            rustls_pemfile::private_key(&mut BufReader::new(File::open(private_key_path)?))?
+                .ok_or_else(|| std::io::Error::other("no private key found in the file"))?;
+        Ok(Self::new(certificate_chain, private_key))
+    }
+
+    pub fn clone_identity(&self) -> Self {
+        Self {
+            certificate_chain: self.certificate_chain.clone(),
+            private_key: self.private_key.clone_key(),
+        }
+    }
+
+    fn tls_acceptor(self) -> Result<TlsAcceptor> {
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(self.certificate_chain, self.private_key)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Manual implementation to avoid printing the private key
+impl std::fmt::Debug for WebSocketIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketIdentity")
+            .field("certificate_chain", &self.certificate_chain)
+            .finish_non_exhaustive()
+    }
+}
+
 pub(crate) struct WebSocketServerSocketBuilder {
     pub(crate) server_addr: SocketAddr,
+    /// If provided, the server only accepts TLS (`wss://`) connections
+    pub(crate) identity: Option<WebSocketIdentity>,
 }
 
 impl ServerTransportBuilder for WebSocketServerSocketBuilder {
@@ -46,6 +112,10 @@ impl ServerTransportBuilder for WebSocketServerSocketBuilder {
         // channels used to check the status of the io task
         let (status_tx, status_rx) = async_channel::unbounded();
         let addr_to_task = Arc::new(Mutex::new(HashMap::new()));
+        let tls_acceptor = self
+            .identity
+            .map(WebSocketIdentity::tls_acceptor)
+            .transpose()?;
 
         let sender = WebSocketServerSocketSender {
             server_addr: self.server_addr,
@@ -96,9 +166,25 @@ impl ServerTransportBuilder for WebSocketServerSocketBuilder {
                         Ok((stream, addr)) = listener.accept() => {
                             let clientbound_tx_map = clientbound_tx_map.clone();
                             let serverbound_tx = serverbound_tx.clone();
-                            let task = IoTaskPool::get().spawn(Compat::new(
-                                WebSocketServerSocket::handle_client(addr, stream, serverbound_tx, clientbound_tx_map, status_tx.clone())
-                            ));
+                            let status_tx = status_tx.clone();
+                            let tls_acceptor = tls_acceptor.clone();
+                            let task = IoTaskPool::get().spawn(Compat::new(async move {
+                                match tls_acceptor {
+                                    Some(tls_acceptor) => {
+                                        let Ok(tls_stream) = tls_acceptor
+                                            .accept(stream)
+                                            .await
+                                            .inspect_err(|e| error!("An error occured during the TLS handshake: {e:?}"))
+                                        else {
+                                            return;
+                                        };
+                                        WebSocketServerSocket::handle_client(addr, tls_stream, serverbound_tx, clientbound_tx_map, status_tx).await
+                                    }
+                                    None => {
+                                        WebSocketServerSocket::handle_client(addr, stream, serverbound_tx, clientbound_tx_map, status_tx).await
+                                    }
+                                }
+                            }));
                             addr_to_task.lock().unwrap().insert(addr, task);
                         }
                     }
@@ -125,31 +211,10 @@ pub struct WebSocketServerSocket {
 }
 
 impl WebSocketServerSocket {
-    /*fn get_tls_acceptor(&self) -> Option<TlsAcceptor> {
-        if let Some(config) = &self.tls_config {
-            let server_config = ServerConfig::builder()
-                .with_no_client_auth()
-                .with_single_cert(
-                    certs(&mut BufReader::new(&*config.certs))
-                        .map(|e| e.unwrap())
-                        .collect(),
-                    rsa_private_keys(&mut BufReader::new(&*config.keys))
-                        .map(|e| e.unwrap().into())
-                        .next()
-                        .unwrap(),
-                )
-                .unwrap();
-            Some(TlsAcceptor::from(Arc::new(server_config)))
-        } else {
-            None
-        }
-    }*/
-}
-
-impl WebSocketServerSocket {
-    async fn handle_client(
+    /// Handle a new connection, which can be either a plain TCP stream or a TLS stream
+    async fn handle_client<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
         addr: SocketAddr,
-        stream: TcpStream,
+        stream: S,
         serverbound_tx: UnboundedSender<(SocketAddr, Message)>,
         clientbound_tx_map: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<Message>>>>,
         status_tx: async_channel::Sender<ServerIoEvent>,
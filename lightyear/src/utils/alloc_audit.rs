@@ -0,0 +1,124 @@
+//! Track the heap allocations performed in the networking hot paths.
+//!
+//! Enable the `alloc_audit` feature and register the [`CountingAllocator`] as the global allocator.
+//! Every allocation that happens while lightyear is sending or receiving packets will then be
+//! recorded in the corresponding [`HotPath`].
+//!
+//! ```rust,ignore
+//! use lightyear::utils::alloc_audit::{self, CountingAllocator, HotPath};
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! fn main() {
+//!     // ... run the app for a few frames
+//!     println!("{:?}", alloc_audit::stats(HotPath::Send));
+//!     alloc_audit::reset();
+//! }
+//! ```
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Sections of the code in which the allocations are tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotPath {
+    /// Building packets from the buffered messages and sending them to the io
+    Send,
+    /// Receiving packets from the io, reading the messages and applying them to the world
+    Receive,
+}
+
+impl HotPath {
+    const COUNT: usize = 2;
+
+    fn index(self) -> usize {
+        match self {
+            HotPath::Send => 0,
+            HotPath::Receive => 1,
+        }
+    }
+}
+
+/// Allocations recorded for a [`HotPath`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Number of allocations (including reallocations)
+    pub allocations: usize,
+    /// Total number of bytes requested
+    pub bytes: usize,
+}
+
+static ALLOCATIONS: [AtomicUsize; HotPath::COUNT] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+static BYTES: [AtomicUsize; HotPath::COUNT] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+thread_local! {
+    static CURRENT_PATH: Cell<Option<HotPath>> = const { Cell::new(None) };
+}
+
+/// Allocations recorded for the hot path since the last [`reset`]
+pub fn stats(path: HotPath) -> AllocationStats {
+    AllocationStats {
+        allocations: ALLOCATIONS[path.index()].load(Ordering::Relaxed),
+        bytes: BYTES[path.index()].load(Ordering::Relaxed),
+    }
+}
+
+/// Reset the recorded allocations of all the hot paths
+pub fn reset() {
+    for (allocations, bytes) in ALLOCATIONS.iter().zip(BYTES.iter()) {
+        allocations.store(0, Ordering::Relaxed);
+        bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Guard that records the allocations of the current thread in a [`HotPath`] until it is dropped
+pub(crate) struct HotPathGuard {
+    previous: Option<HotPath>,
+}
+
+impl Drop for HotPathGuard {
+    fn drop(&mut self) {
+        let _ = CURRENT_PATH.try_with(|current| current.set(self.previous));
+    }
+}
+
+/// Start recording the allocations of the current thread in the given [`HotPath`]
+pub(crate) fn enter(path: HotPath) -> HotPathGuard {
+    let previous = CURRENT_PATH
+        .try_with(|current| current.replace(Some(path)))
+        .unwrap_or_default();
+    HotPathGuard { previous }
+}
+
+fn record(size: usize) {
+    // `try_with` because the allocator can be called while the thread-local is being destroyed
+    if let Ok(Some(path)) = CURRENT_PATH.try_with(Cell::get) {
+        ALLOCATIONS[path.index()].fetch_add(1, Ordering::Relaxed);
+        BYTES[path.index()].fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+/// Global allocator that wraps the [`System`] allocator and records the allocations made in the hot paths
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
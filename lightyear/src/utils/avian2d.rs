@@ -1,12 +1,44 @@
-//! Implement lightyear traits for some common bevy types
+//! Integration with the [`avian2d`] physics engine (enabled with the `avian2d` feature).
+//!
+//! This module provides interpolation/correction functions for the avian components, which can be
+//! registered in your protocol:
+//! ```rust,ignore
+//! use avian2d::prelude::*;
+//! use lightyear::prelude::*;
+//! use lightyear::utils::avian2d::*;
+//!
+//! app.register_component::<Position>(ChannelDirection::ServerToClient)
+//!     .add_prediction(ComponentSyncMode::Full)
+//!     .add_interpolation(ComponentSyncMode::Full)
+//!     .add_interpolation_fn(position::lerp)
+//!     .add_correction_fn(position::lerp);
+//! ```
+//!
+//! Entities that are moved with the physics components can also be rendered with a [`Transform`] on the
+//! other peer without replicating both representations, by replicating the `Transform` only:
+//! ```rust,ignore
+//! app.register_component::<Transform>(ChannelDirection::ServerToClient);
+//! app.add_replication_conversion(position::transform_conversion());
+//! app.add_replication_conversion(rotation::transform_conversion());
+//! ```
+//!
+//! The physics [`PhysicsSet`]s are also automatically ordered relative to lightyear's system sets
+//! in `FixedPostUpdate`; you only need to make sure that the physics systems run after the systems
+//! that apply the user's inputs.
 use crate::prelude::client::{InterpolationSet, PredictionSet};
+use crate::shared::replication::conversion::ReplicationConversion;
 use crate::shared::replication::delta::Diffable;
 use crate::shared::sets::{ClientMarker, InternalReplicationSet, ServerMarker};
-use avian2d::math::Scalar;
+use avian2d::math::{AdjustPrecision, AsF32, Scalar};
 use avian2d::prelude::*;
-use bevy::prelude::{App, FixedPostUpdate, IntoSystemSetConfigs, Plugin};
+use bevy::prelude::{
+    App, EulerRot, FixedPostUpdate, IntoSystemSetConfigs, Plugin, Quat, Transform,
+};
 use tracing::trace;
 
+/// Orders the physics systems relative to lightyear's systems:
+/// - physics runs after the hash of pre-spawned entities is computed
+/// - physics runs before the prediction history and the visual interpolation state are updated
 pub(crate) struct Avian2dPlugin;
 
 impl Plugin for Avian2dPlugin {
@@ -40,6 +72,18 @@ impl Plugin for Avian2dPlugin {
 pub mod position {
     use super::*;
 
+    /// Replicate the [`Position`] through the translation of the [`Transform`]
+    pub fn transform_conversion() -> ReplicationConversion<Position, Transform> {
+        ReplicationConversion {
+            to_wire: |position, transform| {
+                transform.translation = position.0.f32().extend(transform.translation.z)
+            },
+            from_wire: |transform, position| {
+                position.0 = transform.translation.truncate().adjust_precision()
+            },
+        }
+    }
+
     pub fn lerp(start: &Position, other: &Position, t: f32) -> Position {
         let u = Scalar::from(t);
         let res = Position::new(start.0 * (1.0 - u) + other.0 * u);
@@ -73,6 +117,19 @@ pub mod position {
 pub mod rotation {
     use super::*;
 
+    /// Replicate the [`Rotation`] through the rotation of the [`Transform`] around the Z axis
+    pub fn transform_conversion() -> ReplicationConversion<Rotation, Transform> {
+        ReplicationConversion {
+            to_wire: |rotation, transform| {
+                transform.rotation = Quat::from_rotation_z(rotation.as_radians().f32())
+            },
+            from_wire: |transform, rotation| {
+                let (_, _, angle) = transform.rotation.to_euler(EulerRot::XYZ);
+                *rotation = Rotation::radians(angle.adjust_precision())
+            },
+        }
+    }
+
     pub fn lerp(start: &Rotation, other: &Rotation, t: f32) -> Rotation {
         let u = Scalar::from(t);
         let shortest_angle =
@@ -1,13 +1,43 @@
-//! Implement lightyear traits for some common bevy types
+//! Integration with the [`avian3d`] physics engine (enabled with the `avian3d` feature).
+//!
+//! This module provides interpolation/correction functions for the avian components, which can be
+//! registered in your protocol:
+//! ```rust,ignore
+//! use avian3d::prelude::*;
+//! use lightyear::prelude::*;
+//! use lightyear::utils::avian3d::*;
+//!
+//! app.register_component::<Position>(ChannelDirection::ServerToClient)
+//!     .add_prediction(ComponentSyncMode::Full)
+//!     .add_interpolation(ComponentSyncMode::Full)
+//!     .add_interpolation_fn(position::lerp)
+//!     .add_correction_fn(position::lerp);
+//! ```
+//!
+//! Entities that are moved with the physics components can also be rendered with a [`Transform`] on the
+//! other peer without replicating both representations, by replicating the `Transform` only:
+//! ```rust,ignore
+//! app.register_component::<Transform>(ChannelDirection::ServerToClient);
+//! app.add_replication_conversion(position::transform_conversion());
+//! app.add_replication_conversion(rotation::transform_conversion());
+//! ```
+//!
+//! The physics [`PhysicsSet`]s are also automatically ordered relative to lightyear's system sets
+//! in `FixedPostUpdate`; you only need to make sure that the physics systems run after the systems
+//! that apply the user's inputs.
 use crate::prelude::client::{InterpolationSet, PredictionSet};
+use crate::shared::replication::conversion::ReplicationConversion;
 use crate::shared::replication::delta::Diffable;
 use crate::shared::sets::{ClientMarker, InternalReplicationSet, ServerMarker};
-use avian3d::math::Scalar;
+use avian3d::math::{AdjustPrecision, AsF32, Scalar};
 use avian3d::prelude::*;
 use bevy::app::{App, FixedPostUpdate, Plugin};
-use bevy::prelude::IntoSystemSetConfigs;
+use bevy::prelude::{IntoSystemSetConfigs, Transform};
 use tracing::trace;
 
+/// Orders the physics systems relative to lightyear's systems:
+/// - physics runs after the hash of pre-spawned entities is computed
+/// - physics runs before the prediction history and the visual interpolation state are updated
 pub(crate) struct Avian3dPlugin;
 impl Plugin for Avian3dPlugin {
     fn build(&self, app: &mut App) {
@@ -40,6 +70,14 @@ impl Plugin for Avian3dPlugin {
 pub mod position {
     use super::*;
 
+    /// Replicate the [`Position`] through the translation of the [`Transform`]
+    pub fn transform_conversion() -> ReplicationConversion<Position, Transform> {
+        ReplicationConversion {
+            to_wire: |position, transform| transform.translation = position.0.f32(),
+            from_wire: |transform, position| position.0 = transform.translation.adjust_precision(),
+        }
+    }
+
     pub fn lerp(start: &Position, other: &Position, t: f32) -> Position {
         let u = Scalar::from(t);
         let res = Position::new(start.0 * (1.0 - u) + other.0 * u);
@@ -75,6 +113,14 @@ pub mod rotation {
     use avian3d::math::Quaternion;
     use bevy::prelude::Animatable;
 
+    /// Replicate the [`Rotation`] through the rotation of the [`Transform`]
+    pub fn transform_conversion() -> ReplicationConversion<Rotation, Transform> {
+        ReplicationConversion {
+            to_wire: |rotation, transform| transform.rotation = rotation.0.f32(),
+            from_wire: |transform, rotation| rotation.0 = transform.rotation.adjust_precision(),
+        }
+    }
+
     pub fn lerp(start: &Rotation, other: &Rotation, t: f32) -> Rotation {
         Rotation(Quaternion::interpolate(&start.0, &other.0, t))
     }
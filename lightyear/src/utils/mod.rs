@@ -16,6 +16,10 @@ pub mod avian2d;
 #[cfg(feature = "avian3d")]
 pub mod avian3d;
 
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc_audit")))]
+#[cfg(feature = "alloc_audit")]
+pub mod alloc_audit;
+
 pub(crate) mod captures;
 pub(crate) mod pool;
 pub mod wrapping_id;
@@ -107,7 +107,7 @@ impl<T> Pool<T> {
     }
 
     #[inline]
-    pub fn try_pull(&self) -> Option<Reusable<T>> {
+    pub fn try_pull(&self) -> Option<Reusable<'_, T>> {
         self.objects
             .lock()
             .pop()
@@ -115,7 +115,7 @@ impl<T> Pool<T> {
     }
 
     #[inline]
-    pub fn pull<F: Fn() -> T>(&self, fallback: F) -> Reusable<T> {
+    pub fn pull<F: Fn() -> T>(&self, fallback: F) -> Reusable<'_, T> {
         self.try_pull()
             .unwrap_or_else(|| Reusable::new(self, fallback()))
     }
@@ -126,6 +126,12 @@ impl<T> Pool<T> {
     }
 }
 
+impl<T> std::fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pool").field("len", &self.len()).finish()
+    }
+}
+
 impl<T> FromIterator<T> for Pool<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         Self {
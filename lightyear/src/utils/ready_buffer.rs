@@ -143,6 +143,20 @@ impl<K: Ord + Clone, T: PartialEq> ReadyBuffer<K, T> {
         newer
     }
 
+    /// Pop the item with the smallest key, even if it is not ready
+    pub fn pop_oldest(&mut self) -> Option<(K, T)> {
+        self.heap.pop().map(|item| (item.key, item.item))
+    }
+
+    /// Pop the item with the biggest key
+    pub fn pop_newest(&mut self) -> Option<(K, T)> {
+        let mut items = std::mem::take(&mut self.heap).into_sorted_vec();
+        // the items are sorted in reverse order of the keys (the heap is a min-heap)
+        let newest = (!items.is_empty()).then(|| items.remove(0));
+        self.heap = items.into();
+        newest.map(|item| (item.key, item.item))
+    }
+
     /// Returns the length of the underlying queue
     pub fn len(&self) -> usize {
         self.heap.len()
@@ -0,0 +1,125 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Maximum number of fields supported, because the dirty mask is a `u64`
+const MAX_FIELDS: usize = 64;
+
+pub fn delta_replicate_impl(
+    input: proc_macro::TokenStream,
+    shared_crate_name: TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("Can only derive DeltaReplicate on a struct with named fields"),
+        },
+        _ => panic!("Can only derive DeltaReplicate on a struct"),
+    };
+    if !input.generics.params.is_empty() {
+        panic!("Cannot derive DeltaReplicate on a generic struct");
+    }
+    if fields.len() > MAX_FIELDS {
+        panic!("Can only derive DeltaReplicate on a struct with at most {MAX_FIELDS} fields");
+    }
+
+    // Names
+    let vis = &input.vis;
+    let struct_name = &input.ident;
+    let delta_name = format_ident!("{}Delta", struct_name);
+    let visitor_name = format_ident!("{}DeltaVisitor", struct_name);
+    let expecting = LitStr::new(&format!("the delta of {struct_name}"), Span::call_site());
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident.clone().unwrap()).collect();
+    let field_types: Vec<_> = fields.iter().map(|f| f.ty.clone()).collect();
+    let bits: Vec<_> = (0..fields.len()).map(|i| quote! { (1u64 << #i) }).collect();
+    let num_fields = fields.len();
+    let serde = quote! { #shared_crate_name::_reexport::serde };
+
+    let gen = quote! {
+        #[doc = concat!("Delta of [`", stringify!(#struct_name), "`], that only contains the fields that changed")]
+        #[derive(Clone, Debug, Default, PartialEq)]
+        #vis struct #delta_name {
+            #(#vis #field_names: Option<#field_types>,)*
+        }
+
+        impl #delta_name {
+            /// Bitmask of the fields that changed
+            #vis fn dirty_mask(&self) -> u64 {
+                let mut mask = 0u64;
+                #(if self.#field_names.is_some() { mask |= #bits; })*
+                mask
+            }
+        }
+
+        // The delta is serialized as the dirty mask, followed by the values of the fields that changed
+        impl #serde::Serialize for #delta_name {
+            fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use #serde::ser::SerializeTuple;
+                let mask = self.dirty_mask();
+                let mut tuple = serializer.serialize_tuple(1 + mask.count_ones() as usize)?;
+                tuple.serialize_element(&mask)?;
+                #(if let Some(value) = &self.#field_names {
+                    tuple.serialize_element(value)?;
+                })*
+                tuple.end()
+            }
+        }
+
+        impl<'de> #serde::Deserialize<'de> for #delta_name {
+            fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct #visitor_name;
+
+                impl<'de> #serde::de::Visitor<'de> for #visitor_name {
+                    type Value = #delta_name;
+
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    fn visit_seq<A: #serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                        let mut index = 0;
+                        let mask: u64 = seq
+                            .next_element()?
+                            .ok_or_else(|| #serde::de::Error::invalid_length(index, &self))?;
+                        let mut delta = #delta_name::default();
+                        #(if mask & #bits != 0 {
+                            index += 1;
+                            delta.#field_names = Some(
+                                seq.next_element()?
+                                    .ok_or_else(|| #serde::de::Error::invalid_length(index, &self))?,
+                            );
+                        })*
+                        Ok(delta)
+                    }
+                }
+
+                deserializer.deserialize_tuple(1 + #num_fields, #visitor_name)
+            }
+        }
+
+        impl #shared_crate_name::shared::replication::delta::Diffable for #struct_name {
+            type Delta = #delta_name;
+
+            fn base_value() -> Self {
+                Self::default()
+            }
+
+            fn diff(&self, new: &Self) -> Self::Delta {
+                #delta_name {
+                    #(#field_names: (self.#field_names != new.#field_names)
+                        .then(|| new.#field_names.clone()),)*
+                }
+            }
+
+            fn apply_diff(&mut self, delta: &Self::Delta) {
+                #(if let Some(value) = &delta.#field_names {
+                    self.#field_names = value.clone();
+                })*
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
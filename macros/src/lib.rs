@@ -9,8 +9,10 @@ use quote::{format_ident, quote};
 use syn::{parse_macro_input, ItemEnum};
 
 use channel::channel_impl;
+use delta::delta_replicate_impl;
 
 mod channel;
+mod delta;
 mod shared;
 
 // Channel
@@ -27,3 +29,23 @@ pub fn channel_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     let shared_crate_name = quote! { lightyear };
     channel_impl(input, shared_crate_name)
 }
+
+// Delta compression
+#[doc(hidden)]
+#[proc_macro_derive(DeltaReplicateInternal)]
+pub fn delta_replicate_derive_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { crate };
+    delta_replicate_impl(input, shared_crate_name)
+}
+
+/// Derives the `Diffable` trait for a struct with named fields, so that only the fields that changed are replicated.
+///
+/// A `{Struct}Delta` type is generated, where every field is an `Option`. It is serialized as a bitmask of the
+/// fields that changed, followed by the values of those fields.
+/// The struct must implement `Clone` and `Default` (used as the base value), and its fields must implement
+/// `Clone`, `PartialEq`, `Serialize` and `Deserialize`.
+#[proc_macro_derive(DeltaReplicate)]
+pub fn delta_replicate_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { lightyear };
+    delta_replicate_impl(input, shared_crate_name)
+}
@@ -0,0 +1,47 @@
+pub mod some_component {
+    use lightyear::prelude::*;
+
+    #[derive(DeltaReplicate, Clone, Debug, Default, PartialEq)]
+    pub struct SomeComponent {
+        pub health: u32,
+        pub name: String,
+        pub velocity: (f32, f32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lightyear::shared::replication::delta::Diffable;
+
+    use super::some_component::*;
+
+    #[test]
+    fn test_delta_replicate_derive() {
+        let old = SomeComponent {
+            health: 100,
+            name: "player".to_string(),
+            velocity: (0.0, 0.0),
+        };
+        let new = SomeComponent {
+            health: 50,
+            ..old.clone()
+        };
+        let delta = old.diff(&new);
+        assert_eq!(
+            delta,
+            SomeComponentDelta {
+                health: Some(50),
+                ..Default::default()
+            }
+        );
+        assert_eq!(delta.dirty_mask(), 0b001);
+
+        let mut value = old.clone();
+        value.apply_diff(&delta);
+        assert_eq!(value, new);
+
+        // the first delta is computed from the default value
+        let delta = SomeComponent::base_value().diff(&new);
+        assert_eq!(delta.dirty_mask(), 0b111);
+    }
+}